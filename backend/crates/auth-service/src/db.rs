@@ -3,13 +3,34 @@
 /// Handles all database operations for the auth service:
 /// - User profile storage
 /// - Device management
-/// - Session tracking
+/// - Session tracking and revocation
+/// - Refresh token families
+/// - Two-factor authentication settings and pending logins
+/// - Failed login attempts for throttling
+/// - Security events
 /// - Key bundle storage
+/// - Contact identity verification
+///
+/// One-time pre-keys live in the transactional keyspace under
+/// `/one_time_keys/{user}/{device}/` (one-time KEM pre-keys under
+/// `/one_time_kem_keys/{user}/{device}/`), apart from the raw keys, so that
+/// each one is handed out exactly once even with concurrent bundle fetches. Refresh
+/// token families live there too (under `/refresh_families/{session_id}`), so
+/// that each refresh token is rotated exactly once, and so do two-factor
+/// settings (under `/mfa/{user_id}`), so that each code is accepted once,
+/// pending logins (under `/mfa_challenges/`), so that each challenge counts
+/// every wrong code and is redeemed once, and failed login attempts (under `/login_attempts/`), so that none is lost,
+/// and recent key bundle fetches (under `/key_bundle_fetches/`), so that the
+/// rate limit holds across replicas.
 
 use anyhow::{Result, Context};
-use tikv_client::{RawClient, Error as TikvError};
+use guardyn_common::jwks::{JwkSet, JWKS_KEY};
+use guardyn_common::revocation::{Revocation, REVOCATION_PREFIX};
+use tikv_client::{RawClient, Transaction, TransactionClient, Error as TikvError};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use crate::key_bundle_throttle::RecentFetches;
+use crate::login_throttle::LoginAttempts;
 
 /// User profile stored in TiKV
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,6 +57,9 @@ pub struct Device {
 /// Session information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Session {
+    /// ID carried in the `sid` claim of the session's tokens (empty for legacy sessions)
+    #[serde(default)]
+    pub session_id: String,
     pub session_token: String,
     pub user_id: String,
     pub device_id: String,
@@ -43,31 +67,201 @@ pub struct Session {
     pub expires_at: i64,
 }
 
+/// Refresh tokens issued for one session, of which only the latest may be used
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefreshTokenFamily {
+    pub session_id: String,
+    pub user_id: String,
+    pub device_id: String,
+    /// SHA-256 (hex) of the family's current refresh token
+    pub current_token_hash: String,
+    pub rotated_at: i64,
+}
+
+impl RefreshTokenFamily {
+    /// Family whose current token is the session's refresh token
+    pub fn for_session(session: &Session, rotated_at: i64) -> Self {
+        Self {
+            session_id: session.session_id.clone(),
+            user_id: session.user_id.clone(),
+            device_id: session.device_id.clone(),
+            current_token_hash: crate::jwt::refresh_token_hash(&session.session_token),
+            rotated_at,
+        }
+    }
+}
+
+/// Outcome of presenting a refresh token for rotation
+#[derive(Debug)]
+pub enum RefreshRotation {
+    /// The token was current and has been replaced
+    Rotated,
+    /// The token was already rotated away
+    Reused(RefreshTokenFamily),
+    /// The session has no family
+    Unknown,
+}
+
+/// Kind of security event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecurityEventKind {
+    RefreshTokenReuse,
+    TwoFactorEnabled,
+    TwoFactorDisabled,
+    RecoveryCodeUsed,
+    AccountLocked,
+}
+
+/// Security-relevant event on a user's account, listed to the user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecurityEvent {
+    pub event_id: String,
+    pub user_id: String,
+    pub device_id: String,
+    pub kind: SecurityEventKind,
+    pub description: String,
+    pub created_at: i64,
+}
+
+/// Second-factor settings of a user
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct MfaSettings {
+    /// TOTP secret, set once enrolment is confirmed
+    pub totp_secret: Option<Vec<u8>>,
+    /// Secret of an enrolment waiting for its first code
+    pub pending_totp_secret: Option<Vec<u8>>,
+    /// When the pending enrolment was started
+    pub pending_since: i64,
+    /// Last TOTP time step accepted, so every code is accepted once
+    pub last_totp_step: u64,
+    /// Argon2 hashes of the unused recovery codes
+    pub recovery_code_hashes: Vec<String>,
+    pub enabled_at: i64,
+}
+
+impl MfaSettings {
+    pub fn is_enabled(&self) -> bool {
+        self.totp_secret.is_some()
+    }
+}
+
+impl std::fmt::Debug for MfaSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MfaSettings")
+            .field("enabled", &self.is_enabled())
+            .field("pending", &self.pending_totp_secret.is_some())
+            .field("last_totp_step", &self.last_totp_step)
+            .field("recovery_codes", &self.recovery_code_hashes.len())
+            .field("enabled_at", &self.enabled_at)
+            .finish()
+    }
+}
+
+/// Login whose password was accepted, waiting for its second factor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingLogin {
+    pub user_id: String,
+    /// Encoded `LoginRequest`, without the password
+    pub request: Vec<u8>,
+    pub failed_attempts: u32,
+    pub expires_at: i64,
+}
+
 /// Key bundle for E2EE
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KeyBundle {
     pub identity_key: Vec<u8>,
     pub signed_pre_key: Vec<u8>,
     pub signed_pre_key_signature: Vec<u8>,
+    pub signed_pre_key_id: u32,
     pub one_time_pre_keys: Vec<Vec<u8>>,
+    /// Key IDs of `one_time_pre_keys`, chosen by the client
+    pub one_time_pre_key_ids: Vec<u32>,
+    pub last_resort_pre_key: Option<LastResortPreKey>,
+    /// Last-resort ML-KEM-768 pre-key of PQXDH devices
+    #[serde(default)]
+    pub kem_pre_key: Option<KemPreKey>,
+    /// One-time ML-KEM-768 pre-keys (a fetched bundle carries at most one)
+    #[serde(default)]
+    pub one_time_kem_pre_keys: Vec<KemPreKey>,
     pub created_at: i64,
 }
 
+/// Signed pre-key handed out when a device has no one-time pre-keys left
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LastResortPreKey {
+    pub key_id: u32,
+    pub public_key: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+/// Signed ML-KEM-768 pre-key for PQXDH
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KemPreKey {
+    pub key_id: u32,
+    pub public_key: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+/// A one-time pre-key ID was sent twice or is already taken on the device
+#[derive(Debug, thiserror::Error)]
+#[error("one-time pre-key ID {key_id} is already in use")]
+pub struct PreKeyIdInUse {
+    pub key_id: u32,
+}
+
+/// Key ID and creation time of a device's current signed pre-key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedPreKeyMeta {
+    pub key_id: u32,
+    pub created_at: i64,
+}
+
+/// Signed pre-key replaced by a rotation, still accepted until `valid_until`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreviousSignedPreKey {
+    pub key_id: u32,
+    pub public_key: Vec<u8>,
+    pub signature: Vec<u8>,
+    pub valid_until: i64,
+}
+
+/// Signed pre-key ID assumed for bundles uploaded without one
+pub const DEFAULT_SIGNED_PRE_KEY_ID: u32 = 1;
+
+/// Attempts for a transaction that lost a write conflict
+const TRANSACTION_ATTEMPTS: u32 = 3;
+
+/// Identity key a user verified for one of their contacts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContactVerification {
+    pub contact_user_id: String,
+    pub identity_key: Vec<u8>,
+    pub verified_at: i64,
+}
+
 /// Database client
 #[derive(Clone)]
 pub struct DatabaseClient {
     client: Arc<RawClient>,
+    txn: Arc<TransactionClient>,
 }
 
 impl DatabaseClient {
     /// Create new database client
     pub async fn new(pd_endpoints: Vec<String>) -> Result<Self> {
-        let client = RawClient::new(pd_endpoints)
+        let client = RawClient::new(pd_endpoints.clone())
             .await
             .context("Failed to connect to TiKV")?;
 
+        let txn = TransactionClient::new(pd_endpoints)
+            .await
+            .context("Failed to connect to TiKV (transactional)")?;
+
         Ok(Self {
             client: Arc::new(client),
+            txn: Arc::new(txn),
         })
     }
 
@@ -203,6 +397,69 @@ impl DatabaseClient {
         Ok(Some(device))
     }
 
+    /// List all registered devices of a user
+    pub async fn list_devices(&self, user_id: &str) -> Result<Vec<Device>> {
+        let prefix = format!("/devices/{}/", user_id);
+        let start_key = prefix.clone().into_bytes();
+        let mut end_key = start_key.clone();
+        if let Some(last) = end_key.last_mut() {
+            *last += 1;
+        }
+
+        let mut devices = Vec::new();
+        for kv in self.client.scan(start_key..end_key, 1000).await? {
+            let key: Vec<u8> = kv.0.into();
+            // Key material lives under /devices/{user}/{device}/..., skip it
+            if key[prefix.len()..].contains(&b'/') {
+                continue;
+            }
+            if let Ok(device) = serde_json::from_slice::<Device>(&kv.1) {
+                devices.push(device);
+            }
+        }
+
+        Ok(devices)
+    }
+
+    /// Remove a device together with its key material and sessions
+    pub async fn delete_device(&self, user_id: &str, device_id: &str) -> Result<()> {
+        let device_key = format!("/devices/{}/{}", user_id, device_id).into_bytes();
+        self.client.delete(device_key).await?;
+
+        let keys_prefix = format!("/devices/{}/{}/", user_id, device_id);
+        let start_key = keys_prefix.into_bytes();
+        let mut end_key = start_key.clone();
+        if let Some(last) = end_key.last_mut() {
+            *last += 1;
+        }
+
+        for kv in self.client.scan(start_key..end_key, 1000).await? {
+            let key_bytes: Vec<u8> = kv.0.into();
+            self.client.delete(key_bytes).await?;
+        }
+
+        self.delete_one_time_pre_keys(&format!("/one_time_keys/{}/{}/", user_id, device_id)).await?;
+        self.delete_one_time_pre_keys(&format!("/one_time_kem_keys/{}/{}/", user_id, device_id)).await?;
+
+        let sessions_prefix = format!("/sessions/user/{}/", user_id);
+        let start_key = sessions_prefix.into_bytes();
+        let mut end_key = start_key.clone();
+        if let Some(last) = end_key.last_mut() {
+            *last += 1;
+        }
+
+        for kv in self.client.scan(start_key..end_key, 1000).await? {
+            if let Ok(session) = serde_json::from_slice::<Session>(&kv.1) {
+                if session.device_id == device_id {
+                    self.delete_session(&session.session_token).await?;
+                }
+            }
+        }
+
+        tracing::info!("Deleted device {} of user {}", device_id, user_id);
+        Ok(())
+    }
+
     /// Create session
     pub async fn create_session(&self, session: &Session) -> Result<()> {
         // Store session by token
@@ -245,6 +502,332 @@ impl DatabaseClient {
         let user_key = format!("/sessions/user/{}/{}", session.user_id, token).into_bytes();
         self.client.delete(user_key).await?;
 
+        if !session.session_id.is_empty() {
+            self.delete_refresh_family(&session.session_id).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Move a session to the refresh token that replaced `session.session_token`
+    pub async fn replace_session_token(&self, session: &Session, new_token: &str, expires_at: i64) -> Result<Session> {
+        let replacement = Session {
+            session_token: new_token.to_string(),
+            expires_at,
+            ..session.clone()
+        };
+        self.create_session(&replacement).await?;
+
+        let token_key = format!("/sessions/{}", session.session_token).into_bytes();
+        self.client.delete(token_key).await?;
+        let user_key = format!("/sessions/user/{}/{}", session.user_id, session.session_token).into_bytes();
+        self.client.delete(user_key).await?;
+
+        Ok(replacement)
+    }
+
+    /// Start the refresh token family of a new session
+    pub async fn create_refresh_family(&self, family: &RefreshTokenFamily) -> Result<()> {
+        let value = serde_json::to_vec(family)?;
+        let mut txn = self.txn.begin_optimistic().await?;
+        let result = txn.put(refresh_family_path(&family.session_id), value).await;
+        Ok(finish_transaction(txn, result).await?)
+    }
+
+    /// Replace the current refresh token of `next.session_id` if `presented_hash` is it
+    ///
+    /// Concurrent rotations of the same token conflict and retry, so only one
+    /// of them succeeds and the others see a reused token. Sessions created
+    /// before refresh token families have no family; `adopt_missing` starts
+    /// one for them.
+    pub async fn rotate_refresh_token(
+        &self,
+        presented_hash: &str,
+        next: &RefreshTokenFamily,
+        adopt_missing: bool,
+    ) -> Result<RefreshRotation> {
+        let key = refresh_family_path(&next.session_id);
+        let value = serde_json::to_vec(next)?;
+        let mut attempt = 1;
+
+        loop {
+            let mut txn = self.txn.begin_optimistic().await?;
+
+            let result = async {
+                let family = match txn.get(key.clone()).await? {
+                    Some(data) => match serde_json::from_slice::<RefreshTokenFamily>(&data) {
+                        Ok(family) => Some(family),
+                        Err(e) => {
+                            tracing::warn!("Replacing malformed refresh token family {}: {}", next.session_id, e);
+                            None
+                        }
+                    },
+                    None => None,
+                };
+
+                match family {
+                    Some(family) if family.current_token_hash != presented_hash => {
+                        return Ok::<_, TikvError>(RefreshRotation::Reused(family));
+                    }
+                    None if !adopt_missing => return Ok(RefreshRotation::Unknown),
+                    _ => {}
+                }
+
+                txn.put(key.clone(), value.clone()).await?;
+                Ok(RefreshRotation::Rotated)
+            }.await;
+
+            match finish_transaction(txn, result).await {
+                Ok(rotation) => return Ok(rotation),
+                Err(e) if attempt < TRANSACTION_ATTEMPTS => {
+                    tracing::debug!("Retrying refresh token rotation: {}", e);
+                    attempt += 1;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Delete the refresh token family of a session
+    pub async fn delete_refresh_family(&self, session_id: &str) -> Result<()> {
+        let mut txn = self.txn.begin_optimistic().await?;
+        let result = txn.delete(refresh_family_path(session_id)).await;
+        Ok(finish_transaction(txn, result).await?)
+    }
+
+    /// Get a user's second-factor settings
+    pub async fn get_mfa(&self, user_id: &str) -> Result<Option<MfaSettings>> {
+        let mut txn = self.txn.begin_optimistic().await?;
+        let result = txn.get(mfa_path(user_id)).await;
+        match finish_transaction(txn, result).await? {
+            Some(data) => Ok(Some(serde_json::from_slice(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Read-modify-write a user's second-factor settings in one transaction
+    ///
+    /// `update` sees `None` if the user has no settings and may set it to
+    /// `None` to delete them. It runs again if a concurrent update wins, so
+    /// two requests never both consume the same code.
+    pub async fn update_mfa<T>(
+        &self,
+        user_id: &str,
+        update: impl FnMut(&mut Option<MfaSettings>) -> T,
+    ) -> Result<T> {
+        self.update_record(mfa_path(user_id), "second-factor settings", update).await
+    }
+
+    /// Failed login attempts recorded under a throttling key
+    pub async fn get_login_attempts(&self, key: &str) -> Result<Option<LoginAttempts>> {
+        let mut txn = self.txn.begin_optimistic().await?;
+        let result = txn.get(login_attempts_path(key)).await;
+        match finish_transaction(txn, result).await? {
+            Some(data) => Ok(Some(serde_json::from_slice(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Read-modify-write the failed login attempts under a throttling key
+    ///
+    /// Concurrent failures conflict and retry, so none of them is lost.
+    pub async fn update_login_attempts<T>(
+        &self,
+        key: &str,
+        update: impl FnMut(&mut Option<LoginAttempts>) -> T,
+    ) -> Result<T> {
+        self.update_record(login_attempts_path(key), "login attempts", update).await
+    }
+
+    /// Read-modify-write the recent key bundle fetches under a rate limiting key
+    pub async fn update_key_bundle_fetches<T>(
+        &self,
+        key: &str,
+        update: impl FnMut(&mut Option<RecentFetches>) -> T,
+    ) -> Result<T> {
+        self.update_record(key_bundle_fetches_path(key), "key bundle fetches", update).await
+    }
+
+    /// Read-modify-write a JSON value in the transactional keyspace
+    ///
+    /// `update` sees `None` if the key is missing and may set it to `None` to
+    /// delete it. It runs again (on a fresh read) if a concurrent update wins.
+    async fn update_record<V, T>(
+        &self,
+        key: Vec<u8>,
+        what: &str,
+        mut update: impl FnMut(&mut Option<V>) -> T,
+    ) -> Result<T>
+    where
+        V: Serialize + serde::de::DeserializeOwned,
+    {
+        let mut attempt = 1;
+
+        loop {
+            let mut txn = self.txn.begin_optimistic().await?;
+
+            let value = match txn.get(key.clone()).await {
+                Ok(Some(data)) => serde_json::from_slice::<V>(&data)
+                    .map(Some)
+                    .with_context(|| format!("Malformed {}", what)),
+                Ok(None) => Ok(None),
+                Err(e) => Err(e.into()),
+            };
+            let mut value = match value {
+                Ok(value) => value,
+                Err(e) => {
+                    if let Err(rollback_error) = txn.rollback().await {
+                        tracing::warn!("Failed to roll back transaction: {}", rollback_error);
+                    }
+                    return Err(e);
+                }
+            };
+            let output = update(&mut value);
+
+            let result = async {
+                match &value {
+                    Some(value) => {
+                        let data = serde_json::to_vec(value).expect("record serializes");
+                        txn.put(key.clone(), data).await
+                    }
+                    None => txn.delete(key.clone()).await,
+                }
+            }.await;
+
+            match finish_transaction(txn, result).await {
+                Ok(()) => return Ok(output),
+                Err(e) if attempt < TRANSACTION_ATTEMPTS => {
+                    tracing::debug!("Retrying update of {}: {}", what, e);
+                    attempt += 1;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Store a login waiting for its second factor under the challenge token hash
+    pub async fn store_pending_login(&self, challenge_hash: &str, login: &PendingLogin) -> Result<()> {
+        self.update_pending_login(challenge_hash, |pending| *pending = Some(login.clone())).await
+    }
+
+    /// Get a login waiting for its second factor, unless it has expired
+    pub async fn get_pending_login(&self, challenge_hash: &str, now: i64) -> Result<Option<PendingLogin>> {
+        let mut txn = self.txn.begin_optimistic().await?;
+        let result = txn.get(pending_login_path(challenge_hash)).await;
+        let login: PendingLogin = match finish_transaction(txn, result).await? {
+            Some(data) => serde_json::from_slice(&data)?,
+            None => return Ok(None),
+        };
+
+        Ok(Some(login).filter(|login| login.expires_at > now))
+    }
+
+    /// Read-modify-write a login waiting for its second factor in one transaction
+    ///
+    /// Concurrent attempts conflict and retry, so no failed attempt is lost
+    /// and a challenge is redeemed at most once.
+    pub async fn update_pending_login<T>(
+        &self,
+        challenge_hash: &str,
+        update: impl FnMut(&mut Option<PendingLogin>) -> T,
+    ) -> Result<T> {
+        self.update_record(pending_login_path(challenge_hash), "pending login", update).await
+    }
+
+    /// Record a security event on a user's account
+    pub async fn store_security_event(&self, event: &SecurityEvent) -> Result<()> {
+        let key = format!(
+            "/users/{}/security_events/{:020}_{}",
+            event.user_id, event.created_at, event.event_id
+        ).into_bytes();
+        let value = serde_json::to_vec(event)?;
+        self.client.put(key, value).await?;
+        Ok(())
+    }
+
+    /// List a user's most recent security events, newest first
+    pub async fn list_security_events(&self, user_id: &str, limit: u32) -> Result<Vec<SecurityEvent>> {
+        let start_key = format!("/users/{}/security_events/", user_id).into_bytes();
+        let mut end_key = start_key.clone();
+        if let Some(last) = end_key.last_mut() {
+            *last += 1;
+        }
+
+        let mut events: Vec<SecurityEvent> = self
+            .client
+            .scan(start_key..end_key, 1000)
+            .await?
+            .into_iter()
+            .filter_map(|kv| serde_json::from_slice(&kv.1).ok())
+            .collect();
+
+        events.reverse();
+        events.truncate(limit as usize);
+        Ok(events)
+    }
+
+    /// List all sessions of a user
+    pub async fn list_sessions(&self, user_id: &str) -> Result<Vec<Session>> {
+        let start_key = format!("/sessions/user/{}/", user_id).into_bytes();
+        let mut end_key = start_key.clone();
+        if let Some(last) = end_key.last_mut() {
+            *last += 1;
+        }
+
+        let mut sessions = Vec::new();
+        for kv in self.client.scan(start_key..end_key, 1000).await? {
+            match serde_json::from_slice::<Session>(&kv.1) {
+                Ok(session) => sessions.push(session),
+                Err(e) => tracing::warn!("Skipping malformed session of user {}: {}", user_id, e),
+            }
+        }
+
+        Ok(sessions)
+    }
+
+    /// Store a revocation so that every replica and service can pick it up
+    pub async fn store_revocation(&self, revocation: &Revocation) -> Result<()> {
+        let value = serde_json::to_vec(revocation)?;
+        self.client.put(revocation.key().into_bytes(), value).await?;
+        Ok(())
+    }
+
+    /// Whether a session or token ID is revoked
+    pub async fn is_revoked(&self, id: &str) -> Result<bool> {
+        let key = format!("{}{}", REVOCATION_PREFIX, id).into_bytes();
+        match self.client.get(key).await? {
+            Some(data) => {
+                let revocation: Revocation = serde_json::from_slice(&data)?;
+                Ok(!revocation.is_expired())
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Load active revocations, deleting the ones that have expired
+    pub async fn load_revocations(&self) -> Result<Vec<Revocation>> {
+        let start_key = REVOCATION_PREFIX.as_bytes().to_vec();
+        let mut end_key = start_key.clone();
+        if let Some(last) = end_key.last_mut() {
+            *last += 1;
+        }
+
+        let mut revocations = Vec::new();
+        for kv in self.client.scan(start_key..end_key, 10000).await? {
+            let key: Vec<u8> = kv.0.into();
+            match serde_json::from_slice::<Revocation>(&kv.1) {
+                Ok(revocation) if !revocation.is_expired() => revocations.push(revocation),
+                _ => self.client.delete(key).await?,
+            }
+        }
+
+        Ok(revocations)
+    }
+
+    /// Store the published token verification keys, read by the other services
+    pub async fn store_jwks(&self, jwks: &JwkSet) -> Result<()> {
+        let value = serde_json::to_vec(jwks)?;
+        self.client.put(JWKS_KEY.as_bytes().to_vec(), value).await?;
         Ok(())
     }
 
@@ -267,16 +850,41 @@ impl DatabaseClient {
         let sig_path = format!("/devices/{}/{}/signed_pre_key_signature", user_id, device_id).into_bytes();
         self.client.put(sig_path, key_bundle.signed_pre_key_signature.clone()).await?;
 
-        // Store one-time pre-keys
-        for (i, otk) in key_bundle.one_time_pre_keys.iter().enumerate() {
-            let otk_path = format!("/devices/{}/{}/one_time_keys/{}", user_id, device_id, i).into_bytes();
-            self.client.put(otk_path, otk.clone()).await?;
+        // Store signed pre-key ID and age (for rotation)
+        let meta = SignedPreKeyMeta {
+            key_id: if key_bundle.signed_pre_key_id == 0 {
+                DEFAULT_SIGNED_PRE_KEY_ID
+            } else {
+                key_bundle.signed_pre_key_id
+            },
+            created_at: key_bundle.created_at,
+        };
+        let meta_path = format!("/devices/{}/{}/signed_pre_key_meta", user_id, device_id).into_bytes();
+        self.client.put(meta_path, serde_json::to_vec(&meta)?).await?;
+
+        if let Some(last_resort_pre_key) = &key_bundle.last_resort_pre_key {
+            self.set_last_resort_pre_key(user_id, device_id, last_resort_pre_key).await?;
+        }
+        if let Some(kem_pre_key) = &key_bundle.kem_pre_key {
+            self.set_kem_pre_key(user_id, device_id, kem_pre_key).await?;
         }
 
+        // Store one-time pre-keys
+        self.add_one_time_pre_keys(
+            user_id,
+            device_id,
+            &key_bundle.one_time_pre_keys,
+            &key_bundle.one_time_pre_key_ids,
+        ).await?;
+        self.add_one_time_kem_pre_keys(user_id, device_id, &key_bundle.one_time_kem_pre_keys).await?;
+
         Ok(())
     }
 
-    /// Get key bundle
+    /// Get key bundle, consuming one one-time pre-key
+    ///
+    /// The bundle carries at most one one-time pre-key and one one-time KEM
+    /// pre-key, which are deleted so no other initiator gets them.
     pub async fn get_key_bundle(
         &self,
         user_id: &str,
@@ -303,26 +911,38 @@ impl DatabaseClient {
             None => return Ok(None),
         };
 
-        // Get one-time pre-keys using range scan
-        let otk_prefix = format!("/devices/{}/{}/one_time_keys/", user_id, device_id);
-        let start_key = otk_prefix.clone().into_bytes();
-        let mut end_key = start_key.clone();
-        // Increment the last byte to get exclusive upper bound for the prefix
-        if let Some(last) = end_key.last_mut() {
-            *last = *last + 1; // '/' + 1 = '0', so we scan all keys under the prefix
-        }
+        let signed_pre_key_id = match self.get_signed_pre_key_meta(user_id, device_id).await? {
+            Some(meta) => meta.key_id,
+            None => DEFAULT_SIGNED_PRE_KEY_ID,
+        };
 
-        let otk_kvs = self.client.scan(start_key..end_key, 100).await?;
-        let mut one_time_pre_keys = Vec::with_capacity(otk_kvs.len());
-        for kv in otk_kvs {
-            one_time_pre_keys.push(kv.1);
-        }
+        let last_resort_pre_key = self.get_last_resort_pre_key(user_id, device_id).await?;
+        let kem_pre_key = self.get_kem_pre_key(user_id, device_id).await?;
+
+        // Hand out one one-time pre-key (without one the last-resort key is used)
+        let (one_time_pre_key_ids, one_time_pre_keys) =
+            match self.consume_one_time_pre_key(user_id, device_id).await? {
+                Some((key_id, key)) => (vec![key_id], vec![key]),
+                None => (Vec::new(), Vec::new()),
+            };
+
+        // Likewise one one-time KEM pre-key (PQXDH devices only)
+        let one_time_kem_pre_keys = self
+            .consume_one_time_kem_pre_key(user_id, device_id)
+            .await?
+            .into_iter()
+            .collect();
 
         Ok(Some(KeyBundle {
             identity_key,
             signed_pre_key,
             signed_pre_key_signature: signature,
+            signed_pre_key_id,
             one_time_pre_keys,
+            one_time_pre_key_ids,
+            last_resort_pre_key,
+            kem_pre_key,
+            one_time_kem_pre_keys,
             created_at: std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap()
@@ -330,6 +950,403 @@ impl DatabaseClient {
         }))
     }
 
+    /// Get ID and age of a device's signed pre-key
+    ///
+    /// Bundles stored before key IDs were tracked report the default ID and
+    /// creation time 0, so they are due for rotation.
+    pub async fn get_signed_pre_key_meta(
+        &self,
+        user_id: &str,
+        device_id: &str,
+    ) -> Result<Option<SignedPreKeyMeta>> {
+        let meta_path = format!("/devices/{}/{}/signed_pre_key_meta", user_id, device_id).into_bytes();
+        if let Some(data) = self.client.get(meta_path).await? {
+            return Ok(Some(serde_json::from_slice(&data)?));
+        }
+
+        let signed_pre_key_path = format!("/devices/{}/{}/signed_pre_key", user_id, device_id).into_bytes();
+        match self.client.get(signed_pre_key_path).await? {
+            Some(_) => Ok(Some(SignedPreKeyMeta {
+                key_id: DEFAULT_SIGNED_PRE_KEY_ID,
+                created_at: 0,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    /// Store a device's last-resort pre-key, replacing the previous one
+    pub async fn set_last_resort_pre_key(
+        &self,
+        user_id: &str,
+        device_id: &str,
+        last_resort_pre_key: &LastResortPreKey,
+    ) -> Result<()> {
+        let key = format!("/devices/{}/{}/last_resort_pre_key", user_id, device_id).into_bytes();
+        self.client.put(key, serde_json::to_vec(last_resort_pre_key)?).await?;
+        Ok(())
+    }
+
+    /// Get a device's last-resort pre-key
+    pub async fn get_last_resort_pre_key(
+        &self,
+        user_id: &str,
+        device_id: &str,
+    ) -> Result<Option<LastResortPreKey>> {
+        let key = format!("/devices/{}/{}/last_resort_pre_key", user_id, device_id).into_bytes();
+        match self.client.get(key).await? {
+            Some(data) => Ok(Some(serde_json::from_slice(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Store a device's last-resort KEM pre-key, replacing the previous one
+    pub async fn set_kem_pre_key(&self, user_id: &str, device_id: &str, kem_pre_key: &KemPreKey) -> Result<()> {
+        let key = format!("/devices/{}/{}/kem_pre_key", user_id, device_id).into_bytes();
+        self.client.put(key, serde_json::to_vec(kem_pre_key)?).await?;
+        Ok(())
+    }
+
+    /// Get a device's last-resort KEM pre-key
+    pub async fn get_kem_pre_key(&self, user_id: &str, device_id: &str) -> Result<Option<KemPreKey>> {
+        let key = format!("/devices/{}/{}/kem_pre_key", user_id, device_id).into_bytes();
+        match self.client.get(key).await? {
+            Some(data) => Ok(Some(serde_json::from_slice(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Get the signed pre-key replaced by the last rotation
+    pub async fn get_previous_signed_pre_key(
+        &self,
+        user_id: &str,
+        device_id: &str,
+    ) -> Result<Option<PreviousSignedPreKey>> {
+        let key = format!("/devices/{}/{}/previous_signed_pre_key", user_id, device_id).into_bytes();
+        match self.client.get(key).await? {
+            Some(data) => Ok(Some(serde_json::from_slice(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Replace a device's signed pre-key
+    ///
+    /// The current key is kept as the previous one until `valid_until`.
+    /// Returns it, or `None` if the device had no signed pre-key.
+    pub async fn rotate_signed_pre_key(
+        &self,
+        user_id: &str,
+        device_id: &str,
+        meta: &SignedPreKeyMeta,
+        signed_pre_key: Vec<u8>,
+        signature: Vec<u8>,
+        valid_until: i64,
+    ) -> Result<Option<PreviousSignedPreKey>> {
+        let signed_pre_key_path = format!("/devices/{}/{}/signed_pre_key", user_id, device_id).into_bytes();
+        let sig_path = format!("/devices/{}/{}/signed_pre_key_signature", user_id, device_id).into_bytes();
+
+        let current_meta = self.get_signed_pre_key_meta(user_id, device_id).await?;
+        let current_key = self.client.get(signed_pre_key_path.clone()).await?;
+        let current_signature = self.client.get(sig_path.clone()).await?;
+
+        let previous = match (current_meta, current_key, current_signature) {
+            (Some(current_meta), Some(public_key), Some(signature)) => {
+                let previous = PreviousSignedPreKey {
+                    key_id: current_meta.key_id,
+                    public_key,
+                    signature,
+                    valid_until,
+                };
+                let previous_path = format!("/devices/{}/{}/previous_signed_pre_key", user_id, device_id).into_bytes();
+                self.client.put(previous_path, serde_json::to_vec(&previous)?).await?;
+                Some(previous)
+            }
+            _ => None,
+        };
+
+        self.client.put(signed_pre_key_path, signed_pre_key).await?;
+        self.client.put(sig_path, signature).await?;
+
+        let meta_path = format!("/devices/{}/{}/signed_pre_key_meta", user_id, device_id).into_bytes();
+        self.client.put(meta_path, serde_json::to_vec(meta)?).await?;
+
+        Ok(previous)
+    }
+
+    /// Add one-time pre-keys under the IDs the client chose for them
+    ///
+    /// The client needs the ID to find the private key, so every key must
+    /// come with one. Fails with [`PreKeyIdInUse`] without storing anything if
+    /// an ID repeats or is already taken by a stored key.
+    pub async fn add_one_time_pre_keys(
+        &self,
+        user_id: &str,
+        device_id: &str,
+        keys: &[Vec<u8>],
+        key_ids: &[u32],
+    ) -> Result<()> {
+        if key_ids.len() != keys.len() {
+            anyhow::bail!("Got {} one-time pre-key IDs for {} keys", key_ids.len(), keys.len());
+        }
+
+        let keys = key_ids.iter().copied().zip(keys.iter().cloned()).collect();
+        self.add_pre_keys(&one_time_pre_key_prefix(user_id, device_id), keys).await
+    }
+
+    /// Store one-time KEM pre-keys of a device
+    ///
+    /// Fails with [`PreKeyIdInUse`] like [`DatabaseClient::add_one_time_pre_keys`].
+    pub async fn add_one_time_kem_pre_keys(
+        &self,
+        user_id: &str,
+        device_id: &str,
+        keys: &[KemPreKey],
+    ) -> Result<()> {
+        let keys = keys
+            .iter()
+            .map(|key| Ok((key.key_id, serde_json::to_vec(key)?)))
+            .collect::<Result<_>>()?;
+        self.add_pre_keys(&one_time_kem_pre_key_prefix(user_id, device_id), keys).await
+    }
+
+    /// Store pre-keys under `prefix` in one transaction, keyed by their IDs
+    async fn add_pre_keys(&self, prefix: &str, keys: Vec<(u32, Vec<u8>)>) -> Result<()> {
+        let mut seen = std::collections::HashSet::new();
+        if let Some((key_id, _)) = keys.iter().find(|(key_id, _)| !seen.insert(*key_id)) {
+            return Err(PreKeyIdInUse { key_id: *key_id }.into());
+        }
+        if keys.is_empty() {
+            return Ok(());
+        }
+
+        let mut attempt = 1;
+
+        loop {
+            let mut txn = self.txn.begin_optimistic().await?;
+
+            let result = async {
+                for (key_id, _) in &keys {
+                    if txn.get(pre_key_path(prefix, *key_id)).await?.is_some() {
+                        return Ok::<_, TikvError>(Some(*key_id));
+                    }
+                }
+
+                for (key_id, key) in &keys {
+                    txn.put(pre_key_path(prefix, *key_id), key.clone()).await?;
+                }
+
+                Ok(None)
+            }.await;
+
+            match finish_transaction(txn, result).await {
+                Ok(None) => return Ok(()),
+                Ok(Some(key_id)) => return Err(PreKeyIdInUse { key_id }.into()),
+                Err(e) if attempt < TRANSACTION_ATTEMPTS => {
+                    tracing::debug!("Retrying pre-key upload: {}", e);
+                    attempt += 1;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Take one one-time pre-key of a device, deleting it in the same transaction
+    ///
+    /// Concurrent fetches conflict on the deleted key and retry, so every key
+    /// is handed out once. Returns `None` when the device has run out.
+    pub async fn consume_one_time_pre_key(
+        &self,
+        user_id: &str,
+        device_id: &str,
+    ) -> Result<Option<(u32, Vec<u8>)>> {
+        self.consume_pre_key(&one_time_pre_key_prefix(user_id, device_id)).await
+    }
+
+    /// Take one one-time KEM pre-key of a device, like [`DatabaseClient::consume_one_time_pre_key`]
+    pub async fn consume_one_time_kem_pre_key(
+        &self,
+        user_id: &str,
+        device_id: &str,
+    ) -> Result<Option<KemPreKey>> {
+        self.consume_pre_key(&one_time_kem_pre_key_prefix(user_id, device_id))
+            .await?
+            .map(|(_, value)| Ok(serde_json::from_slice(&value)?))
+            .transpose()
+    }
+
+    /// Take the pre-key with the lowest ID under `prefix`
+    async fn consume_pre_key(&self, prefix: &str) -> Result<Option<(u32, Vec<u8>)>> {
+        let start_key = prefix.as_bytes().to_vec();
+        let mut end_key = start_key.clone();
+        if let Some(last) = end_key.last_mut() {
+            *last += 1;
+        }
+
+        let mut attempt = 1;
+
+        let (key, value) = loop {
+            let mut txn = self.txn.begin_optimistic().await?;
+
+            let result = async {
+                let kv = match txn.scan(start_key.clone()..end_key.clone(), 1).await?.next() {
+                    Some(kv) => kv,
+                    None => return Ok::<_, TikvError>(None),
+                };
+
+                let key: Vec<u8> = kv.0.into();
+                txn.delete(key.clone()).await?;
+                Ok(Some((key, kv.1)))
+            }.await;
+
+            match finish_transaction(txn, result).await {
+                Ok(Some(kv)) => break kv,
+                Ok(None) => return Ok(None),
+                Err(e) if attempt < TRANSACTION_ATTEMPTS => {
+                    tracing::debug!("Retrying pre-key fetch: {}", e);
+                    attempt += 1;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        };
+
+        let key_id = String::from_utf8_lossy(&key[prefix.len()..])
+            .parse::<u32>()
+            .context("Malformed pre-key ID")?;
+
+        Ok(Some((key_id, value)))
+    }
+
+    /// Count the one-time pre-keys a device has left
+    pub async fn count_one_time_pre_keys(&self, user_id: &str, device_id: &str) -> Result<u32> {
+        self.count_pre_keys(&one_time_pre_key_prefix(user_id, device_id)).await
+    }
+
+    /// Count the one-time KEM pre-keys a device has left
+    pub async fn count_one_time_kem_pre_keys(&self, user_id: &str, device_id: &str) -> Result<u32> {
+        self.count_pre_keys(&one_time_kem_pre_key_prefix(user_id, device_id)).await
+    }
+
+    /// Count the pre-keys under `prefix`
+    async fn count_pre_keys(&self, prefix: &str) -> Result<u32> {
+        let start_key = prefix.as_bytes().to_vec();
+        let mut end_key = start_key.clone();
+        if let Some(last) = end_key.last_mut() {
+            *last += 1;
+        }
+
+        let mut txn = self.txn.begin_optimistic().await?;
+        let result = async {
+            Ok::<_, TikvError>(txn.scan_keys(start_key..end_key, 1000).await?.count() as u32)
+        }.await;
+
+        Ok(finish_transaction(txn, result).await?)
+    }
+
+    /// Move one-time pre-keys stored by older versions into the transactional keyspace
+    ///
+    /// They were kept in the raw keyspace under
+    /// `/devices/{user}/{device}/one_time_keys/{index}` and handed out without
+    /// being deleted. The index becomes the key ID; a key whose ID is already
+    /// taken is dropped. Returns the number of keys moved.
+    pub async fn migrate_legacy_one_time_pre_keys(&self) -> Result<usize> {
+        let mut start_key = b"/devices/".to_vec();
+        let mut end_key = start_key.clone();
+        if let Some(last) = end_key.last_mut() {
+            *last += 1;
+        }
+
+        let mut migrated = 0;
+
+        loop {
+            let kvs = self.client.scan(start_key.clone()..end_key.clone(), 1000).await?;
+            let last_page = kvs.len() < 1000;
+
+            for kv in kvs {
+                let key: Vec<u8> = kv.0.into();
+                start_key = key.clone();
+                start_key.push(0);
+
+                let (user_id, device_id, key_id) = match parse_legacy_one_time_pre_key_path(&key) {
+                    Some(parsed) => parsed,
+                    None => continue,
+                };
+
+                match self.add_one_time_pre_keys(&user_id, &device_id, &[kv.1], &[key_id]).await {
+                    Ok(()) => migrated += 1,
+                    Err(e) if e.is::<PreKeyIdInUse>() => {
+                        tracing::warn!("Dropping legacy one-time pre-key {} of device {}: ID in use", key_id, device_id);
+                    }
+                    Err(e) => return Err(e),
+                }
+                self.client.delete(key).await?;
+            }
+
+            if last_page {
+                return Ok(migrated);
+            }
+        }
+    }
+
+    /// Delete all one-time pre-keys under a prefix
+    async fn delete_one_time_pre_keys(&self, prefix: &str) -> Result<()> {
+        let start_key = prefix.as_bytes().to_vec();
+        let mut end_key = start_key.clone();
+        if let Some(last) = end_key.last_mut() {
+            *last += 1;
+        }
+
+        let mut txn = self.txn.begin_optimistic().await?;
+        let result = async {
+            let keys: Vec<_> = txn.scan_keys(start_key..end_key, 1000).await?.collect();
+            for key in keys {
+                txn.delete(key).await?;
+            }
+            Ok::<_, TikvError>(())
+        }.await;
+
+        Ok(finish_transaction(txn, result).await?)
+    }
+
+    /// Get a user's current identity key
+    pub async fn get_identity_key(&self, user_id: &str) -> Result<Option<Vec<u8>>> {
+        let key = format!("/users/{}/identity_key", user_id).into_bytes();
+        Ok(self.client.get(key).await?)
+    }
+
+    /// Store the identity key a user verified for a contact
+    pub async fn set_contact_verification(
+        &self,
+        user_id: &str,
+        verification: &ContactVerification,
+    ) -> Result<()> {
+        let key = format!(
+            "/users/{}/contacts/{}/verification",
+            user_id, verification.contact_user_id
+        ).into_bytes();
+        let value = serde_json::to_vec(verification)?;
+        self.client.put(key, value).await?;
+        Ok(())
+    }
+
+    /// Get the identity key a user verified for a contact
+    pub async fn get_contact_verification(
+        &self,
+        user_id: &str,
+        contact_user_id: &str,
+    ) -> Result<Option<ContactVerification>> {
+        let key = format!("/users/{}/contacts/{}/verification", user_id, contact_user_id).into_bytes();
+        match self.client.get(key).await? {
+            Some(data) => Ok(Some(serde_json::from_slice(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Clear a contact's verification
+    pub async fn delete_contact_verification(&self, user_id: &str, contact_user_id: &str) -> Result<()> {
+        let key = format!("/users/{}/contacts/{}/verification", user_id, contact_user_id).into_bytes();
+        self.client.delete(key).await?;
+        Ok(())
+    }
+
     /// Health check - verify TiKV connectivity
     pub async fn health_check(&self) -> Result<()> {
         // Try to perform a simple operation to verify connectivity
@@ -355,7 +1372,9 @@ impl DatabaseClient {
     }
 
     /// Delete all user data from TiKV
-    /// This removes: user profile, username mapping, identity key, devices, sessions, MLS key packages
+    /// This removes: user profile, username mapping, identity key, devices (with one-time pre-keys),
+    /// sessions (with refresh token families), MLS key packages, contact verifications,
+    /// second-factor settings, security events
     pub async fn delete_user(&self, user_id: &str, username: &str) -> Result<()> {
         tracing::info!("Starting deletion of user data for: {} ({})", username, user_id);
 
@@ -387,6 +1406,9 @@ impl DatabaseClient {
             self.client.delete(key_bytes).await?;
         }
 
+        self.delete_one_time_pre_keys(&format!("/one_time_keys/{}/", user_id)).await?;
+        self.delete_one_time_pre_keys(&format!("/one_time_kem_keys/{}/", user_id)).await?;
+
         // 5. Delete all sessions for this user using range scan
         let sessions_prefix = format!("/sessions/user/{}/", user_id);
         let start_key = sessions_prefix.clone().into_bytes();
@@ -402,6 +1424,9 @@ impl DatabaseClient {
             if let Ok(session) = serde_json::from_slice::<Session>(&kv.1) {
                 let token_key = format!("/sessions/{}", session.session_token).into_bytes();
                 let _ = self.client.delete(token_key).await;
+                if !session.session_id.is_empty() {
+                    self.delete_refresh_family(&session.session_id).await?;
+                }
             }
 
             let key_bytes: Vec<u8> = kv.0.into();
@@ -422,7 +1447,109 @@ impl DatabaseClient {
             self.client.delete(key_bytes).await?;
         }
 
+        // 7. Delete contact verifications
+        let contacts_prefix = format!("/users/{}/contacts/", user_id);
+        let start_key = contacts_prefix.clone().into_bytes();
+        let mut end_key = start_key.clone();
+        if let Some(last) = end_key.last_mut() {
+            *last = *last + 1;
+        }
+
+        let contact_keys = self.client.scan(start_key..end_key, 1000).await?;
+        for kv in contact_keys {
+            let key_bytes: Vec<u8> = kv.0.into();
+            self.client.delete(key_bytes).await?;
+        }
+
+        // 8. Delete second-factor settings
+        self.update_mfa(user_id, |settings| *settings = None).await?;
+
+        // 9. Delete failed login attempts
+        self.update_login_attempts(&crate::login_throttle::account_key(user_id), |attempts| *attempts = None).await?;
+
+        // 10. Delete security events
+        let events_prefix = format!("/users/{}/security_events/", user_id);
+        let start_key = events_prefix.clone().into_bytes();
+        let mut end_key = start_key.clone();
+        if let Some(last) = end_key.last_mut() {
+            *last = *last + 1;
+        }
+
+        let event_keys = self.client.scan(start_key..end_key, 1000).await?;
+        for kv in event_keys {
+            let key_bytes: Vec<u8> = kv.0.into();
+            self.client.delete(key_bytes).await?;
+        }
+
         tracing::info!("Deleted all auth data for user: {}", user_id);
         Ok(())
     }
 }
+
+/// Transactional key prefix of a device's one-time pre-keys
+fn one_time_pre_key_prefix(user_id: &str, device_id: &str) -> String {
+    format!("/one_time_keys/{}/{}/keys/", user_id, device_id)
+}
+
+/// Transactional key prefix of a device's one-time KEM pre-keys
+fn one_time_kem_pre_key_prefix(user_id: &str, device_id: &str) -> String {
+    format!("/one_time_kem_keys/{}/{}/keys/", user_id, device_id)
+}
+
+/// Transactional key of one pre-key under a prefix (zero-padded so keys scan in ID order)
+fn pre_key_path(prefix: &str, key_id: u32) -> Vec<u8> {
+    format!("{}{:010}", prefix, key_id).into_bytes()
+}
+
+/// User, device and index of a one-time pre-key in the legacy raw layout
+fn parse_legacy_one_time_pre_key_path(key: &[u8]) -> Option<(String, String, u32)> {
+    let key = std::str::from_utf8(key).ok()?.strip_prefix("/devices/")?;
+    let mut parts = key.split('/');
+    match (parts.next(), parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(user_id), Some(device_id), Some("one_time_keys"), Some(index), None) => {
+            Some((user_id.to_string(), device_id.to_string(), index.parse().ok()?))
+        }
+        _ => None,
+    }
+}
+
+/// Transactional key of the recent key bundle fetches under a rate limiting key
+fn key_bundle_fetches_path(key: &str) -> Vec<u8> {
+    format!("/key_bundle_fetches/{}", key).into_bytes()
+}
+
+/// Transactional key of a user's second-factor settings
+fn mfa_path(user_id: &str) -> Vec<u8> {
+    format!("/mfa/{}", user_id).into_bytes()
+}
+
+/// Transactional key of a login waiting for its second factor
+fn pending_login_path(challenge_hash: &str) -> Vec<u8> {
+    format!("/mfa_challenges/{}", challenge_hash).into_bytes()
+}
+
+/// Transactional key of the failed login attempts under a throttling key
+fn login_attempts_path(key: &str) -> Vec<u8> {
+    format!("/login_attempts/{}", key).into_bytes()
+}
+
+/// Transactional key of a session's refresh token family
+fn refresh_family_path(session_id: &str) -> Vec<u8> {
+    format!("/refresh_families/{}", session_id).into_bytes()
+}
+
+/// Commit `txn` if `result` is Ok, roll it back otherwise
+async fn finish_transaction<T>(mut txn: Transaction, result: Result<T, TikvError>) -> Result<T, TikvError> {
+    match result {
+        Ok(value) => {
+            txn.commit().await?;
+            Ok(value)
+        }
+        Err(e) => {
+            if let Err(rollback_error) = txn.rollback().await {
+                tracing::warn!("Failed to roll back transaction: {}", rollback_error);
+            }
+            Err(e)
+        }
+    }
+}
@@ -83,7 +83,7 @@ pub struct LoginRequest {
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct LoginResponse {
-    #[prost(oneof = "login_response::Result", tags = "1, 2")]
+    #[prost(oneof = "login_response::Result", tags = "1, 2, 3")]
     pub result: ::core::option::Option<login_response::Result>,
 }
 /// Nested message and enum types in `LoginResponse`.
@@ -92,8 +92,12 @@ pub mod login_response {
     pub enum Result {
         #[prost(message, tag = "1")]
         Success(super::LoginSuccess),
+        /// RATE_LIMITED carries a retry_after_seconds detail
         #[prost(message, tag = "2")]
         Error(super::super::common::ErrorResponse),
+        /// Password accepted, call VerifySecondFactor
+        #[prost(message, tag = "3")]
+        MfaRequired(super::MfaChallenge),
     }
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -258,6 +262,9 @@ pub struct GetKeyBundleRequest {
     /// Target device (optional, if not set returns any device)
     #[prost(string, tag = "2")]
     pub device_id: ::prost::alloc::string::String,
+    /// Authentication (or an "authorization: Bearer" header); fetches are rate limited
+    #[prost(string, tag = "3")]
+    pub access_token: ::prost::alloc::string::String,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct GetKeyBundleResponse {
@@ -280,6 +287,7 @@ pub struct GetKeyBundleSuccess {
     pub user_id: ::prost::alloc::string::String,
     #[prost(string, tag = "2")]
     pub device_id: ::prost::alloc::string::String,
+    /// Contains at most one one-time pre-key, consumed by this fetch
     #[prost(message, optional, tag = "3")]
     pub key_bundle: ::core::option::Option<super::common::KeyBundle>,
 }
@@ -291,6 +299,23 @@ pub struct UploadPreKeysRequest {
     /// New X25519 pre-keys
     #[prost(bytes = "vec", repeated, tag = "2")]
     pub one_time_pre_keys: ::prost::alloc::vec::Vec<::prost::alloc::vec::Vec<u8>>,
+    /// Key IDs, one per pre-key, unique on the device
+    #[prost(uint32, repeated, tag = "3")]
+    pub one_time_pre_key_ids: ::prost::alloc::vec::Vec<u32>,
+    /// Replaces the last-resort pre-key (optional, X25519 public key)
+    #[prost(bytes = "vec", tag = "4")]
+    pub last_resort_pre_key: ::prost::alloc::vec::Vec<u8>,
+    /// Ed25519 signature by the identity key
+    #[prost(bytes = "vec", tag = "5")]
+    pub last_resort_pre_key_signature: ::prost::alloc::vec::Vec<u8>,
+    #[prost(uint32, tag = "6")]
+    pub last_resort_pre_key_id: u32,
+    /// Replaces the last-resort KEM pre-key (optional)
+    #[prost(message, optional, tag = "7")]
+    pub kem_pre_key: ::core::option::Option<super::common::KemPreKey>,
+    /// New one-time KEM pre-keys, IDs unique on the device
+    #[prost(message, repeated, tag = "8")]
+    pub one_time_kem_pre_keys: ::prost::alloc::vec::Vec<super::common::KemPreKey>,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct UploadPreKeysResponse {
@@ -307,12 +332,109 @@ pub mod upload_pre_keys_response {
         Error(super::super::common::ErrorResponse),
     }
 }
-#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct UploadPreKeysSuccess {
     #[prost(uint32, tag = "1")]
     pub keys_uploaded: u32,
     #[prost(uint32, tag = "2")]
     pub total_keys_available: u32,
+    /// Key IDs of the uploaded pre-keys
+    #[prost(uint32, repeated, tag = "3")]
+    pub one_time_pre_key_ids: ::prost::alloc::vec::Vec<u32>,
+    /// One-time KEM pre-keys left on the device
+    #[prost(uint32, tag = "4")]
+    pub total_kem_keys_available: u32,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RotateSignedPreKeyRequest {
+    /// Authentication
+    #[prost(string, tag = "1")]
+    pub access_token: ::prost::alloc::string::String,
+    /// Must differ from the current key ID
+    #[prost(uint32, tag = "2")]
+    pub signed_pre_key_id: u32,
+    /// X25519 public key (32 bytes)
+    #[prost(bytes = "vec", tag = "3")]
+    pub signed_pre_key: ::prost::alloc::vec::Vec<u8>,
+    /// Ed25519 signature by the identity key
+    #[prost(bytes = "vec", tag = "4")]
+    pub signed_pre_key_signature: ::prost::alloc::vec::Vec<u8>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RotateSignedPreKeyResponse {
+    #[prost(oneof = "rotate_signed_pre_key_response::Result", tags = "1, 2")]
+    pub result: ::core::option::Option<rotate_signed_pre_key_response::Result>,
+}
+/// Nested message and enum types in `RotateSignedPreKeyResponse`.
+pub mod rotate_signed_pre_key_response {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Result {
+        #[prost(message, tag = "1")]
+        Success(super::RotateSignedPreKeySuccess),
+        #[prost(message, tag = "2")]
+        Error(super::super::common::ErrorResponse),
+    }
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct RotateSignedPreKeySuccess {
+    #[prost(uint32, tag = "1")]
+    pub signed_pre_key_id: u32,
+    #[prost(uint32, tag = "2")]
+    pub previous_signed_pre_key_id: u32,
+    /// Keep the previous private key until then
+    #[prost(message, optional, tag = "3")]
+    pub previous_valid_until: ::core::option::Option<super::common::Timestamp>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetPreKeyStatusRequest {
+    /// Authentication
+    #[prost(string, tag = "1")]
+    pub access_token: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetPreKeyStatusResponse {
+    #[prost(oneof = "get_pre_key_status_response::Result", tags = "1, 2")]
+    pub result: ::core::option::Option<get_pre_key_status_response::Result>,
+}
+/// Nested message and enum types in `GetPreKeyStatusResponse`.
+pub mod get_pre_key_status_response {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Result {
+        #[prost(message, tag = "1")]
+        Success(super::GetPreKeyStatusSuccess),
+        #[prost(message, tag = "2")]
+        Error(super::super::common::ErrorResponse),
+    }
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct GetPreKeyStatusSuccess {
+    #[prost(uint32, tag = "1")]
+    pub one_time_pre_keys_available: u32,
+    /// True when fewer than low_watermark one-time pre-keys are left
+    #[prost(bool, tag = "2")]
+    pub replenish_needed: bool,
+    #[prost(uint32, tag = "3")]
+    pub low_watermark: u32,
+    #[prost(uint32, tag = "4")]
+    pub signed_pre_key_id: u32,
+    /// True when the signed pre-key is older than the rotation interval
+    #[prost(bool, tag = "5")]
+    pub signed_pre_key_rotation_due: bool,
+    /// Unset if there is no previous key in its grace period
+    #[prost(uint32, tag = "6")]
+    pub previous_signed_pre_key_id: u32,
+    #[prost(message, optional, tag = "7")]
+    pub previous_signed_pre_key_valid_until: ::core::option::Option<
+        super::common::Timestamp,
+    >,
+    /// False if the device still has to upload one
+    #[prost(bool, tag = "8")]
+    pub last_resort_pre_key_present: bool,
+    #[prost(uint32, tag = "9")]
+    pub one_time_kem_pre_keys_available: u32,
+    /// False if the device has no last-resort KEM pre-key (X3DH only)
+    #[prost(bool, tag = "10")]
+    pub kem_pre_key_present: bool,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct UploadMlsKeyPackageRequest {
@@ -345,6 +467,12 @@ pub struct UploadMlsKeyPackageSuccess {
     pub package_id: ::prost::alloc::string::String,
     #[prost(message, optional, tag = "2")]
     pub uploaded_at: ::core::option::Option<super::common::Timestamp>,
+    /// MLS ciphersuite the key package was built for
+    #[prost(uint32, tag = "3")]
+    pub ciphersuite: u32,
+    /// Ciphersuites the device advertises
+    #[prost(uint32, repeated, tag = "4")]
+    pub supported_ciphersuites: ::prost::alloc::vec::Vec<u32>,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct GetMlsKeyPackageRequest {
@@ -354,6 +482,9 @@ pub struct GetMlsKeyPackageRequest {
     /// Target device (optional)
     #[prost(string, tag = "2")]
     pub device_id: ::prost::alloc::string::String,
+    /// Required MLS ciphersuite (0 = latest key package)
+    #[prost(uint32, tag = "3")]
+    pub ciphersuite: u32,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct GetMlsKeyPackageResponse {
@@ -381,6 +512,12 @@ pub struct GetMlsKeyPackageSuccess {
     pub key_package: ::prost::alloc::vec::Vec<u8>,
     #[prost(string, tag = "4")]
     pub package_id: ::prost::alloc::string::String,
+    /// MLS ciphersuite the key package was built for
+    #[prost(uint32, tag = "5")]
+    pub ciphersuite: u32,
+    /// Ciphersuites the device advertises
+    #[prost(uint32, repeated, tag = "6")]
+    pub supported_ciphersuites: ::prost::alloc::vec::Vec<u32>,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct SearchUsersRequest {
@@ -480,423 +617,421 @@ pub struct DeleteAccountSuccess {
     #[prost(string, tag = "2")]
     pub message: ::prost::alloc::string::String,
 }
-/// Generated client implementations.
-pub mod auth_service_client {
-    #![allow(
-        unused_variables,
-        dead_code,
-        missing_docs,
-        clippy::wildcard_imports,
-        clippy::let_unit_value,
-    )]
-    use tonic::codegen::*;
-    use tonic::codegen::http::Uri;
-    #[derive(Debug, Clone)]
-    pub struct AuthServiceClient<T> {
-        inner: tonic::client::Grpc<T>,
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SetContactVerificationRequest {
+    /// Authentication
+    #[prost(string, tag = "1")]
+    pub access_token: ::prost::alloc::string::String,
+    /// Contact whose identity key was compared
+    #[prost(string, tag = "2")]
+    pub contact_user_id: ::prost::alloc::string::String,
+    /// True to mark as verified, false to clear verification
+    #[prost(bool, tag = "3")]
+    pub verified: bool,
+    /// Identity key that was verified (must match the contact's current key)
+    #[prost(bytes = "vec", tag = "4")]
+    pub identity_key: ::prost::alloc::vec::Vec<u8>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SetContactVerificationResponse {
+    #[prost(oneof = "set_contact_verification_response::Result", tags = "1, 2")]
+    pub result: ::core::option::Option<set_contact_verification_response::Result>,
+}
+/// Nested message and enum types in `SetContactVerificationResponse`.
+pub mod set_contact_verification_response {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Result {
+        #[prost(message, tag = "1")]
+        Success(super::ContactVerification),
+        #[prost(message, tag = "2")]
+        Error(super::super::common::ErrorResponse),
     }
-    impl AuthServiceClient<tonic::transport::Channel> {
-        /// Attempt to create a new client by connecting to a given endpoint.
-        pub async fn connect<D>(dst: D) -> Result<Self, tonic::transport::Error>
-        where
-            D: TryInto<tonic::transport::Endpoint>,
-            D::Error: Into<StdError>,
-        {
-            let conn = tonic::transport::Endpoint::new(dst)?.connect().await?;
-            Ok(Self::new(conn))
-        }
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetContactVerificationRequest {
+    /// Authentication
+    #[prost(string, tag = "1")]
+    pub access_token: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub contact_user_id: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetContactVerificationResponse {
+    #[prost(oneof = "get_contact_verification_response::Result", tags = "1, 2")]
+    pub result: ::core::option::Option<get_contact_verification_response::Result>,
+}
+/// Nested message and enum types in `GetContactVerificationResponse`.
+pub mod get_contact_verification_response {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Result {
+        #[prost(message, tag = "1")]
+        Success(super::ContactVerification),
+        #[prost(message, tag = "2")]
+        Error(super::super::common::ErrorResponse),
     }
-    impl<T> AuthServiceClient<T>
-    where
-        T: tonic::client::GrpcService<tonic::body::BoxBody>,
-        T::Error: Into<StdError>,
-        T::ResponseBody: Body<Data = Bytes> + std::marker::Send + 'static,
-        <T::ResponseBody as Body>::Error: Into<StdError> + std::marker::Send,
-    {
-        pub fn new(inner: T) -> Self {
-            let inner = tonic::client::Grpc::new(inner);
-            Self { inner }
-        }
-        pub fn with_origin(inner: T, origin: Uri) -> Self {
-            let inner = tonic::client::Grpc::with_origin(inner, origin);
-            Self { inner }
-        }
-        pub fn with_interceptor<F>(
-            inner: T,
-            interceptor: F,
-        ) -> AuthServiceClient<InterceptedService<T, F>>
-        where
-            F: tonic::service::Interceptor,
-            T::ResponseBody: Default,
-            T: tonic::codegen::Service<
-                http::Request<tonic::body::BoxBody>,
-                Response = http::Response<
-                    <T as tonic::client::GrpcService<tonic::body::BoxBody>>::ResponseBody,
-                >,
-            >,
-            <T as tonic::codegen::Service<
-                http::Request<tonic::body::BoxBody>,
-            >>::Error: Into<StdError> + std::marker::Send + std::marker::Sync,
-        {
-            AuthServiceClient::new(InterceptedService::new(inner, interceptor))
-        }
-        /// Compress requests with the given encoding.
-        ///
-        /// This requires the server to support it otherwise it might respond with an
-        /// error.
-        #[must_use]
-        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
-            self.inner = self.inner.send_compressed(encoding);
-            self
-        }
-        /// Enable decompressing responses.
-        #[must_use]
-        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
-            self.inner = self.inner.accept_compressed(encoding);
-            self
-        }
-        /// Limits the maximum size of a decoded message.
-        ///
-        /// Default: `4MB`
-        #[must_use]
-        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
-            self.inner = self.inner.max_decoding_message_size(limit);
-            self
-        }
-        /// Limits the maximum size of an encoded message.
-        ///
-        /// Default: `usize::MAX`
-        #[must_use]
-        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
-            self.inner = self.inner.max_encoding_message_size(limit);
-            self
-        }
-        /// User registration with E2EE key bundle
-        pub async fn register(
-            &mut self,
-            request: impl tonic::IntoRequest<super::RegisterRequest>,
-        ) -> std::result::Result<
-            tonic::Response<super::RegisterResponse>,
-            tonic::Status,
-        > {
-            self.inner
-                .ready()
-                .await
-                .map_err(|e| {
-                    tonic::Status::unknown(
-                        format!("Service was not ready: {}", e.into()),
-                    )
-                })?;
-            let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static(
-                "/guardyn.auth.AuthService/Register",
-            );
-            let mut req = request.into_request();
-            req.extensions_mut()
-                .insert(GrpcMethod::new("guardyn.auth.AuthService", "Register"));
-            self.inner.unary(req, path, codec).await
-        }
-        /// User login (device authentication)
-        pub async fn login(
-            &mut self,
-            request: impl tonic::IntoRequest<super::LoginRequest>,
-        ) -> std::result::Result<tonic::Response<super::LoginResponse>, tonic::Status> {
-            self.inner
-                .ready()
-                .await
-                .map_err(|e| {
-                    tonic::Status::unknown(
-                        format!("Service was not ready: {}", e.into()),
-                    )
-                })?;
-            let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static(
-                "/guardyn.auth.AuthService/Login",
-            );
-            let mut req = request.into_request();
-            req.extensions_mut()
-                .insert(GrpcMethod::new("guardyn.auth.AuthService", "Login"));
-            self.inner.unary(req, path, codec).await
-        }
-        /// User logout (invalidate session)
-        pub async fn logout(
-            &mut self,
-            request: impl tonic::IntoRequest<super::LogoutRequest>,
-        ) -> std::result::Result<tonic::Response<super::LogoutResponse>, tonic::Status> {
-            self.inner
-                .ready()
-                .await
-                .map_err(|e| {
-                    tonic::Status::unknown(
-                        format!("Service was not ready: {}", e.into()),
-                    )
-                })?;
-            let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static(
-                "/guardyn.auth.AuthService/Logout",
-            );
-            let mut req = request.into_request();
-            req.extensions_mut()
-                .insert(GrpcMethod::new("guardyn.auth.AuthService", "Logout"));
-            self.inner.unary(req, path, codec).await
-        }
-        /// Refresh access token using refresh token
-        pub async fn refresh_token(
-            &mut self,
-            request: impl tonic::IntoRequest<super::RefreshTokenRequest>,
-        ) -> std::result::Result<
-            tonic::Response<super::RefreshTokenResponse>,
-            tonic::Status,
-        > {
-            self.inner
-                .ready()
-                .await
-                .map_err(|e| {
-                    tonic::Status::unknown(
-                        format!("Service was not ready: {}", e.into()),
-                    )
-                })?;
-            let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static(
-                "/guardyn.auth.AuthService/RefreshToken",
-            );
-            let mut req = request.into_request();
-            req.extensions_mut()
-                .insert(GrpcMethod::new("guardyn.auth.AuthService", "RefreshToken"));
-            self.inner.unary(req, path, codec).await
-        }
-        /// Validate JWT token (internal service-to-service)
-        pub async fn validate_token(
-            &mut self,
-            request: impl tonic::IntoRequest<super::ValidateTokenRequest>,
-        ) -> std::result::Result<
-            tonic::Response<super::ValidateTokenResponse>,
-            tonic::Status,
-        > {
-            self.inner
-                .ready()
-                .await
-                .map_err(|e| {
-                    tonic::Status::unknown(
-                        format!("Service was not ready: {}", e.into()),
-                    )
-                })?;
-            let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static(
-                "/guardyn.auth.AuthService/ValidateToken",
-            );
-            let mut req = request.into_request();
-            req.extensions_mut()
-                .insert(GrpcMethod::new("guardyn.auth.AuthService", "ValidateToken"));
-            self.inner.unary(req, path, codec).await
-        }
-        /// Get user's key bundle for E2EE initiation
-        pub async fn get_key_bundle(
-            &mut self,
-            request: impl tonic::IntoRequest<super::GetKeyBundleRequest>,
-        ) -> std::result::Result<
-            tonic::Response<super::GetKeyBundleResponse>,
-            tonic::Status,
-        > {
-            self.inner
-                .ready()
-                .await
-                .map_err(|e| {
-                    tonic::Status::unknown(
-                        format!("Service was not ready: {}", e.into()),
-                    )
-                })?;
-            let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static(
-                "/guardyn.auth.AuthService/GetKeyBundle",
-            );
-            let mut req = request.into_request();
-            req.extensions_mut()
-                .insert(GrpcMethod::new("guardyn.auth.AuthService", "GetKeyBundle"));
-            self.inner.unary(req, path, codec).await
-        }
-        /// Upload new pre-keys (key rotation)
-        pub async fn upload_pre_keys(
-            &mut self,
-            request: impl tonic::IntoRequest<super::UploadPreKeysRequest>,
-        ) -> std::result::Result<
-            tonic::Response<super::UploadPreKeysResponse>,
-            tonic::Status,
-        > {
-            self.inner
-                .ready()
-                .await
-                .map_err(|e| {
-                    tonic::Status::unknown(
-                        format!("Service was not ready: {}", e.into()),
-                    )
-                })?;
-            let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static(
-                "/guardyn.auth.AuthService/UploadPreKeys",
-            );
-            let mut req = request.into_request();
-            req.extensions_mut()
-                .insert(GrpcMethod::new("guardyn.auth.AuthService", "UploadPreKeys"));
-            self.inner.unary(req, path, codec).await
-        }
-        /// Upload MLS key package for group chat (MLS Protocol)
-        pub async fn upload_mls_key_package(
-            &mut self,
-            request: impl tonic::IntoRequest<super::UploadMlsKeyPackageRequest>,
-        ) -> std::result::Result<
-            tonic::Response<super::UploadMlsKeyPackageResponse>,
-            tonic::Status,
-        > {
-            self.inner
-                .ready()
-                .await
-                .map_err(|e| {
-                    tonic::Status::unknown(
-                        format!("Service was not ready: {}", e.into()),
-                    )
-                })?;
-            let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static(
-                "/guardyn.auth.AuthService/UploadMlsKeyPackage",
-            );
-            let mut req = request.into_request();
-            req.extensions_mut()
-                .insert(
-                    GrpcMethod::new("guardyn.auth.AuthService", "UploadMlsKeyPackage"),
-                );
-            self.inner.unary(req, path, codec).await
-        }
-        /// Get MLS key package for a user (used when adding to group)
-        pub async fn get_mls_key_package(
-            &mut self,
-            request: impl tonic::IntoRequest<super::GetMlsKeyPackageRequest>,
-        ) -> std::result::Result<
-            tonic::Response<super::GetMlsKeyPackageResponse>,
-            tonic::Status,
-        > {
-            self.inner
-                .ready()
-                .await
-                .map_err(|e| {
-                    tonic::Status::unknown(
-                        format!("Service was not ready: {}", e.into()),
-                    )
-                })?;
-            let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static(
-                "/guardyn.auth.AuthService/GetMlsKeyPackage",
-            );
-            let mut req = request.into_request();
-            req.extensions_mut()
-                .insert(GrpcMethod::new("guardyn.auth.AuthService", "GetMlsKeyPackage"));
-            self.inner.unary(req, path, codec).await
-        }
-        /// Search for users by username
-        pub async fn search_users(
-            &mut self,
-            request: impl tonic::IntoRequest<super::SearchUsersRequest>,
-        ) -> std::result::Result<
-            tonic::Response<super::SearchUsersResponse>,
-            tonic::Status,
-        > {
-            self.inner
-                .ready()
-                .await
-                .map_err(|e| {
-                    tonic::Status::unknown(
-                        format!("Service was not ready: {}", e.into()),
-                    )
-                })?;
-            let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static(
-                "/guardyn.auth.AuthService/SearchUsers",
-            );
-            let mut req = request.into_request();
-            req.extensions_mut()
-                .insert(GrpcMethod::new("guardyn.auth.AuthService", "SearchUsers"));
-            self.inner.unary(req, path, codec).await
-        }
-        /// Get user profile by user ID (internal service-to-service)
-        pub async fn get_user_profile(
-            &mut self,
-            request: impl tonic::IntoRequest<super::GetUserProfileRequest>,
-        ) -> std::result::Result<
-            tonic::Response<super::GetUserProfileResponse>,
-            tonic::Status,
-        > {
-            self.inner
-                .ready()
-                .await
-                .map_err(|e| {
-                    tonic::Status::unknown(
-                        format!("Service was not ready: {}", e.into()),
-                    )
-                })?;
-            let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static(
-                "/guardyn.auth.AuthService/GetUserProfile",
-            );
-            let mut req = request.into_request();
-            req.extensions_mut()
-                .insert(GrpcMethod::new("guardyn.auth.AuthService", "GetUserProfile"));
-            self.inner.unary(req, path, codec).await
-        }
-        /// Delete user account and all associated data
-        pub async fn delete_account(
-            &mut self,
-            request: impl tonic::IntoRequest<super::DeleteAccountRequest>,
-        ) -> std::result::Result<
-            tonic::Response<super::DeleteAccountResponse>,
-            tonic::Status,
-        > {
-            self.inner
-                .ready()
-                .await
-                .map_err(|e| {
-                    tonic::Status::unknown(
-                        format!("Service was not ready: {}", e.into()),
-                    )
-                })?;
-            let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static(
-                "/guardyn.auth.AuthService/DeleteAccount",
-            );
-            let mut req = request.into_request();
-            req.extensions_mut()
-                .insert(GrpcMethod::new("guardyn.auth.AuthService", "DeleteAccount"));
-            self.inner.unary(req, path, codec).await
-        }
-        /// Health check
-        pub async fn health(
-            &mut self,
-            request: impl tonic::IntoRequest<super::HealthRequest>,
-        ) -> std::result::Result<
-            tonic::Response<super::super::common::HealthStatus>,
-            tonic::Status,
-        > {
-            self.inner
-                .ready()
-                .await
-                .map_err(|e| {
-                    tonic::Status::unknown(
-                        format!("Service was not ready: {}", e.into()),
-                    )
-                })?;
-            let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static(
-                "/guardyn.auth.AuthService/Health",
-            );
-            let mut req = request.into_request();
-            req.extensions_mut()
-                .insert(GrpcMethod::new("guardyn.auth.AuthService", "Health"));
-            self.inner.unary(req, path, codec).await
-        }
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ContactVerification {
+    #[prost(string, tag = "1")]
+    pub contact_user_id: ::prost::alloc::string::String,
+    /// True only while the verified key is the contact's current identity key
+    #[prost(bool, tag = "2")]
+    pub verified: bool,
+    /// Identity key at the time of verification (empty if never verified)
+    #[prost(bytes = "vec", tag = "3")]
+    pub verified_identity_key: ::prost::alloc::vec::Vec<u8>,
+    /// Contact's current identity key
+    #[prost(bytes = "vec", tag = "4")]
+    pub current_identity_key: ::prost::alloc::vec::Vec<u8>,
+    /// Contact was verified, but their identity key has changed since
+    #[prost(bool, tag = "5")]
+    pub identity_key_changed: bool,
+    #[prost(message, optional, tag = "6")]
+    pub verified_at: ::core::option::Option<super::common::Timestamp>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetSenderCertificateRequest {
+    /// Authentication
+    #[prost(string, tag = "1")]
+    pub access_token: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetSenderCertificateResponse {
+    #[prost(oneof = "get_sender_certificate_response::Result", tags = "1, 2")]
+    pub result: ::core::option::Option<get_sender_certificate_response::Result>,
+}
+/// Nested message and enum types in `GetSenderCertificateResponse`.
+pub mod get_sender_certificate_response {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Result {
+        #[prost(message, tag = "1")]
+        Success(super::GetSenderCertificateSuccess),
+        #[prost(message, tag = "2")]
+        Error(super::super::common::ErrorResponse),
     }
 }
-/// Generated server implementations.
-pub mod auth_service_server {
-    #![allow(
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetSenderCertificateSuccess {
+    /// Serialized sender certificate, sealed together with each message
+    #[prost(bytes = "vec", tag = "1")]
+    pub certificate: ::prost::alloc::vec::Vec<u8>,
+    #[prost(message, optional, tag = "2")]
+    pub expires_at: ::core::option::Option<super::common::Timestamp>,
+    /// Ed25519 key recipients verify certificates with
+    #[prost(bytes = "vec", tag = "3")]
+    pub server_public_key: ::prost::alloc::vec::Vec<u8>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetUserDevicesRequest {
+    /// Authentication
+    #[prost(string, tag = "1")]
+    pub access_token: ::prost::alloc::string::String,
+    /// User whose devices to list (empty for the caller)
+    #[prost(string, tag = "2")]
+    pub user_id: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetUserDevicesResponse {
+    #[prost(oneof = "get_user_devices_response::Result", tags = "1, 2")]
+    pub result: ::core::option::Option<get_user_devices_response::Result>,
+}
+/// Nested message and enum types in `GetUserDevicesResponse`.
+pub mod get_user_devices_response {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Result {
+        #[prost(message, tag = "1")]
+        Success(super::GetUserDevicesSuccess),
+        #[prost(message, tag = "2")]
+        Error(super::super::common::ErrorResponse),
+    }
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetUserDevicesSuccess {
+    #[prost(string, tag = "1")]
+    pub user_id: ::prost::alloc::string::String,
+    /// Only device_id is set for other users' devices
+    #[prost(message, repeated, tag = "2")]
+    pub devices: ::prost::alloc::vec::Vec<DeviceInfo>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RemoveDeviceRequest {
+    /// Authentication
+    #[prost(string, tag = "1")]
+    pub access_token: ::prost::alloc::string::String,
+    /// Device to remove (must not be the calling device)
+    #[prost(string, tag = "2")]
+    pub device_id: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RemoveDeviceResponse {
+    #[prost(oneof = "remove_device_response::Result", tags = "1, 2")]
+    pub result: ::core::option::Option<remove_device_response::Result>,
+}
+/// Nested message and enum types in `RemoveDeviceResponse`.
+pub mod remove_device_response {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Result {
+        #[prost(message, tag = "1")]
+        Success(super::RemoveDeviceSuccess),
+        #[prost(message, tag = "2")]
+        Error(super::super::common::ErrorResponse),
+    }
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RemoveDeviceSuccess {
+    #[prost(string, tag = "1")]
+    pub device_id: ::prost::alloc::string::String,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct GetJwksRequest {}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetJwksResponse {
+    /// Current, upcoming and recently retired keys
+    #[prost(message, repeated, tag = "1")]
+    pub keys: ::prost::alloc::vec::Vec<JsonWebKey>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct JsonWebKey {
+    /// Key type ("OKP")
+    #[prost(string, tag = "1")]
+    pub kty: ::prost::alloc::string::String,
+    /// Curve ("Ed25519")
+    #[prost(string, tag = "2")]
+    pub crv: ::prost::alloc::string::String,
+    /// Base64url-encoded public key
+    #[prost(string, tag = "3")]
+    pub x: ::prost::alloc::string::String,
+    /// Key ID, matches the kid header of tokens signed with this key
+    #[prost(string, tag = "4")]
+    pub kid: ::prost::alloc::string::String,
+    /// Signature algorithm ("EdDSA")
+    #[prost(string, tag = "5")]
+    pub alg: ::prost::alloc::string::String,
+    /// Public key use ("sig"), serialized as "use" in JSON
+    #[prost(string, tag = "6")]
+    pub key_use: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListSecurityEventsRequest {
+    /// Authentication
+    #[prost(string, tag = "1")]
+    pub access_token: ::prost::alloc::string::String,
+    /// Maximum number of events (default 50)
+    #[prost(uint32, tag = "2")]
+    pub limit: u32,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListSecurityEventsResponse {
+    #[prost(oneof = "list_security_events_response::Result", tags = "1, 2")]
+    pub result: ::core::option::Option<list_security_events_response::Result>,
+}
+/// Nested message and enum types in `ListSecurityEventsResponse`.
+pub mod list_security_events_response {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Result {
+        #[prost(message, tag = "1")]
+        Success(super::ListSecurityEventsSuccess),
+        #[prost(message, tag = "2")]
+        Error(super::super::common::ErrorResponse),
+    }
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListSecurityEventsSuccess {
+    #[prost(message, repeated, tag = "1")]
+    pub events: ::prost::alloc::vec::Vec<SecurityEvent>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SecurityEvent {
+    #[prost(string, tag = "1")]
+    pub event_id: ::prost::alloc::string::String,
+    #[prost(enumeration = "SecurityEventType", tag = "2")]
+    pub event_type: i32,
+    /// Device the event concerns
+    #[prost(string, tag = "3")]
+    pub device_id: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "4")]
+    pub occurred_at: ::core::option::Option<super::common::Timestamp>,
+    /// Human-readable summary
+    #[prost(string, tag = "5")]
+    pub description: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MfaChallenge {
+    /// Single-use, pass to VerifySecondFactor
+    #[prost(string, tag = "1")]
+    pub challenge_token: ::prost::alloc::string::String,
+    /// Seconds
+    #[prost(uint32, tag = "2")]
+    pub expires_in: u32,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct VerifySecondFactorRequest {
+    /// From LoginResponse.mfa_required
+    #[prost(string, tag = "1")]
+    pub challenge_token: ::prost::alloc::string::String,
+    /// 6-digit code from the authenticator app
+    #[prost(string, tag = "2")]
+    pub totp_code: ::prost::alloc::string::String,
+    /// Or a single-use recovery code
+    #[prost(string, tag = "3")]
+    pub recovery_code: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct VerifySecondFactorResponse {
+    #[prost(oneof = "verify_second_factor_response::Result", tags = "1, 2")]
+    pub result: ::core::option::Option<verify_second_factor_response::Result>,
+}
+/// Nested message and enum types in `VerifySecondFactorResponse`.
+pub mod verify_second_factor_response {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Result {
+        #[prost(message, tag = "1")]
+        Success(super::LoginSuccess),
+        #[prost(message, tag = "2")]
+        Error(super::super::common::ErrorResponse),
+    }
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BeginTotpEnrollmentRequest {
+    /// Authentication
+    #[prost(string, tag = "1")]
+    pub access_token: ::prost::alloc::string::String,
+    /// Re-authentication
+    #[prost(string, tag = "2")]
+    pub password: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BeginTotpEnrollmentResponse {
+    #[prost(oneof = "begin_totp_enrollment_response::Result", tags = "1, 2")]
+    pub result: ::core::option::Option<begin_totp_enrollment_response::Result>,
+}
+/// Nested message and enum types in `BeginTotpEnrollmentResponse`.
+pub mod begin_totp_enrollment_response {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Result {
+        #[prost(message, tag = "1")]
+        Success(super::BeginTotpEnrollmentSuccess),
+        #[prost(message, tag = "2")]
+        Error(super::super::common::ErrorResponse),
+    }
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BeginTotpEnrollmentSuccess {
+    /// Base32-encoded TOTP secret
+    #[prost(string, tag = "1")]
+    pub secret: ::prost::alloc::string::String,
+    /// otpauth:// URI for QR codes
+    #[prost(string, tag = "2")]
+    pub provisioning_uri: ::prost::alloc::string::String,
+    /// Seconds to confirm the enrolment
+    #[prost(uint32, tag = "3")]
+    pub expires_in: u32,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ConfirmTotpEnrollmentRequest {
+    /// Authentication
+    #[prost(string, tag = "1")]
+    pub access_token: ::prost::alloc::string::String,
+    /// First code generated from the new secret
+    #[prost(string, tag = "2")]
+    pub totp_code: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ConfirmTotpEnrollmentResponse {
+    #[prost(oneof = "confirm_totp_enrollment_response::Result", tags = "1, 2")]
+    pub result: ::core::option::Option<confirm_totp_enrollment_response::Result>,
+}
+/// Nested message and enum types in `ConfirmTotpEnrollmentResponse`.
+pub mod confirm_totp_enrollment_response {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Result {
+        #[prost(message, tag = "1")]
+        Success(super::ConfirmTotpEnrollmentSuccess),
+        #[prost(message, tag = "2")]
+        Error(super::super::common::ErrorResponse),
+    }
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ConfirmTotpEnrollmentSuccess {
+    /// Single-use, shown only once
+    #[prost(string, repeated, tag = "1")]
+    pub recovery_codes: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DisableTotpRequest {
+    /// Authentication
+    #[prost(string, tag = "1")]
+    pub access_token: ::prost::alloc::string::String,
+    /// Re-authentication
+    #[prost(string, tag = "2")]
+    pub password: ::prost::alloc::string::String,
+    /// Current code
+    #[prost(string, tag = "3")]
+    pub totp_code: ::prost::alloc::string::String,
+    /// Or a recovery code
+    #[prost(string, tag = "4")]
+    pub recovery_code: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DisableTotpResponse {
+    #[prost(oneof = "disable_totp_response::Result", tags = "1, 2")]
+    pub result: ::core::option::Option<disable_totp_response::Result>,
+}
+/// Nested message and enum types in `DisableTotpResponse`.
+pub mod disable_totp_response {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Result {
+        #[prost(message, tag = "1")]
+        Success(super::DisableTotpSuccess),
+        #[prost(message, tag = "2")]
+        Error(super::super::common::ErrorResponse),
+    }
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct DisableTotpSuccess {}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum SecurityEventType {
+    Unknown = 0,
+    /// A rotated refresh token was presented again; its session was revoked
+    RefreshTokenReuse = 1,
+    TwoFactorEnabled = 2,
+    TwoFactorDisabled = 3,
+    RecoveryCodeUsed = 4,
+    /// Too many failed logins; sign-in is locked for a while
+    AccountLocked = 5,
+}
+impl SecurityEventType {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Self::Unknown => "SECURITY_EVENT_TYPE_UNKNOWN",
+            Self::RefreshTokenReuse => "SECURITY_EVENT_TYPE_REFRESH_TOKEN_REUSE",
+            Self::TwoFactorEnabled => "SECURITY_EVENT_TYPE_TWO_FACTOR_ENABLED",
+            Self::TwoFactorDisabled => "SECURITY_EVENT_TYPE_TWO_FACTOR_DISABLED",
+            Self::RecoveryCodeUsed => "SECURITY_EVENT_TYPE_RECOVERY_CODE_USED",
+            Self::AccountLocked => "SECURITY_EVENT_TYPE_ACCOUNT_LOCKED",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "SECURITY_EVENT_TYPE_UNKNOWN" => Some(Self::Unknown),
+            "SECURITY_EVENT_TYPE_REFRESH_TOKEN_REUSE" => Some(Self::RefreshTokenReuse),
+            "SECURITY_EVENT_TYPE_TWO_FACTOR_ENABLED" => Some(Self::TwoFactorEnabled),
+            "SECURITY_EVENT_TYPE_TWO_FACTOR_DISABLED" => Some(Self::TwoFactorDisabled),
+            "SECURITY_EVENT_TYPE_RECOVERY_CODE_USED" => Some(Self::RecoveryCodeUsed),
+            "SECURITY_EVENT_TYPE_ACCOUNT_LOCKED" => Some(Self::AccountLocked),
+            _ => None,
+        }
+    }
+}
+/// Generated client implementations.
+pub mod auth_service_client {
+    #![allow(
         unused_variables,
         dead_code,
         missing_docs,
@@ -904,203 +1039,1506 @@ pub mod auth_service_server {
         clippy::let_unit_value,
     )]
     use tonic::codegen::*;
-    /// Generated trait containing gRPC methods that should be implemented for use with AuthServiceServer.
-    #[async_trait]
-    pub trait AuthService: std::marker::Send + std::marker::Sync + 'static {
+    use tonic::codegen::http::Uri;
+    #[derive(Debug, Clone)]
+    pub struct AuthServiceClient<T> {
+        inner: tonic::client::Grpc<T>,
+    }
+    impl AuthServiceClient<tonic::transport::Channel> {
+        /// Attempt to create a new client by connecting to a given endpoint.
+        pub async fn connect<D>(dst: D) -> Result<Self, tonic::transport::Error>
+        where
+            D: TryInto<tonic::transport::Endpoint>,
+            D::Error: Into<StdError>,
+        {
+            let conn = tonic::transport::Endpoint::new(dst)?.connect().await?;
+            Ok(Self::new(conn))
+        }
+    }
+    impl<T> AuthServiceClient<T>
+    where
+        T: tonic::client::GrpcService<tonic::body::BoxBody>,
+        T::Error: Into<StdError>,
+        T::ResponseBody: Body<Data = Bytes> + std::marker::Send + 'static,
+        <T::ResponseBody as Body>::Error: Into<StdError> + std::marker::Send,
+    {
+        pub fn new(inner: T) -> Self {
+            let inner = tonic::client::Grpc::new(inner);
+            Self { inner }
+        }
+        pub fn with_origin(inner: T, origin: Uri) -> Self {
+            let inner = tonic::client::Grpc::with_origin(inner, origin);
+            Self { inner }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> AuthServiceClient<InterceptedService<T, F>>
+        where
+            F: tonic::service::Interceptor,
+            T::ResponseBody: Default,
+            T: tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+                Response = http::Response<
+                    <T as tonic::client::GrpcService<tonic::body::BoxBody>>::ResponseBody,
+                >,
+            >,
+            <T as tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+            >>::Error: Into<StdError> + std::marker::Send + std::marker::Sync,
+        {
+            AuthServiceClient::new(InterceptedService::new(inner, interceptor))
+        }
+        /// Compress requests with the given encoding.
+        ///
+        /// This requires the server to support it otherwise it might respond with an
+        /// error.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.send_compressed(encoding);
+            self
+        }
+        /// Enable decompressing responses.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.accept_compressed(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.inner = self.inner.max_decoding_message_size(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.inner = self.inner.max_encoding_message_size(limit);
+            self
+        }
         /// User registration with E2EE key bundle
-        async fn register(
-            &self,
-            request: tonic::Request<super::RegisterRequest>,
+        pub async fn register(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RegisterRequest>,
         ) -> std::result::Result<
             tonic::Response<super::RegisterResponse>,
             tonic::Status,
-        >;
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/guardyn.auth.AuthService/Register",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("guardyn.auth.AuthService", "Register"));
+            self.inner.unary(req, path, codec).await
+        }
         /// User login (device authentication)
-        async fn login(
-            &self,
-            request: tonic::Request<super::LoginRequest>,
-        ) -> std::result::Result<tonic::Response<super::LoginResponse>, tonic::Status>;
+        pub async fn login(
+            &mut self,
+            request: impl tonic::IntoRequest<super::LoginRequest>,
+        ) -> std::result::Result<tonic::Response<super::LoginResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/guardyn.auth.AuthService/Login",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("guardyn.auth.AuthService", "Login"));
+            self.inner.unary(req, path, codec).await
+        }
         /// User logout (invalidate session)
-        async fn logout(
-            &self,
-            request: tonic::Request<super::LogoutRequest>,
-        ) -> std::result::Result<tonic::Response<super::LogoutResponse>, tonic::Status>;
+        pub async fn logout(
+            &mut self,
+            request: impl tonic::IntoRequest<super::LogoutRequest>,
+        ) -> std::result::Result<tonic::Response<super::LogoutResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/guardyn.auth.AuthService/Logout",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("guardyn.auth.AuthService", "Logout"));
+            self.inner.unary(req, path, codec).await
+        }
         /// Refresh access token using refresh token
-        async fn refresh_token(
-            &self,
-            request: tonic::Request<super::RefreshTokenRequest>,
+        pub async fn refresh_token(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RefreshTokenRequest>,
         ) -> std::result::Result<
             tonic::Response<super::RefreshTokenResponse>,
             tonic::Status,
-        >;
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/guardyn.auth.AuthService/RefreshToken",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("guardyn.auth.AuthService", "RefreshToken"));
+            self.inner.unary(req, path, codec).await
+        }
         /// Validate JWT token (internal service-to-service)
-        async fn validate_token(
-            &self,
-            request: tonic::Request<super::ValidateTokenRequest>,
+        pub async fn validate_token(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ValidateTokenRequest>,
         ) -> std::result::Result<
             tonic::Response<super::ValidateTokenResponse>,
             tonic::Status,
-        >;
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/guardyn.auth.AuthService/ValidateToken",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("guardyn.auth.AuthService", "ValidateToken"));
+            self.inner.unary(req, path, codec).await
+        }
         /// Get user's key bundle for E2EE initiation
-        async fn get_key_bundle(
-            &self,
-            request: tonic::Request<super::GetKeyBundleRequest>,
+        pub async fn get_key_bundle(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetKeyBundleRequest>,
         ) -> std::result::Result<
             tonic::Response<super::GetKeyBundleResponse>,
             tonic::Status,
-        >;
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/guardyn.auth.AuthService/GetKeyBundle",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("guardyn.auth.AuthService", "GetKeyBundle"));
+            self.inner.unary(req, path, codec).await
+        }
         /// Upload new pre-keys (key rotation)
-        async fn upload_pre_keys(
-            &self,
-            request: tonic::Request<super::UploadPreKeysRequest>,
+        pub async fn upload_pre_keys(
+            &mut self,
+            request: impl tonic::IntoRequest<super::UploadPreKeysRequest>,
         ) -> std::result::Result<
             tonic::Response<super::UploadPreKeysResponse>,
             tonic::Status,
-        >;
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/guardyn.auth.AuthService/UploadPreKeys",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("guardyn.auth.AuthService", "UploadPreKeys"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Replace the device's signed pre-key (the previous one stays valid for a grace period)
+        pub async fn rotate_signed_pre_key(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RotateSignedPreKeyRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::RotateSignedPreKeyResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/guardyn.auth.AuthService/RotateSignedPreKey",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("guardyn.auth.AuthService", "RotateSignedPreKey"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        /// Get remaining one-time pre-keys and signed pre-key age (tells the device when to replenish/rotate)
+        pub async fn get_pre_key_status(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetPreKeyStatusRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetPreKeyStatusResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/guardyn.auth.AuthService/GetPreKeyStatus",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("guardyn.auth.AuthService", "GetPreKeyStatus"));
+            self.inner.unary(req, path, codec).await
+        }
         /// Upload MLS key package for group chat (MLS Protocol)
-        async fn upload_mls_key_package(
-            &self,
-            request: tonic::Request<super::UploadMlsKeyPackageRequest>,
+        pub async fn upload_mls_key_package(
+            &mut self,
+            request: impl tonic::IntoRequest<super::UploadMlsKeyPackageRequest>,
         ) -> std::result::Result<
             tonic::Response<super::UploadMlsKeyPackageResponse>,
             tonic::Status,
-        >;
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/guardyn.auth.AuthService/UploadMlsKeyPackage",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("guardyn.auth.AuthService", "UploadMlsKeyPackage"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
         /// Get MLS key package for a user (used when adding to group)
-        async fn get_mls_key_package(
-            &self,
-            request: tonic::Request<super::GetMlsKeyPackageRequest>,
+        pub async fn get_mls_key_package(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetMlsKeyPackageRequest>,
         ) -> std::result::Result<
             tonic::Response<super::GetMlsKeyPackageResponse>,
             tonic::Status,
-        >;
-        /// Search for users by username
-        async fn search_users(
-            &self,
-            request: tonic::Request<super::SearchUsersRequest>,
-        ) -> std::result::Result<
-            tonic::Response<super::SearchUsersResponse>,
-            tonic::Status,
-        >;
-        /// Get user profile by user ID (internal service-to-service)
-        async fn get_user_profile(
-            &self,
-            request: tonic::Request<super::GetUserProfileRequest>,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/guardyn.auth.AuthService/GetMlsKeyPackage",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("guardyn.auth.AuthService", "GetMlsKeyPackage"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Search for users by username
+        pub async fn search_users(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SearchUsersRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::SearchUsersResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/guardyn.auth.AuthService/SearchUsers",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("guardyn.auth.AuthService", "SearchUsers"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Get user profile by user ID (internal service-to-service)
+        pub async fn get_user_profile(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetUserProfileRequest>,
         ) -> std::result::Result<
             tonic::Response<super::GetUserProfileResponse>,
             tonic::Status,
-        >;
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/guardyn.auth.AuthService/GetUserProfile",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("guardyn.auth.AuthService", "GetUserProfile"));
+            self.inner.unary(req, path, codec).await
+        }
         /// Delete user account and all associated data
-        async fn delete_account(
-            &self,
-            request: tonic::Request<super::DeleteAccountRequest>,
+        pub async fn delete_account(
+            &mut self,
+            request: impl tonic::IntoRequest<super::DeleteAccountRequest>,
         ) -> std::result::Result<
             tonic::Response<super::DeleteAccountResponse>,
             tonic::Status,
-        >;
-        /// Health check
-        async fn health(
-            &self,
-            request: tonic::Request<super::HealthRequest>,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/guardyn.auth.AuthService/DeleteAccount",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("guardyn.auth.AuthService", "DeleteAccount"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Record whether a contact's identity key was verified out of band (safety number / QR)
+        pub async fn set_contact_verification(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SetContactVerificationRequest>,
         ) -> std::result::Result<
-            tonic::Response<super::super::common::HealthStatus>,
+            tonic::Response<super::SetContactVerificationResponse>,
             tonic::Status,
-        >;
-    }
-    #[derive(Debug)]
-    pub struct AuthServiceServer<T> {
-        inner: Arc<T>,
-        accept_compression_encodings: EnabledCompressionEncodings,
-        send_compression_encodings: EnabledCompressionEncodings,
-        max_decoding_message_size: Option<usize>,
-        max_encoding_message_size: Option<usize>,
-    }
-    impl<T> AuthServiceServer<T> {
-        pub fn new(inner: T) -> Self {
-            Self::from_arc(Arc::new(inner))
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/guardyn.auth.AuthService/SetContactVerification",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("guardyn.auth.AuthService", "SetContactVerification"),
+                );
+            self.inner.unary(req, path, codec).await
         }
-        pub fn from_arc(inner: Arc<T>) -> Self {
-            Self {
-                inner,
-                accept_compression_encodings: Default::default(),
-                send_compression_encodings: Default::default(),
-                max_decoding_message_size: None,
-                max_encoding_message_size: None,
-            }
+        /// Get verification status of a contact's identity key
+        pub async fn get_contact_verification(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetContactVerificationRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetContactVerificationResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/guardyn.auth.AuthService/GetContactVerification",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("guardyn.auth.AuthService", "GetContactVerification"),
+                );
+            self.inner.unary(req, path, codec).await
         }
-        pub fn with_interceptor<F>(
-            inner: T,
-            interceptor: F,
-        ) -> InterceptedService<Self, F>
-        where
-            F: tonic::service::Interceptor,
-        {
-            InterceptedService::new(Self::new(inner), interceptor)
+        /// Issue a short-lived sender certificate for sealed sender messages
+        pub async fn get_sender_certificate(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetSenderCertificateRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetSenderCertificateResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/guardyn.auth.AuthService/GetSenderCertificate",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("guardyn.auth.AuthService", "GetSenderCertificate"),
+                );
+            self.inner.unary(req, path, codec).await
         }
-        /// Enable decompressing requests with the given encoding.
-        #[must_use]
-        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
-            self.accept_compression_encodings.enable(encoding);
-            self
+        /// List a user's registered devices (used to address every device of a user)
+        pub async fn get_user_devices(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetUserDevicesRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetUserDevicesResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/guardyn.auth.AuthService/GetUserDevices",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("guardyn.auth.AuthService", "GetUserDevices"));
+            self.inner.unary(req, path, codec).await
         }
-        /// Compress responses with the given encoding, if the client supports it.
-        #[must_use]
-        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
-            self.send_compression_encodings.enable(encoding);
-            self
+        /// Remove one of the caller's other devices
+        pub async fn remove_device(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RemoveDeviceRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::RemoveDeviceResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/guardyn.auth.AuthService/RemoveDevice",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("guardyn.auth.AuthService", "RemoveDevice"));
+            self.inner.unary(req, path, codec).await
         }
-        /// Limits the maximum size of a decoded message.
-        ///
-        /// Default: `4MB`
-        #[must_use]
-        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
-            self.max_decoding_message_size = Some(limit);
-            self
+        /// Public keys access tokens are signed with (JWKS, RFC 7517)
+        pub async fn get_jwks(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetJwksRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetJwksResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/guardyn.auth.AuthService/GetJwks",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("guardyn.auth.AuthService", "GetJwks"));
+            self.inner.unary(req, path, codec).await
         }
-        /// Limits the maximum size of an encoded message.
-        ///
-        /// Default: `usize::MAX`
-        #[must_use]
-        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
-            self.max_encoding_message_size = Some(limit);
-            self
+        /// Security events on the caller's account, newest first
+        pub async fn list_security_events(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListSecurityEventsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListSecurityEventsResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/guardyn.auth.AuthService/ListSecurityEvents",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("guardyn.auth.AuthService", "ListSecurityEvents"));
+            self.inner.unary(req, path, codec).await
         }
-    }
-    impl<T, B> tonic::codegen::Service<http::Request<B>> for AuthServiceServer<T>
-    where
-        T: AuthService,
-        B: Body + std::marker::Send + 'static,
-        B::Error: Into<StdError> + std::marker::Send + 'static,
-    {
-        type Response = http::Response<tonic::body::BoxBody>;
-        type Error = std::convert::Infallible;
-        type Future = BoxFuture<Self::Response, Self::Error>;
-        fn poll_ready(
+        /// Complete a login that requires a second factor
+        pub async fn verify_second_factor(
             &mut self,
-            _cx: &mut Context<'_>,
-        ) -> Poll<std::result::Result<(), Self::Error>> {
-            Poll::Ready(Ok(()))
+            request: impl tonic::IntoRequest<super::VerifySecondFactorRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::VerifySecondFactorResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/guardyn.auth.AuthService/VerifySecondFactor",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("guardyn.auth.AuthService", "VerifySecondFactor"));
+            self.inner.unary(req, path, codec).await
         }
-        fn call(&mut self, req: http::Request<B>) -> Self::Future {
-            match req.uri().path() {
-                "/guardyn.auth.AuthService/Register" => {
+        /// Start TOTP enrolment (requires the password)
+        pub async fn begin_totp_enrollment(
+            &mut self,
+            request: impl tonic::IntoRequest<super::BeginTotpEnrollmentRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::BeginTotpEnrollmentResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/guardyn.auth.AuthService/BeginTotpEnrollment",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("guardyn.auth.AuthService", "BeginTotpEnrollment"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Confirm TOTP enrolment with a first code and receive recovery codes
+        pub async fn confirm_totp_enrollment(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ConfirmTotpEnrollmentRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ConfirmTotpEnrollmentResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/guardyn.auth.AuthService/ConfirmTotpEnrollment",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("guardyn.auth.AuthService", "ConfirmTotpEnrollment"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Remove TOTP (requires the password and a code)
+        pub async fn disable_totp(
+            &mut self,
+            request: impl tonic::IntoRequest<super::DisableTotpRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::DisableTotpResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/guardyn.auth.AuthService/DisableTotp",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("guardyn.auth.AuthService", "DisableTotp"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Health check
+        pub async fn health(
+            &mut self,
+            request: impl tonic::IntoRequest<super::HealthRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::super::common::HealthStatus>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/guardyn.auth.AuthService/Health",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("guardyn.auth.AuthService", "Health"));
+            self.inner.unary(req, path, codec).await
+        }
+    }
+}
+/// Generated server implementations.
+pub mod auth_service_server {
+    #![allow(
+        unused_variables,
+        dead_code,
+        missing_docs,
+        clippy::wildcard_imports,
+        clippy::let_unit_value,
+    )]
+    use tonic::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with AuthServiceServer.
+    #[async_trait]
+    pub trait AuthService: std::marker::Send + std::marker::Sync + 'static {
+        /// User registration with E2EE key bundle
+        async fn register(
+            &self,
+            request: tonic::Request<super::RegisterRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::RegisterResponse>,
+            tonic::Status,
+        >;
+        /// User login (device authentication)
+        async fn login(
+            &self,
+            request: tonic::Request<super::LoginRequest>,
+        ) -> std::result::Result<tonic::Response<super::LoginResponse>, tonic::Status>;
+        /// User logout (invalidate session)
+        async fn logout(
+            &self,
+            request: tonic::Request<super::LogoutRequest>,
+        ) -> std::result::Result<tonic::Response<super::LogoutResponse>, tonic::Status>;
+        /// Refresh access token using refresh token
+        async fn refresh_token(
+            &self,
+            request: tonic::Request<super::RefreshTokenRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::RefreshTokenResponse>,
+            tonic::Status,
+        >;
+        /// Validate JWT token (internal service-to-service)
+        async fn validate_token(
+            &self,
+            request: tonic::Request<super::ValidateTokenRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ValidateTokenResponse>,
+            tonic::Status,
+        >;
+        /// Get user's key bundle for E2EE initiation
+        async fn get_key_bundle(
+            &self,
+            request: tonic::Request<super::GetKeyBundleRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetKeyBundleResponse>,
+            tonic::Status,
+        >;
+        /// Upload new pre-keys (key rotation)
+        async fn upload_pre_keys(
+            &self,
+            request: tonic::Request<super::UploadPreKeysRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::UploadPreKeysResponse>,
+            tonic::Status,
+        >;
+        /// Replace the device's signed pre-key (the previous one stays valid for a grace period)
+        async fn rotate_signed_pre_key(
+            &self,
+            request: tonic::Request<super::RotateSignedPreKeyRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::RotateSignedPreKeyResponse>,
+            tonic::Status,
+        >;
+        /// Get remaining one-time pre-keys and signed pre-key age (tells the device when to replenish/rotate)
+        async fn get_pre_key_status(
+            &self,
+            request: tonic::Request<super::GetPreKeyStatusRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetPreKeyStatusResponse>,
+            tonic::Status,
+        >;
+        /// Upload MLS key package for group chat (MLS Protocol)
+        async fn upload_mls_key_package(
+            &self,
+            request: tonic::Request<super::UploadMlsKeyPackageRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::UploadMlsKeyPackageResponse>,
+            tonic::Status,
+        >;
+        /// Get MLS key package for a user (used when adding to group)
+        async fn get_mls_key_package(
+            &self,
+            request: tonic::Request<super::GetMlsKeyPackageRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetMlsKeyPackageResponse>,
+            tonic::Status,
+        >;
+        /// Search for users by username
+        async fn search_users(
+            &self,
+            request: tonic::Request<super::SearchUsersRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::SearchUsersResponse>,
+            tonic::Status,
+        >;
+        /// Get user profile by user ID (internal service-to-service)
+        async fn get_user_profile(
+            &self,
+            request: tonic::Request<super::GetUserProfileRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetUserProfileResponse>,
+            tonic::Status,
+        >;
+        /// Delete user account and all associated data
+        async fn delete_account(
+            &self,
+            request: tonic::Request<super::DeleteAccountRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::DeleteAccountResponse>,
+            tonic::Status,
+        >;
+        /// Record whether a contact's identity key was verified out of band (safety number / QR)
+        async fn set_contact_verification(
+            &self,
+            request: tonic::Request<super::SetContactVerificationRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::SetContactVerificationResponse>,
+            tonic::Status,
+        >;
+        /// Get verification status of a contact's identity key
+        async fn get_contact_verification(
+            &self,
+            request: tonic::Request<super::GetContactVerificationRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetContactVerificationResponse>,
+            tonic::Status,
+        >;
+        /// Issue a short-lived sender certificate for sealed sender messages
+        async fn get_sender_certificate(
+            &self,
+            request: tonic::Request<super::GetSenderCertificateRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetSenderCertificateResponse>,
+            tonic::Status,
+        >;
+        /// List a user's registered devices (used to address every device of a user)
+        async fn get_user_devices(
+            &self,
+            request: tonic::Request<super::GetUserDevicesRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetUserDevicesResponse>,
+            tonic::Status,
+        >;
+        /// Remove one of the caller's other devices
+        async fn remove_device(
+            &self,
+            request: tonic::Request<super::RemoveDeviceRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::RemoveDeviceResponse>,
+            tonic::Status,
+        >;
+        /// Public keys access tokens are signed with (JWKS, RFC 7517)
+        async fn get_jwks(
+            &self,
+            request: tonic::Request<super::GetJwksRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetJwksResponse>,
+            tonic::Status,
+        >;
+        /// Security events on the caller's account, newest first
+        async fn list_security_events(
+            &self,
+            request: tonic::Request<super::ListSecurityEventsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListSecurityEventsResponse>,
+            tonic::Status,
+        >;
+        /// Complete a login that requires a second factor
+        async fn verify_second_factor(
+            &self,
+            request: tonic::Request<super::VerifySecondFactorRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::VerifySecondFactorResponse>,
+            tonic::Status,
+        >;
+        /// Start TOTP enrolment (requires the password)
+        async fn begin_totp_enrollment(
+            &self,
+            request: tonic::Request<super::BeginTotpEnrollmentRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::BeginTotpEnrollmentResponse>,
+            tonic::Status,
+        >;
+        /// Confirm TOTP enrolment with a first code and receive recovery codes
+        async fn confirm_totp_enrollment(
+            &self,
+            request: tonic::Request<super::ConfirmTotpEnrollmentRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ConfirmTotpEnrollmentResponse>,
+            tonic::Status,
+        >;
+        /// Remove TOTP (requires the password and a code)
+        async fn disable_totp(
+            &self,
+            request: tonic::Request<super::DisableTotpRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::DisableTotpResponse>,
+            tonic::Status,
+        >;
+        /// Health check
+        async fn health(
+            &self,
+            request: tonic::Request<super::HealthRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::super::common::HealthStatus>,
+            tonic::Status,
+        >;
+    }
+    #[derive(Debug)]
+    pub struct AuthServiceServer<T> {
+        inner: Arc<T>,
+        accept_compression_encodings: EnabledCompressionEncodings,
+        send_compression_encodings: EnabledCompressionEncodings,
+        max_decoding_message_size: Option<usize>,
+        max_encoding_message_size: Option<usize>,
+    }
+    impl<T> AuthServiceServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            Self {
+                inner,
+                accept_compression_encodings: Default::default(),
+                send_compression_encodings: Default::default(),
+                max_decoding_message_size: None,
+                max_encoding_message_size: None,
+            }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> InterceptedService<Self, F>
+        where
+            F: tonic::service::Interceptor,
+        {
+            InterceptedService::new(Self::new(inner), interceptor)
+        }
+        /// Enable decompressing requests with the given encoding.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.accept_compression_encodings.enable(encoding);
+            self
+        }
+        /// Compress responses with the given encoding, if the client supports it.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.send_compression_encodings.enable(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.max_decoding_message_size = Some(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.max_encoding_message_size = Some(limit);
+            self
+        }
+    }
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for AuthServiceServer<T>
+    where
+        T: AuthService,
+        B: Body + std::marker::Send + 'static,
+        B::Error: Into<StdError> + std::marker::Send + 'static,
+    {
+        type Response = http::Response<tonic::body::BoxBody>;
+        type Error = std::convert::Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        fn poll_ready(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<std::result::Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            match req.uri().path() {
+                "/guardyn.auth.AuthService/Register" => {
+                    #[allow(non_camel_case_types)]
+                    struct RegisterSvc<T: AuthService>(pub Arc<T>);
+                    impl<
+                        T: AuthService,
+                    > tonic::server::UnaryService<super::RegisterRequest>
+                    for RegisterSvc<T> {
+                        type Response = super::RegisterResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::RegisterRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as AuthService>::register(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = RegisterSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/guardyn.auth.AuthService/Login" => {
+                    #[allow(non_camel_case_types)]
+                    struct LoginSvc<T: AuthService>(pub Arc<T>);
+                    impl<T: AuthService> tonic::server::UnaryService<super::LoginRequest>
+                    for LoginSvc<T> {
+                        type Response = super::LoginResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::LoginRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as AuthService>::login(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = LoginSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/guardyn.auth.AuthService/Logout" => {
+                    #[allow(non_camel_case_types)]
+                    struct LogoutSvc<T: AuthService>(pub Arc<T>);
+                    impl<
+                        T: AuthService,
+                    > tonic::server::UnaryService<super::LogoutRequest>
+                    for LogoutSvc<T> {
+                        type Response = super::LogoutResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::LogoutRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as AuthService>::logout(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = LogoutSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/guardyn.auth.AuthService/RefreshToken" => {
+                    #[allow(non_camel_case_types)]
+                    struct RefreshTokenSvc<T: AuthService>(pub Arc<T>);
+                    impl<
+                        T: AuthService,
+                    > tonic::server::UnaryService<super::RefreshTokenRequest>
+                    for RefreshTokenSvc<T> {
+                        type Response = super::RefreshTokenResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::RefreshTokenRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as AuthService>::refresh_token(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = RefreshTokenSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/guardyn.auth.AuthService/ValidateToken" => {
+                    #[allow(non_camel_case_types)]
+                    struct ValidateTokenSvc<T: AuthService>(pub Arc<T>);
+                    impl<
+                        T: AuthService,
+                    > tonic::server::UnaryService<super::ValidateTokenRequest>
+                    for ValidateTokenSvc<T> {
+                        type Response = super::ValidateTokenResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ValidateTokenRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as AuthService>::validate_token(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = ValidateTokenSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/guardyn.auth.AuthService/GetKeyBundle" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetKeyBundleSvc<T: AuthService>(pub Arc<T>);
+                    impl<
+                        T: AuthService,
+                    > tonic::server::UnaryService<super::GetKeyBundleRequest>
+                    for GetKeyBundleSvc<T> {
+                        type Response = super::GetKeyBundleResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetKeyBundleRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as AuthService>::get_key_bundle(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = GetKeyBundleSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/guardyn.auth.AuthService/UploadPreKeys" => {
+                    #[allow(non_camel_case_types)]
+                    struct UploadPreKeysSvc<T: AuthService>(pub Arc<T>);
+                    impl<
+                        T: AuthService,
+                    > tonic::server::UnaryService<super::UploadPreKeysRequest>
+                    for UploadPreKeysSvc<T> {
+                        type Response = super::UploadPreKeysResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::UploadPreKeysRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as AuthService>::upload_pre_keys(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = UploadPreKeysSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/guardyn.auth.AuthService/RotateSignedPreKey" => {
+                    #[allow(non_camel_case_types)]
+                    struct RotateSignedPreKeySvc<T: AuthService>(pub Arc<T>);
+                    impl<
+                        T: AuthService,
+                    > tonic::server::UnaryService<super::RotateSignedPreKeyRequest>
+                    for RotateSignedPreKeySvc<T> {
+                        type Response = super::RotateSignedPreKeyResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::RotateSignedPreKeyRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as AuthService>::rotate_signed_pre_key(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = RotateSignedPreKeySvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/guardyn.auth.AuthService/GetPreKeyStatus" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetPreKeyStatusSvc<T: AuthService>(pub Arc<T>);
+                    impl<
+                        T: AuthService,
+                    > tonic::server::UnaryService<super::GetPreKeyStatusRequest>
+                    for GetPreKeyStatusSvc<T> {
+                        type Response = super::GetPreKeyStatusResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetPreKeyStatusRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as AuthService>::get_pre_key_status(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = GetPreKeyStatusSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/guardyn.auth.AuthService/UploadMlsKeyPackage" => {
+                    #[allow(non_camel_case_types)]
+                    struct UploadMlsKeyPackageSvc<T: AuthService>(pub Arc<T>);
+                    impl<
+                        T: AuthService,
+                    > tonic::server::UnaryService<super::UploadMlsKeyPackageRequest>
+                    for UploadMlsKeyPackageSvc<T> {
+                        type Response = super::UploadMlsKeyPackageResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::UploadMlsKeyPackageRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as AuthService>::upload_mls_key_package(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = UploadMlsKeyPackageSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/guardyn.auth.AuthService/GetMlsKeyPackage" => {
                     #[allow(non_camel_case_types)]
-                    struct RegisterSvc<T: AuthService>(pub Arc<T>);
+                    struct GetMlsKeyPackageSvc<T: AuthService>(pub Arc<T>);
                     impl<
                         T: AuthService,
-                    > tonic::server::UnaryService<super::RegisterRequest>
-                    for RegisterSvc<T> {
-                        type Response = super::RegisterResponse;
+                    > tonic::server::UnaryService<super::GetMlsKeyPackageRequest>
+                    for GetMlsKeyPackageSvc<T> {
+                        type Response = super::GetMlsKeyPackageResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::RegisterRequest>,
+                            request: tonic::Request<super::GetMlsKeyPackageRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as AuthService>::register(&inner, request).await
+                                <T as AuthService>::get_mls_key_package(&inner, request)
+                                    .await
                             };
                             Box::pin(fut)
                         }
@@ -1111,7 +2549,7 @@ pub mod auth_service_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = RegisterSvc(inner);
+                        let method = GetMlsKeyPackageSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1127,23 +2565,25 @@ pub mod auth_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/guardyn.auth.AuthService/Login" => {
+                "/guardyn.auth.AuthService/SearchUsers" => {
                     #[allow(non_camel_case_types)]
-                    struct LoginSvc<T: AuthService>(pub Arc<T>);
-                    impl<T: AuthService> tonic::server::UnaryService<super::LoginRequest>
-                    for LoginSvc<T> {
-                        type Response = super::LoginResponse;
+                    struct SearchUsersSvc<T: AuthService>(pub Arc<T>);
+                    impl<
+                        T: AuthService,
+                    > tonic::server::UnaryService<super::SearchUsersRequest>
+                    for SearchUsersSvc<T> {
+                        type Response = super::SearchUsersResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::LoginRequest>,
+                            request: tonic::Request<super::SearchUsersRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as AuthService>::login(&inner, request).await
+                                <T as AuthService>::search_users(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -1154,7 +2594,7 @@ pub mod auth_service_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = LoginSvc(inner);
+                        let method = SearchUsersSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1170,25 +2610,25 @@ pub mod auth_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/guardyn.auth.AuthService/Logout" => {
+                "/guardyn.auth.AuthService/GetUserProfile" => {
                     #[allow(non_camel_case_types)]
-                    struct LogoutSvc<T: AuthService>(pub Arc<T>);
+                    struct GetUserProfileSvc<T: AuthService>(pub Arc<T>);
                     impl<
                         T: AuthService,
-                    > tonic::server::UnaryService<super::LogoutRequest>
-                    for LogoutSvc<T> {
-                        type Response = super::LogoutResponse;
+                    > tonic::server::UnaryService<super::GetUserProfileRequest>
+                    for GetUserProfileSvc<T> {
+                        type Response = super::GetUserProfileResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::LogoutRequest>,
+                            request: tonic::Request<super::GetUserProfileRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as AuthService>::logout(&inner, request).await
+                                <T as AuthService>::get_user_profile(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -1199,7 +2639,7 @@ pub mod auth_service_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = LogoutSvc(inner);
+                        let method = GetUserProfileSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1215,25 +2655,25 @@ pub mod auth_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/guardyn.auth.AuthService/RefreshToken" => {
+                "/guardyn.auth.AuthService/DeleteAccount" => {
                     #[allow(non_camel_case_types)]
-                    struct RefreshTokenSvc<T: AuthService>(pub Arc<T>);
+                    struct DeleteAccountSvc<T: AuthService>(pub Arc<T>);
                     impl<
                         T: AuthService,
-                    > tonic::server::UnaryService<super::RefreshTokenRequest>
-                    for RefreshTokenSvc<T> {
-                        type Response = super::RefreshTokenResponse;
+                    > tonic::server::UnaryService<super::DeleteAccountRequest>
+                    for DeleteAccountSvc<T> {
+                        type Response = super::DeleteAccountResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::RefreshTokenRequest>,
+                            request: tonic::Request<super::DeleteAccountRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as AuthService>::refresh_token(&inner, request).await
+                                <T as AuthService>::delete_account(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -1244,7 +2684,7 @@ pub mod auth_service_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = RefreshTokenSvc(inner);
+                        let method = DeleteAccountSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1260,25 +2700,29 @@ pub mod auth_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/guardyn.auth.AuthService/ValidateToken" => {
+                "/guardyn.auth.AuthService/SetContactVerification" => {
                     #[allow(non_camel_case_types)]
-                    struct ValidateTokenSvc<T: AuthService>(pub Arc<T>);
+                    struct SetContactVerificationSvc<T: AuthService>(pub Arc<T>);
                     impl<
                         T: AuthService,
-                    > tonic::server::UnaryService<super::ValidateTokenRequest>
-                    for ValidateTokenSvc<T> {
-                        type Response = super::ValidateTokenResponse;
+                    > tonic::server::UnaryService<super::SetContactVerificationRequest>
+                    for SetContactVerificationSvc<T> {
+                        type Response = super::SetContactVerificationResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::ValidateTokenRequest>,
+                            request: tonic::Request<super::SetContactVerificationRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as AuthService>::validate_token(&inner, request).await
+                                <T as AuthService>::set_contact_verification(
+                                        &inner,
+                                        request,
+                                    )
+                                    .await
                             };
                             Box::pin(fut)
                         }
@@ -1289,7 +2733,7 @@ pub mod auth_service_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = ValidateTokenSvc(inner);
+                        let method = SetContactVerificationSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1305,25 +2749,29 @@ pub mod auth_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/guardyn.auth.AuthService/GetKeyBundle" => {
+                "/guardyn.auth.AuthService/GetContactVerification" => {
                     #[allow(non_camel_case_types)]
-                    struct GetKeyBundleSvc<T: AuthService>(pub Arc<T>);
+                    struct GetContactVerificationSvc<T: AuthService>(pub Arc<T>);
                     impl<
                         T: AuthService,
-                    > tonic::server::UnaryService<super::GetKeyBundleRequest>
-                    for GetKeyBundleSvc<T> {
-                        type Response = super::GetKeyBundleResponse;
+                    > tonic::server::UnaryService<super::GetContactVerificationRequest>
+                    for GetContactVerificationSvc<T> {
+                        type Response = super::GetContactVerificationResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::GetKeyBundleRequest>,
+                            request: tonic::Request<super::GetContactVerificationRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as AuthService>::get_key_bundle(&inner, request).await
+                                <T as AuthService>::get_contact_verification(
+                                        &inner,
+                                        request,
+                                    )
+                                    .await
                             };
                             Box::pin(fut)
                         }
@@ -1334,7 +2782,7 @@ pub mod auth_service_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = GetKeyBundleSvc(inner);
+                        let method = GetContactVerificationSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1350,25 +2798,26 @@ pub mod auth_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/guardyn.auth.AuthService/UploadPreKeys" => {
+                "/guardyn.auth.AuthService/GetSenderCertificate" => {
                     #[allow(non_camel_case_types)]
-                    struct UploadPreKeysSvc<T: AuthService>(pub Arc<T>);
+                    struct GetSenderCertificateSvc<T: AuthService>(pub Arc<T>);
                     impl<
                         T: AuthService,
-                    > tonic::server::UnaryService<super::UploadPreKeysRequest>
-                    for UploadPreKeysSvc<T> {
-                        type Response = super::UploadPreKeysResponse;
+                    > tonic::server::UnaryService<super::GetSenderCertificateRequest>
+                    for GetSenderCertificateSvc<T> {
+                        type Response = super::GetSenderCertificateResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::UploadPreKeysRequest>,
+                            request: tonic::Request<super::GetSenderCertificateRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as AuthService>::upload_pre_keys(&inner, request).await
+                                <T as AuthService>::get_sender_certificate(&inner, request)
+                                    .await
                             };
                             Box::pin(fut)
                         }
@@ -1379,7 +2828,7 @@ pub mod auth_service_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = UploadPreKeysSvc(inner);
+                        let method = GetSenderCertificateSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1395,26 +2844,25 @@ pub mod auth_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/guardyn.auth.AuthService/UploadMlsKeyPackage" => {
+                "/guardyn.auth.AuthService/GetUserDevices" => {
                     #[allow(non_camel_case_types)]
-                    struct UploadMlsKeyPackageSvc<T: AuthService>(pub Arc<T>);
+                    struct GetUserDevicesSvc<T: AuthService>(pub Arc<T>);
                     impl<
                         T: AuthService,
-                    > tonic::server::UnaryService<super::UploadMlsKeyPackageRequest>
-                    for UploadMlsKeyPackageSvc<T> {
-                        type Response = super::UploadMlsKeyPackageResponse;
+                    > tonic::server::UnaryService<super::GetUserDevicesRequest>
+                    for GetUserDevicesSvc<T> {
+                        type Response = super::GetUserDevicesResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::UploadMlsKeyPackageRequest>,
+                            request: tonic::Request<super::GetUserDevicesRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as AuthService>::upload_mls_key_package(&inner, request)
-                                    .await
+                                <T as AuthService>::get_user_devices(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -1425,7 +2873,7 @@ pub mod auth_service_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = UploadMlsKeyPackageSvc(inner);
+                        let method = GetUserDevicesSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1441,26 +2889,25 @@ pub mod auth_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/guardyn.auth.AuthService/GetMlsKeyPackage" => {
+                "/guardyn.auth.AuthService/RemoveDevice" => {
                     #[allow(non_camel_case_types)]
-                    struct GetMlsKeyPackageSvc<T: AuthService>(pub Arc<T>);
+                    struct RemoveDeviceSvc<T: AuthService>(pub Arc<T>);
                     impl<
                         T: AuthService,
-                    > tonic::server::UnaryService<super::GetMlsKeyPackageRequest>
-                    for GetMlsKeyPackageSvc<T> {
-                        type Response = super::GetMlsKeyPackageResponse;
+                    > tonic::server::UnaryService<super::RemoveDeviceRequest>
+                    for RemoveDeviceSvc<T> {
+                        type Response = super::RemoveDeviceResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::GetMlsKeyPackageRequest>,
+                            request: tonic::Request<super::RemoveDeviceRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as AuthService>::get_mls_key_package(&inner, request)
-                                    .await
+                                <T as AuthService>::remove_device(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -1471,7 +2918,7 @@ pub mod auth_service_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = GetMlsKeyPackageSvc(inner);
+                        let method = RemoveDeviceSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1487,25 +2934,25 @@ pub mod auth_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/guardyn.auth.AuthService/SearchUsers" => {
+                "/guardyn.auth.AuthService/GetJwks" => {
                     #[allow(non_camel_case_types)]
-                    struct SearchUsersSvc<T: AuthService>(pub Arc<T>);
+                    struct GetJwksSvc<T: AuthService>(pub Arc<T>);
                     impl<
                         T: AuthService,
-                    > tonic::server::UnaryService<super::SearchUsersRequest>
-                    for SearchUsersSvc<T> {
-                        type Response = super::SearchUsersResponse;
+                    > tonic::server::UnaryService<super::GetJwksRequest>
+                    for GetJwksSvc<T> {
+                        type Response = super::GetJwksResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::SearchUsersRequest>,
+                            request: tonic::Request<super::GetJwksRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as AuthService>::search_users(&inner, request).await
+                                <T as AuthService>::get_jwks(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -1516,7 +2963,7 @@ pub mod auth_service_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = SearchUsersSvc(inner);
+                        let method = GetJwksSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1532,25 +2979,25 @@ pub mod auth_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/guardyn.auth.AuthService/GetUserProfile" => {
+                "/guardyn.auth.AuthService/ListSecurityEvents" => {
                     #[allow(non_camel_case_types)]
-                    struct GetUserProfileSvc<T: AuthService>(pub Arc<T>);
+                    struct ListSecurityEventsSvc<T: AuthService>(pub Arc<T>);
                     impl<
                         T: AuthService,
-                    > tonic::server::UnaryService<super::GetUserProfileRequest>
-                    for GetUserProfileSvc<T> {
-                        type Response = super::GetUserProfileResponse;
+                    > tonic::server::UnaryService<super::ListSecurityEventsRequest>
+                    for ListSecurityEventsSvc<T> {
+                        type Response = super::ListSecurityEventsResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::GetUserProfileRequest>,
+                            request: tonic::Request<super::ListSecurityEventsRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as AuthService>::get_user_profile(&inner, request).await
+                                <T as AuthService>::list_security_events(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -1561,7 +3008,7 @@ pub mod auth_service_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = GetUserProfileSvc(inner);
+                        let method = ListSecurityEventsSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1577,25 +3024,25 @@ pub mod auth_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/guardyn.auth.AuthService/DeleteAccount" => {
+                "/guardyn.auth.AuthService/VerifySecondFactor" => {
                     #[allow(non_camel_case_types)]
-                    struct DeleteAccountSvc<T: AuthService>(pub Arc<T>);
+                    struct VerifySecondFactorSvc<T: AuthService>(pub Arc<T>);
                     impl<
                         T: AuthService,
-                    > tonic::server::UnaryService<super::DeleteAccountRequest>
-                    for DeleteAccountSvc<T> {
-                        type Response = super::DeleteAccountResponse;
+                    > tonic::server::UnaryService<super::VerifySecondFactorRequest>
+                    for VerifySecondFactorSvc<T> {
+                        type Response = super::VerifySecondFactorResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::DeleteAccountRequest>,
+                            request: tonic::Request<super::VerifySecondFactorRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as AuthService>::delete_account(&inner, request).await
+                                <T as AuthService>::verify_second_factor(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -1606,7 +3053,142 @@ pub mod auth_service_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = DeleteAccountSvc(inner);
+                        let method = VerifySecondFactorSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/guardyn.auth.AuthService/BeginTotpEnrollment" => {
+                    #[allow(non_camel_case_types)]
+                    struct BeginTotpEnrollmentSvc<T: AuthService>(pub Arc<T>);
+                    impl<
+                        T: AuthService,
+                    > tonic::server::UnaryService<super::BeginTotpEnrollmentRequest>
+                    for BeginTotpEnrollmentSvc<T> {
+                        type Response = super::BeginTotpEnrollmentResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::BeginTotpEnrollmentRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as AuthService>::begin_totp_enrollment(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = BeginTotpEnrollmentSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/guardyn.auth.AuthService/ConfirmTotpEnrollment" => {
+                    #[allow(non_camel_case_types)]
+                    struct ConfirmTotpEnrollmentSvc<T: AuthService>(pub Arc<T>);
+                    impl<
+                        T: AuthService,
+                    > tonic::server::UnaryService<super::ConfirmTotpEnrollmentRequest>
+                    for ConfirmTotpEnrollmentSvc<T> {
+                        type Response = super::ConfirmTotpEnrollmentResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ConfirmTotpEnrollmentRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as AuthService>::confirm_totp_enrollment(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = ConfirmTotpEnrollmentSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/guardyn.auth.AuthService/DisableTotp" => {
+                    #[allow(non_camel_case_types)]
+                    struct DisableTotpSvc<T: AuthService>(pub Arc<T>);
+                    impl<
+                        T: AuthService,
+                    > tonic::server::UnaryService<super::DisableTotpRequest>
+                    for DisableTotpSvc<T> {
+                        type Response = super::DisableTotpResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::DisableTotpRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as AuthService>::disable_totp(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = DisableTotpSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
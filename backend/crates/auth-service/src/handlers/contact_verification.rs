@@ -0,0 +1,175 @@
+/// Contact verification handlers - record which identity keys a user has verified
+///
+/// After comparing safety numbers (or scanning a QR code) with a contact, the
+/// client marks the contact as verified for the identity key it compared.
+/// Verification is tied to that key: if the contact later registers a new
+/// identity key, the contact is reported as no longer verified and
+/// `identity_key_changed` is set so the client can warn the user.
+
+use crate::{AuthServiceImpl, proto::auth::*, proto::common::*};
+use tonic::{Request, Response, Status};
+
+fn error(code: error_response::ErrorCode, message: &str) -> ErrorResponse {
+    ErrorResponse {
+        code: code as i32,
+        message: message.to_string(),
+        details: std::collections::HashMap::new(),
+    }
+}
+
+/// Build the verification status from the stored record and the current key
+fn verification_status(
+    contact_user_id: &str,
+    stored: Option<crate::db::ContactVerification>,
+    current_identity_key: Vec<u8>,
+) -> ContactVerification {
+    match stored {
+        Some(stored) => {
+            let key_matches = stored.identity_key == current_identity_key;
+            ContactVerification {
+                contact_user_id: contact_user_id.to_string(),
+                verified: key_matches,
+                verified_identity_key: stored.identity_key,
+                current_identity_key,
+                identity_key_changed: !key_matches,
+                verified_at: Some(Timestamp {
+                    seconds: stored.verified_at,
+                    nanos: 0,
+                }),
+            }
+        }
+        None => ContactVerification {
+            contact_user_id: contact_user_id.to_string(),
+            verified: false,
+            verified_identity_key: vec![],
+            current_identity_key,
+            identity_key_changed: false,
+            verified_at: None,
+        },
+    }
+}
+
+/// Mark a contact as verified (or clear the verification)
+pub async fn set(
+    service: &AuthServiceImpl,
+    request: Request<SetContactVerificationRequest>,
+) -> Result<Response<SetContactVerificationResponse>, Status> {
+    let req = request.into_inner();
+
+    let respond_error = |code: error_response::ErrorCode, message: &str|
+        -> Result<Response<SetContactVerificationResponse>, Status> {
+        Ok(Response::new(SetContactVerificationResponse {
+            result: Some(set_contact_verification_response::Result::Error(error(code, message))),
+        }))
+    };
+
+    // Validate access token
+    let claims = match crate::jwt::validate_token(&req.access_token) {
+        Ok(c) => c,
+        Err(_) => return respond_error(error_response::ErrorCode::Unauthorized, "Invalid or expired token"),
+    };
+
+    if req.contact_user_id.is_empty() || req.contact_user_id == claims.sub {
+        return respond_error(error_response::ErrorCode::InvalidRequest, "Invalid contact user ID");
+    }
+
+    let current_identity_key = match service.db.get_identity_key(&req.contact_user_id).await {
+        Ok(Some(key)) => key,
+        Ok(None) => return respond_error(error_response::ErrorCode::NotFound, "Contact identity key not found"),
+        Err(e) => {
+            tracing::error!("Database error: {}", e);
+            return respond_error(error_response::ErrorCode::InternalError, "Internal server error");
+        }
+    };
+
+    let stored = if req.verified {
+        // Only the key the contact currently uses can be verified, otherwise the
+        // client compared a stale safety number
+        if req.identity_key != current_identity_key {
+            return respond_error(
+                error_response::ErrorCode::Conflict,
+                "Identity key does not match the contact's current identity key",
+            );
+        }
+
+        let verification = crate::db::ContactVerification {
+            contact_user_id: req.contact_user_id.clone(),
+            identity_key: current_identity_key.clone(),
+            verified_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs() as i64,
+        };
+
+        if let Err(e) = service.db.set_contact_verification(&claims.sub, &verification).await {
+            tracing::error!("Failed to store contact verification: {}", e);
+            return respond_error(error_response::ErrorCode::InternalError, "Failed to store verification");
+        }
+        Some(verification)
+    } else {
+        if let Err(e) = service.db.delete_contact_verification(&claims.sub, &req.contact_user_id).await {
+            tracing::error!("Failed to clear contact verification: {}", e);
+            return respond_error(error_response::ErrorCode::InternalError, "Failed to clear verification");
+        }
+        None
+    };
+
+    tracing::info!(
+        "User {} set verification of contact {} to {}",
+        claims.sub, req.contact_user_id, req.verified
+    );
+
+    Ok(Response::new(SetContactVerificationResponse {
+        result: Some(set_contact_verification_response::Result::Success(
+            verification_status(&req.contact_user_id, stored, current_identity_key),
+        )),
+    }))
+}
+
+/// Get the verification status of a contact
+pub async fn get(
+    service: &AuthServiceImpl,
+    request: Request<GetContactVerificationRequest>,
+) -> Result<Response<GetContactVerificationResponse>, Status> {
+    let req = request.into_inner();
+
+    let respond_error = |code: error_response::ErrorCode, message: &str|
+        -> Result<Response<GetContactVerificationResponse>, Status> {
+        Ok(Response::new(GetContactVerificationResponse {
+            result: Some(get_contact_verification_response::Result::Error(error(code, message))),
+        }))
+    };
+
+    // Validate access token
+    let claims = match crate::jwt::validate_token(&req.access_token) {
+        Ok(c) => c,
+        Err(_) => return respond_error(error_response::ErrorCode::Unauthorized, "Invalid or expired token"),
+    };
+
+    if req.contact_user_id.is_empty() {
+        return respond_error(error_response::ErrorCode::InvalidRequest, "Invalid contact user ID");
+    }
+
+    let current_identity_key = match service.db.get_identity_key(&req.contact_user_id).await {
+        Ok(Some(key)) => key,
+        Ok(None) => return respond_error(error_response::ErrorCode::NotFound, "Contact identity key not found"),
+        Err(e) => {
+            tracing::error!("Database error: {}", e);
+            return respond_error(error_response::ErrorCode::InternalError, "Internal server error");
+        }
+    };
+
+    let stored = match service.db.get_contact_verification(&claims.sub, &req.contact_user_id).await {
+        Ok(stored) => stored,
+        Err(e) => {
+            tracing::error!("Database error: {}", e);
+            return respond_error(error_response::ErrorCode::InternalError, "Internal server error");
+        }
+    };
+
+    Ok(Response::new(GetContactVerificationResponse {
+        result: Some(get_contact_verification_response::Result::Success(
+            verification_status(&req.contact_user_id, stored, current_identity_key),
+        )),
+    }))
+}
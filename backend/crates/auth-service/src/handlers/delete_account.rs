@@ -33,7 +33,7 @@ pub async fn handle(
     let req = request.into_inner();
 
     // 1. Validate access token
-    let claims = match crate::jwt::validate_token(&req.access_token, &service.jwt_secret) {
+    let claims = match crate::jwt::validate_token(&req.access_token) {
         Ok(c) => c,
         Err(_) => {
             let error = ErrorResponse {
@@ -87,6 +87,19 @@ pub async fn handle(
         }));
     }
 
+    // Remember the sessions so their tokens can be revoked once the data is gone
+    let session_ids: Vec<String> = match service.db.list_sessions(&user_id).await {
+        Ok(sessions) => sessions
+            .into_iter()
+            .filter(|session| !session.session_id.is_empty())
+            .map(|session| session.session_id)
+            .collect(),
+        Err(e) => {
+            tracing::warn!("Failed to list sessions of {}: {}", user_id, e);
+            Vec::new()
+        }
+    };
+
     // 4. Delete all user data from auth-service
     tracing::info!("Deleting user account: {} ({})", user.username, user_id);
     if let Err(e) = service.db.delete_user(&user_id, &user.username).await {
@@ -101,6 +114,12 @@ pub async fn handle(
         }));
     }
 
+    for session_id in &session_ids {
+        if let Err(e) = crate::revocation::revoke_session(service, &user_id, session_id).await {
+            tracing::error!("Failed to revoke session {} of deleted account: {}", session_id, e);
+        }
+    }
+
     // 5. TODO: In production, we would also:
     // - Call messaging-service to delete all messages and conversations
     // - Call media-service to delete all uploaded files
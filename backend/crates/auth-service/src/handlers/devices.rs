@@ -0,0 +1,163 @@
+/// Device management handlers - list and remove a user's devices
+///
+/// Senders fan out every message to all devices of the recipient and to their
+/// own sibling devices, so the device list returned here is the one
+/// messaging-service checks sends against. Removing a device also drops its
+/// key material and sessions, so it disappears from that list immediately.
+
+use crate::{AuthServiceImpl, proto::auth::*, proto::common::*};
+use tonic::{Request, Response, Status};
+
+fn error(code: error_response::ErrorCode, message: &str) -> ErrorResponse {
+    ErrorResponse {
+        code: code as i32,
+        message: message.to_string(),
+        details: std::collections::HashMap::new(),
+    }
+}
+
+/// List the devices of a user (the caller's own devices if `user_id` is empty)
+///
+/// Only the caller's own devices come with names, types and timestamps; other
+/// users' devices are listed by ID, which is all fan-out needs.
+pub async fn list(
+    service: &AuthServiceImpl,
+    request: Request<GetUserDevicesRequest>,
+) -> Result<Response<GetUserDevicesResponse>, Status> {
+    let req = request.into_inner();
+
+    let respond_error = |code: error_response::ErrorCode, message: &str|
+        -> Result<Response<GetUserDevicesResponse>, Status> {
+        Ok(Response::new(GetUserDevicesResponse {
+            result: Some(get_user_devices_response::Result::Error(error(code, message))),
+        }))
+    };
+
+    // Validate access token
+    let claims = match crate::jwt::validate_token(&req.access_token) {
+        Ok(c) => c,
+        Err(_) => return respond_error(error_response::ErrorCode::Unauthorized, "Invalid or expired token"),
+    };
+
+    let user_id = if req.user_id.is_empty() {
+        claims.sub.clone()
+    } else {
+        req.user_id
+    };
+
+    let devices = match service.db.list_devices(&user_id).await {
+        Ok(devices) => devices,
+        Err(e) => {
+            tracing::error!("Database error: {}", e);
+            return respond_error(error_response::ErrorCode::InternalError, "Internal server error");
+        }
+    };
+
+    if devices.is_empty() {
+        return respond_error(error_response::ErrorCode::NotFound, "User has no registered devices");
+    }
+
+    let own = user_id == claims.sub;
+    let devices = devices
+        .into_iter()
+        .map(|device| if own {
+            DeviceInfo {
+                is_current: device.device_id == claims.device_id,
+                device_id: device.device_id,
+                device_name: device.device_name,
+                device_type: device.device_type,
+                created_at: Some(Timestamp {
+                    seconds: device.created_at,
+                    nanos: 0,
+                }),
+                last_seen: Some(Timestamp {
+                    seconds: device.last_seen,
+                    nanos: 0,
+                }),
+            }
+        } else {
+            DeviceInfo {
+                device_id: device.device_id,
+                ..Default::default()
+            }
+        })
+        .collect();
+
+    Ok(Response::new(GetUserDevicesResponse {
+        result: Some(get_user_devices_response::Result::Success(GetUserDevicesSuccess {
+            user_id,
+            devices,
+        })),
+    }))
+}
+
+/// Remove one of the caller's other devices
+pub async fn remove(
+    service: &AuthServiceImpl,
+    request: Request<RemoveDeviceRequest>,
+) -> Result<Response<RemoveDeviceResponse>, Status> {
+    let req = request.into_inner();
+
+    let respond_error = |code: error_response::ErrorCode, message: &str|
+        -> Result<Response<RemoveDeviceResponse>, Status> {
+        Ok(Response::new(RemoveDeviceResponse {
+            result: Some(remove_device_response::Result::Error(error(code, message))),
+        }))
+    };
+
+    // Validate access token
+    let claims = match crate::jwt::validate_token(&req.access_token) {
+        Ok(c) => c,
+        Err(_) => return respond_error(error_response::ErrorCode::Unauthorized, "Invalid or expired token"),
+    };
+
+    if req.device_id.is_empty() {
+        return respond_error(error_response::ErrorCode::InvalidRequest, "Device ID required");
+    }
+
+    // The calling device logs out instead of removing itself
+    if req.device_id == claims.device_id {
+        return respond_error(error_response::ErrorCode::InvalidRequest, "Cannot remove the current device");
+    }
+
+    match service.db.get_device(&claims.sub, &req.device_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return respond_error(error_response::ErrorCode::NotFound, "Device not found"),
+        Err(e) => {
+            tracing::error!("Database error: {}", e);
+            return respond_error(error_response::ErrorCode::InternalError, "Internal server error");
+        }
+    }
+
+    // Sessions are deleted along with the device; their tokens must stop working too
+    let session_ids: Vec<String> = match service.db.list_sessions(&claims.sub).await {
+        Ok(sessions) => sessions
+            .into_iter()
+            .filter(|session| session.device_id == req.device_id && !session.session_id.is_empty())
+            .map(|session| session.session_id)
+            .collect(),
+        Err(e) => {
+            tracing::error!("Failed to list sessions: {}", e);
+            return respond_error(error_response::ErrorCode::InternalError, "Internal server error");
+        }
+    };
+
+    // Revoke first, so a failed revocation leaves the device in place for a retry
+    for session_id in &session_ids {
+        if let Err(e) = crate::revocation::revoke_session(service, &claims.sub, session_id).await {
+            tracing::error!("Failed to revoke session {} of removed device: {}", session_id, e);
+            return respond_error(error_response::ErrorCode::InternalError, "Failed to revoke session");
+        }
+    }
+
+    if let Err(e) = service.db.delete_device(&claims.sub, &req.device_id).await {
+        tracing::error!("Failed to delete device: {}", e);
+        return respond_error(error_response::ErrorCode::InternalError, "Failed to remove device");
+    }
+
+    Ok(Response::new(RemoveDeviceResponse {
+        result: Some(remove_device_response::Result::Success(RemoveDeviceSuccess {
+            device_id: req.device_id,
+        })),
+    }))
+}
@@ -0,0 +1,38 @@
+/// JWKS handler - publishes the token verification keys
+///
+/// Returns the same key set that is served over HTTP and stored in TiKV for
+/// the other services: the current signing key, the next one and the retired
+/// keys whose tokens may still be valid.
+
+use crate::{AuthServiceImpl, proto::auth::*};
+use tonic::{Request, Response, Status};
+
+pub async fn handle(
+    service: &AuthServiceImpl,
+    _request: Request<GetJwksRequest>,
+) -> Result<Response<GetJwksResponse>, Status> {
+    let jwks = service.jwt_keys.jwks().map_err(|e| {
+        tracing::error!("Failed to compute token signing keys: {}", e);
+        Status::internal("Failed to load signing keys")
+    })?;
+
+    let keys = jwks
+        .keys
+        .iter()
+        .map(|jwk| {
+            // Read the JSON representation so the fields match the HTTP document
+            let json = serde_json::to_value(jwk).unwrap_or_default();
+            let field = |name: &str| json.get(name).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+            JsonWebKey {
+                kty: field("kty"),
+                crv: field("crv"),
+                x: field("x"),
+                kid: field("kid"),
+                alg: field("alg"),
+                key_use: field("use"),
+            }
+        })
+        .collect();
+
+    Ok(Response::new(GetJwksResponse { keys }))
+}
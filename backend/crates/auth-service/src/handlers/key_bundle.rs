@@ -1,18 +1,206 @@
 /// Key bundle handlers - for E2EE key exchange
+///
+/// Every bundle fetch consumes one one-time pre-key; bundles also carry the
+/// device's last-resort pre-key, which initiators use once those run out.
+/// PQXDH devices publish ML-KEM-768 pre-keys the same way: a signed
+/// last-resort KEM pre-key and one-time KEM pre-keys, one consumed per fetch.
+/// Devices poll `GetPreKeyStatus` and upload more once they drop below the low
+/// watermark, and rotate their signed pre-key when it is due; the replaced key
+/// stays valid for a grace period so in-flight key exchanges still complete.
+/// Fetching a bundle requires an access token and is rate limited (see
+/// `key_bundle_throttle`), so nobody can drain a device's one-time pre-keys.
 
 use crate::{AuthServiceImpl, proto::auth::*, proto::common::*};
+use guardyn_crypto::pq_kem::KemPreKeyPublic;
+use guardyn_crypto::x3dh::{
+    IdentityKeyPair, LastResortPreKeyPublic, SIGNED_PRE_KEY_GRACE_PERIOD_SECS,
+    SIGNED_PRE_KEY_ROTATION_INTERVAL_SECS,
+};
 use tonic::{Request, Response, Status};
 
-/// Get key bundle for a user
+/// Devices should upload more one-time pre-keys below this count
+pub const ONE_TIME_PRE_KEY_LOW_WATERMARK: u32 = 20;
+
+fn error(code: error_response::ErrorCode, message: &str) -> ErrorResponse {
+    ErrorResponse {
+        code: code as i32,
+        message: message.to_string(),
+        details: std::collections::HashMap::new(),
+    }
+}
+
+impl From<KemPreKey> for crate::db::KemPreKey {
+    fn from(key: KemPreKey) -> Self {
+        Self {
+            key_id: key.key_id,
+            public_key: key.public_key,
+            signature: key.signature,
+        }
+    }
+}
+
+impl From<crate::db::KemPreKey> for KemPreKey {
+    fn from(key: crate::db::KemPreKey) -> Self {
+        Self {
+            key_id: key.key_id,
+            public_key: key.public_key,
+            signature: key.signature,
+        }
+    }
+}
+
+/// Check that KEM pre-keys are signed by the identity key and that one-time
+/// KEM pre-keys have unique IDs
+pub fn check_kem_pre_keys(
+    identity_key: &[u8],
+    kem_pre_key: Option<&KemPreKey>,
+    one_time_kem_pre_keys: &[KemPreKey],
+) -> Result<(), &'static str> {
+    let mut seen = std::collections::HashSet::new();
+    if !one_time_kem_pre_keys.iter().all(|key| seen.insert(key.key_id)) {
+        return Err("Duplicate one-time KEM pre-key ID");
+    }
+
+    let verified = kem_pre_key.into_iter().chain(one_time_kem_pre_keys).all(|key| {
+        KemPreKeyPublic {
+            key_id: key.key_id,
+            public_key: key.public_key.clone(),
+            signature: key.signature.clone(),
+        }
+        .verify(identity_key)
+        .is_ok()
+    });
+    if !verified {
+        return Err("Invalid KEM pre-key signature");
+    }
+
+    Ok(())
+}
+
+/// Check that every one-time pre-key comes with its own key ID
+pub fn check_one_time_pre_key_ids(keys: &[Vec<u8>], key_ids: &[u32]) -> Result<(), &'static str> {
+    if key_ids.len() != keys.len() {
+        return Err("One key ID required per one-time pre-key");
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    if !key_ids.iter().all(|key_id| seen.insert(*key_id)) {
+        return Err("Duplicate one-time pre-key ID");
+    }
+
+    Ok(())
+}
+
+/// Check a key bundle sent with registration or the login of a new device
+///
+/// A last-resort pre-key and KEM pre-keys must be signed by the bundle's
+/// identity key, as in `UploadPreKeys`; initiators fall back to the
+/// last-resort pre-key once one-time pre-keys run out.
+pub fn check_key_bundle(key_bundle: &KeyBundle) -> Result<(), &'static str> {
+    check_one_time_pre_key_ids(&key_bundle.one_time_pre_keys, &key_bundle.one_time_pre_key_ids)?;
+    check_kem_pre_keys(
+        &key_bundle.identity_key,
+        key_bundle.kem_pre_key.as_ref(),
+        &key_bundle.one_time_kem_pre_keys,
+    )?;
+
+    if !key_bundle.last_resort_pre_key.is_empty() {
+        let last_resort_pre_key = LastResortPreKeyPublic {
+            key_id: key_bundle.last_resort_pre_key_id,
+            public_key: key_bundle.last_resort_pre_key.clone(),
+            signature: key_bundle.last_resort_pre_key_signature.clone(),
+        };
+        if last_resort_pre_key.verify(&key_bundle.identity_key).is_err() {
+            return Err("Invalid last-resort pre-key signature");
+        }
+    }
+
+    Ok(())
+}
+
+/// Access token from the request, or from `authorization: Bearer` metadata
+fn access_token(request: &Request<GetKeyBundleRequest>) -> String {
+    if !request.get_ref().access_token.is_empty() {
+        return request.get_ref().access_token.clone();
+    }
+
+    request
+        .metadata()
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Get key bundle for a user (consumes one one-time pre-key)
+///
+/// Callers must be authenticated, and fetches are rate limited per calling
+/// device and per target device. Who took which one-time pre-key is logged.
 pub async fn get(
     service: &AuthServiceImpl,
     request: Request<GetKeyBundleRequest>,
 ) -> Result<Response<GetKeyBundleResponse>, Status> {
+    let access_token = access_token(&request);
     let req = request.into_inner();
 
+    let respond_error = |error: ErrorResponse| -> Result<Response<GetKeyBundleResponse>, Status> {
+        Ok(Response::new(GetKeyBundleResponse {
+            result: Some(get_key_bundle_response::Result::Error(error)),
+        }))
+    };
+
+    // Validate access token
+    let claims = match crate::jwt::validate_token(&access_token) {
+        Ok(c) => c,
+        Err(_) => {
+            return respond_error(error(error_response::ErrorCode::Unauthorized, "Invalid or expired token"));
+        }
+    };
+
+    match crate::key_bundle_throttle::reserve(
+        service,
+        (&claims.sub, &claims.device_id),
+        (&req.user_id, &req.device_id),
+    ).await {
+        Ok(None) => {}
+        Ok(Some(retry_after)) => {
+            tracing::warn!(
+                "Rate limited key bundle fetch of device {} (user {}) by device {} (user {})",
+                req.device_id,
+                req.user_id,
+                claims.device_id,
+                claims.sub
+            );
+            return respond_error(crate::key_bundle_throttle::rate_limited(retry_after));
+        }
+        Err(e) => {
+            tracing::error!("Database error: {}", e);
+            return respond_error(error(error_response::ErrorCode::InternalError, "Internal server error"));
+        }
+    }
+
     // Get key bundle from database
     match service.db.get_key_bundle(&req.user_id, &req.device_id).await {
         Ok(Some(kb)) => {
+            match kb.one_time_pre_key_ids.first() {
+                Some(key_id) => tracing::info!(
+                    "Device {} (user {}) took one-time pre-key {} of device {} (user {})",
+                    claims.device_id,
+                    claims.sub,
+                    key_id,
+                    req.device_id,
+                    req.user_id
+                ),
+                None => tracing::warn!(
+                    "Device {} of user {} has no one-time pre-keys left (fetched by device {} of user {})",
+                    req.device_id,
+                    req.user_id,
+                    claims.device_id,
+                    claims.sub
+                ),
+            }
+
             let key_bundle = KeyBundle {
                 identity_key: kb.identity_key,
                 signed_pre_key: kb.signed_pre_key,
@@ -22,6 +210,13 @@ pub async fn get(
                     seconds: kb.created_at,
                     nanos: 0,
                 }),
+                signed_pre_key_id: kb.signed_pre_key_id,
+                one_time_pre_key_ids: kb.one_time_pre_key_ids,
+                last_resort_pre_key_id: kb.last_resort_pre_key.as_ref().map(|k| k.key_id).unwrap_or_default(),
+                last_resort_pre_key_signature: kb.last_resort_pre_key.as_ref().map(|k| k.signature.clone()).unwrap_or_default(),
+                last_resort_pre_key: kb.last_resort_pre_key.map(|k| k.public_key).unwrap_or_default(),
+                kem_pre_key: kb.kem_pre_key.map(KemPreKey::from),
+                one_time_kem_pre_keys: kb.one_time_kem_pre_keys.into_iter().map(KemPreKey::from).collect(),
             };
 
         let success = GetKeyBundleSuccess {
@@ -64,7 +259,7 @@ pub async fn upload(
     let req = request.into_inner();
 
     // Validate access token
-    let claims = match crate::jwt::validate_token(&req.access_token, &service.jwt_secret) {
+    let claims = match crate::jwt::validate_token(&req.access_token) {
         Ok(c) => c,
         Err(_) => {
             let error = ErrorResponse {
@@ -78,34 +273,166 @@ pub async fn upload(
         }
     };
 
-    let now = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs() as i64;
+    if let Err(message) = check_one_time_pre_key_ids(&req.one_time_pre_keys, &req.one_time_pre_key_ids) {
+        let error = ErrorResponse {
+            code: error_response::ErrorCode::InvalidRequest as i32,
+            message: message.to_string(),
+            details: std::collections::HashMap::new(),
+        };
+        return Ok(Response::new(UploadPreKeysResponse {
+            result: Some(upload_pre_keys_response::Result::Error(error)),
+        }));
+    }
 
-    // Update key bundle in database
-    let key_bundle = crate::db::KeyBundle {
-        identity_key: vec![], // Keep existing identity key
-        signed_pre_key: vec![], // Signed pre-key not provided in this request
-        signed_pre_key_signature: vec![], // Signature not provided in this request
-        one_time_pre_keys: req.one_time_pre_keys.clone(),
-        created_at: now,
-    };
+    // Replace the last-resort pre-key if one was sent; it must be signed by the identity key
+    if !req.last_resort_pre_key.is_empty() {
+        let uploaded = LastResortPreKeyPublic {
+            key_id: req.last_resort_pre_key_id,
+            public_key: req.last_resort_pre_key.clone(),
+            signature: req.last_resort_pre_key_signature.clone(),
+        };
+        let verified = match service.db.get_identity_key(&claims.sub).await {
+            Ok(Some(identity_key)) => uploaded.verify(&identity_key).is_ok(),
+            Ok(None) => false,
+            Err(e) => {
+                tracing::error!("Database error: {}", e);
+                let error = ErrorResponse {
+                    code: error_response::ErrorCode::InternalError as i32,
+                    message: "Internal server error".to_string(),
+                    details: std::collections::HashMap::new(),
+                };
+                return Ok(Response::new(UploadPreKeysResponse {
+                    result: Some(upload_pre_keys_response::Result::Error(error)),
+                }));
+            }
+        };
+
+        if !verified {
+            let error = ErrorResponse {
+                code: error_response::ErrorCode::InvalidRequest as i32,
+                message: "Invalid last-resort pre-key signature".to_string(),
+                details: std::collections::HashMap::new(),
+            };
+            return Ok(Response::new(UploadPreKeysResponse {
+                result: Some(upload_pre_keys_response::Result::Error(error)),
+            }));
+        }
+
+        let last_resort_pre_key = crate::db::LastResortPreKey {
+            key_id: req.last_resort_pre_key_id,
+            public_key: req.last_resort_pre_key.clone(),
+            signature: req.last_resort_pre_key_signature.clone(),
+        };
+
+        if let Err(e) = service.db.set_last_resort_pre_key(&claims.sub, &claims.device_id, &last_resort_pre_key).await {
+            tracing::error!("Failed to store last-resort pre-key: {}", e);
+            let error = ErrorResponse {
+                code: error_response::ErrorCode::InternalError as i32,
+                message: "Failed to upload keys".to_string(),
+                details: std::collections::HashMap::new(),
+            };
+            return Ok(Response::new(UploadPreKeysResponse {
+                result: Some(upload_pre_keys_response::Result::Error(error)),
+            }));
+        }
+    }
+
+    // KEM pre-keys must be signed by the identity key as well
+    if req.kem_pre_key.is_some() || !req.one_time_kem_pre_keys.is_empty() {
+        let checked = match service.db.get_identity_key(&claims.sub).await {
+            Ok(Some(identity_key)) => {
+                check_kem_pre_keys(&identity_key, req.kem_pre_key.as_ref(), &req.one_time_kem_pre_keys)
+            }
+            Ok(None) => Err("Invalid KEM pre-key signature"),
+            Err(e) => {
+                tracing::error!("Database error: {}", e);
+                let error = ErrorResponse {
+                    code: error_response::ErrorCode::InternalError as i32,
+                    message: "Internal server error".to_string(),
+                    details: std::collections::HashMap::new(),
+                };
+                return Ok(Response::new(UploadPreKeysResponse {
+                    result: Some(upload_pre_keys_response::Result::Error(error)),
+                }));
+            }
+        };
+
+        if let Err(message) = checked {
+            let error = ErrorResponse {
+                code: error_response::ErrorCode::InvalidRequest as i32,
+                message: message.to_string(),
+                details: std::collections::HashMap::new(),
+            };
+            return Ok(Response::new(UploadPreKeysResponse {
+                result: Some(upload_pre_keys_response::Result::Error(error)),
+            }));
+        }
+    }
+
+    if let Some(kem_pre_key) = req.kem_pre_key.clone() {
+        if let Err(e) = service.db.set_kem_pre_key(&claims.sub, &claims.device_id, &kem_pre_key.into()).await {
+            tracing::error!("Failed to store KEM pre-key: {}", e);
+            let error = ErrorResponse {
+                code: error_response::ErrorCode::InternalError as i32,
+                message: "Failed to upload keys".to_string(),
+                details: std::collections::HashMap::new(),
+            };
+            return Ok(Response::new(UploadPreKeysResponse {
+                result: Some(upload_pre_keys_response::Result::Error(error)),
+            }));
+        }
+    }
 
+    // Add one-time pre-keys (identity and signed pre-key are left untouched)
     let keys_count = req.one_time_pre_keys.len() as u32;
+    let one_time_kem_pre_keys: Vec<crate::db::KemPreKey> =
+        req.one_time_kem_pre_keys.into_iter().map(Into::into).collect();
 
-    match service.db.store_key_bundle(&claims.sub, &claims.device_id, &key_bundle).await {
-        Ok(_) => {
+    let stored = match service
+        .db
+        .add_one_time_pre_keys(&claims.sub, &claims.device_id, &req.one_time_pre_keys, &req.one_time_pre_key_ids)
+        .await
+    {
+        Ok(()) => service.db.add_one_time_kem_pre_keys(&claims.sub, &claims.device_id, &one_time_kem_pre_keys).await,
+        Err(e) => Err(e),
+    };
+
+    let result = match stored {
+        Ok(()) => match service.db.count_one_time_pre_keys(&claims.sub, &claims.device_id).await {
+            Ok(total_keys_available) => service
+                .db
+                .count_one_time_kem_pre_keys(&claims.sub, &claims.device_id)
+                .await
+                .map(|total_kem_keys_available| (total_keys_available, total_kem_keys_available)),
+            Err(e) => Err(e),
+        },
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok((total_keys_available, total_kem_keys_available)) => {
             let success = UploadPreKeysSuccess {
                 keys_uploaded: keys_count,
-                total_keys_available: keys_count, // TODO: Query actual total from DB
+                total_keys_available,
+                one_time_pre_key_ids: req.one_time_pre_key_ids,
+                total_kem_keys_available,
             };
             Ok(Response::new(UploadPreKeysResponse {
                 result: Some(upload_pre_keys_response::Result::Success(success)),
             }))
         }
+        Err(e) if e.is::<crate::db::PreKeyIdInUse>() => {
+            let error = ErrorResponse {
+                code: error_response::ErrorCode::Conflict as i32,
+                message: e.to_string(),
+                details: std::collections::HashMap::new(),
+            };
+            Ok(Response::new(UploadPreKeysResponse {
+                result: Some(upload_pre_keys_response::Result::Error(error)),
+            }))
+        }
         Err(e) => {
-            tracing::error!("Failed to store key bundle: {}", e);
+            tracing::error!("Failed to store one-time pre-keys: {}", e);
             let error = ErrorResponse {
                 code: error_response::ErrorCode::InternalError as i32,
                 message: "Failed to upload keys".to_string(),
@@ -117,3 +444,202 @@ pub async fn upload(
         }
     }
 }
+
+/// Replace the caller's signed pre-key
+///
+/// The new key must be signed by the caller's identity key and carry a new
+/// key ID. The replaced key stays valid for the grace period.
+pub async fn rotate_signed_pre_key(
+    service: &AuthServiceImpl,
+    request: Request<RotateSignedPreKeyRequest>,
+) -> Result<Response<RotateSignedPreKeyResponse>, Status> {
+    let req = request.into_inner();
+
+    let respond_error = |code: error_response::ErrorCode, message: &str|
+        -> Result<Response<RotateSignedPreKeyResponse>, Status> {
+        Ok(Response::new(RotateSignedPreKeyResponse {
+            result: Some(rotate_signed_pre_key_response::Result::Error(error(code, message))),
+        }))
+    };
+
+    // Validate access token
+    let claims = match crate::jwt::validate_token(&req.access_token) {
+        Ok(c) => c,
+        Err(_) => return respond_error(error_response::ErrorCode::Unauthorized, "Invalid or expired token"),
+    };
+
+    if req.signed_pre_key.len() != 32 || req.signed_pre_key_id == 0 {
+        return respond_error(error_response::ErrorCode::InvalidRequest, "Signed pre-key and key ID required");
+    }
+
+    let identity_key = match service.db.get_identity_key(&claims.sub).await {
+        Ok(Some(key)) => key,
+        Ok(None) => return respond_error(error_response::ErrorCode::NotFound, "Identity key not found"),
+        Err(e) => {
+            tracing::error!("Database error: {}", e);
+            return respond_error(error_response::ErrorCode::InternalError, "Internal server error");
+        }
+    };
+
+    if IdentityKeyPair::verify(&identity_key, &req.signed_pre_key, &req.signed_pre_key_signature).is_err() {
+        return respond_error(error_response::ErrorCode::InvalidRequest, "Invalid signed pre-key signature");
+    }
+
+    let current = match service.db.get_signed_pre_key_meta(&claims.sub, &claims.device_id).await {
+        Ok(Some(meta)) => meta,
+        Ok(None) => return respond_error(error_response::ErrorCode::NotFound, "Key bundle not found"),
+        Err(e) => {
+            tracing::error!("Database error: {}", e);
+            return respond_error(error_response::ErrorCode::InternalError, "Internal server error");
+        }
+    };
+
+    // Initiators name the key by ID, so IDs must not be reused across rotations
+    if req.signed_pre_key_id == current.key_id {
+        return respond_error(error_response::ErrorCode::Conflict, "Signed pre-key ID already in use");
+    }
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+
+    let meta = crate::db::SignedPreKeyMeta {
+        key_id: req.signed_pre_key_id,
+        created_at: now,
+    };
+    let valid_until = now + SIGNED_PRE_KEY_GRACE_PERIOD_SECS;
+
+    let previous = match service
+        .db
+        .rotate_signed_pre_key(
+            &claims.sub,
+            &claims.device_id,
+            &meta,
+            req.signed_pre_key,
+            req.signed_pre_key_signature,
+            valid_until,
+        )
+        .await
+    {
+        Ok(previous) => previous,
+        Err(e) => {
+            tracing::error!("Failed to rotate signed pre-key: {}", e);
+            return respond_error(error_response::ErrorCode::InternalError, "Failed to rotate signed pre-key");
+        }
+    };
+
+    tracing::info!(
+        "Rotated signed pre-key of device {} (user {}) to key {}",
+        claims.device_id,
+        claims.sub,
+        meta.key_id
+    );
+
+    Ok(Response::new(RotateSignedPreKeyResponse {
+        result: Some(rotate_signed_pre_key_response::Result::Success(RotateSignedPreKeySuccess {
+            signed_pre_key_id: meta.key_id,
+            previous_signed_pre_key_id: previous.as_ref().map(|p| p.key_id).unwrap_or_default(),
+            previous_valid_until: previous.map(|p| Timestamp {
+                seconds: p.valid_until,
+                nanos: 0,
+            }),
+        })),
+    }))
+}
+
+/// Report the caller's remaining one-time pre-keys and signed pre-key age
+pub async fn status(
+    service: &AuthServiceImpl,
+    request: Request<GetPreKeyStatusRequest>,
+) -> Result<Response<GetPreKeyStatusResponse>, Status> {
+    let req = request.into_inner();
+
+    let respond_error = |code: error_response::ErrorCode, message: &str|
+        -> Result<Response<GetPreKeyStatusResponse>, Status> {
+        Ok(Response::new(GetPreKeyStatusResponse {
+            result: Some(get_pre_key_status_response::Result::Error(error(code, message))),
+        }))
+    };
+
+    // Validate access token
+    let claims = match crate::jwt::validate_token(&req.access_token) {
+        Ok(c) => c,
+        Err(_) => return respond_error(error_response::ErrorCode::Unauthorized, "Invalid or expired token"),
+    };
+
+    let signed_pre_key = match service.db.get_signed_pre_key_meta(&claims.sub, &claims.device_id).await {
+        Ok(Some(meta)) => meta,
+        Ok(None) => return respond_error(error_response::ErrorCode::NotFound, "Key bundle not found"),
+        Err(e) => {
+            tracing::error!("Database error: {}", e);
+            return respond_error(error_response::ErrorCode::InternalError, "Internal server error");
+        }
+    };
+
+    let available = match service.db.count_one_time_pre_keys(&claims.sub, &claims.device_id).await {
+        Ok(count) => count,
+        Err(e) => {
+            tracing::error!("Database error: {}", e);
+            return respond_error(error_response::ErrorCode::InternalError, "Internal server error");
+        }
+    };
+
+    let last_resort_pre_key_present = match service.db.get_last_resort_pre_key(&claims.sub, &claims.device_id).await {
+        Ok(key) => key.is_some(),
+        Err(e) => {
+            tracing::error!("Database error: {}", e);
+            return respond_error(error_response::ErrorCode::InternalError, "Internal server error");
+        }
+    };
+
+    let kem_pre_key_present = match service.db.get_kem_pre_key(&claims.sub, &claims.device_id).await {
+        Ok(key) => key.is_some(),
+        Err(e) => {
+            tracing::error!("Database error: {}", e);
+            return respond_error(error_response::ErrorCode::InternalError, "Internal server error");
+        }
+    };
+
+    let kem_available = match service.db.count_one_time_kem_pre_keys(&claims.sub, &claims.device_id).await {
+        Ok(count) => count,
+        Err(e) => {
+            tracing::error!("Database error: {}", e);
+            return respond_error(error_response::ErrorCode::InternalError, "Internal server error");
+        }
+    };
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+
+    // Only report a previous key that is still in its grace period
+    let previous = match service.db.get_previous_signed_pre_key(&claims.sub, &claims.device_id).await {
+        Ok(previous) => previous.filter(|p| p.valid_until > now),
+        Err(e) => {
+            tracing::error!("Database error: {}", e);
+            return respond_error(error_response::ErrorCode::InternalError, "Internal server error");
+        }
+    };
+
+    Ok(Response::new(GetPreKeyStatusResponse {
+        result: Some(get_pre_key_status_response::Result::Success(GetPreKeyStatusSuccess {
+            one_time_pre_keys_available: available,
+            // PQXDH devices replenish their one-time KEM pre-keys too
+            replenish_needed: available < ONE_TIME_PRE_KEY_LOW_WATERMARK
+                || (kem_pre_key_present && kem_available < ONE_TIME_PRE_KEY_LOW_WATERMARK),
+            low_watermark: ONE_TIME_PRE_KEY_LOW_WATERMARK,
+            signed_pre_key_id: signed_pre_key.key_id,
+            signed_pre_key_rotation_due: now - signed_pre_key.created_at >= SIGNED_PRE_KEY_ROTATION_INTERVAL_SECS,
+            previous_signed_pre_key_id: previous.as_ref().map(|p| p.key_id).unwrap_or_default(),
+            previous_signed_pre_key_valid_until: previous.map(|p| Timestamp {
+                seconds: p.valid_until,
+                nanos: 0,
+            }),
+            last_resort_pre_key_present,
+            one_time_kem_pre_keys_available: kem_available,
+            kem_pre_key_present,
+        })),
+    }))
+}
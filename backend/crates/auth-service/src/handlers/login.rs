@@ -1,12 +1,15 @@
 /// User login handler
 ///
 /// Flow:
-/// 1. Validate credentials (username + password)
-/// 2. Verify password hash
-/// 3. Check/create device entry
-/// 4. Generate JWT tokens
-/// 5. Create session
-/// 6. Return tokens + device list
+/// 1. Reject throttled clients and accounts (`RATE_LIMITED`)
+/// 2. Validate credentials (username + password), counting every attempt as a
+///    failure until the password turns out to be right
+/// 3. If the account has a second factor, return an MFA challenge
+///    (the login continues in `VerifySecondFactor`)
+/// 4. Check/create device entry
+/// 5. Generate JWT tokens
+/// 6. Create session
+/// 7. Return tokens + device list
 
 use crate::{AuthServiceImpl, proto::auth::*, proto::common::*};
 use tonic::{Request, Response, Status};
@@ -14,19 +17,38 @@ use argon2::{
     password_hash::{PasswordHash, PasswordVerifier},
     Argon2,
 };
-use crate::db::{Device, Session};
-use crate::jwt;
+use crate::db::{Device, PendingLogin, RefreshTokenFamily, Session};
+use crate::{jwt, login_throttle, mfa};
+use prost::Message;
 
 pub async fn handle(
     service: &AuthServiceImpl,
     request: Request<LoginRequest>,
 ) -> Result<Response<LoginResponse>, Status> {
+    let client_ip = login_throttle::client_ip(&request, &service.trusted_proxies);
     let req = request.into_inner();
+
+    if let Some(Err(message)) = req.key_bundle.as_ref().map(super::key_bundle::check_key_bundle) {
+        let error = ErrorResponse {
+            code: error_response::ErrorCode::InvalidRequest as i32,
+            message: message.to_string(),
+            details: std::collections::HashMap::new(),
+        };
+        return Ok(Response::new(LoginResponse {
+            result: Some(login_response::Result::Error(error)),
+        }));
+    }
+
+    // Throttle by client address before touching any account
+    if let Some(response) = throttled(service, client_ip.as_deref(), None).await {
+        return response;
+    }
     
     // Get user by username
     let user = match service.db.get_user_by_username(&req.username).await {
         Ok(Some(u)) => u,
         Ok(None) => {
+            login_throttle::login_failed(service, client_ip.as_deref(), None).await;
             let error = ErrorResponse {
                 code: error_response::ErrorCode::NotFound as i32,
                 message: "Invalid username or password".to_string(),
@@ -38,6 +60,7 @@ pub async fn handle(
         }
         Err(e) => {
             tracing::error!("Database error: {}", e);
+            login_throttle::release(service, client_ip.as_deref(), None).await;
             let error = ErrorResponse {
                 code: error_response::ErrorCode::InternalError as i32,
                 message: "Internal server error".to_string(),
@@ -49,8 +72,15 @@ pub async fn handle(
         }
     };
     
+    // A locked account doesn't get its password checked at all
+    if let Some(response) = throttled(service, None, Some(&user.user_id)).await {
+        login_throttle::release(service, client_ip.as_deref(), None).await;
+        return response;
+    }
+    
     // Verify password
     if !verify_password(&req.password, &user.password_hash) {
+        login_throttle::login_failed(service, client_ip.as_deref(), Some(&user.user_id)).await;
         let error = ErrorResponse {
             code: error_response::ErrorCode::Unauthorized as i32,
             message: "Invalid username or password".to_string(),
@@ -60,7 +90,112 @@ pub async fn handle(
             result: Some(login_response::Result::Error(error)),
         }));
     }
-    
+    login_throttle::release(service, client_ip.as_deref(), Some(&user.user_id)).await;
+
+    // Accounts with a second factor finish logging in with VerifySecondFactor
+    match service.db.get_mfa(&user.user_id).await {
+        Ok(Some(settings)) if settings.is_enabled() => return challenge(service, &user, req).await,
+        Ok(_) => {}
+        Err(e) => {
+            tracing::error!("Failed to load second-factor settings: {}", e);
+            let error = ErrorResponse {
+                code: error_response::ErrorCode::InternalError as i32,
+                message: "Internal server error".to_string(),
+                details: std::collections::HashMap::new(),
+            };
+            return Ok(Response::new(LoginResponse {
+                result: Some(login_response::Result::Error(error)),
+            }));
+        }
+    }
+
+    let result = match complete(service, &user, req).await {
+        Ok(success) => {
+            login_throttle::login_succeeded(service, &user.user_id).await;
+            login_response::Result::Success(success)
+        }
+        Err(error) => login_response::Result::Error(error),
+    };
+
+    Ok(Response::new(LoginResponse {
+        result: Some(result),
+    }))
+}
+
+/// Reserve a login attempt, or the error response if the client or account has
+/// to wait before logging in
+async fn throttled(
+    service: &AuthServiceImpl,
+    client_ip: Option<&str>,
+    user_id: Option<&str>,
+) -> Option<Result<Response<LoginResponse>, Status>> {
+    let error = match login_throttle::reserve(service, client_ip, user_id).await {
+        Ok(Some(retry_after)) => login_throttle::rate_limited(retry_after),
+        Ok(None) => return None,
+        Err(e) => {
+            tracing::error!("Failed to reserve login attempt: {}", e);
+            ErrorResponse {
+                code: error_response::ErrorCode::InternalError as i32,
+                message: "Internal server error".to_string(),
+                details: std::collections::HashMap::new(),
+            }
+        }
+    };
+
+    Some(Ok(Response::new(LoginResponse {
+        result: Some(login_response::Result::Error(error)),
+    })))
+}
+
+/// Hold the login until its second factor is verified
+async fn challenge(
+    service: &AuthServiceImpl,
+    user: &crate::db::UserProfile,
+    mut req: LoginRequest,
+) -> Result<Response<LoginResponse>, Status> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+
+    // The device and its keys are only stored once the login completes
+    req.password.clear();
+    let challenge_token = mfa::generate_challenge_token();
+    let pending = PendingLogin {
+        user_id: user.user_id.clone(),
+        request: req.encode_to_vec(),
+        failed_attempts: 0,
+        expires_at: now + mfa::CHALLENGE_TTL_SECS,
+    };
+
+    if let Err(e) = service.db.store_pending_login(&mfa::challenge_hash(&challenge_token), &pending).await {
+        tracing::error!("Failed to store pending login: {}", e);
+        let error = ErrorResponse {
+            code: error_response::ErrorCode::InternalError as i32,
+            message: "Internal server error".to_string(),
+            details: std::collections::HashMap::new(),
+        };
+        return Ok(Response::new(LoginResponse {
+            result: Some(login_response::Result::Error(error)),
+        }));
+    }
+
+    tracing::info!("Login of user {} awaits its second factor", user.user_id);
+
+    Ok(Response::new(LoginResponse {
+        result: Some(login_response::Result::MfaRequired(MfaChallenge {
+            challenge_token,
+            expires_in: mfa::CHALLENGE_TTL_SECS as u32,
+        })),
+    }))
+}
+
+/// Register the device and issue its tokens once every factor has been verified
+pub async fn complete(
+    service: &AuthServiceImpl,
+    user: &crate::db::UserProfile,
+    req: LoginRequest,
+) -> Result<LoginSuccess, ErrorResponse> {
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap()
@@ -96,7 +231,24 @@ pub async fn handle(
                 identity_key: key_bundle.identity_key,
                 signed_pre_key: key_bundle.signed_pre_key,
                 signed_pre_key_signature: key_bundle.signed_pre_key_signature,
+                signed_pre_key_id: key_bundle.signed_pre_key_id,
                 one_time_pre_keys: key_bundle.one_time_pre_keys,
+                one_time_pre_key_ids: key_bundle.one_time_pre_key_ids,
+                last_resort_pre_key: if key_bundle.last_resort_pre_key.is_empty() {
+                    None
+                } else {
+                    Some(crate::db::LastResortPreKey {
+                        key_id: key_bundle.last_resort_pre_key_id,
+                        public_key: key_bundle.last_resort_pre_key,
+                        signature: key_bundle.last_resort_pre_key_signature,
+                    })
+                },
+                kem_pre_key: key_bundle.kem_pre_key.map(crate::db::KemPreKey::from),
+                one_time_kem_pre_keys: key_bundle
+                    .one_time_kem_pre_keys
+                    .into_iter()
+                    .map(crate::db::KemPreKey::from)
+                    .collect(),
                 created_at: now,
             };
             
@@ -107,7 +259,10 @@ pub async fn handle(
     }
     
     // Generate JWT tokens
-    let access_token = match jwt::generate_access_token(&user.user_id, &device_id, &user.username, &service.jwt_secret) {
+    // Every token of this login carries the session ID, so the session can be revoked
+    let session_id = uuid::Uuid::new_v4().to_string();
+
+    let access_token = match jwt::generate_access_token(&user.user_id, &device_id, &user.username, &session_id, &service.jwt_keys) {
         Ok(token) => token,
         Err(e) => {
             tracing::error!("Failed to generate access token: {}", e);
@@ -116,13 +271,11 @@ pub async fn handle(
                 message: "Failed to generate tokens".to_string(),
                 details: std::collections::HashMap::new(),
             };
-            return Ok(Response::new(LoginResponse {
-                result: Some(login_response::Result::Error(error)),
-            }));
+            return Err(error);
         }
     };
     
-    let refresh_token = match jwt::generate_refresh_token(&user.user_id, &device_id, &user.username, &service.jwt_secret) {
+    let refresh_token = match jwt::generate_refresh_token(&user.user_id, &device_id, &user.username, &session_id, &service.jwt_keys) {
         Ok(token) => token,
         Err(e) => {
             tracing::error!("Failed to generate refresh token: {}", e);
@@ -131,14 +284,13 @@ pub async fn handle(
                 message: "Failed to generate tokens".to_string(),
                 details: std::collections::HashMap::new(),
             };
-            return Ok(Response::new(LoginResponse {
-                result: Some(login_response::Result::Error(error)),
-            }));
+            return Err(error);
         }
     };
     
     // Create session
     let session = Session {
+        session_id,
         session_token: refresh_token.clone(),
         user_id: user.user_id.clone(),
         device_id: device_id.clone(),
@@ -149,24 +301,51 @@ pub async fn handle(
     if let Err(e) = service.db.create_session(&session).await {
         tracing::error!("Failed to create session: {}", e);
     }
+
+    // Only the latest refresh token of the session may be used
+    if let Err(e) = service.db.create_refresh_family(&RefreshTokenFamily::for_session(&session, now)).await {
+        tracing::error!("Failed to create refresh token family: {}", e);
+    }
     
-    // TODO: Get list of user's devices
-    let devices = vec![
-        DeviceInfo {
-            device_id: device_id.clone(),
-            device_name: req.device_name.clone(),
-            device_type: req.device_type.clone(),
-            created_at: Some(Timestamp {
-                seconds: now,
-                nanos: 0,
-            }),
-            last_seen: Some(Timestamp {
-                seconds: now,
-                nanos: 0,
-            }),
-            is_current: true,
+    // List all of the user's devices so the client knows where to fan out
+    let devices = match service.db.list_devices(&user.user_id).await {
+        Ok(devices) => devices
+            .into_iter()
+            .map(|device| DeviceInfo {
+                is_current: device.device_id == device_id,
+                device_id: device.device_id,
+                device_name: device.device_name,
+                device_type: device.device_type,
+                created_at: Some(Timestamp {
+                    seconds: device.created_at,
+                    nanos: 0,
+                }),
+                last_seen: Some(Timestamp {
+                    seconds: device.last_seen,
+                    nanos: 0,
+                }),
+            })
+            .collect(),
+        Err(e) => {
+            tracing::error!("Failed to list devices: {}", e);
+            vec![
+                DeviceInfo {
+                    device_id: device_id.clone(),
+                    device_name: req.device_name.clone(),
+                    device_type: req.device_type.clone(),
+                    created_at: Some(Timestamp {
+                        seconds: now,
+                        nanos: 0,
+                    }),
+                    last_seen: Some(Timestamp {
+                        seconds: now,
+                        nanos: 0,
+                    }),
+                    is_current: true,
+                }
+            ]
         }
-    ];
+    };
     
     // User profile
     let profile = Some(UserProfile {
@@ -184,8 +363,8 @@ pub async fn handle(
     });
     
     // Return success response
-    let success = LoginSuccess {
-        user_id: user.user_id,
+    Ok(LoginSuccess {
+        user_id: user.user_id.clone(),
         device_id,
         access_token,
         access_token_expires_in: 15 * 60, // 15 minutes in seconds
@@ -193,15 +372,11 @@ pub async fn handle(
         refresh_token_expires_in: 30 * 24 * 60 * 60, // 30 days in seconds
         profile,
         devices,
-    };
-    
-    Ok(Response::new(LoginResponse {
-        result: Some(login_response::Result::Success(success)),
-    }))
+    })
 }
 
 /// Verify password against hash
-fn verify_password(password: &str, hash: &str) -> bool {
+pub fn verify_password(password: &str, hash: &str) -> bool {
     let parsed_hash = match PasswordHash::new(hash) {
         Ok(h) => h,
         Err(_) => return false,
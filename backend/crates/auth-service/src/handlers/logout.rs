@@ -1,6 +1,10 @@
 /// Logout handler - invalidates session(s)
+///
+/// Deleting a session stops its refresh token; revoking its ID makes every
+/// service reject the access tokens already issued for it.
 
 use crate::{AuthServiceImpl, proto::auth::*, proto::common::*};
+use std::collections::BTreeSet;
 use tonic::{Request, Response, Status};
 
 pub async fn handle(
@@ -8,9 +12,9 @@ pub async fn handle(
     request: Request<LogoutRequest>,
 ) -> Result<Response<LogoutResponse>, Status> {
     let req = request.into_inner();
-    
+
     // Validate access token
-    let claims = match crate::jwt::validate_token(&req.access_token, &service.jwt_secret) {
+    let claims = match crate::jwt::validate_token(&req.access_token) {
         Ok(c) => c,
         Err(_) => {
             let error = ErrorResponse {
@@ -23,22 +27,84 @@ pub async fn handle(
             }));
         }
     };
-    
+
+    let sessions = match service.db.list_sessions(&claims.sub).await {
+        Ok(sessions) => sessions,
+        Err(e) => {
+            tracing::error!("Failed to list sessions: {}", e);
+            let error = ErrorResponse {
+                code: error_response::ErrorCode::InternalError as i32,
+                message: "Failed to list sessions".to_string(),
+                details: std::collections::HashMap::new(),
+            };
+            return Ok(Response::new(LogoutResponse {
+                result: Some(logout_response::Result::Error(error)),
+            }));
+        }
+    };
+
     // Logout all devices or just current?
-    if req.all_devices {
-        // TODO: Implement logout from all devices
-        // Need to scan all sessions for user_id
-        tracing::warn!("Logout from all devices not yet implemented");
+    let targets: Vec<_> = if req.all_devices {
+        sessions
     } else {
-        // Delete current session (identified by device_id)
-        // We don't have session token directly, so this is a simplified implementation
-        // In production, you'd track session_token -> access_token mapping
-        tracing::info!("Logging out device {} for user {}", claims.device_id, claims.sub);
+        // Tokens issued before session IDs existed can only be matched by device
+        sessions
+            .into_iter()
+            .filter(|session| match claims.sid.as_deref() {
+                Some(sid) => session.session_id == sid,
+                None => session.session_id.is_empty() && session.device_id == claims.device_id,
+            })
+            .collect()
+    };
+
+    let mut revoked: BTreeSet<String> = claims.sid.iter().cloned().collect();
+    for session in &targets {
+        if let Err(e) = service.db.delete_session(&session.session_token).await {
+            tracing::error!("Failed to delete session: {}", e);
+            let error = ErrorResponse {
+                code: error_response::ErrorCode::InternalError as i32,
+                message: "Failed to delete session".to_string(),
+                details: std::collections::HashMap::new(),
+            };
+            return Ok(Response::new(LogoutResponse {
+                result: Some(logout_response::Result::Error(error)),
+            }));
+        }
+        if !session.session_id.is_empty() {
+            revoked.insert(session.session_id.clone());
+        }
+    }
+
+    for session_id in &revoked {
+        if let Err(e) = crate::revocation::revoke_session(service, &claims.sub, session_id).await {
+            tracing::error!("Failed to revoke session {}: {}", session_id, e);
+            let error = ErrorResponse {
+                code: error_response::ErrorCode::InternalError as i32,
+                message: "Failed to revoke session".to_string(),
+                details: std::collections::HashMap::new(),
+            };
+            return Ok(Response::new(LogoutResponse {
+                result: Some(logout_response::Result::Error(error)),
+            }));
+        }
     }
-    
+
+    // The current session counts even if its record was already gone
+    let current_listed = targets
+        .iter()
+        .any(|session| Some(session.session_id.as_str()) == claims.sid.as_deref());
+    let sessions_invalidated = targets.len() + usize::from(claims.sid.is_some() && !current_listed);
+
+    tracing::info!(
+        "Logged out user {} from {} session(s) (all_devices: {})",
+        claims.sub,
+        sessions_invalidated,
+        req.all_devices
+    );
+
     Ok(Response::new(LogoutResponse {
         result: Some(logout_response::Result::Success(LogoutSuccess {
-            sessions_invalidated: 1,
+            sessions_invalidated: sessions_invalidated as u32,
         })),
     }))
 }
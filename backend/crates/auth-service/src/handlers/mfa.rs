@@ -0,0 +1,484 @@
+/// Two-factor authentication handlers - second login step, TOTP enrolment and removal
+///
+/// Enrolment and removal require the account password in the request, so a
+/// stolen access token alone can neither add an attacker's authenticator nor
+/// remove the user's.
+
+use crate::{AuthServiceImpl, proto::auth::*, proto::common::*};
+use crate::db::SecurityEventKind;
+use crate::handlers::login::verify_password;
+use crate::{login_throttle, mfa};
+use prost::Message;
+use tonic::{Request, Response, Status};
+
+fn error(code: error_response::ErrorCode, message: &str) -> ErrorResponse {
+    ErrorResponse {
+        code: code as i32,
+        message: message.to_string(),
+        details: std::collections::HashMap::new(),
+    }
+}
+
+fn now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+/// Second factor presented with a request
+enum Factor<'a> {
+    Totp(&'a str),
+    RecoveryCode(&'a str),
+}
+
+impl<'a> Factor<'a> {
+    fn from_request(totp_code: &'a str, recovery_code: &'a str) -> Option<Self> {
+        if !totp_code.is_empty() {
+            Some(Factor::Totp(totp_code))
+        } else if !recovery_code.is_empty() {
+            Some(Factor::RecoveryCode(recovery_code))
+        } else {
+            None
+        }
+    }
+}
+
+/// Check a second factor against the user's settings, consuming it
+///
+/// Returns whether it was accepted and, for recovery codes, how many remain.
+async fn consume_factor(
+    service: &AuthServiceImpl,
+    user_id: &str,
+    factor: &Factor<'_>,
+) -> anyhow::Result<Option<Option<usize>>> {
+    let now = now() as u64;
+
+    service.db.update_mfa(user_id, |settings| {
+        let settings = settings.as_mut()?;
+        let secret = settings.totp_secret.as_ref()?;
+
+        match factor {
+            Factor::Totp(code) => {
+                let step = mfa::verify_totp(secret, code, now, settings.last_totp_step)?;
+                settings.last_totp_step = step;
+                Some(None)
+            }
+            Factor::RecoveryCode(code) => {
+                let index = mfa::find_recovery_code(code, &settings.recovery_code_hashes)?;
+                settings.recovery_code_hashes.remove(index);
+                Some(Some(settings.recovery_code_hashes.len()))
+            }
+        }
+    }).await
+}
+
+/// Complete a login that requires a second factor
+pub async fn verify_second_factor(
+    service: &AuthServiceImpl,
+    request: Request<VerifySecondFactorRequest>,
+) -> Result<Response<VerifySecondFactorResponse>, Status> {
+    let client_ip = login_throttle::client_ip(&request, &service.trusted_proxies);
+    let req = request.into_inner();
+
+    let respond_error = |code: error_response::ErrorCode, message: &str|
+        -> Result<Response<VerifySecondFactorResponse>, Status> {
+        Ok(Response::new(VerifySecondFactorResponse {
+            result: Some(verify_second_factor_response::Result::Error(error(code, message))),
+        }))
+    };
+
+    let factor = match Factor::from_request(&req.totp_code, &req.recovery_code) {
+        Some(factor) => factor,
+        None => return respond_error(error_response::ErrorCode::InvalidRequest, "TOTP or recovery code required"),
+    };
+
+    let challenge_hash = mfa::challenge_hash(&req.challenge_token);
+    let pending = match service.db.get_pending_login(&challenge_hash, now()).await {
+        Ok(Some(pending)) => pending,
+        Ok(None) => return respond_error(error_response::ErrorCode::Unauthorized, "Invalid or expired challenge"),
+        Err(e) => {
+            tracing::error!("Database error: {}", e);
+            return respond_error(error_response::ErrorCode::InternalError, "Internal server error");
+        }
+    };
+
+    // Wrong codes count against the account like wrong passwords
+    match login_throttle::reserve(service, client_ip.as_deref(), Some(&pending.user_id)).await {
+        Ok(Some(retry_after)) => {
+            return Ok(Response::new(VerifySecondFactorResponse {
+                result: Some(verify_second_factor_response::Result::Error(login_throttle::rate_limited(retry_after))),
+            }));
+        }
+        Ok(None) => {}
+        Err(e) => {
+            tracing::error!("Failed to reserve login attempt: {}", e);
+            return respond_error(error_response::ErrorCode::InternalError, "Internal server error");
+        }
+    }
+
+    // Count the attempt before checking the code, so concurrent guesses can't
+    // exceed the limit; a correct code redeems the challenge below
+    let now = now();
+    let reserved = service.db.update_pending_login(&challenge_hash, |pending| match pending {
+        Some(login) if login.expires_at > now && login.failed_attempts < mfa::MAX_CHALLENGE_ATTEMPTS => {
+            login.failed_attempts += 1;
+            Some(login.clone())
+        }
+        _ => {
+            *pending = None;
+            None
+        }
+    }).await;
+    let pending = match reserved {
+        Ok(Some(pending)) => pending,
+        Ok(None) => {
+            login_throttle::release(service, client_ip.as_deref(), Some(&pending.user_id)).await;
+            return respond_error(error_response::ErrorCode::Unauthorized, "Invalid or expired challenge");
+        }
+        Err(e) => {
+            tracing::error!("Failed to update pending login: {}", e);
+            login_throttle::release(service, client_ip.as_deref(), Some(&pending.user_id)).await;
+            return respond_error(error_response::ErrorCode::InternalError, "Internal server error");
+        }
+    };
+
+    let remaining_recovery_codes = match consume_factor(service, &pending.user_id, &factor).await {
+        Ok(Some(remaining)) => {
+            login_throttle::release(service, client_ip.as_deref(), Some(&pending.user_id)).await;
+            remaining
+        }
+        Ok(None) => {
+            // A challenge survives a few typos, then the password has to be entered again
+            let dropped = service.db.update_pending_login(&challenge_hash, |pending| {
+                if pending.as_ref().is_some_and(|login| login.failed_attempts >= mfa::MAX_CHALLENGE_ATTEMPTS) {
+                    *pending = None;
+                }
+            }).await;
+            if let Err(e) = dropped {
+                tracing::error!("Failed to update pending login: {}", e);
+            }
+            tracing::warn!("Wrong second factor for user {}", pending.user_id);
+            login_throttle::login_failed(service, client_ip.as_deref(), Some(&pending.user_id)).await;
+            return respond_error(error_response::ErrorCode::Unauthorized, "Invalid verification code");
+        }
+        Err(e) => {
+            tracing::error!("Failed to verify second factor: {}", e);
+            login_throttle::release(service, client_ip.as_deref(), Some(&pending.user_id)).await;
+            return respond_error(error_response::ErrorCode::InternalError, "Internal server error");
+        }
+    };
+
+    // Each challenge completes one login; a concurrent request may have redeemed it first
+    match service.db.update_pending_login(&challenge_hash, |pending| pending.take().is_some()).await {
+        Ok(true) => {}
+        Ok(false) => return respond_error(error_response::ErrorCode::Unauthorized, "Invalid or expired challenge"),
+        Err(e) => {
+            tracing::error!("Failed to redeem pending login: {}", e);
+            return respond_error(error_response::ErrorCode::InternalError, "Internal server error");
+        }
+    }
+
+    let login_request = match LoginRequest::decode(pending.request.as_slice()) {
+        Ok(login_request) => login_request,
+        Err(e) => {
+            tracing::error!("Malformed pending login: {}", e);
+            return respond_error(error_response::ErrorCode::InternalError, "Internal server error");
+        }
+    };
+
+    let user = match service.db.get_user_by_id(&pending.user_id).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return respond_error(error_response::ErrorCode::Unauthorized, "Invalid or expired challenge"),
+        Err(e) => {
+            tracing::error!("Database error: {}", e);
+            return respond_error(error_response::ErrorCode::InternalError, "Internal server error");
+        }
+    };
+
+    let result = match super::login::complete(service, &user, login_request).await {
+        Ok(success) => success,
+        Err(error) => {
+            return Ok(Response::new(VerifySecondFactorResponse {
+                result: Some(verify_second_factor_response::Result::Error(error)),
+            }));
+        }
+    };
+
+    login_throttle::login_succeeded(service, &user.user_id).await;
+
+    if let Some(remaining) = remaining_recovery_codes {
+        let description = format!("A recovery code was used to sign in. {} recovery codes remain.", remaining);
+        if let Err(e) = crate::security_events::record(
+            service,
+            &user.user_id,
+            &result.device_id,
+            SecurityEventKind::RecoveryCodeUsed,
+            description,
+        ).await {
+            tracing::error!("Failed to record recovery code use: {}", e);
+        }
+    }
+
+    Ok(Response::new(VerifySecondFactorResponse {
+        result: Some(verify_second_factor_response::Result::Success(result)),
+    }))
+}
+
+/// Start TOTP enrolment with a new secret
+pub async fn begin_totp_enrollment(
+    service: &AuthServiceImpl,
+    request: Request<BeginTotpEnrollmentRequest>,
+) -> Result<Response<BeginTotpEnrollmentResponse>, Status> {
+    let client_ip = login_throttle::client_ip(&request, &service.trusted_proxies);
+    let req = request.into_inner();
+
+    let respond_error = |code: error_response::ErrorCode, message: &str|
+        -> Result<Response<BeginTotpEnrollmentResponse>, Status> {
+        Ok(Response::new(BeginTotpEnrollmentResponse {
+            result: Some(begin_totp_enrollment_response::Result::Error(error(code, message))),
+        }))
+    };
+
+    // Validate access token
+    let claims = match crate::jwt::validate_token(&req.access_token) {
+        Ok(c) => c,
+        Err(_) => return respond_error(error_response::ErrorCode::Unauthorized, "Invalid or expired token"),
+    };
+
+    let user = match service.db.get_user_by_id(&claims.sub).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return respond_error(error_response::ErrorCode::NotFound, "User not found"),
+        Err(e) => {
+            tracing::error!("Database error: {}", e);
+            return respond_error(error_response::ErrorCode::InternalError, "Internal server error");
+        }
+    };
+
+    // Password guesses here are throttled like logins
+    match login_throttle::reserve(service, client_ip.as_deref(), Some(&user.user_id)).await {
+        Ok(Some(retry_after)) => {
+            return Ok(Response::new(BeginTotpEnrollmentResponse {
+                result: Some(begin_totp_enrollment_response::Result::Error(login_throttle::rate_limited(retry_after))),
+            }));
+        }
+        Ok(None) => {}
+        Err(e) => {
+            tracing::error!("Failed to reserve login attempt: {}", e);
+            return respond_error(error_response::ErrorCode::InternalError, "Internal server error");
+        }
+    }
+
+    if !verify_password(&req.password, &user.password_hash) {
+        login_throttle::login_failed(service, client_ip.as_deref(), Some(&user.user_id)).await;
+        return respond_error(error_response::ErrorCode::Unauthorized, "Invalid password");
+    }
+    login_throttle::release(service, client_ip.as_deref(), Some(&user.user_id)).await;
+
+    let secret = mfa::generate_totp_secret();
+    let started_at = now();
+    let started = service.db.update_mfa(&user.user_id, |settings| {
+        let settings = settings.get_or_insert_with(Default::default);
+        if settings.is_enabled() {
+            return false;
+        }
+        settings.pending_totp_secret = Some(secret.to_vec());
+        settings.pending_since = started_at;
+        true
+    }).await;
+
+    match started {
+        Ok(true) => {}
+        Ok(false) => return respond_error(error_response::ErrorCode::Conflict, "Two-factor authentication is already enabled"),
+        Err(e) => {
+            tracing::error!("Failed to start TOTP enrolment: {}", e);
+            return respond_error(error_response::ErrorCode::InternalError, "Internal server error");
+        }
+    }
+
+    Ok(Response::new(BeginTotpEnrollmentResponse {
+        result: Some(begin_totp_enrollment_response::Result::Success(BeginTotpEnrollmentSuccess {
+            secret: mfa::encode_totp_secret(&secret),
+            provisioning_uri: mfa::provisioning_uri(&user.username, &secret),
+            expires_in: mfa::ENROLLMENT_TTL_SECS as u32,
+        })),
+    }))
+}
+
+/// Confirm TOTP enrolment with a first code and hand out recovery codes
+pub async fn confirm_totp_enrollment(
+    service: &AuthServiceImpl,
+    request: Request<ConfirmTotpEnrollmentRequest>,
+) -> Result<Response<ConfirmTotpEnrollmentResponse>, Status> {
+    let req = request.into_inner();
+
+    let respond_error = |code: error_response::ErrorCode, message: &str|
+        -> Result<Response<ConfirmTotpEnrollmentResponse>, Status> {
+        Ok(Response::new(ConfirmTotpEnrollmentResponse {
+            result: Some(confirm_totp_enrollment_response::Result::Error(error(code, message))),
+        }))
+    };
+
+    // Validate access token
+    let claims = match crate::jwt::validate_token(&req.access_token) {
+        Ok(c) => c,
+        Err(_) => return respond_error(error_response::ErrorCode::Unauthorized, "Invalid or expired token"),
+    };
+
+    // Hashed up front so the transaction stays short
+    let recovery_codes = mfa::generate_recovery_codes();
+    let recovery_code_hashes = match recovery_codes
+        .iter()
+        .map(|code| mfa::hash_recovery_code(code))
+        .collect::<anyhow::Result<Vec<_>>>()
+    {
+        Ok(hashes) => hashes,
+        Err(e) => {
+            tracing::error!("{}", e);
+            return respond_error(error_response::ErrorCode::InternalError, "Internal server error");
+        }
+    };
+
+    let now = now();
+    let confirmed = service.db.update_mfa(&claims.sub, |settings| {
+        let settings = settings.as_mut()?;
+        if settings.pending_since + mfa::ENROLLMENT_TTL_SECS < now {
+            settings.pending_totp_secret = None;
+            return None;
+        }
+        let secret = settings.pending_totp_secret.take()?;
+        match mfa::verify_totp(&secret, &req.totp_code, now as u64, 0) {
+            Some(step) => {
+                settings.totp_secret = Some(secret);
+                settings.last_totp_step = step;
+                settings.recovery_code_hashes = recovery_code_hashes.clone();
+                settings.enabled_at = now;
+                Some(())
+            }
+            None => {
+                settings.pending_totp_secret = Some(secret);
+                None
+            }
+        }
+    }).await;
+
+    match confirmed {
+        Ok(Some(())) => {}
+        Ok(None) => return respond_error(error_response::ErrorCode::Unauthorized, "Invalid verification code or no enrolment in progress"),
+        Err(e) => {
+            tracing::error!("Failed to confirm TOTP enrolment: {}", e);
+            return respond_error(error_response::ErrorCode::InternalError, "Internal server error");
+        }
+    }
+
+    if let Err(e) = crate::security_events::record(
+        service,
+        &claims.sub,
+        &claims.device_id,
+        SecurityEventKind::TwoFactorEnabled,
+        "Two-factor authentication was turned on.".to_string(),
+    ).await {
+        tracing::error!("Failed to record two-factor enrolment: {}", e);
+    }
+
+    Ok(Response::new(ConfirmTotpEnrollmentResponse {
+        result: Some(confirm_totp_enrollment_response::Result::Success(ConfirmTotpEnrollmentSuccess {
+            recovery_codes,
+        })),
+    }))
+}
+
+/// Remove TOTP and the recovery codes
+pub async fn disable_totp(
+    service: &AuthServiceImpl,
+    request: Request<DisableTotpRequest>,
+) -> Result<Response<DisableTotpResponse>, Status> {
+    let client_ip = login_throttle::client_ip(&request, &service.trusted_proxies);
+    let req = request.into_inner();
+
+    let respond_error = |code: error_response::ErrorCode, message: &str|
+        -> Result<Response<DisableTotpResponse>, Status> {
+        Ok(Response::new(DisableTotpResponse {
+            result: Some(disable_totp_response::Result::Error(error(code, message))),
+        }))
+    };
+
+    // Validate access token
+    let claims = match crate::jwt::validate_token(&req.access_token) {
+        Ok(c) => c,
+        Err(_) => return respond_error(error_response::ErrorCode::Unauthorized, "Invalid or expired token"),
+    };
+
+    let factor = match Factor::from_request(&req.totp_code, &req.recovery_code) {
+        Some(factor) => factor,
+        None => return respond_error(error_response::ErrorCode::InvalidRequest, "TOTP or recovery code required"),
+    };
+
+    let user = match service.db.get_user_by_id(&claims.sub).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return respond_error(error_response::ErrorCode::NotFound, "User not found"),
+        Err(e) => {
+            tracing::error!("Database error: {}", e);
+            return respond_error(error_response::ErrorCode::InternalError, "Internal server error");
+        }
+    };
+
+    // Password guesses here are throttled like logins
+    match login_throttle::reserve(service, client_ip.as_deref(), Some(&user.user_id)).await {
+        Ok(Some(retry_after)) => {
+            return Ok(Response::new(DisableTotpResponse {
+                result: Some(disable_totp_response::Result::Error(login_throttle::rate_limited(retry_after))),
+            }));
+        }
+        Ok(None) => {}
+        Err(e) => {
+            tracing::error!("Failed to reserve login attempt: {}", e);
+            return respond_error(error_response::ErrorCode::InternalError, "Internal server error");
+        }
+    }
+
+    if !verify_password(&req.password, &user.password_hash) {
+        login_throttle::login_failed(service, client_ip.as_deref(), Some(&user.user_id)).await;
+        return respond_error(error_response::ErrorCode::Unauthorized, "Invalid password");
+    }
+    login_throttle::release(service, client_ip.as_deref(), Some(&user.user_id)).await;
+
+    let now = now() as u64;
+    let disabled = service.db.update_mfa(&user.user_id, |settings| {
+        let current = settings.as_ref()?;
+        let secret = current.totp_secret.as_ref()?;
+        let accepted = match &factor {
+            Factor::Totp(code) => mfa::verify_totp(secret, code, now, current.last_totp_step).is_some(),
+            Factor::RecoveryCode(code) => mfa::find_recovery_code(code, &current.recovery_code_hashes).is_some(),
+        };
+        if accepted {
+            *settings = None;
+            Some(())
+        } else {
+            None
+        }
+    }).await;
+
+    match disabled {
+        Ok(Some(())) => {}
+        Ok(None) => return respond_error(error_response::ErrorCode::Unauthorized, "Invalid verification code or two-factor authentication not enabled"),
+        Err(e) => {
+            tracing::error!("Failed to disable TOTP: {}", e);
+            return respond_error(error_response::ErrorCode::InternalError, "Internal server error");
+        }
+    }
+
+    if let Err(e) = crate::security_events::record(
+        service,
+        &user.user_id,
+        &claims.device_id,
+        SecurityEventKind::TwoFactorDisabled,
+        "Two-factor authentication was turned off.".to_string(),
+    ).await {
+        tracing::error!("Failed to record two-factor removal: {}", e);
+    }
+
+    Ok(Response::new(DisableTotpResponse {
+        result: Some(disable_totp_response::Result::Success(DisableTotpSuccess {})),
+    }))
+}
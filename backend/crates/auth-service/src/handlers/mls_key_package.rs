@@ -12,6 +12,7 @@ use crate::proto::auth::{
     UploadMlsKeyPackageRequest, UploadMlsKeyPackageResponse, UploadMlsKeyPackageSuccess,
 };
 use crate::proto::common::{ErrorResponse, Timestamp};
+use guardyn_crypto::mls::MlsCiphersuite;
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -24,9 +25,18 @@ use tracing::{error, info};
 /// Stores the serialized MLS KeyPackage in TiKV.
 /// Each user/device can have multiple key packages (for key rotation).
 ///
+/// The key package must be valid; its ciphersuite and the ciphersuites its
+/// leaf node advertises are returned and indexed, so group creators can fetch
+/// a key package for the group's ciphersuite.
+///
+/// Its MLS credential must be issued for the uploading user and signed with
+/// the identity key registered for them. The package is stored for the
+/// device named in the credential.
+///
 /// # Storage Schema
 /// - `/mls/key_packages/<user_id>/<device_id>/<package_id>` → key_package_bytes
 /// - `/mls/key_packages/by_user/<user_id>` → list of package_ids
+/// - `/mls/key_packages/by_suite/<user_id>/<device_id>/<ciphersuite>` → latest package_id
 pub async fn upload_mls_key_package(
     request: Request<UploadMlsKeyPackageRequest>,
     db: Arc<DatabaseClient>,
@@ -35,7 +45,7 @@ pub async fn upload_mls_key_package(
     info!("Uploading MLS key package");
 
     // Validate JWT token
-    let user_id = match verify_jwt(&req.access_token, "your-secret-key") { // TODO: Get from config
+    let user_id = match verify_jwt(&req.access_token) {
         Ok(user_id) => user_id,
         Err(e) => {
             error!("JWT validation failed: {:?}", e);
@@ -51,8 +61,6 @@ pub async fn upload_mls_key_package(
         }
     };
 
-    let device_id = "default"; // TODO: Extract from token claims or request
-
     // Validate key package
     if req.key_package.is_empty() {
         return Ok(Response::new(UploadMlsKeyPackageResponse {
@@ -66,6 +74,68 @@ pub async fn upload_mls_key_package(
         }));
     }
 
+    let (ciphersuite, supported_ciphersuites) = match key_package_ciphersuites(&req.key_package) {
+        Ok(ciphersuites) => ciphersuites,
+        Err(e) => {
+            return Ok(Response::new(UploadMlsKeyPackageResponse {
+                result: Some(crate::proto::auth::upload_mls_key_package_response::Result::Error(
+                    ErrorResponse {
+                        code: crate::proto::common::error_response::ErrorCode::InvalidRequest as i32,
+                        message: "Invalid key package".to_string(),
+                        details: { let mut map = HashMap::new(); map.insert("error".to_string(), e.to_string()); map },
+                    },
+                )),
+            }));
+        }
+    };
+
+    let identity = match guardyn_crypto::mls::key_package_identity(&req.key_package) {
+        Ok(identity) => identity,
+        Err(e) => {
+            return Ok(Response::new(UploadMlsKeyPackageResponse {
+                result: Some(crate::proto::auth::upload_mls_key_package_response::Result::Error(
+                    ErrorResponse {
+                        code: crate::proto::common::error_response::ErrorCode::InvalidRequest as i32,
+                        message: "Invalid MLS credential".to_string(),
+                        details: { let mut map = HashMap::new(); map.insert("error".to_string(), e.to_string()); map },
+                    },
+                )),
+            }));
+        }
+    };
+
+    // The credential must name the uploading user and carry their identity key
+    let registered_identity_key = match db.get_identity_key(&user_id).await {
+        Ok(identity_key) => identity_key,
+        Err(e) => {
+            error!("Failed to load identity key: {:?}", e);
+            return Ok(Response::new(UploadMlsKeyPackageResponse {
+                result: Some(crate::proto::auth::upload_mls_key_package_response::Result::Error(
+                    ErrorResponse {
+                        code: crate::proto::common::error_response::ErrorCode::InternalError as i32,
+                        message: "Failed to load identity key".to_string(),
+                        details: { let mut map = HashMap::new(); map.insert("error".to_string(), e.to_string()); map },
+                    },
+                )),
+            }));
+        }
+    };
+    if identity.user_id != user_id
+        || registered_identity_key.as_deref() != Some(identity.identity_key.as_slice())
+    {
+        error!("MLS credential of {} rejected for user {}", identity.label(), user_id);
+        return Ok(Response::new(UploadMlsKeyPackageResponse {
+            result: Some(crate::proto::auth::upload_mls_key_package_response::Result::Error(
+                ErrorResponse {
+                    code: crate::proto::common::error_response::ErrorCode::Unauthorized as i32,
+                    message: "MLS credential is not bound to this account".to_string(),
+                    details: HashMap::new(),
+                },
+            )),
+        }));
+    }
+    let device_id = identity.device_id.as_str();
+
     // Generate package ID (hash of key package for uniqueness)
     let mut hasher = Sha256::new();
     hasher.update(&req.key_package);
@@ -81,6 +151,10 @@ pub async fn upload_mls_key_package(
             let list_key = format!("/mls/key_packages/by_user/{}/{}", user_id, device_id);
             let _ = db.put(list_key.as_bytes(), package_id.as_bytes().to_vec()).await;
 
+            // Latest package per ciphersuite (for groups using another suite)
+            let suite_key = format!("/mls/key_packages/by_suite/{}/{}/{}", user_id, device_id, ciphersuite);
+            let _ = db.put(suite_key.as_bytes(), package_id.as_bytes().to_vec()).await;
+
             let now = SystemTime::now()
                 .duration_since(SystemTime::UNIX_EPOCH)
                 .unwrap()
@@ -94,6 +168,8 @@ pub async fn upload_mls_key_package(
                             seconds: now as i64,
                             nanos: 0,
                         }),
+                        ciphersuite,
+                        supported_ciphersuites,
                     },
                 )),
             }))
@@ -118,6 +194,7 @@ pub async fn upload_mls_key_package(
 /// Fetches a key package for adding a user to a group.
 /// If device_id is specified, fetches for that device.
 /// Otherwise, fetches for any available device.
+/// If ciphersuite is non-zero, fetches the latest package built for it.
 ///
 /// # Returns
 /// - Key package bytes (serialized MLS KeyPackage)
@@ -161,7 +238,11 @@ pub async fn get_mls_key_package(
     };
 
     // Get package list for user/device
-    let list_key = format!("/mls/key_packages/by_user/{}/{}", req.user_id, device_id);
+    let list_key = if req.ciphersuite == 0 {
+        format!("/mls/key_packages/by_user/{}/{}", req.user_id, device_id)
+    } else {
+        format!("/mls/key_packages/by_suite/{}/{}/{}", req.user_id, device_id, req.ciphersuite)
+    };
     match db.get(list_key.as_bytes()).await {
         Ok(Some(package_id_bytes)) => {
             let package_id = String::from_utf8_lossy(&package_id_bytes).to_string();
@@ -171,6 +252,9 @@ pub async fn get_mls_key_package(
             match db.get(key.as_bytes()).await {
                 Ok(Some(key_package)) => {
                     info!("MLS key package found: {} for user {}", package_id, req.user_id);
+                    // Uploads are validated, so this only fails for legacy packages
+                    let (ciphersuite, supported_ciphersuites) =
+                        key_package_ciphersuites(&key_package).unwrap_or_default();
                     Ok(Response::new(GetMlsKeyPackageResponse {
                         result: Some(crate::proto::auth::get_mls_key_package_response::Result::Success(
                             GetMlsKeyPackageSuccess {
@@ -178,6 +262,8 @@ pub async fn get_mls_key_package(
                                 device_id,
                                 key_package,
                                 package_id,
+                                ciphersuite,
+                                supported_ciphersuites,
                             },
                         )),
                     }))
@@ -234,3 +320,14 @@ pub async fn get_mls_key_package(
         }
     }
 }
+
+/// Ciphersuite code points of a serialized key package
+///
+/// # Returns
+/// Tuple of (ciphersuite, supported_ciphersuites)
+fn key_package_ciphersuites(key_package: &[u8]) -> guardyn_crypto::Result<(u32, Vec<u32>)> {
+    let (ciphersuite, supported) = guardyn_crypto::mls::key_package_ciphersuites(key_package)?;
+    let code = |suite: MlsCiphersuite| u32::from(u16::from(suite));
+
+    Ok((code(ciphersuite), supported.into_iter().map(code).collect()))
+}
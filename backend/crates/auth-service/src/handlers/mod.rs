@@ -10,3 +10,9 @@ pub mod mls_key_package;
 pub mod search_users;
 pub mod get_user_profile;
 pub mod delete_account;
+pub mod contact_verification;
+pub mod sender_certificate;
+pub mod devices;
+pub mod jwks;
+pub mod security_events;
+pub mod mfa;
@@ -1,107 +1,164 @@
-/// Refresh token handler - issues new access token
+/// Refresh token handler - rotates the refresh token and issues a new access token
+///
+/// Every refresh replaces the session's refresh token, and only the latest
+/// token of the session's family is accepted. A token that was already
+/// rotated away can only be presented again if it was copied, so the whole
+/// session is revoked and the user gets a security event.
 
 use crate::{AuthServiceImpl, proto::auth::*, proto::common::*};
+use crate::db::{RefreshRotation, RefreshTokenFamily, SecurityEventKind};
+use crate::jwt::{self, REFRESH_TOKEN_TTL_SECS};
 use tonic::{Request, Response, Status};
-use crate::jwt;
+
+fn error(code: error_response::ErrorCode, message: &str) -> ErrorResponse {
+    ErrorResponse {
+        code: code as i32,
+        message: message.to_string(),
+        details: std::collections::HashMap::new(),
+    }
+}
 
 pub async fn handle(
     service: &AuthServiceImpl,
     request: Request<RefreshTokenRequest>,
 ) -> Result<Response<RefreshTokenResponse>, Status> {
     let req = request.into_inner();
-    
+
+    let respond_error = |code: error_response::ErrorCode, message: &str|
+        -> Result<Response<RefreshTokenResponse>, Status> {
+        Ok(Response::new(RefreshTokenResponse {
+            result: Some(refresh_token_response::Result::Error(error(code, message))),
+        }))
+    };
+
     // Validate refresh token
-    let claims = match jwt::validate_token(&req.refresh_token, &service.jwt_secret) {
+    let claims = match jwt::validate_token(&req.refresh_token) {
         Ok(c) => c,
-        Err(_) => {
-            let error = ErrorResponse {
-                code: error_response::ErrorCode::Unauthorized as i32,
-                message: "Invalid or expired refresh token".to_string(),
-                details: std::collections::HashMap::new(),
-            };
-            return Ok(Response::new(RefreshTokenResponse {
-                result: Some(refresh_token_response::Result::Error(error)),
-            }));
-        }
+        Err(_) => return respond_error(error_response::ErrorCode::Unauthorized, "Invalid or expired refresh token"),
     };
-    
+
     // Check if token type is refresh
     if claims.token_type != Some("refresh".to_string()) {
-        let error = ErrorResponse {
-            code: error_response::ErrorCode::Unauthorized as i32,
-            message: "Invalid token type".to_string(),
-            details: std::collections::HashMap::new(),
-        };
-        return Ok(Response::new(RefreshTokenResponse {
-            result: Some(refresh_token_response::Result::Error(error)),
-        }));
+        return respond_error(error_response::ErrorCode::Unauthorized, "Invalid token type");
     }
-    
-    // Check if session exists in database
-    match service.db.get_session(&req.refresh_token).await {
-        Ok(Some(_)) => {},
-        Ok(None) => {
-            let error = ErrorResponse {
-                code: error_response::ErrorCode::Unauthorized as i32,
-                message: "Session not found or expired".to_string(),
-                details: std::collections::HashMap::new(),
-            };
-            return Ok(Response::new(RefreshTokenResponse {
-                result: Some(refresh_token_response::Result::Error(error)),
-            }));
-        }
+
+    // The session ID names the token family
+    let session_id = match claims.sid.clone() {
+        Some(sid) => sid,
+        None => return respond_error(error_response::ErrorCode::Unauthorized, "Invalid or expired refresh token"),
+    };
+
+    // Logout deletes the session
+    let session = match service.db.get_session(&req.refresh_token).await {
+        Ok(session) => session,
         Err(e) => {
             tracing::error!("Database error: {}", e);
-            let error = ErrorResponse {
-                code: error_response::ErrorCode::InternalError as i32,
-                message: "Internal server error".to_string(),
-                details: std::collections::HashMap::new(),
-            };
-            return Ok(Response::new(RefreshTokenResponse {
-                result: Some(refresh_token_response::Result::Error(error)),
-            }));
+            return respond_error(error_response::ErrorCode::InternalError, "Internal server error");
         }
-    }
-    
-    // Generate new access token
-    let access_token = match jwt::generate_access_token(&claims.sub, &claims.device_id, &claims.username, &service.jwt_secret) {
+    };
+
+    // New tokens stay in the same session, so revoking it covers them too
+    let access_token = match jwt::generate_access_token(&claims.sub, &claims.device_id, &claims.username, &session_id, &service.jwt_keys) {
         Ok(token) => token,
         Err(e) => {
             tracing::error!("Failed to generate access token: {}", e);
-            let error = ErrorResponse {
-                code: error_response::ErrorCode::InternalError as i32,
-                message: "Failed to generate token".to_string(),
-                details: std::collections::HashMap::new(),
-            };
-            return Ok(Response::new(RefreshTokenResponse {
-                result: Some(refresh_token_response::Result::Error(error)),
-            }));
+            return respond_error(error_response::ErrorCode::InternalError, "Failed to generate token");
         }
     };
-    
-    // Generate new refresh token as well (rotation)
-    let new_refresh_token = match jwt::generate_refresh_token(&claims.sub, &claims.device_id, &claims.username, &service.jwt_secret) {
+
+    let new_refresh_token = match jwt::generate_refresh_token(&claims.sub, &claims.device_id, &claims.username, &session_id, &service.jwt_keys) {
         Ok(token) => token,
         Err(e) => {
             tracing::error!("Failed to generate new refresh token: {}", e);
-            let error = ErrorResponse {
-                code: error_response::ErrorCode::InternalError as i32,
-                message: "Failed to generate refresh token".to_string(),
-                details: std::collections::HashMap::new(),
-            };
-            return Ok(Response::new(RefreshTokenResponse {
-                result: Some(refresh_token_response::Result::Error(error)),
-            }));
+            return respond_error(error_response::ErrorCode::InternalError, "Failed to generate refresh token");
         }
     };
-    
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
+
+    let next = RefreshTokenFamily {
+        session_id: session_id.clone(),
+        user_id: claims.sub.clone(),
+        device_id: claims.device_id.clone(),
+        current_token_hash: jwt::refresh_token_hash(&new_refresh_token),
+        rotated_at: now,
+    };
+
+    // Sessions from before token families get one on their first refresh
+    let presented_hash = jwt::refresh_token_hash(&req.refresh_token);
+    let rotation = match service.db.rotate_refresh_token(&presented_hash, &next, session.is_some()).await {
+        Ok(rotation) => rotation,
+        Err(e) => {
+            tracing::error!("Failed to rotate refresh token: {}", e);
+            return respond_error(error_response::ErrorCode::InternalError, "Internal server error");
+        }
+    };
+
+    let session = match (rotation, session) {
+        (RefreshRotation::Rotated, Some(session)) => session,
+        (RefreshRotation::Reused(family), _) => {
+            if let Err(e) = revoke_family(service, &family).await {
+                tracing::error!("Failed to revoke refresh token family {}: {}", family.session_id, e);
+            }
+            return respond_error(error_response::ErrorCode::Unauthorized, "Refresh token reuse detected, session revoked");
+        }
+        (RefreshRotation::Rotated, None) => {
+            // The family outlived its session
+            if let Err(e) = service.db.delete_refresh_family(&session_id).await {
+                tracing::warn!("Failed to delete refresh token family {}: {}", session_id, e);
+            }
+            return respond_error(error_response::ErrorCode::Unauthorized, "Session not found or expired");
+        }
+        (RefreshRotation::Unknown, _) => {
+            return respond_error(error_response::ErrorCode::Unauthorized, "Session not found or expired");
+        }
+    };
+
+    if let Err(e) = service.db.replace_session_token(&session, &new_refresh_token, now + REFRESH_TOKEN_TTL_SECS).await {
+        tracing::error!("Failed to store rotated session: {}", e);
+        return respond_error(error_response::ErrorCode::InternalError, "Internal server error");
+    }
+
     let success = RefreshTokenSuccess {
         access_token,
         access_token_expires_in: 15 * 60, // 15 minutes
         refresh_token: new_refresh_token,
     };
-    
+
     Ok(Response::new(RefreshTokenResponse {
         result: Some(refresh_token_response::Result::Success(success)),
     }))
 }
+
+/// Sign the device out after one of its rotated refresh tokens was replayed
+async fn revoke_family(service: &AuthServiceImpl, family: &RefreshTokenFamily) -> anyhow::Result<()> {
+    tracing::warn!(
+        "Refresh token reuse on session {} of user {}, revoking it",
+        family.session_id,
+        family.user_id
+    );
+
+    for session in service.db.list_sessions(&family.user_id).await? {
+        if session.session_id == family.session_id {
+            service.db.delete_session(&session.session_token).await?;
+        }
+    }
+    service.db.delete_refresh_family(&family.session_id).await?;
+    crate::revocation::revoke_session(service, &family.user_id, &family.session_id).await?;
+
+    crate::security_events::record(
+        service,
+        &family.user_id,
+        &family.device_id,
+        SecurityEventKind::RefreshTokenReuse,
+        "A refresh token of this device was used after it had been replaced, so it may have been copied. \
+         The device has been signed out."
+            .to_string(),
+    )
+    .await?;
+
+    Ok(())
+}
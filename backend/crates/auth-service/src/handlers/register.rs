@@ -18,7 +18,7 @@ use argon2::{
     password_hash::{rand_core::OsRng, PasswordHasher, SaltString},
     Argon2,
 };
-use crate::db::{UserProfile, Device, Session, KeyBundle as DbKeyBundle};
+use crate::db::{UserProfile, Device, Session, RefreshTokenFamily, KeyBundle as DbKeyBundle};
 use crate::jwt;
 
 pub async fn handle(
@@ -51,6 +51,17 @@ pub async fn handle(
         }));
     }
 
+    if let Some(Err(message)) = req.key_bundle.as_ref().map(super::key_bundle::check_key_bundle) {
+        let error = ErrorResponse {
+            code: error_response::ErrorCode::InvalidRequest as i32,
+            message: message.to_string(),
+            details: std::collections::HashMap::new(),
+        };
+        return Ok(Response::new(RegisterResponse {
+            result: Some(register_response::Result::Error(error)),
+        }));
+    }
+
     // Check if username exists
     match service.db.username_exists(&req.username).await {
         Ok(true) => {
@@ -146,7 +157,24 @@ pub async fn handle(
             identity_key: key_bundle.identity_key,
             signed_pre_key: key_bundle.signed_pre_key,
             signed_pre_key_signature: key_bundle.signed_pre_key_signature,
+            signed_pre_key_id: key_bundle.signed_pre_key_id,
             one_time_pre_keys: key_bundle.one_time_pre_keys,
+            one_time_pre_key_ids: key_bundle.one_time_pre_key_ids,
+            last_resort_pre_key: if key_bundle.last_resort_pre_key.is_empty() {
+                None
+            } else {
+                Some(crate::db::LastResortPreKey {
+                    key_id: key_bundle.last_resort_pre_key_id,
+                    public_key: key_bundle.last_resort_pre_key,
+                    signature: key_bundle.last_resort_pre_key_signature,
+                })
+            },
+            kem_pre_key: key_bundle.kem_pre_key.map(crate::db::KemPreKey::from),
+            one_time_kem_pre_keys: key_bundle
+                .one_time_kem_pre_keys
+                .into_iter()
+                .map(crate::db::KemPreKey::from)
+                .collect(),
             created_at: now,
         };
 
@@ -156,7 +184,10 @@ pub async fn handle(
     }
 
     // Generate JWT tokens
-    let access_token = match jwt::generate_access_token(&user_id, &device_id, &req.username, &service.jwt_secret) {
+    // Every token of this login carries the session ID, so the session can be revoked
+    let session_id = uuid::Uuid::new_v4().to_string();
+
+    let access_token = match jwt::generate_access_token(&user_id, &device_id, &req.username, &session_id, &service.jwt_keys) {
         Ok(token) => token,
         Err(e) => {
             tracing::error!("Failed to generate access token: {}", e);
@@ -171,7 +202,7 @@ pub async fn handle(
         }
     };
 
-    let refresh_token = match jwt::generate_refresh_token(&user_id, &device_id, &req.username, &service.jwt_secret) {
+    let refresh_token = match jwt::generate_refresh_token(&user_id, &device_id, &req.username, &session_id, &service.jwt_keys) {
         Ok(token) => token,
         Err(e) => {
             tracing::error!("Failed to generate refresh token: {}", e);
@@ -188,6 +219,7 @@ pub async fn handle(
 
     // Create session
     let session = Session {
+        session_id,
         session_token: refresh_token.clone(),
         user_id: user_id.clone(),
         device_id: device_id.clone(),
@@ -199,6 +231,11 @@ pub async fn handle(
         tracing::error!("Failed to create session: {}", e);
     }
 
+    // Only the latest refresh token of the session may be used
+    if let Err(e) = service.db.create_refresh_family(&RefreshTokenFamily::for_session(&session, now)).await {
+        tracing::error!("Failed to create refresh token family: {}", e);
+    }
+
     // Return success response
     let success = RegisterSuccess {
         user_id,
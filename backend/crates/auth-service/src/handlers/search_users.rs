@@ -13,10 +13,9 @@ const MAX_SEARCH_LIMIT: u32 = 100;
 pub async fn handle_search_users(
     request: SearchUsersRequest,
     db: DatabaseClient,
-    jwt_secret: &str,
 ) -> SearchUsersResponse {
     // Validate access token and extract current user_id
-    let current_user_id = match jwt::validate_token(&request.access_token, jwt_secret) {
+    let current_user_id = match jwt::validate_token(&request.access_token) {
         Ok(claims) => claims.sub,
         Err(e) => {
             warn!("Invalid access token for search: {}", e);
@@ -0,0 +1,72 @@
+/// Security events handler - lists security events on the caller's account
+
+use crate::{AuthServiceImpl, proto::auth::*, proto::common::*};
+use crate::db::SecurityEventKind;
+use tonic::{Request, Response, Status};
+
+/// Events returned when the request sets no limit
+const DEFAULT_LIMIT: u32 = 50;
+
+/// Upper bound for the requested limit
+const MAX_LIMIT: u32 = 200;
+
+pub async fn list(
+    service: &AuthServiceImpl,
+    request: Request<ListSecurityEventsRequest>,
+) -> Result<Response<ListSecurityEventsResponse>, Status> {
+    let req = request.into_inner();
+
+    let respond_error = |code: error_response::ErrorCode, message: &str|
+        -> Result<Response<ListSecurityEventsResponse>, Status> {
+        Ok(Response::new(ListSecurityEventsResponse {
+            result: Some(list_security_events_response::Result::Error(ErrorResponse {
+                code: code as i32,
+                message: message.to_string(),
+                details: std::collections::HashMap::new(),
+            })),
+        }))
+    };
+
+    // Validate access token
+    let claims = match crate::jwt::validate_token(&req.access_token) {
+        Ok(c) => c,
+        Err(_) => return respond_error(error_response::ErrorCode::Unauthorized, "Invalid or expired token"),
+    };
+
+    let limit = match req.limit {
+        0 => DEFAULT_LIMIT,
+        limit => limit.min(MAX_LIMIT),
+    };
+
+    let events = match service.db.list_security_events(&claims.sub, limit).await {
+        Ok(events) => events,
+        Err(e) => {
+            tracing::error!("Database error: {}", e);
+            return respond_error(error_response::ErrorCode::InternalError, "Internal server error");
+        }
+    };
+
+    let events = events
+        .into_iter()
+        .map(|event| SecurityEvent {
+            event_id: event.event_id,
+            event_type: match event.kind {
+                SecurityEventKind::RefreshTokenReuse => SecurityEventType::RefreshTokenReuse,
+                SecurityEventKind::TwoFactorEnabled => SecurityEventType::TwoFactorEnabled,
+                SecurityEventKind::TwoFactorDisabled => SecurityEventType::TwoFactorDisabled,
+                SecurityEventKind::RecoveryCodeUsed => SecurityEventType::RecoveryCodeUsed,
+                SecurityEventKind::AccountLocked => SecurityEventType::AccountLocked,
+            } as i32,
+            device_id: event.device_id,
+            occurred_at: Some(Timestamp {
+                seconds: event.created_at,
+                nanos: 0,
+            }),
+            description: event.description,
+        })
+        .collect();
+
+    Ok(Response::new(ListSecurityEventsResponse {
+        result: Some(list_security_events_response::Result::Success(ListSecurityEventsSuccess { events })),
+    }))
+}
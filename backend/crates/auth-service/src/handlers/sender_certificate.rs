@@ -0,0 +1,80 @@
+/// Sender certificate handler - issues certificates for sealed sender messages
+///
+/// A sender certificate binds the caller's user ID, device ID and identity key
+/// and is signed with the service's certificate key. Clients seal it together
+/// with each message so the recipient can authenticate the sender while
+/// messaging-service never learns who sent the message.
+
+use crate::{AuthServiceImpl, proto::auth::*, proto::common::*};
+use guardyn_crypto::SenderCertificate;
+use tonic::{Request, Response, Status};
+
+/// How long an issued sender certificate stays valid (24 hours)
+const SENDER_CERTIFICATE_TTL_SECS: i64 = 24 * 3600;
+
+pub async fn handle(
+    service: &AuthServiceImpl,
+    request: Request<GetSenderCertificateRequest>,
+) -> Result<Response<GetSenderCertificateResponse>, Status> {
+    let req = request.into_inner();
+
+    let respond_error = |code: error_response::ErrorCode, message: &str|
+        -> Result<Response<GetSenderCertificateResponse>, Status> {
+        Ok(Response::new(GetSenderCertificateResponse {
+            result: Some(get_sender_certificate_response::Result::Error(ErrorResponse {
+                code: code as i32,
+                message: message.to_string(),
+                details: std::collections::HashMap::new(),
+            })),
+        }))
+    };
+
+    // Validate access token
+    let claims = match crate::jwt::validate_token(&req.access_token) {
+        Ok(c) => c,
+        Err(_) => return respond_error(error_response::ErrorCode::Unauthorized, "Invalid or expired token"),
+    };
+
+    let identity_key = match service.db.get_identity_key(&claims.sub).await {
+        Ok(Some(key)) => key,
+        Ok(None) => return respond_error(error_response::ErrorCode::NotFound, "Identity key not found"),
+        Err(e) => {
+            tracing::error!("Database error: {}", e);
+            return respond_error(error_response::ErrorCode::InternalError, "Internal server error");
+        }
+    };
+
+    let expires_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+        + SENDER_CERTIFICATE_TTL_SECS;
+
+    let certificate = SenderCertificate::issue(
+        &service.sender_certificate_key,
+        &claims.sub,
+        &claims.device_id,
+        &identity_key,
+        expires_at,
+    )
+    .and_then(|certificate| certificate.to_bytes());
+
+    let certificate = match certificate {
+        Ok(certificate) => certificate,
+        Err(e) => {
+            tracing::error!("Failed to issue sender certificate: {}", e);
+            return respond_error(error_response::ErrorCode::InternalError, "Failed to issue sender certificate");
+        }
+    };
+
+    Ok(Response::new(GetSenderCertificateResponse {
+        result: Some(get_sender_certificate_response::Result::Success(GetSenderCertificateSuccess {
+            certificate,
+            expires_at: Some(Timestamp {
+                seconds: expires_at,
+                nanos: 0,
+            }),
+            server_public_key: service.sender_certificate_key.public_bytes(),
+        })),
+    }))
+}
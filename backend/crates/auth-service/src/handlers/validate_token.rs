@@ -11,7 +11,7 @@ pub async fn handle(
     let req = request.into_inner();
     
     // Validate token
-    let claims = match jwt::validate_token(&req.access_token, &service.jwt_secret) {
+    let claims = match jwt::validate_token(&req.access_token) {
         Ok(c) => c,
         Err(_) => {
             let error = ErrorResponse {
@@ -37,6 +37,34 @@ pub async fn handle(
         }));
     }
     
+    // The local list may lag behind a revocation made on another replica
+    for id in claims.sid.iter().chain(claims.jti.iter()) {
+        match service.db.is_revoked(id).await {
+            Ok(false) => {}
+            Ok(true) => {
+                let error = ErrorResponse {
+                    code: error_response::ErrorCode::Unauthorized as i32,
+                    message: "Token has been revoked".to_string(),
+                    details: std::collections::HashMap::new(),
+                };
+                return Ok(Response::new(ValidateTokenResponse {
+                    result: Some(validate_token_response::Result::Error(error)),
+                }));
+            }
+            Err(e) => {
+                tracing::error!("Failed to check revocation: {}", e);
+                let error = ErrorResponse {
+                    code: error_response::ErrorCode::InternalError as i32,
+                    message: "Internal server error".to_string(),
+                    details: std::collections::HashMap::new(),
+                };
+                return Ok(Response::new(ValidateTokenResponse {
+                    result: Some(validate_token_response::Result::Error(error)),
+                }));
+            }
+        }
+    }
+    
     // Return user info
     let success = ValidateTokenSuccess {
         user_id: claims.sub,
@@ -0,0 +1,56 @@
+/// JWKS over HTTP
+///
+/// Serves the token verification keys at `/.well-known/jwks.json` for
+/// consumers that fetch JWKS documents the standard way instead of over gRPC.
+
+use crate::signing_keys::JwtKeys;
+use anyhow::Result;
+use hyper::header::{CACHE_CONTROL, CONTENT_TYPE};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+/// Path of the JWKS document
+pub const JWKS_PATH: &str = "/.well-known/jwks.json";
+
+/// Serve the JWKS document until the server fails
+pub async fn serve(addr: SocketAddr, keys: Arc<JwtKeys>) -> Result<()> {
+    let make_service = make_service_fn(move |_conn| {
+        let keys = keys.clone();
+        async move { Ok::<_, Infallible>(service_fn(move |request| handle(request, keys.clone()))) }
+    });
+
+    tracing::info!(address = %addr, "JWKS HTTP server starting");
+    Server::bind(&addr).serve(make_service).await?;
+    Ok(())
+}
+
+async fn handle(request: Request<Body>, keys: Arc<JwtKeys>) -> Result<Response<Body>, Infallible> {
+    if request.method() != Method::GET || request.uri().path() != JWKS_PATH {
+        return Ok(status_response(StatusCode::NOT_FOUND));
+    }
+
+    let body = match keys.jwks().and_then(|jwks| Ok(serde_json::to_vec(&jwks)?)) {
+        Ok(body) => body,
+        Err(e) => {
+            tracing::error!("Failed to serve JWKS: {}", e);
+            return Ok(status_response(StatusCode::INTERNAL_SERVER_ERROR));
+        }
+    };
+
+    // The next key is published a full rotation period ahead, so caching is safe
+    let response = Response::builder()
+        .header(CONTENT_TYPE, "application/json")
+        .header(CACHE_CONTROL, "public, max-age=300")
+        .body(Body::from(body))
+        .unwrap_or_else(|_| status_response(StatusCode::INTERNAL_SERVER_ERROR));
+    Ok(response)
+}
+
+fn status_response(status: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = status;
+    response
+}
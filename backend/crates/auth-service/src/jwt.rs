@@ -3,12 +3,21 @@
 /// Handles:
 /// - Access token generation (15 min expiry)
 /// - Refresh token generation (30 days expiry)
-/// - Token validation
+/// - Token validation against the published signing keys (including session revocation)
 /// - Claims extraction
 
+use crate::signing_keys::JwtKeys;
 use anyhow::{Result, bail};
-use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use guardyn_common::jwks::key_set;
+use guardyn_common::revocation::revocations;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Access token lifetime in seconds
+pub const ACCESS_TOKEN_TTL_SECS: i64 = 15 * 60;
+
+/// Refresh token lifetime in seconds
+pub const REFRESH_TOKEN_TTL_SECS: i64 = 30 * 24 * 60 * 60;
 
 /// JWT token type
 #[derive(Debug, Clone, PartialEq)]
@@ -28,6 +37,10 @@ pub struct Claims {
     pub permissions: Vec<String>, // user permissions
     #[serde(skip_serializing_if = "Option::is_none")]
     pub token_type: Option<String>, // "access" or "refresh"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>, // session ID, shared by all tokens of a login
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>, // unique token ID
 }
 
 /// Generate access token (15 minutes)
@@ -35,7 +48,8 @@ pub fn generate_access_token(
     user_id: &str,
     device_id: &str,
     username: &str,
-    secret: &str,
+    session_id: &str,
+    keys: &JwtKeys,
 ) -> Result<String> {
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)?
@@ -45,20 +59,15 @@ pub fn generate_access_token(
         sub: user_id.to_string(),
         device_id: device_id.to_string(),
         username: username.to_string(),
-        exp: now + 15 * 60, // 15 minutes
+        exp: now + ACCESS_TOKEN_TTL_SECS,
         iat: now,
         permissions: vec!["read".to_string(), "write".to_string()],
         token_type: Some("access".to_string()),
+        sid: Some(session_id.to_string()),
+        jti: Some(uuid::Uuid::new_v4().to_string()),
     };
     
-    let header = Header::new(Algorithm::HS256);
-    let token = encode(
-        &header,
-        &claims,
-        &EncodingKey::from_secret(secret.as_bytes()),
-    )?;
-    
-    Ok(token)
+    keys.signing_key()?.sign(&claims)
 }
 
 /// Generate refresh token (30 days)
@@ -66,7 +75,8 @@ pub fn generate_refresh_token(
     user_id: &str,
     device_id: &str,
     username: &str,
-    secret: &str,
+    session_id: &str,
+    keys: &JwtKeys,
 ) -> Result<String> {
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)?
@@ -76,42 +86,40 @@ pub fn generate_refresh_token(
         sub: user_id.to_string(),
         device_id: device_id.to_string(),
         username: username.to_string(),
-        exp: now + 30 * 24 * 60 * 60, // 30 days
+        exp: now + REFRESH_TOKEN_TTL_SECS,
         iat: now,
         permissions: vec![],
         token_type: Some("refresh".to_string()),
+        sid: Some(session_id.to_string()),
+        jti: Some(uuid::Uuid::new_v4().to_string()),
     };
     
-    let header = Header::new(Algorithm::HS256);
-    let token = encode(
-        &header,
-        &claims,
-        &EncodingKey::from_secret(secret.as_bytes()),
-    )?;
-    
-    Ok(token)
+    keys.signing_key()?.sign(&claims)
 }
 
 /// Validate token and extract claims
-pub fn validate_token(token: &str, secret: &str) -> Result<Claims> {
-    let validation = Validation::new(Algorithm::HS256);
-    
-    let token_data = decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(secret.as_bytes()),
-        &validation,
-    )?;
+pub fn validate_token(token: &str) -> Result<Claims> {
+    let claims: Claims = key_set().verify(token)?;
     
     // Check if token is expired
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)?
         .as_secs() as i64;
     
-    if token_data.claims.exp < now {
+    if claims.exp < now {
         bail!("Token expired");
     }
+
+    if revocations().is_token_revoked(claims.sid.as_deref(), claims.jti.as_deref()) {
+        bail!("Token revoked");
+    }
     
-    Ok(token_data.claims)
+    Ok(claims)
+}
+
+/// SHA-256 (hex) of a refresh token, stored instead of the token to track its family
+pub fn refresh_token_hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
 }
 
 /// Extract token type from claims
@@ -123,8 +131,8 @@ pub fn get_token_type(claims: &Claims) -> TokenType {
 }
 
 /// Verify a JWT token and return user ID
-pub fn verify_jwt(token: &str, secret: &str) -> Result<String, tonic::Status> {
-    match validate_token(token, secret) {
+pub fn verify_jwt(token: &str) -> Result<String, tonic::Status> {
+    match validate_token(token) {
         Ok(claims) => Ok(claims.sub),
         Err(_) => Err(tonic::Status::unauthenticated("Invalid token")),
     }
@@ -133,27 +141,36 @@ pub fn verify_jwt(token: &str, secret: &str) -> Result<String, tonic::Status> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::signing_keys::DEFAULT_ROTATION_PERIOD_SECS;
+    use zeroize::Zeroizing;
 
-    const TEST_SECRET: &str = "test-secret-key";
+    /// Signing keys whose key set is installed for verification
+    fn test_keys() -> JwtKeys {
+        let keys = JwtKeys::new(Zeroizing::new([42u8; 32]), DEFAULT_ROTATION_PERIOD_SECS).unwrap();
+        key_set().replace(&keys.jwks().unwrap());
+        keys
+    }
 
     #[test]
     fn test_generate_access_token() {
-        let token = generate_access_token("user123", "device456", "testuser", TEST_SECRET).unwrap();
+        let token = generate_access_token("user123", "device456", "testuser", "session789", &test_keys()).unwrap();
         assert!(!token.is_empty());
         
-        let claims = validate_token(&token, TEST_SECRET).unwrap();
+        let claims = validate_token(&token).unwrap();
         assert_eq!(claims.sub, "user123");
         assert_eq!(claims.device_id, "device456");
         assert_eq!(claims.username, "testuser");
         assert_eq!(get_token_type(&claims), TokenType::Access);
+        assert_eq!(claims.sid.as_deref(), Some("session789"));
+        assert!(claims.jti.is_some());
     }
 
     #[test]
     fn test_generate_refresh_token() {
-        let token = generate_refresh_token("user123", "device456", "testuser", TEST_SECRET).unwrap();
+        let token = generate_refresh_token("user123", "device456", "testuser", "session789", &test_keys()).unwrap();
         assert!(!token.is_empty());
         
-        let claims = validate_token(&token, TEST_SECRET).unwrap();
+        let claims = validate_token(&token).unwrap();
         assert_eq!(claims.sub, "user123");
         assert_eq!(claims.device_id, "device456");
         assert_eq!(claims.username, "testuser");
@@ -161,18 +178,71 @@ mod tests {
     }
 
     #[test]
-    fn test_invalid_secret() {
-        let token = generate_access_token("user123", "device456", "testuser", TEST_SECRET).unwrap();
-        let result = validate_token(&token, "wrong-secret");
+    fn test_rotated_refresh_tokens_hash_differently() {
+        let keys = test_keys();
+        let first = generate_refresh_token("user123", "device456", "testuser", "session789", &keys).unwrap();
+        let second = generate_refresh_token("user123", "device456", "testuser", "session789", &keys).unwrap();
+
+        // Each rotation issues a distinct token, so a family can tell its current one apart
+        assert_eq!(refresh_token_hash(&first), refresh_token_hash(&first));
+        assert_ne!(refresh_token_hash(&first), refresh_token_hash(&second));
+        assert_eq!(refresh_token_hash(&first).len(), 64);
+    }
+
+    #[test]
+    fn test_unpublished_signing_key() {
+        test_keys();
+        let other = JwtKeys::new(Zeroizing::new([7u8; 32]), DEFAULT_ROTATION_PERIOD_SECS).unwrap();
+        let token = generate_access_token("user123", "device456", "testuser", "session789", &other).unwrap();
+        let result = validate_token(&token);
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_hs256_token_rejected() {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        let claims = Claims {
+            sub: "user123".to_string(),
+            device_id: "device456".to_string(),
+            username: "testuser".to_string(),
+            exp: now + 60,
+            iat: now,
+            permissions: vec![],
+            token_type: Some("access".to_string()),
+            sid: None,
+            jti: None,
+        };
+        let token = jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &claims,
+            &jsonwebtoken::EncodingKey::from_secret(b"development-secret-change-in-production"),
+        )
+        .unwrap();
+        assert!(validate_token(&token).is_err());
+    }
+
+    #[test]
+    fn test_revoked_session() {
+        let token = generate_access_token("user123", "device456", "testuser", "revoked-session", &test_keys()).unwrap();
+        assert!(validate_token(&token).is_ok());
+
+        revocations().insert(&guardyn_common::revocation::Revocation {
+            id: "revoked-session".to_string(),
+            user_id: "user123".to_string(),
+            expires_at: i64::MAX,
+        });
+        assert!(validate_token(&token).is_err());
+    }
+
     #[test]
     fn test_token_expiry() {
         // This test would need to mock time or use a very short expiry
         // For now, we just verify the expiry time is set correctly
-        let token = generate_access_token("user123", "device456", "testuser", TEST_SECRET).unwrap();
-        let claims = validate_token(&token, TEST_SECRET).unwrap();
+        let token = generate_access_token("user123", "device456", "testuser", "session789", &test_keys()).unwrap();
+        let claims = validate_token(&token).unwrap();
         
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
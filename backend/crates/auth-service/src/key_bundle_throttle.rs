@@ -0,0 +1,149 @@
+/// Key bundle fetch rate limiting
+///
+/// Every bundle fetch hands out one of the target device's one-time pre-keys,
+/// so fetches are counted per requesting device and per target device in
+/// sliding windows stored in TiKV. A fetch reserves its slot in the same
+/// transaction that checks the limit, so concurrent fetches can't overshoot
+/// it. Limited fetches get `RATE_LIMITED` with a `retry_after_seconds` detail.
+
+use crate::proto::common::*;
+use crate::AuthServiceImpl;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Limit of one kind of rate limiting key
+#[derive(Debug, Clone, Copy)]
+pub struct FetchPolicy {
+    /// Sliding window fetches are counted in
+    pub window_secs: i64,
+    /// Fetches allowed in the window
+    pub max_fetches: usize,
+}
+
+/// Bundles one device may fetch (of any devices)
+pub const REQUESTER_POLICY: FetchPolicy = FetchPolicy {
+    window_secs: 60 * 60,
+    max_fetches: 300,
+};
+
+/// Fetches of one device's bundle (by anyone), kept below a full upload of
+/// one-time pre-keys so a single window can't drain them
+pub const TARGET_POLICY: FetchPolicy = FetchPolicy {
+    window_secs: 60 * 60,
+    max_fetches: 50,
+};
+
+/// Recent key bundle fetches under one rate limiting key
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RecentFetches {
+    /// Unix times of the fetches in the current window
+    pub fetches: Vec<i64>,
+}
+
+impl RecentFetches {
+    /// Reserve a fetch, or return the seconds until one is allowed
+    pub fn reserve(&mut self, policy: &FetchPolicy, now: i64) -> Option<i64> {
+        self.fetches.retain(|time| *time > now - policy.window_secs);
+
+        if self.fetches.len() >= policy.max_fetches {
+            let oldest = self.fetches.iter().copied().min().unwrap_or(now);
+            return Some((oldest + policy.window_secs - now).max(1));
+        }
+
+        self.fetches.push(now);
+        None
+    }
+}
+
+/// Rate limiting key of a device fetching bundles
+pub fn requester_key(user_id: &str, device_id: &str) -> String {
+    format!("requester/{}/{}", user_id, device_id)
+}
+
+/// Rate limiting key of a device whose bundle is fetched
+pub fn target_key(user_id: &str, device_id: &str) -> String {
+    format!("target/{}/{}", user_id, device_id)
+}
+
+fn now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+/// Reserve a fetch of `target`'s bundle by `requester`
+///
+/// Returns the seconds to wait if either limit is reached. The requester's
+/// slot is spent even when the target's limit turns the fetch down.
+pub async fn reserve(
+    service: &AuthServiceImpl,
+    (requester_user_id, requester_device_id): (&str, &str),
+    (target_user_id, target_device_id): (&str, &str),
+) -> Result<Option<i64>> {
+    let now = now();
+
+    let keys = [
+        (requester_key(requester_user_id, requester_device_id), REQUESTER_POLICY),
+        (target_key(target_user_id, target_device_id), TARGET_POLICY),
+    ];
+    for (key, policy) in keys {
+        let retry_after = service.db.update_key_bundle_fetches(&key, |fetches| {
+            fetches.get_or_insert_with(Default::default).reserve(&policy, now)
+        }).await?;
+
+        if retry_after.is_some() {
+            return Ok(retry_after);
+        }
+    }
+
+    Ok(None)
+}
+
+/// `RATE_LIMITED` error telling the client when to retry
+pub fn rate_limited(retry_after: i64) -> ErrorResponse {
+    let mut details = std::collections::HashMap::new();
+    details.insert("retry_after_seconds".to_string(), retry_after.to_string());
+
+    ErrorResponse {
+        code: error_response::ErrorCode::RateLimited as i32,
+        message: "Too many key bundle requests, try again later".to_string(),
+        details,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reserve_until_limit() {
+        let mut fetches = RecentFetches::default();
+        let now = 1_000_000;
+
+        for _ in 0..TARGET_POLICY.max_fetches {
+            assert_eq!(fetches.reserve(&TARGET_POLICY, now), None);
+        }
+        assert_eq!(fetches.reserve(&TARGET_POLICY, now), Some(TARGET_POLICY.window_secs));
+
+        // A refused fetch doesn't take a slot
+        assert_eq!(fetches.fetches.len(), TARGET_POLICY.max_fetches);
+    }
+
+    #[test]
+    fn test_fetches_slide_out_of_window() {
+        let mut fetches = RecentFetches::default();
+        let now = 1_000_000;
+
+        assert_eq!(fetches.reserve(&TARGET_POLICY, now), None);
+        for _ in 1..TARGET_POLICY.max_fetches {
+            assert_eq!(fetches.reserve(&TARGET_POLICY, now + 10), None);
+        }
+        assert_eq!(fetches.reserve(&TARGET_POLICY, now + 20), Some(TARGET_POLICY.window_secs - 20));
+
+        // Once the first fetch leaves the window there is room for one more
+        let later = now + TARGET_POLICY.window_secs;
+        assert_eq!(fetches.reserve(&TARGET_POLICY, later), None);
+        assert_eq!(fetches.reserve(&TARGET_POLICY, later), Some(10));
+    }
+}
@@ -0,0 +1,528 @@
+/// Login brute-force protection
+///
+/// Failed logins are counted per account and per client address in sliding
+/// windows stored in TiKV. After a few free failures each attempt has to wait
+/// exponentially longer, and reaching the window's limit locks the key out for
+/// a while (doubling on repeated lockouts). Throttled logins get
+/// `RATE_LIMITED` with a `retry_after_seconds` detail, and an account lockout
+/// is recorded as a security event so the user learns about it.
+///
+/// Each attempt is reserved as a failure in the same transaction that checks
+/// the limits, so concurrent guesses can't all pass the check before any of
+/// them is counted; a correct password or code releases the reservation.
+///
+/// Wrong second-factor codes count against the account too, and its counter is
+/// only reset once a login fully completes, so a known password doesn't allow
+/// guessing TOTP codes without limit.
+
+use crate::db::SecurityEventKind;
+use crate::proto::common::*;
+use crate::AuthServiceImpl;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use tonic::Request;
+
+/// Limits of one kind of throttling key
+#[derive(Debug, Clone, Copy)]
+pub struct ThrottlePolicy {
+    /// Sliding window failures are counted in
+    pub window_secs: i64,
+    /// Failures in the window that lock the key out
+    pub max_failures: usize,
+    /// Failures in the window before attempts are delayed
+    pub free_failures: usize,
+    /// Upper bound of the delay between attempts
+    pub max_backoff_secs: i64,
+    /// Length of the first lockout
+    pub lockout_secs: i64,
+    /// Upper bound of a lockout
+    pub max_lockout_secs: i64,
+}
+
+/// Failed logins of one account
+pub const ACCOUNT_POLICY: ThrottlePolicy = ThrottlePolicy {
+    window_secs: 60 * 60,
+    max_failures: 10,
+    free_failures: 3,
+    max_backoff_secs: 60,
+    lockout_secs: 15 * 60,
+    max_lockout_secs: 24 * 60 * 60,
+};
+
+/// Failed logins from one client address (any account)
+pub const IP_POLICY: ThrottlePolicy = ThrottlePolicy {
+    window_secs: 60 * 60,
+    max_failures: 100,
+    free_failures: 20,
+    max_backoff_secs: 60,
+    lockout_secs: 15 * 60,
+    max_lockout_secs: 24 * 60 * 60,
+};
+
+/// Failed login attempts under one throttling key
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LoginAttempts {
+    /// Unix times of the failures in the current window
+    pub failures: Vec<i64>,
+    /// End of the current lockout (0 if none)
+    pub locked_until: i64,
+    /// Consecutive lockouts, reset once a window passes after the last one
+    pub lockouts: u32,
+}
+
+impl LoginAttempts {
+    /// Seconds until the next attempt is allowed, if it has to wait
+    pub fn retry_after(&self, policy: &ThrottlePolicy, now: i64) -> Option<i64> {
+        if self.locked_until > now {
+            return Some(self.locked_until - now);
+        }
+
+        let recent: Vec<i64> = self
+            .failures
+            .iter()
+            .copied()
+            .filter(|time| *time > now - policy.window_secs)
+            .collect();
+        if recent.len() < policy.free_failures {
+            return None;
+        }
+
+        let doublings = (recent.len() - policy.free_failures).min(30) as u32;
+        let delay = (1i64 << doublings).min(policy.max_backoff_secs);
+        let last = recent.iter().copied().max().unwrap_or(now);
+        Some(last + delay - now).filter(|wait| *wait > 0)
+    }
+
+    /// Count an attempt as a failure until it is released, unless it has to wait
+    ///
+    /// Returns the seconds to wait if the attempt isn't allowed yet.
+    pub fn reserve(&mut self, policy: &ThrottlePolicy, now: i64) -> Option<i64> {
+        self.prune(policy, now);
+        if let Some(retry_after) = self.retry_after(policy, now) {
+            return Some(retry_after);
+        }
+
+        self.failures.push(now);
+        None
+    }
+
+    /// Drop the latest reserved attempt once it turned out not to be a failure
+    pub fn release(&mut self) {
+        let latest = self
+            .failures
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, time)| **time)
+            .map(|(index, _)| index);
+        if let Some(index) = latest {
+            self.failures.remove(index);
+        }
+    }
+
+    /// Keep a reserved attempt as a failure, returning the lockout length if it
+    /// locked the key out
+    pub fn confirm_failure(&mut self, policy: &ThrottlePolicy, now: i64) -> Option<i64> {
+        self.prune(policy, now);
+        if self.failures.len() < policy.max_failures {
+            return None;
+        }
+
+        self.lockouts += 1;
+        let doublings = (self.lockouts - 1).min(16);
+        let duration = policy
+            .lockout_secs
+            .saturating_mul(1i64 << doublings)
+            .min(policy.max_lockout_secs);
+        self.locked_until = now + duration;
+        self.failures.clear();
+        Some(duration)
+    }
+
+    /// Drop failures outside the window and forget lockouts a window in the past
+    fn prune(&mut self, policy: &ThrottlePolicy, now: i64) {
+        self.failures.retain(|time| *time > now - policy.window_secs);
+        if self.lockouts > 0 && self.locked_until + policy.window_secs <= now {
+            self.lockouts = 0;
+            self.locked_until = 0;
+        }
+    }
+}
+
+/// Throttling key of an account
+pub fn account_key(user_id: &str) -> String {
+    format!("account/{}", user_id)
+}
+
+/// Throttling key of a client address
+pub fn ip_key(ip: &str) -> String {
+    format!("ip/{}", ip)
+}
+
+/// Proxy address or network allowed to report the client address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrustedProxy {
+    network: IpAddr,
+    prefix_len: u32,
+}
+
+impl TrustedProxy {
+    /// Parse an address (`10.0.0.5`) or a CIDR network (`10.0.0.0/8`)
+    pub fn parse(value: &str) -> Result<Self> {
+        let (address, prefix_len) = match value.trim().split_once('/') {
+            Some((address, prefix_len)) => (address, Some(prefix_len.parse::<u32>()?)),
+            None => (value.trim(), None),
+        };
+        let network: IpAddr = address.parse()?;
+        let max_len = if network.is_ipv4() { 32 } else { 128 };
+        let prefix_len = prefix_len.unwrap_or(max_len);
+        if prefix_len > max_len {
+            anyhow::bail!("prefix length {} is too long for {}", prefix_len, network);
+        }
+
+        Ok(Self { network, prefix_len })
+    }
+
+    /// Parse a comma-separated list such as the `TRUSTED_PROXIES` setting
+    pub fn parse_list(value: &str) -> Result<Vec<Self>> {
+        value
+            .split(',')
+            .filter(|entry| !entry.trim().is_empty())
+            .map(Self::parse)
+            .collect()
+    }
+
+    /// Whether `ip` is the proxy or inside its network
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+            ip => ip,
+        };
+        match (self.network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix_len).unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix_len).unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Address of the client that sent `request`
+///
+/// `x-forwarded-for` is only honoured if the peer is one of `trusted_proxies`,
+/// otherwise any client could pick the address it is throttled under. The
+/// entries are read from the right, skipping the trusted proxies of a chain;
+/// everything left of the first untrusted entry was set by the client.
+pub fn client_ip<T>(request: &Request<T>, trusted_proxies: &[TrustedProxy]) -> Option<String> {
+    let peer = request.remote_addr()?.ip();
+    let trusted = |ip: IpAddr| trusted_proxies.iter().any(|proxy| proxy.contains(ip));
+    if !trusted(peer) {
+        return Some(peer.to_string());
+    }
+
+    let forwarded = request
+        .metadata()
+        .get("x-forwarded-for")
+        .and_then(|value| value.to_str().ok())
+        .map(|value| {
+            value
+                .split(',')
+                .map(|entry| entry.trim().parse::<IpAddr>().ok())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    let mut client = peer;
+    for entry in forwarded.into_iter().rev() {
+        match entry {
+            Some(ip) => {
+                client = ip;
+                if !trusted(ip) {
+                    break;
+                }
+            }
+            // A malformed entry can't be attributed to anyone behind it
+            None => break,
+        }
+    }
+
+    Some(client.to_string())
+}
+
+fn now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+fn throttling_keys(client_ip: Option<&str>, user_id: Option<&str>) -> Vec<(String, ThrottlePolicy)> {
+    client_ip
+        .map(|ip| (ip_key(ip), IP_POLICY))
+        .into_iter()
+        .chain(user_id.map(|user_id| (account_key(user_id), ACCOUNT_POLICY)))
+        .collect()
+}
+
+/// Reserve a login attempt for the given client and/or account
+///
+/// Returns the seconds to wait if the attempt isn't allowed yet, in which case
+/// nothing stays reserved. Otherwise the attempt counts as a failure until
+/// [`release`]d, and [`login_failed`] confirms it.
+pub async fn reserve(
+    service: &AuthServiceImpl,
+    client_ip: Option<&str>,
+    user_id: Option<&str>,
+) -> Result<Option<i64>> {
+    let now = now();
+    let mut reserved = Vec::new();
+
+    for (key, policy) in throttling_keys(client_ip, user_id) {
+        let result = service.db.update_login_attempts(&key, |attempts| {
+            attempts.get_or_insert_with(Default::default).reserve(&policy, now)
+        }).await;
+        match result {
+            Ok(None) => reserved.push(key),
+            Ok(Some(retry_after)) => {
+                release_keys(service, &reserved).await;
+                return Ok(Some(retry_after));
+            }
+            Err(e) => {
+                release_keys(service, &reserved).await;
+                return Err(e);
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Release an attempt reserved with [`reserve`] that wasn't a failed login
+///
+/// Errors are logged rather than returned: at worst the attempt stays counted.
+pub async fn release(service: &AuthServiceImpl, client_ip: Option<&str>, user_id: Option<&str>) {
+    let keys: Vec<String> = throttling_keys(client_ip, user_id).into_iter().map(|(key, _)| key).collect();
+    release_keys(service, &keys).await;
+}
+
+async fn release_keys(service: &AuthServiceImpl, keys: &[String]) {
+    for key in keys {
+        let released = service.db.update_login_attempts(key, |attempts| {
+            if let Some(attempts) = attempts {
+                attempts.release();
+            }
+        }).await;
+        if let Err(e) = released {
+            tracing::error!("Failed to release login attempt of {}: {}", key, e);
+        }
+    }
+}
+
+/// Count a failed login reserved with [`reserve`] against the client and/or account
+///
+/// Errors are logged rather than returned: the login has failed either way.
+pub async fn login_failed(service: &AuthServiceImpl, client_ip: Option<&str>, user_id: Option<&str>) {
+    let now = now();
+
+    if let Some(ip) = client_ip {
+        match service.db.update_login_attempts(&ip_key(ip), |attempts| {
+            attempts.get_or_insert_with(Default::default).confirm_failure(&IP_POLICY, now)
+        }).await {
+            Ok(Some(duration)) => tracing::warn!("Logins from {} locked for {}s", ip, duration),
+            Ok(None) => {}
+            Err(e) => tracing::error!("Failed to record failed login from {}: {}", ip, e),
+        }
+    }
+
+    if let Some(user_id) = user_id {
+        match service.db.update_login_attempts(&account_key(user_id), |attempts| {
+            attempts.get_or_insert_with(Default::default).confirm_failure(&ACCOUNT_POLICY, now)
+        }).await {
+            Ok(Some(duration)) => {
+                tracing::warn!("Logins to user {} locked for {}s", user_id, duration);
+                let description = format!(
+                    "Sign-in was locked for {} minutes after {} failed attempts.",
+                    duration / 60,
+                    ACCOUNT_POLICY.max_failures,
+                );
+                if let Err(e) = crate::security_events::record(
+                    service,
+                    user_id,
+                    "",
+                    SecurityEventKind::AccountLocked,
+                    description,
+                ).await {
+                    tracing::error!("Failed to record account lockout: {}", e);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => tracing::error!("Failed to record failed login for user {}: {}", user_id, e),
+        }
+    }
+}
+
+/// Clear an account's failures once a login has completed
+pub async fn login_succeeded(service: &AuthServiceImpl, user_id: &str) {
+    if let Err(e) = service.db.update_login_attempts(&account_key(user_id), |attempts| *attempts = None).await {
+        tracing::error!("Failed to reset failed logins for user {}: {}", user_id, e);
+    }
+}
+
+/// `RATE_LIMITED` error telling the client when to retry
+pub fn rate_limited(retry_after: i64) -> ErrorResponse {
+    let mut details = std::collections::HashMap::new();
+    details.insert("retry_after_seconds".to_string(), retry_after.to_string());
+
+    ErrorResponse {
+        code: error_response::ErrorCode::RateLimited as i32,
+        message: "Too many failed login attempts, try again later".to_string(),
+        details,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A failure reserved without waiting for the backoff
+    fn record_failure(attempts: &mut LoginAttempts, policy: &ThrottlePolicy, now: i64) -> Option<i64> {
+        attempts.failures.push(now);
+        attempts.confirm_failure(policy, now)
+    }
+
+    #[test]
+    fn test_backoff_after_free_failures() {
+        let mut attempts = LoginAttempts::default();
+        let now = 1_000_000;
+
+        for _ in 0..ACCOUNT_POLICY.free_failures - 1 {
+            record_failure(&mut attempts, &ACCOUNT_POLICY, now);
+        }
+        assert_eq!(attempts.retry_after(&ACCOUNT_POLICY, now), None);
+
+        // Each further failure doubles the wait
+        record_failure(&mut attempts, &ACCOUNT_POLICY, now);
+        assert_eq!(attempts.retry_after(&ACCOUNT_POLICY, now), Some(1));
+        record_failure(&mut attempts, &ACCOUNT_POLICY, now);
+        assert_eq!(attempts.retry_after(&ACCOUNT_POLICY, now), Some(2));
+        record_failure(&mut attempts, &ACCOUNT_POLICY, now);
+        assert_eq!(attempts.retry_after(&ACCOUNT_POLICY, now + 1), Some(3));
+        assert_eq!(attempts.retry_after(&ACCOUNT_POLICY, now + 4), None);
+    }
+
+    #[test]
+    fn test_lockout_escalates() {
+        let mut attempts = LoginAttempts::default();
+        let mut now = 1_000_000;
+
+        for _ in 0..ACCOUNT_POLICY.max_failures - 1 {
+            assert_eq!(record_failure(&mut attempts, &ACCOUNT_POLICY, now), None);
+        }
+        assert_eq!(record_failure(&mut attempts, &ACCOUNT_POLICY, now), Some(ACCOUNT_POLICY.lockout_secs));
+        assert_eq!(attempts.retry_after(&ACCOUNT_POLICY, now), Some(ACCOUNT_POLICY.lockout_secs));
+
+        // Failing again right after the lockout locks out twice as long
+        now += ACCOUNT_POLICY.lockout_secs;
+        for _ in 0..ACCOUNT_POLICY.max_failures - 1 {
+            record_failure(&mut attempts, &ACCOUNT_POLICY, now);
+        }
+        assert_eq!(record_failure(&mut attempts, &ACCOUNT_POLICY, now), Some(2 * ACCOUNT_POLICY.lockout_secs));
+
+        // A quiet window later the next lockout starts short again
+        now += 2 * ACCOUNT_POLICY.lockout_secs + ACCOUNT_POLICY.window_secs;
+        for _ in 0..ACCOUNT_POLICY.max_failures - 1 {
+            record_failure(&mut attempts, &ACCOUNT_POLICY, now);
+        }
+        assert_eq!(record_failure(&mut attempts, &ACCOUNT_POLICY, now), Some(ACCOUNT_POLICY.lockout_secs));
+    }
+
+    #[test]
+    fn test_failures_slide_out_of_window() {
+        let mut attempts = LoginAttempts::default();
+        let now = 1_000_000;
+
+        for _ in 0..ACCOUNT_POLICY.max_failures - 1 {
+            record_failure(&mut attempts, &ACCOUNT_POLICY, now);
+        }
+        let later = now + ACCOUNT_POLICY.window_secs;
+        assert_eq!(attempts.retry_after(&ACCOUNT_POLICY, later), None);
+        assert_eq!(record_failure(&mut attempts, &ACCOUNT_POLICY, later), None);
+        assert_eq!(attempts.failures.len(), 1);
+    }
+
+    #[test]
+    fn test_reservations_count_until_released() {
+        let mut attempts = LoginAttempts::default();
+        let now = 1_000_000;
+
+        // Concurrent attempts are counted before any of them fails
+        for _ in 0..ACCOUNT_POLICY.free_failures {
+            assert_eq!(attempts.reserve(&ACCOUNT_POLICY, now), None);
+        }
+        assert_eq!(attempts.reserve(&ACCOUNT_POLICY, now), Some(1));
+        assert_eq!(attempts.failures.len(), ACCOUNT_POLICY.free_failures);
+
+        // A successful attempt gives its slot back
+        attempts.release();
+        assert_eq!(attempts.reserve(&ACCOUNT_POLICY, now), None);
+        assert_eq!(attempts.confirm_failure(&ACCOUNT_POLICY, now), None);
+        assert_eq!(attempts.failures.len(), ACCOUNT_POLICY.free_failures);
+    }
+
+    fn request_from(peer: &str, forwarded: Option<&str>) -> Request<()> {
+        let mut request = Request::new(());
+        request.extensions_mut().insert(tonic::transport::server::TcpConnectInfo {
+            local_addr: None,
+            remote_addr: Some(std::net::SocketAddr::new(peer.parse().unwrap(), 443)),
+        });
+        if let Some(forwarded) = forwarded {
+            request.metadata_mut().insert("x-forwarded-for", forwarded.parse().unwrap());
+        }
+        request
+    }
+
+    #[test]
+    fn test_client_ip_trusts_only_configured_proxies() {
+        let proxies = TrustedProxy::parse_list("10.0.0.0/8, 192.0.2.1").unwrap();
+        assert_eq!(client_ip(&Request::new(()), &proxies), None);
+
+        // Clients can't pick their address
+        let direct = request_from("198.51.100.9", Some("203.0.113.7"));
+        assert_eq!(client_ip(&direct, &proxies).as_deref(), Some("198.51.100.9"));
+        assert_eq!(client_ip(&direct, &[]).as_deref(), Some("198.51.100.9"));
+
+        // Behind the proxies the first untrusted entry from the right is the client
+        let proxied = request_from("10.1.2.3", Some("1.2.3.4, 203.0.113.7, 192.0.2.1"));
+        assert_eq!(client_ip(&proxied, &proxies).as_deref(), Some("203.0.113.7"));
+
+        let unforwarded = request_from("10.1.2.3", None);
+        assert_eq!(client_ip(&unforwarded, &proxies).as_deref(), Some("10.1.2.3"));
+    }
+
+    #[test]
+    fn test_trusted_proxy_networks() {
+        let network = TrustedProxy::parse("10.0.0.0/8").unwrap();
+        assert!(network.contains("10.255.0.1".parse().unwrap()));
+        assert!(network.contains("::ffff:10.0.0.1".parse().unwrap()));
+        assert!(!network.contains("11.0.0.1".parse().unwrap()));
+
+        let v6 = TrustedProxy::parse("2001:db8::/32").unwrap();
+        assert!(v6.contains("2001:db8::1".parse().unwrap()));
+        assert!(!v6.contains("2001:db9::1".parse().unwrap()));
+
+        assert!(TrustedProxy::parse("10.0.0.0/33").is_err());
+        assert!(TrustedProxy::parse("proxy.local").is_err());
+    }
+
+    #[test]
+    fn test_rate_limited_detail() {
+        let error = rate_limited(42);
+        assert_eq!(error.code, error_response::ErrorCode::RateLimited as i32);
+        assert_eq!(error.details.get("retry_after_seconds").map(String::as_str), Some("42"));
+    }
+}
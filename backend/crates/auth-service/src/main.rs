@@ -4,17 +4,33 @@
 /// - User registration with E2EE key bundles
 /// - Login/logout with JWT tokens
 /// - Device management
-/// - Session handling
+/// - Session handling and revocation
+/// - Refresh token rotation with reuse detection
+/// - TOTP two-factor authentication with recovery codes
+/// - Login throttling and account lockout
 /// - Token generation and validation
+/// - Token signing key rotation and JWKS publication
+/// - Sender certificates for sealed sender
 
 mod handlers;
 mod models;
 mod jwt;
 mod db;
+mod jwks_http;
+mod key_bundle_throttle;
+mod login_throttle;
+mod mfa;
+mod revocation;
+mod security_events;
+mod signing_keys;
 
 use guardyn_common::{config::ServiceConfig, observability};
 use tonic::{transport::Server, Request, Response, Status};
 use anyhow::Result;
+use guardyn_crypto::x3dh::IdentityKeyPair;
+use signing_keys::JwtKeys;
+use std::sync::Arc;
+use zeroize::Zeroizing;
 
 // Import generated protobuf code
 pub mod proto {
@@ -35,11 +51,24 @@ use proto::auth::{
     ValidateTokenRequest, ValidateTokenResponse,
     GetKeyBundleRequest, GetKeyBundleResponse,
     UploadPreKeysRequest, UploadPreKeysResponse,
+    RotateSignedPreKeyRequest, RotateSignedPreKeyResponse,
+    GetPreKeyStatusRequest, GetPreKeyStatusResponse,
     UploadMlsKeyPackageRequest, UploadMlsKeyPackageResponse,
     GetMlsKeyPackageRequest, GetMlsKeyPackageResponse,
     SearchUsersRequest, SearchUsersResponse,
     GetUserProfileRequest, GetUserProfileResponse,
     DeleteAccountRequest, DeleteAccountResponse,
+    SetContactVerificationRequest, SetContactVerificationResponse,
+    GetContactVerificationRequest, GetContactVerificationResponse,
+    GetSenderCertificateRequest, GetSenderCertificateResponse,
+    GetUserDevicesRequest, GetUserDevicesResponse,
+    RemoveDeviceRequest, RemoveDeviceResponse,
+    GetJwksRequest, GetJwksResponse,
+    ListSecurityEventsRequest, ListSecurityEventsResponse,
+    VerifySecondFactorRequest, VerifySecondFactorResponse,
+    BeginTotpEnrollmentRequest, BeginTotpEnrollmentResponse,
+    ConfirmTotpEnrollmentRequest, ConfirmTotpEnrollmentResponse,
+    DisableTotpRequest, DisableTotpResponse,
     HealthRequest,
 };
 use proto::common::HealthStatus;
@@ -47,12 +76,28 @@ use proto::common::HealthStatus;
 /// Authentication Service Implementation
 pub struct AuthServiceImpl {
     db: db::DatabaseClient,
-    jwt_secret: String,
+    jwt_keys: Arc<JwtKeys>,
+    sender_certificate_key: IdentityKeyPair,
+    /// Broadcasts session revocations (None if NATS is unreachable)
+    nats: Option<async_nats::Client>,
+    /// Proxies whose `x-forwarded-for` header names the client
+    trusted_proxies: Vec<login_throttle::TrustedProxy>,
 }
 
 impl AuthServiceImpl {
-    pub fn new(db: db::DatabaseClient, jwt_secret: String) -> Self {
-        Self { db, jwt_secret }
+    pub fn new(
+        db: db::DatabaseClient,
+        jwt_keys: Arc<JwtKeys>,
+        sender_certificate_key: IdentityKeyPair,
+        nats: Option<async_nats::Client>,
+    ) -> Self {
+        Self { db, jwt_keys, sender_certificate_key, nats, trusted_proxies: Vec::new() }
+    }
+
+    /// Take the client address of logins from these proxies' `x-forwarded-for`
+    pub fn with_trusted_proxies(mut self, trusted_proxies: Vec<login_throttle::TrustedProxy>) -> Self {
+        self.trusted_proxies = trusted_proxies;
+        self
     }
 }
 
@@ -107,6 +152,20 @@ impl AuthService for AuthServiceImpl {
         handlers::key_bundle::upload(self, request).await
     }
 
+    async fn rotate_signed_pre_key(
+        &self,
+        request: Request<RotateSignedPreKeyRequest>,
+    ) -> Result<Response<RotateSignedPreKeyResponse>, Status> {
+        handlers::key_bundle::rotate_signed_pre_key(self, request).await
+    }
+
+    async fn get_pre_key_status(
+        &self,
+        request: Request<GetPreKeyStatusRequest>,
+    ) -> Result<Response<GetPreKeyStatusResponse>, Status> {
+        handlers::key_bundle::status(self, request).await
+    }
+
     async fn upload_mls_key_package(
         &self,
         request: Request<UploadMlsKeyPackageRequest>,
@@ -130,7 +189,6 @@ impl AuthService for AuthServiceImpl {
         let response = handlers::search_users::handle_search_users(
             request.into_inner(),
             self.db.clone(),
-            &self.jwt_secret,
         )
         .await;
         Ok(Response::new(response))
@@ -155,6 +213,83 @@ impl AuthService for AuthServiceImpl {
         handlers::delete_account::handle(self, request).await
     }
 
+    async fn set_contact_verification(
+        &self,
+        request: Request<SetContactVerificationRequest>,
+    ) -> Result<Response<SetContactVerificationResponse>, Status> {
+        handlers::contact_verification::set(self, request).await
+    }
+
+    async fn get_contact_verification(
+        &self,
+        request: Request<GetContactVerificationRequest>,
+    ) -> Result<Response<GetContactVerificationResponse>, Status> {
+        handlers::contact_verification::get(self, request).await
+    }
+
+    async fn get_sender_certificate(
+        &self,
+        request: Request<GetSenderCertificateRequest>,
+    ) -> Result<Response<GetSenderCertificateResponse>, Status> {
+        handlers::sender_certificate::handle(self, request).await
+    }
+
+    async fn get_user_devices(
+        &self,
+        request: Request<GetUserDevicesRequest>,
+    ) -> Result<Response<GetUserDevicesResponse>, Status> {
+        handlers::devices::list(self, request).await
+    }
+
+    async fn remove_device(
+        &self,
+        request: Request<RemoveDeviceRequest>,
+    ) -> Result<Response<RemoveDeviceResponse>, Status> {
+        handlers::devices::remove(self, request).await
+    }
+
+    async fn get_jwks(
+        &self,
+        request: Request<GetJwksRequest>,
+    ) -> Result<Response<GetJwksResponse>, Status> {
+        handlers::jwks::handle(self, request).await
+    }
+
+    async fn list_security_events(
+        &self,
+        request: Request<ListSecurityEventsRequest>,
+    ) -> Result<Response<ListSecurityEventsResponse>, Status> {
+        handlers::security_events::list(self, request).await
+    }
+
+    async fn verify_second_factor(
+        &self,
+        request: Request<VerifySecondFactorRequest>,
+    ) -> Result<Response<VerifySecondFactorResponse>, Status> {
+        handlers::mfa::verify_second_factor(self, request).await
+    }
+
+    async fn begin_totp_enrollment(
+        &self,
+        request: Request<BeginTotpEnrollmentRequest>,
+    ) -> Result<Response<BeginTotpEnrollmentResponse>, Status> {
+        handlers::mfa::begin_totp_enrollment(self, request).await
+    }
+
+    async fn confirm_totp_enrollment(
+        &self,
+        request: Request<ConfirmTotpEnrollmentRequest>,
+    ) -> Result<Response<ConfirmTotpEnrollmentResponse>, Status> {
+        handlers::mfa::confirm_totp_enrollment(self, request).await
+    }
+
+    async fn disable_totp(
+        &self,
+        request: Request<DisableTotpRequest>,
+    ) -> Result<Response<DisableTotpResponse>, Status> {
+        handlers::mfa::disable_totp(self, request).await
+    }
+
     async fn health(
         &self,
         _request: Request<HealthRequest>,
@@ -221,16 +356,92 @@ async fn main() -> Result<()> {
     // Initialize database connection
     let db = db::DatabaseClient::new(config.database.tikv_pd_endpoints.clone()).await?;
 
-    // Load JWT secret from environment or config
-    let jwt_secret = std::env::var("JWT_SECRET")
-        .unwrap_or_else(|_| "development-secret-change-in-production".to_string());
+    // Older versions kept one-time pre-keys in the raw keyspace and never consumed them
+    match db.migrate_legacy_one_time_pre_keys().await {
+        Ok(0) => {}
+        Ok(migrated) => tracing::info!("Migrated {} legacy one-time pre-keys", migrated),
+        Err(e) => tracing::warn!("Failed to migrate legacy one-time pre-keys: {}", e),
+    }
+
+    // Load the token signing master key (hex-encoded 32-byte seed)
+    let jwt_master_seed = match std::env::var("JWT_SIGNING_KEY") {
+        Ok(seed) => parse_jwt_seed("JWT_SIGNING_KEY", &seed)?,
+        Err(_) => {
+            return Err(anyhow::anyhow!(
+                "JWT_SIGNING_KEY not set - refusing to start with an ephemeral token signing key"
+            ));
+        }
+    };
+
+    let rotation_period_secs = match std::env::var("JWT_KEY_ROTATION_DAYS") {
+        Ok(days) => days
+            .parse::<i64>()
+            .map_err(|e| anyhow::anyhow!("Invalid JWT_KEY_ROTATION_DAYS: {}", e))?
+            * 24 * 60 * 60,
+        Err(_) => signing_keys::DEFAULT_ROTATION_PERIOD_SECS,
+    };
 
-    if jwt_secret == "development-secret-change-in-production" {
-        tracing::warn!("Using default JWT secret - DO NOT USE IN PRODUCTION");
+    // Master seeds being phased in or out only publish verification keys
+    let mut jwt_verification_seeds = Vec::new();
+    for name in ["JWT_SIGNING_KEY_PREVIOUS", "JWT_SIGNING_KEY_NEXT"] {
+        if let Ok(seed) = std::env::var(name) {
+            jwt_verification_seeds.push(parse_jwt_seed(name, &seed)?);
+        }
     }
 
+    let jwt_keys = Arc::new(
+        JwtKeys::new(jwt_master_seed, rotation_period_secs)?.with_verification_seeds(jwt_verification_seeds),
+    );
+
+    // Load the sender certificate signing key (hex-encoded Ed25519 seed)
+    let sender_certificate_key = match std::env::var("SENDER_CERTIFICATE_KEY") {
+        Ok(seed) => {
+            let seed = hex::decode(seed.trim())
+                .map_err(|e| anyhow::anyhow!("Invalid SENDER_CERTIFICATE_KEY: {}", e))?;
+            IdentityKeyPair::from_secret_bytes(&seed)
+                .map_err(|e| anyhow::anyhow!("Invalid SENDER_CERTIFICATE_KEY: {}", e))?
+        }
+        Err(_) => {
+            tracing::warn!("SENDER_CERTIFICATE_KEY not set - using an ephemeral key, sender certificates will not survive restarts");
+            IdentityKeyPair::generate()
+                .map_err(|e| anyhow::anyhow!("Failed to generate sender certificate key: {}", e))?
+        }
+    };
+
+    // Connect to NATS for revocation broadcasts; TiKV stays the source of truth
+    let nats = match async_nats::connect(&config.messaging.nats_url).await {
+        Ok(client) => Some(client),
+        Err(e) => {
+            tracing::warn!("Failed to connect to NATS, revocations will only propagate through TiKV: {}", e);
+            None
+        }
+    };
+
+    revocation::spawn_listener(db.clone(), nats.clone());
+
+    // Publish the token verification keys before issuing any tokens
+    let published = signing_keys::publish(&jwt_keys, &db, nats.as_ref()).await?;
+    signing_keys::spawn_publisher(jwt_keys.clone(), db.clone(), nats.clone(), published);
+
+    let jwks_port = std::env::var("JWKS_HTTP_PORT").unwrap_or_else(|_| "8080".to_string());
+    let jwks_addr: std::net::SocketAddr = format!("{}:{}", config.host, jwks_port).parse()?;
+    let http_keys = jwt_keys.clone();
+    tokio::spawn(async move {
+        if let Err(e) = jwks_http::serve(jwks_addr, http_keys).await {
+            tracing::error!("JWKS HTTP server failed: {}", e);
+        }
+    });
+
+    // Addresses or CIDR networks of the proxies in front of the service
+    let trusted_proxies = match std::env::var("TRUSTED_PROXIES") {
+        Ok(proxies) => login_throttle::TrustedProxy::parse_list(&proxies)
+            .map_err(|e| anyhow::anyhow!("Invalid TRUSTED_PROXIES: {}", e))?,
+        Err(_) => Vec::new(),
+    };
+
     // Create service instance
-    let auth_service = AuthServiceImpl::new(db, jwt_secret);
+    let auth_service = AuthServiceImpl::new(db, jwt_keys, sender_certificate_key, nats)
+        .with_trusted_proxies(trusted_proxies);
 
     // Build gRPC server
     let addr = format!("{}:{}", config.host, config.port).parse()?;
@@ -247,3 +458,13 @@ async fn main() -> Result<()> {
 
     Ok(())
 }
+
+/// Parse a hex-encoded 32-byte token signing master seed from the variable `name`
+fn parse_jwt_seed(name: &str, value: &str) -> Result<Zeroizing<[u8; 32]>> {
+    let seed = Zeroizing::new(hex::decode(value.trim()).map_err(|e| anyhow::anyhow!("Invalid {}: {}", name, e))?);
+    let seed: [u8; 32] = seed
+        .as_slice()
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("Invalid {}: expected 32 bytes", name))?;
+    Ok(Zeroizing::new(seed))
+}
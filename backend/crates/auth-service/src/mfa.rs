@@ -0,0 +1,244 @@
+/// Two-factor authentication
+///
+/// TOTP (RFC 6238, HMAC-SHA1, 6 digits, 30 second steps) with single-use
+/// recovery codes. Accepted TOTP steps are remembered so each code works once,
+/// and recovery codes are stored as Argon2 hashes and deleted when used.
+///
+/// Accounts with a second factor log in in two steps: `Login` checks the
+/// password and returns an opaque challenge token, and `VerifySecondFactor`
+/// redeems it with a code. The challenge is a random token rather than a JWT,
+/// so it can never be mistaken for an access token by another service.
+
+use anyhow::{anyhow, Result};
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use data_encoding::BASE32_NOPAD;
+use guardyn_crypto::secret::ct_eq;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+/// TOTP time step
+pub const TOTP_STEP_SECS: u64 = 30;
+
+/// Digits of a TOTP code
+const TOTP_DIGITS: u32 = 6;
+
+/// Steps before and after the current one that are still accepted (clock drift)
+const TOTP_SKEW_STEPS: u64 = 1;
+
+/// Length of a TOTP secret (RFC 4226 recommends 160 bits)
+const TOTP_SECRET_BYTES: usize = 20;
+
+/// Name shown by authenticator apps
+const TOTP_ISSUER: &str = "Guardyn";
+
+/// Recovery codes handed out per enrolment
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+/// Random bytes per recovery code (16 base32 characters)
+const RECOVERY_CODE_BYTES: usize = 10;
+
+/// Lifetime of a login challenge
+pub const CHALLENGE_TTL_SECS: i64 = 5 * 60;
+
+/// Wrong codes a login challenge survives
+pub const MAX_CHALLENGE_ATTEMPTS: u32 = 5;
+
+/// Time to confirm an enrolment with a first code
+pub const ENROLLMENT_TTL_SECS: i64 = 10 * 60;
+
+/// Generate a new TOTP secret
+pub fn generate_totp_secret() -> Zeroizing<Vec<u8>> {
+    let mut secret = Zeroizing::new(vec![0u8; TOTP_SECRET_BYTES]);
+    rand::thread_rng().fill_bytes(&mut secret);
+    secret
+}
+
+/// Secret in the base32 form authenticator apps accept
+pub fn encode_totp_secret(secret: &[u8]) -> String {
+    BASE32_NOPAD.encode(secret)
+}
+
+/// `otpauth://` URI for enrolling the secret by QR code
+pub fn provisioning_uri(username: &str, secret: &[u8]) -> String {
+    format!(
+        "otpauth://totp/{issuer}:{username}?secret={secret}&issuer={issuer}&algorithm=SHA1&digits={digits}&period={period}",
+        issuer = TOTP_ISSUER,
+        username = username,
+        secret = encode_totp_secret(secret),
+        digits = TOTP_DIGITS,
+        period = TOTP_STEP_SECS,
+    )
+}
+
+/// TOTP code of a time step (HOTP of the step counter, RFC 4226)
+pub fn totp_code(secret: &[u8], step: u64) -> String {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(&step.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    // Dynamic truncation
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+
+    format!("{:0width$}", binary % 10u32.pow(TOTP_DIGITS), width = TOTP_DIGITS as usize)
+}
+
+/// Check a TOTP code at Unix time `now`, returning the step it matched
+///
+/// Steps up to and including `last_used_step` are rejected, so a code (or an
+/// older one) can't be replayed.
+pub fn verify_totp(secret: &[u8], code: &str, now: u64, last_used_step: u64) -> Option<u64> {
+    let code = code.trim();
+    if code.len() != TOTP_DIGITS as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let current = now / TOTP_STEP_SECS;
+    (current.saturating_sub(TOTP_SKEW_STEPS)..=current + TOTP_SKEW_STEPS)
+        .filter(|step| *step > last_used_step)
+        .find(|step| ct_eq(totp_code(secret, *step).as_bytes(), code.as_bytes()))
+}
+
+/// Generate a set of recovery codes, formatted `xxxx-xxxx-xxxx-xxxx`
+pub fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let mut bytes = Zeroizing::new([0u8; RECOVERY_CODE_BYTES]);
+            rand::thread_rng().fill_bytes(bytes.as_mut_slice());
+            let encoded = BASE32_NOPAD.encode(bytes.as_slice()).to_ascii_lowercase();
+            encoded
+                .as_bytes()
+                .chunks(4)
+                .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+                .collect::<Vec<_>>()
+                .join("-")
+        })
+        .collect()
+}
+
+/// Recovery code as hashed: lowercase without separators
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Argon2 hash of a recovery code
+pub fn hash_recovery_code(code: &str) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(normalize_recovery_code(code).as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| anyhow!("Failed to hash recovery code: {}", e))
+}
+
+/// Index of the hash `code` matches
+pub fn find_recovery_code(code: &str, hashes: &[String]) -> Option<usize> {
+    let code = normalize_recovery_code(code);
+    if code.is_empty() {
+        return None;
+    }
+
+    hashes.iter().position(|hash| {
+        PasswordHash::new(hash)
+            .map(|parsed| Argon2::default().verify_password(code.as_bytes(), &parsed).is_ok())
+            .unwrap_or(false)
+    })
+}
+
+/// Generate a login challenge token
+pub fn generate_challenge_token() -> String {
+    let mut bytes = Zeroizing::new([0u8; 32]);
+    rand::thread_rng().fill_bytes(bytes.as_mut_slice());
+    hex::encode(bytes.as_slice())
+}
+
+/// SHA-256 (hex) of a challenge token, the key its login is stored under
+pub fn challenge_hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// RFC 6238 Appendix B test secret (SHA-1)
+    const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn test_rfc6238_vectors() {
+        // Appendix B lists 8-digit codes; the 6-digit code is their last six digits
+        for (time, expected) in [
+            (59u64, "287082"),
+            (1_111_111_109, "081804"),
+            (1_111_111_111, "050471"),
+            (1_234_567_890, "005924"),
+            (2_000_000_000, "279037"),
+            (20_000_000_000, "353130"),
+        ] {
+            assert_eq!(totp_code(RFC_SECRET, time / TOTP_STEP_SECS), expected);
+        }
+    }
+
+    #[test]
+    fn test_verify_totp_window_and_replay() {
+        let now = 1_234_567_890;
+        let step = now / TOTP_STEP_SECS;
+        let code = totp_code(RFC_SECRET, step);
+
+        assert_eq!(verify_totp(RFC_SECRET, &code, now, 0), Some(step));
+        assert_eq!(verify_totp(RFC_SECRET, &code, now + TOTP_STEP_SECS, 0), Some(step));
+        assert_eq!(verify_totp(RFC_SECRET, &code, now + 2 * TOTP_STEP_SECS, 0), None);
+
+        // A used code can't be replayed, and neither can an older one
+        assert_eq!(verify_totp(RFC_SECRET, &code, now, step), None);
+        let previous = totp_code(RFC_SECRET, step - 1);
+        assert_eq!(verify_totp(RFC_SECRET, &previous, now, step), None);
+
+        assert_eq!(verify_totp(RFC_SECRET, "12345", now, 0), None);
+        assert_eq!(verify_totp(RFC_SECRET, "abcdef", now, 0), None);
+    }
+
+    #[test]
+    fn test_recovery_codes() {
+        let codes = generate_recovery_codes();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+        assert_eq!(codes[0].len(), 19);
+
+        let hashes: Vec<String> = codes[..2].iter().map(|code| hash_recovery_code(code).unwrap()).collect();
+        assert!(!hashes[0].contains(&codes[0]));
+        assert_eq!(find_recovery_code(&codes[1], &hashes), Some(1));
+        assert_eq!(find_recovery_code(&codes[0].to_uppercase().replace('-', " "), &hashes), Some(0));
+        assert_eq!(find_recovery_code(&codes[2], &hashes), None);
+        assert_eq!(find_recovery_code("", &hashes), None);
+    }
+
+    #[test]
+    fn test_provisioning_uri() {
+        let uri = provisioning_uri("alice", RFC_SECRET);
+        assert_eq!(
+            uri,
+            "otpauth://totp/Guardyn:alice?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&issuer=Guardyn&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+
+    #[test]
+    fn test_challenge_tokens() {
+        let token = generate_challenge_token();
+        assert_eq!(token.len(), 64);
+        assert_ne!(token, generate_challenge_token());
+        assert_ne!(challenge_hash(&token), token);
+    }
+}
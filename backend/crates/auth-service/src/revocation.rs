@@ -0,0 +1,65 @@
+/// Session revocation
+///
+/// Revoking a session deletes it, so its refresh token stops working, and
+/// records its ID in TiKV and on NATS, so access tokens that were already
+/// handed out are rejected by every service until they expire.
+
+use crate::db::DatabaseClient;
+use crate::jwt::ACCESS_TOKEN_TTL_SECS;
+use crate::AuthServiceImpl;
+use anyhow::Result;
+use guardyn_common::listener;
+use guardyn_common::revocation::{revocations, Revocation, REVOCATION_SUBJECT};
+
+/// Revoke a session: persist, apply locally and broadcast
+pub async fn revoke_session(service: &AuthServiceImpl, user_id: &str, session_id: &str) -> Result<()> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs() as i64;
+
+    let revocation = Revocation {
+        id: session_id.to_string(),
+        user_id: user_id.to_string(),
+        // Refresh tokens die with the session, so only access tokens need covering
+        expires_at: now + ACCESS_TOKEN_TTL_SECS,
+    };
+
+    service.db.store_revocation(&revocation).await?;
+    revocations().insert(&revocation);
+
+    match &service.nats {
+        Some(client) => {
+            let payload = serde_json::to_vec(&revocation)?;
+            if let Err(e) = client.publish(REVOCATION_SUBJECT, payload.into()).await {
+                tracing::warn!("Failed to publish revocation of session {}: {}", session_id, e);
+            }
+        }
+        None => tracing::warn!("NATS unavailable - revocation of session {} relies on TiKV sync", session_id),
+    }
+
+    tracing::info!("Revoked session {} of user {}", session_id, user_id);
+    Ok(())
+}
+
+/// Load active revocations from TiKV into the local list
+pub async fn sync(db: &DatabaseClient) -> Result<usize> {
+    let active = db.load_revocations().await?;
+    for revocation in &active {
+        revocations().insert(revocation);
+    }
+    Ok(active.len())
+}
+
+/// Keep the local revocation list up to date from NATS and TiKV
+pub fn spawn_listener(db: DatabaseClient, nats: Option<async_nats::Client>) {
+    listener::spawn_listener(
+        "revocations",
+        nats,
+        REVOCATION_SUBJECT,
+        |revocation: Revocation| revocations().insert(&revocation),
+        move || {
+            let db = db.clone();
+            async move { sync(&db).await }
+        },
+    );
+}
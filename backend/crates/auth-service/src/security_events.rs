@@ -0,0 +1,47 @@
+/// Security events
+///
+/// Events that a user should know about, such as a stolen refresh token being
+/// replayed, are stored with the account (listed by `ListSecurityEvents`) and
+/// published on `auth.security_events.{user_id}` for clients that are online.
+
+use crate::db::{SecurityEvent, SecurityEventKind};
+use crate::AuthServiceImpl;
+use anyhow::Result;
+
+/// NATS subject prefix for security events, followed by the user ID
+pub const SECURITY_EVENT_SUBJECT_PREFIX: &str = "auth.security_events";
+
+/// Record a security event on a user's account and notify the user
+pub async fn record(
+    service: &AuthServiceImpl,
+    user_id: &str,
+    device_id: &str,
+    kind: SecurityEventKind,
+    description: String,
+) -> Result<SecurityEvent> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs() as i64;
+
+    let event = SecurityEvent {
+        event_id: uuid::Uuid::new_v4().to_string(),
+        user_id: user_id.to_string(),
+        device_id: device_id.to_string(),
+        kind,
+        description,
+        created_at: now,
+    };
+
+    service.db.store_security_event(&event).await?;
+
+    if let Some(client) = &service.nats {
+        let subject = format!("{}.{}", SECURITY_EVENT_SUBJECT_PREFIX, user_id);
+        let payload = serde_json::to_vec(&event)?;
+        if let Err(e) = client.publish(subject, payload.into()).await {
+            tracing::warn!("Failed to publish security event {}: {}", event.event_id, e);
+        }
+    }
+
+    tracing::warn!("Security event {:?} on account {}: {}", kind, user_id, event.description);
+    Ok(event)
+}
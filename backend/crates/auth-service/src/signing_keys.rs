@@ -0,0 +1,249 @@
+/// Token signing keys
+///
+/// Tokens are signed with Ed25519 keys derived from a master seed
+/// (`JWT_SIGNING_KEY`), one per rotation period. Every replica derives the
+/// same keys, so no private key material is stored or shared. The published
+/// key set includes the next period's key before it is used and keeps
+/// retired keys until the last refresh token they signed has expired.
+///
+/// The master seed itself is changed with an overlap window: the new seed is
+/// first deployed as `JWT_SIGNING_KEY_NEXT`, so its keys are published while
+/// replicas still sign with the old one; it then becomes `JWT_SIGNING_KEY`
+/// with the old seed as `JWT_SIGNING_KEY_PREVIOUS`, which is dropped once the
+/// refresh tokens it signed have expired. Seeds given this way only add
+/// verification keys and never sign.
+
+use crate::db::DatabaseClient;
+use crate::jwt::REFRESH_TOKEN_TTL_SECS;
+use anyhow::{bail, Result};
+use guardyn_common::jwks::{key_set, JwkSet, SigningKey, JWKS_SUBJECT};
+use hkdf::Hkdf;
+use sha2::Sha256;
+use std::sync::Arc;
+use std::time::Duration;
+use zeroize::Zeroizing;
+
+/// Default rotation period
+pub const DEFAULT_ROTATION_PERIOD_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// How often the published key set is checked for a rotation
+const PUBLISH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// HKDF salt for signing key derivation
+const KEY_DERIVATION_SALT: &[u8] = b"guardyn-jwt-signing-key-v1";
+
+/// Master seed and rotation schedule of the token signing keys
+pub struct JwtKeys {
+    master_seed: Zeroizing<[u8; 32]>,
+    /// Previous or next master seeds whose keys are published but never sign
+    verification_seeds: Vec<Zeroizing<[u8; 32]>>,
+    rotation_period_secs: i64,
+}
+
+impl JwtKeys {
+    pub fn new(master_seed: Zeroizing<[u8; 32]>, rotation_period_secs: i64) -> Result<Self> {
+        if rotation_period_secs <= 0 {
+            bail!("Key rotation period must be positive");
+        }
+        Ok(Self {
+            master_seed,
+            verification_seeds: Vec::new(),
+            rotation_period_secs,
+        })
+    }
+
+    /// Also publish the keys of other master seeds, for changing the master seed
+    pub fn with_verification_seeds(mut self, seeds: Vec<Zeroizing<[u8; 32]>>) -> Self {
+        self.verification_seeds = seeds;
+        self
+    }
+
+    /// Key that signs new tokens
+    pub fn signing_key(&self) -> Result<SigningKey> {
+        Ok(self.key_for_period(self.period_at(now()?)))
+    }
+
+    /// Verification keys to publish at `at` (Unix time)
+    pub fn jwks_at(&self, at: i64) -> JwkSet {
+        let current = self.period_at(at);
+        let oldest = self.period_at(at - REFRESH_TOKEN_TTL_SECS);
+
+        JwkSet {
+            keys: std::iter::once(&self.master_seed)
+                .chain(&self.verification_seeds)
+                .flat_map(|seed| {
+                    (oldest..=current + 1)
+                        .rev()
+                        .map(move |period| derive_key(seed, period).jwk())
+                })
+                .collect(),
+        }
+    }
+
+    /// Verification keys to publish now
+    pub fn jwks(&self) -> Result<JwkSet> {
+        Ok(self.jwks_at(now()?))
+    }
+
+    fn period_at(&self, at: i64) -> i64 {
+        at.div_euclid(self.rotation_period_secs)
+    }
+
+    fn key_for_period(&self, period: i64) -> SigningKey {
+        derive_key(&self.master_seed, period)
+    }
+}
+
+/// Signing key of `period` under `master_seed`
+fn derive_key(master_seed: &[u8; 32], period: i64) -> SigningKey {
+    let hkdf = Hkdf::<Sha256>::new(Some(KEY_DERIVATION_SALT), master_seed);
+    let mut seed = Zeroizing::new([0u8; 32]);
+    hkdf.expand(&period.to_be_bytes(), seed.as_mut_slice())
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    SigningKey::from_seed(&seed)
+}
+
+impl std::fmt::Debug for JwtKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JwtKeys")
+            .field("master_seed", &"<redacted>")
+            .field("verification_seeds", &self.verification_seeds.len())
+            .field("rotation_period_secs", &self.rotation_period_secs)
+            .finish()
+    }
+}
+
+/// Install the current key set locally, store it in TiKV and announce it
+///
+/// Services reload the set from TiKV on the announcement; the payload is
+/// informational only.
+pub async fn publish(keys: &JwtKeys, db: &DatabaseClient, nats: Option<&async_nats::Client>) -> Result<JwkSet> {
+    let jwks = keys.jwks()?;
+    key_set().replace(&jwks);
+    db.store_jwks(&jwks).await?;
+
+    if let Some(client) = nats {
+        let payload = serde_json::to_vec(&jwks)?;
+        if let Err(e) = client.publish(JWKS_SUBJECT, payload.into()).await {
+            tracing::warn!("Failed to publish token signing keys: {}", e);
+        }
+    }
+
+    tracing::info!("Published {} token signing keys", jwks.keys.len());
+    Ok(jwks)
+}
+
+/// Republish the key set whenever a rotation changes it
+pub fn spawn_publisher(
+    keys: Arc<JwtKeys>,
+    db: DatabaseClient,
+    nats: Option<async_nats::Client>,
+    mut published: JwkSet,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PUBLISH_INTERVAL);
+        loop {
+            interval.tick().await;
+            match keys.jwks() {
+                Ok(jwks) if jwks == published => continue,
+                Ok(_) => {}
+                Err(e) => {
+                    tracing::warn!("Failed to compute token signing keys: {}", e);
+                    continue;
+                }
+            }
+
+            match publish(&keys, &db, nats.as_ref()).await {
+                Ok(jwks) => published = jwks,
+                Err(e) => tracing::warn!("Failed to publish rotated token signing keys: {}", e),
+            }
+        }
+    });
+}
+
+fn now() -> Result<i64> {
+    Ok(std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PERIOD: i64 = DEFAULT_ROTATION_PERIOD_SECS;
+
+    fn keys(master: u8) -> JwtKeys {
+        JwtKeys::new(Zeroizing::new([master; 32]), PERIOD).unwrap()
+    }
+
+    #[test]
+    fn test_replicas_derive_same_keys() {
+        assert_eq!(keys(1).signing_key().unwrap().kid(), keys(1).signing_key().unwrap().kid());
+        assert_ne!(keys(1).signing_key().unwrap().kid(), keys(2).signing_key().unwrap().kid());
+    }
+
+    #[test]
+    fn test_rotation_overlap() {
+        let keys = keys(1);
+        let at = 1_000 * PERIOD + 10;
+        let current = keys.key_for_period(1_000);
+        let next = keys.key_for_period(1_001);
+        let jwks = keys.jwks_at(at);
+
+        // The next key is published a full period before it signs anything
+        assert!(jwks.find(current.kid()).is_some());
+        assert!(jwks.find(next.kid()).is_some());
+
+        // A retired key stays published until the last refresh token it signed expires
+        let last_signed_at = 1_001 * PERIOD - 1;
+        let still_valid = keys.jwks_at(last_signed_at + REFRESH_TOKEN_TTL_SECS);
+        assert!(still_valid.find(current.kid()).is_some());
+        let expired = keys.jwks_at(last_signed_at + REFRESH_TOKEN_TTL_SECS + 1);
+        assert!(expired.find(current.kid()).is_none());
+    }
+
+    #[test]
+    fn test_signing_key_rotates_each_period() {
+        let keys = keys(1);
+        let start = 1_000 * PERIOD;
+        let signing_kid = |at: i64| keys.key_for_period(keys.period_at(at)).kid().to_string();
+
+        assert_eq!(signing_kid(start), signing_kid(start + PERIOD - 1));
+        assert_ne!(signing_kid(start), signing_kid(start + PERIOD));
+        for at in [start, start + PERIOD / 2, start + PERIOD - 1] {
+            assert!(keys.jwks_at(at).find(&signing_kid(at)).is_some());
+        }
+    }
+
+    #[test]
+    fn test_master_seed_change_overlap() {
+        let at = 1_000 * PERIOD + 10;
+        let old = keys(1);
+        let new = keys(2);
+
+        // Replicas still on the old seed publish the new seed's keys ahead of the switch
+        let before = keys(1).with_verification_seeds(vec![Zeroizing::new([2; 32])]);
+        assert_eq!(before.signing_key().unwrap().kid(), old.signing_key().unwrap().kid());
+        assert!(before.jwks_at(at).find(new.key_for_period(1_000).kid()).is_some());
+
+        // After the switch, tokens signed with the old seed still verify until they expire
+        let after = keys(2).with_verification_seeds(vec![Zeroizing::new([1; 32])]);
+        assert_eq!(after.signing_key().unwrap().kid(), new.signing_key().unwrap().kid());
+        let jwks = after.jwks_at(at);
+        assert!(jwks.find(old.key_for_period(1_000).kid()).is_some());
+        assert!(jwks.find(old.key_for_period(1_000 - REFRESH_TOKEN_TTL_SECS / PERIOD).kid()).is_some());
+    }
+
+    #[test]
+    fn test_invalid_rotation_period() {
+        assert!(JwtKeys::new(Zeroizing::new([1; 32]), 0).is_err());
+    }
+
+    #[test]
+    fn test_debug_redacts_master_seed() {
+        let debug = format!("{:?}", keys(0xab));
+        assert!(debug.contains("redacted"));
+        assert!(!debug.contains("171, 171"));
+    }
+}
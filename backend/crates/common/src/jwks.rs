@@ -0,0 +1,269 @@
+/// JSON Web Key Sets for access token verification
+///
+/// auth-service signs tokens with Ed25519 keys named by the `kid` header and
+/// publishes the public halves as a JWKS document: stored in TiKV under
+/// `JWKS_KEY`, announced on `JWKS_SUBJECT` and served over gRPC and HTTP.
+/// Services reload the document from TiKV on an announcement rather than
+/// trusting the NATS message, which anyone able to publish could forge.
+/// Every service keeps the current document in the process-wide [`key_set`]
+/// and verifies tokens against it, so only auth-service holds signing keys.
+use anyhow::{anyhow, bail, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ed25519_dalek::Signer;
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, KeyAlgorithm, OctetKeyPairParameters, OctetKeyPairType,
+    PublicKeyUse,
+};
+use jsonwebtoken::{Algorithm, DecodingKey, Header, Validation};
+use serde::{de::DeserializeOwned, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+
+pub use jsonwebtoken::jwk::{Jwk, JwkSet};
+
+/// NATS subject announcing that the JWKS document in TiKV changed
+pub const JWKS_SUBJECT: &str = "auth.jwks";
+
+/// TiKV key of the current JWKS document
+pub const JWKS_KEY: &str = "/jwks/current";
+
+/// Ed25519 key signing access and refresh tokens
+pub struct SigningKey {
+    kid: String,
+    key: ed25519_dalek::SigningKey,
+}
+
+impl SigningKey {
+    /// Create a signing key from a 32-byte Ed25519 seed
+    ///
+    /// The key ID is the RFC 7638 thumbprint of the public key.
+    pub fn from_seed(seed: &[u8; 32]) -> Self {
+        let key = ed25519_dalek::SigningKey::from_bytes(seed);
+        let x = URL_SAFE_NO_PAD.encode(key.verifying_key().as_bytes());
+        let thumbprint_input = format!(r#"{{"crv":"Ed25519","kty":"OKP","x":"{}"}}"#, x);
+        let kid = URL_SAFE_NO_PAD.encode(Sha256::digest(thumbprint_input.as_bytes()));
+        Self { kid, key }
+    }
+
+    pub fn kid(&self) -> &str {
+        &self.kid
+    }
+
+    /// Public key as a JWK
+    pub fn jwk(&self) -> Jwk {
+        Jwk {
+            common: CommonParameters {
+                public_key_use: Some(PublicKeyUse::Signature),
+                key_algorithm: Some(KeyAlgorithm::EdDSA),
+                key_id: Some(self.kid.clone()),
+                ..Default::default()
+            },
+            algorithm: AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                key_type: OctetKeyPairType::OctetKeyPair,
+                curve: EllipticCurve::Ed25519,
+                x: URL_SAFE_NO_PAD.encode(self.key.verifying_key().as_bytes()),
+            }),
+        }
+    }
+
+    /// Sign `claims` as an EdDSA JWT carrying this key's ID
+    pub fn sign<T: Serialize>(&self, claims: &T) -> Result<String> {
+        let mut header = Header::new(Algorithm::EdDSA);
+        header.kid = Some(self.kid.clone());
+
+        let signing_input = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(serde_json::to_vec(&header)?),
+            URL_SAFE_NO_PAD.encode(serde_json::to_vec(claims)?),
+        );
+        let signature = self.key.sign(signing_input.as_bytes());
+
+        Ok(format!("{}.{}", signing_input, URL_SAFE_NO_PAD.encode(signature.to_bytes())))
+    }
+}
+
+impl std::fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SigningKey")
+            .field("kid", &self.kid)
+            .field("key", &"<redacted>")
+            .finish()
+    }
+}
+
+/// Verification keys by key ID
+#[derive(Default)]
+pub struct KeySet {
+    keys: RwLock<HashMap<String, DecodingKey>>,
+}
+
+impl KeySet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the keys with the Ed25519 keys of `jwks`, returning how many were accepted
+    pub fn replace(&self, jwks: &JwkSet) -> usize {
+        let keys: HashMap<String, DecodingKey> = jwks
+            .keys
+            .iter()
+            .filter_map(|jwk| {
+                let kid = jwk.common.key_id.clone()?;
+                match &jwk.algorithm {
+                    AlgorithmParameters::OctetKeyPair(params) if params.curve == EllipticCurve::Ed25519 => {
+                        DecodingKey::from_ed_components(&params.x).ok().map(|key| (kid, key))
+                    }
+                    _ => None,
+                }
+            })
+            .collect();
+
+        let accepted = keys.len();
+        *self.keys.write().unwrap_or_else(|e| e.into_inner()) = keys;
+        accepted
+    }
+
+    /// Whether a key with this ID is known
+    pub fn contains(&self, kid: &str) -> bool {
+        let keys = self.keys.read().unwrap_or_else(|e| e.into_inner());
+        keys.contains_key(kid)
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.read().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Verify an EdDSA token against the key named by its `kid` header and return its claims
+    ///
+    /// Also checks `exp`.
+    pub fn verify<T: DeserializeOwned>(&self, token: &str) -> Result<T> {
+        let header = jsonwebtoken::decode_header(token)?;
+        if header.alg != Algorithm::EdDSA {
+            bail!("Unsupported token algorithm {:?}", header.alg);
+        }
+        let kid = header.kid.ok_or_else(|| anyhow!("Token has no key ID"))?;
+
+        let key = {
+            let keys = self.keys.read().unwrap_or_else(|e| e.into_inner());
+            keys.get(&kid).cloned().ok_or_else(|| anyhow!("Unknown signing key {}", kid))?
+        };
+
+        let token_data = jsonwebtoken::decode::<T>(token, &key, &Validation::new(Algorithm::EdDSA))?;
+        Ok(token_data.claims)
+    }
+}
+
+/// Verification keys of this process
+pub fn key_set() -> &'static KeySet {
+    static KEY_SET: OnceLock<KeySet> = OnceLock::new();
+    KEY_SET.get_or_init(KeySet::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct TestClaims {
+        sub: String,
+        exp: i64,
+    }
+
+    fn claims() -> TestClaims {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        TestClaims { sub: "user123".to_string(), exp: now + 60 }
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let key = SigningKey::from_seed(&[7u8; 32]);
+        let key_set = KeySet::new();
+        assert_eq!(key_set.replace(&JwkSet { keys: vec![key.jwk()] }), 1);
+        assert!(key_set.contains(key.kid()));
+
+        let token = key.sign(&claims()).unwrap();
+        assert_eq!(key_set.verify::<TestClaims>(&token).unwrap(), claims());
+    }
+
+    #[test]
+    fn test_unknown_key_rejected() {
+        let key = SigningKey::from_seed(&[7u8; 32]);
+        let other = SigningKey::from_seed(&[8u8; 32]);
+        let key_set = KeySet::new();
+        key_set.replace(&JwkSet { keys: vec![other.jwk()] });
+
+        let token = key.sign(&claims()).unwrap();
+        assert!(key_set.verify::<TestClaims>(&token).is_err());
+
+        // Replacing the set drops keys that are no longer published
+        key_set.replace(&JwkSet { keys: vec![key.jwk()] });
+        assert!(key_set.verify::<TestClaims>(&token).is_ok());
+        key_set.replace(&JwkSet { keys: vec![other.jwk()] });
+        assert!(key_set.verify::<TestClaims>(&token).is_err());
+    }
+
+    #[test]
+    fn test_forged_token_rejected() {
+        let key = SigningKey::from_seed(&[7u8; 32]);
+        let key_set = KeySet::new();
+        key_set.replace(&JwkSet { keys: vec![key.jwk()] });
+
+        // Claims swapped under the original signature
+        let token = key.sign(&claims()).unwrap();
+        let mut parts: Vec<&str> = token.split('.').collect();
+        let forged_claims = URL_SAFE_NO_PAD.encode(br#"{"sub":"admin","exp":99999999999}"#);
+        parts[1] = &forged_claims;
+        assert!(key_set.verify::<TestClaims>(&parts.join(".")).is_err());
+
+        // Symmetric tokens are never accepted
+        let mut header = Header::new(Algorithm::HS256);
+        header.kid = Some(key.kid().to_string());
+        let hs256 = jsonwebtoken::encode(
+            &header,
+            &claims(),
+            &jsonwebtoken::EncodingKey::from_secret(b"shared-secret"),
+        )
+        .unwrap();
+        assert!(key_set.verify::<TestClaims>(&hs256).is_err());
+    }
+
+    #[test]
+    fn test_expired_token_rejected() {
+        let key = SigningKey::from_seed(&[7u8; 32]);
+        let key_set = KeySet::new();
+        key_set.replace(&JwkSet { keys: vec![key.jwk()] });
+
+        let mut expired = claims();
+        expired.exp -= 3600;
+        assert!(key_set.verify::<TestClaims>(&key.sign(&expired).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_rfc8037_thumbprint() {
+        // RFC 8037 Appendix A.1 and A.3
+        let seed: [u8; 32] = URL_SAFE_NO_PAD
+            .decode("nWGxne_9WmC6hEr0kuwsxERJxWl7MmkZcDusAxyuf2A")
+            .unwrap()
+            .try_into()
+            .unwrap();
+        let key = SigningKey::from_seed(&seed);
+
+        assert_eq!(key.kid(), "kPrK_qmxVWaYVA9wwBF6Iuo3vVzz7TxHCTwXBygrS4k");
+        match key.jwk().algorithm {
+            AlgorithmParameters::OctetKeyPair(params) => {
+                assert_eq!(params.x, "11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo")
+            }
+            _ => panic!("expected an OKP key"),
+        }
+        assert!(!format!("{:?}", key).contains("nWGxne"));
+    }
+}
@@ -1,6 +1,9 @@
 /// Common utilities and shared types for Guardyn backend services
 pub mod config;
 pub mod error;
+pub mod jwks;
+pub mod listener;
 pub mod observability;
+pub mod revocation;
 
 pub use error::{Error, Result};
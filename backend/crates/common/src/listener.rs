@@ -0,0 +1,112 @@
+/// Process-wide state kept in sync with auth-service broadcasts
+///
+/// auth-service stores shared state (session revocations, token verification
+/// keys) in TiKV and publishes every update on NATS. Services apply the
+/// updates as they arrive, or reload from TiKV when a message only announces
+/// one, and reload the state from TiKV every [`RESYNC_INTERVAL`], so a missed
+/// broadcast, a NATS outage or a restart only delays an update.
+use futures::StreamExt;
+use serde::de::DeserializeOwned;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How often the state is reloaded from TiKV
+pub const RESYNC_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Apply updates published on `subject` and reload from TiKV periodically
+///
+/// Each message is decoded as a `T` and passed to `on_message`; malformed
+/// messages are logged and skipped. `resync` reloads the state from TiKV and
+/// returns the number of entries loaded. Without NATS only the periodic
+/// reload runs. `what` names the state in log messages.
+pub fn spawn_listener<T, H, R, Fut>(
+    what: &'static str,
+    nats: Option<async_nats::Client>,
+    subject: &'static str,
+    on_message: H,
+    resync: R,
+) where
+    T: DeserializeOwned,
+    H: Fn(T) + Send + 'static,
+    R: Fn() -> Fut + Send + 'static,
+    Fut: Future<Output = anyhow::Result<usize>> + Send,
+{
+    if let Some(client) = nats {
+        tokio::spawn(async move {
+            let mut subscriber = match client.subscribe(subject).await {
+                Ok(subscriber) => subscriber,
+                Err(e) => {
+                    tracing::error!("Failed to subscribe to {}: {}", what, e);
+                    return;
+                }
+            };
+
+            while let Some(message) = subscriber.next().await {
+                match serde_json::from_slice::<T>(&message.payload) {
+                    Ok(update) => on_message(update),
+                    Err(e) => tracing::warn!("Ignoring malformed message on {}: {}", subject, e),
+                }
+            }
+        });
+    }
+
+    spawn_resync(what, resync);
+}
+
+/// Reload from TiKV whenever a message arrives on `subject`, and periodically
+///
+/// For state whose broadcasts are not trusted on their own: message payloads
+/// are ignored and only TiKV, written by auth-service, is read.
+pub fn spawn_reload_listener<R, Fut>(
+    what: &'static str,
+    nats: Option<async_nats::Client>,
+    subject: &'static str,
+    resync: R,
+) where
+    R: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = anyhow::Result<usize>> + Send,
+{
+    let resync = Arc::new(resync);
+
+    if let Some(client) = nats {
+        let resync = resync.clone();
+        tokio::spawn(async move {
+            let mut subscriber = match client.subscribe(subject).await {
+                Ok(subscriber) => subscriber,
+                Err(e) => {
+                    tracing::error!("Failed to subscribe to {}: {}", what, e);
+                    return;
+                }
+            };
+
+            while subscriber.next().await.is_some() {
+                log_reload(what, resync().await);
+            }
+        });
+    }
+
+    spawn_resync(what, move || resync())
+}
+
+/// Reload from TiKV every [`RESYNC_INTERVAL`]
+fn spawn_resync<R, Fut>(what: &'static str, resync: R)
+where
+    R: Fn() -> Fut + Send + 'static,
+    Fut: Future<Output = anyhow::Result<usize>> + Send,
+{
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(RESYNC_INTERVAL);
+        loop {
+            interval.tick().await;
+            log_reload(what, resync().await);
+        }
+    });
+}
+
+fn log_reload(what: &'static str, result: anyhow::Result<usize>) {
+    match result {
+        Ok(loaded) => tracing::debug!("Reloaded {} {} from TiKV", loaded, what),
+        Err(e) => tracing::warn!("Failed to load {} from TiKV: {}", what, e),
+    }
+}
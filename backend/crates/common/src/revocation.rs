@@ -0,0 +1,156 @@
+/// Session revocation shared by auth-service and the services accepting its tokens
+///
+/// Access tokens are stateless JWTs, so logging out only takes effect once
+/// every service refuses them. auth-service stores each revocation in TiKV
+/// under `REVOCATION_PREFIX` and publishes it on `REVOCATION_SUBJECT`; every
+/// service keeps the entries in the process-wide [`revocations`] list and
+/// checks the `sid` and `jti` claims of incoming tokens against it.
+///
+/// An entry only has to outlive the tokens it covers, so it expires together
+/// with the last access token that could carry its ID.
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// NATS subject revocations are published on
+pub const REVOCATION_SUBJECT: &str = "auth.revocations";
+
+/// TiKV key prefix of stored revocations (`/revocations/{id}`)
+pub const REVOCATION_PREFIX: &str = "/revocations/";
+
+/// A revoked session (`sid` claim) or single token (`jti` claim)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Revocation {
+    pub id: String,
+    pub user_id: String,
+    /// Unix time after which no unexpired token can carry `id`
+    pub expires_at: i64,
+}
+
+impl Revocation {
+    /// TiKV key of this revocation
+    pub fn key(&self) -> String {
+        format!("{}{}", REVOCATION_PREFIX, self.id)
+    }
+
+    /// Whether every token covered by this revocation has expired
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= now()
+    }
+}
+
+/// In-memory set of active revocations
+#[derive(Debug, Default)]
+pub struct RevocationList {
+    entries: RwLock<HashMap<String, i64>>,
+}
+
+impl RevocationList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a revocation, dropping entries that have expired meanwhile
+    pub fn insert(&self, revocation: &Revocation) {
+        let now = now();
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+        entries.retain(|_, expires_at| *expires_at > now);
+        if revocation.expires_at > now {
+            let expires_at = entries.entry(revocation.id.clone()).or_insert(revocation.expires_at);
+            *expires_at = (*expires_at).max(revocation.expires_at);
+        }
+    }
+
+    /// Whether `id` (a session or token ID) is revoked
+    pub fn is_revoked(&self, id: &str) -> bool {
+        let entries = self.entries.read().unwrap_or_else(|e| e.into_inner());
+        entries.get(id).is_some_and(|expires_at| *expires_at > now())
+    }
+
+    /// Whether a token with these `sid` and `jti` claims is revoked
+    pub fn is_token_revoked(&self, session_id: Option<&str>, token_id: Option<&str>) -> bool {
+        session_id.is_some_and(|id| self.is_revoked(id)) || token_id.is_some_and(|id| self.is_revoked(id))
+    }
+
+    /// Number of active revocations
+    pub fn len(&self) -> usize {
+        let now = now();
+        let entries = self.entries.read().unwrap_or_else(|e| e.into_inner());
+        entries.values().filter(|expires_at| **expires_at > now).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Revocation list of this process
+pub fn revocations() -> &'static RevocationList {
+    static REVOCATIONS: OnceLock<RevocationList> = OnceLock::new();
+    REVOCATIONS.get_or_init(RevocationList::new)
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn revocation(id: &str, ttl: i64) -> Revocation {
+        Revocation {
+            id: id.to_string(),
+            user_id: "user".to_string(),
+            expires_at: now() + ttl,
+        }
+    }
+
+    #[test]
+    fn test_revoked_until_expiry() {
+        let list = RevocationList::new();
+        list.insert(&revocation("session-1", 60));
+        list.insert(&revocation("session-2", -1));
+
+        assert!(list.is_revoked("session-1"));
+        assert!(!list.is_revoked("session-2"));
+        assert!(!list.is_revoked("session-3"));
+        assert_eq!(list.len(), 1);
+    }
+
+    #[test]
+    fn test_token_revoked_by_session_or_token_id() {
+        let list = RevocationList::new();
+        list.insert(&revocation("session", 60));
+        list.insert(&revocation("token", 60));
+
+        assert!(list.is_token_revoked(Some("session"), Some("other")));
+        assert!(list.is_token_revoked(None, Some("token")));
+        assert!(!list.is_token_revoked(Some("other"), None));
+        assert!(!list.is_token_revoked(None, None));
+    }
+
+    #[test]
+    fn test_later_expiry_wins() {
+        let list = RevocationList::new();
+        let long = revocation("session", 120);
+        list.insert(&long);
+        list.insert(&revocation("session", 10));
+
+        let entries = list.entries.read().unwrap();
+        assert_eq!(entries.get("session"), Some(&long.expires_at));
+    }
+
+    #[test]
+    fn test_revocation_roundtrip() {
+        let revocation = revocation("session", 60);
+        let json = serde_json::to_vec(&revocation).unwrap();
+        assert_eq!(serde_json::from_slice::<Revocation>(&json).unwrap(), revocation);
+        assert_eq!(revocation.key(), "/revocations/session");
+        assert!(!revocation.is_expired());
+    }
+}
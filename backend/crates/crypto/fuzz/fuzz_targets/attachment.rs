@@ -0,0 +1,36 @@
+//! Attachment ciphertext layout and ranged decryption
+//!
+//! The first 16 bytes are the requested plaintext offset and length, the rest
+//! is the attachment ciphertext.
+#![no_main]
+
+use guardyn_crypto::attachment::{validate_attachment_layout, AttachmentDecryptor, AttachmentHeader};
+use libfuzzer_sys::fuzz_target;
+
+const KEY: [u8; 32] = [5u8; 32];
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 16 {
+        return;
+    }
+    let (range, ciphertext) = data.split_at(16);
+    let offset = u64::from_be_bytes(range[..8].try_into().unwrap());
+    let length = u64::from_be_bytes(range[8..].try_into().unwrap());
+
+    let _ = AttachmentHeader::parse(ciphertext);
+    if validate_attachment_layout(ciphertext).is_err() {
+        return;
+    }
+
+    let ciphertext_len = ciphertext.len() as u64;
+    let Ok(decryptor) = AttachmentDecryptor::new(&KEY, ciphertext, ciphertext_len) else {
+        return;
+    };
+    assert!(decryptor.plaintext_len() <= ciphertext_len);
+
+    if let Ok(range) = decryptor.ciphertext_range(offset, length) {
+        let end = range.offset.checked_add(range.length).unwrap();
+        assert!(end <= ciphertext_len);
+        let _ = decryptor.decrypt_range(&range, &ciphertext[range.offset as usize..end as usize]);
+    }
+});
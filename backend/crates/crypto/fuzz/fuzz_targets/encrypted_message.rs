@@ -0,0 +1,39 @@
+//! Double Ratchet message parsing and decryption
+//!
+//! Parses the input as both message formats and feeds whatever parses to a
+//! fresh receiving session. A rejected message must leave the session able to
+//! decrypt the next genuine one.
+#![no_main]
+
+use guardyn_crypto::double_ratchet::{DoubleRatchet, EncryptedMessage, HeaderEncryptedMessage};
+use libfuzzer_sys::fuzz_target;
+use x25519_dalek::{PublicKey, StaticSecret};
+
+const SHARED_SECRET: [u8; 32] = [7u8; 32];
+const ASSOCIATED_DATA: &[u8] = b"fuzz";
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(message) = EncryptedMessage::from_bytes(data) {
+        assert_eq!(EncryptedMessage::from_bytes(&message.to_bytes()).unwrap().to_bytes(), message.to_bytes());
+
+        let mut bob = DoubleRatchet::init_bob(&SHARED_SECRET).unwrap();
+        let mut alice = DoubleRatchet::init_alice(&SHARED_SECRET, bob.public_key()).unwrap();
+        let _ = bob.decrypt(&message, ASSOCIATED_DATA);
+
+        let genuine = alice.encrypt(b"genuine", ASSOCIATED_DATA).unwrap();
+        assert_eq!(bob.decrypt(&genuine, ASSOCIATED_DATA).unwrap(), b"genuine");
+    }
+
+    if let Ok(message) = HeaderEncryptedMessage::from_bytes(data) {
+        assert_eq!(message.to_bytes(), data);
+
+        let bob_key = StaticSecret::from([9u8; 32]);
+        let bob_public = PublicKey::from(&bob_key);
+        let mut bob = DoubleRatchet::init_bob_with_header_encryption(&SHARED_SECRET, bob_key).unwrap();
+        let mut alice = DoubleRatchet::init_alice_with_header_encryption(&SHARED_SECRET, bob_public).unwrap();
+        let _ = bob.decrypt_header_encrypted(&message, ASSOCIATED_DATA);
+
+        let genuine = alice.encrypt_header_encrypted(b"genuine", ASSOCIATED_DATA).unwrap();
+        assert_eq!(bob.decrypt_header_encrypted(&genuine, ASSOCIATED_DATA).unwrap(), b"genuine");
+    }
+});
@@ -0,0 +1,40 @@
+//! Persisted key records and other small client-supplied encodings
+#![no_main]
+
+use guardyn_crypto::backup::RecoveryCode;
+use guardyn_crypto::fingerprint::Fingerprint;
+use guardyn_crypto::mls_credential::MlsCredential;
+use guardyn_crypto::mls_storage::MlsStorage;
+use guardyn_crypto::pq_kem::KemPreKey;
+use guardyn_crypto::x3dh::{IdentityKeyPair, OneTimePreKey, SignedPreKey};
+use libfuzzer_sys::fuzz_target;
+use std::sync::OnceLock;
+
+/// Fingerprints take thousands of hash iterations, so derive one per process
+static FINGERPRINT: OnceLock<Fingerprint> = OnceLock::new();
+
+fuzz_target!(|data: &[u8]| {
+    let _ = IdentityKeyPair::from_secret_bytes(data);
+    let _ = OneTimePreKey::from_secret_bytes(0, data);
+
+    if let Ok(signed_pre_key) = SignedPreKey::from_bytes(data) {
+        SignedPreKey::from_bytes(&signed_pre_key.to_bytes()).unwrap();
+    }
+    if let Ok(kem_pre_key) = KemPreKey::from_bytes(data) {
+        KemPreKey::from_bytes(&kem_pre_key.to_bytes()).unwrap();
+    }
+    if let Ok(credential) = MlsCredential::from_bytes(data) {
+        MlsCredential::from_bytes(&credential.to_bytes()).unwrap();
+    }
+    if let Ok(storage) = MlsStorage::from_bytes(data) {
+        MlsStorage::from_bytes(&storage.to_bytes()).unwrap();
+    }
+
+    let fingerprint =
+        FINGERPRINT.get_or_init(|| Fingerprint::new(b"alice", &[1u8; 32], b"bob", &[2u8; 32]).unwrap());
+    let _ = fingerprint.verify_scanned(data);
+
+    if let Ok(code) = std::str::from_utf8(data) {
+        let _ = RecoveryCode::parse(code);
+    }
+});
@@ -0,0 +1,10 @@
+//! MLS key packages uploaded by clients
+#![no_main]
+
+use guardyn_crypto::mls::{key_package_ciphersuites, key_package_identity};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = key_package_ciphersuites(data);
+    let _ = key_package_identity(data);
+});
@@ -0,0 +1,46 @@
+//! MLS messages received by a group member
+//!
+//! The first byte selects the entry point, the rest is the message. The group
+//! is created once per process; inputs that fail to process leave it unchanged.
+#![no_main]
+
+use guardyn_crypto::mls::{message_epoch, MlsGroupManager, DEFAULT_MLS_CIPHERSUITE};
+use guardyn_crypto::mls_credential::MlsCredential;
+use guardyn_crypto::x3dh::IdentityKeyPair;
+use libfuzzer_sys::fuzz_target;
+use std::cell::RefCell;
+
+thread_local! {
+    static GROUP: RefCell<MlsGroupManager> = RefCell::new({
+        let identity = IdentityKeyPair::generate().unwrap();
+        let (credential, signature_keypair) =
+            MlsCredential::generate("fuzz", "device", &identity, DEFAULT_MLS_CIPHERSUITE).unwrap();
+        MlsGroupManager::create_group("fuzz-group", &credential, signature_keypair).unwrap()
+    });
+}
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&selector, message)) = data.split_first() else {
+        return;
+    };
+
+    let _ = message_epoch(message);
+
+    GROUP.with(|group| {
+        let mut group = group.borrow_mut();
+        match selector % 4 {
+            0 => {
+                let _ = group.decrypt_message(message);
+            }
+            1 => {
+                let _ = group.process_proposal(message);
+            }
+            2 => {
+                let _ = group.process_commit(message);
+            }
+            _ => {
+                let _ = group.process_external_commit(message);
+            }
+        }
+    });
+});
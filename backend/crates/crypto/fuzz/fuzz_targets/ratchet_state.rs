@@ -0,0 +1,26 @@
+//! Serialized Double Ratchet state (current and legacy v0 layouts)
+//!
+//! State that loads must serialize and load again.
+#![no_main]
+
+use guardyn_crypto::double_ratchet::DoubleRatchet;
+use libfuzzer_sys::fuzz_target;
+
+const STATE_KEY: [u8; 32] = [3u8; 32];
+
+fuzz_target!(|data: &[u8]| {
+    let _ = DoubleRatchet::is_current_format(data, true);
+
+    if let Ok(ratchet) = DoubleRatchet::deserialize(data) {
+        DoubleRatchet::deserialize(&ratchet.serialize()).unwrap();
+    }
+
+    if let Ok(ratchet) = DoubleRatchet::deserialize_encrypted(data, &STATE_KEY) {
+        let encrypted = ratchet.serialize_encrypted(&STATE_KEY).unwrap();
+        DoubleRatchet::deserialize_encrypted(&encrypted, &STATE_KEY).unwrap();
+    }
+
+    if let Ok(migrated) = DoubleRatchet::migrate_to_encrypted(data, &STATE_KEY) {
+        DoubleRatchet::deserialize_encrypted(&migrated, &STATE_KEY).unwrap();
+    }
+});
@@ -0,0 +1,18 @@
+//! Sealed sender envelopes and sender certificates
+#![no_main]
+
+use guardyn_crypto::sealed_sender::{SealedSender, SenderCertificate};
+use guardyn_crypto::x3dh::IdentityKeyPair;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let server = IdentityKeyPair::from_secret_bytes(&[1u8; 32]).unwrap();
+    let recipient = IdentityKeyPair::from_secret_bytes(&[2u8; 32]).unwrap();
+
+    if let Ok(certificate) = SenderCertificate::from_bytes(data) {
+        let _ = certificate.verify(&server.public_bytes(), 0);
+        SenderCertificate::from_bytes(&certificate.to_bytes().unwrap()).unwrap();
+    }
+
+    let _ = SealedSender::unseal(data, &recipient, &server.public_bytes(), 0);
+});
@@ -0,0 +1,596 @@
+/// Encrypted media attachments
+///
+/// Attachments are encrypted client-side with a random per-attachment key
+/// using the STREAM construction (Hoang et al., "Online Authenticated-
+/// Encryption and its Nonce-Reuse Misuse-Resistance") over AES-256-GCM, so
+/// they can be encrypted and uploaded as a stream and any range of chunks
+/// can be downloaded and decrypted on its own.
+///
+/// Ciphertext format (version 1):
+/// `header (12) || chunk_0 || ... || chunk_n-1`
+/// where the header is `version (1) || chunk size (u32 BE) || nonce prefix (7)`
+/// and every chunk is the AES-256-GCM ciphertext and tag of `chunk size`
+/// plaintext bytes; only the last chunk may be shorter (down to empty).
+/// Chunk `i` uses the nonce `nonce prefix || i (u32 BE) || last flag (1)` and
+/// the header as associated data, which prevents reordering, truncation and
+/// appending of chunks.
+///
+/// The sender embeds the key and the SHA-256 digest of the whole ciphertext
+/// ([`AttachmentPointer`]) in the Double Ratchet or MLS message referencing
+/// the attachment. Full downloads are checked against the digest; range
+/// downloads are authenticated chunk by chunk.
+use crate::secret::ct_eq;
+use crate::{CryptoError, Result};
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Nonce,
+};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use zeroize::Zeroizing;
+
+/// Current attachment format version
+pub const ATTACHMENT_VERSION: u8 = 1;
+
+/// Length of the attachment header
+pub const ATTACHMENT_HEADER_LEN: usize = 12;
+
+/// Length of an attachment key (AES-256)
+pub const ATTACHMENT_KEY_LEN: usize = 32;
+
+/// Default plaintext chunk size
+pub const DEFAULT_CHUNK_SIZE: u32 = 64 * 1024;
+
+/// Largest supported plaintext chunk size
+pub const MAX_CHUNK_SIZE: u32 = 16 * 1024 * 1024;
+
+/// Length of the random nonce prefix in the header
+const NONCE_PREFIX_LEN: usize = 7;
+
+/// Length of an AES-GCM tag
+const TAG_LEN: u64 = 16;
+
+/// Everything a recipient needs to fetch and decrypt an attachment,
+/// sent inside the end-to-end encrypted message
+///
+/// `Debug` output leaves out the key.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttachmentPointer {
+    /// Attachment key (AES-256)
+    pub key: Vec<u8>,
+    /// SHA-256 of the complete ciphertext
+    pub digest: Vec<u8>,
+    /// Plaintext size in bytes
+    pub size: u64,
+}
+
+impl fmt::Debug for AttachmentPointer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AttachmentPointer")
+            .field("digest", &self.digest)
+            .field("size", &self.size)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Attachment header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AttachmentHeader {
+    chunk_size: u32,
+    nonce_prefix: [u8; NONCE_PREFIX_LEN],
+}
+
+impl AttachmentHeader {
+    fn generate(chunk_size: u32) -> Result<Self> {
+        if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+            return Err(CryptoError::Encryption(format!(
+                "Invalid attachment chunk size {}",
+                chunk_size
+            )));
+        }
+
+        let mut nonce_prefix = [0u8; NONCE_PREFIX_LEN];
+        OsRng.fill_bytes(&mut nonce_prefix);
+        Ok(Self {
+            chunk_size,
+            nonce_prefix,
+        })
+    }
+
+    /// Parse the header at the start of an attachment ciphertext
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let header = bytes
+            .get(..ATTACHMENT_HEADER_LEN)
+            .ok_or_else(|| CryptoError::Decryption("Attachment header truncated".to_string()))?;
+
+        if header[0] != ATTACHMENT_VERSION {
+            return Err(CryptoError::Decryption(format!(
+                "Unsupported attachment version {}",
+                header[0]
+            )));
+        }
+
+        let chunk_size = u32::from_be_bytes([header[1], header[2], header[3], header[4]]);
+        if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+            return Err(CryptoError::Decryption(format!(
+                "Invalid attachment chunk size {}",
+                chunk_size
+            )));
+        }
+
+        let mut nonce_prefix = [0u8; NONCE_PREFIX_LEN];
+        nonce_prefix.copy_from_slice(&header[5..]);
+        Ok(Self {
+            chunk_size,
+            nonce_prefix,
+        })
+    }
+
+    /// Encode the header
+    pub fn to_bytes(&self) -> [u8; ATTACHMENT_HEADER_LEN] {
+        let mut bytes = [0u8; ATTACHMENT_HEADER_LEN];
+        bytes[0] = ATTACHMENT_VERSION;
+        bytes[1..5].copy_from_slice(&self.chunk_size.to_be_bytes());
+        bytes[5..].copy_from_slice(&self.nonce_prefix);
+        bytes
+    }
+
+    /// Plaintext chunk size
+    pub fn chunk_size(&self) -> u32 {
+        self.chunk_size
+    }
+
+    /// Size of a full chunk in the ciphertext
+    fn sealed_chunk_size(&self) -> u64 {
+        u64::from(self.chunk_size) + TAG_LEN
+    }
+
+    /// Number of chunks of a ciphertext with the given total length
+    ///
+    /// Fails if no plaintext encrypts to that length.
+    pub fn chunk_count(&self, ciphertext_len: u64) -> Result<u64> {
+        let invalid = || CryptoError::Decryption(format!("Invalid attachment length {}", ciphertext_len));
+
+        let body = ciphertext_len
+            .checked_sub(ATTACHMENT_HEADER_LEN as u64)
+            .filter(|body| *body >= TAG_LEN)
+            .ok_or_else(invalid)?;
+
+        let full_chunks = body / self.sealed_chunk_size();
+        let chunk_count = match body % self.sealed_chunk_size() {
+            0 => full_chunks,
+            remainder if remainder < TAG_LEN => return Err(invalid()),
+            _ => full_chunks + 1,
+        };
+
+        // Chunk indices must fit the nonce counter
+        if chunk_count > u64::from(u32::MAX) + 1 {
+            return Err(invalid());
+        }
+        Ok(chunk_count)
+    }
+
+    /// Plaintext length of a ciphertext with the given total length
+    pub fn plaintext_len(&self, ciphertext_len: u64) -> Result<u64> {
+        let chunk_count = self.chunk_count(ciphertext_len)?;
+        Ok(ciphertext_len - ATTACHMENT_HEADER_LEN as u64 - chunk_count * TAG_LEN)
+    }
+
+    /// Ciphertext byte range holding a plaintext byte range
+    ///
+    /// The returned range covers whole chunks and can be requested from
+    /// media-service as a range download.
+    pub fn ciphertext_range(
+        &self,
+        ciphertext_len: u64,
+        plaintext_offset: u64,
+        plaintext_len: u64,
+    ) -> Result<ChunkRange> {
+        let chunk_count = self.chunk_count(ciphertext_len)?;
+        let total_plaintext = ciphertext_len - ATTACHMENT_HEADER_LEN as u64 - chunk_count * TAG_LEN;
+
+        let end = plaintext_offset
+            .checked_add(plaintext_len)
+            .filter(|end| plaintext_len > 0 && *end <= total_plaintext)
+            .ok_or_else(|| {
+                CryptoError::Decryption(format!(
+                    "Range {}+{} outside attachment of {} bytes",
+                    plaintext_offset, plaintext_len, total_plaintext
+                ))
+            })?;
+
+        let chunk_size = u64::from(self.chunk_size);
+        let first_chunk = plaintext_offset / chunk_size;
+        let last_chunk = (end - 1) / chunk_size;
+
+        let offset = ATTACHMENT_HEADER_LEN as u64 + first_chunk * self.sealed_chunk_size();
+        let range_end = if last_chunk + 1 == chunk_count {
+            ciphertext_len
+        } else {
+            ATTACHMENT_HEADER_LEN as u64 + (last_chunk + 1) * self.sealed_chunk_size()
+        };
+
+        Ok(ChunkRange {
+            offset,
+            length: range_end - offset,
+            first_chunk,
+            last_chunk,
+            chunk_count,
+            plaintext_offset,
+            plaintext_len,
+        })
+    }
+
+    /// Nonce of chunk `index`
+    fn nonce(&self, index: u64, last: bool) -> Result<[u8; 12]> {
+        let index = u32::try_from(index)
+            .map_err(|_| CryptoError::Encryption("Attachment has too many chunks".to_string()))?;
+
+        let mut nonce = [0u8; 12];
+        nonce[..NONCE_PREFIX_LEN].copy_from_slice(&self.nonce_prefix);
+        nonce[NONCE_PREFIX_LEN..11].copy_from_slice(&index.to_be_bytes());
+        nonce[11] = u8::from(last);
+        Ok(nonce)
+    }
+}
+
+/// Ciphertext range covering a plaintext range, see
+/// [`AttachmentHeader::ciphertext_range`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkRange {
+    /// Ciphertext offset to download from (including the header)
+    pub offset: u64,
+    /// Number of ciphertext bytes to download
+    pub length: u64,
+    first_chunk: u64,
+    last_chunk: u64,
+    chunk_count: u64,
+    plaintext_offset: u64,
+    plaintext_len: u64,
+}
+
+/// Streaming attachment encryption
+///
+/// Feed the plaintext with [`AttachmentEncryptor::update`] and upload the
+/// returned ciphertext as it is produced; [`AttachmentEncryptor::finish`]
+/// returns the rest of the ciphertext and the pointer to send to recipients.
+pub struct AttachmentEncryptor {
+    key: Zeroizing<[u8; ATTACHMENT_KEY_LEN]>,
+    header: AttachmentHeader,
+    header_written: bool,
+    next_chunk: u64,
+    buffer: Zeroizing<Vec<u8>>,
+    digest: Sha256,
+    plaintext_len: u64,
+}
+
+impl AttachmentEncryptor {
+    /// Start encrypting an attachment with a fresh random key
+    pub fn new() -> Result<Self> {
+        Self::with_chunk_size(DEFAULT_CHUNK_SIZE)
+    }
+
+    /// Start encrypting an attachment with a custom chunk size
+    pub fn with_chunk_size(chunk_size: u32) -> Result<Self> {
+        let mut key = Zeroizing::new([0u8; ATTACHMENT_KEY_LEN]);
+        OsRng.fill_bytes(key.as_mut());
+
+        Ok(Self {
+            key,
+            header: AttachmentHeader::generate(chunk_size)?,
+            header_written: false,
+            next_chunk: 0,
+            buffer: Zeroizing::new(Vec::new()),
+            digest: Sha256::new(),
+            plaintext_len: 0,
+        })
+    }
+
+    /// Encrypt the next part of the plaintext
+    ///
+    /// # Returns
+    /// Ciphertext bytes ready for upload (possibly empty)
+    pub fn update(&mut self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        self.plaintext_len += plaintext.len() as u64;
+        self.buffer.extend_from_slice(plaintext);
+
+        let chunk_size = self.header.chunk_size as usize;
+        let mut output = self.take_header();
+
+        // A full chunk is only sealed once more data follows it, because
+        // the last chunk has to carry the last flag
+        let mut consumed = 0;
+        while self.buffer.len() - consumed > chunk_size {
+            let chunk = &self.buffer[consumed..consumed + chunk_size];
+            let sealed = seal_chunk(&self.key, &self.header, self.next_chunk, false, chunk)?;
+            self.next_chunk += 1;
+            output.extend_from_slice(&sealed);
+            consumed += chunk_size;
+        }
+        self.buffer.drain(..consumed);
+
+        self.digest.update(&output);
+        Ok(output)
+    }
+
+    /// Encrypt the last chunk
+    ///
+    /// # Returns
+    /// Tuple of (remaining ciphertext, pointer for recipients)
+    pub fn finish(mut self) -> Result<(Vec<u8>, AttachmentPointer)> {
+        let mut output = self.take_header();
+        let sealed = seal_chunk(&self.key, &self.header, self.next_chunk, true, &self.buffer)?;
+        output.extend_from_slice(&sealed);
+        self.digest.update(&output);
+
+        let pointer = AttachmentPointer {
+            key: self.key.to_vec(),
+            digest: self.digest.finalize().to_vec(),
+            size: self.plaintext_len,
+        };
+        Ok((output, pointer))
+    }
+
+    fn take_header(&mut self) -> Vec<u8> {
+        if self.header_written {
+            return Vec::new();
+        }
+        self.header_written = true;
+        self.header.to_bytes().to_vec()
+    }
+}
+
+/// Random-access attachment decryption
+pub struct AttachmentDecryptor {
+    key: Zeroizing<[u8; ATTACHMENT_KEY_LEN]>,
+    header: AttachmentHeader,
+    ciphertext_len: u64,
+    chunk_count: u64,
+}
+
+impl AttachmentDecryptor {
+    /// Prepare decryption of an attachment
+    ///
+    /// # Arguments
+    /// * `key` - Attachment key from the [`AttachmentPointer`]
+    /// * `header` - First [`ATTACHMENT_HEADER_LEN`] bytes of the ciphertext
+    /// * `ciphertext_len` - Total ciphertext length (media size)
+    pub fn new(key: &[u8], header: &[u8], ciphertext_len: u64) -> Result<Self> {
+        let key: [u8; ATTACHMENT_KEY_LEN] = key
+            .try_into()
+            .map_err(|_| CryptoError::InvalidKey("Attachment key must be 32 bytes".to_string()))?;
+        let header = AttachmentHeader::parse(header)?;
+        let chunk_count = header.chunk_count(ciphertext_len)?;
+
+        Ok(Self {
+            key: Zeroizing::new(key),
+            header,
+            ciphertext_len,
+            chunk_count,
+        })
+    }
+
+    /// Plaintext length of the attachment
+    pub fn plaintext_len(&self) -> u64 {
+        self.ciphertext_len - ATTACHMENT_HEADER_LEN as u64 - self.chunk_count * TAG_LEN
+    }
+
+    /// Ciphertext range to download for a plaintext range
+    pub fn ciphertext_range(&self, plaintext_offset: u64, plaintext_len: u64) -> Result<ChunkRange> {
+        self.header
+            .ciphertext_range(self.ciphertext_len, plaintext_offset, plaintext_len)
+    }
+
+    /// Decrypt a downloaded range
+    ///
+    /// # Arguments
+    /// * `range` - Range from [`AttachmentDecryptor::ciphertext_range`]
+    /// * `ciphertext` - The `range.length` bytes at `range.offset`
+    ///
+    /// # Returns
+    /// Exactly the plaintext range the ciphertext range was computed for
+    pub fn decrypt_range(&self, range: &ChunkRange, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        if range.chunk_count != self.chunk_count || ciphertext.len() as u64 != range.length {
+            return Err(CryptoError::Decryption("Ciphertext does not match range".to_string()));
+        }
+
+        let plaintext = self.decrypt_chunks(range.first_chunk, range.last_chunk, ciphertext)?;
+        let skip = (range.plaintext_offset - range.first_chunk * u64::from(self.header.chunk_size)) as usize;
+        Ok(plaintext[skip..skip + range.plaintext_len as usize].to_vec())
+    }
+
+    /// Decrypt consecutive chunks `first..=last` stored back to back in `ciphertext`
+    fn decrypt_chunks(&self, first: u64, last: u64, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        let sealed_chunk_size = self.header.sealed_chunk_size() as usize;
+        let mut plaintext = Vec::with_capacity(ciphertext.len());
+
+        let mut remaining = ciphertext;
+        for index in first..=last {
+            let is_last = index + 1 == self.chunk_count;
+            let len = if is_last { remaining.len() } else { sealed_chunk_size.min(remaining.len()) };
+            let (sealed, rest) = remaining.split_at(len);
+            plaintext.extend_from_slice(&open_chunk(&self.key, &self.header, index, is_last, sealed)?);
+            remaining = rest;
+        }
+
+        if !remaining.is_empty() {
+            return Err(CryptoError::Decryption("Trailing attachment data".to_string()));
+        }
+        Ok(plaintext)
+    }
+}
+
+/// Encrypt a complete attachment in memory
+///
+/// # Returns
+/// Tuple of (ciphertext to upload, pointer for recipients)
+pub fn encrypt_attachment(plaintext: &[u8]) -> Result<(Vec<u8>, AttachmentPointer)> {
+    let mut encryptor = AttachmentEncryptor::new()?;
+    let mut ciphertext = encryptor.update(plaintext)?;
+    let (rest, pointer) = encryptor.finish()?;
+    ciphertext.extend_from_slice(&rest);
+    Ok((ciphertext, pointer))
+}
+
+/// Decrypt a complete downloaded attachment
+///
+/// Checks the ciphertext against the digest and size of the pointer.
+pub fn decrypt_attachment(pointer: &AttachmentPointer, ciphertext: &[u8]) -> Result<Vec<u8>> {
+    if !ct_eq(&Sha256::digest(ciphertext), &pointer.digest) {
+        return Err(CryptoError::Decryption("Attachment digest mismatch".to_string()));
+    }
+
+    let decryptor = AttachmentDecryptor::new(&pointer.key, ciphertext, ciphertext.len() as u64)?;
+    if decryptor.plaintext_len() != pointer.size {
+        return Err(CryptoError::Decryption("Attachment size mismatch".to_string()));
+    }
+
+    decryptor.decrypt_chunks(
+        0,
+        decryptor.chunk_count - 1,
+        &ciphertext[ATTACHMENT_HEADER_LEN..],
+    )
+}
+
+/// Check that a ciphertext has the attachment format
+///
+/// Lets the media service reject malformed uploads without the key.
+pub fn validate_attachment_layout(ciphertext: &[u8]) -> Result<AttachmentHeader> {
+    let header = AttachmentHeader::parse(ciphertext)?;
+    header.chunk_count(ciphertext.len() as u64)?;
+    Ok(header)
+}
+
+fn seal_chunk(
+    key: &[u8; ATTACHMENT_KEY_LEN],
+    header: &AttachmentHeader,
+    index: u64,
+    last: bool,
+    chunk: &[u8],
+) -> Result<Vec<u8>> {
+    let cipher = Aes256Gcm::new(key.into());
+    let nonce = header.nonce(index, last)?;
+    cipher
+        .encrypt(
+            &Nonce::from(nonce),
+            Payload {
+                msg: chunk,
+                aad: &header.to_bytes(),
+            },
+        )
+        .map_err(|e| CryptoError::Encryption(format!("Attachment chunk encryption failed: {}", e)))
+}
+
+fn open_chunk(
+    key: &[u8; ATTACHMENT_KEY_LEN],
+    header: &AttachmentHeader,
+    index: u64,
+    last: bool,
+    sealed: &[u8],
+) -> Result<Vec<u8>> {
+    let cipher = Aes256Gcm::new(key.into());
+    let nonce = header.nonce(index, last)?;
+    cipher
+        .decrypt(
+            &Nonce::from(nonce),
+            Payload {
+                msg: sealed,
+                aad: &header.to_bytes(),
+            },
+        )
+        .map_err(|_| CryptoError::Decryption(format!("Attachment chunk {} failed authentication", index)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encrypt_with_chunk_size(plaintext: &[u8], chunk_size: u32) -> (Vec<u8>, AttachmentPointer) {
+        let mut encryptor = AttachmentEncryptor::with_chunk_size(chunk_size).unwrap();
+        // Feed in uneven pieces to exercise buffering
+        let mut ciphertext = Vec::new();
+        for piece in plaintext.chunks(7) {
+            ciphertext.extend_from_slice(&encryptor.update(piece).unwrap());
+        }
+        let (rest, pointer) = encryptor.finish().unwrap();
+        ciphertext.extend_from_slice(&rest);
+        (ciphertext, pointer)
+    }
+
+    #[test]
+    fn test_attachment_round_trip() {
+        for len in [0usize, 1, 15, 16, 17, 32, 33, 100] {
+            let plaintext: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            let (ciphertext, pointer) = encrypt_with_chunk_size(&plaintext, 16);
+
+            assert_eq!(pointer.size, len as u64);
+            assert!(validate_attachment_layout(&ciphertext).is_ok());
+            assert_eq!(decrypt_attachment(&pointer, &ciphertext).unwrap(), plaintext);
+        }
+    }
+
+    #[test]
+    fn test_attachment_range_decryption() {
+        let plaintext: Vec<u8> = (0..1000u32).map(|i| (i % 251) as u8).collect();
+        let (ciphertext, pointer) = encrypt_with_chunk_size(&plaintext, 64);
+        let decryptor = AttachmentDecryptor::new(&pointer.key, &ciphertext, ciphertext.len() as u64).unwrap();
+        assert_eq!(decryptor.plaintext_len(), plaintext.len() as u64);
+
+        for (offset, len) in [(0u64, 1u64), (63, 2), (64, 64), (100, 500), (990, 10), (0, 1000)] {
+            let range = decryptor.ciphertext_range(offset, len).unwrap();
+            let slice = &ciphertext[range.offset as usize..(range.offset + range.length) as usize];
+            let decrypted = decryptor.decrypt_range(&range, slice).unwrap();
+            assert_eq!(decrypted, plaintext[offset as usize..(offset + len) as usize]);
+        }
+
+        assert!(decryptor.ciphertext_range(990, 11).is_err());
+        assert!(decryptor.ciphertext_range(0, 0).is_err());
+    }
+
+    #[test]
+    fn test_attachment_rejects_tampering() {
+        let plaintext = vec![42u8; 200];
+        let (ciphertext, pointer) = encrypt_with_chunk_size(&plaintext, 64);
+
+        // Digest catches any modification of a full download
+        let mut modified = ciphertext.clone();
+        modified[ATTACHMENT_HEADER_LEN + 3] ^= 1;
+        assert!(decrypt_attachment(&pointer, &modified).is_err());
+
+        // Chunk authentication catches it for range downloads
+        let decryptor = AttachmentDecryptor::new(&pointer.key, &modified, modified.len() as u64).unwrap();
+        let range = decryptor.ciphertext_range(0, 10).unwrap();
+        let slice = &modified[range.offset as usize..(range.offset + range.length) as usize];
+        assert!(decryptor.decrypt_range(&range, slice).is_err());
+    }
+
+    #[test]
+    fn test_attachment_rejects_truncation() {
+        let plaintext = vec![7u8; 256];
+        let (ciphertext, pointer) = encrypt_with_chunk_size(&plaintext, 64);
+
+        // Dropping the last chunk leaves a valid layout whose last chunk
+        // lacks the last flag
+        let sealed_chunk = 64 + TAG_LEN as usize;
+        let truncated = &ciphertext[..ciphertext.len() - sealed_chunk];
+        assert!(validate_attachment_layout(truncated).is_ok());
+
+        let decryptor = AttachmentDecryptor::new(&pointer.key, truncated, truncated.len() as u64).unwrap();
+        let range = decryptor.ciphertext_range(128, 64).unwrap();
+        let slice = &truncated[range.offset as usize..(range.offset + range.length) as usize];
+        assert!(decryptor.decrypt_range(&range, slice).is_err());
+    }
+
+    #[test]
+    fn test_attachment_layout_validation() {
+        let (ciphertext, _pointer) = encrypt_attachment(b"hello").unwrap();
+        assert!(validate_attachment_layout(&ciphertext).is_ok());
+        // The last chunk must at least hold its tag
+        assert!(validate_attachment_layout(&ciphertext[..ATTACHMENT_HEADER_LEN + TAG_LEN as usize - 1]).is_err());
+        assert!(validate_attachment_layout(&ciphertext[..ATTACHMENT_HEADER_LEN]).is_err());
+        assert!(validate_attachment_layout(b"not an attachment").is_err());
+    }
+}
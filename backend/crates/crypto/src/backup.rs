@@ -0,0 +1,471 @@
+//! Encrypted backups of keys and message history
+//!
+//! A backup holds every record of a [`KeyStorage`] (identity key, pre-keys,
+//! Double Ratchet sessions, MLS signature keys) together with the message
+//! history serialized by the client. It is encrypted under a key derived
+//! with Argon2id from a random 64-character recovery code that only the user
+//! holds, so the server storing the backup learns nothing about its contents.
+//!
+//! Backup format (version 1):
+//! `version (1) || memory_kib (u32 BE) || iterations (u32 BE) ||
+//! parallelism (u32 BE) || salt (16) || nonce (12) || AES-256-GCM ciphertext + tag`
+//! with associated data `"guardyn-backup" || everything before the ciphertext`.
+//!
+//! Encrypted payload:
+//! `key count (u32 BE) || (id len (u16 BE) || id || key len (u32 BE) || key)* || history`
+use crate::key_storage::{derive_kek, KdfParams, KeyStorage};
+use crate::{CryptoError, Result};
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Nonce,
+};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use std::collections::BTreeMap;
+use std::fmt;
+use zeroize::Zeroizing;
+
+/// Current backup format version
+const BACKUP_VERSION: u8 = 1;
+
+/// Associated data prefix of the backup ciphertext
+const BACKUP_AAD_PREFIX: &[u8] = b"guardyn-backup";
+
+/// Number of random bytes in a recovery code (64 hex characters)
+const RECOVERY_CODE_BYTES: usize = 32;
+
+const SALT_SIZE: usize = 16;
+const NONCE_SIZE: usize = 12;
+const HEADER_LEN: usize = 13 + SALT_SIZE + NONCE_SIZE;
+
+/// Upper bounds on the KDF parameters accepted from a backup, so a tampered
+/// backup cannot make restoring exhaust the device's memory or time
+const MAX_MEMORY_KIB: u32 = 256 * 1024;
+const MAX_ITERATIONS: u32 = 16;
+const MAX_PARALLELISM: u32 = 16;
+
+/// Recovery code protecting a backup
+///
+/// 64 hexadecimal characters (256 bits). Shown to the user once when backups
+/// are enabled; entering it on a new device restores the backup.
+pub struct RecoveryCode(Zeroizing<String>);
+
+impl RecoveryCode {
+    /// Generate a new random recovery code
+    pub fn generate() -> Self {
+        let mut bytes = Zeroizing::new([0u8; RECOVERY_CODE_BYTES]);
+        OsRng.fill_bytes(bytes.as_mut());
+        Self(Zeroizing::new(
+            bytes.iter().map(|b| format!("{:02x}", b)).collect(),
+        ))
+    }
+
+    /// Parse a recovery code entered by the user
+    ///
+    /// Whitespace and dashes are ignored and letters may be upper case, so
+    /// the grouped form from [`RecoveryCode::to_grouped_string`] is accepted.
+    pub fn parse(code: &str) -> Result<Self> {
+        let normalized: Zeroizing<String> = Zeroizing::new(
+            code.chars()
+                .filter(|c| !c.is_whitespace() && *c != '-')
+                .map(|c| c.to_ascii_lowercase())
+                .collect(),
+        );
+
+        if normalized.len() != RECOVERY_CODE_BYTES * 2
+            || !normalized.chars().all(|c| c.is_ascii_hexdigit())
+        {
+            return Err(CryptoError::InvalidKey(
+                "Recovery code must be 64 hexadecimal characters".to_string(),
+            ));
+        }
+
+        Ok(Self(normalized))
+    }
+
+    /// The 64-character code
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The code in dash-separated groups of four for display
+    pub fn to_grouped_string(&self) -> Zeroizing<String> {
+        let groups: Vec<&str> = (0..self.0.len())
+            .step_by(4)
+            .map(|i| &self.0[i..i + 4])
+            .collect();
+        Zeroizing::new(groups.join("-"))
+    }
+}
+
+impl fmt::Debug for RecoveryCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RecoveryCode(..)")
+    }
+}
+
+/// Decrypted contents of a backup
+pub struct BackupContents {
+    /// Key store records by key id
+    pub keys: BTreeMap<String, Zeroizing<Vec<u8>>>,
+    /// Message history, as serialized by the client
+    pub history: Zeroizing<Vec<u8>>,
+}
+
+/// Result of restoring a backup into a key store
+pub struct RestoredBackup {
+    /// Number of key records restored
+    pub key_count: usize,
+    /// Sessions whose Double Ratchet state was restored, see
+    /// [`KeyStorage::load_ratchet_state`]
+    pub session_ids: Vec<String>,
+    /// Message history, as serialized by the client
+    pub history: Zeroizing<Vec<u8>>,
+}
+
+/// Create a backup of a key store and the message history
+///
+/// # Arguments
+/// * `storage` - Key store to back up
+/// * `history` - Message history serialized by the client
+/// * `code` - Recovery code the backup is encrypted under
+///
+/// # Returns
+/// Encrypted backup, ready to upload
+pub fn create_backup(storage: &KeyStorage, history: &[u8], code: &RecoveryCode) -> Result<Vec<u8>> {
+    create_backup_with_params(storage, history, code, KdfParams::default())
+}
+
+/// Create a backup using custom Argon2id parameters
+pub fn create_backup_with_params(
+    storage: &KeyStorage,
+    history: &[u8],
+    code: &RecoveryCode,
+    params: KdfParams,
+) -> Result<Vec<u8>> {
+    check_params(params)?;
+
+    let mut keys = BTreeMap::new();
+    for key_id in storage.list_keys()? {
+        if let Some(key) = storage.get_key(&key_id)? {
+            keys.insert(key_id, key);
+        }
+    }
+    let payload = encode_payload(&keys, history)?;
+
+    let mut salt = [0u8; SALT_SIZE];
+    OsRng.fill_bytes(&mut salt);
+    let mut nonce = [0u8; NONCE_SIZE];
+    OsRng.fill_bytes(&mut nonce);
+
+    let mut backup = Vec::with_capacity(HEADER_LEN + payload.len() + 16);
+    backup.push(BACKUP_VERSION);
+    backup.extend_from_slice(&params.memory_kib.to_be_bytes());
+    backup.extend_from_slice(&params.iterations.to_be_bytes());
+    backup.extend_from_slice(&params.parallelism.to_be_bytes());
+    backup.extend_from_slice(&salt);
+    backup.extend_from_slice(&nonce);
+
+    let key = derive_kek(code.as_str().as_bytes(), &salt, params)?;
+    let cipher = Aes256Gcm::new((&*key).into());
+    let ciphertext = cipher
+        .encrypt(
+            &Nonce::from(nonce),
+            Payload {
+                msg: &payload,
+                aad: &backup_aad(&backup),
+            },
+        )
+        .map_err(|e| CryptoError::Encryption(format!("Failed to encrypt backup: {}", e)))?;
+
+    backup.extend_from_slice(&ciphertext);
+    Ok(backup)
+}
+
+/// Decrypt a backup without restoring it
+pub fn decrypt_backup(backup: &[u8], code: &RecoveryCode) -> Result<BackupContents> {
+    if backup.len() < HEADER_LEN {
+        return Err(CryptoError::Decryption("Backup too short".to_string()));
+    }
+    if backup[0] != BACKUP_VERSION {
+        return Err(CryptoError::Decryption(format!(
+            "Unsupported backup version: {}",
+            backup[0]
+        )));
+    }
+
+    let read_u32 = |offset: usize| u32::from_be_bytes([
+        backup[offset],
+        backup[offset + 1],
+        backup[offset + 2],
+        backup[offset + 3],
+    ]);
+    let params = KdfParams {
+        memory_kib: read_u32(1),
+        iterations: read_u32(5),
+        parallelism: read_u32(9),
+    };
+    check_params(params)?;
+
+    let (header, ciphertext) = backup.split_at(HEADER_LEN);
+    let salt = &header[13..13 + SALT_SIZE];
+    let mut nonce = [0u8; NONCE_SIZE];
+    nonce.copy_from_slice(&header[13 + SALT_SIZE..]);
+
+    let key = derive_kek(code.as_str().as_bytes(), salt, params)?;
+    let cipher = Aes256Gcm::new((&*key).into());
+    let payload = Zeroizing::new(
+        cipher
+            .decrypt(
+                &Nonce::from(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: &backup_aad(header),
+                },
+            )
+            .map_err(|_| {
+                CryptoError::Decryption("Wrong recovery code or corrupted backup".to_string())
+            })?,
+    );
+
+    decode_payload(&payload)
+}
+
+/// Restore a backup into an empty key store
+///
+/// The key store is typically newly created on the new device, protected by
+/// that device's own passphrase. Restored Double Ratchet sessions are
+/// receive-only (see [`crate::double_ratchet::DoubleRatchet::make_receive_only`]): the backed up
+/// device may have sent with their sending chains since the backup was made,
+/// so sending resumes after the peer's next ratchet step.
+pub fn restore_backup(backup: &[u8], code: &RecoveryCode, storage: &KeyStorage) -> Result<RestoredBackup> {
+    if !storage.list_keys()?.is_empty() {
+        return Err(CryptoError::Storage(
+            "Backups can only be restored into an empty key store".to_string(),
+        ));
+    }
+
+    let contents = decrypt_backup(backup, code)?;
+    for (key_id, key) in &contents.keys {
+        storage.store_key(key_id, key)?;
+    }
+
+    let session_ids = storage.list_ratchet_sessions()?;
+    for session_id in &session_ids {
+        if let Some(mut ratchet) = storage.load_ratchet_state(session_id)? {
+            ratchet.make_receive_only();
+            storage.store_ratchet_state(session_id, &ratchet)?;
+        }
+    }
+
+    Ok(RestoredBackup {
+        key_count: contents.keys.len(),
+        session_ids,
+        history: contents.history,
+    })
+}
+
+fn check_params(params: KdfParams) -> Result<()> {
+    if params.memory_kib > MAX_MEMORY_KIB
+        || params.iterations > MAX_ITERATIONS
+        || params.parallelism > MAX_PARALLELISM
+    {
+        return Err(CryptoError::KeyGeneration(
+            "Backup KDF parameters out of range".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn backup_aad(header: &[u8]) -> Vec<u8> {
+    let mut aad = Vec::with_capacity(BACKUP_AAD_PREFIX.len() + header.len());
+    aad.extend_from_slice(BACKUP_AAD_PREFIX);
+    aad.extend_from_slice(header);
+    aad
+}
+
+fn encode_payload(keys: &BTreeMap<String, Zeroizing<Vec<u8>>>, history: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+    let too_long = || CryptoError::Encryption("Key record too large for backup".to_string());
+
+    let mut payload = Zeroizing::new(Vec::new());
+    payload.extend_from_slice(&u32::try_from(keys.len()).map_err(|_| too_long())?.to_be_bytes());
+    for (key_id, key) in keys {
+        payload.extend_from_slice(&u16::try_from(key_id.len()).map_err(|_| too_long())?.to_be_bytes());
+        payload.extend_from_slice(key_id.as_bytes());
+        payload.extend_from_slice(&u32::try_from(key.len()).map_err(|_| too_long())?.to_be_bytes());
+        payload.extend_from_slice(key);
+    }
+    payload.extend_from_slice(history);
+    Ok(payload)
+}
+
+fn decode_payload(payload: &[u8]) -> Result<BackupContents> {
+    let malformed = || CryptoError::Decryption("Malformed backup payload".to_string());
+
+    let mut rest = payload;
+    let mut take = |len: usize| -> Result<&[u8]> {
+        if rest.len() < len {
+            return Err(malformed());
+        }
+        let (field, remaining) = rest.split_at(len);
+        rest = remaining;
+        Ok(field)
+    };
+
+    let key_count = u32::from_be_bytes(take(4)?.try_into().unwrap());
+    let mut keys = BTreeMap::new();
+    for _ in 0..key_count {
+        let id_len = u16::from_be_bytes(take(2)?.try_into().unwrap()) as usize;
+        let key_id = String::from_utf8(take(id_len)?.to_vec()).map_err(|_| malformed())?;
+        let key_len = u32::from_be_bytes(take(4)?.try_into().unwrap()) as usize;
+        let key = Zeroizing::new(take(key_len)?.to_vec());
+        keys.insert(key_id, key);
+    }
+
+    Ok(BackupContents {
+        keys,
+        history: Zeroizing::new(rest.to_vec()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::double_ratchet::DoubleRatchet;
+    use crate::key_storage::MemoryBackend;
+    use crate::x3dh::IdentityKeyPair;
+
+    /// Cheap Argon2 parameters so tests run quickly
+    fn test_params() -> KdfParams {
+        KdfParams {
+            memory_kib: 64,
+            iterations: 1,
+            parallelism: 1,
+        }
+    }
+
+    fn test_storage() -> KeyStorage {
+        KeyStorage::open_with_params(MemoryBackend::new(), b"device passphrase", test_params()).unwrap()
+    }
+
+    #[test]
+    fn test_recovery_code_format() {
+        let code = RecoveryCode::generate();
+        assert_eq!(code.as_str().len(), 64);
+
+        let grouped = code.to_grouped_string();
+        assert_eq!(grouped.len(), 64 + 15);
+        let parsed = RecoveryCode::parse(&grouped.to_uppercase()).unwrap();
+        assert_eq!(parsed.as_str(), code.as_str());
+
+        assert!(RecoveryCode::parse("too short").is_err());
+        assert!(RecoveryCode::parse(&"g".repeat(64)).is_err());
+    }
+
+    #[test]
+    fn test_backup_restore_round_trip() {
+        let storage = test_storage();
+        let identity = IdentityKeyPair::generate().unwrap();
+        storage.store_identity_key(&identity).unwrap();
+
+        let bob_secret = x25519_dalek::StaticSecret::random_from_rng(OsRng);
+        let bob_public = x25519_dalek::PublicKey::from(&bob_secret);
+        let ratchet = DoubleRatchet::init_alice(&[1u8; 32], bob_public).unwrap();
+        storage.store_ratchet_state("bob", &ratchet).unwrap();
+
+        let code = RecoveryCode::generate();
+        let backup = create_backup_with_params(&storage, b"message history", &code, test_params()).unwrap();
+
+        // Restore on a new device with its own passphrase
+        let new_storage = KeyStorage::open_with_params(MemoryBackend::new(), b"new device", test_params()).unwrap();
+        let restored = restore_backup(&backup, &RecoveryCode::parse(code.as_str()).unwrap(), &new_storage).unwrap();
+
+        assert_eq!(restored.key_count, 2);
+        assert_eq!(restored.session_ids, vec!["bob".to_string()]);
+        assert_eq!(restored.history.as_slice(), b"message history");
+        assert_eq!(
+            new_storage.load_identity_key().unwrap().unwrap().public_bytes(),
+            identity.public_bytes()
+        );
+        assert_eq!(
+            new_storage.load_ratchet_state("bob").unwrap().unwrap().public_key(),
+            ratchet.public_key()
+        );
+    }
+
+    #[test]
+    fn test_restored_sessions_do_not_reuse_sending_chain() {
+        let bob_secret = x25519_dalek::StaticSecret::random_from_rng(OsRng);
+        let bob_public = x25519_dalek::PublicKey::from(&bob_secret);
+        let mut alice = DoubleRatchet::init_alice_with_header_encryption(&[7u8; 32], bob_public).unwrap();
+        let mut bob = DoubleRatchet::init_bob_with_header_encryption(&[7u8; 32], bob_secret).unwrap();
+
+        let hello = alice.encrypt_header_encrypted(b"hello", b"ad").unwrap();
+        assert_eq!(bob.decrypt_header_encrypted(&hello, b"ad").unwrap(), b"hello");
+
+        let storage = test_storage();
+        storage.store_ratchet_state("bob", &alice).unwrap();
+        let code = RecoveryCode::generate();
+        let backup = create_backup_with_params(&storage, b"", &code, test_params()).unwrap();
+
+        // The old device keeps sending after the backup was made
+        let from_old_device = alice.encrypt_header_encrypted(b"old device", b"ad").unwrap();
+        assert_eq!(bob.decrypt_header_encrypted(&from_old_device, b"ad").unwrap(), b"old device");
+
+        let new_storage = KeyStorage::open_with_params(MemoryBackend::new(), b"new device", test_params()).unwrap();
+        restore_backup(&backup, &code, &new_storage).unwrap();
+        let mut restored = new_storage.load_ratchet_state("bob").unwrap().unwrap();
+
+        // The backed up sending chain is never used again
+        assert!(!restored.can_send());
+        assert!(restored.encrypt_header_encrypted(b"new device", b"ad").is_err());
+
+        // Bob's reply carries a new ratchet key, giving a fresh sending chain
+        let reply = bob.encrypt_header_encrypted(b"reply", b"ad").unwrap();
+        assert_eq!(restored.decrypt_header_encrypted(&reply, b"ad").unwrap(), b"reply");
+        assert!(restored.can_send());
+        assert_ne!(restored.public_key(), alice.public_key());
+
+        let from_new_device = restored.encrypt_header_encrypted(b"new device", b"ad").unwrap();
+        assert_eq!(bob.decrypt_header_encrypted(&from_new_device, b"ad").unwrap(), b"new device");
+    }
+
+    #[test]
+    fn test_backup_rejects_wrong_code_and_tampering() {
+        let storage = test_storage();
+        storage.store_key("key", b"secret").unwrap();
+
+        let code = RecoveryCode::generate();
+        let backup = create_backup_with_params(&storage, b"history", &code, test_params()).unwrap();
+
+        assert!(decrypt_backup(&backup, &RecoveryCode::generate()).is_err());
+
+        // Header fields are authenticated too
+        for index in [5, HEADER_LEN - 1, backup.len() - 1] {
+            let mut tampered = backup.clone();
+            tampered[index] ^= 1;
+            assert!(decrypt_backup(&tampered, &code).is_err());
+        }
+    }
+
+    #[test]
+    fn test_backup_rejects_excessive_kdf_params() {
+        let storage = test_storage();
+        let code = RecoveryCode::generate();
+        let mut backup = create_backup_with_params(&storage, b"", &code, test_params()).unwrap();
+
+        backup[1..5].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(decrypt_backup(&backup, &code).is_err());
+    }
+
+    #[test]
+    fn test_restore_requires_empty_store() {
+        let storage = test_storage();
+        storage.store_key("key", b"secret").unwrap();
+
+        let code = RecoveryCode::generate();
+        let backup = create_backup_with_params(&storage, b"", &code, test_params()).unwrap();
+
+        assert!(restore_backup(&backup, &code, &storage).is_err());
+    }
+}
@@ -1,6 +1,13 @@
 /// Double Ratchet algorithm for forward-secret E2EE messaging
 ///
 /// Based on Signal Protocol specification
+///
+/// Sessions can optionally use the header encryption variant (section 4 of the
+/// specification): message headers are encrypted with header keys that ratchet
+/// together with the root key, so the ratchet public key and message counters
+/// are not visible to the server.
+use crate::franking::{self, FrankedPlaintext, FrankingCommitment};
+use crate::secret::{ct_eq, SecretKey};
 use crate::{CryptoError, Result};
 use aes_gcm::{
     aead::{Aead, KeyInit},
@@ -9,31 +16,60 @@ use aes_gcm::{
 use hkdf::Hkdf;
 use rand::rngs::OsRng;
 use rand::RngCore;
-use sha2::Sha256;
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
+use std::fmt;
 use x25519_dalek::{PublicKey as X25519PublicKey, StaticSecret};
+use zeroize::Zeroizing;
 
 // Constants for key derivation
 const CHAIN_KEY_INFO: &[u8] = b"guardyn-chain-key";
 const MESSAGE_KEY_INFO: &[u8] = b"guardyn-message-key";
 const ROOT_KEY_INFO: &[u8] = b"guardyn-root-key";
-const MAX_SKIP: usize = 1000; // Maximum number of skipped messages to store
+const ROOT_KEY_HE_INFO: &[u8] = b"guardyn-root-key-he";
+const HEADER_KEY_INFO: &[u8] = b"guardyn-header-keys";
+
+/// Maximum number of skipped message keys a session stores
+pub const MAX_SKIP: usize = 1000;
+
+// Serialized session format
+const SESSION_MAGIC: &[u8; 8] = b"GDNRATCH";
+const SESSION_FLAG_ENCRYPTED: u8 = 0x01;
+
+/// Current version of the serialized session format
+pub const SESSION_FORMAT_VERSION: u8 = 1;
+
+// Serialized session field tags
+const FIELD_DH_SELF: u8 = 1;
+const FIELD_DH_REMOTE: u8 = 2;
+const FIELD_ROOT_KEY: u8 = 3;
+const FIELD_SENDING_CHAIN_KEY: u8 = 4;
+const FIELD_SENDING_MESSAGE_NUMBER: u8 = 5;
+const FIELD_RECEIVING_CHAIN_KEY: u8 = 6;
+const FIELD_RECEIVING_MESSAGE_NUMBER: u8 = 7;
+const FIELD_PREVIOUS_CHAIN_LENGTH: u8 = 8;
+const FIELD_SKIPPED_MESSAGE_KEY: u8 = 9;
+const FIELD_SENDING_HEADER_KEY: u8 = 16;
+const FIELD_RECEIVING_HEADER_KEY: u8 = 17;
+const FIELD_NEXT_SENDING_HEADER_KEY: u8 = 18;
+const FIELD_NEXT_RECEIVING_HEADER_KEY: u8 = 19;
+const FIELD_SKIPPED_HEADER_MESSAGE_KEY: u8 = 20;
 
 /// Chain key for symmetric ratchet
 #[derive(Clone)]
 struct ChainKey {
-    key: [u8; 32],
+    key: SecretKey<32>,
 }
 
 impl ChainKey {
     /// Create new chain key from bytes
     fn new(key: [u8; 32]) -> Self {
-        Self { key }
+        Self { key: SecretKey::new(key) }
     }
 
     /// Derive next chain key using HKDF
     fn next(&self) -> Result<Self> {
-        let hkdf = Hkdf::<Sha256>::new(None, &self.key);
+        let hkdf = Hkdf::<Sha256>::new(None, self.key.expose_secret());
         let mut next_key = [0u8; 32];
         hkdf.expand(CHAIN_KEY_INFO, &mut next_key)
             .map_err(|e| CryptoError::KeyGeneration(format!("Chain key derivation failed: {}", e)))?;
@@ -42,7 +78,7 @@ impl ChainKey {
 
     /// Derive message key from current chain key
     fn message_key(&self) -> Result<MessageKey> {
-        let hkdf = Hkdf::<Sha256>::new(None, &self.key);
+        let hkdf = Hkdf::<Sha256>::new(None, self.key.expose_secret());
         let mut msg_key = [0u8; 32];
         hkdf.expand(MESSAGE_KEY_INFO, &mut msg_key)
             .map_err(|e| CryptoError::KeyGeneration(format!("Message key derivation failed: {}", e)))?;
@@ -53,17 +89,17 @@ impl ChainKey {
 /// Message key for encrypting/decrypting individual messages
 #[derive(Clone)]
 struct MessageKey {
-    key: [u8; 32],
+    key: SecretKey<32>,
 }
 
 impl MessageKey {
     fn new(key: [u8; 32]) -> Self {
-        Self { key }
+        Self { key: SecretKey::new(key) }
     }
 
     /// Encrypt plaintext with AES-256-GCM
     fn encrypt(&self, plaintext: &[u8], associated_data: &[u8]) -> Result<Vec<u8>> {
-        let cipher = Aes256Gcm::new((&self.key).into());
+        let cipher = Aes256Gcm::new(self.key.expose_secret().into());
         
         // Generate cryptographically secure random nonce (12 bytes)
         let mut nonce_bytes = [0u8; 12];
@@ -88,7 +124,7 @@ impl MessageKey {
 
         let (nonce_bytes, actual_ciphertext) = ciphertext.split_at(12);
         let nonce = Nonce::from_slice(nonce_bytes);
-        let cipher = Aes256Gcm::new((&self.key).into());
+        let cipher = Aes256Gcm::new(self.key.expose_secret().into());
 
         cipher
             .decrypt(nonce, aes_gcm::aead::Payload { msg: actual_ciphertext, aad: associated_data })
@@ -99,17 +135,17 @@ impl MessageKey {
 /// Root key for DH ratchet
 #[derive(Clone)]
 struct RootKey {
-    key: [u8; 32],
+    key: SecretKey<32>,
 }
 
 impl RootKey {
     fn new(key: [u8; 32]) -> Self {
-        Self { key }
+        Self { key: SecretKey::new(key) }
     }
 
     /// Perform DH ratchet step: derive new root key and chain key
     fn dh_ratchet(&self, dh_output: &[u8]) -> Result<(Self, ChainKey)> {
-        let hkdf = Hkdf::<Sha256>::new(Some(&self.key), dh_output);
+        let hkdf = Hkdf::<Sha256>::new(Some(self.key.expose_secret()), dh_output);
 
         let mut new_root_key = [0u8; 32];
         let mut new_chain_key = [0u8; 32];
@@ -123,6 +159,70 @@ impl RootKey {
 
         Ok((RootKey::new(new_root_key), ChainKey::new(new_chain_key)))
     }
+
+    /// Perform DH ratchet step for header encryption:
+    /// derive new root key, chain key and next header key
+    fn dh_ratchet_he(&self, dh_output: &[u8]) -> Result<(Self, ChainKey, HeaderKey)> {
+        let hkdf = Hkdf::<Sha256>::new(Some(self.key.expose_secret()), dh_output);
+
+        let mut new_root_key = [0u8; 32];
+        let mut new_chain_key = [0u8; 32];
+        let mut next_header_key = [0u8; 32];
+        let mut output = [0u8; 96];
+
+        hkdf.expand(ROOT_KEY_HE_INFO, &mut output)
+            .map_err(|e| CryptoError::KeyGeneration(format!("Root key derivation failed: {}", e)))?;
+
+        new_root_key.copy_from_slice(&output[..32]);
+        new_chain_key.copy_from_slice(&output[32..64]);
+        next_header_key.copy_from_slice(&output[64..]);
+
+        Ok((
+            RootKey::new(new_root_key),
+            ChainKey::new(new_chain_key),
+            HeaderKey::new(next_header_key),
+        ))
+    }
+}
+
+/// Header key for encrypting message headers
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct HeaderKey {
+    key: SecretKey<32>,
+}
+
+impl HeaderKey {
+    fn new(key: [u8; 32]) -> Self {
+        Self { key: SecretKey::new(key) }
+    }
+
+    /// Encrypt a serialized header with AES-256-GCM
+    fn encrypt(&self, header: &MessageHeader) -> Result<Vec<u8>> {
+        MessageKey::new(*self.key.expose_secret()).encrypt(&header.to_bytes(), HEADER_KEY_INFO)
+    }
+
+    /// Try to decrypt an encrypted header, returning None if this is not the right key
+    fn decrypt(&self, encrypted_header: &[u8]) -> Option<MessageHeader> {
+        let bytes = MessageKey::new(*self.key.expose_secret())
+            .decrypt(encrypted_header, HEADER_KEY_INFO)
+            .ok()?;
+        MessageHeader::from_bytes(&bytes).ok()
+    }
+}
+
+/// Header key state for sessions using header encryption
+#[derive(Clone)]
+struct HeaderKeyState {
+    // Current header keys (None until the first DH ratchet step)
+    sending: Option<HeaderKey>,
+    receiving: Option<HeaderKey>,
+
+    // Header keys for the next DH ratchet step
+    next_sending: HeaderKey,
+    next_receiving: HeaderKey,
+
+    // Skipped message keys indexed by the header key of their chain
+    skipped_message_keys: HashMap<(HeaderKey, u32), MessageKey>,
 }
 
 /// Message header containing DH public key and message counter
@@ -207,6 +307,49 @@ impl EncryptedMessage {
     }
 }
 
+/// Encrypted message with encrypted header (header encryption mode)
+pub struct HeaderEncryptedMessage {
+    pub encrypted_header: Vec<u8>,
+    pub ciphertext: Vec<u8>,
+}
+
+impl HeaderEncryptedMessage {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut result = Vec::new();
+        // Use Big-Endian (Network Byte Order) per RFC 1700
+        result.extend_from_slice(&(self.encrypted_header.len() as u32).to_be_bytes());
+        result.extend_from_slice(&self.encrypted_header);
+        result.extend_from_slice(&self.ciphertext);
+        result
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 4 {
+            return Err(CryptoError::Protocol("Message too short".to_string()));
+        }
+
+        let mut header_len_bytes = [0u8; 4];
+        header_len_bytes.copy_from_slice(&bytes[..4]);
+        let header_len = u32::from_be_bytes(header_len_bytes) as usize;
+
+        if bytes.len() < 4 + header_len {
+            return Err(CryptoError::Protocol("Invalid message format".to_string()));
+        }
+
+        Ok(Self {
+            encrypted_header: bytes[4..4 + header_len].to_vec(),
+            ciphertext: bytes[4 + header_len..].to_vec(),
+        })
+    }
+
+    /// Associated data for the message body: caller AD || encrypted header
+    fn associated_data(&self, associated_data: &[u8]) -> Vec<u8> {
+        let mut ad = associated_data.to_vec();
+        ad.extend_from_slice(&self.encrypted_header);
+        ad
+    }
+}
+
 /// Double Ratchet state
 pub struct DoubleRatchet {
     // DH ratchet state
@@ -229,6 +372,24 @@ pub struct DoubleRatchet {
 
     // Skipped message keys for out-of-order handling
     skipped_message_keys: HashMap<(X25519PublicKey, u32), MessageKey>,
+
+    // Header encryption state (None if header encryption is disabled)
+    header_keys: Option<HeaderKeyState>,
+}
+
+/// Only public state is printed; keys never appear in `Debug` output
+impl fmt::Debug for DoubleRatchet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DoubleRatchet")
+            .field("public_key", &self.public_key())
+            .field("dh_remote", &self.dh_remote)
+            .field("sending_message_number", &self.sending_message_number)
+            .field("receiving_message_number", &self.receiving_message_number)
+            .field("previous_chain_length", &self.previous_chain_length)
+            .field("skipped_messages", &self.skipped_messages_count())
+            .field("header_encryption", &self.has_header_encryption())
+            .finish_non_exhaustive()
+    }
 }
 
 impl DoubleRatchet {
@@ -258,6 +419,7 @@ impl DoubleRatchet {
             receiving_message_number: 0,
             previous_chain_length: 0,
             skipped_message_keys: HashMap::new(),
+            header_keys: None,
         })
     }
 
@@ -282,6 +444,68 @@ impl DoubleRatchet {
             receiving_message_number: 0,
             previous_chain_length: 0,
             skipped_message_keys: HashMap::new(),
+            header_keys: None,
+        })
+    }
+
+    /// Initialize Double Ratchet with header encryption as sender (Alice)
+    ///
+    /// The root key and the initial header keys are derived from the X3DH
+    /// shared secret, so both parties agree on them without extra messages.
+    pub fn init_alice_with_header_encryption(
+        shared_secret: &[u8],
+        bob_public_key: X25519PublicKey,
+    ) -> Result<Self> {
+        let (root_key, header_key_a, next_header_key_b) = derive_header_encryption_keys(shared_secret)?;
+
+        let dh_self = StaticSecret::random_from_rng(OsRng);
+        let dh_output = dh_self.diffie_hellman(&bob_public_key);
+        let (new_root_key, sending_chain_key, next_sending) = root_key.dh_ratchet_he(dh_output.as_bytes())?;
+
+        Ok(Self {
+            dh_self,
+            dh_remote: Some(bob_public_key),
+            root_key: new_root_key,
+            sending_chain_key: Some(sending_chain_key),
+            sending_message_number: 0,
+            receiving_chain_key: None,
+            receiving_message_number: 0,
+            previous_chain_length: 0,
+            skipped_message_keys: HashMap::new(),
+            header_keys: Some(HeaderKeyState {
+                sending: Some(header_key_a),
+                receiving: None,
+                next_sending,
+                next_receiving: next_header_key_b,
+                skipped_message_keys: HashMap::new(),
+            }),
+        })
+    }
+
+    /// Initialize Double Ratchet with header encryption as receiver (Bob)
+    ///
+    /// `dh_self` is the key pair whose public key Alice used in
+    /// [`DoubleRatchet::init_alice_with_header_encryption`] (Bob's signed pre-key).
+    pub fn init_bob_with_header_encryption(shared_secret: &[u8], dh_self: StaticSecret) -> Result<Self> {
+        let (root_key, header_key_a, next_header_key_b) = derive_header_encryption_keys(shared_secret)?;
+
+        Ok(Self {
+            dh_self,
+            dh_remote: None,
+            root_key,
+            sending_chain_key: None,
+            sending_message_number: 0,
+            receiving_chain_key: None,
+            receiving_message_number: 0,
+            previous_chain_length: 0,
+            skipped_message_keys: HashMap::new(),
+            header_keys: Some(HeaderKeyState {
+                sending: None,
+                receiving: None,
+                next_sending: next_header_key_b,
+                next_receiving: header_key_a,
+                skipped_message_keys: HashMap::new(),
+            }),
         })
     }
 
@@ -290,8 +514,37 @@ impl DoubleRatchet {
         X25519PublicKey::from(&self.dh_self)
     }
 
+    /// Returns true if this session uses header encryption
+    pub fn has_header_encryption(&self) -> bool {
+        self.header_keys.is_some()
+    }
+
+    /// Returns true if this session has a sending chain to encrypt with
+    pub fn can_send(&self) -> bool {
+        self.sending_chain_key.is_some()
+    }
+
+    /// Drop the sending chain, making the session receive-only
+    ///
+    /// For state copied from another device (e.g. restored from a backup),
+    /// whose sending chain that device may already have used: encrypting
+    /// fails until a message from the peer carries a new ratchet key, and
+    /// the resulting DH ratchet step derives a fresh sending chain.
+    pub fn make_receive_only(&mut self) {
+        self.sending_chain_key = None;
+    }
+
     /// Encrypt a message
+    ///
+    /// Fails for sessions using header encryption, which must use
+    /// [`DoubleRatchet::encrypt_header_encrypted`] so the header is never sent in the clear.
     pub fn encrypt(&mut self, plaintext: &[u8], associated_data: &[u8]) -> Result<EncryptedMessage> {
+        if self.header_keys.is_some() {
+            return Err(CryptoError::Protocol(
+                "Session uses header encryption".to_string()
+            ));
+        }
+
         let chain_key = self.sending_chain_key.as_ref()
             .ok_or_else(|| CryptoError::Protocol("No sending chain key".to_string()))?;
 
@@ -312,7 +565,20 @@ impl DoubleRatchet {
     }
 
     /// Decrypt a message
+    ///
+    /// A message that fails to decrypt (forged, or replayed from an old chain)
+    /// leaves the session unchanged.
     pub fn decrypt(&mut self, message: &EncryptedMessage, associated_data: &[u8]) -> Result<Vec<u8>> {
+        self.transaction(|state| state.decrypt_uncommitted(message, associated_data))
+    }
+
+    fn decrypt_uncommitted(&mut self, message: &EncryptedMessage, associated_data: &[u8]) -> Result<Vec<u8>> {
+        if self.header_keys.is_some() {
+            return Err(CryptoError::Protocol(
+                "Session uses header encryption".to_string()
+            ));
+        }
+
         // Check if we have a skipped message key
         let key = (message.header.dh_public_key, message.header.message_number);
         if let Some(message_key) = self.skipped_message_keys.remove(&key) {
@@ -322,6 +588,8 @@ impl DoubleRatchet {
         // Check if we need to perform DH ratchet
         if let Some(remote_key) = self.dh_remote {
             if message.header.dh_public_key.as_bytes() != remote_key.as_bytes() {
+                // Keep the keys of messages still missing from the previous chain
+                self.skip_message_keys(message.header.previous_chain_length)?;
                 self.dh_ratchet_receive(&message.header)?;
             }
         } else {
@@ -346,6 +614,197 @@ impl DoubleRatchet {
         Ok(plaintext)
     }
 
+    /// Encrypt a franked message (see [`crate::franking`])
+    ///
+    /// # Returns
+    /// Tuple of (message, commitment to send to the server alongside it)
+    pub fn encrypt_franked(
+        &mut self,
+        plaintext: &[u8],
+        associated_data: &[u8],
+    ) -> Result<(EncryptedMessage, FrankingCommitment)> {
+        let (payload, commitment) = franking::frank(plaintext)?;
+        let message = self.encrypt(&payload, &franking::associated_data(associated_data, &commitment))?;
+        Ok((message, commitment))
+    }
+
+    /// Decrypt a franked message
+    ///
+    /// `commitment` is taken from the server's franking tag; a message that
+    /// does not match it is rejected like a forged one.
+    pub fn decrypt_franked(
+        &mut self,
+        message: &EncryptedMessage,
+        associated_data: &[u8],
+        commitment: &FrankingCommitment,
+    ) -> Result<FrankedPlaintext> {
+        self.transaction(|state| {
+            let payload = Zeroizing::new(
+                state.decrypt_uncommitted(message, &franking::associated_data(associated_data, commitment))?,
+            );
+            franking::open(&payload, commitment)
+        })
+    }
+
+    /// Encrypt a message with an encrypted header
+    pub fn encrypt_header_encrypted(
+        &mut self,
+        plaintext: &[u8],
+        associated_data: &[u8],
+    ) -> Result<HeaderEncryptedMessage> {
+        let header_key = self.header_keys.as_ref()
+            .ok_or_else(|| CryptoError::Protocol("Header encryption not enabled".to_string()))?
+            .sending
+            .as_ref()
+            .ok_or_else(|| CryptoError::Protocol("No sending header key".to_string()))?;
+        let chain_key = self.sending_chain_key.as_ref()
+            .ok_or_else(|| CryptoError::Protocol("No sending chain key".to_string()))?;
+
+        let header = MessageHeader {
+            dh_public_key: self.public_key(),
+            previous_chain_length: self.previous_chain_length,
+            message_number: self.sending_message_number,
+        };
+        let encrypted_header = header_key.encrypt(&header)?;
+
+        let mut message = HeaderEncryptedMessage {
+            encrypted_header,
+            ciphertext: Vec::new(),
+        };
+        let message_key = chain_key.message_key()?;
+        message.ciphertext = message_key.encrypt(plaintext, &message.associated_data(associated_data))?;
+
+        // Advance sending chain
+        self.sending_chain_key = Some(chain_key.next()?);
+        self.sending_message_number += 1;
+
+        Ok(message)
+    }
+
+    /// Decrypt a message with an encrypted header
+    ///
+    /// The header is decrypted by trial: first with the header keys of chains
+    /// that have skipped messages, then with the current receiving header key,
+    /// and finally with the next header key, which triggers a DH ratchet step.
+    /// A message that fails to decrypt leaves the session unchanged.
+    pub fn decrypt_header_encrypted(
+        &mut self,
+        message: &HeaderEncryptedMessage,
+        associated_data: &[u8],
+    ) -> Result<Vec<u8>> {
+        self.transaction(|state| state.decrypt_header_encrypted_uncommitted(message, associated_data))
+    }
+
+    /// Encrypt a franked message with an encrypted header
+    pub fn encrypt_header_encrypted_franked(
+        &mut self,
+        plaintext: &[u8],
+        associated_data: &[u8],
+    ) -> Result<(HeaderEncryptedMessage, FrankingCommitment)> {
+        let (payload, commitment) = franking::frank(plaintext)?;
+        let message =
+            self.encrypt_header_encrypted(&payload, &franking::associated_data(associated_data, &commitment))?;
+        Ok((message, commitment))
+    }
+
+    /// Decrypt a franked message with an encrypted header
+    pub fn decrypt_header_encrypted_franked(
+        &mut self,
+        message: &HeaderEncryptedMessage,
+        associated_data: &[u8],
+        commitment: &FrankingCommitment,
+    ) -> Result<FrankedPlaintext> {
+        self.transaction(|state| {
+            let payload = Zeroizing::new(state.decrypt_header_encrypted_uncommitted(
+                message,
+                &franking::associated_data(associated_data, commitment),
+            )?);
+            franking::open(&payload, commitment)
+        })
+    }
+
+    fn decrypt_header_encrypted_uncommitted(
+        &mut self,
+        message: &HeaderEncryptedMessage,
+        associated_data: &[u8],
+    ) -> Result<Vec<u8>> {
+        let associated_data = message.associated_data(associated_data);
+        let header_keys = self.header_keys.as_mut()
+            .ok_or_else(|| CryptoError::Protocol("Header encryption not enabled".to_string()))?;
+
+        // Check if the message belongs to a chain with skipped message keys
+        let mut skipped_header_keys: Vec<HeaderKey> = header_keys.skipped_message_keys
+            .keys()
+            .map(|(header_key, _)| header_key.clone())
+            .collect();
+        skipped_header_keys.sort_unstable_by(|a, b| a.key.expose_secret().cmp(b.key.expose_secret()));
+        skipped_header_keys.dedup();
+
+        for header_key in skipped_header_keys {
+            if let Some(header) = header_key.decrypt(&message.encrypted_header) {
+                if let Some(message_key) = header_keys.skipped_message_keys
+                    .remove(&(header_key, header.message_number))
+                {
+                    return message_key.decrypt(&message.ciphertext, &associated_data);
+                }
+            }
+        }
+
+        // Decrypt header with the current or the next receiving header key
+        let current = header_keys.receiving
+            .as_ref()
+            .and_then(|header_key| header_key.decrypt(&message.encrypted_header));
+        let (header, dh_ratchet) = match current {
+            Some(header) => (header, false),
+            None => {
+                let header = header_keys.next_receiving
+                    .decrypt(&message.encrypted_header)
+                    .ok_or_else(|| CryptoError::Decryption("Failed to decrypt message header".to_string()))?;
+                (header, true)
+            }
+        };
+
+        if dh_ratchet {
+            self.skip_message_keys(header.previous_chain_length)?;
+            self.dh_ratchet_receive(&header)?;
+        }
+
+        // Skip messages if needed
+        self.skip_message_keys(header.message_number)?;
+
+        // Decrypt the message
+        let chain_key = self.receiving_chain_key.as_ref()
+            .ok_or_else(|| CryptoError::Protocol("No receiving chain key".to_string()))?;
+
+        let message_key = chain_key.message_key()?;
+        let plaintext = message_key.decrypt(&message.ciphertext, &associated_data)?;
+
+        // Advance receiving chain
+        self.receiving_chain_key = Some(chain_key.next()?);
+        self.receiving_message_number += 1;
+
+        Ok(plaintext)
+    }
+
+    /// Run `f` on a copy of the state and keep the copy only if `f` succeeds
+    fn transaction<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let mut state = Self {
+            dh_self: self.dh_self.clone(),
+            dh_remote: self.dh_remote,
+            root_key: self.root_key.clone(),
+            sending_chain_key: self.sending_chain_key.clone(),
+            sending_message_number: self.sending_message_number,
+            receiving_chain_key: self.receiving_chain_key.clone(),
+            receiving_message_number: self.receiving_message_number,
+            previous_chain_length: self.previous_chain_length,
+            skipped_message_keys: self.skipped_message_keys.clone(),
+            header_keys: self.header_keys.clone(),
+        };
+        let result = f(&mut state)?;
+        *self = state;
+        Ok(result)
+    }
+
     /// Perform DH ratchet when receiving new public key
     fn dh_ratchet_receive(&mut self, header: &MessageHeader) -> Result<()> {
         // Store previous chain length
@@ -356,6 +815,29 @@ impl DoubleRatchet {
         // Update remote DH key
         self.dh_remote = Some(header.dh_public_key);
 
+        if let Some(header_keys) = self.header_keys.as_mut() {
+            // Next header keys become current, new next header keys come from the root chain
+            header_keys.sending = Some(header_keys.next_sending.clone());
+            header_keys.receiving = Some(header_keys.next_receiving.clone());
+
+            let dh_output = self.dh_self.diffie_hellman(&header.dh_public_key);
+            let (new_root_key, receiving_chain_key, next_receiving) =
+                self.root_key.dh_ratchet_he(dh_output.as_bytes())?;
+            self.root_key = new_root_key;
+            self.receiving_chain_key = Some(receiving_chain_key);
+            header_keys.next_receiving = next_receiving;
+
+            self.dh_self = StaticSecret::random_from_rng(OsRng);
+            let dh_output = self.dh_self.diffie_hellman(&header.dh_public_key);
+            let (new_root_key, sending_chain_key, next_sending) =
+                self.root_key.dh_ratchet_he(dh_output.as_bytes())?;
+            self.root_key = new_root_key;
+            self.sending_chain_key = Some(sending_chain_key);
+            header_keys.next_sending = next_sending;
+
+            return Ok(());
+        }
+
         // Perform DH and derive new receiving chain
         let dh_output = self.dh_self.diffie_hellman(&header.dh_public_key);
         let (new_root_key, receiving_chain_key) = self.root_key.dh_ratchet(dh_output.as_bytes())?;
@@ -378,20 +860,31 @@ impl DoubleRatchet {
             let mut current_chain_key = chain_key.clone();
 
             while self.receiving_message_number < until {
-                if self.skipped_message_keys.len() >= MAX_SKIP {
+                if self.skipped_messages_count() >= MAX_SKIP {
                     return Err(CryptoError::Protocol(
                         format!("Too many skipped messages (max: {})", MAX_SKIP)
                     ));
                 }
 
                 let message_key = current_chain_key.message_key()?;
-                let remote_key = self.dh_remote
-                    .ok_or_else(|| CryptoError::Protocol("No remote key".to_string()))?;
 
-                self.skipped_message_keys.insert(
-                    (remote_key, self.receiving_message_number),
-                    message_key,
-                );
+                if let Some(header_keys) = self.header_keys.as_mut() {
+                    let header_key = header_keys.receiving
+                        .clone()
+                        .ok_or_else(|| CryptoError::Protocol("No receiving header key".to_string()))?;
+                    header_keys.skipped_message_keys.insert(
+                        (header_key, self.receiving_message_number),
+                        message_key,
+                    );
+                } else {
+                    let remote_key = self.dh_remote
+                        .ok_or_else(|| CryptoError::Protocol("No remote key".to_string()))?;
+
+                    self.skipped_message_keys.insert(
+                        (remote_key, self.receiving_message_number),
+                        message_key,
+                    );
+                }
 
                 current_chain_key = current_chain_key.next()?;
                 self.receiving_message_number += 1;
@@ -403,83 +896,300 @@ impl DoubleRatchet {
         Ok(())
     }
 
+    /// Root, chain and header keys currently held, for redaction tests
+    #[cfg(test)]
+    pub(crate) fn secret_keys(&self) -> Vec<[u8; 32]> {
+        let mut keys = vec![*self.root_key.key.expose_secret()];
+        keys.extend(self.sending_chain_key.iter().map(|chain_key| *chain_key.key.expose_secret()));
+        keys.extend(self.receiving_chain_key.iter().map(|chain_key| *chain_key.key.expose_secret()));
+        if let Some(ref header_keys) = self.header_keys {
+            keys.extend(
+                [&header_keys.sending, &header_keys.receiving]
+                    .into_iter()
+                    .flatten()
+                    .chain([&header_keys.next_sending, &header_keys.next_receiving])
+                    .map(|header_key| *header_key.key.expose_secret()),
+            );
+        }
+        keys
+    }
+
     /// Get number of skipped messages in cache
     pub fn skipped_messages_count(&self) -> usize {
-        self.skipped_message_keys.len()
+        let skipped_header_encrypted = self.header_keys
+            .as_ref()
+            .map_or(0, |header_keys| header_keys.skipped_message_keys.len());
+        self.skipped_message_keys.len() + skipped_header_encrypted
     }
 
     /// Serialize Double Ratchet state for persistent storage
     ///
-    /// Format (all little-endian):
-    /// - dh_self (32 bytes)
-    /// - dh_remote_present (1 byte: 0 or 1)
-    /// - dh_remote (32 bytes, if present)
-    /// - root_key (32 bytes)
-    /// - sending_chain_key_present (1 byte)
-    /// - sending_chain_key (32 bytes, if present)
-    /// - sending_message_number (4 bytes)
-    /// - receiving_chain_key_present (1 byte)
-    /// - receiving_chain_key (32 bytes, if present)
-    /// - receiving_message_number (4 bytes)
-    /// - previous_chain_length (4 bytes)
-    /// - skipped_keys_count (4 bytes)
-    /// - skipped_keys [(dh_key, msg_num, key) repeated]
-    pub fn serialize(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
+    /// Format (version 1):
+    /// - magic "GDNRATCH" (8 bytes)
+    /// - format version (1 byte)
+    /// - flags (1 byte, bit 0: encrypted)
+    /// - body: fields as (tag: 1 byte, length: u32 BE, value) records
+    /// - SHA-256 checksum over everything above (32 bytes)
+    ///
+    /// Readers skip records with unknown tags, so optional fields can be added
+    /// without a version bump. The checksum only detects corruption: anyone who
+    /// can write the stored state can recompute it. Use
+    /// [`DoubleRatchet::serialize_encrypted`] to encrypt and authenticate the
+    /// state at rest.
+    pub fn serialize(&self) -> Zeroizing<Vec<u8>> {
+        let body = self.encode_fields();
+
+        let mut bytes = Zeroizing::new(session_format_prefix(0));
+        bytes.extend_from_slice(&body);
+        let checksum = Sha256::digest(&bytes);
+        bytes.extend_from_slice(&checksum);
+        bytes
+    }
 
-        // Serialize DH self (secret key)
-        bytes.extend_from_slice(&self.dh_self.to_bytes());
+    /// Serialize Double Ratchet state encrypted under a caller-supplied key
+    ///
+    /// Same layout as [`DoubleRatchet::serialize`] with the encrypted flag set,
+    /// but the body is replaced by nonce (12 bytes) || AES-256-GCM ciphertext.
+    /// The GCM tag authenticates the body together with the format prefix.
+    pub fn serialize_encrypted(&self, key: &[u8; 32]) -> Result<Vec<u8>> {
+        let body = self.encode_fields();
+
+        let mut bytes = session_format_prefix(SESSION_FLAG_ENCRYPTED);
+        let ciphertext = MessageKey::new(*key).encrypt(&body, &bytes)?;
+        bytes.extend_from_slice(&ciphertext);
+        Ok(bytes)
+    }
 
-        // Serialize DH remote (optional public key)
-        if let Some(remote) = self.dh_remote {
-            bytes.push(1); // present
-            bytes.extend_from_slice(remote.as_bytes());
-        } else {
-            bytes.push(0); // not present
-            bytes.extend_from_slice(&[0u8; 32]); // padding
+    /// Deserialize Double Ratchet state from bytes
+    ///
+    /// Accepts the current format and the legacy unversioned (v0) layout, so
+    /// sessions stored by older versions are upgraded on their next save.
+    /// Encrypted state must be loaded with [`DoubleRatchet::deserialize_encrypted`].
+    pub fn deserialize(bytes: &[u8]) -> Result<Self> {
+        if !bytes.starts_with(SESSION_MAGIC) {
+            return Self::deserialize_v0(bytes);
         }
 
-        // Serialize root key
-        bytes.extend_from_slice(&self.root_key.key);
+        let (flags, payload) = parse_session_prefix(bytes)?;
+        if flags & SESSION_FLAG_ENCRYPTED != 0 {
+            return Err(CryptoError::Protocol(
+                "Session state is encrypted".to_string()
+            ));
+        }
 
-        // Serialize sending chain
-        if let Some(ref chain_key) = self.sending_chain_key {
-            bytes.push(1);
-            bytes.extend_from_slice(&chain_key.key);
-        } else {
-            bytes.push(0);
-            bytes.extend_from_slice(&[0u8; 32]);
+        if payload.len() < 32 {
+            return Err(CryptoError::Protocol("Session state too short".to_string()));
+        }
+        let (checked, checksum) = bytes.split_at(bytes.len() - 32);
+        if !ct_eq(&Sha256::digest(checked), checksum) {
+            return Err(CryptoError::Protocol(
+                "Session state checksum mismatch".to_string()
+            ));
         }
-        bytes.extend_from_slice(&self.sending_message_number.to_le_bytes());
 
-        // Serialize receiving chain
-        if let Some(ref chain_key) = self.receiving_chain_key {
-            bytes.push(1);
-            bytes.extend_from_slice(&chain_key.key);
+        Self::decode_fields(&payload[..payload.len() - 32])
+    }
+
+    /// Deserialize Double Ratchet state encrypted with [`DoubleRatchet::serialize_encrypted`]
+    ///
+    /// Unencrypted state (current or v0 layout) is rejected, so state written
+    /// by someone without the key can't replace an encrypted session. Existing
+    /// unencrypted sessions are converted once with
+    /// [`DoubleRatchet::migrate_to_encrypted`].
+    pub fn deserialize_encrypted(bytes: &[u8], key: &[u8; 32]) -> Result<Self> {
+        let (flags, payload) = parse_session_prefix(bytes)?;
+        if flags & SESSION_FLAG_ENCRYPTED == 0 {
+            return Err(CryptoError::Protocol(
+                "Session state is not encrypted".to_string()
+            ));
+        }
+
+        let prefix = &bytes[..bytes.len() - payload.len()];
+        let body = MessageKey::new(*key)
+            .decrypt(payload, prefix)
+            .map_err(|_| CryptoError::Decryption("Failed to decrypt session state".to_string()))?;
+        let body = Zeroizing::new(body);
+
+        Self::decode_fields(&body)
+    }
+
+    /// Re-encode stored state (any supported format) encrypted under `key`
+    ///
+    /// For the one-time migration of existing sessions to at-rest encryption;
+    /// state that is already encrypted must decrypt under `key`. Regular loads
+    /// go through [`DoubleRatchet::deserialize_encrypted`], which rejects
+    /// unencrypted state.
+    pub fn migrate_to_encrypted(bytes: &[u8], key: &[u8; 32]) -> Result<Vec<u8>> {
+        let encrypted = matches!(
+            parse_session_prefix(bytes),
+            Ok((flags, _)) if flags & SESSION_FLAG_ENCRYPTED != 0
+        );
+        let ratchet = if encrypted {
+            Self::deserialize_encrypted(bytes, key)?
         } else {
-            bytes.push(0);
-            bytes.extend_from_slice(&[0u8; 32]);
+            Self::deserialize(bytes)?
+        };
+
+        ratchet.serialize_encrypted(key)
+    }
+
+    /// Returns true if serialized state uses the current format version
+    ///
+    /// With `encrypted` set, unencrypted state is also reported as outdated.
+    pub fn is_current_format(bytes: &[u8], encrypted: bool) -> bool {
+        match parse_session_prefix(bytes) {
+            Ok((flags, _)) => {
+                bytes[SESSION_MAGIC.len()] == SESSION_FORMAT_VERSION
+                    && (!encrypted || flags & SESSION_FLAG_ENCRYPTED != 0)
+            }
+            Err(_) => false,
         }
-        bytes.extend_from_slice(&self.receiving_message_number.to_le_bytes());
+    }
 
-        // Serialize previous chain length
-        bytes.extend_from_slice(&self.previous_chain_length.to_le_bytes());
+    /// Encode all state fields as tag-length-value records
+    fn encode_fields(&self) -> Zeroizing<Vec<u8>> {
+        let mut body = Zeroizing::new(Vec::new());
 
-        // Serialize skipped message keys
-        let skipped_count = self.skipped_message_keys.len() as u32;
-        bytes.extend_from_slice(&skipped_count.to_le_bytes());
+        put_field(&mut body, FIELD_DH_SELF, &Zeroizing::new(self.dh_self.to_bytes())[..]);
+        if let Some(remote) = self.dh_remote {
+            put_field(&mut body, FIELD_DH_REMOTE, remote.as_bytes());
+        }
+        put_field(&mut body, FIELD_ROOT_KEY, self.root_key.key.expose_secret());
+        if let Some(ref chain_key) = self.sending_chain_key {
+            put_field(&mut body, FIELD_SENDING_CHAIN_KEY, chain_key.key.expose_secret());
+        }
+        put_field(&mut body, FIELD_SENDING_MESSAGE_NUMBER, &self.sending_message_number.to_be_bytes());
+        if let Some(ref chain_key) = self.receiving_chain_key {
+            put_field(&mut body, FIELD_RECEIVING_CHAIN_KEY, chain_key.key.expose_secret());
+        }
+        put_field(&mut body, FIELD_RECEIVING_MESSAGE_NUMBER, &self.receiving_message_number.to_be_bytes());
+        put_field(&mut body, FIELD_PREVIOUS_CHAIN_LENGTH, &self.previous_chain_length.to_be_bytes());
 
         for ((dh_key, msg_num), message_key) in &self.skipped_message_keys {
-            bytes.extend_from_slice(dh_key.as_bytes()); // 32 bytes
-            bytes.extend_from_slice(&msg_num.to_le_bytes()); // 4 bytes
-            bytes.extend_from_slice(&message_key.key); // 32 bytes
+            put_field(
+                &mut body,
+                FIELD_SKIPPED_MESSAGE_KEY,
+                &skipped_key_record(dh_key.as_bytes(), *msg_num, message_key),
+            );
         }
 
-        bytes
+        if let Some(ref header_keys) = self.header_keys {
+            if let Some(ref header_key) = header_keys.sending {
+                put_field(&mut body, FIELD_SENDING_HEADER_KEY, header_key.key.expose_secret());
+            }
+            if let Some(ref header_key) = header_keys.receiving {
+                put_field(&mut body, FIELD_RECEIVING_HEADER_KEY, header_key.key.expose_secret());
+            }
+            put_field(&mut body, FIELD_NEXT_SENDING_HEADER_KEY, header_keys.next_sending.key.expose_secret());
+            put_field(&mut body, FIELD_NEXT_RECEIVING_HEADER_KEY, header_keys.next_receiving.key.expose_secret());
+
+            for ((header_key, msg_num), message_key) in &header_keys.skipped_message_keys {
+                put_field(
+                    &mut body,
+                    FIELD_SKIPPED_HEADER_MESSAGE_KEY,
+                    &skipped_key_record(header_key.key.expose_secret(), *msg_num, message_key),
+                );
+            }
+        }
+
+        body
     }
 
-    /// Deserialize Double Ratchet state from bytes
-    pub fn deserialize(bytes: &[u8]) -> Result<Self> {
+    /// Decode state from tag-length-value records
+    fn decode_fields(body: &[u8]) -> Result<Self> {
+        let mut dh_self = None;
+        let mut dh_remote = None;
+        let mut root_key = None;
+        let mut sending_chain_key = None;
+        let mut sending_message_number = None;
+        let mut receiving_chain_key = None;
+        let mut receiving_message_number = None;
+        let mut previous_chain_length = None;
+        let mut skipped_message_keys = HashMap::new();
+        let mut sending_header_key = None;
+        let mut receiving_header_key = None;
+        let mut next_sending_header_key = None;
+        let mut next_receiving_header_key = None;
+        let mut skipped_header_message_keys = HashMap::new();
+
+        let mut offset = 0;
+        while offset < body.len() {
+            let (tag, value) = read_field(body, &mut offset)?;
+            match tag {
+                FIELD_DH_SELF => dh_self = Some(StaticSecret::from(field_key(value)?)),
+                FIELD_DH_REMOTE => dh_remote = Some(X25519PublicKey::from(field_key(value)?)),
+                FIELD_ROOT_KEY => root_key = Some(RootKey::new(field_key(value)?)),
+                FIELD_SENDING_CHAIN_KEY => sending_chain_key = Some(ChainKey::new(field_key(value)?)),
+                FIELD_SENDING_MESSAGE_NUMBER => sending_message_number = Some(field_u32(value)?),
+                FIELD_RECEIVING_CHAIN_KEY => receiving_chain_key = Some(ChainKey::new(field_key(value)?)),
+                FIELD_RECEIVING_MESSAGE_NUMBER => receiving_message_number = Some(field_u32(value)?),
+                FIELD_PREVIOUS_CHAIN_LENGTH => previous_chain_length = Some(field_u32(value)?),
+                FIELD_SKIPPED_MESSAGE_KEY => {
+                    let (dh_key, msg_num, message_key) = parse_skipped_key_record(value)?;
+                    skipped_message_keys.insert((X25519PublicKey::from(dh_key), msg_num), message_key);
+                }
+                FIELD_SENDING_HEADER_KEY => sending_header_key = Some(HeaderKey::new(field_key(value)?)),
+                FIELD_RECEIVING_HEADER_KEY => receiving_header_key = Some(HeaderKey::new(field_key(value)?)),
+                FIELD_NEXT_SENDING_HEADER_KEY => next_sending_header_key = Some(HeaderKey::new(field_key(value)?)),
+                FIELD_NEXT_RECEIVING_HEADER_KEY => {
+                    next_receiving_header_key = Some(HeaderKey::new(field_key(value)?))
+                }
+                FIELD_SKIPPED_HEADER_MESSAGE_KEY => {
+                    let (header_key, msg_num, message_key) = parse_skipped_key_record(value)?;
+                    skipped_header_message_keys.insert((HeaderKey::new(header_key), msg_num), message_key);
+                }
+                // Unknown optional field written by a newer version
+                _ => {}
+            }
+        }
+
+        let missing = |name: &str| CryptoError::Protocol(format!("Session state missing {}", name));
+
+        let header_keys = match (next_sending_header_key, next_receiving_header_key) {
+            (Some(next_sending), Some(next_receiving)) => Some(HeaderKeyState {
+                sending: sending_header_key,
+                receiving: receiving_header_key,
+                next_sending,
+                next_receiving,
+                skipped_message_keys: skipped_header_message_keys,
+            }),
+            (None, None) => None,
+            _ => return Err(missing("next header keys")),
+        };
+
+        Ok(Self {
+            dh_self: dh_self.ok_or_else(|| missing("DH key pair"))?,
+            dh_remote,
+            root_key: root_key.ok_or_else(|| missing("root key"))?,
+            sending_chain_key,
+            sending_message_number: sending_message_number.ok_or_else(|| missing("sending message number"))?,
+            receiving_chain_key,
+            receiving_message_number: receiving_message_number
+                .ok_or_else(|| missing("receiving message number"))?,
+            previous_chain_length: previous_chain_length.ok_or_else(|| missing("previous chain length"))?,
+            skipped_message_keys,
+            header_keys,
+        })
+    }
+
+    /// Deserialize Double Ratchet state from the legacy unversioned (v0) layout
+    ///
+    /// Format (all little-endian):
+    /// - dh_self (32 bytes)
+    /// - dh_remote_present (1 byte: 0 or 1)
+    /// - dh_remote (32 bytes, if present)
+    /// - root_key (32 bytes)
+    /// - sending_chain_key_present (1 byte)
+    /// - sending_chain_key (32 bytes, if present)
+    /// - sending_message_number (4 bytes)
+    /// - receiving_chain_key_present (1 byte)
+    /// - receiving_chain_key (32 bytes, if present)
+    /// - receiving_message_number (4 bytes)
+    /// - previous_chain_length (4 bytes)
+    /// - skipped_keys_count (4 bytes)
+    /// - skipped_keys [(dh_key, msg_num, key) repeated]
+    /// - header_encryption_present (1 byte, optional) followed by header key state
+    fn deserialize_v0(bytes: &[u8]) -> Result<Self> {
         let min_size = 32 + 1 + 32 + 32 + 1 + 32 + 4 + 1 + 32 + 4 + 4 + 4;
         if bytes.len() < min_size {
             return Err(CryptoError::Protocol(
@@ -583,6 +1293,14 @@ impl DoubleRatchet {
             skipped_message_keys.insert((dh_key, msg_num), msg_key);
         }
 
+        // Deserialize header encryption state (absent in older serialized sessions)
+        let header_keys = if offset < bytes.len() && bytes[offset] != 0 {
+            offset += 1;
+            Some(deserialize_header_keys(bytes, offset)?)
+        } else {
+            None
+        };
+
         Ok(Self {
             dh_self,
             dh_remote,
@@ -593,14 +1311,262 @@ impl DoubleRatchet {
             receiving_message_number,
             previous_chain_length,
             skipped_message_keys,
+            header_keys,
         })
     }
 }
 
+/// Build the format prefix: magic || version || flags
+fn session_format_prefix(flags: u8) -> Vec<u8> {
+    let mut bytes = SESSION_MAGIC.to_vec();
+    bytes.push(SESSION_FORMAT_VERSION);
+    bytes.push(flags);
+    bytes
+}
+
+/// Parse the format prefix, returning the flags and the remaining payload
+fn parse_session_prefix(bytes: &[u8]) -> Result<(u8, &[u8])> {
+    let prefix_len = SESSION_MAGIC.len() + 2;
+    if bytes.len() < prefix_len || !bytes.starts_with(SESSION_MAGIC) {
+        return Err(CryptoError::Protocol("Invalid session state format".to_string()));
+    }
+
+    let version = bytes[SESSION_MAGIC.len()];
+    if version == 0 || version > SESSION_FORMAT_VERSION {
+        return Err(CryptoError::Protocol(
+            format!("Unsupported session state version: {}", version)
+        ));
+    }
+
+    Ok((bytes[SESSION_MAGIC.len() + 1], &bytes[prefix_len..]))
+}
+
+fn put_field(body: &mut Vec<u8>, tag: u8, value: &[u8]) {
+    body.push(tag);
+    body.extend_from_slice(&(value.len() as u32).to_be_bytes());
+    body.extend_from_slice(value);
+}
+
+fn read_field<'a>(body: &'a [u8], offset: &mut usize) -> Result<(u8, &'a [u8])> {
+    let truncated = || CryptoError::Protocol("Truncated session state field".to_string());
+
+    let header = body.get(*offset..*offset + 5).ok_or_else(truncated)?;
+    let tag = header[0];
+    let len = u32::from_be_bytes([header[1], header[2], header[3], header[4]]) as usize;
+    let start = *offset + 5;
+    let end = start.checked_add(len).ok_or_else(truncated)?;
+    let value = body.get(start..end).ok_or_else(truncated)?;
+
+    *offset = end;
+    Ok((tag, value))
+}
+
+fn field_key(value: &[u8]) -> Result<[u8; 32]> {
+    value
+        .try_into()
+        .map_err(|_| CryptoError::Protocol("Invalid session state key length".to_string()))
+}
+
+fn field_u32(value: &[u8]) -> Result<u32> {
+    let bytes: [u8; 4] = value
+        .try_into()
+        .map_err(|_| CryptoError::Protocol("Invalid session state counter length".to_string()))?;
+    Ok(u32::from_be_bytes(bytes))
+}
+
+/// Skipped message key record: chain identifier (32 bytes) || msg_num (u32 BE) || key (32 bytes)
+fn skipped_key_record(chain_id: &[u8], msg_num: u32, message_key: &MessageKey) -> Zeroizing<Vec<u8>> {
+    let mut record = Zeroizing::new(Vec::with_capacity(68));
+    record.extend_from_slice(chain_id);
+    record.extend_from_slice(&msg_num.to_be_bytes());
+    record.extend_from_slice(message_key.key.expose_secret());
+    record
+}
+
+fn parse_skipped_key_record(value: &[u8]) -> Result<([u8; 32], u32, MessageKey)> {
+    if value.len() != 68 {
+        return Err(CryptoError::Protocol("Invalid skipped key record".to_string()));
+    }
+
+    let chain_id = field_key(&value[..32])?;
+    let msg_num = field_u32(&value[32..36])?;
+    let message_key = MessageKey::new(field_key(&value[36..])?);
+    Ok((chain_id, msg_num, message_key))
+}
+
+/// Derive the initial root key and shared header keys from the X3DH shared secret
+///
+/// Returns: (root key, Alice's first sending header key, Bob's first next header key)
+fn derive_header_encryption_keys(shared_secret: &[u8]) -> Result<(RootKey, HeaderKey, HeaderKey)> {
+    if shared_secret.len() != 32 {
+        return Err(CryptoError::InvalidKey("Shared secret must be 32 bytes".to_string()));
+    }
+
+    let hkdf = Hkdf::<Sha256>::new(None, shared_secret);
+    let mut output = [0u8; 96];
+    hkdf.expand(HEADER_KEY_INFO, &mut output)
+        .map_err(|e| CryptoError::KeyGeneration(format!("Header key derivation failed: {}", e)))?;
+
+    let mut root_key = [0u8; 32];
+    let mut header_key_a = [0u8; 32];
+    let mut next_header_key_b = [0u8; 32];
+    root_key.copy_from_slice(&output[..32]);
+    header_key_a.copy_from_slice(&output[32..64]);
+    next_header_key_b.copy_from_slice(&output[64..]);
+
+    Ok((
+        RootKey::new(root_key),
+        HeaderKey::new(header_key_a),
+        HeaderKey::new(next_header_key_b),
+    ))
+}
+
+/// Deserialize header encryption state starting at `offset`
+fn deserialize_header_keys(bytes: &[u8], mut offset: usize) -> Result<HeaderKeyState> {
+    let truncated = || CryptoError::Protocol("Truncated header key data".to_string());
+    let read_key = |offset: usize| -> Result<HeaderKey> {
+        let mut key = [0u8; 32];
+        key.copy_from_slice(bytes.get(offset..offset + 32).ok_or_else(truncated)?);
+        Ok(HeaderKey::new(key))
+    };
+
+    let mut current = [None, None];
+    for header_key in current.iter_mut() {
+        let present = *bytes.get(offset).ok_or_else(truncated)? != 0;
+        let key = read_key(offset + 1)?;
+        if present {
+            *header_key = Some(key);
+        }
+        offset += 33;
+    }
+    let [sending, receiving] = current;
+
+    let next_sending = read_key(offset)?;
+    offset += 32;
+    let next_receiving = read_key(offset)?;
+    offset += 32;
+
+    let mut skipped_count_bytes = [0u8; 4];
+    skipped_count_bytes.copy_from_slice(bytes.get(offset..offset + 4).ok_or_else(truncated)?);
+    let skipped_count = u32::from_le_bytes(skipped_count_bytes);
+    offset += 4;
+
+    let mut skipped_message_keys = HashMap::new();
+    for _ in 0..skipped_count {
+        if offset + 68 > bytes.len() {
+            return Err(CryptoError::Protocol("Truncated skipped keys data".to_string()));
+        }
+
+        let header_key = read_key(offset)?;
+        offset += 32;
+
+        let mut msg_num_bytes = [0u8; 4];
+        msg_num_bytes.copy_from_slice(&bytes[offset..offset + 4]);
+        let msg_num = u32::from_le_bytes(msg_num_bytes);
+        offset += 4;
+
+        let mut msg_key_bytes = [0u8; 32];
+        msg_key_bytes.copy_from_slice(&bytes[offset..offset + 32]);
+        offset += 32;
+
+        skipped_message_keys.insert((header_key, msg_num), MessageKey::new(msg_key_bytes));
+    }
+
+    Ok(HeaderKeyState {
+        sending,
+        receiving,
+        next_sending,
+        next_receiving,
+        skipped_message_keys,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// Writer for the legacy v0 layout, used to test migration
+    fn serialize_v0(ratchet: &DoubleRatchet) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        // Serialize DH self (secret key)
+        bytes.extend_from_slice(&ratchet.dh_self.to_bytes());
+
+        // Serialize DH remote (optional public key)
+        if let Some(remote) = ratchet.dh_remote {
+            bytes.push(1); // present
+            bytes.extend_from_slice(remote.as_bytes());
+        } else {
+            bytes.push(0); // not present
+            bytes.extend_from_slice(&[0u8; 32]); // padding
+        }
+
+        // Serialize root key
+        bytes.extend_from_slice(ratchet.root_key.key.expose_secret());
+
+        // Serialize sending chain
+        if let Some(ref chain_key) = ratchet.sending_chain_key {
+            bytes.push(1);
+            bytes.extend_from_slice(chain_key.key.expose_secret());
+        } else {
+            bytes.push(0);
+            bytes.extend_from_slice(&[0u8; 32]);
+        }
+        bytes.extend_from_slice(&ratchet.sending_message_number.to_le_bytes());
+
+        // Serialize receiving chain
+        if let Some(ref chain_key) = ratchet.receiving_chain_key {
+            bytes.push(1);
+            bytes.extend_from_slice(chain_key.key.expose_secret());
+        } else {
+            bytes.push(0);
+            bytes.extend_from_slice(&[0u8; 32]);
+        }
+        bytes.extend_from_slice(&ratchet.receiving_message_number.to_le_bytes());
+
+        // Serialize previous chain length
+        bytes.extend_from_slice(&ratchet.previous_chain_length.to_le_bytes());
+
+        // Serialize skipped message keys
+        let skipped_count = ratchet.skipped_message_keys.len() as u32;
+        bytes.extend_from_slice(&skipped_count.to_le_bytes());
+
+        for ((dh_key, msg_num), message_key) in &ratchet.skipped_message_keys {
+            bytes.extend_from_slice(dh_key.as_bytes()); // 32 bytes
+            bytes.extend_from_slice(&msg_num.to_le_bytes()); // 4 bytes
+            bytes.extend_from_slice(message_key.key.expose_secret()); // 32 bytes
+        }
+
+        // Serialize header encryption state
+        if let Some(ref header_keys) = ratchet.header_keys {
+            bytes.push(1);
+            for header_key in [&header_keys.sending, &header_keys.receiving] {
+                if let Some(header_key) = header_key {
+                    bytes.push(1);
+                    bytes.extend_from_slice(header_key.key.expose_secret());
+                } else {
+                    bytes.push(0);
+                    bytes.extend_from_slice(&[0u8; 32]);
+                }
+            }
+            bytes.extend_from_slice(header_keys.next_sending.key.expose_secret());
+            bytes.extend_from_slice(header_keys.next_receiving.key.expose_secret());
+
+            let skipped_count = header_keys.skipped_message_keys.len() as u32;
+            bytes.extend_from_slice(&skipped_count.to_le_bytes());
+
+            for ((header_key, msg_num), message_key) in &header_keys.skipped_message_keys {
+                bytes.extend_from_slice(header_key.key.expose_secret()); // 32 bytes
+                bytes.extend_from_slice(&msg_num.to_le_bytes()); // 4 bytes
+                bytes.extend_from_slice(message_key.key.expose_secret()); // 32 bytes
+            }
+        } else {
+            bytes.push(0);
+        }
+
+        bytes
+    }
+
     #[test]
     fn test_chain_key_derivation() {
         let initial_key = [42u8; 32];
@@ -840,5 +1806,369 @@ mod tests {
         let decrypted_reply = alice.decrypt(&reply, b"ad5").unwrap();
         assert_eq!(b"Reply after restore", &decrypted_reply[..]);
     }
-}
 
+    fn header_encrypted_pair() -> (DoubleRatchet, DoubleRatchet) {
+        let shared_secret = [42u8; 32];
+        let bob_dh = StaticSecret::random_from_rng(OsRng);
+        let bob_public = X25519PublicKey::from(&bob_dh);
+
+        let alice = DoubleRatchet::init_alice_with_header_encryption(&shared_secret, bob_public).unwrap();
+        let bob = DoubleRatchet::init_bob_with_header_encryption(&shared_secret, bob_dh).unwrap();
+        (alice, bob)
+    }
+
+    #[test]
+    fn test_header_encryption_exchange() {
+        let (mut alice, mut bob) = header_encrypted_pair();
+        assert!(alice.has_header_encryption());
+
+        for i in 0..3 {
+            let msg = format!("Alice message {}", i);
+            let encrypted = alice.encrypt_header_encrypted(msg.as_bytes(), b"ad").unwrap();
+
+            // Ratchet public key must not appear in the encrypted header
+            let alice_public = alice.public_key();
+            assert!(!encrypted
+                .encrypted_header
+                .windows(32)
+                .any(|w| w == alice_public.as_bytes()));
+
+            let decrypted = bob.decrypt_header_encrypted(&encrypted, b"ad").unwrap();
+            assert_eq!(msg.as_bytes(), &decrypted[..]);
+
+            let reply = format!("Bob reply {}", i);
+            let encrypted_reply = bob.encrypt_header_encrypted(reply.as_bytes(), b"ad").unwrap();
+            let decrypted_reply = alice.decrypt_header_encrypted(&encrypted_reply, b"ad").unwrap();
+            assert_eq!(reply.as_bytes(), &decrypted_reply[..]);
+        }
+    }
+
+    #[test]
+    fn test_header_encryption_out_of_order_across_ratchet() {
+        let (mut alice, mut bob) = header_encrypted_pair();
+
+        let msg1 = alice.encrypt_header_encrypted(b"First", b"ad").unwrap();
+        let msg2 = alice.encrypt_header_encrypted(b"Second", b"ad").unwrap();
+        let msg3 = alice.encrypt_header_encrypted(b"Third", b"ad").unwrap();
+
+        assert_eq!(bob.decrypt_header_encrypted(&msg1, b"ad").unwrap(), b"First");
+        assert_eq!(bob.decrypt_header_encrypted(&msg3, b"ad").unwrap(), b"Third");
+        assert_eq!(bob.skipped_messages_count(), 1);
+
+        // Bob replies, Alice ratchets and sends from a new chain
+        let reply = bob.encrypt_header_encrypted(b"Reply", b"ad").unwrap();
+        alice.decrypt_header_encrypted(&reply, b"ad").unwrap();
+        let msg4 = alice.encrypt_header_encrypted(b"Fourth", b"ad").unwrap();
+        assert_eq!(bob.decrypt_header_encrypted(&msg4, b"ad").unwrap(), b"Fourth");
+
+        // Skipped message from the previous chain is found by trial decryption
+        assert_eq!(bob.decrypt_header_encrypted(&msg2, b"ad").unwrap(), b"Second");
+        assert_eq!(bob.skipped_messages_count(), 0);
+    }
+
+    #[test]
+    fn test_header_encryption_rejects_plain_api_and_tampering() {
+        let (mut alice, mut bob) = header_encrypted_pair();
+        assert!(alice.encrypt(b"plain", b"ad").is_err());
+
+        let mut encrypted = alice.encrypt_header_encrypted(b"Hello", b"ad").unwrap();
+        assert!(bob.decrypt_header_encrypted(&encrypted, b"wrong").is_err());
+
+        encrypted.encrypted_header[20] ^= 1;
+        assert!(bob.decrypt_header_encrypted(&encrypted, b"ad").is_err());
+
+        let mut plain_bob = DoubleRatchet::init_bob(&[42u8; 32]).unwrap();
+        let parsed = HeaderEncryptedMessage::from_bytes(&encrypted.to_bytes()).unwrap();
+        assert!(plain_bob.decrypt_header_encrypted(&parsed, b"ad").is_err());
+    }
+
+    #[test]
+    fn test_failed_decrypt_leaves_session_unchanged() {
+        let (mut alice, mut bob) = header_encrypted_pair();
+
+        let msg1 = alice.encrypt_header_encrypted(b"First", b"ad").unwrap();
+        let msg2 = alice.encrypt_header_encrypted(b"Second", b"ad").unwrap();
+
+        // A valid header with a forged body must not advance the receiving chain
+        let mut forged = HeaderEncryptedMessage::from_bytes(&msg2.to_bytes()).unwrap();
+        let last = forged.ciphertext.len() - 1;
+        forged.ciphertext[last] ^= 1;
+        assert!(bob.decrypt_header_encrypted(&forged, b"ad").is_err());
+        assert_eq!(bob.skipped_messages_count(), 0);
+
+        assert_eq!(bob.decrypt_header_encrypted(&msg1, b"ad").unwrap(), b"First");
+        assert_eq!(bob.decrypt_header_encrypted(&msg2, b"ad").unwrap(), b"Second");
+    }
+
+    #[test]
+    fn test_skipped_messages_survive_dh_ratchet() {
+        let mut bob = DoubleRatchet::init_bob(&[42u8; 32]).unwrap();
+        let mut alice = DoubleRatchet::init_alice(&[42u8; 32], bob.public_key()).unwrap();
+
+        let msg1 = alice.encrypt(b"First", b"ad").unwrap();
+        let msg2 = alice.encrypt(b"Second", b"ad").unwrap();
+        assert_eq!(bob.decrypt(&msg1, b"ad").unwrap(), b"First");
+
+        let reply = bob.encrypt(b"Reply", b"ad").unwrap();
+        alice.decrypt(&reply, b"ad").unwrap();
+        let msg3 = alice.encrypt(b"Third", b"ad").unwrap();
+
+        // The second message is still missing when Alice's new chain arrives
+        assert_eq!(bob.decrypt(&msg3, b"ad").unwrap(), b"Third");
+        assert_eq!(bob.skipped_messages_count(), 1);
+        assert_eq!(bob.decrypt(&msg2, b"ad").unwrap(), b"Second");
+    }
+
+    #[test]
+    fn test_header_encryption_serialization() {
+        let (mut alice, mut bob) = header_encrypted_pair();
+
+        let msg1 = alice.encrypt_header_encrypted(b"Message 1", b"ad").unwrap();
+        let msg2 = alice.encrypt_header_encrypted(b"Message 2", b"ad").unwrap();
+        let msg3 = alice.encrypt_header_encrypted(b"Message 3", b"ad").unwrap();
+        bob.decrypt_header_encrypted(&msg1, b"ad").unwrap();
+        bob.decrypt_header_encrypted(&msg3, b"ad").unwrap();
+
+        let mut bob_restored = DoubleRatchet::deserialize(&bob.serialize()).unwrap();
+        assert!(bob_restored.has_header_encryption());
+        assert_eq!(bob_restored.skipped_messages_count(), 1);
+
+        assert_eq!(bob_restored.decrypt_header_encrypted(&msg2, b"ad").unwrap(), b"Message 2");
+
+        let reply = bob_restored.encrypt_header_encrypted(b"Reply", b"ad").unwrap();
+        assert_eq!(alice.decrypt_header_encrypted(&reply, b"ad").unwrap(), b"Reply");
+    }
+
+    #[test]
+    fn test_session_format_roundtrip_and_integrity() {
+        let (mut alice, mut bob) = header_encrypted_pair();
+        let msg = alice.encrypt_header_encrypted(b"Hello", b"ad").unwrap();
+        bob.decrypt_header_encrypted(&msg, b"ad").unwrap();
+
+        let serialized = bob.serialize();
+        assert!(serialized.starts_with(SESSION_MAGIC));
+        assert!(DoubleRatchet::is_current_format(&serialized, false));
+        assert!(!DoubleRatchet::is_current_format(&serialized, true));
+
+        let mut restored = DoubleRatchet::deserialize(&serialized).unwrap();
+        assert_eq!(restored.serialize().len(), serialized.len());
+        let reply = restored.encrypt_header_encrypted(b"Reply", b"ad").unwrap();
+        assert_eq!(alice.decrypt_header_encrypted(&reply, b"ad").unwrap(), b"Reply");
+
+        // Any modified byte is detected
+        let mut corrupted = serialized.clone();
+        corrupted[serialized.len() / 2] ^= 1;
+        assert!(DoubleRatchet::deserialize(&corrupted).is_err());
+
+        // Unsupported future versions are rejected
+        let mut future = serialized.clone();
+        future[SESSION_MAGIC.len()] = SESSION_FORMAT_VERSION + 1;
+        assert!(DoubleRatchet::deserialize(&future).is_err());
+    }
+
+    #[test]
+    fn test_session_format_skips_unknown_fields() {
+        let (_, bob) = header_encrypted_pair();
+
+        let mut body = bob.encode_fields().to_vec();
+        put_field(&mut body, 200, b"added by a newer version");
+
+        let mut bytes = session_format_prefix(0);
+        bytes.extend_from_slice(&body);
+        let checksum = Sha256::digest(&bytes);
+        bytes.extend_from_slice(&checksum);
+
+        let restored = DoubleRatchet::deserialize(&bytes).unwrap();
+        assert_eq!(restored.public_key().as_bytes(), bob.public_key().as_bytes());
+        assert!(restored.has_header_encryption());
+    }
+
+    #[test]
+    fn test_session_format_migrates_v0() {
+        let (mut alice, mut bob) = header_encrypted_pair();
+        let msg1 = alice.encrypt_header_encrypted(b"Message 1", b"ad").unwrap();
+        let msg2 = alice.encrypt_header_encrypted(b"Message 2", b"ad").unwrap();
+        bob.decrypt_header_encrypted(&msg2, b"ad").unwrap();
+
+        let legacy = serialize_v0(&bob);
+        assert!(!DoubleRatchet::is_current_format(&legacy, false));
+
+        // Legacy state loads and is re-saved in the current format
+        let restored = DoubleRatchet::deserialize(&legacy).unwrap();
+        assert_eq!(restored.skipped_messages_count(), 1);
+        let upgraded = restored.serialize();
+        assert!(DoubleRatchet::is_current_format(&upgraded, false));
+
+        let mut restored = DoubleRatchet::deserialize(&upgraded).unwrap();
+        assert_eq!(restored.decrypt_header_encrypted(&msg1, b"ad").unwrap(), b"Message 1");
+
+        // Plain (non header-encrypted) v0 state without the trailing flag byte
+        let plain = DoubleRatchet::init_alice(&[42u8; 32], bob.public_key()).unwrap();
+        let mut legacy_plain = serialize_v0(&plain);
+        legacy_plain.pop();
+        let restored_plain = DoubleRatchet::deserialize(&legacy_plain).unwrap();
+        assert_eq!(restored_plain.public_key().as_bytes(), plain.public_key().as_bytes());
+        assert_eq!(restored_plain.serialize().len(), plain.serialize().len());
+    }
+
+    #[test]
+    fn test_session_format_encrypted() {
+        let key = [7u8; 32];
+        let (mut alice, bob) = header_encrypted_pair();
+
+        let encrypted = bob.serialize_encrypted(&key).unwrap();
+        assert!(DoubleRatchet::is_current_format(&encrypted, true));
+        assert!(DoubleRatchet::deserialize(&encrypted).is_err());
+        assert!(DoubleRatchet::deserialize_encrypted(&encrypted, &[8u8; 32]).is_err());
+
+        // Prefix is authenticated
+        let mut tampered = encrypted.clone();
+        tampered[SESSION_MAGIC.len() + 1] |= 0x80;
+        assert!(DoubleRatchet::deserialize_encrypted(&tampered, &key).is_err());
+
+        let mut restored = DoubleRatchet::deserialize_encrypted(&encrypted, &key).unwrap();
+        let msg = alice.encrypt_header_encrypted(b"Hello", b"ad").unwrap();
+        assert_eq!(restored.decrypt_header_encrypted(&msg, b"ad").unwrap(), b"Hello");
+
+        // Unencrypted state can't be swapped in once a key is used
+        assert!(DoubleRatchet::deserialize_encrypted(&bob.serialize(), &key).is_err());
+        assert!(DoubleRatchet::deserialize_encrypted(&serialize_v0(&bob), &key).is_err());
+    }
+
+    #[test]
+    fn test_session_format_migrates_to_encrypted() {
+        let key = [7u8; 32];
+        let (mut alice, mut bob) = header_encrypted_pair();
+        let msg1 = alice.encrypt_header_encrypted(b"Message 1", b"ad").unwrap();
+        let msg2 = alice.encrypt_header_encrypted(b"Message 2", b"ad").unwrap();
+        bob.decrypt_header_encrypted(&msg2, b"ad").unwrap();
+
+        for stored in [bob.serialize().to_vec(), serialize_v0(&bob)] {
+            let migrated = DoubleRatchet::migrate_to_encrypted(&stored, &key).unwrap();
+            assert!(DoubleRatchet::is_current_format(&migrated, true));
+
+            let mut restored = DoubleRatchet::deserialize_encrypted(&migrated, &key).unwrap();
+            assert_eq!(restored.decrypt_header_encrypted(&msg1, b"ad").unwrap(), b"Message 1");
+
+            // Migrating again is a no-op, but only under the same key
+            assert!(DoubleRatchet::migrate_to_encrypted(&migrated, &key).is_ok());
+            assert!(DoubleRatchet::migrate_to_encrypted(&migrated, &[8u8; 32]).is_err());
+        }
+    }
+
+    /// Known-answer vectors from `test-vectors/double_ratchet.json`, shared with the client implementations
+    #[test]
+    fn test_franked_exchange() {
+        let shared_secret = [42u8; 32];
+        let mut bob = DoubleRatchet::init_bob(&shared_secret).unwrap();
+        let mut alice = DoubleRatchet::init_alice(&shared_secret, bob.public_key()).unwrap();
+
+        let (message, commitment) = alice.encrypt_franked(b"Hello Bob", b"ad").unwrap();
+        let franked = bob.decrypt_franked(&message, b"ad", &commitment).unwrap();
+        assert_eq!(franked.plaintext, b"Hello Bob");
+        franking::verify_commitment(&commitment, franked.franking_key.expose_secret(), b"Hello Bob").unwrap();
+
+        let (mut alice, mut bob) = header_encrypted_pair();
+        let (message, commitment) = alice.encrypt_header_encrypted_franked(b"Hello Bob", b"ad").unwrap();
+        let franked = bob.decrypt_header_encrypted_franked(&message, b"ad", &commitment).unwrap();
+        assert_eq!(franked.plaintext, b"Hello Bob");
+    }
+
+    #[test]
+    fn test_franked_message_rejects_other_commitment() {
+        let shared_secret = [42u8; 32];
+        let mut bob = DoubleRatchet::init_bob(&shared_secret).unwrap();
+        let mut alice = DoubleRatchet::init_alice(&shared_secret, bob.public_key()).unwrap();
+
+        // The server tag carries a commitment to something else
+        let (message, commitment) = alice.encrypt_franked(b"Hello Bob", b"ad").unwrap();
+        let (_, other_commitment) = franking::frank(b"Hello Bob").unwrap();
+        assert!(bob.decrypt_franked(&message, b"ad", &other_commitment).is_err());
+
+        // Unfranked decryption does not accept it either
+        assert!(bob.decrypt(&message, b"ad").is_err());
+
+        // The session is unchanged by the rejected attempts
+        let franked = bob.decrypt_franked(&message, b"ad", &commitment).unwrap();
+        assert_eq!(franked.plaintext, b"Hello Bob");
+    }
+
+    #[test]
+    fn test_known_answer_vectors() {
+        let vectors: serde_json::Value =
+            serde_json::from_str(include_str!("../test-vectors/double_ratchet.json")).unwrap();
+        let x3dh: serde_json::Value =
+            serde_json::from_str(include_str!("../test-vectors/x3dh.json")).unwrap();
+        let bytes = |value: &serde_json::Value| hex::decode(value.as_str().unwrap()).unwrap();
+        let key = |value: &serde_json::Value| -> [u8; 32] { bytes(value).try_into().unwrap() };
+
+        // Root KDF on Alice's first DH ratchet step
+        let root_kdf = &vectors["root_kdf"];
+        let dh_secret = StaticSecret::from(key(&root_kdf["dh_secret"]));
+        assert_eq!(X25519PublicKey::from(&dh_secret).as_bytes(), &key(&root_kdf["dh_public"]));
+        let dh_output = dh_secret.diffie_hellman(&X25519PublicKey::from(key(&root_kdf["remote_dh_public"])));
+        assert_eq!(dh_output.as_bytes(), &key(&root_kdf["dh_output"]));
+        let (root_key, chain_key) = RootKey::new(key(&root_kdf["root_key"]))
+            .dh_ratchet(dh_output.as_bytes())
+            .unwrap();
+        assert_eq!(root_key.key.expose_secret(), &key(&root_kdf["next_root_key"]));
+        assert_eq!(chain_key.key.expose_secret(), &key(&root_kdf["chain_key"]));
+
+        // Symmetric chain
+        let chain_kdf = &vectors["chain_kdf"];
+        let mut chain_key = ChainKey::new(key(&chain_kdf["chain_key"]));
+        for step in chain_kdf["steps"].as_array().unwrap() {
+            assert_eq!(chain_key.message_key().unwrap().key.expose_secret(), &key(&step["message_key"]));
+            chain_key = chain_key.next().unwrap();
+            assert_eq!(chain_key.key.expose_secret(), &key(&step["next_chain_key"]));
+        }
+
+        // Message layer
+        let message = &vectors["message"];
+        let parsed = EncryptedMessage::from_bytes(&bytes(&message["wire"])).unwrap();
+        assert_eq!(parsed.header.to_bytes(), bytes(&message["header"]));
+        assert_eq!(parsed.header.dh_public_key.as_bytes(), &key(&message["dh_public_key"]));
+        assert_eq!(parsed.header.previous_chain_length as u64, message["previous_chain_length"].as_u64().unwrap());
+        assert_eq!(parsed.header.message_number as u64, message["message_number"].as_u64().unwrap());
+        assert_eq!(parsed.ciphertext, bytes(&message["ciphertext"]));
+        let plaintext = MessageKey::new(key(&message["message_key"]))
+            .decrypt(&parsed.ciphertext, &bytes(&message["associated_data"]))
+            .unwrap();
+        assert_eq!(plaintext, bytes(&message["plaintext"]));
+
+        // Header encryption keys and root KDF
+        let header_key_init = &vectors["header_key_init"];
+        let (root_key, header_key_a, next_header_key_b) =
+            derive_header_encryption_keys(&bytes(&header_key_init["shared_secret"])).unwrap();
+        assert_eq!(root_key.key.expose_secret(), &key(&header_key_init["root_key"]));
+        assert_eq!(header_key_a.key.expose_secret(), &key(&header_key_init["header_key_a"]));
+        assert_eq!(next_header_key_b.key.expose_secret(), &key(&header_key_init["next_header_key_b"]));
+
+        let root_kdf_he = &vectors["root_kdf_header_encryption"];
+        let (next_root_key, chain_key, next_header_key) = RootKey::new(key(&root_kdf_he["root_key"]))
+            .dh_ratchet_he(dh_output.as_bytes())
+            .unwrap();
+        assert_eq!(next_root_key.key.expose_secret(), &key(&root_kdf_he["next_root_key"]));
+        assert_eq!(chain_key.key.expose_secret(), &key(&root_kdf_he["chain_key"]));
+        assert_eq!(next_header_key.key.expose_secret(), &key(&root_kdf_he["next_header_key"]));
+
+        // Bob decrypts Alice's header-encrypted first message; his ratchet key is his signed pre-key
+        let message = &vectors["header_encrypted_message"];
+        let header = HeaderKey::new(key(&message["header_key"]))
+            .decrypt(&bytes(&message["encrypted_header"]))
+            .unwrap();
+        assert_eq!(header.to_bytes(), bytes(&message["header"]));
+
+        let signed_pre_key = StaticSecret::from(key(&x3dh["vectors"][0]["bob_signed_pre_key_secret"]));
+        let mut bob = DoubleRatchet::init_bob_with_header_encryption(
+            &bytes(&header_key_init["shared_secret"]),
+            signed_pre_key,
+        )
+        .unwrap();
+        let parsed = HeaderEncryptedMessage::from_bytes(&bytes(&message["wire"])).unwrap();
+        let plaintext = bob
+            .decrypt_header_encrypted(&parsed, &bytes(&message["associated_data"]))
+            .unwrap();
+        assert_eq!(plaintext, bytes(&message["plaintext"]));
+    }
+}
@@ -0,0 +1,206 @@
+/// Property-based Double Ratchet simulations
+///
+/// Alice and Bob exchange rounds of messages over a simulated network that
+/// reorders, drops, delays and duplicates them. Delayed messages and replays
+/// of duplicated ones arrive after the conversation has moved on to later
+/// chains. Every message must decrypt the first time it arrives, every
+/// repeated delivery must fail without disturbing the session, and up to
+/// [`MAX_SKIP`] skipped messages must be recoverable.
+use crate::double_ratchet::{DoubleRatchet, EncryptedMessage, HeaderEncryptedMessage, MAX_SKIP};
+use crate::Result;
+use proptest::prelude::*;
+use rand::rngs::OsRng;
+use x25519_dalek::StaticSecret;
+
+const SHARED_SECRET: [u8; 32] = [7u8; 32];
+const ASSOCIATED_DATA: &[u8] = b"guardyn-proptest";
+
+/// What the network does with a message
+#[derive(Debug, Clone, Copy)]
+enum Fate {
+    Deliver,
+    Drop,
+    /// Delivered twice within the round and replayed at the end
+    Duplicate,
+    /// Delivered at the end of the conversation
+    Delay,
+}
+
+/// Messages sent by one party; `deliveries` are indices into the round
+#[derive(Debug, Clone)]
+struct Round {
+    from_alice: bool,
+    fates: Vec<Fate>,
+    deliveries: Vec<usize>,
+}
+
+fn fate() -> impl Strategy<Value = Fate> {
+    prop_oneof![
+        6 => Just(Fate::Deliver),
+        1 => Just(Fate::Drop),
+        1 => Just(Fate::Duplicate),
+        1 => Just(Fate::Delay),
+    ]
+}
+
+fn round() -> impl Strategy<Value = Round> {
+    (any::<bool>(), prop::collection::vec(fate(), 1..=24))
+        .prop_flat_map(|(from_alice, fates)| {
+            let deliveries: Vec<usize> = fates
+                .iter()
+                .enumerate()
+                .flat_map(|(index, fate)| match fate {
+                    Fate::Deliver => vec![index],
+                    Fate::Duplicate => vec![index, index],
+                    Fate::Drop | Fate::Delay => Vec::new(),
+                })
+                .collect();
+            (Just(from_alice), Just(fates), Just(deliveries).prop_shuffle())
+        })
+        .prop_map(|(from_alice, fates, deliveries)| Round {
+            from_alice,
+            fates,
+            deliveries,
+        })
+}
+
+/// Session pair where Alice initiates, with or without header encryption
+fn session_pair(header_encryption: bool) -> (DoubleRatchet, DoubleRatchet) {
+    if header_encryption {
+        let bob_key = StaticSecret::random_from_rng(OsRng);
+        let bob_public = x25519_dalek::PublicKey::from(&bob_key);
+        let bob = DoubleRatchet::init_bob_with_header_encryption(&SHARED_SECRET, bob_key).unwrap();
+        let alice = DoubleRatchet::init_alice_with_header_encryption(&SHARED_SECRET, bob_public).unwrap();
+        (alice, bob)
+    } else {
+        let bob = DoubleRatchet::init_bob(&SHARED_SECRET).unwrap();
+        let alice = DoubleRatchet::init_alice(&SHARED_SECRET, bob.public_key()).unwrap();
+        (alice, bob)
+    }
+}
+
+/// Encrypt and serialize, so every delivery also goes through the parser
+fn send(session: &mut DoubleRatchet, plaintext: &[u8]) -> Vec<u8> {
+    if session.has_header_encryption() {
+        session.encrypt_header_encrypted(plaintext, ASSOCIATED_DATA).unwrap().to_bytes()
+    } else {
+        session.encrypt(plaintext, ASSOCIATED_DATA).unwrap().to_bytes()
+    }
+}
+
+fn receive(session: &mut DoubleRatchet, wire: &[u8]) -> Result<Vec<u8>> {
+    if session.has_header_encryption() {
+        session.decrypt_header_encrypted(&HeaderEncryptedMessage::from_bytes(wire)?, ASSOCIATED_DATA)
+    } else {
+        session.decrypt(&EncryptedMessage::from_bytes(wire)?, ASSOCIATED_DATA)
+    }
+}
+
+/// A message in flight: receiver, wire bytes and what it must decrypt to
+struct InFlight {
+    to_alice: bool,
+    wire: Vec<u8>,
+    plaintext: Vec<u8>,
+    delivered: bool,
+}
+
+fn simulate(header_encryption: bool, rounds: Vec<Round>) {
+    let (mut alice, mut bob) = session_pair(header_encryption);
+    let mut bob_can_send = false;
+    let mut late: Vec<usize> = Vec::new();
+    let mut messages: Vec<InFlight> = Vec::new();
+
+    let deliver = |messages: &mut Vec<InFlight>,
+                       alice: &mut DoubleRatchet,
+                       bob: &mut DoubleRatchet,
+                       id: usize|
+     -> bool {
+        let message = &mut messages[id];
+        let session = if message.to_alice { alice } else { bob };
+        let result = receive(session, &message.wire);
+        if message.delivered {
+            assert!(result.is_err(), "replayed message {} was accepted", id);
+        } else {
+            assert_eq!(result.unwrap(), message.plaintext, "message {}", id);
+            message.delivered = true;
+        }
+        !message.to_alice
+    };
+
+    for round in rounds {
+        // Bob has no sending chain until he has received a message
+        let from_alice = round.from_alice || !bob_can_send;
+        let first = messages.len();
+
+        for (index, fate) in round.fates.iter().enumerate() {
+            let plaintext = format!("message {} of round starting at {}", index, first).into_bytes();
+            let sender = if from_alice { &mut alice } else { &mut bob };
+            let wire = send(sender, &plaintext);
+            messages.push(InFlight {
+                to_alice: !from_alice,
+                wire,
+                plaintext,
+                delivered: false,
+            });
+            if matches!(fate, Fate::Duplicate | Fate::Delay) {
+                late.push(first + index);
+            }
+        }
+
+        for index in round.deliveries {
+            if deliver(&mut messages, &mut alice, &mut bob, first + index) {
+                bob_can_send = true;
+            }
+        }
+    }
+
+    // Delayed messages and replays arrive last, newest first
+    for id in late.into_iter().rev() {
+        deliver(&mut messages, &mut alice, &mut bob, id);
+    }
+
+    // Both sessions still work after everything the network did
+    let wire = send(&mut alice, b"final");
+    assert_eq!(receive(&mut bob, &wire).unwrap(), b"final");
+}
+
+/// Alice sends `gap + 1` messages; Bob receives the last one first, then the rest
+fn skip_then_catch_up(header_encryption: bool, gap: usize) -> Result<()> {
+    let (mut alice, mut bob) = session_pair(header_encryption);
+    let sent: Vec<Vec<u8>> = (0..=gap).map(|i| send(&mut alice, &i.to_be_bytes())).collect();
+
+    assert_eq!(receive(&mut bob, &sent[gap])?, gap.to_be_bytes());
+    for (i, wire) in sent[..gap].iter().enumerate() {
+        assert_eq!(receive(&mut bob, wire)?, i.to_be_bytes());
+    }
+    assert_eq!(bob.skipped_messages_count(), 0);
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn prop_ratchet_survives_unreliable_network(rounds in prop::collection::vec(round(), 1..8)) {
+        simulate(false, rounds);
+    }
+
+    #[test]
+    fn prop_header_encrypted_ratchet_survives_unreliable_network(rounds in prop::collection::vec(round(), 1..8)) {
+        simulate(true, rounds);
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(16))]
+
+    #[test]
+    fn prop_skipped_messages_up_to_max_skip_decrypt(gap in 0..=MAX_SKIP, header_encryption in any::<bool>()) {
+        skip_then_catch_up(header_encryption, gap).unwrap();
+    }
+}
+
+#[test]
+fn test_skipping_more_than_max_skip_fails() {
+    for header_encryption in [false, true] {
+        assert!(skip_then_catch_up(header_encryption, MAX_SKIP + 1).is_err());
+    }
+}
@@ -0,0 +1,226 @@
+/// Safety numbers for out-of-band identity key verification
+///
+/// Two users compare a numeric safety number (read aloud or shown side by side)
+/// or scan each other's QR code to make sure the identity keys they use for
+/// X3DH belong to each other and not to a man in the middle.
+///
+/// Based on Signal's numeric fingerprint (version 1): each party's fingerprint
+/// is an iterated SHA-512 hash over its identity key and stable identifier
+/// (user ID). Both parties derive the same 60-digit safety number because the
+/// two 30-digit halves are sorted before concatenation.
+use crate::{CryptoError, Result};
+use sha2::{Digest, Sha512};
+
+/// Number of hash iterations per fingerprint
+pub const FINGERPRINT_ITERATIONS: u32 = 5200;
+
+/// Version of the scannable (QR) payload format
+pub const SCANNABLE_FINGERPRINT_VERSION: u8 = 1;
+
+// Version prefix hashed into every fingerprint
+const FINGERPRINT_VERSION: u16 = 0;
+
+// Identity keys are Ed25519 public keys
+const IDENTITY_KEY_SIZE: usize = 32;
+
+// Fingerprint bytes used in the scannable payload
+const SCANNABLE_FINGERPRINT_SIZE: usize = 32;
+
+/// Length of the scannable payload: version || local fingerprint || remote fingerprint
+pub const SCANNABLE_PAYLOAD_SIZE: usize = 1 + 2 * SCANNABLE_FINGERPRINT_SIZE;
+
+/// Safety number and QR payload for a pair of identity keys
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fingerprint {
+    local_digits: String,
+    remote_digits: String,
+    local_scannable: [u8; SCANNABLE_FINGERPRINT_SIZE],
+    remote_scannable: [u8; SCANNABLE_FINGERPRINT_SIZE],
+}
+
+impl Fingerprint {
+    /// Compute the fingerprint from the local and remote identity keys
+    ///
+    /// `local_id` and `remote_id` are stable identifiers of the key owners
+    /// (user IDs), so a key cannot be presented as belonging to another user.
+    pub fn new(
+        local_id: &[u8],
+        local_identity_key: &[u8],
+        remote_id: &[u8],
+        remote_identity_key: &[u8],
+    ) -> Result<Self> {
+        let local_hash = iterated_hash(local_id, local_identity_key)?;
+        let remote_hash = iterated_hash(remote_id, remote_identity_key)?;
+
+        let mut local_scannable = [0u8; SCANNABLE_FINGERPRINT_SIZE];
+        local_scannable.copy_from_slice(&local_hash[..SCANNABLE_FINGERPRINT_SIZE]);
+        let mut remote_scannable = [0u8; SCANNABLE_FINGERPRINT_SIZE];
+        remote_scannable.copy_from_slice(&remote_hash[..SCANNABLE_FINGERPRINT_SIZE]);
+
+        Ok(Self {
+            local_digits: encode_digits(&local_hash),
+            remote_digits: encode_digits(&remote_hash),
+            local_scannable,
+            remote_scannable,
+        })
+    }
+
+    /// Get the 60-digit safety number (identical for both parties)
+    pub fn safety_number(&self) -> String {
+        if self.local_digits <= self.remote_digits {
+            format!("{}{}", self.local_digits, self.remote_digits)
+        } else {
+            format!("{}{}", self.remote_digits, self.local_digits)
+        }
+    }
+
+    /// Get the safety number in groups of five digits for display
+    pub fn formatted_safety_number(&self) -> String {
+        self.safety_number()
+            .as_bytes()
+            .chunks(5)
+            .map(|chunk| std::str::from_utf8(chunk).unwrap_or_default())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Get the payload to encode in a QR code
+    ///
+    /// Format: version (1 byte) || local fingerprint (32 bytes) || remote fingerprint (32 bytes)
+    pub fn scannable_payload(&self) -> Vec<u8> {
+        let mut payload = Vec::with_capacity(SCANNABLE_PAYLOAD_SIZE);
+        payload.push(SCANNABLE_FINGERPRINT_VERSION);
+        payload.extend_from_slice(&self.local_scannable);
+        payload.extend_from_slice(&self.remote_scannable);
+        payload
+    }
+
+    /// Compare against a QR payload scanned from the other party's device
+    ///
+    /// Returns true if both sides see the same pair of identity keys. Fails if
+    /// the payload is malformed or uses an unsupported version.
+    pub fn verify_scanned(&self, payload: &[u8]) -> Result<bool> {
+        if payload.len() != SCANNABLE_PAYLOAD_SIZE {
+            return Err(CryptoError::Protocol("Invalid fingerprint payload length".to_string()));
+        }
+        if payload[0] != SCANNABLE_FINGERPRINT_VERSION {
+            return Err(CryptoError::Protocol(format!(
+                "Unsupported fingerprint version: {}",
+                payload[0]
+            )));
+        }
+
+        // The other party's "local" fingerprint is our "remote" one and vice versa
+        let (scanned_local, scanned_remote) = payload[1..].split_at(SCANNABLE_FINGERPRINT_SIZE);
+        Ok(scanned_local == self.remote_scannable && scanned_remote == self.local_scannable)
+    }
+}
+
+/// Iterated SHA-512 over version || key || identifier, then (hash || key)
+fn iterated_hash(stable_id: &[u8], identity_key: &[u8]) -> Result<[u8; 64]> {
+    if identity_key.len() != IDENTITY_KEY_SIZE {
+        return Err(CryptoError::InvalidKey(format!(
+            "Identity key must be {} bytes",
+            IDENTITY_KEY_SIZE
+        )));
+    }
+
+    let mut hasher = Sha512::new();
+    hasher.update(FINGERPRINT_VERSION.to_be_bytes());
+    hasher.update(identity_key);
+    hasher.update(stable_id);
+    let mut hash = hasher.finalize();
+
+    for _ in 0..FINGERPRINT_ITERATIONS {
+        let mut hasher = Sha512::new();
+        hasher.update(hash);
+        hasher.update(identity_key);
+        hash = hasher.finalize();
+    }
+
+    Ok(hash.into())
+}
+
+/// Encode the first 30 bytes of a hash as six 5-digit groups
+fn encode_digits(hash: &[u8; 64]) -> String {
+    hash[..30]
+        .chunks(5)
+        .map(|chunk| {
+            let value = chunk.iter().fold(0u64, |acc, byte| (acc << 8) | u64::from(*byte));
+            format!("{:05}", value % 100_000)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::x3dh::IdentityKeyPair;
+
+    #[test]
+    fn test_safety_number_is_symmetric() {
+        let alice = IdentityKeyPair::generate().unwrap();
+        let bob = IdentityKeyPair::generate().unwrap();
+
+        let alice_view = Fingerprint::new(b"alice", &alice.public_bytes(), b"bob", &bob.public_bytes()).unwrap();
+        let bob_view = Fingerprint::new(b"bob", &bob.public_bytes(), b"alice", &alice.public_bytes()).unwrap();
+
+        let number = alice_view.safety_number();
+        assert_eq!(number.len(), 60);
+        assert!(number.chars().all(|c| c.is_ascii_digit()));
+        assert_eq!(number, bob_view.safety_number());
+
+        let formatted = alice_view.formatted_safety_number();
+        assert_eq!(formatted.split(' ').count(), 12);
+        assert_eq!(formatted.replace(' ', ""), number);
+    }
+
+    #[test]
+    fn test_safety_number_changes_with_key_or_identifier() {
+        let alice = IdentityKeyPair::generate().unwrap();
+        let bob = IdentityKeyPair::generate().unwrap();
+        let mallory = IdentityKeyPair::generate().unwrap();
+
+        let original = Fingerprint::new(b"alice", &alice.public_bytes(), b"bob", &bob.public_bytes()).unwrap();
+        let new_key = Fingerprint::new(b"alice", &alice.public_bytes(), b"bob", &mallory.public_bytes()).unwrap();
+        let other_user = Fingerprint::new(b"alice", &alice.public_bytes(), b"carol", &bob.public_bytes()).unwrap();
+
+        assert_ne!(original.safety_number(), new_key.safety_number());
+        assert_ne!(original.safety_number(), other_user.safety_number());
+    }
+
+    #[test]
+    fn test_scannable_payload_verification() {
+        let alice = IdentityKeyPair::generate().unwrap();
+        let bob = IdentityKeyPair::generate().unwrap();
+        let mallory = IdentityKeyPair::generate().unwrap();
+
+        let alice_view = Fingerprint::new(b"alice", &alice.public_bytes(), b"bob", &bob.public_bytes()).unwrap();
+        let bob_view = Fingerprint::new(b"bob", &bob.public_bytes(), b"alice", &alice.public_bytes()).unwrap();
+
+        let payload = bob_view.scannable_payload();
+        assert_eq!(payload.len(), SCANNABLE_PAYLOAD_SIZE);
+        assert!(alice_view.verify_scanned(&payload).unwrap());
+        assert!(bob_view.verify_scanned(&alice_view.scannable_payload()).unwrap());
+
+        // Bob sees Mallory's key instead of Alice's
+        let mitm_view = Fingerprint::new(b"bob", &bob.public_bytes(), b"alice", &mallory.public_bytes()).unwrap();
+        assert!(!alice_view.verify_scanned(&mitm_view.scannable_payload()).unwrap());
+
+        // Scanning your own code does not verify anything
+        assert!(!alice_view.verify_scanned(&alice_view.scannable_payload()).unwrap());
+    }
+
+    #[test]
+    fn test_rejects_invalid_input() {
+        let alice = IdentityKeyPair::generate().unwrap();
+        assert!(Fingerprint::new(b"alice", &alice.public_bytes(), b"bob", &[0u8; 16]).is_err());
+
+        let fingerprint = Fingerprint::new(b"alice", &alice.public_bytes(), b"bob", &alice.public_bytes()).unwrap();
+        let mut payload = fingerprint.scannable_payload();
+        assert!(fingerprint.verify_scanned(&payload[..10]).is_err());
+
+        payload[0] = SCANNABLE_FINGERPRINT_VERSION + 1;
+        assert!(fingerprint.verify_scanned(&payload).is_err());
+    }
+}
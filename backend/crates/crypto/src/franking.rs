@@ -0,0 +1,396 @@
+/// Message franking for verifiable abuse reports
+///
+/// The server cannot read end-to-end encrypted messages, so a report would
+/// otherwise be the reporter's word against the sender's. With franking the
+/// sender commits to every message:
+///
+/// 1. The sender picks a random franking key and computes the commitment
+///    `HMAC-SHA256(franking_key, label || plaintext)`. The franking key is
+///    encrypted together with the plaintext (the franked payload); the
+///    commitment is sent to the server next to the ciphertext and bound into
+///    the AEAD associated data.
+/// 2. The server signs a [`FrankingTag`] over the commitment, the sender, the
+///    recipient and the message ID, and delivers it with the message.
+/// 3. The recipient decrypts, recomputes the commitment from the plaintext
+///    and franking key and rejects the message if it differs from the one in
+///    the tag. A sender therefore cannot deliver content it did not commit to.
+/// 4. To report, the recipient reveals the plaintext and franking key. The
+///    server recomputes the commitment and checks it against the tag, which
+///    proves who sent that exact plaintext. HMAC is collision resistant, so
+///    the reporter cannot find another plaintext matching the commitment.
+///
+/// Franked payload format (version 1): `version (1) || franking key (32) || plaintext`
+use crate::secret::{ct_eq, SecretKey};
+use crate::x3dh::IdentityKeyPair;
+use crate::{CryptoError, Result};
+use hmac::{Hmac, Mac};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+/// Current franked payload version
+pub const FRANKING_VERSION: u8 = 1;
+
+/// Current franking tag version
+pub const FRANKING_TAG_VERSION: u8 = 1;
+
+/// Length of a franking key
+pub const FRANKING_KEY_LEN: usize = 32;
+
+/// Length of a franking commitment
+pub const COMMITMENT_LEN: usize = 32;
+
+/// Domain separation for commitments
+const COMMITMENT_LABEL: &[u8] = b"Guardyn_Franking_v1";
+
+/// Domain separation for franking tag signatures
+const TAG_SIGNATURE_CONTEXT: &[u8] = b"Guardyn_FrankingTag";
+
+const SIGNATURE_SIZE: usize = 64;
+
+/// Commitment to a message plaintext
+pub type FrankingCommitment = [u8; COMMITMENT_LEN];
+
+/// Decrypted franked message
+pub struct FrankedPlaintext {
+    pub plaintext: Vec<u8>,
+    /// Revealed together with the plaintext when reporting the message
+    pub franking_key: SecretKey<FRANKING_KEY_LEN>,
+    pub commitment: FrankingCommitment,
+}
+
+/// Frank a plaintext with a fresh franking key
+///
+/// # Returns
+/// Tuple of (payload to encrypt, commitment to send to the server)
+pub fn frank(plaintext: &[u8]) -> Result<(Zeroizing<Vec<u8>>, FrankingCommitment)> {
+    let mut franking_key = Zeroizing::new([0u8; FRANKING_KEY_LEN]);
+    OsRng.fill_bytes(franking_key.as_mut());
+    let commitment = commit(franking_key.as_ref(), plaintext)?;
+
+    let mut payload = Zeroizing::new(Vec::with_capacity(1 + FRANKING_KEY_LEN + plaintext.len()));
+    payload.push(FRANKING_VERSION);
+    payload.extend_from_slice(franking_key.as_ref());
+    payload.extend_from_slice(plaintext);
+    Ok((payload, commitment))
+}
+
+/// Open a decrypted franked payload and check it against the commitment
+pub fn open(payload: &[u8], commitment: &FrankingCommitment) -> Result<FrankedPlaintext> {
+    let (&version, rest) = payload
+        .split_first()
+        .ok_or_else(|| CryptoError::Protocol("Franked payload too short".to_string()))?;
+    if version != FRANKING_VERSION {
+        return Err(CryptoError::Protocol(format!("Unsupported franking version: {}", version)));
+    }
+    if rest.len() < FRANKING_KEY_LEN {
+        return Err(CryptoError::Protocol("Franked payload too short".to_string()));
+    }
+
+    let (franking_key, plaintext) = rest.split_at(FRANKING_KEY_LEN);
+    verify_commitment(commitment, franking_key, plaintext)?;
+
+    Ok(FrankedPlaintext {
+        plaintext: plaintext.to_vec(),
+        franking_key: SecretKey::from_slice(franking_key)
+            .ok_or_else(|| CryptoError::InvalidKey("Invalid franking key length".to_string()))?,
+        commitment: *commitment,
+    })
+}
+
+/// Commit to a plaintext: HMAC-SHA256(franking_key, label || plaintext)
+pub fn commit(franking_key: &[u8], plaintext: &[u8]) -> Result<FrankingCommitment> {
+    if franking_key.len() != FRANKING_KEY_LEN {
+        return Err(CryptoError::InvalidKey("Invalid franking key length".to_string()));
+    }
+
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(franking_key)
+        .map_err(|e| CryptoError::InvalidKey(format!("Invalid franking key: {}", e)))?;
+    mac.update(COMMITMENT_LABEL);
+    mac.update(plaintext);
+    Ok(mac.finalize().into_bytes().into())
+}
+
+/// Check a revealed plaintext and franking key against a commitment
+pub fn verify_commitment(commitment: &FrankingCommitment, franking_key: &[u8], plaintext: &[u8]) -> Result<()> {
+    if !ct_eq(&commit(franking_key, plaintext)?, commitment) {
+        return Err(CryptoError::InvalidSignature(
+            "Plaintext does not match the franking commitment".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// AEAD associated data of a franked message: caller AD || commitment
+///
+/// Binds the ciphertext to the commitment the server signs.
+pub fn associated_data(associated_data: &[u8], commitment: &FrankingCommitment) -> Vec<u8> {
+    [associated_data, commitment.as_slice()].concat()
+}
+
+/// Recipient of a franked message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrankingRecipient {
+    /// 1-on-1 message to a user (including the sender's own other devices)
+    User(String),
+    /// Group message
+    Group(String),
+}
+
+impl FrankingRecipient {
+    fn kind(&self) -> u8 {
+        match self {
+            FrankingRecipient::User(_) => 1,
+            FrankingRecipient::Group(_) => 2,
+        }
+    }
+
+    fn id(&self) -> &str {
+        match self {
+            FrankingRecipient::User(id) | FrankingRecipient::Group(id) => id,
+        }
+    }
+}
+
+/// Server signature binding a commitment to the message's sender and recipient
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrankingTag {
+    pub message_id: String,
+    pub sender_user_id: String,
+    pub sender_device_id: String,
+    pub recipient: FrankingRecipient,
+    /// Server timestamp of the message (Unix seconds)
+    pub timestamp: i64,
+    pub commitment: FrankingCommitment,
+    signature: Vec<u8>,
+}
+
+impl FrankingTag {
+    /// Issue a tag signed with the server's franking key
+    pub fn issue(
+        server_key: &IdentityKeyPair,
+        message_id: &str,
+        sender_user_id: &str,
+        sender_device_id: &str,
+        recipient: FrankingRecipient,
+        timestamp: i64,
+        commitment: FrankingCommitment,
+    ) -> Result<Self> {
+        let mut tag = Self {
+            message_id: message_id.to_string(),
+            sender_user_id: sender_user_id.to_string(),
+            sender_device_id: sender_device_id.to_string(),
+            recipient,
+            timestamp,
+            commitment,
+            signature: vec![],
+        };
+        tag.signature = server_key.sign(&Self::signature_input(&tag.signed_data()?))?;
+        Ok(tag)
+    }
+
+    /// Check the signature against the server's public key
+    pub fn verify(&self, server_public_key: &[u8]) -> Result<()> {
+        IdentityKeyPair::verify(
+            server_public_key,
+            &Self::signature_input(&self.signed_data()?),
+            &self.signature,
+        )
+    }
+
+    /// Check a reported plaintext: the tag is genuine and the plaintext matches its commitment
+    pub fn verify_report(&self, server_public_key: &[u8], plaintext: &[u8], franking_key: &[u8]) -> Result<()> {
+        self.verify(server_public_key)?;
+        verify_commitment(&self.commitment, franking_key, plaintext)
+    }
+
+    /// Serialize the tag
+    ///
+    /// Format: version || message ID || sender user ID || sender device ID
+    /// || recipient kind (1) || recipient ID || timestamp (i64 BE) || commitment (32)
+    /// || signature (64), with IDs u16 length-prefixed
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = self.signed_data()?;
+        bytes.extend_from_slice(&self.signature);
+        Ok(bytes)
+    }
+
+    /// Deserialize a tag (the signature is not checked, see [`Self::verify`])
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let invalid = || CryptoError::Protocol("Invalid franking tag".to_string());
+
+        let (&version, mut rest) = bytes.split_first().ok_or_else(invalid)?;
+        if version != FRANKING_TAG_VERSION {
+            return Err(CryptoError::Protocol(format!("Unsupported franking tag version: {}", version)));
+        }
+
+        let read_string = |rest: &mut &[u8]| -> Result<String> {
+            if rest.len() < 2 {
+                return Err(invalid());
+            }
+            let len = u16::from_be_bytes([rest[0], rest[1]]) as usize;
+            if rest.len() < 2 + len {
+                return Err(invalid());
+            }
+            let value = String::from_utf8(rest[2..2 + len].to_vec()).map_err(|_| invalid())?;
+            *rest = &rest[2 + len..];
+            Ok(value)
+        };
+        let message_id = read_string(&mut rest)?;
+        let sender_user_id = read_string(&mut rest)?;
+        let sender_device_id = read_string(&mut rest)?;
+
+        let (&kind, mut rest) = rest.split_first().ok_or_else(invalid)?;
+        let recipient_id = read_string(&mut rest)?;
+        let recipient = match kind {
+            1 => FrankingRecipient::User(recipient_id),
+            2 => FrankingRecipient::Group(recipient_id),
+            _ => return Err(invalid()),
+        };
+
+        if rest.len() != 8 + COMMITMENT_LEN + SIGNATURE_SIZE {
+            return Err(invalid());
+        }
+        let (timestamp, rest) = rest.split_at(8);
+        let (commitment, signature) = rest.split_at(COMMITMENT_LEN);
+
+        Ok(Self {
+            message_id,
+            sender_user_id,
+            sender_device_id,
+            recipient,
+            timestamp: i64::from_be_bytes(timestamp.try_into().map_err(|_| invalid())?),
+            commitment: commitment.try_into().map_err(|_| invalid())?,
+            signature: signature.to_vec(),
+        })
+    }
+
+    /// Everything but the signature
+    fn signed_data(&self) -> Result<Vec<u8>> {
+        let fields = [
+            self.message_id.as_bytes(),
+            self.sender_user_id.as_bytes(),
+            self.sender_device_id.as_bytes(),
+            self.recipient.id().as_bytes(),
+        ];
+        if fields.iter().any(|field| field.len() > u16::MAX as usize) {
+            return Err(CryptoError::Protocol("Franking tag identifier too long".to_string()));
+        }
+
+        let put_string = |data: &mut Vec<u8>, field: &[u8]| {
+            data.extend_from_slice(&(field.len() as u16).to_be_bytes());
+            data.extend_from_slice(field);
+        };
+
+        let mut data = Vec::with_capacity(
+            2 + 8 + fields.iter().map(|field| 2 + field.len()).sum::<usize>() + 8 + COMMITMENT_LEN,
+        );
+        data.push(FRANKING_TAG_VERSION);
+        put_string(&mut data, fields[0]);
+        put_string(&mut data, fields[1]);
+        put_string(&mut data, fields[2]);
+        data.push(self.recipient.kind());
+        put_string(&mut data, fields[3]);
+        data.extend_from_slice(&self.timestamp.to_be_bytes());
+        data.extend_from_slice(&self.commitment);
+        Ok(data)
+    }
+
+    fn signature_input(signed_data: &[u8]) -> Vec<u8> {
+        [TAG_SIGNATURE_CONTEXT, signed_data].concat()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issue_tag(server_key: &IdentityKeyPair, commitment: FrankingCommitment) -> FrankingTag {
+        FrankingTag::issue(
+            server_key,
+            "message-1",
+            "alice",
+            "alice-phone",
+            FrankingRecipient::User("bob".to_string()),
+            1_700_000_000,
+            commitment,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_frank_and_open() {
+        let (payload, commitment) = frank(b"hello bob").unwrap();
+        let opened = open(&payload, &commitment).unwrap();
+
+        assert_eq!(opened.plaintext, b"hello bob");
+        assert_eq!(opened.commitment, commitment);
+        verify_commitment(&commitment, opened.franking_key.expose_secret(), b"hello bob").unwrap();
+    }
+
+    #[test]
+    fn test_open_rejects_other_commitment() {
+        let (payload, _) = frank(b"hello bob").unwrap();
+        let (_, other_commitment) = frank(b"hello bob").unwrap();
+
+        assert!(open(&payload, &other_commitment).is_err());
+    }
+
+    #[test]
+    fn test_open_rejects_malformed_payload() {
+        let (payload, commitment) = frank(b"hello bob").unwrap();
+
+        assert!(open(&[], &commitment).is_err());
+        assert!(open(&payload[..FRANKING_KEY_LEN], &commitment).is_err());
+
+        let mut wrong_version = payload.to_vec();
+        wrong_version[0] = FRANKING_VERSION + 1;
+        assert!(open(&wrong_version, &commitment).is_err());
+    }
+
+    #[test]
+    fn test_tag_round_trip_and_report() {
+        let server_key = IdentityKeyPair::generate().unwrap();
+        let (payload, commitment) = frank(b"abusive message").unwrap();
+        let opened = open(&payload, &commitment).unwrap();
+
+        let tag = FrankingTag::from_bytes(&issue_tag(&server_key, commitment).to_bytes().unwrap()).unwrap();
+        assert_eq!(tag.recipient, FrankingRecipient::User("bob".to_string()));
+
+        let franking_key = opened.franking_key.expose_secret();
+        tag.verify_report(&server_key.public_bytes(), b"abusive message", franking_key).unwrap();
+    }
+
+    #[test]
+    fn test_report_rejects_fabricated_plaintext() {
+        let server_key = IdentityKeyPair::generate().unwrap();
+        let (payload, commitment) = frank(b"hello bob").unwrap();
+        let opened = open(&payload, &commitment).unwrap();
+        let tag = issue_tag(&server_key, commitment);
+
+        let franking_key = opened.franking_key.expose_secret();
+        assert!(tag.verify_report(&server_key.public_bytes(), b"something abusive", franking_key).is_err());
+        assert!(tag.verify_report(&server_key.public_bytes(), b"hello bob", &[0u8; FRANKING_KEY_LEN]).is_err());
+    }
+
+    #[test]
+    fn test_tag_rejects_forgery() {
+        let server_key = IdentityKeyPair::generate().unwrap();
+        let (_, commitment) = frank(b"hello bob").unwrap();
+        let tag = issue_tag(&server_key, commitment);
+
+        let other_key = IdentityKeyPair::generate().unwrap();
+        assert!(tag.verify(&other_key.public_bytes()).is_err());
+
+        let mut forged = tag.clone();
+        forged.sender_user_id = "mallory".to_string();
+        assert!(forged.verify(&server_key.public_bytes()).is_err());
+
+        let mut bytes = tag.to_bytes().unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        assert!(FrankingTag::from_bytes(&bytes).unwrap().verify(&server_key.public_bytes()).is_err());
+        assert!(FrankingTag::from_bytes(&bytes[..last]).is_err());
+    }
+}
@@ -1,24 +1,673 @@
 //! Secure key storage and management
-use crate::Result;
+//!
+//! Key material is wrapped with a key-encryption key (KEK) derived from a
+//! passphrase using Argon2id, and encrypted with AES-256-GCM before it reaches
+//! a storage backend. Backends only ever see versioned, encrypted records, so
+//! they can be swapped (in-memory for tests, files on disk for clients) without
+//! changing how keys are protected.
+//!
+//! Record format (version 1):
+//! `version (1) || nonce (12) || AES-256-GCM ciphertext + tag`
+//! with associated data `"guardyn-keystore" || version || key_id`, which binds
+//! each record to the id it was stored under.
+use crate::double_ratchet::DoubleRatchet;
+use crate::secret::ct_eq;
+use crate::x3dh::{IdentityKeyPair, SignedPreKey};
+use crate::{CryptoError, Result};
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Nonce,
+};
+use argon2::{Algorithm, Argon2, Params, Version};
+use openmls::prelude::tls_codec::{Deserialize as TlsDeserialize, Serialize as TlsSerialize};
+use openmls_basic_credential::SignatureKeyPair;
+use rand::RngCore;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use zeroize::Zeroizing;
 
-pub struct KeyStorage;
+/// Current record format version
+const RECORD_VERSION: u8 = 1;
+
+/// Current KDF parameter record version
+const KDF_PARAMS_VERSION: u8 = 1;
+
+/// Reserved id of the record holding the KDF salt and parameters
+const KDF_PARAMS_ID: &str = ".kdf";
+
+/// Associated data prefix for wrapped records
+const RECORD_AAD_PREFIX: &[u8] = b"guardyn-keystore";
+
+/// Plaintext encrypted into the KDF record to detect a wrong passphrase
+const KEK_CHECK_PLAINTEXT: &[u8] = b"guardyn-keystore-kek-check";
+
+const NONCE_SIZE: usize = 12;
+const SALT_SIZE: usize = 16;
+const KEY_ID_MAX_LEN: usize = 255;
+
+/// Well-known key ids used by the typed helpers
+const IDENTITY_KEY_ID: &str = "identity_key";
+const SIGNED_PRE_KEY_PREFIX: &str = "signed_pre_key/";
+const RATCHET_STATE_PREFIX: &str = "ratchet_state/";
+const MLS_SIGNATURE_KEY_PREFIX: &str = "mls_signature_key/";
+
+/// Storage backend for encrypted key records
+///
+/// Backends persist opaque records by id. They never see plaintext key
+/// material; encryption is handled by [`KeyStorage`].
+pub trait KeyStorageBackend: Send + Sync {
+    /// Write a record, replacing any existing record with the same id
+    fn write(&self, key_id: &str, record: &[u8]) -> Result<()>;
+
+    /// Read a record, returning `None` if it does not exist
+    fn read(&self, key_id: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Remove a record (no-op if it does not exist)
+    fn remove(&self, key_id: &str) -> Result<()>;
+
+    /// List ids of all stored records
+    fn list(&self) -> Result<Vec<String>>;
+}
+
+/// In-memory backend for tests and short-lived harnesses
+#[derive(Debug, Default)]
+pub struct MemoryBackend {
+    records: RwLock<HashMap<String, Vec<u8>>>,
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl KeyStorageBackend for MemoryBackend {
+    fn write(&self, key_id: &str, record: &[u8]) -> Result<()> {
+        self.records
+            .write()
+            .unwrap()
+            .insert(key_id.to_string(), record.to_vec());
+        Ok(())
+    }
+
+    fn read(&self, key_id: &str) -> Result<Option<Vec<u8>>> {
+        Ok(self.records.read().unwrap().get(key_id).cloned())
+    }
+
+    fn remove(&self, key_id: &str) -> Result<()> {
+        self.records.write().unwrap().remove(key_id);
+        Ok(())
+    }
+
+    fn list(&self) -> Result<Vec<String>> {
+        Ok(self.records.read().unwrap().keys().cloned().collect())
+    }
+}
+
+/// File backend storing one record per file in a directory
+///
+/// File names are the hex-encoded key id, so arbitrary ids cannot escape the
+/// directory. Writes go to a temporary file which is then renamed over the
+/// target, so a crash never leaves a half-written record behind.
+#[derive(Debug, Clone)]
+pub struct FileBackend {
+    dir: PathBuf,
+}
+
+impl FileBackend {
+    /// Open (and create if needed) a key directory
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir).map_err(|e| {
+            CryptoError::Storage(format!("Failed to create key directory {:?}: {}", dir, e))
+        })?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&dir, fs::Permissions::from_mode(0o700)).map_err(|e| {
+                CryptoError::Storage(format!("Failed to restrict key directory permissions: {}", e))
+            })?;
+        }
+
+        Ok(Self { dir })
+    }
+
+    fn record_path(&self, key_id: &str) -> PathBuf {
+        self.dir.join(format!("{}.key", hex_encode(key_id.as_bytes())))
+    }
+}
+
+impl KeyStorageBackend for FileBackend {
+    fn write(&self, key_id: &str, record: &[u8]) -> Result<()> {
+        let path = self.record_path(key_id);
+        let tmp_path = path.with_extension("tmp");
+
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+
+        let mut file = options
+            .open(&tmp_path)
+            .map_err(|e| CryptoError::Storage(format!("Failed to create key file: {}", e)))?;
+        file.write_all(record)
+            .and_then(|_| file.sync_all())
+            .map_err(|e| CryptoError::Storage(format!("Failed to write key file: {}", e)))?;
+
+        fs::rename(&tmp_path, &path)
+            .map_err(|e| CryptoError::Storage(format!("Failed to replace key file: {}", e)))?;
+
+        Ok(())
+    }
+
+    fn read(&self, key_id: &str) -> Result<Option<Vec<u8>>> {
+        match fs::read(self.record_path(key_id)) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(CryptoError::Storage(format!("Failed to read key file: {}", e))),
+        }
+    }
+
+    fn remove(&self, key_id: &str) -> Result<()> {
+        match fs::remove_file(self.record_path(key_id)) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(CryptoError::Storage(format!("Failed to delete key file: {}", e))),
+        }
+    }
+
+    fn list(&self) -> Result<Vec<String>> {
+        let entries = fs::read_dir(&self.dir)
+            .map_err(|e| CryptoError::Storage(format!("Failed to list key directory: {}", e)))?;
+
+        let mut ids = Vec::new();
+        for entry in entries {
+            let entry = entry
+                .map_err(|e| CryptoError::Storage(format!("Failed to list key directory: {}", e)))?;
+            let file_name = entry.file_name();
+            let Some(stem) = file_name.to_str().and_then(|name| name.strip_suffix(".key")) else {
+                continue;
+            };
+            if let Some(id) = hex_decode(stem).and_then(|bytes| String::from_utf8(bytes).ok()) {
+                ids.push(id);
+            }
+        }
+
+        Ok(ids)
+    }
+}
+
+/// Argon2id cost parameters for deriving the key-encryption key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KdfParams {
+    /// Memory cost in KiB
+    pub memory_kib: u32,
+    /// Number of iterations
+    pub iterations: u32,
+    /// Degree of parallelism
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    /// OWASP recommended Argon2id parameters (19 MiB, 2 iterations, 1 lane)
+    fn default() -> Self {
+        Self {
+            memory_kib: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+/// Encrypted key store
+///
+/// Wraps every key with a passphrase-derived KEK before handing it to the
+/// backend. The KDF salt and parameters are stored in a reserved record of the
+/// backend, so reopening the store with the same passphrase derives the same KEK.
+pub struct KeyStorage {
+    backend: Box<dyn KeyStorageBackend>,
+    kek: Zeroizing<[u8; 32]>,
+}
 
 impl KeyStorage {
+    /// Open a key store, initializing it with default KDF parameters if empty
+    ///
+    /// # Arguments
+    /// * `backend` - Storage backend holding the encrypted records
+    /// * `passphrase` - Passphrase the KEK is derived from
+    ///
+    /// # Returns
+    /// KeyStorage, or an error if the passphrase does not match the store
+    pub fn open(backend: impl KeyStorageBackend + 'static, passphrase: &[u8]) -> Result<Self> {
+        Self::open_with_params(backend, passphrase, KdfParams::default())
+    }
+
+    /// Open a key store, using `params` if the store has to be initialized
+    ///
+    /// Existing stores always use the parameters they were created with.
+    pub fn open_with_params(
+        backend: impl KeyStorageBackend + 'static,
+        passphrase: &[u8],
+        params: KdfParams,
+    ) -> Result<Self> {
+        let backend: Box<dyn KeyStorageBackend> = Box::new(backend);
+
+        match backend.read(KDF_PARAMS_ID)? {
+            Some(record) => {
+                let (params, salt, check) = decode_kdf_record(&record)?;
+                let kek = derive_kek(passphrase, &salt, params)?;
+
+                let check_plaintext = decrypt_record(&kek, KDF_PARAMS_ID, check)
+                    .map_err(|_| CryptoError::Storage("Invalid key storage passphrase".to_string()))?;
+                if !ct_eq(&check_plaintext, KEK_CHECK_PLAINTEXT) {
+                    return Err(CryptoError::Storage("Invalid key storage passphrase".to_string()));
+                }
+
+                Ok(Self { backend, kek })
+            }
+            None => {
+                let mut salt = [0u8; SALT_SIZE];
+                rand::thread_rng().fill_bytes(&mut salt);
+                let kek = derive_kek(passphrase, &salt, params)?;
+
+                let check = encrypt_record(&kek, KDF_PARAMS_ID, KEK_CHECK_PLAINTEXT)?;
+                backend.write(KDF_PARAMS_ID, &encode_kdf_record(params, &salt, &check))?;
+
+                Ok(Self { backend, kek })
+            }
+        }
+    }
+
     /// Store a key securely
-    pub fn store_key(_key_id: &str, _key_material: &[u8]) -> Result<()> {
-        // TODO: Implement using encrypted storage (e.g., encrypted SQLite)
-        Ok(())
+    pub fn store_key(&self, key_id: &str, key_material: &[u8]) -> Result<()> {
+        validate_key_id(key_id)?;
+        let record = encrypt_record(&self.kek, key_id, key_material)?;
+        self.backend.write(key_id, &record)
     }
 
     /// Retrieve a key
-    pub fn get_key(_key_id: &str) -> Result<Vec<u8>> {
-        // TODO: Implement key retrieval
-        Ok(vec![])
+    ///
+    /// Returns `None` if no key is stored under `key_id`. The returned
+    /// material is wiped from memory when dropped.
+    pub fn get_key(&self, key_id: &str) -> Result<Option<Zeroizing<Vec<u8>>>> {
+        validate_key_id(key_id)?;
+        match self.backend.read(key_id)? {
+            Some(record) => decrypt_record(&self.kek, key_id, &record).map(Some),
+            None => Ok(None),
+        }
     }
 
     /// Delete a key
-    pub fn delete_key(_key_id: &str) -> Result<()> {
-        // TODO: Implement key deletion
-        Ok(())
+    pub fn delete_key(&self, key_id: &str) -> Result<()> {
+        validate_key_id(key_id)?;
+        self.backend.remove(key_id)
+    }
+
+    /// List ids of all stored keys
+    pub fn list_keys(&self) -> Result<Vec<String>> {
+        let mut ids: Vec<String> = self
+            .backend
+            .list()?
+            .into_iter()
+            .filter(|id| id != KDF_PARAMS_ID)
+            .collect();
+        ids.sort();
+        Ok(ids)
+    }
+
+    /// Store the long-term identity key pair
+    pub fn store_identity_key(&self, identity: &IdentityKeyPair) -> Result<()> {
+        self.store_key(IDENTITY_KEY_ID, identity.secret_bytes().as_slice())
+    }
+
+    /// Load the long-term identity key pair
+    pub fn load_identity_key(&self) -> Result<Option<IdentityKeyPair>> {
+        self.get_key(IDENTITY_KEY_ID)?
+            .map(|bytes| IdentityKeyPair::from_secret_bytes(&bytes))
+            .transpose()
+    }
+
+    /// Store a signed pre-key under its key id
+    pub fn store_signed_pre_key(&self, signed_pre_key: &SignedPreKey) -> Result<()> {
+        let key_id = format!("{}{}", SIGNED_PRE_KEY_PREFIX, signed_pre_key.key_id);
+        self.store_key(&key_id, &signed_pre_key.to_bytes())
+    }
+
+    /// Load a signed pre-key by its key id
+    pub fn load_signed_pre_key(&self, signed_pre_key_id: u32) -> Result<Option<SignedPreKey>> {
+        let key_id = format!("{}{}", SIGNED_PRE_KEY_PREFIX, signed_pre_key_id);
+        self.get_key(&key_id)?
+            .map(|bytes| SignedPreKey::from_bytes(&bytes))
+            .transpose()
+    }
+
+    /// Store the Double Ratchet state of a session
+    pub fn store_ratchet_state(&self, session_id: &str, ratchet: &DoubleRatchet) -> Result<()> {
+        let state = ratchet.serialize();
+        self.store_key(&format!("{}{}", RATCHET_STATE_PREFIX, session_id), &state)
+    }
+
+    /// Load the Double Ratchet state of a session
+    pub fn load_ratchet_state(&self, session_id: &str) -> Result<Option<DoubleRatchet>> {
+        self.get_key(&format!("{}{}", RATCHET_STATE_PREFIX, session_id))?
+            .map(|bytes| DoubleRatchet::deserialize(&bytes))
+            .transpose()
+    }
+
+    /// List ids of all sessions with a stored Double Ratchet state
+    pub fn list_ratchet_sessions(&self) -> Result<Vec<String>> {
+        Ok(self
+            .list_keys()?
+            .into_iter()
+            .filter_map(|id| id.strip_prefix(RATCHET_STATE_PREFIX).map(str::to_string))
+            .collect())
+    }
+
+    /// Store an MLS signature key pair under a name (e.g. the device id)
+    pub fn store_mls_signature_key(&self, name: &str, keypair: &SignatureKeyPair) -> Result<()> {
+        let bytes = Zeroizing::new(keypair.tls_serialize_detached().map_err(|e| {
+            CryptoError::Storage(format!("Failed to serialize MLS signature key: {:?}", e))
+        })?);
+        self.store_key(&format!("{}{}", MLS_SIGNATURE_KEY_PREFIX, name), &bytes)
+    }
+
+    /// Load an MLS signature key pair by name
+    pub fn load_mls_signature_key(&self, name: &str) -> Result<Option<SignatureKeyPair>> {
+        self.get_key(&format!("{}{}", MLS_SIGNATURE_KEY_PREFIX, name))?
+            .map(|bytes| {
+                SignatureKeyPair::tls_deserialize_exact(bytes.as_slice()).map_err(|e| {
+                    CryptoError::Storage(format!("Failed to deserialize MLS signature key: {:?}", e))
+                })
+            })
+            .transpose()
+    }
+}
+
+fn validate_key_id(key_id: &str) -> Result<()> {
+    if key_id.is_empty() || key_id.len() > KEY_ID_MAX_LEN {
+        return Err(CryptoError::Storage(format!(
+            "Key id must be 1-{} bytes long",
+            KEY_ID_MAX_LEN
+        )));
+    }
+    if key_id.starts_with('.') {
+        return Err(CryptoError::Storage(format!("Key id '{}' is reserved", key_id)));
+    }
+    Ok(())
+}
+
+pub(crate) fn derive_kek(passphrase: &[u8], salt: &[u8], params: KdfParams) -> Result<Zeroizing<[u8; 32]>> {
+    let argon2_params = Params::new(params.memory_kib, params.iterations, params.parallelism, Some(32))
+        .map_err(|e| CryptoError::KeyGeneration(format!("Invalid Argon2 parameters: {}", e)))?;
+    let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, argon2_params);
+
+    let mut kek = Zeroizing::new([0u8; 32]);
+    argon2
+        .hash_password_into(passphrase, salt, kek.as_mut())
+        .map_err(|e| CryptoError::KeyGeneration(format!("Failed to derive KEK: {}", e)))?;
+
+    Ok(kek)
+}
+
+fn record_aad(version: u8, key_id: &str) -> Vec<u8> {
+    let mut aad = Vec::with_capacity(RECORD_AAD_PREFIX.len() + 1 + key_id.len());
+    aad.extend_from_slice(RECORD_AAD_PREFIX);
+    aad.push(version);
+    aad.extend_from_slice(key_id.as_bytes());
+    aad
+}
+
+fn encrypt_record(kek: &[u8; 32], key_id: &str, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let cipher = Aes256Gcm::new_from_slice(kek)
+        .map_err(|e| CryptoError::Encryption(format!("Failed to create cipher: {}", e)))?;
+
+    let mut nonce_bytes = [0u8; NONCE_SIZE];
+    rand::thread_rng().fill_bytes(&mut nonce_bytes);
+
+    let aad = record_aad(RECORD_VERSION, key_id);
+    let ciphertext = cipher
+        .encrypt(&Nonce::from(nonce_bytes), Payload { msg: plaintext, aad: &aad })
+        .map_err(|e| CryptoError::Encryption(format!("Failed to wrap key: {}", e)))?;
+
+    let mut record = Vec::with_capacity(1 + NONCE_SIZE + ciphertext.len());
+    record.push(RECORD_VERSION);
+    record.extend_from_slice(&nonce_bytes);
+    record.extend_from_slice(&ciphertext);
+    Ok(record)
+}
+
+fn decrypt_record(kek: &[u8; 32], key_id: &str, record: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+    let (&version, rest) = record
+        .split_first()
+        .ok_or_else(|| CryptoError::Storage("Empty key record".to_string()))?;
+
+    if version != RECORD_VERSION {
+        return Err(CryptoError::Storage(format!(
+            "Unsupported key record version: {}",
+            version
+        )));
+    }
+    let (nonce_bytes, ciphertext) = rest
+        .split_first_chunk::<NONCE_SIZE>()
+        .ok_or_else(|| CryptoError::Storage("Key record too short".to_string()))?;
+    let cipher = Aes256Gcm::new_from_slice(kek)
+        .map_err(|e| CryptoError::Decryption(format!("Failed to create cipher: {}", e)))?;
+
+    let aad = record_aad(version, key_id);
+    let plaintext = cipher
+        .decrypt(&Nonce::from(*nonce_bytes), Payload { msg: ciphertext, aad: &aad })
+        .map_err(|e| CryptoError::Decryption(format!("Failed to unwrap key: {}", e)))?;
+
+    Ok(Zeroizing::new(plaintext))
+}
+
+/// KDF record: version (1) || memory_kib (u32 BE) || iterations (u32 BE) ||
+/// parallelism (u32 BE) || salt (16) || passphrase check record
+fn encode_kdf_record(params: KdfParams, salt: &[u8; SALT_SIZE], check: &[u8]) -> Vec<u8> {
+    let mut record = Vec::with_capacity(13 + SALT_SIZE + check.len());
+    record.push(KDF_PARAMS_VERSION);
+    record.extend_from_slice(&params.memory_kib.to_be_bytes());
+    record.extend_from_slice(&params.iterations.to_be_bytes());
+    record.extend_from_slice(&params.parallelism.to_be_bytes());
+    record.extend_from_slice(salt);
+    record.extend_from_slice(check);
+    record
+}
+
+fn decode_kdf_record(record: &[u8]) -> Result<(KdfParams, [u8; SALT_SIZE], &[u8])> {
+    if record.len() < 13 + SALT_SIZE {
+        return Err(CryptoError::Storage("KDF parameter record too short".to_string()));
+    }
+    if record[0] != KDF_PARAMS_VERSION {
+        return Err(CryptoError::Storage(format!(
+            "Unsupported KDF parameter record version: {}",
+            record[0]
+        )));
+    }
+
+    let read_u32 = |offset: usize| u32::from_be_bytes(record[offset..offset + 4].try_into().unwrap());
+    let params = KdfParams {
+        memory_kib: read_u32(1),
+        iterations: read_u32(5),
+        parallelism: read_u32(9),
+    };
+
+    let mut salt = [0u8; SALT_SIZE];
+    salt.copy_from_slice(&record[13..13 + SALT_SIZE]);
+
+    Ok((params, salt, &record[13 + SALT_SIZE..]))
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hex_decode(s: &str) -> Option<Vec<u8>> {
+    s.as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            let pair = std::str::from_utf8(pair).ok().filter(|pair| pair.len() == 2)?;
+            u8::from_str_radix(pair, 16).ok()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    /// Cheap Argon2 parameters so tests run quickly
+    fn test_params() -> KdfParams {
+        KdfParams {
+            memory_kib: 64,
+            iterations: 1,
+            parallelism: 1,
+        }
+    }
+
+    /// Backend sharing records between several KeyStorage instances
+    #[derive(Clone, Default)]
+    struct SharedBackend(Arc<MemoryBackend>);
+
+    impl KeyStorageBackend for SharedBackend {
+        fn write(&self, key_id: &str, record: &[u8]) -> Result<()> {
+            self.0.write(key_id, record)
+        }
+        fn read(&self, key_id: &str) -> Result<Option<Vec<u8>>> {
+            self.0.read(key_id)
+        }
+        fn remove(&self, key_id: &str) -> Result<()> {
+            self.0.remove(key_id)
+        }
+        fn list(&self) -> Result<Vec<String>> {
+            self.0.list()
+        }
+    }
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("guardyn-keystore-test-{:016x}", rand::random::<u64>()))
+    }
+
+    #[test]
+    fn test_store_get_delete() {
+        let storage = KeyStorage::open_with_params(MemoryBackend::new(), b"passphrase", test_params()).unwrap();
+
+        storage.store_key("device_key", b"secret material").unwrap();
+        assert_eq!(storage.get_key("device_key").unwrap().unwrap().as_slice(), b"secret material");
+        assert_eq!(storage.list_keys().unwrap(), vec!["device_key".to_string()]);
+
+        storage.delete_key("device_key").unwrap();
+        assert!(storage.get_key("device_key").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_records_are_encrypted() {
+        let backend = SharedBackend::default();
+        let storage = KeyStorage::open_with_params(backend.clone(), b"passphrase", test_params()).unwrap();
+        storage.store_key("device_key", b"secret material").unwrap();
+
+        let record = backend.read("device_key").unwrap().unwrap();
+        assert_eq!(record[0], RECORD_VERSION);
+        assert!(!record.windows(15).any(|window| window == b"secret material"));
+    }
+
+    #[test]
+    fn test_reopen_with_passphrase() {
+        let backend = SharedBackend::default();
+        {
+            let storage = KeyStorage::open_with_params(backend.clone(), b"passphrase", test_params()).unwrap();
+            storage.store_key("device_key", b"secret material").unwrap();
+        }
+
+        // Stored parameters take precedence over the ones passed on reopen
+        let storage = KeyStorage::open(backend.clone(), b"passphrase").unwrap();
+        assert_eq!(storage.get_key("device_key").unwrap().unwrap().as_slice(), b"secret material");
+
+        let wrong = KeyStorage::open(backend, b"wrong passphrase");
+        assert!(matches!(wrong, Err(CryptoError::Storage(_))));
+    }
+
+    #[test]
+    fn test_record_bound_to_key_id() {
+        let backend = SharedBackend::default();
+        let storage = KeyStorage::open_with_params(backend.clone(), b"passphrase", test_params()).unwrap();
+        storage.store_key("key_a", b"material a").unwrap();
+
+        // Moving a record to another id must not decrypt
+        let record = backend.read("key_a").unwrap().unwrap();
+        backend.write("key_b", &record).unwrap();
+        assert!(storage.get_key("key_b").is_err());
+    }
+
+    #[test]
+    fn test_rejects_reserved_and_unknown_versions() {
+        let backend = SharedBackend::default();
+        let storage = KeyStorage::open_with_params(backend.clone(), b"passphrase", test_params()).unwrap();
+        assert!(storage.store_key(KDF_PARAMS_ID, b"x").is_err());
+        assert!(storage.store_key("", b"x").is_err());
+
+        storage.store_key("device_key", b"secret").unwrap();
+        let mut record = backend.read("device_key").unwrap().unwrap();
+        record[0] = RECORD_VERSION + 1;
+        backend.write("device_key", &record).unwrap();
+        assert!(matches!(storage.get_key("device_key"), Err(CryptoError::Storage(_))));
+    }
+
+    #[test]
+    fn test_file_backend_roundtrip() {
+        let dir = temp_dir();
+        {
+            let storage =
+                KeyStorage::open_with_params(FileBackend::open(&dir).unwrap(), b"passphrase", test_params()).unwrap();
+            storage.store_key("sessions/../escape", b"secret material").unwrap();
+        }
+
+        let storage = KeyStorage::open(FileBackend::open(&dir).unwrap(), b"passphrase").unwrap();
+        assert_eq!(storage.list_keys().unwrap(), vec!["sessions/../escape".to_string()]);
+        assert_eq!(storage.get_key("sessions/../escape").unwrap().unwrap().as_slice(), b"secret material");
+
+        storage.delete_key("sessions/../escape").unwrap();
+        assert!(storage.list_keys().unwrap().is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_typed_key_roundtrips() {
+        let storage = KeyStorage::open_with_params(MemoryBackend::new(), b"passphrase", test_params()).unwrap();
+
+        let identity = IdentityKeyPair::generate().unwrap();
+        storage.store_identity_key(&identity).unwrap();
+        let loaded_identity = storage.load_identity_key().unwrap().unwrap();
+        assert_eq!(loaded_identity.public_bytes(), identity.public_bytes());
+
+        let signed_pre_key = SignedPreKey::generate(7, &identity).unwrap();
+        storage.store_signed_pre_key(&signed_pre_key).unwrap();
+        let loaded_spk = storage.load_signed_pre_key(7).unwrap().unwrap();
+        assert_eq!(loaded_spk.public_bytes(), signed_pre_key.public_bytes());
+        assert_eq!(loaded_spk.signature, signed_pre_key.signature);
+        assert_eq!(loaded_spk.timestamp, signed_pre_key.timestamp);
+        assert!(storage.load_signed_pre_key(8).unwrap().is_none());
+
+        let bob_secret = x25519_dalek::StaticSecret::random_from_rng(rand::rngs::OsRng);
+        let bob_public = x25519_dalek::PublicKey::from(&bob_secret);
+        let ratchet = DoubleRatchet::init_alice(&[1u8; 32], bob_public).unwrap();
+        storage.store_ratchet_state("session", &ratchet).unwrap();
+        let loaded_ratchet = storage.load_ratchet_state("session").unwrap().unwrap();
+        assert_eq!(loaded_ratchet.public_key(), ratchet.public_key());
+        assert_eq!(storage.list_ratchet_sessions().unwrap(), vec!["session".to_string()]);
+
+        let mls_keypair = crate::mls::create_test_keypair().unwrap();
+        storage.store_mls_signature_key("device1", &mls_keypair).unwrap();
+        let loaded_mls = storage.load_mls_signature_key("device1").unwrap().unwrap();
+        assert_eq!(loaded_mls.public(), mls_keypair.public());
     }
 }
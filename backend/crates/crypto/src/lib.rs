@@ -1,14 +1,29 @@
 /// Cryptographic protocols and primitives for Guardyn
 ///
 /// This crate implements:
-/// - X3DH key agreement protocol
+/// - X3DH key agreement protocol (with hybrid post-quantum PQXDH mode)
 /// - Double Ratchet for 1-on-1 messaging
-/// - MLS (Messaging Layer Security) for group chat
+/// - MLS (Messaging Layer Security) for group chat, with credentials bound to identity keys
 /// - Key derivation and storage
+/// - Secret key material that is zeroized on drop and never printed
+/// - Encrypted backups of keys and message history
+/// - Safety numbers for identity key verification
+/// - Sealed sender envelopes hiding the sender from the server
+/// - Chunked streaming encryption of media attachments
+/// - Message franking for verifiable abuse reports
 pub mod x3dh;
+pub mod pq_kem;
 pub mod double_ratchet;
 pub mod mls;
+pub mod mls_credential;
+pub mod mls_storage;
 pub mod key_storage;
+pub mod secret;
+pub mod backup;
+pub mod fingerprint;
+pub mod sealed_sender;
+pub mod attachment;
+pub mod franking;
 
 #[cfg(test)]
 mod mls_tests;
@@ -16,9 +31,20 @@ mod mls_tests;
 #[cfg(test)]
 mod x3dh_conversion_tests;
 
+#[cfg(test)]
+mod double_ratchet_proptests;
+
 pub use x3dh::{X3DHKeyBundle, X3DHProtocol};
 pub use double_ratchet::DoubleRatchet;
-pub use mls::{MlsGroupManager, create_test_credential};
+pub use mls::MlsGroupManager;
+pub use mls_credential::{MlsCredential, MlsMemberIdentity};
+pub use key_storage::{KeyStorage, KeyStorageBackend, FileBackend, MemoryBackend};
+pub use backup::RecoveryCode;
+pub use fingerprint::Fingerprint;
+pub use secret::SecretKey;
+pub use sealed_sender::{SealedSender, SenderCertificate};
+pub use attachment::{AttachmentDecryptor, AttachmentEncryptor, AttachmentPointer};
+pub use franking::{FrankedPlaintext, FrankingRecipient, FrankingTag};
 
 use thiserror::Error;
 
@@ -41,6 +67,9 @@ pub enum CryptoError {
 
     #[error("Protocol error: {0}")]
     Protocol(String),
+
+    #[error("Key storage error: {0}")]
+    Storage(String),
 }
 
 pub type Result<T> = std::result::Result<T, CryptoError>;
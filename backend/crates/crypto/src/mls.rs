@@ -4,27 +4,185 @@
 /// Provides secure group communication with forward secrecy, post-compromise security,
 /// and membership changes (add/remove members).
 
+use crate::franking::{self, FrankedPlaintext, FrankingCommitment};
+use crate::secret::ct_eq;
 use crate::{CryptoError, Result};
 use openmls::prelude::*;
+use openmls::messages::group_info::VerifiableGroupInfo;
 use openmls_basic_credential::SignatureKeyPair;
+use crate::mls_credential::{MlsCredential, MlsMemberIdentity};
+use crate::mls_storage::{MlsProvider, MlsStorage, MlsStorageChanges};
 use openmls_rust_crypto::{OpenMlsRustCrypto, RustCrypto};
 use serde::{Deserialize, Serialize};
 use tls_codec::{Deserialize as TlsDeserialize, Serialize as TlsSerialize};
+use zeroize::Zeroizing;
 
-/// MLS ciphersuite configuration
+pub use openmls::prelude::Ciphersuite as MlsCiphersuite;
+pub use openmls_basic_credential::SignatureKeyPair as MlsSignatureKeyPair;
+
+/// Default MLS ciphersuite
 /// Using MLS_128_DHKEMX25519_AES128GCM_SHA256_Ed25519 for performance and security balance
-const MLS_CIPHERSUITE: Ciphersuite =
+pub const DEFAULT_MLS_CIPHERSUITE: Ciphersuite =
     Ciphersuite::MLS_128_DHKEMX25519_AES128GCM_SHA256_Ed25519;
 
+/// Ciphersuites registered for MLS (RFC 9420), in code point order
+const KNOWN_CIPHERSUITES: [Ciphersuite; 7] = [
+    Ciphersuite::MLS_128_DHKEMX25519_AES128GCM_SHA256_Ed25519,
+    Ciphersuite::MLS_128_DHKEMP256_AES128GCM_SHA256_P256,
+    Ciphersuite::MLS_128_DHKEMX25519_CHACHA20POLY1305_SHA256_Ed25519,
+    Ciphersuite::MLS_256_DHKEMX448_AES256GCM_SHA512_Ed448,
+    Ciphersuite::MLS_256_DHKEMP521_AES256GCM_SHA512_P521,
+    Ciphersuite::MLS_256_DHKEMX448_CHACHA20POLY1305_SHA512_Ed448,
+    Ciphersuite::MLS_256_DHKEMP384_AES256GCM_SHA384_P384,
+];
+
+/// Ciphersuites the crypto backend can run
+pub fn supported_ciphersuites() -> Vec<Ciphersuite> {
+    RustCrypto::default().supported_ciphersuites()
+}
+
+/// Parse an MLS ciphersuite by its RFC 9420 name or code point (e.g. `0x0003`)
+///
+/// Registered suites the crypto backend cannot run (X448, P-384, P-521) are
+/// rejected with an explicit error instead of failing later at group creation.
+pub fn parse_ciphersuite(name: &str) -> Result<Ciphersuite> {
+    let name = name.trim();
+    let ciphersuite = match name.strip_prefix("0x") {
+        Some(code) => u16::from_str_radix(code, 16)
+            .ok()
+            .and_then(|code| Ciphersuite::try_from(code).ok()),
+        None => KNOWN_CIPHERSUITES
+            .into_iter()
+            .find(|ciphersuite| ciphersuite.to_string().eq_ignore_ascii_case(name)),
+    }
+    .ok_or_else(|| CryptoError::Protocol(format!("Unknown MLS ciphersuite: {}", name)))?;
+
+    if !supported_ciphersuites().contains(&ciphersuite) {
+        return Err(CryptoError::Protocol(format!(
+            "MLS ciphersuite {} is not supported by the crypto backend",
+            ciphersuite
+        )));
+    }
+
+    Ok(ciphersuite)
+}
+
+/// Generate a signature keypair matching a ciphersuite's signature scheme
+pub fn generate_signature_keypair(ciphersuite: Ciphersuite) -> Result<SignatureKeyPair> {
+    SignatureKeyPair::new(ciphersuite.signature_algorithm())
+        .map_err(|e| CryptoError::Protocol(format!("Failed to generate signature key: {:?}", e)))
+}
+
+/// Ciphersuites a serialized key package can join groups with
+///
+/// # Returns
+/// Tuple of (ciphersuite, supported) - the suite the key package was built
+/// for and the suites its leaf node advertises
+pub fn key_package_ciphersuites(key_package_bytes: &[u8]) -> Result<(Ciphersuite, Vec<Ciphersuite>)> {
+    let key_package = validate_key_package(key_package_bytes)?;
+
+    let capabilities = key_package.leaf_node().capabilities();
+    let supported = KNOWN_CIPHERSUITES
+        .into_iter()
+        .filter(|ciphersuite| capabilities.ciphersuites().contains(&(*ciphersuite).into()))
+        .collect();
+
+    Ok((key_package.ciphersuite(), supported))
+}
+
+/// Verified identity of a serialized key package's owner
+///
+/// The key package is validated and its credential checked against the
+/// leaf's signature key (see [`MlsCredential`]).
+pub fn key_package_identity(key_package_bytes: &[u8]) -> Result<MlsMemberIdentity> {
+    let key_package = validate_key_package(key_package_bytes)?;
+    verify_leaf_node(key_package.leaf_node())
+}
+
+/// Epoch a serialized group message (commit, proposal or application) was sent in
+pub fn message_epoch(message_bytes: &[u8]) -> Result<u64> {
+    let mut reader = message_bytes;
+    let message = MlsMessageIn::tls_deserialize(&mut reader)
+        .map_err(|e| CryptoError::Protocol(format!("Failed to deserialize message: {:?}", e)))?;
+
+    let protocol_message: ProtocolMessage = message.try_into()
+        .map_err(|e| CryptoError::Protocol(format!("Failed to convert message: {:?}", e)))?;
+
+    Ok(protocol_message.epoch().as_u64())
+}
+
+/// Deserialize and validate a key package
+fn validate_key_package(key_package_bytes: &[u8]) -> Result<KeyPackage> {
+    // Deserialize key package (OpenMLS 0.6 - KeyPackageIn needs validation)
+    let mut reader = key_package_bytes;
+    let key_package_in = KeyPackageIn::tls_deserialize(&mut reader)
+        .map_err(|e| {
+            CryptoError::Protocol(format!("Failed to deserialize key package: {:?}", e))
+        })?;
+
+    // Validate and convert KeyPackageIn to KeyPackage
+    let rust_crypto = RustCrypto::default();
+    key_package_in.validate(&rust_crypto, ProtocolVersion::default())
+        .map_err(|e| {
+            CryptoError::Protocol(format!("Failed to validate key package: {:?}", e))
+        })
+}
+
+/// Verify the Guardyn credential of a leaf node
+fn verify_leaf_node(leaf_node: &LeafNode) -> Result<MlsMemberIdentity> {
+    MlsCredential::verify_leaf(leaf_node.credential(), leaf_node.signature_key().as_slice())
+}
+
+/// Verify credentials of members a proposal adds or updates
+fn verify_proposal(proposal: &Proposal) -> Result<()> {
+    match proposal {
+        Proposal::Add(add) => verify_leaf_node(add.key_package().leaf_node()).map(|_| ()),
+        Proposal::Update(update) => verify_leaf_node(update.leaf_node()).map(|_| ()),
+        _ => Ok(()),
+    }
+}
+
+/// Verify credentials of all leaves a commit introduces or changes
+///
+/// # Returns
+/// Identity of the committer's new leaf, if the commit has an update path
+fn verify_staged_commit(staged_commit: &StagedCommit) -> Result<Option<MlsMemberIdentity>> {
+    for add in staged_commit.add_proposals() {
+        verify_leaf_node(add.add_proposal().key_package().leaf_node())?;
+    }
+    for update in staged_commit.update_proposals() {
+        verify_leaf_node(update.update_proposal().leaf_node())?;
+    }
+
+    staged_commit
+        .update_path_leaf_node()
+        .map(verify_leaf_node)
+        .transpose()
+}
+
+/// Verify the credentials of all members of a group
+fn verify_members(mls_group: &MlsGroup) -> Result<Vec<MlsMemberIdentity>> {
+    mls_group
+        .members()
+        .map(|member| MlsCredential::verify_leaf(&member.credential, &member.signature_key))
+        .collect()
+}
+
 /// Key package with metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MlsKeyPackage {
     pub package_id: Vec<u8>,
     pub key_package_bytes: Vec<u8>,
     pub credential_identity: Vec<u8>,
+    /// Ciphersuite the key package was built for
+    pub ciphersuite: u16,
 }
 
 /// Group state for serialization/deserialization
+///
+/// `serialized_state` is a snapshot of the group's OpenMLS storage
+/// (see [`MlsStorage::to_bytes`]) and can be restored with
+/// [`MlsGroupManager::from_state`].
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MlsGroupState {
     pub group_id: Vec<u8>,
@@ -38,31 +196,63 @@ pub struct MlsGroupState {
 /// Each instance represents a single MLS group from one member's perspective.
 pub struct MlsGroupManager {
     mls_group: MlsGroup,
-    crypto_backend: OpenMlsRustCrypto,
+    crypto_backend: MlsProvider,
     credential_with_key: CredentialWithKey,
     signature_keypair: SignatureKeyPair,  // Store the keypair for signing operations
 }
 
 impl MlsGroupManager {
-    /// Create a new MLS group as the creator
+    /// Create a new MLS group as the creator with the default ciphersuite
     ///
     /// # Arguments
     /// * `group_id` - Unique group identifier
-    /// * `creator_identity` - Creator's identity (user_id:device_id)
+    /// * `creator_credential` - Creator's credential, issued for the signature keypair
     /// * `signature_keypair` - Creator's signature keypair for signing operations
     ///
     /// # Returns
     /// New MlsGroupManager instance with initialized group
     pub fn create_group(
         group_id: &str,
-        creator_identity: &[u8],
+        creator_credential: &MlsCredential,
         signature_keypair: SignatureKeyPair,
     ) -> Result<Self> {
-        let crypto_backend = OpenMlsRustCrypto::default();
-        let group_id_bytes = group_id.as_bytes().to_vec();
+        Self::create_group_with_ciphersuite(
+            group_id,
+            creator_credential,
+            signature_keypair,
+            DEFAULT_MLS_CIPHERSUITE,
+        )
+    }
+
+    /// Create a new MLS group as the creator
+    ///
+    /// # Arguments
+    /// * `group_id` - Unique group identifier
+    /// * `creator_credential` - Creator's credential, issued for the signature keypair
+    /// * `signature_keypair` - Creator's signature keypair, matching the ciphersuite
+    /// * `ciphersuite` - Ciphersuite of the group (see [`parse_ciphersuite`])
+    ///
+    /// # Returns
+    /// New MlsGroupManager instance with initialized group
+    pub fn create_group_with_ciphersuite(
+        group_id: &str,
+        creator_credential: &MlsCredential,
+        signature_keypair: SignatureKeyPair,
+        ciphersuite: Ciphersuite,
+    ) -> Result<Self> {
+        if signature_keypair.signature_scheme() != ciphersuite.signature_algorithm() {
+            return Err(CryptoError::Protocol(format!(
+                "Signature key does not match ciphersuite {}",
+                ciphersuite
+            )));
+        }
+
+        creator_credential.verify(signature_keypair.public())?;
 
-        // Create credential (OpenMLS 0.7 API: credential_type first, then identity)
-        let credential = Credential::new(CredentialType::Basic, creator_identity.to_vec());
+        let crypto_backend = MlsProvider::new();
+        let group_id_bytes = GroupId::from_slice(group_id.as_bytes());
+
+        let credential = creator_credential.to_credential();
 
         // Create credential with key bundle
         let credential_with_key = CredentialWithKey {
@@ -70,21 +260,29 @@ impl MlsGroupManager {
             signature_key: signature_keypair.public().into(),
         };
 
-        // Configure MLS group
+        // Configure MLS group; handshake messages are sent as PublicMessages
+        // so the Delivery Service can validate them (see [`MlsPublicGroup`])
         let group_config = MlsGroupCreateConfig::builder()
-            .ciphersuite(MLS_CIPHERSUITE)
+            .ciphersuite(ciphersuite)
+            .wire_format_policy(PURE_PLAINTEXT_WIRE_FORMAT_POLICY)
             .use_ratchet_tree_extension(true)
             .build();
 
-        // Create MLS group (OpenMLS 0.7 API: provider, signer, group_config, group_id, credential)
-        let mls_group = MlsGroup::new(
+        // Create MLS group (OpenMLS 0.6 API: provider, signer, group_config, group_id, credential)
+        let mls_group = MlsGroup::new_with_group_id(
             &crypto_backend,
             &signature_keypair,
             &group_config,
+            group_id_bytes,
             credential_with_key.clone(),
         )
         .map_err(|e| CryptoError::Protocol(format!("Failed to create MLS group: {:?}", e)))?;
 
+        // Persist the signer alongside the group so it can be reloaded later
+        signature_keypair
+            .store(crypto_backend.storage())
+            .map_err(|e| CryptoError::Protocol(format!("Failed to store signature key: {:?}", e)))?;
+
         Ok(Self {
             mls_group,
             crypto_backend,
@@ -95,6 +293,8 @@ impl MlsGroupManager {
 
     /// Join an existing MLS group using a Welcome message
     ///
+    /// Fails if any member of the group lacks a valid Guardyn credential.
+    ///
     /// # Arguments
     /// * `welcome_bytes` - Serialized Welcome message from group admin
     /// * `signature_keypair` - Member's signature keypair for signing operations
@@ -107,7 +307,7 @@ impl MlsGroupManager {
         signature_keypair: SignatureKeyPair,
         key_package: KeyPackage,
     ) -> Result<Self> {
-        let crypto_backend = OpenMlsRustCrypto::default();
+        let crypto_backend = MlsProvider::new();
 
         // Get identity from key package credential
         let credential = key_package.leaf_node().credential();
@@ -130,7 +330,9 @@ impl MlsGroupManager {
         };
 
         // Configure MLS group (needed for joining)
-        let group_config = MlsGroupJoinConfig::default();
+        let group_config = MlsGroupJoinConfig::builder()
+            .wire_format_policy(PURE_PLAINTEXT_WIRE_FORMAT_POLICY)
+            .build();
 
         // Process Welcome and join group (OpenMLS 0.6 API)
         // For ratchet_tree, we need to pass None or extract it from Welcome
@@ -144,6 +346,13 @@ impl MlsGroupManager {
         .into_group(&crypto_backend)
         .map_err(|e| CryptoError::Protocol(format!("Failed to join group: {:?}", e)))?;
 
+        verify_members(&mls_group)?;
+
+        // Persist the signer alongside the group so it can be reloaded later
+        signature_keypair
+            .store(crypto_backend.storage())
+            .map_err(|e| CryptoError::Protocol(format!("Failed to store signature key: {:?}", e)))?;
+
         Ok(Self {
             mls_group,
             crypto_backend,
@@ -152,39 +361,226 @@ impl MlsGroupManager {
         })
     }
 
-    /// Generate a key package for this member
+    /// Join an existing MLS group with an external commit
+    ///
+    /// Lets a device join without being added by a member, e.g. a newly
+    /// linked device of a user who is already in the group. The returned
+    /// commit must be delivered to the group; until it is accepted the
+    /// device is not a member. Fails if any member of the group lacks a
+    /// valid Guardyn credential.
+    ///
+    /// # Arguments
+    /// * `group_info_bytes` - GroupInfo from [`MlsGroupManager::export_group_info`]
+    /// * `credential` - Joining member's credential, issued for the signature keypair
+    /// * `signature_keypair` - Joining member's signature keypair for signing operations
+    ///
+    /// # Returns
+    /// Tuple of (group manager, commit_bytes) - joined group and commit for the group
+    pub fn join_by_external_commit(
+        group_info_bytes: &[u8],
+        credential: &MlsCredential,
+        signature_keypair: SignatureKeyPair,
+    ) -> Result<(Self, Vec<u8>)> {
+        credential.verify(signature_keypair.public())?;
+
+        let crypto_backend = MlsProvider::new();
+
+        // Deserialize GroupInfo message (OpenMLS 0.6 uses tls_deserialize)
+        let mut reader = group_info_bytes;
+        let mls_message_in = MlsMessageIn::tls_deserialize(&mut reader)
+            .map_err(|e| CryptoError::Protocol(format!("Failed to deserialize GroupInfo: {:?}", e)))?;
+
+        let group_info = match mls_message_in.extract() {
+            MlsMessageBodyIn::GroupInfo(group_info) => group_info,
+            _ => return Err(CryptoError::Protocol("Expected GroupInfo message".to_string())),
+        };
+
+        if signature_keypair.signature_scheme() != group_info.ciphersuite().signature_algorithm() {
+            return Err(CryptoError::Protocol(format!(
+                "Signature key does not match ciphersuite {}",
+                group_info.ciphersuite()
+            )));
+        }
+
+        let credential_with_key = CredentialWithKey {
+            credential: credential.to_credential(),
+            signature_key: signature_keypair.public().into(),
+        };
+
+        let group_config = MlsGroupJoinConfig::builder()
+            .wire_format_policy(PURE_PLAINTEXT_WIRE_FORMAT_POLICY)
+            .use_ratchet_tree_extension(true)
+            .build();
+
+        // The ratchet tree comes from the GroupInfo's ratchet tree extension
+        let (mut mls_group, commit, _group_info) = MlsGroup::join_by_external_commit(
+            &crypto_backend,
+            &signature_keypair,
+            None,
+            group_info,
+            &group_config,
+            None,
+            None,
+            &[],
+            credential_with_key.clone(),
+        )
+        .map_err(|e| CryptoError::Protocol(format!("Failed to create external commit: {:?}", e)))?;
+
+        verify_members(&mls_group)?;
+
+        mls_group
+            .merge_pending_commit(&crypto_backend)
+            .map_err(|e| CryptoError::Protocol(format!("Failed to merge commit: {:?}", e)))?;
+
+        // Persist the signer alongside the group so it can be reloaded later
+        signature_keypair
+            .store(crypto_backend.storage())
+            .map_err(|e| CryptoError::Protocol(format!("Failed to store signature key: {:?}", e)))?;
+
+        let commit_bytes = commit
+            .tls_serialize_detached()
+            .map_err(|e| CryptoError::Protocol(format!("Failed to serialize commit: {:?}", e)))?;
+
+        Ok((
+            Self {
+                mls_group,
+                crypto_backend,
+                credential_with_key,
+                signature_keypair,
+            },
+            commit_bytes,
+        ))
+    }
+
+    /// Load an existing MLS group from persisted storage
+    ///
+    /// Restores the group at the epoch it had when the storage was last
+    /// flushed, together with the member's signature keypair.
+    ///
+    /// # Arguments
+    /// * `group_id` - Unique group identifier
+    /// * `storage` - OpenMLS storage entries previously persisted for this group
+    ///
+    /// # Returns
+    /// MlsGroupManager instance for the stored group
+    pub fn load(group_id: &str, storage: MlsStorage) -> Result<Self> {
+        let crypto_backend = MlsProvider::with_storage(storage);
+        let group_id_bytes = GroupId::from_slice(group_id.as_bytes());
+
+        let mls_group = MlsGroup::load(crypto_backend.storage(), &group_id_bytes)
+            .map_err(|e| CryptoError::Protocol(format!("Failed to load MLS group: {:?}", e)))?
+            .ok_or_else(|| CryptoError::Protocol(format!("MLS group not found: {}", group_id)))?;
+
+        let own_leaf = mls_group
+            .own_leaf_node()
+            .ok_or_else(|| CryptoError::Protocol("Own leaf node not found".to_string()))?;
+
+        let credential_with_key = CredentialWithKey {
+            credential: own_leaf.credential().clone(),
+            signature_key: own_leaf.signature_key().clone(),
+        };
+
+        let signature_keypair = SignatureKeyPair::read(
+            crypto_backend.storage(),
+            own_leaf.signature_key().as_slice(),
+            mls_group.ciphersuite().signature_algorithm(),
+        )
+        .ok_or_else(|| CryptoError::Protocol("Signature key not found in storage".to_string()))?;
+
+        Ok(Self {
+            mls_group,
+            crypto_backend,
+            credential_with_key,
+            signature_keypair,
+        })
+    }
+
+    /// Restore an MLS group from a state snapshot
+    ///
+    /// # Arguments
+    /// * `state` - Group state produced by [`MlsGroupManager::serialize_state`]
+    ///
+    /// # Returns
+    /// MlsGroupManager instance at the snapshot's epoch
+    pub fn from_state(state: &MlsGroupState) -> Result<Self> {
+        let storage = MlsStorage::from_bytes(&state.serialized_state)?;
+        let group_id = String::from_utf8(state.group_id.clone())
+            .map_err(|_| CryptoError::Protocol("Group ID is not valid UTF-8".to_string()))?;
+
+        Self::load(&group_id, storage)
+    }
+
+    /// Generate a key package for this member with the default ciphersuite
     ///
     /// Key packages are pre-generated and stored on the server.
     /// They are used by group admins to add members to groups.
     ///
     /// # Arguments
-    /// * `identity` - Member identity (user_id:device_id)
+    /// * `credential` - Member's credential, issued for the signature keypair
+    /// * `signature_keypair` - Member's signature keypair
     ///
     /// # Returns
     /// MlsKeyPackage with serialized key package and metadata
-    pub fn generate_key_package(identity: &[u8]) -> Result<MlsKeyPackage> {
-        let crypto_backend = OpenMlsRustCrypto::default();
+    pub fn generate_key_package(
+        credential: &MlsCredential,
+        signature_keypair: &SignatureKeyPair,
+    ) -> Result<MlsKeyPackage> {
+        Self::generate_key_package_with_ciphersuites(
+            credential,
+            signature_keypair,
+            DEFAULT_MLS_CIPHERSUITE,
+            &[],
+        )
+    }
 
-        // Create credential (OpenMLS 0.6: credential_type first)
-        let credential = Credential::new(CredentialType::Basic, identity.to_vec());
+    /// Generate a key package for a ciphersuite
+    ///
+    /// The key package's leaf node advertises `ciphersuite` together with
+    /// `additional_ciphersuites`, so group creators can tell which suites
+    /// the device supports.
+    ///
+    /// # Arguments
+    /// * `credential` - Member's credential, issued for the signature keypair
+    /// * `signature_keypair` - Member's signature keypair, matching the ciphersuite
+    /// * `ciphersuite` - Ciphersuite the key package is built for
+    /// * `additional_ciphersuites` - Further ciphersuites the device supports
+    ///
+    /// # Returns
+    /// MlsKeyPackage with serialized key package and metadata
+    pub fn generate_key_package_with_ciphersuites(
+        credential: &MlsCredential,
+        signature_keypair: &SignatureKeyPair,
+        ciphersuite: Ciphersuite,
+        additional_ciphersuites: &[Ciphersuite],
+    ) -> Result<MlsKeyPackage> {
+        if signature_keypair.signature_scheme() != ciphersuite.signature_algorithm() {
+            return Err(CryptoError::Protocol(format!(
+                "Signature key does not match ciphersuite {}",
+                ciphersuite
+            )));
+        }
+        credential.verify(signature_keypair.public())?;
 
-        // Generate signature keypair
-        let signature_keypair = SignatureKeyPair::new(MLS_CIPHERSUITE.signature_algorithm())
-            .map_err(|e| CryptoError::Protocol(format!("Failed to generate signature key: {:?}", e)))?;
+        let crypto_backend = OpenMlsRustCrypto::default();
 
         // Create credential with key bundle
         let credential_with_key = CredentialWithKey {
-            credential: credential.clone(),
+            credential: credential.to_credential(),
             signature_key: signature_keypair.public().into(),
         };
 
+        let mut ciphersuites = vec![ciphersuite];
+        ciphersuites.extend(additional_ciphersuites.iter().filter(|suite| **suite != ciphersuite));
+        let capabilities = Capabilities::builder().ciphersuites(ciphersuites).build();
+
         // Generate key package bundle (OpenMLS 0.6 returns KeyPackageBundle)
         let key_package_bundle = KeyPackage::builder()
+            .leaf_node_capabilities(capabilities)
             .build(
-                MLS_CIPHERSUITE,
+                ciphersuite,
                 &crypto_backend,
-                &signature_keypair,
-                credential_with_key.clone(),
+                signature_keypair,
+                credential_with_key,
             )
             .map_err(|e| CryptoError::Protocol(format!("Failed to build key package: {:?}", e)))?;
 
@@ -207,7 +603,8 @@ impl MlsGroupManager {
         Ok(MlsKeyPackage {
             package_id,
             key_package_bytes,
-            credential_identity: identity.to_vec(),
+            credential_identity: credential.identity().label().into_bytes(),
+            ciphersuite: ciphersuite.into(),
         })
     }
 
@@ -222,19 +619,19 @@ impl MlsGroupManager {
     /// # Returns
     /// Tuple of (commit_bytes, welcome_bytes) - commit for group, welcome for new member
     pub fn add_member(&mut self, member_key_package_bytes: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
-        // Deserialize key package (OpenMLS 0.6 - KeyPackageIn needs validation)
-        let mut reader = member_key_package_bytes.as_ref();
-        let key_package_in = KeyPackageIn::tls_deserialize(&mut reader)
-            .map_err(|e| {
-                CryptoError::Protocol(format!("Failed to deserialize key package: {:?}", e))
-            })?;
-
-        // Validate and convert KeyPackageIn to KeyPackage
-        let rust_crypto = RustCrypto::default();
-        let key_package = key_package_in.validate(&rust_crypto, ProtocolVersion::default())
-            .map_err(|e| {
-                CryptoError::Protocol(format!("Failed to validate key package: {:?}", e))
-            })?;
+        let key_package = validate_key_package(member_key_package_bytes)?;
+
+        // A member can only join with a key package built for the group's suite
+        if key_package.ciphersuite() != self.ciphersuite() {
+            return Err(CryptoError::Protocol(format!(
+                "Key package uses ciphersuite {}, group uses {}",
+                key_package.ciphersuite(),
+                self.ciphersuite()
+            )));
+        }
+
+        // Only leaves bound to a Guardyn identity may join
+        verify_leaf_node(key_package.leaf_node())?;
 
         // Propose adding the member
         let (commit, welcome, _group_info) = self
@@ -289,9 +686,90 @@ impl MlsGroupManager {
         Ok(commit_bytes)
     }
 
+    /// Rotate own leaf key material with a self-update commit
+    ///
+    /// Generates a fresh leaf encryption key and path secrets, so a member
+    /// whose earlier key material leaked regains confidentiality
+    /// (post-compromise security). Pending proposals are committed as well.
+    ///
+    /// # Returns
+    /// Tuple of (commit_bytes, welcome_bytes) - welcome is present only if a
+    /// pending proposal added members
+    pub fn self_update(&mut self) -> Result<(Vec<u8>, Option<Vec<u8>>)> {
+        let (commit, welcome, _group_info) = self
+            .mls_group
+            .self_update(&self.crypto_backend, &self.signature_keypair, LeafNodeParameters::default())
+            .map_err(|e| CryptoError::Protocol(format!("Failed to create self-update: {:?}", e)))?;
+
+        self.merge_own_commit(commit, welcome)
+    }
+
+    /// Propose rotating own leaf key material
+    ///
+    /// The proposal is queued locally and must be distributed to the group;
+    /// any member can then commit it by reference.
+    ///
+    /// # Returns
+    /// Tuple of (proposal_bytes, proposal_ref) - message for the group and its reference
+    pub fn propose_self_update(&mut self) -> Result<(Vec<u8>, Vec<u8>)> {
+        let (proposal, proposal_ref) = self
+            .mls_group
+            .propose_self_update(&self.crypto_backend, &self.signature_keypair, LeafNodeParameters::default())
+            .map_err(|e| CryptoError::Protocol(format!("Failed to propose self-update: {:?}", e)))?;
+
+        let proposal_bytes = proposal
+            .tls_serialize_detached()
+            .map_err(|e| CryptoError::Protocol(format!("Failed to serialize proposal: {:?}", e)))?;
+
+        Ok((proposal_bytes, proposal_ref.as_slice().to_vec()))
+    }
+
+    /// Process an incoming proposal from another member
+    ///
+    /// The proposal is stored until a commit references it.
+    ///
+    /// # Arguments
+    /// * `proposal_bytes` - Serialized proposal message
+    ///
+    /// # Returns
+    /// Ok(()) if the proposal was stored
+    pub fn process_proposal(&mut self, proposal_bytes: &[u8]) -> Result<()> {
+        match self.process_handshake(proposal_bytes)?.into_content() {
+            ProcessedMessageContent::ProposalMessage(proposal) => {
+                verify_proposal(proposal.proposal())?;
+                self.mls_group
+                    .store_pending_proposal(self.crypto_backend.storage(), *proposal)
+                    .map_err(|e| CryptoError::Protocol(format!("Failed to store proposal: {:?}", e)))?;
+                Ok(())
+            }
+            _ => Err(CryptoError::Protocol("Expected proposal message".to_string())),
+        }
+    }
+
+    /// Commit all pending proposals, including ones received by reference
+    ///
+    /// # Returns
+    /// Tuple of (commit_bytes, welcome_bytes) - welcome is present only if a
+    /// pending proposal added members
+    pub fn commit_pending_proposals(&mut self) -> Result<(Vec<u8>, Option<Vec<u8>>)> {
+        let (commit, welcome, _group_info) = self
+            .mls_group
+            .commit_to_pending_proposals(&self.crypto_backend, &self.signature_keypair)
+            .map_err(|e| CryptoError::Protocol(format!("Failed to commit proposals: {:?}", e)))?;
+
+        self.merge_own_commit(commit, welcome)
+    }
+
+    /// Number of proposals waiting to be committed
+    pub fn pending_proposal_count(&self) -> usize {
+        self.mls_group.pending_proposals().count()
+    }
+
     /// Process incoming commit message from another member
     ///
     /// Updates group state based on membership changes or epoch advancement.
+    /// Proposals the commit includes by reference must have been processed
+    /// with [`MlsGroupManager::process_proposal`] first.
     ///
     /// # Arguments
     /// * `commit_bytes` - Serialized commit message
@@ -299,21 +777,125 @@ impl MlsGroupManager {
     /// # Returns
     /// Ok(()) if commit processed successfully
     pub fn process_commit(&mut self, commit_bytes: &[u8]) -> Result<()> {
-        // Deserialize commit (OpenMLS 0.6)
-        let mut reader = commit_bytes.as_ref();
-        let commit = MlsMessageIn::tls_deserialize(&mut reader)
-            .map_err(|e| CryptoError::Protocol(format!("Failed to deserialize commit: {:?}", e)))?;
+        match self.process_handshake(commit_bytes)?.into_content() {
+            ProcessedMessageContent::StagedCommitMessage(staged_commit) => {
+                verify_staged_commit(&staged_commit)?;
+                self.mls_group
+                    .merge_staged_commit(&self.crypto_backend, *staged_commit)
+                    .map_err(|e| CryptoError::Protocol(format!("Failed to merge commit: {:?}", e)))?;
+                Ok(())
+            }
+            _ => Err(CryptoError::Protocol("Expected commit message".to_string())),
+        }
+    }
+
+    /// Process an external commit of a device joining the group
+    ///
+    /// # Arguments
+    /// * `commit_bytes` - Serialized commit created by
+    ///   [`MlsGroupManager::join_by_external_commit`]
+    ///
+    /// # Returns
+    /// Verified identity of the joining member
+    pub fn process_external_commit(&mut self, commit_bytes: &[u8]) -> Result<MlsMemberIdentity> {
+        let processed = self.process_handshake(commit_bytes)?;
+        if !matches!(processed.sender(), Sender::NewMemberCommit) {
+            return Err(CryptoError::Protocol("Expected external commit".to_string()));
+        }
+
+        match processed.into_content() {
+            ProcessedMessageContent::StagedCommitMessage(staged_commit) => {
+                let identity = verify_staged_commit(&staged_commit)?
+                    .ok_or_else(|| CryptoError::Protocol("External commit without joiner leaf".to_string()))?;
+
+                self.mls_group
+                    .merge_staged_commit(&self.crypto_backend, *staged_commit)
+                    .map_err(|e| CryptoError::Protocol(format!("Failed to merge commit: {:?}", e)))?;
+                Ok(identity)
+            }
+            _ => Err(CryptoError::Protocol("Expected commit message".to_string())),
+        }
+    }
+
+    /// Process a self-update commit of another member
+    ///
+    /// Accepts only commits of an existing member that rotate its own leaf
+    /// (with update proposals at most), so a device can't sneak membership
+    /// changes in through a key rotation.
+    ///
+    /// # Arguments
+    /// * `commit_bytes` - Serialized commit created by
+    ///   [`MlsGroupManager::self_update`]
+    ///
+    /// # Returns
+    /// Verified identity of the member that rotated its leaf
+    pub fn process_self_update(&mut self, commit_bytes: &[u8]) -> Result<MlsMemberIdentity> {
+        let processed = self.process_handshake(commit_bytes)?;
+        if !matches!(processed.sender(), Sender::Member(_)) {
+            return Err(CryptoError::Protocol("Expected commit of a group member".to_string()));
+        }
+
+        match processed.into_content() {
+            ProcessedMessageContent::StagedCommitMessage(staged_commit) => {
+                if !staged_commit
+                    .queued_proposals()
+                    .all(|queued| matches!(queued.proposal(), Proposal::Update(_)))
+                {
+                    return Err(CryptoError::Protocol("Self-update commit changes the group".to_string()));
+                }
+
+                let identity = verify_staged_commit(&staged_commit)?
+                    .ok_or_else(|| CryptoError::Protocol("Self-update commit without update path".to_string()))?;
+
+                self.mls_group
+                    .merge_staged_commit(&self.crypto_backend, *staged_commit)
+                    .map_err(|e| CryptoError::Protocol(format!("Failed to merge commit: {:?}", e)))?;
+                Ok(identity)
+            }
+            _ => Err(CryptoError::Protocol("Expected commit message".to_string())),
+        }
+    }
+
+    /// Deserialize and process a handshake message from another member
+    fn process_handshake(&mut self, message_bytes: &[u8]) -> Result<ProcessedMessage> {
+        // Deserialize message (OpenMLS 0.6)
+        let mut reader = message_bytes;
+        let message = MlsMessageIn::tls_deserialize(&mut reader)
+            .map_err(|e| CryptoError::Protocol(format!("Failed to deserialize handshake: {:?}", e)))?;
 
         // Convert MlsMessageIn to ProtocolMessage for processing
-        let protocol_message: ProtocolMessage = commit.try_into()
+        let protocol_message: ProtocolMessage = message.try_into()
             .map_err(|e| CryptoError::Protocol(format!("Failed to convert message: {:?}", e)))?;
 
-        // Process commit (OpenMLS 0.6 API - pass message by reference)
-        let _processed = self.mls_group
+        // Process message (OpenMLS 0.6 API - provider first)
+        let processed = self.mls_group
             .process_message(&self.crypto_backend, protocol_message)
-            .map_err(|e| CryptoError::Protocol(format!("Failed to process commit: {:?}", e)))?;
+            .map_err(|e| CryptoError::Protocol(format!("Failed to process handshake: {:?}", e)))?;
 
-        Ok(())
+        Ok(processed)
+    }
+
+    /// Merge an own pending commit and serialize it with its optional welcome
+    fn merge_own_commit(
+        &mut self,
+        commit: MlsMessageOut,
+        welcome: Option<MlsMessageOut>,
+    ) -> Result<(Vec<u8>, Option<Vec<u8>>)> {
+        // Merge commit (apply changes to local state)
+        self.mls_group
+            .merge_pending_commit(&self.crypto_backend)
+            .map_err(|e| CryptoError::Protocol(format!("Failed to merge commit: {:?}", e)))?;
+
+        let commit_bytes = commit
+            .tls_serialize_detached()
+            .map_err(|e| CryptoError::Protocol(format!("Failed to serialize commit: {:?}", e)))?;
+
+        let welcome_bytes = welcome
+            .map(|welcome| welcome.tls_serialize_detached())
+            .transpose()
+            .map_err(|e| CryptoError::Protocol(format!("Failed to serialize welcome: {:?}", e)))?;
+
+        Ok((commit_bytes, welcome_bytes))
     }
 
     /// Encrypt a group message
@@ -365,12 +947,13 @@ impl MlsGroupManager {
 
         // Extract plaintext from ProcessedMessage (OpenMLS 0.6)
         // We need to consume the ProcessedMessage to get the bytes
+        let aad = processed_message.aad().to_vec();
         let content = processed_message.into_content();
         match content {
             ProcessedMessageContent::ApplicationMessage(app_msg) => {
                 // Now we own app_msg and can call into_bytes()
                 let bytes = app_msg.into_bytes();
-                Ok((bytes, vec![]))
+                Ok((bytes, aad))
             }
             ProcessedMessageContent::ProposalMessage(_) => {
                 let err_msg = String::from("Unexpected") + " " + "proposal";
@@ -386,104 +969,466 @@ impl MlsGroupManager {
         }
     }
 
-    /// Serialize group state for storage
+    /// Encrypt a franked group message (see [`crate::franking`])
+    ///
+    /// The commitment is carried as the message's MLS authenticated data.
     ///
     /// # Returns
-    /// MlsGroupState with serialized group data
-    pub fn serialize_state(&self) -> Result<MlsGroupState> {
-        let serialized_state = self
+    /// Tuple of (serialized encrypted MLS message, commitment to send to the server)
+    pub fn encrypt_franked_message(&mut self, plaintext: &[u8]) -> Result<(Vec<u8>, FrankingCommitment)> {
+        let (payload, commitment) = franking::frank(plaintext)?;
+        self.mls_group.set_aad(commitment.to_vec());
+        let ciphertext = self.encrypt_message(&payload);
+        // Never leak the commitment into a later message if encryption failed
+        self.mls_group.set_aad(Vec::new());
+        Ok((ciphertext?, commitment))
+    }
+
+    /// Decrypt a franked group message
+    ///
+    /// `commitment` is taken from the server's franking tag and must match
+    /// the message's authenticated data and content.
+    pub fn decrypt_franked_message(
+        &mut self,
+        ciphertext: &[u8],
+        commitment: &FrankingCommitment,
+    ) -> Result<FrankedPlaintext> {
+        let (payload, aad) = self.decrypt_message(ciphertext)?;
+        let payload = Zeroizing::new(payload);
+        if !ct_eq(&aad, commitment) {
+            return Err(CryptoError::InvalidSignature(
+                "Message is not bound to the franking commitment".to_string(),
+            ));
+        }
+        franking::open(&payload, commitment)
+    }
+
+    /// Export the group's signed GroupInfo, including the ratchet tree
+    ///
+    /// Devices outside the group use it to join with
+    /// [`MlsGroupManager::join_by_external_commit`]. It is only valid for
+    /// the current epoch.
+    pub fn export_group_info(&self) -> Result<Vec<u8>> {
+        let group_info = self
             .mls_group
-            .export_secret(&self.crypto_backend, "group_state", &[], 32)
-            .map_err(|e| CryptoError::Protocol(format!("Failed to export group state: {:?}", e)))?;
+            .export_group_info(&self.crypto_backend, &self.signature_keypair, true)
+            .map_err(|e| CryptoError::Protocol(format!("Failed to export GroupInfo: {:?}", e)))?;
 
+        group_info
+            .tls_serialize_detached()
+            .map_err(|e| CryptoError::Protocol(format!("Failed to serialize GroupInfo: {:?}", e)))
+    }
+
+    /// Serialize group state for storage
+    ///
+    /// # Returns
+    /// MlsGroupState with a snapshot of the group's OpenMLS storage
+    pub fn serialize_state(&self) -> Result<MlsGroupState> {
         Ok(MlsGroupState {
             group_id: self.mls_group.group_id().as_slice().to_vec(),
             epoch: self.mls_group.epoch().as_u64(),
-            serialized_state,
+            serialized_state: self.crypto_backend.mls_storage().to_bytes(),
         })
     }
 
+    /// Collect storage changes made since the last call
+    ///
+    /// The returned changes must be written to the persistent store so the
+    /// group can later be restored with [`MlsGroupManager::load`].
+    pub fn take_storage_changes(&self) -> MlsStorageChanges {
+        self.crypto_backend.mls_storage().take_changes()
+    }
+
+    /// Access the group's OpenMLS storage
+    pub fn storage(&self) -> &MlsStorage {
+        self.crypto_backend.mls_storage()
+    }
+
     /// Get current epoch number
     pub fn epoch(&self) -> u64 {
         self.mls_group.epoch().as_u64()
     }
 
+    /// Get the group's ciphersuite
+    pub fn ciphersuite(&self) -> Ciphersuite {
+        self.mls_group.ciphersuite()
+    }
+
     /// Get group ID
     pub fn group_id(&self) -> Vec<u8> {
         self.mls_group.group_id().as_slice().to_vec()
     }
 
-    /// Get list of member identities in the group
-    pub fn members(&self) -> Vec<Vec<u8>> {
-        self.mls_group
+    /// Get verified identities of the group members, in leaf order
+    pub fn members(&self) -> Result<Vec<MlsMemberIdentity>> {
+        verify_members(&self.mls_group)
+    }
+}
+
+/// Deserialize a GroupInfo message and take the ratchet tree from its extension
+fn parse_group_info(group_info_bytes: &[u8]) -> Result<(VerifiableGroupInfo, RatchetTreeIn)> {
+    let mut reader = group_info_bytes;
+    let mls_message_in = MlsMessageIn::tls_deserialize(&mut reader)
+        .map_err(|e| CryptoError::Protocol(format!("Failed to deserialize GroupInfo: {:?}", e)))?;
+
+    let group_info = match mls_message_in.extract() {
+        MlsMessageBodyIn::GroupInfo(group_info) => group_info,
+        _ => return Err(CryptoError::Protocol("Expected GroupInfo message".to_string())),
+    };
+
+    let ratchet_tree = group_info
+        .extensions()
+        .ratchet_tree()
+        .map(|extension| extension.ratchet_tree().clone())
+        .ok_or_else(|| CryptoError::Protocol("GroupInfo does not include the ratchet tree".to_string()))?;
+
+    Ok((group_info, ratchet_tree))
+}
+
+/// Membership changes of a commit validated by [`MlsPublicGroup::process_commit`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MlsCommitSummary {
+    /// Epoch the commit was created in
+    pub epoch: u64,
+    /// Verified identity of the committing member, or of the device joining
+    /// by external commit
+    pub committer: MlsMemberIdentity,
+    /// True for an external commit of a device joining the group
+    pub external: bool,
+    /// True if the commit rotates the committer's leaf (has an update path)
+    pub has_update_path: bool,
+    /// Members the commit adds
+    pub added: Vec<MlsMemberIdentity>,
+    /// Members the commit removes
+    pub removed: Vec<MlsMemberIdentity>,
+    /// Members whose update proposals the commit includes
+    pub updated: Vec<MlsMemberIdentity>,
+    /// Number of other proposals (external init, PSKs, extensions, ...)
+    pub other_proposals: usize,
+}
+
+impl MlsCommitSummary {
+    /// Returns true if the commit of an existing member only rotates its own
+    /// leaf (with update proposals at most), so a device can't sneak
+    /// membership changes in through a key rotation
+    pub fn is_self_update(&self) -> bool {
+        !self.external
+            && self.has_update_path
+            && self.added.is_empty()
+            && self.removed.is_empty()
+            && self.other_proposals == 0
+    }
+}
+
+/// Public view of an MLS group, as kept by the Delivery Service
+///
+/// Holds the ratchet tree and group context of the current epoch but no
+/// secrets and no signature key, so the server can validate and order
+/// handshake messages created by member devices without being able to
+/// create or decrypt any itself. Member devices must send handshake
+/// messages as PublicMessages, which [`MlsGroupManager`] does.
+pub struct MlsPublicGroup {
+    public_group: PublicGroup,
+    crypto_backend: MlsProvider,
+}
+
+impl MlsPublicGroup {
+    /// Start tracking a group from a GroupInfo created by a member
+    ///
+    /// Verifies the GroupInfo's signature against the included ratchet tree
+    /// and the Guardyn credentials of all members.
+    ///
+    /// # Arguments
+    /// * `group_info_bytes` - GroupInfo from [`MlsGroupManager::export_group_info`]
+    pub fn from_group_info(group_info_bytes: &[u8]) -> Result<Self> {
+        Self::from_group_info_with_storage(group_info_bytes, MlsStorage::new())
+    }
+
+    fn from_group_info_with_storage(group_info_bytes: &[u8], storage: MlsStorage) -> Result<Self> {
+        let (group_info, ratchet_tree) = parse_group_info(group_info_bytes)?;
+        let crypto_backend = MlsProvider::with_storage(storage);
+
+        let (public_group, _group_info) = PublicGroup::from_external(
+            crypto_backend.crypto(),
+            crypto_backend.storage(),
+            ratchet_tree,
+            group_info,
+            ProposalStore::new(),
+        )
+        .map_err(|e| CryptoError::Protocol(format!("Invalid GroupInfo: {:?}", e)))?;
+
+        let group = Self {
+            public_group,
+            crypto_backend,
+        };
+        group.members()?;
+
+        Ok(group)
+    }
+
+    /// Load a group from persisted storage
+    ///
+    /// Storage that still holds a member's view of the group (created when
+    /// the server kept its own leaf) is converted: the signature key and all
+    /// secrets are dropped and only the public state is kept, so the next
+    /// flush deletes them from the persistent store.
+    ///
+    /// # Arguments
+    /// * `group_id` - Unique group identifier
+    /// * `storage` - OpenMLS storage entries previously persisted for this group
+    pub fn load(group_id: &str, storage: MlsStorage) -> Result<Self> {
+        let group_id_bytes = GroupId::from_slice(group_id.as_bytes());
+
+        let member_group = MlsGroup::load(storage.provider(), &group_id_bytes)
+            .map_err(|e| CryptoError::Protocol(format!("Failed to load MLS group: {:?}", e)))?;
+        if member_group.is_some() {
+            let manager = MlsGroupManager::load(group_id, storage)?;
+            let group_info = manager.export_group_info()?;
+
+            let storage = manager.crypto_backend.into_storage();
+            storage.clear();
+            return Self::from_group_info_with_storage(&group_info, storage);
+        }
+
+        let crypto_backend = MlsProvider::with_storage(storage);
+        let public_group = PublicGroup::load(crypto_backend.storage(), &group_id_bytes)
+            .map_err(|e| CryptoError::Protocol(format!("Failed to load MLS group: {:?}", e)))?
+            .ok_or_else(|| CryptoError::Protocol(format!("MLS group not found: {}", group_id)))?;
+
+        Ok(Self {
+            public_group,
+            crypto_backend,
+        })
+    }
+
+    /// Validate a commit created by a member device and apply it
+    ///
+    /// Checks the commit's signature, its proposals and update path against
+    /// the current epoch and the Guardyn credentials of all leaves it adds or
+    /// changes. The caller decides whether the summarized changes are allowed
+    /// for the sender and must discard the group if they are not.
+    ///
+    /// # Arguments
+    /// * `commit_bytes` - Serialized commit, sent as a PublicMessage
+    ///
+    /// # Returns
+    /// Summary of the membership changes the commit made
+    pub fn process_commit(&mut self, commit_bytes: &[u8]) -> Result<MlsCommitSummary> {
+        let mut reader = commit_bytes;
+        let message = MlsMessageIn::tls_deserialize(&mut reader)
+            .map_err(|e| CryptoError::Protocol(format!("Failed to deserialize commit: {:?}", e)))?;
+
+        let protocol_message: ProtocolMessage = message.try_into()
+            .map_err(|e| CryptoError::Protocol(format!("Failed to convert message: {:?}", e)))?;
+
+        let processed = self
+            .public_group
+            .process_message(self.crypto_backend.crypto(), protocol_message)
+            .map_err(|e| CryptoError::Protocol(format!("Failed to process commit: {:?}", e)))?;
+
+        let epoch = processed.epoch().as_u64();
+        let sender = processed.sender().clone();
+        let staged_commit = match processed.into_content() {
+            ProcessedMessageContent::StagedCommitMessage(staged_commit) => staged_commit,
+            _ => return Err(CryptoError::Protocol("Expected commit message".to_string())),
+        };
+
+        let path_identity = verify_staged_commit(&staged_commit)?;
+        let (committer, external) = match sender {
+            Sender::Member(leaf_index) => {
+                let committer = self.member_identity(leaf_index)?;
+                // A member may rotate its leaf, but not take another identity
+                if path_identity.as_ref().is_some_and(|identity| *identity != committer) {
+                    return Err(CryptoError::Protocol(
+                        "Commit changes the committer's identity".to_string(),
+                    ));
+                }
+                (committer, false)
+            }
+            Sender::NewMemberCommit => {
+                let joiner = path_identity.clone()
+                    .ok_or_else(|| CryptoError::Protocol("External commit without joiner leaf".to_string()))?;
+                (joiner, true)
+            }
+            _ => return Err(CryptoError::Protocol("Expected commit of a group member".to_string())),
+        };
+
+        let added = staged_commit
+            .add_proposals()
+            .map(|add| verify_leaf_node(add.add_proposal().key_package().leaf_node()))
+            .collect::<Result<Vec<_>>>()?;
+        let removed = staged_commit
+            .remove_proposals()
+            .map(|remove| self.member_identity(remove.remove_proposal().removed()))
+            .collect::<Result<Vec<_>>>()?;
+        let updated = staged_commit
+            .update_proposals()
+            .map(|update| verify_leaf_node(update.update_proposal().leaf_node()))
+            .collect::<Result<Vec<_>>>()?;
+        let other_proposals = staged_commit.queued_proposals().count()
+            - added.len()
+            - removed.len()
+            - updated.len();
+
+        let summary = MlsCommitSummary {
+            epoch,
+            committer,
+            external,
+            has_update_path: path_identity.is_some(),
+            added,
+            removed,
+            updated,
+            other_proposals,
+        };
+
+        self.public_group
+            .merge_commit(self.crypto_backend.storage(), *staged_commit)
+            .map_err(|e| CryptoError::Protocol(format!("Failed to merge commit: {:?}", e)))?;
+
+        Ok(summary)
+    }
+
+    /// Check that a GroupInfo created by a member matches the current epoch
+    ///
+    /// Only a GroupInfo signed by a member over the group's current context,
+    /// ratchet tree and confirmation tag passes, so it can be served to
+    /// devices joining by external commit.
+    ///
+    /// # Arguments
+    /// * `group_info_bytes` - GroupInfo from [`MlsGroupManager::export_group_info`]
+    pub fn verify_group_info(&self, group_info_bytes: &[u8]) -> Result<()> {
+        let (group_info, ratchet_tree) = parse_group_info(group_info_bytes)?;
+        let crypto_backend = MlsProvider::new();
+
+        let (public_group, _group_info) = PublicGroup::from_external(
+            crypto_backend.crypto(),
+            crypto_backend.storage(),
+            ratchet_tree,
+            group_info,
+            ProposalStore::new(),
+        )
+        .map_err(|e| CryptoError::Protocol(format!("Invalid GroupInfo: {:?}", e)))?;
+
+        if public_group.group_context() != self.public_group.group_context()
+            || public_group.confirmation_tag() != self.public_group.confirmation_tag()
+        {
+            return Err(CryptoError::Protocol(format!(
+                "GroupInfo does not match epoch {} of the group",
+                self.epoch()
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Verified identity of the member at a leaf
+    fn member_identity(&self, leaf_index: LeafNodeIndex) -> Result<MlsMemberIdentity> {
+        let leaf = self
+            .public_group
+            .leaf(leaf_index)
+            .ok_or_else(|| CryptoError::Protocol(format!("No member at leaf {}", leaf_index.u32())))?;
+        verify_leaf_node(leaf)
+    }
+
+    /// Collect storage changes made since the last call
+    ///
+    /// The returned changes must be written to the persistent store so the
+    /// group can later be restored with [`MlsPublicGroup::load`].
+    pub fn take_storage_changes(&self) -> MlsStorageChanges {
+        self.crypto_backend.mls_storage().take_changes()
+    }
+
+    /// Access the group's OpenMLS storage
+    pub fn storage(&self) -> &MlsStorage {
+        self.crypto_backend.mls_storage()
+    }
+
+    /// Get current epoch number
+    pub fn epoch(&self) -> u64 {
+        self.public_group.group_context().epoch().as_u64()
+    }
+
+    /// Get the group's ciphersuite
+    pub fn ciphersuite(&self) -> Ciphersuite {
+        self.public_group.ciphersuite()
+    }
+
+    /// Get group ID
+    pub fn group_id(&self) -> Vec<u8> {
+        self.public_group.group_id().as_slice().to_vec()
+    }
+
+    /// Get verified identities of the group members, in leaf order
+    pub fn members(&self) -> Result<Vec<MlsMemberIdentity>> {
+        self.public_group
             .members()
-            .map(|member| {
-                // In OpenMLS 0.6, credentials are accessed via serialization
-                // For Basic credentials, identity is the serialized form
-                member.credential.serialized_content().to_vec()
-            })
+            .map(|member| MlsCredential::verify_leaf(&member.credential, &member.signature_key))
             .collect()
     }
 }
 
-/// Create a test credential (helper for testing and initial development)
-///
-/// **SECURITY WARNING**: This is for testing only. In production, credentials
 /// Test helper to create a signature keypair for testing
-///
-/// ⚠️ WARNING: This is for testing only! In production, signature keypairs
-/// should be generated securely and stored with proper key management.
-pub fn create_test_keypair() -> Result<SignatureKeyPair> {
-    let signature_keypair = SignatureKeyPair::new(MLS_CIPHERSUITE.signature_algorithm())
-        .map_err(|e| CryptoError::Protocol(format!("Failed to generate signature key: {:?}", e)))?;
-    Ok(signature_keypair)
+#[cfg(test)]
+pub(crate) fn create_test_keypair() -> Result<SignatureKeyPair> {
+    generate_signature_keypair(DEFAULT_MLS_CIPHERSUITE)
+}
+
+/// Test helper to create a member credential with a fresh identity key
+#[cfg(test)]
+pub(crate) fn create_test_member(user_id: &str, device_id: &str) -> Result<(MlsCredential, SignatureKeyPair)> {
+    let identity_key = crate::x3dh::IdentityKeyPair::generate()?;
+    MlsCredential::generate(user_id, device_id, &identity_key, DEFAULT_MLS_CIPHERSUITE)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    fn create_test_keypair() -> Result<SignatureKeyPair> {
-        let signature_keypair = SignatureKeyPair::new(MLS_CIPHERSUITE.signature_algorithm())
-            .map_err(|e| CryptoError::Protocol(format!("Failed to generate signature key: {:?}", e)))?;
-        Ok(signature_keypair)
-    }
-
     #[test]
     fn test_mls_group_creation() {
-        let alice_keypair = create_test_keypair().unwrap();
-        let alice_id = b"alice_device1";
-        let group = MlsGroupManager::create_group("test_group", alice_id, alice_keypair);
+        let (alice_credential, alice_keypair) = create_test_member("alice", "device1").unwrap();
+        let group = MlsGroupManager::create_group("test_group", &alice_credential, alice_keypair);
         assert!(group.is_ok());
 
         let group = group.unwrap();
         assert_eq!(group.epoch(), 0);
-        assert_eq!(group.members().len(), 1);
+        assert_eq!(group.members().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_create_group_rejects_credential_for_other_key() {
+        let (alice_credential, _alice_keypair) = create_test_member("alice", "device1").unwrap();
+        let other_keypair = create_test_keypair().unwrap();
+
+        assert!(MlsGroupManager::create_group("test_group", &alice_credential, other_keypair).is_err());
     }
 
     #[test]
     fn test_key_package_generation() {
-        let bob_id = b"bob_device1";
-        let key_package = MlsGroupManager::generate_key_package(bob_id);
+        let (bob_credential, bob_keypair) = create_test_member("bob", "device1").unwrap();
+        let key_package = MlsGroupManager::generate_key_package(&bob_credential, &bob_keypair);
         assert!(key_package.is_ok());
 
         let key_package = key_package.unwrap();
         assert!(!key_package.package_id.is_empty());
         assert!(!key_package.key_package_bytes.is_empty());
-        assert_eq!(key_package.credential_identity, bob_id);
+        assert_eq!(key_package.credential_identity, b"bob:device1".to_vec());
+
+        let identity = key_package_identity(&key_package.key_package_bytes).unwrap();
+        assert_eq!(&identity, bob_credential.identity());
     }
 
     #[test]
     fn test_add_member_to_group() {
         // Create group with Alice
-        let alice_keypair = create_test_keypair().unwrap();
-        let alice_id = b"alice_device1";
+        let (alice_credential, alice_keypair) = create_test_member("alice", "device1").unwrap();
         let mut alice_group =
-            MlsGroupManager::create_group("test_group", alice_id, alice_keypair).unwrap();
+            MlsGroupManager::create_group("test_group", &alice_credential, alice_keypair).unwrap();
 
         // Generate key package for Bob
-        let bob_id = b"bob_device1";
-        let bob_key_package = MlsGroupManager::generate_key_package(bob_id).unwrap();
+        let (bob_credential, bob_keypair) = create_test_member("bob", "device1").unwrap();
+        let bob_key_package =
+            MlsGroupManager::generate_key_package(&bob_credential, &bob_keypair).unwrap();
 
         // Alice adds Bob
         let result = alice_group.add_member(&bob_key_package.key_package_bytes);
@@ -493,16 +1438,49 @@ mod tests {
         assert!(!commit.is_empty());
         assert!(!welcome.is_empty());
         assert_eq!(alice_group.epoch(), 1); // Epoch advanced
-        assert_eq!(alice_group.members().len(), 2); // Alice + Bob
+
+        // Alice + Bob, with their parsed identities
+        let members = alice_group.members().unwrap();
+        assert_eq!(members.len(), 2);
+        assert_eq!(&members[0], alice_credential.identity());
+        assert_eq!(&members[1], bob_credential.identity());
+    }
+
+    #[test]
+    fn test_add_member_rejects_unbound_credential() {
+        let (alice_credential, alice_keypair) = create_test_member("alice", "device1").unwrap();
+        let mut alice_group =
+            MlsGroupManager::create_group("test_group", &alice_credential, alice_keypair).unwrap();
+
+        // Key package whose basic credential is a bare "user_id:device_id"
+        let bob_keypair = create_test_keypair().unwrap();
+        let key_package_bundle = KeyPackage::builder()
+            .build(
+                DEFAULT_MLS_CIPHERSUITE,
+                &OpenMlsRustCrypto::default(),
+                &bob_keypair,
+                CredentialWithKey {
+                    credential: BasicCredential::new(b"bob:device1".to_vec()).into(),
+                    signature_key: bob_keypair.public().into(),
+                },
+            )
+            .unwrap();
+        let key_package_bytes = key_package_bundle
+            .key_package()
+            .tls_serialize_detached()
+            .unwrap();
+
+        assert!(key_package_identity(&key_package_bytes).is_err());
+        assert!(alice_group.add_member(&key_package_bytes).is_err());
+        assert_eq!(alice_group.epoch(), 0);
     }
 
     #[test]
     fn test_encrypt_decrypt_message() {
         // Create group with Alice
-        let alice_keypair = create_test_keypair().unwrap();
-        let alice_id = b"alice_device1";
+        let (alice_credential, alice_keypair) = create_test_member("alice", "device1").unwrap();
         let mut alice_group =
-            MlsGroupManager::create_group("test_group", alice_id, alice_keypair)
+            MlsGroupManager::create_group("test_group", &alice_credential, alice_keypair)
                 .unwrap();
 
         // Encrypt message
@@ -519,12 +1497,231 @@ mod tests {
         assert_eq!(decrypted, plaintext.to_vec());
     }
 
+    #[test]
+    fn test_self_update_rotates_leaf_key() {
+        let (alice_credential, alice_keypair) = create_test_member("alice", "device1").unwrap();
+        let mut alice_group =
+            MlsGroupManager::create_group("test_group", &alice_credential, alice_keypair).unwrap();
+        let old_leaf_key = alice_group.mls_group.own_leaf_node().unwrap().encryption_key().clone();
+
+        let (commit, welcome) = alice_group.self_update().unwrap();
+        assert!(!commit.is_empty());
+        assert!(welcome.is_none());
+        assert_eq!(alice_group.epoch(), 1);
+
+        let new_leaf_key = alice_group.mls_group.own_leaf_node().unwrap().encryption_key().clone();
+        assert_ne!(old_leaf_key, new_leaf_key);
+    }
+
+    #[test]
+    fn test_commit_self_update_proposal() {
+        let (alice_credential, alice_keypair) = create_test_member("alice", "device1").unwrap();
+        let mut alice_group =
+            MlsGroupManager::create_group("test_group", &alice_credential, alice_keypair).unwrap();
+        let old_leaf_key = alice_group.mls_group.own_leaf_node().unwrap().encryption_key().clone();
+
+        let (proposal, proposal_ref) = alice_group.propose_self_update().unwrap();
+        assert!(!proposal.is_empty());
+        assert!(!proposal_ref.is_empty());
+        assert_eq!(alice_group.pending_proposal_count(), 1);
+        assert_eq!(alice_group.epoch(), 0);
+
+        let (commit, welcome) = alice_group.commit_pending_proposals().unwrap();
+        assert!(!commit.is_empty());
+        assert!(welcome.is_none());
+        assert_eq!(alice_group.pending_proposal_count(), 0);
+        assert_eq!(alice_group.epoch(), 1);
+
+        let new_leaf_key = alice_group.mls_group.own_leaf_node().unwrap().encryption_key().clone();
+        assert_ne!(old_leaf_key, new_leaf_key);
+    }
+
+    #[test]
+    fn test_parse_ciphersuite() {
+        assert_eq!(
+            parse_ciphersuite("MLS_128_DHKEMX25519_CHACHA20POLY1305_SHA256_Ed25519").unwrap(),
+            Ciphersuite::MLS_128_DHKEMX25519_CHACHA20POLY1305_SHA256_Ed25519
+        );
+        assert_eq!(
+            parse_ciphersuite("0x0002").unwrap(),
+            Ciphersuite::MLS_128_DHKEMP256_AES128GCM_SHA256_P256
+        );
+        assert!(parse_ciphersuite("MLS_256_DHKEMX448_CHACHA20POLY1305_SHA512_Ed448").is_err());
+        assert!(parse_ciphersuite("MLS_NOT_A_SUITE").is_err());
+    }
+
+    #[test]
+    fn test_p256_group_adds_matching_member() {
+        let ciphersuite = Ciphersuite::MLS_128_DHKEMP256_AES128GCM_SHA256_P256;
+        let (alice_credential, alice_keypair) = MlsCredential::generate(
+            "alice",
+            "device1",
+            &crate::x3dh::IdentityKeyPair::generate().unwrap(),
+            ciphersuite,
+        )
+        .unwrap();
+        let mut alice_group = MlsGroupManager::create_group_with_ciphersuite(
+            "test_group",
+            &alice_credential,
+            alice_keypair,
+            ciphersuite,
+        )
+        .unwrap();
+        assert_eq!(alice_group.ciphersuite(), ciphersuite);
+
+        let (bob_credential, bob_keypair) = MlsCredential::generate(
+            "bob",
+            "device1",
+            &crate::x3dh::IdentityKeyPair::generate().unwrap(),
+            ciphersuite,
+        )
+        .unwrap();
+        let bob_key_package = MlsGroupManager::generate_key_package_with_ciphersuites(
+            &bob_credential,
+            &bob_keypair,
+            ciphersuite,
+            &[DEFAULT_MLS_CIPHERSUITE],
+        )
+        .unwrap();
+        let (key_package_suite, supported) =
+            key_package_ciphersuites(&bob_key_package.key_package_bytes).unwrap();
+        assert_eq!(key_package_suite, ciphersuite);
+        assert!(supported.contains(&ciphersuite));
+        assert!(supported.contains(&DEFAULT_MLS_CIPHERSUITE));
+
+        alice_group.add_member(&bob_key_package.key_package_bytes).unwrap();
+        assert_eq!(alice_group.members().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_add_member_rejects_other_ciphersuite() {
+        let (alice_credential, alice_keypair) = create_test_member("alice", "device1").unwrap();
+        let mut alice_group =
+            MlsGroupManager::create_group("test_group", &alice_credential, alice_keypair).unwrap();
+
+        let (bob_credential, bob_keypair) = create_test_member("bob", "device1").unwrap();
+        let bob_key_package = MlsGroupManager::generate_key_package_with_ciphersuites(
+            &bob_credential,
+            &bob_keypair,
+            Ciphersuite::MLS_128_DHKEMX25519_CHACHA20POLY1305_SHA256_Ed25519,
+            &[],
+        )
+        .unwrap();
+
+        assert!(alice_group.add_member(&bob_key_package.key_package_bytes).is_err());
+        assert_eq!(alice_group.epoch(), 0);
+    }
+
+    #[test]
+    fn test_join_by_external_commit() {
+        let (alice_credential, alice_keypair) = create_test_member("alice", "device1").unwrap();
+        let mut alice_group =
+            MlsGroupManager::create_group("test_group", &alice_credential, alice_keypair).unwrap();
+        let group_info = alice_group.export_group_info().unwrap();
+
+        let (alice_device2_credential, alice_device2_keypair) =
+            create_test_member("alice", "device2").unwrap();
+        let (mut alice_device2_group, commit) = MlsGroupManager::join_by_external_commit(
+            &group_info,
+            &alice_device2_credential,
+            alice_device2_keypair,
+        )
+        .unwrap();
+        assert_eq!(message_epoch(&commit).unwrap(), 0);
+        assert_eq!(alice_device2_group.epoch(), 1);
+
+        let joiner = alice_group.process_external_commit(&commit).unwrap();
+        assert_eq!(joiner.label(), "alice:device2");
+        assert_eq!(&joiner, alice_device2_credential.identity());
+        assert_eq!(alice_group.epoch(), 1);
+        assert_eq!(alice_group.members().unwrap().len(), 2);
+
+        // Both devices share the new epoch's secrets
+        let ciphertext = alice_device2_group.encrypt_message(b"hello from device 2").unwrap();
+        let (plaintext, _aad) = alice_group.decrypt_message(&ciphertext).unwrap();
+        assert_eq!(plaintext, b"hello from device 2".to_vec());
+    }
+
+    #[test]
+    fn test_franked_message() {
+        let (alice_credential, alice_keypair) = create_test_member("alice", "device1").unwrap();
+        let mut alice_group =
+            MlsGroupManager::create_group("test_group", &alice_credential, alice_keypair).unwrap();
+        let group_info = alice_group.export_group_info().unwrap();
+
+        let (bob_credential, bob_keypair) = create_test_member("bob", "device1").unwrap();
+        let (mut bob_group, commit) =
+            MlsGroupManager::join_by_external_commit(&group_info, &bob_credential, bob_keypair).unwrap();
+        alice_group.process_external_commit(&commit).unwrap();
+
+        let (ciphertext, commitment) = bob_group.encrypt_franked_message(b"hello alice").unwrap();
+        let franked = alice_group.decrypt_franked_message(&ciphertext, &commitment).unwrap();
+        assert_eq!(franked.plaintext, b"hello alice");
+        franking::verify_commitment(&commitment, franked.franking_key.expose_secret(), b"hello alice").unwrap();
+
+        // A tag carrying another commitment is rejected
+        let (ciphertext, _) = bob_group.encrypt_franked_message(b"hello again").unwrap();
+        let (_, other_commitment) = franking::frank(b"hello again").unwrap();
+        assert!(alice_group.decrypt_franked_message(&ciphertext, &other_commitment).is_err());
+
+        // The commitment does not stick to later unfranked messages
+        let ciphertext = bob_group.encrypt_message(b"unfranked").unwrap();
+        let (plaintext, aad) = alice_group.decrypt_message(&ciphertext).unwrap();
+        assert_eq!(plaintext, b"unfranked");
+        assert!(aad.is_empty());
+    }
+
+    #[test]
+    fn test_external_commit_rejected_by_regular_commit_path() {
+        let (alice_credential, alice_keypair) = create_test_member("alice", "device1").unwrap();
+        let mut alice_group =
+            MlsGroupManager::create_group("test_group", &alice_credential, alice_keypair).unwrap();
+        let group_info = alice_group.export_group_info().unwrap();
+
+        // A GroupInfo from an older epoch cannot be used once the group moved on
+        alice_group.self_update().unwrap();
+        let (alice_device2_credential, alice_device2_keypair) =
+            create_test_member("alice", "device2").unwrap();
+        let (_group, commit) = MlsGroupManager::join_by_external_commit(
+            &group_info,
+            &alice_device2_credential,
+            alice_device2_keypair,
+        )
+        .unwrap();
+        assert!(alice_group.process_external_commit(&commit).is_err());
+        assert_eq!(alice_group.members().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_process_self_update_of_other_member() {
+        let (alice_credential, alice_keypair) = create_test_member("alice", "device1").unwrap();
+        let mut alice_group =
+            MlsGroupManager::create_group("test_group", &alice_credential, alice_keypair).unwrap();
+        let group_info = alice_group.export_group_info().unwrap();
+
+        let (bob_credential, bob_keypair) = create_test_member("bob", "device1").unwrap();
+        let (mut bob_group, commit) =
+            MlsGroupManager::join_by_external_commit(&group_info, &bob_credential, bob_keypair).unwrap();
+
+        // A join is not a self-update
+        assert!(alice_group.process_self_update(&commit).is_err());
+        alice_group.process_external_commit(&commit).unwrap();
+
+        let (commit, _welcome) = bob_group.self_update().unwrap();
+        let updater = alice_group.process_self_update(&commit).unwrap();
+        assert_eq!(&updater, bob_credential.identity());
+        assert_eq!(alice_group.epoch(), 2);
+
+        let ciphertext = bob_group.encrypt_message(b"fresh keys").unwrap();
+        let (plaintext, _aad) = alice_group.decrypt_message(&ciphertext).unwrap();
+        assert_eq!(plaintext, b"fresh keys".to_vec());
+    }
+
     #[test]
     fn test_serialize_group_state() {
-        let alice_keypair = create_test_keypair().unwrap();
-        let alice_id = b"alice_device1";
+        let (alice_credential, alice_keypair) = create_test_member("alice", "device1").unwrap();
         let alice_group =
-            MlsGroupManager::create_group("test_group", alice_id, alice_keypair).unwrap();
+            MlsGroupManager::create_group("test_group", &alice_credential, alice_keypair).unwrap();
 
         let state = alice_group.serialize_state();
         assert!(state.is_ok());
@@ -536,23 +1733,134 @@ mod tests {
         assert_eq!(state.epoch, 0);
         assert!(!state.serialized_state.is_empty());
     }
-}
 
-/// Helper function to create test credential for MLS operations
-///
-/// This is a convenience function for testing and development.
-/// In production, use proper credential management with secure storage.
-///
-/// # Arguments
-/// * `identity` - User identity bytes (typically "user_id:device_id")
-///
-/// # Returns
-/// SignatureKeyPair for MLS operations
-pub fn create_test_credential(_identity: &[u8]) -> Result<SignatureKeyPair> {
-    // Note: identity parameter is kept for API compatibility but not used
-    // OpenMLS 0.6 SignatureKeyPair::new() only takes signature scheme
-    let signature_keypair = SignatureKeyPair::new(MLS_CIPHERSUITE.signature_algorithm())
-        .map_err(|e| CryptoError::Protocol(format!("Failed to generate signature key: {:?}", e)))?;
-    Ok(signature_keypair)
-}
+    #[test]
+    fn test_public_group_validates_member_commits() {
+        let (alice_credential, alice_keypair) = create_test_member("alice", "device1").unwrap();
+        let mut alice_group =
+            MlsGroupManager::create_group("test_group", &alice_credential, alice_keypair).unwrap();
+        let mut public_group =
+            MlsPublicGroup::from_group_info(&alice_group.export_group_info().unwrap()).unwrap();
+        assert_eq!(public_group.epoch(), 0);
+        assert_eq!(public_group.group_id(), b"test_group".to_vec());
+
+        let (bob_credential, bob_keypair) = create_test_member("bob", "device1").unwrap();
+        let bob_key_package =
+            MlsGroupManager::generate_key_package(&bob_credential, &bob_keypair).unwrap();
+        let (commit, _welcome) = alice_group.add_member(&bob_key_package.key_package_bytes).unwrap();
+
+        let summary = public_group.process_commit(&commit).unwrap();
+        assert_eq!(summary.epoch, 0);
+        assert_eq!(&summary.committer, alice_credential.identity());
+        assert!(!summary.external);
+        assert_eq!(summary.added, vec![bob_credential.identity().clone()]);
+        assert!(summary.removed.is_empty());
+        assert!(!summary.is_self_update());
+        assert_eq!(public_group.epoch(), 1);
+        assert_eq!(public_group.members().unwrap(), alice_group.members().unwrap());
+
+        // Only a GroupInfo of the current epoch is accepted
+        public_group.verify_group_info(&alice_group.export_group_info().unwrap()).unwrap();
+
+        let (commit, _welcome) = alice_group.self_update().unwrap();
+        let summary = public_group.process_commit(&commit).unwrap();
+        assert!(summary.is_self_update());
+        assert_eq!(&summary.committer, alice_credential.identity());
+        assert_eq!(public_group.epoch(), 2);
+
+        // A commit cannot be replayed
+        assert!(public_group.process_commit(&commit).is_err());
+    }
+
+    #[test]
+    fn test_public_group_rejects_group_info_of_other_epoch() {
+        let (alice_credential, alice_keypair) = create_test_member("alice", "device1").unwrap();
+        let mut alice_group =
+            MlsGroupManager::create_group("test_group", &alice_credential, alice_keypair).unwrap();
+        let stale_group_info = alice_group.export_group_info().unwrap();
+        let mut public_group = MlsPublicGroup::from_group_info(&stale_group_info).unwrap();
+
+        let (commit, _welcome) = alice_group.self_update().unwrap();
+        public_group.process_commit(&commit).unwrap();
+        assert!(public_group.verify_group_info(&stale_group_info).is_err());
+
+        // A group with the same ID but other members does not match either
+        let (mallory_credential, mallory_keypair) = create_test_member("mallory", "device1").unwrap();
+        let mut mallory_group =
+            MlsGroupManager::create_group("test_group", &mallory_credential, mallory_keypair).unwrap();
+        mallory_group.self_update().unwrap();
+        assert!(public_group
+            .verify_group_info(&mallory_group.export_group_info().unwrap())
+            .is_err());
+        assert!(public_group.verify_group_info(b"garbage").is_err());
+    }
 
+    #[test]
+    fn test_public_group_validates_external_commit() {
+        let (alice_credential, alice_keypair) = create_test_member("alice", "device1").unwrap();
+        let alice_group =
+            MlsGroupManager::create_group("test_group", &alice_credential, alice_keypair).unwrap();
+        let group_info = alice_group.export_group_info().unwrap();
+        let mut public_group = MlsPublicGroup::from_group_info(&group_info).unwrap();
+
+        let (alice_device2_credential, alice_device2_keypair) =
+            create_test_member("alice", "device2").unwrap();
+        let (alice_device2_group, commit) = MlsGroupManager::join_by_external_commit(
+            &group_info,
+            &alice_device2_credential,
+            alice_device2_keypair,
+        )
+        .unwrap();
+
+        let summary = public_group.process_commit(&commit).unwrap();
+        assert!(summary.external);
+        assert_eq!(&summary.committer, alice_device2_credential.identity());
+        assert!(summary.added.is_empty());
+        assert!(!summary.is_self_update());
+        assert_eq!(public_group.members().unwrap().len(), 2);
+        public_group
+            .verify_group_info(&alice_device2_group.export_group_info().unwrap())
+            .unwrap();
+    }
+
+    #[test]
+    fn test_public_group_load_roundtrip() {
+        let (alice_credential, alice_keypair) = create_test_member("alice", "device1").unwrap();
+        let mut alice_group =
+            MlsGroupManager::create_group("test_group", &alice_credential, alice_keypair).unwrap();
+        let public_group =
+            MlsPublicGroup::from_group_info(&alice_group.export_group_info().unwrap()).unwrap();
+
+        let storage = MlsStorage::from_entries(public_group.storage().entries());
+        let mut public_group = MlsPublicGroup::load("test_group", storage).unwrap();
+        assert_eq!(public_group.epoch(), 0);
+
+        let (commit, _welcome) = alice_group.self_update().unwrap();
+        public_group.process_commit(&commit).unwrap();
+        assert_eq!(public_group.epoch(), 1);
+        assert!(!public_group.take_storage_changes().is_empty());
+    }
+
+    #[test]
+    fn test_public_group_load_drops_member_secrets() {
+        // Groups created before the server stopped keeping a leaf of its own
+        let (alice_credential, alice_keypair) = create_test_member("alice", "device1").unwrap();
+        let signature_key = alice_keypair.public().to_vec();
+        let alice_group =
+            MlsGroupManager::create_group("test_group", &alice_credential, alice_keypair).unwrap();
+        let storage = MlsStorage::from_entries(alice_group.storage().entries());
+
+        let public_group = MlsPublicGroup::load("test_group", storage).unwrap();
+        assert_eq!(public_group.epoch(), 0);
+        assert_eq!(&public_group.members().unwrap()[0], alice_credential.identity());
+
+        let changes = public_group.take_storage_changes();
+        assert!(!changes.deletions.is_empty());
+        assert!(SignatureKeyPair::read(
+            public_group.storage().provider(),
+            &signature_key,
+            DEFAULT_MLS_CIPHERSUITE.signature_algorithm(),
+        )
+        .is_none());
+    }
+}
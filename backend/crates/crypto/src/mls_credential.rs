@@ -0,0 +1,299 @@
+//! MLS credentials bound to Guardyn identities
+//!
+//! Every MLS leaf carries a `BasicCredential` whose identity is an encoded
+//! [`MlsCredential`]: the member's `user_id` and `device_id`, the user's
+//! Ed25519 identity key from X3DH, and the identity key's signature over the
+//! leaf's MLS signature key. Checking the signature proves the leaf was
+//! created by the holder of the identity key; comparing the identity key
+//! with the one published for the user (as done for X3DH key bundles) proves
+//! the leaf belongs to that Guardyn account.
+
+use crate::mls::generate_signature_keypair;
+use crate::x3dh::IdentityKeyPair;
+use crate::{CryptoError, Result};
+use openmls::prelude::{BasicCredential, Ciphersuite, Credential};
+use openmls_basic_credential::SignatureKeyPair;
+use serde::{Deserialize, Serialize};
+
+/// Encoding version of [`MlsCredential`]
+const CREDENTIAL_VERSION: u8 = 1;
+
+/// Domain separation label of the identity key signature
+const CREDENTIAL_SIGNATURE_LABEL: &[u8] = b"Guardyn MLS credential v1";
+
+/// Length of an Ed25519 identity key
+const IDENTITY_KEY_LEN: usize = 32;
+
+/// Length of an Ed25519 signature
+const SIGNATURE_LEN: usize = 64;
+
+/// Identity of an MLS group member, taken from a verified credential
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MlsMemberIdentity {
+    pub user_id: String,
+    pub device_id: String,
+    /// User's Ed25519 identity key that signed the member's MLS signature key
+    pub identity_key: Vec<u8>,
+}
+
+impl MlsMemberIdentity {
+    /// Member identity as `user_id:device_id`
+    pub fn label(&self) -> String {
+        format!("{}:{}", self.user_id, self.device_id)
+    }
+}
+
+/// Guardyn MLS credential
+///
+/// Binds an MLS signature key to a user's device, signed with the user's
+/// identity key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MlsCredential {
+    identity: MlsMemberIdentity,
+    identity_signature: Vec<u8>,
+}
+
+impl MlsCredential {
+    /// Generate an MLS signature keypair and a credential for it
+    ///
+    /// # Arguments
+    /// * `user_id` - Guardyn user ID
+    /// * `device_id` - Device of the user
+    /// * `identity_key` - User's X3DH identity keypair
+    /// * `ciphersuite` - Ciphersuite the signature keypair is used with
+    ///
+    /// # Returns
+    /// Tuple of (credential, signature_keypair)
+    pub fn generate(
+        user_id: &str,
+        device_id: &str,
+        identity_key: &IdentityKeyPair,
+        ciphersuite: Ciphersuite,
+    ) -> Result<(Self, SignatureKeyPair)> {
+        let signature_keypair = generate_signature_keypair(ciphersuite)?;
+        let credential = Self::issue(user_id, device_id, identity_key, signature_keypair.public())?;
+        Ok((credential, signature_keypair))
+    }
+
+    /// Issue a credential for an existing MLS signature key
+    ///
+    /// # Arguments
+    /// * `user_id` - Guardyn user ID
+    /// * `device_id` - Device of the user
+    /// * `identity_key` - User's X3DH identity keypair
+    /// * `signature_key` - Public MLS signature key of the device
+    pub fn issue(
+        user_id: &str,
+        device_id: &str,
+        identity_key: &IdentityKeyPair,
+        signature_key: &[u8],
+    ) -> Result<Self> {
+        if user_id.is_empty() || device_id.is_empty() {
+            return Err(CryptoError::Protocol(
+                "MLS credential requires a user and device ID".to_string(),
+            ));
+        }
+
+        let identity = MlsMemberIdentity {
+            user_id: user_id.to_string(),
+            device_id: device_id.to_string(),
+            identity_key: identity_key.public_bytes(),
+        };
+        let identity_signature = identity_key.sign(&signed_content(&identity, signature_key)?)?;
+
+        Ok(Self {
+            identity,
+            identity_signature,
+        })
+    }
+
+    /// Verify that the identity key signed `signature_key` for this member
+    pub fn verify(&self, signature_key: &[u8]) -> Result<()> {
+        IdentityKeyPair::verify(
+            &self.identity.identity_key,
+            &signed_content(&self.identity, signature_key)?,
+            &self.identity_signature,
+        )
+        .map_err(|_| {
+            CryptoError::InvalidSignature(format!(
+                "MLS credential of {} is not signed by its identity key",
+                self.identity.label()
+            ))
+        })
+    }
+
+    /// Parse an MLS credential and verify it against the leaf's signature key
+    ///
+    /// # Returns
+    /// Identity of the member the leaf belongs to
+    pub fn verify_leaf(credential: &Credential, signature_key: &[u8]) -> Result<MlsMemberIdentity> {
+        let credential = Self::from_credential(credential)?;
+        credential.verify(signature_key)?;
+        Ok(credential.identity)
+    }
+
+    /// Parse a Guardyn credential from an MLS credential (unverified)
+    pub fn from_credential(credential: &Credential) -> Result<Self> {
+        let basic = BasicCredential::try_from(credential.clone())
+            .map_err(|_| CryptoError::Protocol("Expected a basic MLS credential".to_string()))?;
+        Self::from_bytes(basic.identity())
+    }
+
+    /// MLS credential for leaf nodes and key packages
+    pub fn to_credential(&self) -> Credential {
+        BasicCredential::new(self.to_bytes()).into()
+    }
+
+    /// Identity the credential was issued for
+    pub fn identity(&self) -> &MlsMemberIdentity {
+        &self.identity
+    }
+
+    /// Encode the credential as the identity of a basic credential
+    ///
+    /// Layout: version | u16 len | user_id | u16 len | device_id |
+    /// identity_key (32) | identity_signature (64)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(
+            5 + self.identity.user_id.len()
+                + self.identity.device_id.len()
+                + IDENTITY_KEY_LEN
+                + SIGNATURE_LEN,
+        );
+        bytes.push(CREDENTIAL_VERSION);
+        // Lengths were checked when the credential was issued or parsed
+        put_field(&mut bytes, self.identity.user_id.as_bytes());
+        put_field(&mut bytes, self.identity.device_id.as_bytes());
+        bytes.extend_from_slice(&self.identity.identity_key);
+        bytes.extend_from_slice(&self.identity_signature);
+        bytes
+    }
+
+    /// Decode a credential encoded with [`MlsCredential::to_bytes`] (unverified)
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let malformed = || CryptoError::Protocol("Malformed MLS credential".to_string());
+
+        let (&version, mut rest) = bytes.split_first().ok_or_else(malformed)?;
+        if version != CREDENTIAL_VERSION {
+            return Err(CryptoError::Protocol(format!(
+                "Unsupported MLS credential version {}",
+                version
+            )));
+        }
+
+        let user_id = take_field(&mut rest).ok_or_else(malformed)?;
+        let device_id = take_field(&mut rest).ok_or_else(malformed)?;
+        if user_id.is_empty() || device_id.is_empty() || rest.len() != IDENTITY_KEY_LEN + SIGNATURE_LEN {
+            return Err(malformed());
+        }
+        let (identity_key, identity_signature) = rest.split_at(IDENTITY_KEY_LEN);
+
+        Ok(Self {
+            identity: MlsMemberIdentity {
+                user_id: String::from_utf8(user_id.to_vec()).map_err(|_| malformed())?,
+                device_id: String::from_utf8(device_id.to_vec()).map_err(|_| malformed())?,
+                identity_key: identity_key.to_vec(),
+            },
+            identity_signature: identity_signature.to_vec(),
+        })
+    }
+}
+
+/// Content signed by the identity key: the member and its MLS signature key
+fn signed_content(identity: &MlsMemberIdentity, signature_key: &[u8]) -> Result<Vec<u8>> {
+    let too_long = || CryptoError::Protocol("MLS credential field too long".to_string());
+    if identity.user_id.len() > u16::MAX as usize
+        || identity.device_id.len() > u16::MAX as usize
+        || signature_key.len() > u16::MAX as usize
+    {
+        return Err(too_long());
+    }
+
+    let mut content = Vec::with_capacity(
+        CREDENTIAL_SIGNATURE_LABEL.len() + 6 + identity.user_id.len() + identity.device_id.len() + signature_key.len(),
+    );
+    content.extend_from_slice(CREDENTIAL_SIGNATURE_LABEL);
+    put_field(&mut content, identity.user_id.as_bytes());
+    put_field(&mut content, identity.device_id.as_bytes());
+    put_field(&mut content, signature_key);
+    Ok(content)
+}
+
+/// Append a u16 length-prefixed field
+fn put_field(bytes: &mut Vec<u8>, field: &[u8]) {
+    bytes.extend_from_slice(&(field.len() as u16).to_be_bytes());
+    bytes.extend_from_slice(field);
+}
+
+/// Split a u16 length-prefixed field off the front of `bytes`
+fn take_field<'a>(bytes: &mut &'a [u8]) -> Option<&'a [u8]> {
+    if bytes.len() < 2 {
+        return None;
+    }
+    let (len, rest) = bytes.split_at(2);
+    let len = u16::from_be_bytes([len[0], len[1]]) as usize;
+    if rest.len() < len {
+        return None;
+    }
+    let (field, rest) = rest.split_at(len);
+    *bytes = rest;
+    Some(field)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mls::DEFAULT_MLS_CIPHERSUITE;
+
+    #[test]
+    fn test_credential_round_trip() {
+        let identity_key = IdentityKeyPair::generate().unwrap();
+        let (credential, signature_keypair) =
+            MlsCredential::generate("alice", "device1", &identity_key, DEFAULT_MLS_CIPHERSUITE).unwrap();
+
+        let parsed = MlsCredential::from_credential(&credential.to_credential()).unwrap();
+        assert_eq!(parsed, credential);
+
+        let identity = MlsCredential::verify_leaf(&credential.to_credential(), signature_keypair.public()).unwrap();
+        assert_eq!(identity.label(), "alice:device1");
+        assert_eq!(identity.identity_key, identity_key.public_bytes());
+    }
+
+    #[test]
+    fn test_credential_rejects_other_signature_key() {
+        let identity_key = IdentityKeyPair::generate().unwrap();
+        let (credential, _) =
+            MlsCredential::generate("alice", "device1", &identity_key, DEFAULT_MLS_CIPHERSUITE).unwrap();
+        let other_keypair = generate_signature_keypair(DEFAULT_MLS_CIPHERSUITE).unwrap();
+
+        assert!(credential.verify(other_keypair.public()).is_err());
+    }
+
+    #[test]
+    fn test_credential_rejects_claimed_identity_key() {
+        let alice_identity = IdentityKeyPair::generate().unwrap();
+        let mallory_identity = IdentityKeyPair::generate().unwrap();
+        let (mallory_credential, signature_keypair) =
+            MlsCredential::generate("alice", "device1", &mallory_identity, DEFAULT_MLS_CIPHERSUITE).unwrap();
+
+        // Claiming Alice's identity key without her signature fails
+        let mut forged = mallory_credential.to_bytes();
+        let key_offset = forged.len() - IDENTITY_KEY_LEN - SIGNATURE_LEN;
+        forged[key_offset..key_offset + IDENTITY_KEY_LEN].copy_from_slice(&alice_identity.public_bytes());
+        let forged = MlsCredential::from_bytes(&forged).unwrap();
+
+        assert!(forged.verify(signature_keypair.public()).is_err());
+    }
+
+    #[test]
+    fn test_credential_rejects_malformed_bytes() {
+        assert!(MlsCredential::from_bytes(b"alice:device1").is_err());
+        assert!(MlsCredential::from_bytes(&[]).is_err());
+
+        let identity_key = IdentityKeyPair::generate().unwrap();
+        let (credential, _) =
+            MlsCredential::generate("alice", "device1", &identity_key, DEFAULT_MLS_CIPHERSUITE).unwrap();
+        let bytes = credential.to_bytes();
+        assert!(MlsCredential::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
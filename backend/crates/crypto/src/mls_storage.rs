@@ -0,0 +1,296 @@
+//! Persistent storage for OpenMLS group state
+//!
+//! OpenMLS keeps all group state (ratchet tree, epoch secrets, message secrets,
+//! own leaf nodes, signature keys) in a key-value `StorageProvider`. This module
+//! provides an OpenMLS provider whose storage can be exported as a set of
+//! key-value entries and re-imported later, so that group state can be persisted
+//! in an external store (TiKV in messaging-service) and resumed at the current
+//! epoch after a restart.
+//!
+//! The OpenMLS storage traits are synchronous while TiKV access is async, so the
+//! storage works as a write-back cache: callers load all entries of a group before
+//! operating on it and flush the accumulated changes afterwards.
+
+use crate::{CryptoError, Result};
+use openmls_rust_crypto::{MemoryStorage, RustCrypto};
+use openmls_traits::OpenMlsProvider;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// Changes to storage entries since the last flush
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MlsStorageChanges {
+    /// Entries that were created or modified
+    pub upserts: Vec<(Vec<u8>, Vec<u8>)>,
+    /// Keys of entries that were removed
+    pub deletions: Vec<Vec<u8>>,
+}
+
+impl MlsStorageChanges {
+    /// Returns true if there is nothing to persist
+    pub fn is_empty(&self) -> bool {
+        self.upserts.is_empty() && self.deletions.is_empty()
+    }
+}
+
+/// Exportable key-value storage for OpenMLS
+///
+/// Wraps the OpenMLS in-memory `StorageProvider` and remembers which entries
+/// are already persisted, so only changed entries need to be written back.
+#[derive(Debug, Default)]
+pub struct MlsStorage {
+    store: MemoryStorage,
+    persisted: RwLock<HashMap<Vec<u8>, Vec<u8>>>,
+}
+
+impl MlsStorage {
+    /// Create empty storage for a new group
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create storage from entries loaded from the persistent store
+    ///
+    /// The loaded entries are considered persisted and are not reported by
+    /// [`MlsStorage::take_changes`] until they are modified.
+    pub fn from_entries(entries: HashMap<Vec<u8>, Vec<u8>>) -> Self {
+        let store = MemoryStorage::default();
+        *store.values.write().unwrap() = entries.clone();
+
+        Self {
+            store,
+            persisted: RwLock::new(entries),
+        }
+    }
+
+    /// Restore storage from a snapshot created by [`MlsStorage::to_bytes`]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut pos = 0usize;
+        let count = read_u32(bytes, &mut pos)?;
+        let mut entries = HashMap::with_capacity(count.min(1024));
+
+        for _ in 0..count {
+            let key_len = read_u32(bytes, &mut pos)?;
+            let key = read_slice(bytes, &mut pos, key_len)?.to_vec();
+            let value_len = read_u32(bytes, &mut pos)?;
+            let value = read_slice(bytes, &mut pos, value_len)?.to_vec();
+            entries.insert(key, value);
+        }
+
+        if pos != bytes.len() {
+            return Err(snapshot_error());
+        }
+
+        Ok(Self::from_entries(entries))
+    }
+
+    /// Serialize all entries into a single snapshot
+    ///
+    /// Format: entry count (u32 BE), then for each entry key length (u32 BE),
+    /// key, value length (u32 BE), value. Entries are sorted by key so the
+    /// snapshot is deterministic.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut entries: Vec<_> = self.entries().into_iter().collect();
+        entries.sort();
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+        for (key, value) in entries {
+            bytes.extend_from_slice(&(key.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(&key);
+            bytes.extend_from_slice(&(value.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(&value);
+        }
+        bytes
+    }
+
+    /// Get a copy of all current entries
+    pub fn entries(&self) -> HashMap<Vec<u8>, Vec<u8>> {
+        self.store.values.read().unwrap().clone()
+    }
+
+    /// Get the number of stored entries
+    pub fn len(&self) -> usize {
+        self.store.values.read().unwrap().len()
+    }
+
+    /// Returns true if no entries are stored
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Collect changes since the last flush and mark them as persisted
+    ///
+    /// Callers must write the returned changes to the persistent store.
+    /// If that write fails, [`MlsStorage::mark_unpersisted`] can be used to
+    /// report all entries again on the next call.
+    pub fn take_changes(&self) -> MlsStorageChanges {
+        let current = self.store.values.read().unwrap();
+        let mut persisted = self.persisted.write().unwrap();
+
+        let upserts = current
+            .iter()
+            .filter(|(key, value)| persisted.get(*key) != Some(*value))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+
+        let deletions = persisted
+            .keys()
+            .filter(|key| !current.contains_key(*key))
+            .cloned()
+            .collect();
+
+        *persisted = current.clone();
+
+        MlsStorageChanges { upserts, deletions }
+    }
+
+    /// Remove all entries
+    ///
+    /// The next [`MlsStorage::take_changes`] reports every persisted entry
+    /// as deleted.
+    pub fn clear(&self) {
+        self.store.values.write().unwrap().clear();
+    }
+
+    /// Forget which entries are persisted
+    ///
+    /// The next [`MlsStorage::take_changes`] reports every entry as upserted.
+    /// Deletions cannot be recovered this way, so stale keys may remain in
+    /// the persistent store until the group is deleted.
+    pub fn mark_unpersisted(&self) {
+        self.persisted.write().unwrap().clear();
+    }
+
+    /// Access the underlying OpenMLS storage provider
+    pub fn provider(&self) -> &MemoryStorage {
+        &self.store
+    }
+}
+
+fn snapshot_error() -> CryptoError {
+    CryptoError::Protocol("Invalid MLS storage snapshot".to_string())
+}
+
+fn read_slice<'a>(bytes: &'a [u8], pos: &mut usize, len: usize) -> Result<&'a [u8]> {
+    let end = pos.checked_add(len).ok_or_else(snapshot_error)?;
+    let slice = bytes.get(*pos..end).ok_or_else(snapshot_error)?;
+    *pos = end;
+    Ok(slice)
+}
+
+fn read_u32(bytes: &[u8], pos: &mut usize) -> Result<usize> {
+    let slice = read_slice(bytes, pos, 4)?;
+    Ok(u32::from_be_bytes([slice[0], slice[1], slice[2], slice[3]]) as usize)
+}
+
+/// OpenMLS provider backed by [`MlsStorage`]
+///
+/// Uses the RustCrypto backend for cryptographic operations and randomness.
+#[derive(Debug, Default)]
+pub struct MlsProvider {
+    crypto: RustCrypto,
+    storage: MlsStorage,
+}
+
+impl MlsProvider {
+    /// Create a provider with empty storage
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a provider on top of existing storage
+    pub fn with_storage(storage: MlsStorage) -> Self {
+        Self {
+            crypto: RustCrypto::default(),
+            storage,
+        }
+    }
+
+    /// Access the exportable storage
+    pub fn mls_storage(&self) -> &MlsStorage {
+        &self.storage
+    }
+
+    /// Take the exportable storage out of the provider
+    pub fn into_storage(self) -> MlsStorage {
+        self.storage
+    }
+}
+
+impl OpenMlsProvider for MlsProvider {
+    type CryptoProvider = RustCrypto;
+    type RandProvider = RustCrypto;
+    type StorageProvider = MemoryStorage;
+
+    fn storage(&self) -> &Self::StorageProvider {
+        self.storage.provider()
+    }
+
+    fn crypto(&self) -> &Self::CryptoProvider {
+        &self.crypto
+    }
+
+    fn rand(&self) -> &Self::RandProvider {
+        &self.crypto
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_entries() -> HashMap<Vec<u8>, Vec<u8>> {
+        let mut entries = HashMap::new();
+        entries.insert(b"key1".to_vec(), b"value1".to_vec());
+        entries.insert(b"key2".to_vec(), vec![]);
+        entries
+    }
+
+    #[test]
+    fn test_snapshot_roundtrip() {
+        let storage = MlsStorage::from_entries(sample_entries());
+        let bytes = storage.to_bytes();
+
+        let restored = MlsStorage::from_bytes(&bytes).unwrap();
+        assert_eq!(restored.entries(), sample_entries());
+        assert_eq!(restored.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_snapshot_rejects_truncated_input() {
+        let bytes = MlsStorage::from_entries(sample_entries()).to_bytes();
+        assert!(MlsStorage::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(MlsStorage::from_bytes(&[0, 0]).is_err());
+    }
+
+    #[test]
+    fn test_take_changes_tracks_upserts_and_deletions() {
+        let storage = MlsStorage::from_entries(sample_entries());
+        assert!(storage.take_changes().is_empty());
+
+        {
+            let mut values = storage.provider().values.write().unwrap();
+            values.insert(b"key1".to_vec(), b"updated".to_vec());
+            values.insert(b"key3".to_vec(), b"new".to_vec());
+            values.remove(b"key2".as_slice());
+        }
+
+        let mut changes = storage.take_changes();
+        changes.upserts.sort();
+        assert_eq!(
+            changes.upserts,
+            vec![
+                (b"key1".to_vec(), b"updated".to_vec()),
+                (b"key3".to_vec(), b"new".to_vec()),
+            ]
+        );
+        assert_eq!(changes.deletions, vec![b"key2".to_vec()]);
+
+        // Changes are only reported once
+        assert!(storage.take_changes().is_empty());
+
+        storage.mark_unpersisted();
+        assert_eq!(storage.take_changes().upserts.len(), 2);
+    }
+}
@@ -9,21 +9,21 @@
 
 #[cfg(test)]
 mod mls_integration_tests {
-    use crate::mls::{MlsGroupManager, MlsKeyPackage};
+    use crate::mls::{create_test_member, MlsGroupManager, MlsKeyPackage};
     use crate::{CryptoError, Result};
 
-    /// Helper to create a test keypair
-    fn create_test_keypair() -> Result<openmls_basic_credential::SignatureKeyPair> {
-        crate::mls::create_test_keypair()
+    /// Helper to generate a key package for a new member
+    fn generate_test_key_package(user_id: &str, device_id: &str) -> Result<MlsKeyPackage> {
+        let (credential, signature_keypair) = create_test_member(user_id, device_id)?;
+        MlsGroupManager::generate_key_package(&credential, &signature_keypair)
     }
 
     #[test]
     fn test_create_group() {
         // Alice creates a group
-        let alice_identity = b"alice:device1";
-        let alice_keypair = create_test_keypair().unwrap();
+        let (alice_credential, alice_keypair) = create_test_member("alice", "device1").unwrap();
 
-        let group = MlsGroupManager::create_group("test_group_001", alice_identity, alice_keypair);
+        let group = MlsGroupManager::create_group("test_group_001", &alice_credential, alice_keypair);
 
         assert!(group.is_ok());
         let group = group.unwrap();
@@ -34,7 +34,7 @@ mod mls_integration_tests {
     #[test]
     fn test_generate_key_package() {
         // Generate key package for Bob
-        let bob_key_package = MlsGroupManager::generate_key_package(b"bob:device1");
+        let bob_key_package = generate_test_key_package("bob", "device1");
 
         assert!(bob_key_package.is_ok());
         let key_package = bob_key_package.unwrap();
@@ -47,16 +47,16 @@ mod mls_integration_tests {
     #[test]
     fn test_add_single_member() {
         // Alice creates a group
-        let alice_keypair = create_test_keypair().unwrap();
+        let (alice_credential, alice_keypair) = create_test_member("alice", "device1").unwrap();
         let mut alice_group =
-            MlsGroupManager::create_group("test_group_002", b"alice:device1", alice_keypair)
+            MlsGroupManager::create_group("test_group_002", &alice_credential, alice_keypair)
                 .unwrap();
 
-        assert_eq!(alice_group.members().len(), 1);
+        assert_eq!(alice_group.members().unwrap().len(), 1);
         assert_eq!(alice_group.epoch(), 0);
 
         // Generate key package for Bob
-        let bob_key_package = MlsGroupManager::generate_key_package(b"bob:device1").unwrap();
+        let bob_key_package = generate_test_key_package("bob", "device1").unwrap();
 
         // Alice adds Bob
         let result = alice_group.add_member(&bob_key_package.key_package_bytes);
@@ -70,50 +70,50 @@ mod mls_integration_tests {
         assert_eq!(alice_group.epoch(), 1);
 
         // Verify member count increased
-        assert_eq!(alice_group.members().len(), 2);
+        assert_eq!(alice_group.members().unwrap().len(), 2);
     }
 
     #[test]
     fn test_add_multiple_members() {
         // Alice creates a group
-        let alice_keypair = create_test_keypair().unwrap();
+        let (alice_credential, alice_keypair) = create_test_member("alice", "device1").unwrap();
         let mut alice_group =
-            MlsGroupManager::create_group("test_group_003", b"alice:device1", alice_keypair)
+            MlsGroupManager::create_group("test_group_003", &alice_credential, alice_keypair)
                 .unwrap();
 
         // Add Bob
-        let bob_key_package = MlsGroupManager::generate_key_package(b"bob:device1").unwrap();
+        let bob_key_package = generate_test_key_package("bob", "device1").unwrap();
         let _ = alice_group.add_member(&bob_key_package.key_package_bytes).unwrap();
 
         assert_eq!(alice_group.epoch(), 1);
-        assert_eq!(alice_group.members().len(), 2);
+        assert_eq!(alice_group.members().unwrap().len(), 2);
 
         // Add Charlie
         let charlie_key_package =
-            MlsGroupManager::generate_key_package(b"charlie:device1").unwrap();
+            generate_test_key_package("charlie", "device1").unwrap();
         let _ = alice_group
             .add_member(&charlie_key_package.key_package_bytes)
             .unwrap();
 
         assert_eq!(alice_group.epoch(), 2);
-        assert_eq!(alice_group.members().len(), 3);
+        assert_eq!(alice_group.members().unwrap().len(), 3);
 
         // Add Dave
-        let dave_key_package = MlsGroupManager::generate_key_package(b"dave:device1").unwrap();
+        let dave_key_package = generate_test_key_package("dave", "device1").unwrap();
         let _ = alice_group
             .add_member(&dave_key_package.key_package_bytes)
             .unwrap();
 
         assert_eq!(alice_group.epoch(), 3);
-        assert_eq!(alice_group.members().len(), 4);
+        assert_eq!(alice_group.members().unwrap().len(), 4);
     }
 
     #[test]
     fn test_encrypt_decrypt_message() {
         // Alice creates a group
-        let alice_keypair = create_test_keypair().unwrap();
+        let (alice_credential, alice_keypair) = create_test_member("alice", "device1").unwrap();
         let mut alice_group =
-            MlsGroupManager::create_group("test_group_004", b"alice:device1", alice_keypair)
+            MlsGroupManager::create_group("test_group_004", &alice_credential, alice_keypair)
                 .unwrap();
 
         // Encrypt a message
@@ -138,9 +138,9 @@ mod mls_integration_tests {
     #[test]
     fn test_group_state_serialization() {
         // Alice creates a group
-        let alice_keypair = create_test_keypair().unwrap();
+        let (alice_credential, alice_keypair) = create_test_member("alice", "device1").unwrap();
         let alice_group =
-            MlsGroupManager::create_group("test_group_005", b"alice:device1", alice_keypair)
+            MlsGroupManager::create_group("test_group_005", &alice_credential, alice_keypair)
                 .unwrap();
 
         // Serialize group state
@@ -153,16 +153,85 @@ mod mls_integration_tests {
         assert!(!state.serialized_state.is_empty());
     }
 
+    #[test]
+    fn test_load_group_from_persisted_changes() {
+        use crate::mls_storage::MlsStorage;
+        use std::collections::HashMap;
+
+        // Simulated persistent store (TiKV in messaging-service)
+        let mut persisted: HashMap<Vec<u8>, Vec<u8>> = HashMap::new();
+        let mut flush = |group: &MlsGroupManager| {
+            let changes = group.take_storage_changes();
+            for key in changes.deletions {
+                persisted.remove(&key);
+            }
+            persisted.extend(changes.upserts);
+            persisted.clone()
+        };
+
+        let (alice_credential, alice_keypair) = create_test_member("alice", "device1").unwrap();
+        let mut alice_group =
+            MlsGroupManager::create_group("test_group_012", &alice_credential, alice_keypair)
+                .unwrap();
+        flush(&alice_group);
+
+        let bob_key_package = generate_test_key_package("bob", "device1").unwrap();
+        alice_group.add_member(&bob_key_package.key_package_bytes).unwrap();
+        let entries = flush(&alice_group);
+        drop(alice_group);
+
+        // Resume the group from persisted entries
+        let mut restored =
+            MlsGroupManager::load("test_group_012", MlsStorage::from_entries(entries)).unwrap();
+        assert_eq!(restored.group_id(), b"test_group_012");
+        assert_eq!(restored.epoch(), 1);
+        assert_eq!(restored.members().unwrap().len(), 2);
+        assert!(restored.take_storage_changes().is_empty());
+
+        // Restored group can continue advancing epochs
+        let carol_key_package = generate_test_key_package("carol", "device1").unwrap();
+        restored.add_member(&carol_key_package.key_package_bytes).unwrap();
+        assert_eq!(restored.epoch(), 2);
+        assert!(restored.encrypt_message(b"after restart").is_ok());
+        assert!(!restored.take_storage_changes().is_empty());
+    }
+
+    #[test]
+    fn test_restore_from_serialized_state() {
+        let (alice_credential, alice_keypair) = create_test_member("alice", "device1").unwrap();
+        let mut alice_group =
+            MlsGroupManager::create_group("test_group_013", &alice_credential, alice_keypair)
+                .unwrap();
+
+        let bob_key_package = generate_test_key_package("bob", "device1").unwrap();
+        alice_group.add_member(&bob_key_package.key_package_bytes).unwrap();
+
+        let state = alice_group.serialize_state().unwrap();
+        let restored = MlsGroupManager::from_state(&state).unwrap();
+
+        assert_eq!(restored.group_id(), alice_group.group_id());
+        assert_eq!(restored.epoch(), state.epoch);
+        assert_eq!(restored.members().unwrap(), alice_group.members().unwrap());
+    }
+
+    #[test]
+    fn test_load_unknown_group_fails() {
+        use crate::mls_storage::MlsStorage;
+
+        let result = MlsGroupManager::load("missing_group", MlsStorage::new());
+        assert!(matches!(result, Err(CryptoError::Protocol(_))));
+    }
+
     #[test]
     fn test_encrypt_after_adding_member() {
         // Alice creates a group
-        let alice_keypair = create_test_keypair().unwrap();
+        let (alice_credential, alice_keypair) = create_test_member("alice", "device1").unwrap();
         let mut alice_group =
-            MlsGroupManager::create_group("test_group_006", b"alice:device1", alice_keypair)
+            MlsGroupManager::create_group("test_group_006", &alice_credential, alice_keypair)
                 .unwrap();
 
         // Add Bob
-        let bob_key_package = MlsGroupManager::generate_key_package(b"bob:device1").unwrap();
+        let bob_key_package = generate_test_key_package("bob", "device1").unwrap();
         let _ = alice_group.add_member(&bob_key_package.key_package_bytes).unwrap();
 
         // Alice encrypts a message after adding Bob
@@ -180,39 +249,39 @@ mod mls_integration_tests {
     #[test]
     fn test_member_list() {
         // Alice creates a group
-        let alice_keypair = create_test_keypair().unwrap();
+        let (alice_credential, alice_keypair) = create_test_member("alice", "device1").unwrap();
         let mut alice_group =
-            MlsGroupManager::create_group("test_group_007", b"alice:device1", alice_keypair)
+            MlsGroupManager::create_group("test_group_007", &alice_credential, alice_keypair)
                 .unwrap();
 
-        let members = alice_group.members();
+        let members = alice_group.members().unwrap();
         assert_eq!(members.len(), 1);
-        assert_eq!(members[0], b"alice:device1");
+        assert_eq!(members[0].label(), "alice:device1");
+        assert_eq!(&members[0], alice_credential.identity());
 
         // Add Bob
-        let bob_key_package = MlsGroupManager::generate_key_package(b"bob:device1").unwrap();
+        let bob_key_package = generate_test_key_package("bob", "device1").unwrap();
         let _ = alice_group.add_member(&bob_key_package.key_package_bytes).unwrap();
 
-        let members = alice_group.members();
+        let members = alice_group.members().unwrap();
         assert_eq!(members.len(), 2);
-        assert!(members.contains(&b"alice:device1".to_vec()));
-        assert!(members.contains(&b"bob:device1".to_vec()));
+        assert!(members.iter().any(|member| member.label() == "alice:device1"));
+        assert!(members.iter().any(|member| member.label() == "bob:device1"));
     }
 
     #[test]
     fn test_epoch_advancement() {
         // Alice creates a group
-        let alice_keypair = create_test_keypair().unwrap();
+        let (alice_credential, alice_keypair) = create_test_member("alice", "device1").unwrap();
         let mut alice_group =
-            MlsGroupManager::create_group("test_group_008", b"alice:device1", alice_keypair)
+            MlsGroupManager::create_group("test_group_008", &alice_credential, alice_keypair)
                 .unwrap();
 
         assert_eq!(alice_group.epoch(), 0);
 
         // Each member addition should advance the epoch
         for i in 1..=5 {
-            let identity = format!("user{}:device1", i);
-            let key_package = MlsGroupManager::generate_key_package(identity.as_bytes()).unwrap();
+            let key_package = generate_test_key_package(&format!("user{}", i), "device1").unwrap();
             let _ = alice_group.add_member(&key_package.key_package_bytes).unwrap();
             assert_eq!(alice_group.epoch(), i);
         }
@@ -221,9 +290,9 @@ mod mls_integration_tests {
     #[test]
     fn test_multiple_messages() {
         // Alice creates a group
-        let alice_keypair = create_test_keypair().unwrap();
+        let (alice_credential, alice_keypair) = create_test_member("alice", "device1").unwrap();
         let mut alice_group =
-            MlsGroupManager::create_group("test_group_009", b"alice:device1", alice_keypair)
+            MlsGroupManager::create_group("test_group_009", &alice_credential, alice_keypair)
                 .unwrap();
 
         // Send multiple messages
@@ -244,10 +313,10 @@ mod mls_integration_tests {
     #[test]
     fn test_key_package_uniqueness() {
         // Generate multiple key packages for the same identity
-        let identity = b"test:device1";
+        let (credential, signature_keypair) = create_test_member("test", "device1").unwrap();
 
-        let pkg1 = MlsGroupManager::generate_key_package(identity).unwrap();
-        let pkg2 = MlsGroupManager::generate_key_package(identity).unwrap();
+        let pkg1 = MlsGroupManager::generate_key_package(&credential, &signature_keypair).unwrap();
+        let pkg2 = MlsGroupManager::generate_key_package(&credential, &signature_keypair).unwrap();
 
         // Package IDs should be different (random key generation)
         assert_ne!(pkg1.package_id, pkg2.package_id);
@@ -259,9 +328,9 @@ mod mls_integration_tests {
     #[test]
     fn test_empty_message() {
         // Alice creates a group
-        let alice_keypair = create_test_keypair().unwrap();
+        let (alice_credential, alice_keypair) = create_test_member("alice", "device1").unwrap();
         let mut alice_group =
-            MlsGroupManager::create_group("test_group_010", b"alice:device1", alice_keypair)
+            MlsGroupManager::create_group("test_group_010", &alice_credential, alice_keypair)
                 .unwrap();
 
         // Try to encrypt empty message
@@ -279,9 +348,9 @@ mod mls_integration_tests {
     #[test]
     fn test_large_message() {
         // Alice creates a group
-        let alice_keypair = create_test_keypair().unwrap();
+        let (alice_credential, alice_keypair) = create_test_member("alice", "device1").unwrap();
         let mut alice_group =
-            MlsGroupManager::create_group("test_group_011", b"alice:device1", alice_keypair)
+            MlsGroupManager::create_group("test_group_011", &alice_credential, alice_keypair)
                 .unwrap();
 
         // Encrypt a large message (1 MB)
@@ -305,9 +374,9 @@ mod mls_error_handling_tests {
 
     #[test]
     fn test_decrypt_invalid_ciphertext() {
-        let alice_keypair = crate::mls::create_test_keypair().unwrap();
+        let (alice_credential, alice_keypair) = crate::mls::create_test_member("alice", "device1").unwrap();
         let mut alice_group =
-            MlsGroupManager::create_group("test_group_error_001", b"alice:device1", alice_keypair)
+            MlsGroupManager::create_group("test_group_error_001", &alice_credential, alice_keypair)
                 .unwrap();
 
         // Try to decrypt invalid ciphertext
@@ -319,9 +388,9 @@ mod mls_error_handling_tests {
 
     #[test]
     fn test_add_invalid_key_package() {
-        let alice_keypair = crate::mls::create_test_keypair().unwrap();
+        let (alice_credential, alice_keypair) = crate::mls::create_test_member("alice", "device1").unwrap();
         let mut alice_group =
-            MlsGroupManager::create_group("test_group_error_002", b"alice:device1", alice_keypair)
+            MlsGroupManager::create_group("test_group_error_002", &alice_credential, alice_keypair)
                 .unwrap();
 
         // Try to add member with invalid key package
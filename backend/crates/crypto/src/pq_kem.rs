@@ -0,0 +1,221 @@
+/// Post-quantum KEM prekeys for PQXDH
+///
+/// ML-KEM-768 (FIPS 203) prekeys are published next to the X25519 prekeys in a
+/// key bundle. The initiator encapsulates to one of them and mixes the resulting
+/// shared secret into the X3DH key derivation, so a session key stays secret even
+/// if the recorded handshake is later attacked with a quantum computer
+/// (harvest-now-decrypt-later).
+///
+/// Every KEM prekey is signed with the owner's Ed25519 identity key, like the
+/// X25519 signed pre-key.
+use crate::x3dh::IdentityKeyPair;
+use crate::{CryptoError, Result};
+use ml_kem::kem::{Decapsulate, Encapsulate};
+use ml_kem::{Ciphertext, Encoded, EncodedSizeUser, KemCore, MlKem768};
+use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+type DecapsulationKey = <MlKem768 as KemCore>::DecapsulationKey;
+type EncapsulationKey = <MlKem768 as KemCore>::EncapsulationKey;
+
+/// ML-KEM-768 encapsulation (public) key size in bytes
+pub const ML_KEM_768_PUBLIC_KEY_SIZE: usize = 1184;
+
+/// ML-KEM-768 decapsulation (secret) key size in bytes
+pub const ML_KEM_768_SECRET_KEY_SIZE: usize = 2400;
+
+/// ML-KEM-768 ciphertext size in bytes
+pub const ML_KEM_768_CIPHERTEXT_SIZE: usize = 1088;
+
+/// Public part of a KEM prekey as published in a key bundle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KemPreKeyPublic {
+    pub key_id: u32,
+    pub public_key: Vec<u8>,      // ML-KEM-768 encapsulation key
+    pub signature: Vec<u8>,       // Ed25519 signature over public_key
+}
+
+impl KemPreKeyPublic {
+    /// Verify the prekey signature against the owner's Ed25519 identity key
+    pub fn verify(&self, identity_key: &[u8]) -> Result<()> {
+        if self.public_key.len() != ML_KEM_768_PUBLIC_KEY_SIZE {
+            return Err(CryptoError::InvalidKey(format!(
+                "ML-KEM-768 public key must be {} bytes",
+                ML_KEM_768_PUBLIC_KEY_SIZE
+            )));
+        }
+        IdentityKeyPair::verify(identity_key, &self.public_key, &self.signature)
+    }
+}
+
+/// ML-KEM-768 prekey signed with the identity key
+pub struct KemPreKey {
+    pub key_id: u32,
+    pub public_key: Vec<u8>,
+    pub signature: Vec<u8>,
+    pub timestamp: i64,
+    decapsulation_key: DecapsulationKey,
+}
+
+impl KemPreKey {
+    /// Generate a new KEM prekey signed with the identity key
+    pub fn generate(key_id: u32, identity_key: &IdentityKeyPair) -> Result<Self> {
+        let (decapsulation_key, encapsulation_key) = MlKem768::generate(&mut OsRng);
+        let public_key = encapsulation_key.as_bytes().to_vec();
+        let signature = identity_key.sign(&public_key)?;
+
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        Ok(Self {
+            key_id,
+            public_key,
+            signature,
+            timestamp,
+            decapsulation_key,
+        })
+    }
+
+    /// Export the public part for publishing
+    pub fn public(&self) -> KemPreKeyPublic {
+        KemPreKeyPublic {
+            key_id: self.key_id,
+            public_key: self.public_key.clone(),
+            signature: self.signature.clone(),
+        }
+    }
+
+    /// Recover the shared secret from a ciphertext produced by [`encapsulate`]
+    pub fn decapsulate(&self, ciphertext: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+        let ciphertext = Ciphertext::<MlKem768>::try_from(ciphertext).map_err(|_| {
+            CryptoError::InvalidKey(format!(
+                "ML-KEM-768 ciphertext must be {} bytes",
+                ML_KEM_768_CIPHERTEXT_SIZE
+            ))
+        })?;
+
+        let shared_secret = self
+            .decapsulation_key
+            .decapsulate(&ciphertext)
+            .map_err(|e| CryptoError::Decryption(format!("ML-KEM decapsulation failed: {:?}", e)))?;
+
+        Ok(Zeroizing::new(shared_secret.as_slice().to_vec()))
+    }
+
+    /// Serialize the full prekey (including secret) for persistent storage
+    ///
+    /// Format: key_id (u32 BE) || timestamp (i64 BE) || decapsulation key (2400) || signature
+    pub fn to_bytes(&self) -> Zeroizing<Vec<u8>> {
+        let decapsulation_key = Zeroizing::new(self.decapsulation_key.as_bytes().to_vec());
+
+        let mut bytes = Zeroizing::new(Vec::with_capacity(
+            12 + ML_KEM_768_SECRET_KEY_SIZE + self.signature.len(),
+        ));
+        bytes.extend_from_slice(&self.key_id.to_be_bytes());
+        bytes.extend_from_slice(&self.timestamp.to_be_bytes());
+        bytes.extend_from_slice(&decapsulation_key);
+        bytes.extend_from_slice(&self.signature);
+        bytes
+    }
+
+    /// Restore a KEM prekey serialized with [`KemPreKey::to_bytes`]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 12 + ML_KEM_768_SECRET_KEY_SIZE {
+            return Err(CryptoError::InvalidKey("KEM prekey data too short".into()));
+        }
+
+        let key_id = u32::from_be_bytes(bytes[0..4].try_into().unwrap());
+        let timestamp = i64::from_be_bytes(bytes[4..12].try_into().unwrap());
+        let encoded = Encoded::<DecapsulationKey>::try_from(&bytes[12..12 + ML_KEM_768_SECRET_KEY_SIZE])
+            .map_err(|_| CryptoError::InvalidKey("Invalid ML-KEM-768 secret key".into()))?;
+        let decapsulation_key = DecapsulationKey::from_bytes(&encoded);
+        let public_key = decapsulation_key.encapsulation_key().as_bytes().to_vec();
+        let signature = bytes[12 + ML_KEM_768_SECRET_KEY_SIZE..].to_vec();
+
+        Ok(Self {
+            key_id,
+            public_key,
+            signature,
+            timestamp,
+            decapsulation_key,
+        })
+    }
+}
+
+/// Encapsulate a fresh shared secret to an ML-KEM-768 public key
+///
+/// Returns: (ciphertext to send to the key owner, 32-byte shared secret)
+pub fn encapsulate(public_key: &[u8]) -> Result<(Vec<u8>, Zeroizing<Vec<u8>>)> {
+    let encoded = Encoded::<EncapsulationKey>::try_from(public_key).map_err(|_| {
+        CryptoError::InvalidKey(format!(
+            "ML-KEM-768 public key must be {} bytes",
+            ML_KEM_768_PUBLIC_KEY_SIZE
+        ))
+    })?;
+    let encapsulation_key = EncapsulationKey::from_bytes(&encoded);
+
+    let (ciphertext, shared_secret) = encapsulation_key
+        .encapsulate(&mut OsRng)
+        .map_err(|e| CryptoError::Encryption(format!("ML-KEM encapsulation failed: {:?}", e)))?;
+
+    Ok((
+        ciphertext.as_slice().to_vec(),
+        Zeroizing::new(shared_secret.as_slice().to_vec()),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kem_prekey_generation() {
+        let identity = IdentityKeyPair::generate().unwrap();
+        let prekey = KemPreKey::generate(1, &identity).unwrap();
+
+        assert_eq!(prekey.public_key.len(), ML_KEM_768_PUBLIC_KEY_SIZE);
+        assert!(prekey.public().verify(&identity.public_bytes()).is_ok());
+
+        let other_identity = IdentityKeyPair::generate().unwrap();
+        assert!(prekey.public().verify(&other_identity.public_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_encapsulate_decapsulate() {
+        let identity = IdentityKeyPair::generate().unwrap();
+        let prekey = KemPreKey::generate(1, &identity).unwrap();
+
+        let (ciphertext, sender_secret) = encapsulate(&prekey.public_key).unwrap();
+        assert_eq!(ciphertext.len(), ML_KEM_768_CIPHERTEXT_SIZE);
+
+        let receiver_secret = prekey.decapsulate(&ciphertext).unwrap();
+        assert_eq!(sender_secret.len(), 32);
+        assert_eq!(sender_secret, receiver_secret);
+    }
+
+    #[test]
+    fn test_kem_prekey_serialization() {
+        let identity = IdentityKeyPair::generate().unwrap();
+        let prekey = KemPreKey::generate(9, &identity).unwrap();
+
+        let restored = KemPreKey::from_bytes(&prekey.to_bytes()).unwrap();
+        assert_eq!(restored.key_id, 9);
+        assert_eq!(restored.public_key, prekey.public_key);
+        assert_eq!(restored.signature, prekey.signature);
+
+        let (ciphertext, secret) = encapsulate(&prekey.public_key).unwrap();
+        assert_eq!(restored.decapsulate(&ciphertext).unwrap(), secret);
+    }
+
+    #[test]
+    fn test_rejects_invalid_lengths() {
+        assert!(encapsulate(&[0u8; 32]).is_err());
+
+        let identity = IdentityKeyPair::generate().unwrap();
+        let prekey = KemPreKey::generate(1, &identity).unwrap();
+        assert!(prekey.decapsulate(&[0u8; 32]).is_err());
+    }
+}
@@ -0,0 +1,363 @@
+/// Sealed sender envelopes for 1-on-1 messages
+///
+/// Hides the sender from the server: instead of authenticating, the sender
+/// encrypts a short-lived sender certificate (issued and signed by
+/// auth-service) together with the message to the recipient's identity key.
+/// The server only learns the recipient address; the recipient learns and
+/// verifies the sender after unsealing.
+///
+/// Envelope format (version 1):
+/// `version (1) || ephemeral X25519 public key (32) || nonce (12) || AES-256-GCM ciphertext`
+/// where the plaintext is `certificate length (u32 BE) || certificate || content`
+/// and the key is derived with HKDF-SHA256 from an ephemeral-static DH with the
+/// recipient's identity key (converted to X25519).
+///
+/// The content is normally a Double Ratchet message, which already
+/// authenticates the sender. Recipients must check that the certificate's
+/// identity key matches the identity key of the session the content decrypts
+/// with, so a certificate cannot be paired with someone else's message.
+use crate::x3dh::IdentityKeyPair;
+use crate::{CryptoError, Result};
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Nonce,
+};
+use ed25519_dalek::VerifyingKey;
+use hkdf::Hkdf;
+use rand::RngCore;
+use sha2::Sha256;
+use x25519_dalek::{EphemeralSecret, PublicKey as X25519PublicKey};
+use zeroize::Zeroizing;
+
+/// Current sealed sender envelope version
+pub const SEALED_SENDER_VERSION: u8 = 1;
+
+/// Current sender certificate version
+pub const SENDER_CERTIFICATE_VERSION: u8 = 1;
+
+/// Domain separation for certificate signatures
+const CERTIFICATE_SIGNATURE_CONTEXT: &[u8] = b"Guardyn_SenderCertificate";
+
+/// HKDF info for the envelope key
+const SEALED_SENDER_INFO: &[u8] = b"Guardyn_SealedSender_v1";
+
+const KEY_SIZE: usize = 32;
+const NONCE_SIZE: usize = 12;
+const SIGNATURE_SIZE: usize = 64;
+
+/// Certificate binding a sender's user, device and identity key, signed by auth-service
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SenderCertificate {
+    pub sender_user_id: String,
+    pub sender_device_id: String,
+    pub sender_identity_key: Vec<u8>,
+    /// Expiration time (Unix seconds)
+    pub expires_at: i64,
+    signature: Vec<u8>,
+}
+
+impl SenderCertificate {
+    /// Issue a certificate signed with the server's certificate signing key
+    pub fn issue(
+        server_key: &IdentityKeyPair,
+        sender_user_id: &str,
+        sender_device_id: &str,
+        sender_identity_key: &[u8],
+        expires_at: i64,
+    ) -> Result<Self> {
+        if sender_identity_key.len() != KEY_SIZE {
+            return Err(CryptoError::InvalidKey("Invalid sender identity key length".to_string()));
+        }
+
+        let mut certificate = Self {
+            sender_user_id: sender_user_id.to_string(),
+            sender_device_id: sender_device_id.to_string(),
+            sender_identity_key: sender_identity_key.to_vec(),
+            expires_at,
+            signature: vec![],
+        };
+        certificate.signature = server_key.sign(&Self::signature_input(&certificate.signed_data()?))?;
+        Ok(certificate)
+    }
+
+    /// Check the signature against the server's public key and the expiry against `now`
+    pub fn verify(&self, server_public_key: &[u8], now: i64) -> Result<()> {
+        IdentityKeyPair::verify(
+            server_public_key,
+            &Self::signature_input(&self.signed_data()?),
+            &self.signature,
+        )?;
+
+        if now >= self.expires_at {
+            return Err(CryptoError::Protocol("Sender certificate has expired".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Serialize the certificate
+    ///
+    /// Format: version || user ID (u16 length-prefixed) || device ID (u16 length-prefixed)
+    /// || identity key (32) || expires_at (i64 BE) || signature (64)
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = self.signed_data()?;
+        bytes.extend_from_slice(&self.signature);
+        Ok(bytes)
+    }
+
+    /// Deserialize a certificate (the signature is not checked, see [`Self::verify`])
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let invalid = || CryptoError::Protocol("Invalid sender certificate".to_string());
+
+        let (&version, mut rest) = bytes.split_first().ok_or_else(invalid)?;
+        if version != SENDER_CERTIFICATE_VERSION {
+            return Err(CryptoError::Protocol(format!(
+                "Unsupported sender certificate version: {}",
+                version
+            )));
+        }
+
+        let read_string = |rest: &mut &[u8]| -> Result<String> {
+            if rest.len() < 2 {
+                return Err(invalid());
+            }
+            let len = u16::from_be_bytes([rest[0], rest[1]]) as usize;
+            if rest.len() < 2 + len {
+                return Err(invalid());
+            }
+            let value = String::from_utf8(rest[2..2 + len].to_vec()).map_err(|_| invalid())?;
+            *rest = &rest[2 + len..];
+            Ok(value)
+        };
+        let sender_user_id = read_string(&mut rest)?;
+        let sender_device_id = read_string(&mut rest)?;
+
+        if rest.len() != KEY_SIZE + 8 + SIGNATURE_SIZE {
+            return Err(invalid());
+        }
+        let (sender_identity_key, rest) = rest.split_at(KEY_SIZE);
+        let (expires_at, signature) = rest.split_at(8);
+
+        Ok(Self {
+            sender_user_id,
+            sender_device_id,
+            sender_identity_key: sender_identity_key.to_vec(),
+            expires_at: i64::from_be_bytes(expires_at.try_into().map_err(|_| invalid())?),
+            signature: signature.to_vec(),
+        })
+    }
+
+    /// Everything but the signature
+    fn signed_data(&self) -> Result<Vec<u8>> {
+        let user_id = self.sender_user_id.as_bytes();
+        let device_id = self.sender_device_id.as_bytes();
+        if user_id.len() > u16::MAX as usize || device_id.len() > u16::MAX as usize {
+            return Err(CryptoError::Protocol("Sender identifier too long".to_string()));
+        }
+
+        let mut data = Vec::with_capacity(1 + 4 + user_id.len() + device_id.len() + KEY_SIZE + 8);
+        data.push(SENDER_CERTIFICATE_VERSION);
+        data.extend_from_slice(&(user_id.len() as u16).to_be_bytes());
+        data.extend_from_slice(user_id);
+        data.extend_from_slice(&(device_id.len() as u16).to_be_bytes());
+        data.extend_from_slice(device_id);
+        data.extend_from_slice(&self.sender_identity_key);
+        data.extend_from_slice(&self.expires_at.to_be_bytes());
+        Ok(data)
+    }
+
+    fn signature_input(signed_data: &[u8]) -> Vec<u8> {
+        [CERTIFICATE_SIGNATURE_CONTEXT, signed_data].concat()
+    }
+}
+
+/// Sealed sender envelope operations
+pub struct SealedSender;
+
+impl SealedSender {
+    /// Seal `content` and the sender certificate to the recipient's identity key
+    pub fn seal(
+        certificate: &SenderCertificate,
+        content: &[u8],
+        recipient_identity_key: &[u8],
+    ) -> Result<Vec<u8>> {
+        let recipient_public = identity_key_to_x25519(recipient_identity_key)?;
+
+        let ephemeral = EphemeralSecret::random_from_rng(rand::rngs::OsRng);
+        let ephemeral_public = X25519PublicKey::from(&ephemeral);
+        let shared_secret = ephemeral.diffie_hellman(&recipient_public);
+
+        let key = derive_key(shared_secret.as_bytes(), &ephemeral_public, &recipient_public)?;
+
+        let certificate_bytes = certificate.to_bytes()?;
+        let mut plaintext = Zeroizing::new(Vec::with_capacity(4 + certificate_bytes.len() + content.len()));
+        plaintext.extend_from_slice(&(certificate_bytes.len() as u32).to_be_bytes());
+        plaintext.extend_from_slice(&certificate_bytes);
+        plaintext.extend_from_slice(content);
+
+        let mut nonce = [0u8; NONCE_SIZE];
+        rand::rngs::OsRng.fill_bytes(&mut nonce);
+
+        let mut header = Vec::with_capacity(1 + KEY_SIZE);
+        header.push(SEALED_SENDER_VERSION);
+        header.extend_from_slice(ephemeral_public.as_bytes());
+
+        let cipher = Aes256Gcm::new_from_slice(key.as_slice())
+            .map_err(|e| CryptoError::Encryption(format!("Invalid key: {}", e)))?;
+        let ciphertext = cipher
+            .encrypt(&Nonce::from(nonce), Payload { msg: &plaintext, aad: &header })
+            .map_err(|e| CryptoError::Encryption(format!("Sealed sender encryption failed: {}", e)))?;
+
+        let mut sealed = header;
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    /// Open an envelope with the recipient's identity key pair
+    ///
+    /// Verifies the sender certificate against the server's public key and
+    /// returns it together with the content.
+    pub fn unseal(
+        sealed: &[u8],
+        recipient_identity: &IdentityKeyPair,
+        server_public_key: &[u8],
+        now: i64,
+    ) -> Result<(SenderCertificate, Vec<u8>)> {
+        if sealed.len() < 1 + KEY_SIZE + NONCE_SIZE {
+            return Err(CryptoError::Decryption("Sealed message too short".to_string()));
+        }
+        if sealed[0] != SEALED_SENDER_VERSION {
+            return Err(CryptoError::Protocol(format!(
+                "Unsupported sealed sender version: {}",
+                sealed[0]
+            )));
+        }
+
+        let (header, rest) = sealed.split_at(1 + KEY_SIZE);
+        let (nonce, ciphertext) = rest
+            .split_first_chunk::<NONCE_SIZE>()
+            .ok_or_else(|| CryptoError::Decryption("Sealed message too short".to_string()))?;
+
+        let mut ephemeral_bytes = [0u8; KEY_SIZE];
+        ephemeral_bytes.copy_from_slice(&header[1..]);
+        let ephemeral_public = X25519PublicKey::from(ephemeral_bytes);
+
+        let shared_secret = recipient_identity.to_x25519_secret().diffie_hellman(&ephemeral_public);
+        let key = derive_key(shared_secret.as_bytes(), &ephemeral_public, &recipient_identity.to_x25519_public())?;
+
+        let cipher = Aes256Gcm::new_from_slice(key.as_slice())
+            .map_err(|e| CryptoError::Decryption(format!("Invalid key: {}", e)))?;
+        let plaintext = Zeroizing::new(
+            cipher
+                .decrypt(&Nonce::from(*nonce), Payload { msg: ciphertext, aad: header })
+                .map_err(|e| CryptoError::Decryption(format!("Sealed sender decryption failed: {}", e)))?,
+        );
+
+        if plaintext.len() < 4 {
+            return Err(CryptoError::Protocol("Invalid sealed sender payload".to_string()));
+        }
+        let certificate_len = u32::from_be_bytes([plaintext[0], plaintext[1], plaintext[2], plaintext[3]]) as usize;
+        if plaintext.len() - 4 < certificate_len {
+            return Err(CryptoError::Protocol("Invalid sealed sender payload".to_string()));
+        }
+
+        let certificate = SenderCertificate::from_bytes(&plaintext[4..4 + certificate_len])?;
+        certificate.verify(server_public_key, now)?;
+
+        Ok((certificate, plaintext[4 + certificate_len..].to_vec()))
+    }
+}
+
+/// Convert an Ed25519 identity public key to X25519
+fn identity_key_to_x25519(identity_key: &[u8]) -> Result<X25519PublicKey> {
+    let bytes: &[u8; KEY_SIZE] = identity_key
+        .try_into()
+        .map_err(|_| CryptoError::InvalidKey("Invalid identity key length".to_string()))?;
+    let verifying_key = VerifyingKey::from_bytes(bytes)
+        .map_err(|e| CryptoError::InvalidKey(format!("Invalid identity key: {}", e)))?;
+    Ok(X25519PublicKey::from(verifying_key.to_montgomery().to_bytes()))
+}
+
+/// Derive the envelope key, binding both public keys
+fn derive_key(
+    shared_secret: &[u8],
+    ephemeral_public: &X25519PublicKey,
+    recipient_public: &X25519PublicKey,
+) -> Result<Zeroizing<[u8; KEY_SIZE]>> {
+    let salt = [ephemeral_public.as_bytes().as_slice(), recipient_public.as_bytes().as_slice()].concat();
+    let hkdf = Hkdf::<Sha256>::new(Some(&salt), shared_secret);
+    let mut key = Zeroizing::new([0u8; KEY_SIZE]);
+    hkdf.expand(SEALED_SENDER_INFO, key.as_mut_slice())
+        .map_err(|e| CryptoError::KeyGeneration(format!("HKDF expand failed: {}", e)))?;
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn issue(server: &IdentityKeyPair, sender: &IdentityKeyPair) -> SenderCertificate {
+        SenderCertificate::issue(server, "alice", "device-1", &sender.public_bytes(), NOW + 3600).unwrap()
+    }
+
+    #[test]
+    fn test_seal_and_unseal() {
+        let server = IdentityKeyPair::generate().unwrap();
+        let alice = IdentityKeyPair::generate().unwrap();
+        let bob = IdentityKeyPair::generate().unwrap();
+
+        let certificate = issue(&server, &alice);
+        let sealed = SealedSender::seal(&certificate, b"ratchet message", &bob.public_bytes()).unwrap();
+        assert!(!sealed.windows(5).any(|window| window == b"alice"));
+
+        let (sender, content) = SealedSender::unseal(&sealed, &bob, &server.public_bytes(), NOW).unwrap();
+        assert_eq!(sender, certificate);
+        assert_eq!(sender.sender_identity_key, alice.public_bytes());
+        assert_eq!(content, b"ratchet message");
+    }
+
+    #[test]
+    fn test_unseal_rejects_wrong_recipient_or_tampering() {
+        let server = IdentityKeyPair::generate().unwrap();
+        let alice = IdentityKeyPair::generate().unwrap();
+        let bob = IdentityKeyPair::generate().unwrap();
+        let eve = IdentityKeyPair::generate().unwrap();
+
+        let sealed = SealedSender::seal(&issue(&server, &alice), b"hello", &bob.public_bytes()).unwrap();
+        assert!(SealedSender::unseal(&sealed, &eve, &server.public_bytes(), NOW).is_err());
+
+        let mut tampered = sealed.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 0x01;
+        assert!(SealedSender::unseal(&tampered, &bob, &server.public_bytes(), NOW).is_err());
+    }
+
+    #[test]
+    fn test_certificate_verification() {
+        let server = IdentityKeyPair::generate().unwrap();
+        let rogue_server = IdentityKeyPair::generate().unwrap();
+        let alice = IdentityKeyPair::generate().unwrap();
+        let bob = IdentityKeyPair::generate().unwrap();
+
+        let certificate = issue(&server, &alice);
+        let parsed = SenderCertificate::from_bytes(&certificate.to_bytes().unwrap()).unwrap();
+        assert_eq!(parsed, certificate);
+        assert!(parsed.verify(&server.public_bytes(), NOW).is_ok());
+
+        // Expired
+        assert!(parsed.verify(&server.public_bytes(), NOW + 3600).is_err());
+
+        // Issued by someone else
+        let forged = issue(&rogue_server, &alice);
+        assert!(forged.verify(&server.public_bytes(), NOW).is_err());
+        let sealed = SealedSender::seal(&forged, b"hello", &bob.public_bytes()).unwrap();
+        assert!(SealedSender::unseal(&sealed, &bob, &server.public_bytes(), NOW).is_err());
+
+        // Modified after signing
+        let mut modified = certificate.clone();
+        modified.sender_user_id = "mallory".to_string();
+        assert!(modified.verify(&server.public_bytes(), NOW).is_err());
+    }
+}
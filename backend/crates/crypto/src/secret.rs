@@ -0,0 +1,131 @@
+//! Secret key material handling
+//!
+//! [`SecretKey`] wraps fixed-size key material: it is wiped from memory when
+//! dropped, compares in constant time and never shows up in `Debug` output.
+//! [`ct_eq`] is the constant-time comparison used wherever a secret or a
+//! value derived from one (MAC, digest, check value) is compared.
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use subtle::ConstantTimeEq;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// Fixed-size secret key material
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub struct SecretKey<const N: usize>([u8; N]);
+
+impl<const N: usize> SecretKey<N> {
+    /// Wrap key material
+    pub fn new(bytes: [u8; N]) -> Self {
+        Self(bytes)
+    }
+
+    /// Copy key material from a slice of exactly `N` bytes
+    pub fn from_slice(bytes: &[u8]) -> Option<Self> {
+        let mut key = Self([0u8; N]);
+        if bytes.len() != N {
+            return None;
+        }
+        key.0.copy_from_slice(bytes);
+        Some(key)
+    }
+
+    /// Access the key material
+    pub fn expose_secret(&self) -> &[u8; N] {
+        &self.0
+    }
+}
+
+impl<const N: usize> fmt::Debug for SecretKey<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretKey<{}>([REDACTED])", N)
+    }
+}
+
+impl<const N: usize> PartialEq for SecretKey<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.ct_eq(&other.0).into()
+    }
+}
+
+impl<const N: usize> Eq for SecretKey<N> {}
+
+/// Lets keys index in-memory maps (e.g. skipped message keys per header key).
+/// `HashMap` hashes with a random per-map key, so hashes reveal nothing stable.
+impl<const N: usize> Hash for SecretKey<N> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
+/// Compare two byte strings in constant time
+///
+/// Only the lengths may leak; use for MACs, digests and secret values.
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    a.ct_eq(b).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attachment::encrypt_attachment;
+    use crate::backup::RecoveryCode;
+    use crate::double_ratchet::DoubleRatchet;
+    use crate::x3dh::{IdentityKeyPair, OneTimePreKey, SignedPreKey};
+
+    /// True if `debug` contains `secret` printed as a byte list or as hex
+    fn leaks(debug: &str, secret: &[u8]) -> bool {
+        let as_list = format!("{:?}", secret);
+        let as_list = &as_list[1..as_list.len() - 1];
+        let as_hex: String = secret.iter().map(|b| format!("{:02x}", b)).collect();
+        debug.contains(as_list) || debug.to_lowercase().contains(&as_hex)
+    }
+
+    #[test]
+    fn test_secret_key_equality_and_redaction() {
+        let key = SecretKey::new([7u8; 32]);
+        assert_eq!(key, SecretKey::from_slice(&[7u8; 32]).unwrap());
+        assert_ne!(key, SecretKey::new([8u8; 32]));
+        assert!(SecretKey::<32>::from_slice(&[7u8; 31]).is_none());
+
+        assert_eq!(format!("{:?}", key), "SecretKey<32>([REDACTED])");
+        assert!(ct_eq(b"digest", b"digest"));
+        assert!(!ct_eq(b"digest", b"digesT"));
+        assert!(!ct_eq(b"digest", b"diges"));
+    }
+
+    #[test]
+    fn test_debug_output_never_contains_key_bytes() {
+        let identity = IdentityKeyPair::generate().unwrap();
+        assert!(!leaks(&format!("{:?}", identity), identity.secret_bytes().as_slice()));
+
+        let signed_pre_key = SignedPreKey::generate(1, &identity).unwrap();
+        let secret = &signed_pre_key.to_bytes()[4..36];
+        assert!(!leaks(&format!("{:?}", signed_pre_key), secret));
+
+        let one_time_pre_key = OneTimePreKey::generate(1);
+        assert!(!leaks(&format!("{:?}", one_time_pre_key), one_time_pre_key.secret_bytes().as_slice()));
+
+        // A session that has sent and received holds root, chain and header keys
+        let bob_secret = x25519_dalek::StaticSecret::random_from_rng(rand::rngs::OsRng);
+        let bob_public = x25519_dalek::PublicKey::from(&bob_secret);
+        let mut alice = DoubleRatchet::init_alice_with_header_encryption(&[42u8; 32], bob_public).unwrap();
+        let mut bob = DoubleRatchet::init_bob_with_header_encryption(&[42u8; 32], bob_secret).unwrap();
+        let hello = alice.encrypt_header_encrypted(b"hello", b"").unwrap();
+        bob.decrypt_header_encrypted(&hello, b"").unwrap();
+        let reply = bob.encrypt_header_encrypted(b"reply", b"").unwrap();
+        alice.decrypt_header_encrypted(&reply, b"").unwrap();
+
+        for ratchet in [&alice, &bob] {
+            let keys = ratchet.secret_keys();
+            assert_eq!(keys.len(), 7);
+            let debug = format!("{:?}", ratchet);
+            assert!(keys.iter().all(|key| !leaks(&debug, key)));
+        }
+
+        let (_ciphertext, pointer) = encrypt_attachment(b"attachment").unwrap();
+        assert!(!leaks(&format!("{:?}", pointer), &pointer.key));
+
+        let code = RecoveryCode::generate();
+        assert!(!format!("{:?}", code).contains(code.as_str()));
+    }
+}
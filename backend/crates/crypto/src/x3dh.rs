@@ -5,6 +5,11 @@
 /// Key conversion: Ed25519 identity keys are converted to X25519 for DH operations
 /// using the birational equivalence between twisted Edwards curve (Ed25519) and
 /// Montgomery curve (Curve25519/X25519). This is the same approach used by Signal Protocol.
+///
+/// PQXDH: bundles with version [`BUNDLE_VERSION_PQXDH`] additionally carry signed
+/// ML-KEM-768 prekeys. The initiator encapsulates to one of them and the KEM shared
+/// secret is mixed into the key derivation together with the DH outputs.
+use crate::pq_kem::{self, KemPreKey, KemPreKeyPublic};
 use crate::{CryptoError, Result};
 use curve25519_dalek::scalar::clamp_integer;
 use ed25519_dalek::{SigningKey, VerifyingKey, Signature, Signer, Verifier};
@@ -13,9 +18,13 @@ use rand::rngs::OsRng;
 use serde::{Deserialize, Serialize};
 use hkdf::Hkdf;
 use sha2::Sha256;
+use std::fmt;
+use zeroize::Zeroizing;
 
 /// Identity key pair (Ed25519 for signing)
-#[derive(Debug, Clone)]
+///
+/// The signing key wipes itself on drop and is left out of `Debug` output.
+#[derive(Clone)]
 pub struct IdentityKeyPair {
     pub public: VerifyingKey,
     secret: SigningKey,
@@ -62,6 +71,26 @@ impl IdentityKeyPair {
         self.public.to_bytes().to_vec()
     }
 
+    /// Export the secret key seed for persistent storage
+    ///
+    /// The returned bytes are wiped from memory when dropped.
+    pub fn secret_bytes(&self) -> Zeroizing<[u8; 32]> {
+        Zeroizing::new(self.secret.to_bytes())
+    }
+
+    /// Restore an identity key pair from its secret key seed
+    pub fn from_secret_bytes(bytes: &[u8]) -> Result<Self> {
+        let seed: Zeroizing<[u8; 32]> = Zeroizing::new(
+            bytes
+                .try_into()
+                .map_err(|_| CryptoError::InvalidKey("Invalid identity secret key length".into()))?,
+        );
+        let secret = SigningKey::from_bytes(&seed);
+        let public = secret.verifying_key();
+
+        Ok(Self { public, secret })
+    }
+
     /// Convert Ed25519 public key to X25519 for Diffie-Hellman operations.
     ///
     /// Uses birational equivalence mapping between twisted Edwards curve (Ed25519)
@@ -91,6 +120,14 @@ impl IdentityKeyPair {
     }
 }
 
+impl fmt::Debug for IdentityKeyPair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IdentityKeyPair")
+            .field("public", &self.public)
+            .finish_non_exhaustive()
+    }
+}
+
 /// Signed pre-key (X25519 for DH, signed with Ed25519)
 #[derive(Clone)]
 pub struct SignedPreKey {
@@ -130,9 +167,44 @@ impl SignedPreKey {
     }
 
     /// Perform Diffie-Hellman with another X25519 public key
-    pub fn dh(&self, other_public: &X25519PublicKey) -> Vec<u8> {
+    pub fn dh(&self, other_public: &X25519PublicKey) -> Zeroizing<Vec<u8>> {
         let shared = self.secret.diffie_hellman(other_public);
-        shared.as_bytes().to_vec()
+        Zeroizing::new(shared.as_bytes().to_vec())
+    }
+
+    /// Serialize the full signed pre-key (including secret) for persistent storage
+    ///
+    /// Format: key_id (u32 BE) || secret (32) || timestamp (i64 BE) || signature
+    pub fn to_bytes(&self) -> Zeroizing<Vec<u8>> {
+        let mut bytes = Zeroizing::new(Vec::with_capacity(44 + self.signature.len()));
+        bytes.extend_from_slice(&self.key_id.to_be_bytes());
+        bytes.extend_from_slice(self.secret.as_bytes());
+        bytes.extend_from_slice(&self.timestamp.to_be_bytes());
+        bytes.extend_from_slice(&self.signature);
+        bytes
+    }
+
+    /// Restore a signed pre-key serialized with [`SignedPreKey::to_bytes`]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 44 {
+            return Err(CryptoError::InvalidKey("Signed pre-key data too short".into()));
+        }
+
+        let key_id = u32::from_be_bytes(bytes[0..4].try_into().unwrap());
+        let mut secret_bytes = Zeroizing::new([0u8; 32]);
+        secret_bytes.copy_from_slice(&bytes[4..36]);
+        let secret = StaticSecret::from(*secret_bytes);
+        let public = X25519PublicKey::from(&secret);
+        let timestamp = i64::from_be_bytes(bytes[36..44].try_into().unwrap());
+        let signature = bytes[44..].to_vec();
+
+        Ok(Self {
+            key_id,
+            public,
+            secret,
+            signature,
+            timestamp,
+        })
     }
 }
 
@@ -162,21 +234,94 @@ impl OneTimePreKey {
         self.public.as_bytes().to_vec()
     }
 
+    /// The secret key, for tests
+    #[cfg(test)]
+    pub(crate) fn secret_bytes(&self) -> Zeroizing<[u8; 32]> {
+        Zeroizing::new(self.secret.to_bytes())
+    }
+
+    /// Restore a one-time pre-key from its 32 secret key bytes
+    pub fn from_secret_bytes(key_id: u32, bytes: &[u8]) -> Result<Self> {
+        let secret_bytes: Zeroizing<[u8; 32]> = Zeroizing::new(
+            bytes
+                .try_into()
+                .map_err(|_| CryptoError::InvalidKey("Invalid one-time pre-key length".into()))?,
+        );
+        let secret = StaticSecret::from(*secret_bytes);
+        let public = X25519PublicKey::from(&secret);
+
+        Ok(Self { key_id, public, secret })
+    }
+
     /// Perform Diffie-Hellman
-    pub fn dh(&self, other_public: &X25519PublicKey) -> Vec<u8> {
+    pub fn dh(&self, other_public: &X25519PublicKey) -> Zeroizing<Vec<u8>> {
         let shared = self.secret.diffie_hellman(other_public);
-        shared.as_bytes().to_vec()
+        Zeroizing::new(shared.as_bytes().to_vec())
+    }
+}
+
+impl fmt::Debug for SignedPreKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SignedPreKey")
+            .field("key_id", &self.key_id)
+            .field("public", &self.public)
+            .field("timestamp", &self.timestamp)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Debug for OneTimePreKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OneTimePreKey")
+            .field("key_id", &self.key_id)
+            .field("public", &self.public)
+            .finish_non_exhaustive()
     }
 }
 
+/// Key bundle version for classic X3DH (X25519 only)
+pub const BUNDLE_VERSION_X3DH: u32 = 1;
+
+/// Key bundle version for hybrid PQXDH (X25519 + ML-KEM-768)
+pub const BUNDLE_VERSION_PQXDH: u32 = 2;
+
+/// How often a device should replace its signed pre-key (7 days)
+pub const SIGNED_PRE_KEY_ROTATION_INTERVAL_SECS: i64 = 7 * 24 * 3600;
+
+/// How long a replaced signed pre-key still accepts key exchanges (30 days),
+/// so initiators holding an older bundle can still reach the device
+pub const SIGNED_PRE_KEY_GRACE_PERIOD_SECS: i64 = 30 * 24 * 3600;
+
+/// HKDF info for classic X3DH
+const X3DH_INFO: &[u8] = b"X3DH";
+
+/// HKDF info for PQXDH
+const PQXDH_INFO: &[u8] = b"PQXDH_X25519_SHA256_MLKEM768";
+
+fn default_bundle_version() -> u32 {
+    BUNDLE_VERSION_X3DH
+}
+
 /// Key bundle for publishing to server
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct X3DHKeyBundle {
+    /// Protocol version, selects X3DH or PQXDH (bundles without it are X3DH)
+    #[serde(default = "default_bundle_version")]
+    pub version: u32,
     pub identity_key: Vec<u8>,           // Ed25519 public key
     pub signed_pre_key: Vec<u8>,         // X25519 public key
     pub signed_pre_key_id: u32,
     pub signed_pre_key_signature: Vec<u8>,
     pub one_time_pre_keys: Vec<OneTimePreKeyPublic>,
+    /// Signed last-resort X25519 prekey, used once the one-time pre-keys run out
+    #[serde(default)]
+    pub last_resort_pre_key: Option<LastResortPreKeyPublic>,
+    /// Signed last-resort ML-KEM-768 prekey (PQXDH only)
+    #[serde(default)]
+    pub kem_pre_key: Option<KemPreKeyPublic>,
+    /// One-time ML-KEM-768 prekeys (PQXDH only)
+    #[serde(default)]
+    pub one_time_kem_pre_keys: Vec<KemPreKeyPublic>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -185,11 +330,81 @@ pub struct OneTimePreKeyPublic {
     pub public_key: Vec<u8>,
 }
 
+/// Public part of the last-resort pre-key as published in a key bundle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LastResortPreKeyPublic {
+    pub key_id: u32,
+    pub public_key: Vec<u8>,      // X25519 public key
+    pub signature: Vec<u8>,       // Ed25519 signature over public_key
+}
+
+impl LastResortPreKeyPublic {
+    /// Verify the prekey signature against the owner's Ed25519 identity key
+    pub fn verify(&self, identity_key: &[u8]) -> Result<()> {
+        if self.public_key.len() != 32 {
+            return Err(CryptoError::InvalidKey("X25519 public key must be 32 bytes".into()));
+        }
+        IdentityKeyPair::verify(identity_key, &self.public_key, &self.signature)
+    }
+}
+
+/// Pre-key used for DH4 in a key exchange
+///
+/// One-time and last-resort pre-keys are numbered independently, so the
+/// kind is needed to find the private key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PreKeyId {
+    OneTime(u32),
+    LastResort(u32),
+}
+
+/// Initial key exchange data sent by the initiator with the first message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InitialKeyExchange {
+    /// Bundle version the initiator negotiated
+    pub version: u32,
+    pub ephemeral_key: Vec<u8>,            // X25519 public key
+    /// Signed pre-key the initiator used (exchanges without it use the current one)
+    #[serde(default)]
+    pub signed_pre_key_id: Option<u32>,
+    pub one_time_pre_key_id: Option<u32>,
+    /// Last-resort pre-key used instead of a one-time pre-key
+    #[serde(default)]
+    pub last_resort_pre_key_id: Option<u32>,
+    /// KEM prekey the initiator encapsulated to (PQXDH only)
+    pub kem_pre_key_id: Option<u32>,
+    /// ML-KEM-768 ciphertext (PQXDH only)
+    pub kem_ciphertext: Option<Vec<u8>>,
+}
+
+impl InitialKeyExchange {
+    /// Pre-key the initiator used for DH4, if any
+    pub fn pre_key_id(&self) -> Result<Option<PreKeyId>> {
+        match (self.one_time_pre_key_id, self.last_resort_pre_key_id) {
+            (Some(_), Some(_)) => Err(CryptoError::Protocol(
+                "Key exchange references both a one-time and a last-resort pre-key".into(),
+            )),
+            (Some(key_id), None) => Ok(Some(PreKeyId::OneTime(key_id))),
+            (None, Some(key_id)) => Ok(Some(PreKeyId::LastResort(key_id))),
+            (None, None) => Ok(None),
+        }
+    }
+}
+
 /// Complete key material for a device
 pub struct X3DHKeyMaterial {
     pub identity_key: IdentityKeyPair,
     pub signed_pre_key: SignedPreKey,
+    /// Signed pre-key replaced by the last rotation, kept for the grace period
+    pub previous_signed_pre_key: Option<SignedPreKey>,
     pub one_time_pre_keys: Vec<OneTimePreKey>,
+    /// Signed X25519 pre-key that is never consumed, for when one-time pre-keys run out
+    pub last_resort_pre_key: SignedPreKey,
+    /// Signed last-resort KEM prekey; present for PQXDH-capable devices
+    pub kem_pre_key: Option<KemPreKey>,
+    pub one_time_kem_pre_keys: Vec<KemPreKey>,
+    /// Key id for the next generated one-time pre-key (ids are never reused)
+    next_one_time_pre_key_id: u32,
 }
 
 impl X3DHKeyMaterial {
@@ -197,6 +412,7 @@ impl X3DHKeyMaterial {
     pub fn generate(num_one_time_keys: usize) -> Result<Self> {
         let identity_key = IdentityKeyPair::generate()?;
         let signed_pre_key = SignedPreKey::generate(1, &identity_key)?;
+        let last_resort_pre_key = SignedPreKey::generate(1, &identity_key)?;
 
         let mut one_time_pre_keys = Vec::with_capacity(num_one_time_keys);
         for i in 0..num_one_time_keys {
@@ -206,13 +422,150 @@ impl X3DHKeyMaterial {
         Ok(Self {
             identity_key,
             signed_pre_key,
+            previous_signed_pre_key: None,
             one_time_pre_keys,
+            last_resort_pre_key,
+            kem_pre_key: None,
+            one_time_kem_pre_keys: Vec::new(),
+            next_one_time_pre_key_id: num_one_time_keys as u32,
         })
     }
 
+    /// Generate key material for PQXDH (X3DH keys plus signed ML-KEM-768 prekeys)
+    ///
+    /// The last-resort KEM prekey uses key id 1; one-time KEM prekeys are
+    /// numbered from 2 so ids never collide.
+    pub fn generate_pq(num_one_time_keys: usize, num_one_time_kem_keys: usize) -> Result<Self> {
+        let mut material = Self::generate(num_one_time_keys)?;
+
+        material.kem_pre_key = Some(KemPreKey::generate(1, &material.identity_key)?);
+        for i in 0..num_one_time_kem_keys {
+            material
+                .one_time_kem_pre_keys
+                .push(KemPreKey::generate(i as u32 + 2, &material.identity_key)?);
+        }
+
+        Ok(material)
+    }
+
+    /// Replace the signed pre-key, keeping the old one for the grace period
+    ///
+    /// The new key gets the next key id; publish it with the new bundle.
+    pub fn rotate_signed_pre_key(&mut self) -> Result<()> {
+        let key_id = self.signed_pre_key.key_id.wrapping_add(1);
+        let signed_pre_key = SignedPreKey::generate(key_id, &self.identity_key)?;
+        self.previous_signed_pre_key = Some(std::mem::replace(&mut self.signed_pre_key, signed_pre_key));
+        Ok(())
+    }
+
+    /// Whether the signed pre-key is older than the rotation interval
+    pub fn signed_pre_key_rotation_due(&self, now: i64) -> bool {
+        now - self.signed_pre_key.timestamp >= SIGNED_PRE_KEY_ROTATION_INTERVAL_SECS
+    }
+
+    /// Forget the previous signed pre-key once its grace period is over
+    pub fn expire_previous_signed_pre_key(&mut self, now: i64) {
+        if now >= self.previous_signed_pre_key_valid_until() {
+            self.previous_signed_pre_key = None;
+        }
+    }
+
+    /// Time until which the previous signed pre-key is accepted
+    ///
+    /// The grace period starts when the current key replaced it.
+    fn previous_signed_pre_key_valid_until(&self) -> i64 {
+        self.signed_pre_key.timestamp + SIGNED_PRE_KEY_GRACE_PERIOD_SECS
+    }
+
+    /// Find the signed pre-key an initiator used
+    fn signed_pre_key_for_exchange(&self, key_id: Option<u32>) -> Result<&SignedPreKey> {
+        let key_id = match key_id {
+            Some(key_id) => key_id,
+            None => return Ok(&self.signed_pre_key),
+        };
+
+        if key_id == self.signed_pre_key.key_id {
+            return Ok(&self.signed_pre_key);
+        }
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        match &self.previous_signed_pre_key {
+            Some(previous) if previous.key_id == key_id && now < self.previous_signed_pre_key_valid_until() => {
+                Ok(previous)
+            }
+            _ => Err(CryptoError::InvalidKey(format!("Unknown or expired signed pre-key: {}", key_id))),
+        }
+    }
+
+    /// Generate more one-time pre-keys, continuing the key ids
+    ///
+    /// Returns the public keys to upload.
+    pub fn generate_one_time_pre_keys(&mut self, count: usize) -> Vec<OneTimePreKeyPublic> {
+        let mut public_keys = Vec::with_capacity(count);
+
+        for _ in 0..count {
+            let key = OneTimePreKey::generate(self.next_one_time_pre_key_id);
+            self.next_one_time_pre_key_id = self.next_one_time_pre_key_id.wrapping_add(1);
+
+            public_keys.push(OneTimePreKeyPublic {
+                key_id: key.key_id,
+                public_key: key.public_bytes(),
+            });
+            self.one_time_pre_keys.push(key);
+        }
+
+        public_keys
+    }
+
+    /// Delete a one-time pre-key after it was used in a key exchange
+    ///
+    /// Returns `false` if no key with this id exists.
+    pub fn remove_one_time_pre_key(&mut self, key_id: u32) -> bool {
+        let count = self.one_time_pre_keys.len();
+        self.one_time_pre_keys.retain(|key| key.key_id != key_id);
+        self.one_time_pre_keys.len() != count
+    }
+
+    /// Diffie-Hellman with the pre-key an initiator used for DH4
+    ///
+    /// Unknown key ids are errors, so a mismatch never silently yields a
+    /// different shared secret.
+    fn pre_key_dh(
+        &self,
+        pre_key: Option<PreKeyId>,
+        peer_ephemeral: &X25519PublicKey,
+    ) -> Result<Option<Zeroizing<Vec<u8>>>> {
+        match pre_key {
+            None => Ok(None),
+            Some(PreKeyId::OneTime(key_id)) => self
+                .one_time_pre_keys
+                .iter()
+                .find(|k| k.key_id == key_id)
+                .map(|otk| Some(otk.dh(peer_ephemeral)))
+                .ok_or_else(|| CryptoError::InvalidKey(format!("Unknown one-time pre-key: {}", key_id))),
+            Some(PreKeyId::LastResort(key_id)) if key_id == self.last_resort_pre_key.key_id => {
+                Ok(Some(self.last_resort_pre_key.dh(peer_ephemeral)))
+            }
+            Some(PreKeyId::LastResort(key_id)) => {
+                Err(CryptoError::InvalidKey(format!("Unknown last-resort pre-key: {}", key_id)))
+            }
+        }
+    }
+
     /// Export public key bundle for publishing
     pub fn export_bundle(&self) -> X3DHKeyBundle {
+        let version = if self.kem_pre_key.is_some() {
+            BUNDLE_VERSION_PQXDH
+        } else {
+            BUNDLE_VERSION_X3DH
+        };
+
         X3DHKeyBundle {
+            version,
             identity_key: self.identity_key.public_bytes(),
             signed_pre_key: self.signed_pre_key.public_bytes(),
             signed_pre_key_id: self.signed_pre_key.key_id,
@@ -223,6 +576,13 @@ impl X3DHKeyMaterial {
                     public_key: key.public_bytes(),
                 }
             }).collect(),
+            last_resort_pre_key: Some(LastResortPreKeyPublic {
+                key_id: self.last_resort_pre_key.key_id,
+                public_key: self.last_resort_pre_key.public_bytes(),
+                signature: self.last_resort_pre_key.signature.clone(),
+            }),
+            kem_pre_key: self.kem_pre_key.as_ref().map(KemPreKey::public),
+            one_time_kem_pre_keys: self.one_time_kem_pre_keys.iter().map(KemPreKey::public).collect(),
         }
     }
 }
@@ -239,17 +599,73 @@ impl X3DHProtocol {
 
     /// Perform 4-DH key agreement as initiator (Alice)
     ///
+    /// Classic X3DH only. PQXDH bundles are rejected because the KEM ciphertext
+    /// has to reach the peer; use [`X3DHProtocol::initiate_session`] instead.
+    ///
     /// Inputs:
     /// - local_identity: Alice's long-term identity key pair (Ed25519, converted to X25519 for DH)
     /// - peer_bundle: Bob's public key bundle
-    /// - use_one_time_key: Whether to use a one-time pre-key (if available)
+    /// - use_one_time_key: Whether to use a one-time pre-key (falls back to the
+    ///   last-resort pre-key if none is left)
     ///
-    /// Returns: (32-byte shared secret, ephemeral public key to send to peer)
+    /// Returns: (32-byte shared secret, ephemeral public key to send to peer,
+    /// pre-key used for DH4 to send along)
     pub fn initiate_key_agreement(
         local_identity: &IdentityKeyPair,
         peer_bundle: &X3DHKeyBundle,
         use_one_time_key: bool,
-    ) -> Result<(Vec<u8>, X25519PublicKey)> {
+    ) -> Result<(Zeroizing<Vec<u8>>, X25519PublicKey, Option<PreKeyId>)> {
+        if peer_bundle.version != BUNDLE_VERSION_X3DH {
+            return Err(CryptoError::Protocol(format!(
+                "Key bundle version {} requires initiate_session",
+                peer_bundle.version
+            )));
+        }
+
+        let (shared_secret, exchange) =
+            Self::initiate_session(local_identity, peer_bundle, use_one_time_key)?;
+        let ephemeral_public = x25519_public_from_bytes(&exchange.ephemeral_key)?;
+
+        Ok((shared_secret, ephemeral_public, exchange.pre_key_id()?))
+    }
+
+    /// Perform key agreement as initiator, negotiating X3DH or PQXDH
+    ///
+    /// The mode is selected by the bundle version. For PQXDH the initiator
+    /// encapsulates to a one-time KEM prekey if available (and allowed by
+    /// `use_one_time_key`), otherwise to the signed last-resort KEM prekey.
+    /// DH4 likewise uses a one-time pre-key, or the signed last-resort pre-key
+    /// once they have run out; the exchange records which one.
+    ///
+    /// Inputs:
+    /// - local_identity: Alice's long-term identity key pair
+    /// - peer_bundle: Bob's public key bundle
+    /// - use_one_time_key: Whether to use one-time prekeys (if available)
+    ///
+    /// Returns: (32-byte shared secret, key exchange data to send to peer)
+    pub fn initiate_session(
+        local_identity: &IdentityKeyPair,
+        peer_bundle: &X3DHKeyBundle,
+        use_one_time_key: bool,
+    ) -> Result<(Zeroizing<Vec<u8>>, InitialKeyExchange)> {
+        let ephemeral_secret = StaticSecret::random_from_rng(OsRng);
+        Self::initiate_session_with_ephemeral(local_identity, peer_bundle, use_one_time_key, ephemeral_secret)
+    }
+
+    /// [`X3DHProtocol::initiate_session`] with a given ephemeral key (for known-answer tests)
+    fn initiate_session_with_ephemeral(
+        local_identity: &IdentityKeyPair,
+        peer_bundle: &X3DHKeyBundle,
+        use_one_time_key: bool,
+        ephemeral_secret: StaticSecret,
+    ) -> Result<(Zeroizing<Vec<u8>>, InitialKeyExchange)> {
+        if peer_bundle.version != BUNDLE_VERSION_X3DH && peer_bundle.version != BUNDLE_VERSION_PQXDH {
+            return Err(CryptoError::Protocol(format!(
+                "Unsupported key bundle version: {}",
+                peer_bundle.version
+            )));
+        }
+
         // Convert peer's Ed25519 identity key to X25519 for DH
         let peer_identity = ed25519_public_to_x25519(&peer_bundle.identity_key)?;
         let peer_signed_pre_key = x25519_public_from_bytes(&peer_bundle.signed_pre_key)?;
@@ -261,11 +677,23 @@ impl X3DHProtocol {
             &peer_bundle.signed_pre_key_signature,
         )?;
 
+        // Select and verify the KEM prekey before doing any DH work
+        let kem_pre_key = if peer_bundle.version == BUNDLE_VERSION_PQXDH {
+            let kem_pre_key = match peer_bundle.one_time_kem_pre_keys.first() {
+                Some(key) if use_one_time_key => key,
+                _ => peer_bundle.kem_pre_key.as_ref().ok_or_else(|| {
+                    CryptoError::Protocol("PQXDH bundle is missing a KEM prekey".into())
+                })?,
+            };
+            kem_pre_key.verify(&peer_bundle.identity_key)?;
+            Some(kem_pre_key)
+        } else {
+            None
+        };
+
         // Convert local Ed25519 identity key to X25519 for DH operations
         let local_identity_x25519 = local_identity.to_x25519_secret();
 
-        // Generate ephemeral key for this exchange
-        let ephemeral_secret = StaticSecret::random_from_rng(OsRng);
         let ephemeral_public = X25519PublicKey::from(&ephemeral_secret);
 
         // Perform 4-DH:
@@ -279,24 +707,54 @@ impl X3DHProtocol {
         let dh3 = ephemeral_secret.diffie_hellman(&peer_signed_pre_key);
 
         // Optional DH4 = DH(EK_A, OPK_B)
-        let mut dh_outputs: Vec<Vec<u8>> = vec![
-            dh1.as_bytes().to_vec(),
-            dh2.as_bytes().to_vec(),
-            dh3.as_bytes().to_vec(),
+        let mut dh_outputs: Vec<Zeroizing<Vec<u8>>> = vec![
+            Zeroizing::new(dh1.as_bytes().to_vec()),
+            Zeroizing::new(dh2.as_bytes().to_vec()),
+            Zeroizing::new(dh3.as_bytes().to_vec()),
         ];
 
-        if use_one_time_key && !peer_bundle.one_time_pre_keys.is_empty() {
-            let peer_one_time_key = x25519_public_from_bytes(
-                &peer_bundle.one_time_pre_keys[0].public_key
-            )?;
-            let dh4 = ephemeral_secret.diffie_hellman(&peer_one_time_key);
-            dh_outputs.push(dh4.as_bytes().to_vec());
+        let mut one_time_pre_key_id = None;
+        let mut last_resort_pre_key_id = None;
+        if use_one_time_key {
+            if let Some(one_time_key) = peer_bundle.one_time_pre_keys.first() {
+                let peer_one_time_key = x25519_public_from_bytes(&one_time_key.public_key)?;
+                let dh4 = ephemeral_secret.diffie_hellman(&peer_one_time_key);
+                dh_outputs.push(Zeroizing::new(dh4.as_bytes().to_vec()));
+                one_time_pre_key_id = Some(one_time_key.key_id);
+            } else if let Some(last_resort_key) = &peer_bundle.last_resort_pre_key {
+                last_resort_key.verify(&peer_bundle.identity_key)?;
+                let peer_last_resort_key = x25519_public_from_bytes(&last_resort_key.public_key)?;
+                let dh4 = ephemeral_secret.diffie_hellman(&peer_last_resort_key);
+                dh_outputs.push(Zeroizing::new(dh4.as_bytes().to_vec()));
+                last_resort_pre_key_id = Some(last_resort_key.key_id);
+            }
         }
 
+        let mut exchange = InitialKeyExchange {
+            version: peer_bundle.version,
+            ephemeral_key: ephemeral_public.as_bytes().to_vec(),
+            signed_pre_key_id: Some(peer_bundle.signed_pre_key_id),
+            one_time_pre_key_id,
+            last_resort_pre_key_id,
+            kem_pre_key_id: None,
+            kem_ciphertext: None,
+        };
+
         // Derive shared secret using HKDF-SHA256
-        let shared_secret = derive_shared_secret(&dh_outputs)?;
+        let shared_secret = match kem_pre_key {
+            Some(kem_pre_key) => {
+                // SS = KEM-ENC(PQPK_B), appended after the DH outputs
+                let (ciphertext, kem_secret) = pq_kem::encapsulate(&kem_pre_key.public_key)?;
+                dh_outputs.push(kem_secret);
+                exchange.kem_pre_key_id = Some(kem_pre_key.key_id);
+                exchange.kem_ciphertext = Some(ciphertext);
+
+                derive_shared_secret(&dh_outputs, PQXDH_INFO)?
+            }
+            None => derive_shared_secret(&dh_outputs, X3DH_INFO)?,
+        };
 
-        Ok((shared_secret, ephemeral_public))
+        Ok((shared_secret, exchange))
     }
 
     /// Perform 4-DH key agreement as responder (Bob)
@@ -305,15 +763,15 @@ impl X3DHProtocol {
     /// - key_material: Bob's key material (identity, signed pre-key, one-time keys)
     /// - peer_identity_bytes: Alice's identity public key (Ed25519 format)
     /// - peer_ephemeral_bytes: Alice's ephemeral public key (X25519 format)
-    /// - one_time_key_id: Which one-time key was used (if any)
+    /// - pre_key: Which one-time or last-resort pre-key was used (if any)
     ///
     /// Returns: 32-byte shared secret
     pub fn respond_key_agreement(
         key_material: &X3DHKeyMaterial,
         peer_identity_bytes: &[u8],
         peer_ephemeral_bytes: &[u8],
-        one_time_key_id: Option<u32>,
-    ) -> Result<Vec<u8>> {
+        pre_key: Option<PreKeyId>,
+    ) -> Result<Zeroizing<Vec<u8>>> {
         // Convert peer's Ed25519 identity key to X25519 for DH
         let peer_identity_x25519 = ed25519_public_to_x25519(peer_identity_bytes)?;
         let peer_ephemeral = x25519_public_from_bytes(peer_ephemeral_bytes)?;
@@ -326,26 +784,104 @@ impl X3DHProtocol {
         let dh1 = key_material.signed_pre_key.dh(&peer_identity_x25519);
 
         // DH2 = DH(IK_B, EK_A) - Bob's identity (converted to X25519) with Alice's ephemeral
-        let dh2_bytes = local_identity_x25519.diffie_hellman(&peer_ephemeral).as_bytes().to_vec();
+        let dh2_bytes = Zeroizing::new(local_identity_x25519.diffie_hellman(&peer_ephemeral).as_bytes().to_vec());
 
         // DH3 = DH(SPK_B, EK_A)
         let dh3 = key_material.signed_pre_key.dh(&peer_ephemeral);
 
-        let mut dh_outputs: Vec<Vec<u8>> = vec![
+        let mut dh_outputs: Vec<Zeroizing<Vec<u8>>> = vec![
             dh1,
             dh2_bytes,
             dh3,
         ];
 
-        // Optional DH4 with one-time key
-        if let Some(key_id) = one_time_key_id {
-            if let Some(otk) = key_material.one_time_pre_keys.iter().find(|k| k.key_id == key_id) {
-                let dh4 = otk.dh(&peer_ephemeral);
-                dh_outputs.push(dh4);
+        // Optional DH4 with one-time or last-resort key
+        if let Some(dh4) = key_material.pre_key_dh(pre_key, &peer_ephemeral)? {
+            dh_outputs.push(dh4);
+        }
+
+        derive_shared_secret(&dh_outputs, X3DH_INFO)
+    }
+
+    /// Perform key agreement as responder for an [`InitialKeyExchange`]
+    ///
+    /// Devices holding a KEM prekey only accept PQXDH, so a classic X3DH
+    /// exchange cannot be used to downgrade them. Referenced one-time prekeys
+    /// must exist; the caller is responsible for deleting them afterwards
+    /// (the last-resort pre-key is kept).
+    /// Exchanges made with the previous signed pre-key are accepted until its
+    /// grace period ends.
+    ///
+    /// Inputs:
+    /// - key_material: Bob's key material
+    /// - peer_identity_bytes: Alice's identity public key (Ed25519 format)
+    /// - exchange: Key exchange data received from Alice
+    ///
+    /// Returns: 32-byte shared secret
+    pub fn respond_session(
+        key_material: &X3DHKeyMaterial,
+        peer_identity_bytes: &[u8],
+        exchange: &InitialKeyExchange,
+    ) -> Result<Zeroizing<Vec<u8>>> {
+        match exchange.version {
+            BUNDLE_VERSION_X3DH if key_material.kem_pre_key.is_some() => {
+                return Err(CryptoError::Protocol(
+                    "PQXDH required: refusing classic X3DH key exchange".into(),
+                ));
+            }
+            BUNDLE_VERSION_X3DH | BUNDLE_VERSION_PQXDH => {}
+            version => {
+                return Err(CryptoError::Protocol(format!(
+                    "Unsupported key exchange version: {}",
+                    version
+                )));
             }
         }
 
-        derive_shared_secret(&dh_outputs)
+        let signed_pre_key = key_material.signed_pre_key_for_exchange(exchange.signed_pre_key_id)?;
+        let peer_ephemeral = x25519_public_from_bytes(&exchange.ephemeral_key)?;
+        let dh4 = key_material.pre_key_dh(exchange.pre_key_id()?, &peer_ephemeral)?;
+
+        // Decapsulate before DH so a bad ciphertext fails early
+        let kem_secret = if exchange.version == BUNDLE_VERSION_PQXDH {
+            let (key_id, ciphertext) = exchange
+                .kem_pre_key_id
+                .zip(exchange.kem_ciphertext.as_ref())
+                .ok_or_else(|| CryptoError::Protocol("PQXDH key exchange is missing the KEM ciphertext".into()))?;
+
+            let kem_pre_key = key_material
+                .one_time_kem_pre_keys
+                .iter()
+                .chain(key_material.kem_pre_key.as_ref())
+                .find(|k| k.key_id == key_id)
+                .ok_or_else(|| CryptoError::InvalidKey(format!("Unknown KEM prekey: {}", key_id)))?;
+
+            Some(kem_pre_key.decapsulate(ciphertext)?)
+        } else {
+            None
+        };
+
+        // DH part mirrors respond_key_agreement, then the KEM secret is mixed in
+        let peer_identity_x25519 = ed25519_public_to_x25519(peer_identity_bytes)?;
+        let local_identity_x25519 = key_material.identity_key.to_x25519_secret();
+
+        let mut dh_outputs: Vec<Zeroizing<Vec<u8>>> = vec![
+            signed_pre_key.dh(&peer_identity_x25519),
+            Zeroizing::new(local_identity_x25519.diffie_hellman(&peer_ephemeral).as_bytes().to_vec()),
+            signed_pre_key.dh(&peer_ephemeral),
+        ];
+
+        if let Some(dh4) = dh4 {
+            dh_outputs.push(dh4);
+        }
+
+        match kem_secret {
+            Some(kem_secret) => {
+                dh_outputs.push(kem_secret);
+                derive_shared_secret(&dh_outputs, PQXDH_INFO)
+            }
+            None => derive_shared_secret(&dh_outputs, X3DH_INFO),
+        }
     }
 }
 
@@ -377,21 +913,24 @@ fn x25519_public_from_bytes(bytes: &[u8]) -> Result<X25519PublicKey> {
     Ok(X25519PublicKey::from(key_bytes))
 }
 
-/// Helper: Derive shared secret from DH outputs using HKDF
-fn derive_shared_secret(dh_outputs: &[Vec<u8>]) -> Result<Vec<u8>> {
+/// Helper: Derive shared secret from DH outputs (and KEM secret for PQXDH) using HKDF
+///
+/// `info` separates X3DH and PQXDH so the same DH outputs never yield the
+/// same key under both protocols.
+fn derive_shared_secret(dh_outputs: &[Zeroizing<Vec<u8>>], info: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
     // Concatenate all DH outputs
-    let mut concat = Vec::new();
+    let mut concat = Zeroizing::new(Vec::new());
     for output in dh_outputs {
         concat.extend_from_slice(output);
     }
 
     // Use HKDF-SHA256 to derive 32-byte shared secret
     let hk = Hkdf::<Sha256>::new(None, &concat);
-    let mut okm = [0u8; 32];
-    hk.expand(b"X3DH", &mut okm)
+    let mut okm = Zeroizing::new(vec![0u8; 32]);
+    hk.expand(info, &mut okm)
         .map_err(|e| CryptoError::Protocol(format!("HKDF expansion failed: {}", e)))?;
 
-    Ok(okm.to_vec())
+    Ok(okm)
 }
 
 #[cfg(test)]
@@ -450,20 +989,21 @@ mod tests {
         let bob_bundle = bob_material.export_bundle();
 
         // Alice initiates key agreement with Bob's bundle
-        let (alice_shared_secret, alice_ephemeral) = X3DHProtocol::initiate_key_agreement(
+        let (alice_shared_secret, alice_ephemeral, pre_key) = X3DHProtocol::initiate_key_agreement(
             &alice_material.identity_key,
             &bob_bundle,
             true,
         ).unwrap();
 
         assert_eq!(alice_shared_secret.len(), 32);
+        assert_eq!(pre_key, Some(PreKeyId::OneTime(0))); // Using first one-time prekey
 
         // Bob responds to complete the key agreement
         let bob_shared_secret = X3DHProtocol::respond_key_agreement(
             &bob_material,
             &alice_material.identity_key.public_bytes(),
             alice_ephemeral.as_bytes(),
-            Some(0), // Using first one-time prekey
+            pre_key,
         ).unwrap();
 
         assert_eq!(bob_shared_secret.len(), 32);
@@ -494,11 +1034,12 @@ mod tests {
         let bob_bundle = bob_material.export_bundle();
 
         // Alice initiates without one-time key
-        let (alice_shared_secret, alice_ephemeral) = X3DHProtocol::initiate_key_agreement(
+        let (alice_shared_secret, alice_ephemeral, pre_key) = X3DHProtocol::initiate_key_agreement(
             &alice_material.identity_key,
             &bob_bundle,
             false, // No one-time key
         ).unwrap();
+        assert_eq!(pre_key, None);
 
         // Bob responds
         let bob_shared_secret = X3DHProtocol::respond_key_agreement(
@@ -510,4 +1051,380 @@ mod tests {
 
         assert_eq!(alice_shared_secret, bob_shared_secret);
     }
+
+    #[test]
+    fn test_x3dh_falls_back_to_last_resort_pre_key() {
+        let alice_material = X3DHKeyMaterial::generate(0).unwrap();
+        let bob_material = X3DHKeyMaterial::generate(0).unwrap();
+        let bob_bundle = bob_material.export_bundle();
+
+        let (alice_shared_secret, alice_ephemeral, pre_key) = X3DHProtocol::initiate_key_agreement(
+            &alice_material.identity_key,
+            &bob_bundle,
+            true,
+        ).unwrap();
+        assert_eq!(pre_key, Some(PreKeyId::LastResort(bob_material.last_resort_pre_key.key_id)));
+
+        let bob_shared_secret = X3DHProtocol::respond_key_agreement(
+            &bob_material,
+            &alice_material.identity_key.public_bytes(),
+            alice_ephemeral.as_bytes(),
+            pre_key,
+        ).unwrap();
+        assert_eq!(alice_shared_secret, bob_shared_secret);
+
+        // Without DH4 the secrets must differ
+        let three_dh_secret = X3DHProtocol::respond_key_agreement(
+            &bob_material,
+            &alice_material.identity_key.public_bytes(),
+            alice_ephemeral.as_bytes(),
+            None,
+        ).unwrap();
+        assert_ne!(alice_shared_secret, three_dh_secret);
+    }
+
+    #[test]
+    fn test_last_resort_pre_key_is_not_consumed() {
+        let alice_identity = IdentityKeyPair::generate().unwrap();
+        let mut bob_material = X3DHKeyMaterial::generate(1).unwrap();
+
+        // First exchange takes the one-time pre-key, which Bob then deletes
+        let (alice_secret, exchange) =
+            X3DHProtocol::initiate_session(&alice_identity, &bob_material.export_bundle(), true).unwrap();
+        assert_eq!(exchange.pre_key_id().unwrap(), Some(PreKeyId::OneTime(0)));
+        let bob_secret =
+            X3DHProtocol::respond_session(&bob_material, &alice_identity.public_bytes(), &exchange).unwrap();
+        assert_eq!(alice_secret, bob_secret);
+        assert!(bob_material.remove_one_time_pre_key(0));
+
+        // Further exchanges use the last-resort pre-key, any number of times
+        for _ in 0..2 {
+            let (alice_secret, exchange) =
+                X3DHProtocol::initiate_session(&alice_identity, &bob_material.export_bundle(), true).unwrap();
+            assert_eq!(exchange.one_time_pre_key_id, None);
+            assert_eq!(exchange.last_resort_pre_key_id, Some(bob_material.last_resort_pre_key.key_id));
+            let bob_secret =
+                X3DHProtocol::respond_session(&bob_material, &alice_identity.public_bytes(), &exchange).unwrap();
+            assert_eq!(alice_secret, bob_secret);
+        }
+    }
+
+    #[test]
+    fn test_responder_rejects_unknown_pre_key() {
+        let alice_identity = IdentityKeyPair::generate().unwrap();
+        let bob_material = X3DHKeyMaterial::generate(0).unwrap();
+
+        let (_, mut exchange) =
+            X3DHProtocol::initiate_session(&alice_identity, &bob_material.export_bundle(), true).unwrap();
+        exchange.last_resort_pre_key_id = Some(bob_material.last_resort_pre_key.key_id + 1);
+        assert!(matches!(
+            X3DHProtocol::respond_session(&bob_material, &alice_identity.public_bytes(), &exchange),
+            Err(CryptoError::InvalidKey(_))
+        ));
+
+        // Naming both kinds is ambiguous
+        exchange.last_resort_pre_key_id = Some(bob_material.last_resort_pre_key.key_id);
+        exchange.one_time_pre_key_id = Some(0);
+        assert!(matches!(
+            X3DHProtocol::respond_session(&bob_material, &alice_identity.public_bytes(), &exchange),
+            Err(CryptoError::Protocol(_))
+        ));
+    }
+
+    #[test]
+    fn test_last_resort_pre_key_signature_is_verified() {
+        let alice_identity = IdentityKeyPair::generate().unwrap();
+        let bob_material = X3DHKeyMaterial::generate(0).unwrap();
+        let mallory_material = X3DHKeyMaterial::generate(0).unwrap();
+
+        let mut bundle = bob_material.export_bundle();
+        bundle.last_resort_pre_key = mallory_material.export_bundle().last_resort_pre_key;
+
+        let result = X3DHProtocol::initiate_session(&alice_identity, &bundle, true);
+        assert!(matches!(result, Err(CryptoError::InvalidSignature(_))));
+    }
+
+    #[test]
+    fn test_signed_pre_key_rotation_grace_period() {
+        let alice_identity = IdentityKeyPair::generate().unwrap();
+        let mut bob_material = X3DHKeyMaterial::generate(2).unwrap();
+        let old_bundle = bob_material.export_bundle();
+
+        bob_material.rotate_signed_pre_key().unwrap();
+        assert_eq!(bob_material.signed_pre_key.key_id, old_bundle.signed_pre_key_id + 1);
+
+        // An initiator holding the old bundle can still reach Bob
+        let (alice_secret, exchange) =
+            X3DHProtocol::initiate_session(&alice_identity, &old_bundle, true).unwrap();
+        assert_eq!(exchange.signed_pre_key_id, Some(old_bundle.signed_pre_key_id));
+        let bob_secret =
+            X3DHProtocol::respond_session(&bob_material, &alice_identity.public_bytes(), &exchange).unwrap();
+        assert_eq!(alice_secret, bob_secret);
+
+        // The new bundle uses the new key
+        let (alice_secret, exchange) =
+            X3DHProtocol::initiate_session(&alice_identity, &bob_material.export_bundle(), true).unwrap();
+        let bob_secret =
+            X3DHProtocol::respond_session(&bob_material, &alice_identity.public_bytes(), &exchange).unwrap();
+        assert_eq!(alice_secret, bob_secret);
+
+        // Once the grace period is over the old key is rejected
+        bob_material.signed_pre_key.timestamp -= SIGNED_PRE_KEY_GRACE_PERIOD_SECS;
+        let (_, exchange) = X3DHProtocol::initiate_session(&alice_identity, &old_bundle, false).unwrap();
+        assert!(X3DHProtocol::respond_session(&bob_material, &alice_identity.public_bytes(), &exchange).is_err());
+
+        let now = bob_material.signed_pre_key.timestamp + SIGNED_PRE_KEY_GRACE_PERIOD_SECS;
+        assert!(bob_material.signed_pre_key_rotation_due(now));
+        bob_material.expire_previous_signed_pre_key(now);
+        assert!(bob_material.previous_signed_pre_key.is_none());
+    }
+
+    #[test]
+    fn test_one_time_pre_key_ids_are_not_reused() {
+        let mut material = X3DHKeyMaterial::generate(2).unwrap();
+
+        assert!(material.remove_one_time_pre_key(0));
+        assert!(material.remove_one_time_pre_key(1));
+        assert!(!material.remove_one_time_pre_key(1));
+
+        let new_keys = material.generate_one_time_pre_keys(3);
+        let ids: Vec<u32> = new_keys.iter().map(|key| key.key_id).collect();
+        assert_eq!(ids, vec![2, 3, 4]);
+        assert_eq!(material.export_bundle().one_time_pre_keys.len(), 3);
+    }
+
+    #[test]
+    fn test_bundle_without_version_is_x3dh() {
+        let bob_material = X3DHKeyMaterial::generate(1).unwrap();
+        let mut json = serde_json::to_value(bob_material.export_bundle()).unwrap();
+        let object = json.as_object_mut().unwrap();
+        object.remove("version");
+        object.remove("kem_pre_key");
+        object.remove("one_time_kem_pre_keys");
+
+        let bundle: X3DHKeyBundle = serde_json::from_value(json).unwrap();
+        assert_eq!(bundle.version, BUNDLE_VERSION_X3DH);
+        assert!(bundle.kem_pre_key.is_none());
+    }
+
+    #[test]
+    fn test_pqxdh_key_agreement() {
+        let alice_identity = IdentityKeyPair::generate().unwrap();
+        let bob_material = X3DHKeyMaterial::generate_pq(5, 5).unwrap();
+        let bob_bundle = bob_material.export_bundle();
+        assert_eq!(bob_bundle.version, BUNDLE_VERSION_PQXDH);
+
+        let (alice_shared_secret, exchange) =
+            X3DHProtocol::initiate_session(&alice_identity, &bob_bundle, true).unwrap();
+        assert_eq!(exchange.version, BUNDLE_VERSION_PQXDH);
+        assert_eq!(exchange.one_time_pre_key_id, Some(0));
+        assert_eq!(exchange.kem_pre_key_id, Some(bob_bundle.one_time_kem_pre_keys[0].key_id));
+        assert_eq!(
+            exchange.kem_ciphertext.as_ref().map(Vec::len),
+            Some(pq_kem::ML_KEM_768_CIPHERTEXT_SIZE)
+        );
+
+        let bob_shared_secret = X3DHProtocol::respond_session(
+            &bob_material,
+            &alice_identity.public_bytes(),
+            &exchange,
+        ).unwrap();
+
+        assert_eq!(alice_shared_secret, bob_shared_secret);
+    }
+
+    #[test]
+    fn test_pqxdh_falls_back_to_last_resort_kem_key() {
+        let alice_identity = IdentityKeyPair::generate().unwrap();
+        let bob_material = X3DHKeyMaterial::generate_pq(0, 0).unwrap();
+        let bob_bundle = bob_material.export_bundle();
+
+        let (alice_shared_secret, exchange) =
+            X3DHProtocol::initiate_session(&alice_identity, &bob_bundle, true).unwrap();
+        assert_eq!(exchange.one_time_pre_key_id, None);
+        assert_eq!(exchange.kem_pre_key_id, Some(1));
+
+        let bob_shared_secret = X3DHProtocol::respond_session(
+            &bob_material,
+            &alice_identity.public_bytes(),
+            &exchange,
+        ).unwrap();
+
+        assert_eq!(alice_shared_secret, bob_shared_secret);
+    }
+
+    #[test]
+    fn test_pqxdh_secret_differs_without_kem_secret() {
+        let alice_identity = IdentityKeyPair::generate().unwrap();
+        let bob_material = X3DHKeyMaterial::generate_pq(0, 0).unwrap();
+        let bob_bundle = bob_material.export_bundle();
+
+        let (alice_shared_secret, exchange) =
+            X3DHProtocol::initiate_session(&alice_identity, &bob_bundle, false).unwrap();
+
+        // The classic responder ignores the KEM ciphertext and must not agree
+        let classic_secret = X3DHProtocol::respond_key_agreement(
+            &bob_material,
+            &alice_identity.public_bytes(),
+            &exchange.ephemeral_key,
+            None,
+        ).unwrap();
+
+        assert_ne!(alice_shared_secret, classic_secret);
+    }
+
+    #[test]
+    fn test_pqxdh_rejects_downgrade() {
+        let alice_identity = IdentityKeyPair::generate().unwrap();
+        let bob_material = X3DHKeyMaterial::generate_pq(1, 1).unwrap();
+
+        // Server strips the KEM prekeys and serves a classic bundle
+        let mut stripped_bundle = bob_material.export_bundle();
+        stripped_bundle.version = BUNDLE_VERSION_X3DH;
+        stripped_bundle.kem_pre_key = None;
+        stripped_bundle.one_time_kem_pre_keys.clear();
+
+        let (_, exchange) =
+            X3DHProtocol::initiate_session(&alice_identity, &stripped_bundle, true).unwrap();
+        let result = X3DHProtocol::respond_session(
+            &bob_material,
+            &alice_identity.public_bytes(),
+            &exchange,
+        );
+
+        assert!(matches!(result, Err(CryptoError::Protocol(_))));
+    }
+
+    #[test]
+    fn test_pqxdh_rejects_invalid_kem_signature() {
+        let alice_identity = IdentityKeyPair::generate().unwrap();
+        let bob_material = X3DHKeyMaterial::generate_pq(0, 0).unwrap();
+        let mallory_material = X3DHKeyMaterial::generate_pq(0, 0).unwrap();
+
+        // KEM prekey signed by a different identity
+        let mut bundle = bob_material.export_bundle();
+        bundle.kem_pre_key = mallory_material.export_bundle().kem_pre_key;
+
+        let result = X3DHProtocol::initiate_session(&alice_identity, &bundle, true);
+        assert!(matches!(result, Err(CryptoError::InvalidSignature(_))));
+    }
+
+    #[test]
+    fn test_initiate_key_agreement_rejects_pq_bundle() {
+        let alice_identity = IdentityKeyPair::generate().unwrap();
+        let bob_bundle = X3DHKeyMaterial::generate_pq(1, 1).unwrap().export_bundle();
+
+        let result = X3DHProtocol::initiate_key_agreement(&alice_identity, &bob_bundle, true);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_unknown_bundle_version_rejected() {
+        let alice_identity = IdentityKeyPair::generate().unwrap();
+        let mut bob_bundle = X3DHKeyMaterial::generate(1).unwrap().export_bundle();
+        bob_bundle.version = 99;
+
+        let result = X3DHProtocol::initiate_session(&alice_identity, &bob_bundle, true);
+        assert!(matches!(result, Err(CryptoError::Protocol(_))));
+    }
+
+    /// Known-answer vectors from `test-vectors/x3dh.json`, shared with the client implementations
+    #[test]
+    fn test_known_answer_vectors() {
+        let vectors: serde_json::Value =
+            serde_json::from_str(include_str!("../test-vectors/x3dh.json")).unwrap();
+        let bytes = |value: &serde_json::Value| hex::decode(value.as_str().unwrap()).unwrap();
+        let key = |value: &serde_json::Value| -> [u8; 32] { bytes(value).try_into().unwrap() };
+
+        for vector in vectors["vectors"].as_array().unwrap() {
+            let name = vector["name"].as_str().unwrap();
+
+            let alice = IdentityKeyPair::from_secret_bytes(&bytes(&vector["alice_identity_seed"])).unwrap();
+            assert_eq!(alice.public_bytes(), bytes(&vector["alice_identity_public"]), "{}", name);
+            assert_eq!(
+                alice.to_x25519_public().as_bytes().to_vec(),
+                bytes(&vector["alice_identity_x25519_public"]),
+                "{}",
+                name
+            );
+
+            // Bob's key material with the fixed keys of the vector
+            let mut bob = X3DHKeyMaterial::generate(0).unwrap();
+            bob.identity_key = IdentityKeyPair::from_secret_bytes(&bytes(&vector["bob_identity_seed"])).unwrap();
+            assert_eq!(bob.identity_key.public_bytes(), bytes(&vector["bob_identity_public"]), "{}", name);
+            assert_eq!(
+                bob.identity_key.to_x25519_public().as_bytes().to_vec(),
+                bytes(&vector["bob_identity_x25519_public"]),
+                "{}",
+                name
+            );
+
+            let mut signed_pre_key = (vector["bob_signed_pre_key_id"].as_u64().unwrap() as u32).to_be_bytes().to_vec();
+            signed_pre_key.extend_from_slice(&bytes(&vector["bob_signed_pre_key_secret"]));
+            signed_pre_key.extend_from_slice(&0i64.to_be_bytes());
+            signed_pre_key.extend_from_slice(&bytes(&vector["bob_signed_pre_key_signature"]));
+            bob.signed_pre_key = SignedPreKey::from_bytes(&signed_pre_key).unwrap();
+            assert_eq!(bob.signed_pre_key.public_bytes(), bytes(&vector["bob_signed_pre_key_public"]), "{}", name);
+            // Ed25519 signatures are deterministic
+            assert_eq!(
+                bob.identity_key.sign(&bob.signed_pre_key.public_bytes()).unwrap(),
+                bytes(&vector["bob_signed_pre_key_signature"]),
+                "{}",
+                name
+            );
+
+            let one_time_pre_key_id = vector["bob_one_time_pre_key_id"].as_u64().map(|id| id as u32);
+            if let Some(key_id) = one_time_pre_key_id {
+                let one_time_pre_key =
+                    OneTimePreKey::from_secret_bytes(key_id, &bytes(&vector["bob_one_time_pre_key_secret"])).unwrap();
+                assert_eq!(one_time_pre_key.public_bytes(), bytes(&vector["bob_one_time_pre_key_public"]), "{}", name);
+                bob.one_time_pre_keys = vec![one_time_pre_key];
+            }
+
+            // Individual DH outputs
+            let ephemeral = StaticSecret::from(key(&vector["alice_ephemeral_secret"]));
+            let signed_pre_key_public = X25519PublicKey::from(key(&vector["bob_signed_pre_key_public"]));
+            let bob_identity_x25519 = X25519PublicKey::from(key(&vector["bob_identity_x25519_public"]));
+            let dh1 = alice.to_x25519_secret().diffie_hellman(&signed_pre_key_public);
+            assert_eq!(dh1.as_bytes().to_vec(), bytes(&vector["dh1"]), "{}", name);
+            assert_eq!(ephemeral.diffie_hellman(&bob_identity_x25519).as_bytes().to_vec(), bytes(&vector["dh2"]), "{}", name);
+            assert_eq!(ephemeral.diffie_hellman(&signed_pre_key_public).as_bytes().to_vec(), bytes(&vector["dh3"]), "{}", name);
+            if !vector["dh4"].is_null() {
+                let one_time_pre_key_public = X25519PublicKey::from(key(&vector["bob_one_time_pre_key_public"]));
+                assert_eq!(
+                    ephemeral.diffie_hellman(&one_time_pre_key_public).as_bytes().to_vec(),
+                    bytes(&vector["dh4"]),
+                    "{}",
+                    name
+                );
+            }
+
+            // Both sides of the protocol arrive at the published shared secret
+            let (alice_secret, exchange) = X3DHProtocol::initiate_session_with_ephemeral(
+                &alice,
+                &bob.export_bundle(),
+                one_time_pre_key_id.is_some(),
+                ephemeral,
+            )
+            .unwrap();
+            assert_eq!(exchange.ephemeral_key, bytes(&vector["alice_ephemeral_public"]), "{}", name);
+            assert_eq!(exchange.one_time_pre_key_id, one_time_pre_key_id, "{}", name);
+            assert_eq!(alice_secret.to_vec(), bytes(&vector["shared_secret"]), "{}", name);
+
+            let bob_secret = X3DHProtocol::respond_session(&bob, &alice.public_bytes(), &exchange).unwrap();
+            assert_eq!(bob_secret.to_vec(), bytes(&vector["shared_secret"]), "{}", name);
+        }
+
+        // PQXDH mixes the KEM shared secret into the same KDF
+        let pqxdh = &vectors["pqxdh_kdf"];
+        let mut inputs: Vec<Zeroizing<Vec<u8>>> = pqxdh["dh_outputs"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|output| Zeroizing::new(bytes(output)))
+            .collect();
+        inputs.push(Zeroizing::new(bytes(&pqxdh["kem_shared_secret"])));
+        let shared_secret = derive_shared_secret(&inputs, PQXDH_INFO).unwrap();
+        assert_eq!(shared_secret.to_vec(), bytes(&pqxdh["shared_secret"]));
+    }
 }
@@ -216,6 +216,7 @@ async fn test_01_create_group() -> Result<(), Box<dyn std::error::Error>> {
         group_name: "Test Group (Empty)".to_string(),
         member_user_ids: vec![],
         mls_group_state: vec![],
+        ..Default::default(),
     });
 
     let create_response = messaging_client
@@ -269,6 +270,7 @@ async fn test_02_create_group_with_members() -> Result<(), Box<dyn std::error::E
         group_name: "Test Group with Members".to_string(),
         member_user_ids: vec![member1.user_id()?, member2.user_id()?],
         mls_group_state: vec![],
+        ..Default::default(),
     });
 
     let create_response = messaging_client
@@ -352,6 +354,7 @@ async fn test_03_group_messaging() -> Result<(), Box<dyn std::error::Error>> {
         group_name: "Messaging Test Group".to_string(),
         member_user_ids: vec![member.user_id()?],
         mls_group_state: vec![],
+        ..Default::default(),
     });
 
     let create_response = messaging_client
@@ -492,6 +495,7 @@ async fn test_04_get_groups_list() -> Result<(), Box<dyn std::error::Error>> {
             group_name: format!("Test Group {}", i),
             member_user_ids: vec![],
             mls_group_state: vec![],
+            ..Default::default(),
         });
 
         let response = messaging_client
@@ -577,6 +581,7 @@ async fn test_05_add_group_member() -> Result<(), Box<dyn std::error::Error>> {
         group_name: "Add Member Test Group".to_string(),
         member_user_ids: vec![],
         mls_group_state: vec![],
+        ..Default::default(),
     });
 
     let create_response = messaging_client
@@ -606,6 +611,7 @@ async fn test_05_add_group_member() -> Result<(), Box<dyn std::error::Error>> {
         member_user_id: new_member.user_id()?,
         member_device_id: new_member.device_id()?,
         mls_group_state: vec![],
+        ..Default::default(),
     });
 
     let add_response = messaging_client
@@ -681,6 +687,7 @@ async fn test_06_remove_group_member() -> Result<(), Box<dyn std::error::Error>>
         group_name: "Remove Member Test Group".to_string(),
         member_user_ids: vec![member.user_id()?],
         mls_group_state: vec![],
+        ..Default::default(),
     });
 
     let create_response = messaging_client
@@ -795,6 +802,7 @@ async fn test_07_leave_group() -> Result<(), Box<dyn std::error::Error>> {
         group_name: "Leave Group Test".to_string(),
         member_user_ids: vec![member.user_id()?],
         mls_group_state: vec![],
+        ..Default::default(),
     });
 
     let create_response = messaging_client
@@ -897,6 +905,7 @@ async fn test_08_get_group_by_id() -> Result<(), Box<dyn std::error::Error>> {
         group_name: group_name.to_string(),
         member_user_ids: vec![],
         mls_group_state: vec![],
+        ..Default::default(),
     });
 
     let create_response = messaging_client
@@ -991,6 +1000,7 @@ async fn test_09_full_group_flow() -> Result<(), Box<dyn std::error::Error>> {
         group_name: "Project Team".to_string(),
         member_user_ids: vec![bob.user_id()?],
         mls_group_state: vec![],
+        ..Default::default(),
     });
 
     let create_response = messaging_client
@@ -1094,6 +1104,7 @@ async fn test_09_full_group_flow() -> Result<(), Box<dyn std::error::Error>> {
         member_user_id: charlie.user_id()?,
         member_device_id: charlie.device_id()?,
         mls_group_state: vec![],
+        ..Default::default(),
     });
 
     let add_response = messaging_client
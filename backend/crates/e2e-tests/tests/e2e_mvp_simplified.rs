@@ -533,6 +533,7 @@ async fn test_05_group_chat_flow() -> Result<(), Box<dyn std::error::Error>> {
         group_name: "E2E Test Group".to_string(),
         member_user_ids: vec![user2.user_id()?, user3.user_id()?],
         mls_group_state: vec![], // Mock MLS state for MVP
+        ..Default::default(),
     });
 
     let create_response = messaging_client.create_group(create_group_request).await?.into_inner();
@@ -726,6 +727,7 @@ async fn test_07_group_member_management() -> Result<(), Box<dyn std::error::Err
         group_name: "Member Management Test".to_string(),
         member_user_ids: vec![user2.user_id()?, user3.user_id()?],
         mls_group_state: vec![], // Mock MLS state
+        ..Default::default(),
     });
 
     let create_response = messaging_client.create_group(create_group_request).await?.into_inner();
@@ -747,6 +749,7 @@ async fn test_07_group_member_management() -> Result<(), Box<dyn std::error::Err
         member_user_id: user4.user_id()?,
         member_device_id: user4.device_id()?,
         mls_group_state: vec![], // Mock MLS state
+        ..Default::default(),
     });
 
     let add_response = messaging_client.add_group_member(add_member_request).await?.into_inner();
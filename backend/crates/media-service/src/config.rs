@@ -17,6 +17,8 @@ pub struct MediaConfig {
     pub bucket_name: String,
     /// Maximum file size in bytes (default: 100MB)
     pub max_file_size_bytes: u64,
+    /// Maximum size of a user's encrypted history backup (default: 256MB)
+    pub max_backup_size_bytes: u64,
     /// Chunk size for streaming uploads/downloads (default: 1MB)
     pub chunk_size_bytes: usize,
     /// Pre-signed URL expiration in seconds (default: 3600 = 1 hour)
@@ -40,6 +42,7 @@ impl Default for MediaConfig {
             s3_secret_key: "minioadmin".to_string(),
             bucket_name: "guardyn-media".to_string(),
             max_file_size_bytes: 100 * 1024 * 1024, // 100 MB
+            max_backup_size_bytes: 256 * 1024 * 1024, // 256 MB
             chunk_size_bytes: 1024 * 1024, // 1 MB
             presigned_url_expiry_seconds: 3600, // 1 hour
             thumbnail_max_width: 256,
@@ -75,6 +78,11 @@ impl MediaConfig {
                 config.max_file_size_bytes = size;
             }
         }
+        if let Ok(val) = std::env::var("MAX_BACKUP_SIZE_BYTES") {
+            if let Ok(size) = val.parse() {
+                config.max_backup_size_bytes = size;
+            }
+        }
         if let Ok(val) = std::env::var("CHUNK_SIZE_BYTES") {
             if let Ok(size) = val.parse() {
                 config.chunk_size_bytes = size;
@@ -116,6 +124,7 @@ mod tests {
     fn test_default_config() {
         let config = MediaConfig::default();
         assert_eq!(config.max_file_size_bytes, 100 * 1024 * 1024);
+        assert_eq!(config.max_backup_size_bytes, 256 * 1024 * 1024);
         assert_eq!(config.chunk_size_bytes, 1024 * 1024);
         assert_eq!(config.presigned_url_expiry_seconds, 3600);
         assert!(config.thumbnails_enabled);
@@ -3,6 +3,8 @@
 //! Uses TiKV for storing media metadata
 
 use anyhow::Result;
+use guardyn_common::jwks::{JwkSet, JWKS_KEY};
+use guardyn_common::revocation::{Revocation, REVOCATION_PREFIX};
 use serde::{Deserialize, Serialize};
 use tikv_client::RawClient;
 use uuid::Uuid;
@@ -38,6 +40,16 @@ pub struct MediaMetadataRecord {
     pub storage_path: String,
 }
 
+/// Encrypted history backup of a user, stored in TiKV
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupRecord {
+    pub user_id: String,
+    pub size_bytes: i64,
+    pub checksum_sha256: String,
+    pub storage_path: String,
+    pub updated_at: i64,
+}
+
 impl DatabaseClient {
     /// Create a new database client
     pub async fn new(endpoints: &str) -> Result<Self> {
@@ -68,6 +80,11 @@ impl DatabaseClient {
         format!("/media/conversation/{}/{}", conversation_id, media_id)
     }
 
+    // Key format: /media/backup/<user_id>
+    fn backup_key(user_id: &str) -> String {
+        format!("/media/backup/{}", user_id)
+    }
+
     /// Store media metadata
     pub async fn store_media_metadata(&self, metadata: &MediaMetadataRecord) -> Result<()> {
         let data = serde_json::to_vec(metadata)?;
@@ -216,6 +233,51 @@ impl DatabaseClient {
         Ok((records, next_cursor))
     }
 
+    /// Store a user's backup record, replacing the previous one
+    pub async fn store_backup_record(&self, record: &BackupRecord) -> Result<()> {
+        let data = serde_json::to_vec(record)?;
+        self.client.put(Self::backup_key(&record.user_id), data).await?;
+        Ok(())
+    }
+
+    /// Get a user's backup record
+    pub async fn get_backup_record(&self, user_id: &str) -> Result<Option<BackupRecord>> {
+        if let Some(data) = self.client.get(Self::backup_key(user_id)).await? {
+            Ok(Some(serde_json::from_slice(&data)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Delete a user's backup record
+    pub async fn delete_backup_record(&self, user_id: &str) -> Result<()> {
+        self.client.delete(Self::backup_key(user_id)).await?;
+        Ok(())
+    }
+
+    /// Load active session revocations (written by auth-service)
+    pub async fn load_revocations(&self) -> Result<Vec<Revocation>> {
+        let start_key = REVOCATION_PREFIX.to_string();
+        let end_key = format!("{}{}", REVOCATION_PREFIX, '\u{FFFF}');
+
+        let pairs = self.client.scan(start_key..end_key, 10_000).await?;
+
+        Ok(pairs
+            .into_iter()
+            .filter_map(|kv| serde_json::from_slice::<Revocation>(kv.value()).ok())
+            .filter(|revocation| !revocation.is_expired())
+            .collect())
+    }
+
+    /// Load the token verification keys published by auth-service
+    pub async fn load_jwks(&self) -> Result<Option<JwkSet>> {
+        if let Some(data) = self.client.get(JWKS_KEY.to_string()).await? {
+            Ok(Some(serde_json::from_slice(&data)?))
+        } else {
+            Ok(None)
+        }
+    }
+
     /// Generate a new media ID
     pub fn generate_media_id() -> String {
         Uuid::new_v4().to_string()
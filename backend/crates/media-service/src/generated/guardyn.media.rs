@@ -297,6 +297,82 @@ pub struct ListMediaResponse {
     #[prost(message, optional, tag = "4")]
     pub error: ::core::option::Option<super::common::ErrorResponse>,
 }
+/// Encrypted history backup of a user (one slot per user).
+/// The blob is encrypted client-side under the user's recovery code.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BackupInfo {
+    #[prost(int64, tag = "1")]
+    pub size_bytes: i64,
+    /// SHA-256 of the encrypted blob
+    #[prost(string, tag = "2")]
+    pub checksum_sha256: ::prost::alloc::string::String,
+    #[prost(int64, tag = "3")]
+    pub updated_at: i64,
+}
+/// Backup upload request (streaming)
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UploadBackupRequest {
+    #[prost(oneof = "upload_backup_request::Content", tags = "1, 2")]
+    pub content: ::core::option::Option<upload_backup_request::Content>,
+}
+/// Nested message and enum types in `UploadBackupRequest`.
+pub mod upload_backup_request {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Content {
+        #[prost(message, tag = "1")]
+        Header(super::UploadBackupHeader),
+        #[prost(bytes, tag = "2")]
+        Chunk(::prost::alloc::vec::Vec<u8>),
+    }
+}
+/// Header sent as first message in backup upload stream
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UploadBackupHeader {
+    /// Expected total size
+    #[prost(int64, tag = "1")]
+    pub size_bytes: i64,
+    /// Optional: for integrity verification
+    #[prost(string, tag = "2")]
+    pub checksum_sha256: ::prost::alloc::string::String,
+}
+/// Backup upload response
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UploadBackupResponse {
+    #[prost(message, optional, tag = "1")]
+    pub info: ::core::option::Option<BackupInfo>,
+    #[prost(message, optional, tag = "2")]
+    pub error: ::core::option::Option<super::common::ErrorResponse>,
+}
+/// Backup download request
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct DownloadBackupRequest {}
+/// Backup download response (streaming)
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DownloadBackupResponse {
+    #[prost(oneof = "download_backup_response::Content", tags = "1, 2")]
+    pub content: ::core::option::Option<download_backup_response::Content>,
+}
+/// Nested message and enum types in `DownloadBackupResponse`.
+pub mod download_backup_response {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Content {
+        #[prost(message, tag = "1")]
+        Info(super::BackupInfo),
+        #[prost(bytes, tag = "2")]
+        Chunk(::prost::alloc::vec::Vec<u8>),
+    }
+}
+/// Delete backup request
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct DeleteBackupRequest {}
+/// Delete backup response
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DeleteBackupResponse {
+    #[prost(bool, tag = "1")]
+    pub success: bool,
+    #[prost(message, optional, tag = "2")]
+    pub error: ::core::option::Option<super::common::ErrorResponse>,
+}
 /// Media type enumeration
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
@@ -670,6 +746,83 @@ pub mod media_service_client {
                 .insert(GrpcMethod::new("guardyn.media.MediaService", "ListMedia"));
             self.inner.unary(req, path, codec).await
         }
+        /// Upload the caller's encrypted history backup, replacing the previous one
+        pub async fn upload_backup(
+            &mut self,
+            request: impl tonic::IntoStreamingRequest<
+                Message = super::UploadBackupRequest,
+            >,
+        ) -> std::result::Result<
+            tonic::Response<super::UploadBackupResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/guardyn.media.MediaService/UploadBackup",
+            );
+            let mut req = request.into_streaming_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("guardyn.media.MediaService", "UploadBackup"));
+            self.inner.client_streaming(req, path, codec).await
+        }
+        /// Download the caller's encrypted history backup
+        pub async fn download_backup(
+            &mut self,
+            request: impl tonic::IntoRequest<super::DownloadBackupRequest>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::DownloadBackupResponse>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/guardyn.media.MediaService/DownloadBackup",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("guardyn.media.MediaService", "DownloadBackup"));
+            self.inner.server_streaming(req, path, codec).await
+        }
+        /// Delete the caller's encrypted history backup
+        pub async fn delete_backup(
+            &mut self,
+            request: impl tonic::IntoRequest<super::DeleteBackupRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::DeleteBackupResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/guardyn.media.MediaService/DeleteBackup",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("guardyn.media.MediaService", "DeleteBackup"));
+            self.inner.unary(req, path, codec).await
+        }
     }
 }
 /// Generated server implementations.
@@ -755,6 +908,36 @@ pub mod media_service_server {
             tonic::Response<super::ListMediaResponse>,
             tonic::Status,
         >;
+        /// Upload the caller's encrypted history backup, replacing the previous one
+        async fn upload_backup(
+            &self,
+            request: tonic::Request<tonic::Streaming<super::UploadBackupRequest>>,
+        ) -> std::result::Result<
+            tonic::Response<super::UploadBackupResponse>,
+            tonic::Status,
+        >;
+        /// Server streaming response type for the DownloadBackup method.
+        type DownloadBackupStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::DownloadBackupResponse, tonic::Status>,
+            >
+            + std::marker::Send
+            + 'static;
+        /// Download the caller's encrypted history backup
+        async fn download_backup(
+            &self,
+            request: tonic::Request<super::DownloadBackupRequest>,
+        ) -> std::result::Result<
+            tonic::Response<Self::DownloadBackupStream>,
+            tonic::Status,
+        >;
+        /// Delete the caller's encrypted history backup
+        async fn delete_backup(
+            &self,
+            request: tonic::Request<super::DeleteBackupRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::DeleteBackupResponse>,
+            tonic::Status,
+        >;
     }
     /// Media Service - handles file uploads, downloads, thumbnails, and encryption
     #[derive(Debug)]
@@ -1198,6 +1381,144 @@ pub mod media_service_server {
                     };
                     Box::pin(fut)
                 }
+                "/guardyn.media.MediaService/UploadBackup" => {
+                    #[allow(non_camel_case_types)]
+                    struct UploadBackupSvc<T: MediaService>(pub Arc<T>);
+                    impl<
+                        T: MediaService,
+                    > tonic::server::ClientStreamingService<super::UploadBackupRequest>
+                    for UploadBackupSvc<T> {
+                        type Response = super::UploadBackupResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<
+                                tonic::Streaming<super::UploadBackupRequest>,
+                            >,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as MediaService>::upload_backup(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = UploadBackupSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.client_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/guardyn.media.MediaService/DownloadBackup" => {
+                    #[allow(non_camel_case_types)]
+                    struct DownloadBackupSvc<T: MediaService>(pub Arc<T>);
+                    impl<
+                        T: MediaService,
+                    > tonic::server::ServerStreamingService<super::DownloadBackupRequest>
+                    for DownloadBackupSvc<T> {
+                        type Response = super::DownloadBackupResponse;
+                        type ResponseStream = T::DownloadBackupStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::DownloadBackupRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as MediaService>::download_backup(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = DownloadBackupSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/guardyn.media.MediaService/DeleteBackup" => {
+                    #[allow(non_camel_case_types)]
+                    struct DeleteBackupSvc<T: MediaService>(pub Arc<T>);
+                    impl<
+                        T: MediaService,
+                    > tonic::server::UnaryService<super::DeleteBackupRequest>
+                    for DeleteBackupSvc<T> {
+                        type Response = super::DeleteBackupResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::DeleteBackupRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as MediaService>::delete_backup(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = DeleteBackupSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 _ => {
                     Box::pin(async move {
                         let mut response = http::Response::new(empty_body());
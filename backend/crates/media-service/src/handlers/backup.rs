@@ -0,0 +1,302 @@
+//! Backup Handler
+//!
+//! Per-user slot for the encrypted message history backup. The backup is
+//! encrypted client-side under the user's recovery code
+//! (`guardyn_crypto::backup`), so the server only stores an opaque blob.
+
+use crate::{
+    config::MediaConfig,
+    db::{BackupRecord, DatabaseClient},
+    jwt,
+    proto::{
+        common::{error_response::ErrorCode, ErrorResponse},
+        media::{
+            download_backup_response, upload_backup_request, BackupInfo, DeleteBackupRequest,
+            DeleteBackupResponse, DownloadBackupRequest, DownloadBackupResponse,
+            UploadBackupRequest, UploadBackupResponse,
+        },
+    },
+    storage::StorageClient,
+};
+use bytes::BytesMut;
+use futures::{stream::BoxStream, StreamExt};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tonic::{Request, Response, Status, Streaming};
+
+/// Handle backup upload - replaces the caller's previous backup
+pub async fn upload(
+    request: Request<Streaming<UploadBackupRequest>>,
+    db: Arc<DatabaseClient>,
+    storage: Arc<StorageClient>,
+    config: &MediaConfig,
+) -> Result<Response<UploadBackupResponse>, Status> {
+    // Validate JWT token
+    let claims = jwt::validate_request(&request)?;
+    let user_id = claims.sub;
+
+    let mut stream = request.into_inner();
+    let max_size = config.max_backup_size_bytes;
+
+    // First message should be the header
+    let first_msg = stream.next().await
+        .ok_or_else(|| Status::invalid_argument("Empty upload stream"))?
+        .map_err(|e| Status::internal(format!("Stream error: {}", e)))?;
+
+    let header = match first_msg.content {
+        Some(upload_backup_request::Content::Header(h)) => h,
+        _ => return Err(Status::invalid_argument("First message must be upload header")),
+    };
+
+    if header.size_bytes < 0 || header.size_bytes as u64 > max_size {
+        return Ok(Response::new(error_upload_response(
+            ErrorCode::InvalidRequest,
+            format!("Backup too large. Maximum size is {} bytes", max_size),
+        )));
+    }
+
+    // Collect chunks and calculate checksum
+    let mut data = BytesMut::with_capacity(header.size_bytes as usize);
+    let mut hasher = Sha256::new();
+
+    while let Some(msg) = stream.next().await {
+        let msg = msg.map_err(|e| Status::internal(format!("Stream error: {}", e)))?;
+
+        if let Some(upload_backup_request::Content::Chunk(chunk_data)) = msg.content {
+            if (data.len() + chunk_data.len()) as u64 > max_size {
+                return Ok(Response::new(error_upload_response(
+                    ErrorCode::InvalidRequest,
+                    "Backup exceeds maximum size".to_string(),
+                )));
+            }
+
+            hasher.update(&chunk_data);
+            data.extend_from_slice(&chunk_data);
+        }
+    }
+
+    if data.is_empty() {
+        return Ok(Response::new(error_upload_response(
+            ErrorCode::InvalidRequest,
+            "Backup is empty".to_string(),
+        )));
+    }
+
+    // Verify checksum if provided
+    let calculated_checksum = hex::encode(hasher.finalize());
+    if !header.checksum_sha256.is_empty() && header.checksum_sha256 != calculated_checksum {
+        return Ok(Response::new(error_upload_response(
+            ErrorCode::InvalidRequest,
+            format!(
+                "Checksum mismatch. Expected: {}, Got: {}",
+                header.checksum_sha256, calculated_checksum
+            ),
+        )));
+    }
+
+    let previous = match db.get_backup_record(&user_id).await {
+        Ok(previous) => previous,
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to get backup record");
+            return Ok(Response::new(error_upload_response(
+                ErrorCode::InternalError,
+                "Database error".to_string(),
+            )));
+        }
+    };
+
+    // Each backup gets a fresh object, so the record never points at a
+    // partially replaced blob
+    let storage_path = format!("{}/backup-{}.bin", user_id, DatabaseClient::generate_media_id());
+    let size_bytes = data.len() as i64;
+
+    if let Err(e) = storage
+        .upload_file(&storage_path, data.freeze(), "application/octet-stream")
+        .await
+    {
+        tracing::error!(error = %e, "Failed to upload backup to storage");
+        return Ok(Response::new(error_upload_response(
+            ErrorCode::InternalError,
+            "Failed to store backup".to_string(),
+        )));
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+
+    let record = BackupRecord {
+        user_id: user_id.clone(),
+        size_bytes,
+        checksum_sha256: calculated_checksum,
+        storage_path,
+        updated_at: now,
+    };
+
+    if let Err(e) = db.store_backup_record(&record).await {
+        tracing::error!(error = %e, "Failed to save backup record");
+        if let Err(e) = storage.delete_file(&record.storage_path).await {
+            tracing::warn!(error = %e, "Failed to remove orphaned backup");
+        }
+        return Ok(Response::new(error_upload_response(
+            ErrorCode::InternalError,
+            "Failed to save backup record".to_string(),
+        )));
+    }
+
+    // Remove the replaced backup
+    if let Some(previous) = previous {
+        if let Err(e) = storage.delete_file(&previous.storage_path).await {
+            tracing::warn!(
+                error = %e,
+                storage_path = %previous.storage_path,
+                "Failed to delete previous backup"
+            );
+        }
+    }
+
+    tracing::info!(
+        user_id = %user_id,
+        size = size_bytes,
+        "Backup uploaded"
+    );
+
+    Ok(Response::new(UploadBackupResponse {
+        info: Some(to_backup_info(&record)),
+        error: None,
+    }))
+}
+
+/// Handle backup download
+pub async fn download(
+    request: Request<DownloadBackupRequest>,
+    db: Arc<DatabaseClient>,
+    storage: Arc<StorageClient>,
+) -> Result<Response<BoxStream<'static, Result<DownloadBackupResponse, Status>>>, Status> {
+    // Validate JWT token
+    let claims = jwt::validate_request(&request)?;
+    let user_id = claims.sub;
+
+    let record = db
+        .get_backup_record(&user_id)
+        .await
+        .map_err(|e| Status::internal(format!("Database error: {}", e)))?
+        .ok_or_else(|| Status::not_found("No backup stored"))?;
+
+    tracing::info!(user_id = %user_id, "Starting backup download");
+
+    let chunk_size: usize = 64 * 1024; // 64KB chunks
+
+    // Stream response
+    let output_stream = async_stream::try_stream! {
+        // First message: backup info
+        yield DownloadBackupResponse {
+            content: Some(download_backup_response::Content::Info(to_backup_info(&record))),
+        };
+
+        let data = storage
+            .download_file(&record.storage_path)
+            .await
+            .map_err(|e| Status::internal(format!("Storage error: {}", e)))?;
+
+        for chunk in data.chunks(chunk_size) {
+            yield DownloadBackupResponse {
+                content: Some(download_backup_response::Content::Chunk(chunk.to_vec())),
+            };
+        }
+
+        tracing::info!(
+            user_id = %record.user_id,
+            bytes_sent = data.len(),
+            "Backup download complete"
+        );
+    };
+
+    Ok(Response::new(Box::pin(output_stream) as BoxStream<'static, Result<DownloadBackupResponse, Status>>))
+}
+
+/// Handle backup deletion
+pub async fn delete(
+    request: Request<DeleteBackupRequest>,
+    db: Arc<DatabaseClient>,
+    storage: Arc<StorageClient>,
+) -> Result<Response<DeleteBackupResponse>, Status> {
+    // Validate JWT token
+    let claims = jwt::validate_request(&request)?;
+    let user_id = claims.sub;
+
+    let record = match db.get_backup_record(&user_id).await {
+        Ok(Some(record)) => record,
+        Ok(None) => {
+            return Ok(Response::new(DeleteBackupResponse {
+                success: false,
+                error: Some(ErrorResponse {
+                    code: ErrorCode::NotFound as i32,
+                    message: "No backup stored".to_string(),
+                    details: Default::default(),
+                }),
+            }));
+        }
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to get backup record");
+            return Ok(Response::new(DeleteBackupResponse {
+                success: false,
+                error: Some(ErrorResponse {
+                    code: ErrorCode::InternalError as i32,
+                    message: "Database error".to_string(),
+                    details: Default::default(),
+                }),
+            }));
+        }
+    };
+
+    // Delete from storage
+    if let Err(e) = storage.delete_file(&record.storage_path).await {
+        tracing::error!(
+            error = %e,
+            storage_path = %record.storage_path,
+            "Failed to delete backup from storage"
+        );
+        // Continue to delete the record even if storage deletion fails
+    }
+
+    if let Err(e) = db.delete_backup_record(&user_id).await {
+        tracing::error!(error = %e, "Failed to delete backup record");
+        return Ok(Response::new(DeleteBackupResponse {
+            success: false,
+            error: Some(ErrorResponse {
+                code: ErrorCode::InternalError as i32,
+                message: "Failed to delete backup record".to_string(),
+                details: Default::default(),
+            }),
+        }));
+    }
+
+    tracing::info!(user_id = %user_id, "Backup deleted");
+
+    Ok(Response::new(DeleteBackupResponse {
+        success: true,
+        error: None,
+    }))
+}
+
+fn error_upload_response(code: ErrorCode, message: String) -> UploadBackupResponse {
+    UploadBackupResponse {
+        info: None,
+        error: Some(ErrorResponse {
+            code: code as i32,
+            message,
+            details: Default::default(),
+        }),
+    }
+}
+
+fn to_backup_info(record: &BackupRecord) -> BackupInfo {
+    BackupInfo {
+        size_bytes: record.size_bytes,
+        checksum_sha256: record.checksum_sha256.clone(),
+        updated_at: record.updated_at,
+    }
+}
@@ -19,10 +19,9 @@ pub async fn handle(
     request: Request<DeleteMediaRequest>,
     db: Arc<DatabaseClient>,
     storage: Arc<StorageClient>,
-    jwt_secret: &str,
 ) -> Result<Response<DeleteMediaResponse>, Status> {
     // Validate JWT token
-    let claims = jwt::validate_request(&request, jwt_secret)?;
+    let claims = jwt::validate_request(&request)?;
     let user_id = claims.sub;
 
     let req = request.into_inner();
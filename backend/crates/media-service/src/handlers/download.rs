@@ -1,6 +1,9 @@
 //! Download Handler
 //!
 //! Handles streaming file downloads
+//!
+//! For encrypted media, `offset` and `length` address the stored ciphertext;
+//! clients compute whole-chunk ranges with `AttachmentDecryptor::ciphertext_range`.
 
 use crate::{
     db::DatabaseClient,
@@ -20,10 +23,9 @@ pub async fn handle(
     request: Request<DownloadMediaRequest>,
     db: Arc<DatabaseClient>,
     storage: Arc<StorageClient>,
-    jwt_secret: &str,
 ) -> Result<Response<BoxStream<'static, Result<DownloadMediaResponse, Status>>>, Status> {
     // Validate JWT token
-    let claims = jwt::validate_request(&request, jwt_secret)?;
+    let claims = jwt::validate_request(&request)?;
     let user_id = claims.sub;
 
     let req = request.into_inner();
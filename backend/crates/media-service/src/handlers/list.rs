@@ -17,10 +17,9 @@ use tonic::{Request, Response, Status};
 pub async fn handle(
     request: Request<ListMediaRequest>,
     db: Arc<DatabaseClient>,
-    jwt_secret: &str,
 ) -> Result<Response<ListMediaResponse>, Status> {
     // Validate JWT token
-    let claims = jwt::validate_request(&request, jwt_secret)?;
+    let claims = jwt::validate_request(&request)?;
     let user_id = claims.sub;
 
     let req = request.into_inner();
@@ -17,10 +17,9 @@ use tonic::{Request, Response, Status};
 pub async fn get(
     request: Request<GetMediaMetadataRequest>,
     db: Arc<DatabaseClient>,
-    jwt_secret: &str,
 ) -> Result<Response<GetMediaMetadataResponse>, Status> {
     // Validate JWT token
-    let claims = jwt::validate_request(&request, jwt_secret)?;
+    let claims = jwt::validate_request(&request)?;
     let user_id = claims.sub;
 
     let req = request.into_inner();
@@ -1,5 +1,6 @@
 //! Request Handlers for Media Service
 
+pub mod backup;
 pub mod delete;
 pub mod download;
 pub mod list;
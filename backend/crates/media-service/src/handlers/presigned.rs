@@ -26,11 +26,10 @@ pub async fn get_upload_url(
     request: Request<GetUploadUrlRequest>,
     db: Arc<DatabaseClient>,
     storage: Arc<StorageClient>,
-    jwt_secret: &str,
     config: &MediaConfig,
 ) -> Result<Response<GetUploadUrlResponse>, Status> {
     // Validate JWT token
-    let claims = jwt::validate_request(&request, jwt_secret)?;
+    let claims = jwt::validate_request(&request)?;
     let user_id = claims.sub;
 
     let req = request.into_inner();
@@ -163,10 +162,9 @@ pub async fn get_download_url(
     request: Request<GetDownloadUrlRequest>,
     db: Arc<DatabaseClient>,
     storage: Arc<StorageClient>,
-    jwt_secret: &str,
 ) -> Result<Response<GetDownloadUrlResponse>, Status> {
     // Validate JWT token
-    let claims = jwt::validate_request(&request, jwt_secret)?;
+    let claims = jwt::validate_request(&request)?;
     let user_id = claims.sub;
 
     let req = request.into_inner();
@@ -25,11 +25,10 @@ pub async fn handle(
     request: Request<GenerateThumbnailRequest>,
     db: Arc<DatabaseClient>,
     storage: Arc<StorageClient>,
-    jwt_secret: &str,
     config: &MediaConfig,
 ) -> Result<Response<GenerateThumbnailResponse>, Status> {
     // Validate JWT token
-    let claims = jwt::validate_request(&request, jwt_secret)?;
+    let claims = jwt::validate_request(&request)?;
     let user_id = claims.sub;
 
     let req = request.into_inner();
@@ -100,6 +99,19 @@ pub async fn handle(
         }));
     }
 
+    // Encrypted attachments can only be thumbnailed by clients
+    if source_metadata.is_encrypted {
+        return Ok(Response::new(GenerateThumbnailResponse {
+            thumbnail_id: String::new(),
+            metadata: None,
+            error: Some(ErrorResponse {
+                code: ErrorCode::InvalidRequest as i32,
+                message: "Cannot generate thumbnails for encrypted media".to_string(),
+                details: Default::default(),
+            }),
+        }));
+    }
+
     // Check if source is an image
     if !ThumbnailGenerator::is_supported_mime(&source_metadata.mime_type) {
         return Ok(Response::new(GenerateThumbnailResponse {
@@ -1,6 +1,10 @@
 //! Upload Handler
 //!
 //! Handles streaming file uploads with unary response
+//!
+//! Encrypted uploads (`is_encrypted`) carry attachment ciphertext in the
+//! chunked format of `guardyn_crypto::attachment`. The server never sees the
+//! key, but checks the layout so range downloads stay decryptable.
 
 use crate::{
     config::MediaConfig,
@@ -24,11 +28,10 @@ pub async fn handle(
     request: Request<Streaming<UploadMediaRequest>>,
     db: Arc<DatabaseClient>,
     storage: Arc<StorageClient>,
-    jwt_secret: &str,
     config: &MediaConfig,
 ) -> Result<Response<UploadMediaResponse>, Status> {
     // Validate JWT token
-    let claims = jwt::validate_request(&request, jwt_secret)?;
+    let claims = jwt::validate_request(&request)?;
     let user_id = claims.sub;
 
     let mut stream = request.into_inner();
@@ -97,6 +100,14 @@ pub async fn handle(
         )));
     }
 
+    // Reject encrypted uploads that are not attachment ciphertext
+    if header.is_encrypted {
+        if let Err(e) = guardyn_crypto::attachment::validate_attachment_layout(&data) {
+            tracing::warn!(media_id = %media_id, error = %e, "Malformed encrypted attachment");
+            return Err(Status::invalid_argument("Encrypted upload is not a valid attachment ciphertext"));
+        }
+    }
+
     // Upload to storage
     storage.upload_file(
         &storage_path,
@@ -0,0 +1,36 @@
+//! Token Verification Keys
+//!
+//! Keeps the key set checked by `jwt::validate_token` current with the
+//! signing keys auth-service stores in TiKV, reloading them whenever a
+//! rotation is announced over NATS and periodically. Keys are published a
+//! full rotation period before they sign anything, so a reload interval of a
+//! minute is enough.
+
+use crate::db::DatabaseClient;
+use anyhow::Result;
+use guardyn_common::jwks::{key_set, JWKS_SUBJECT};
+use guardyn_common::listener;
+
+/// Load the key set from TiKV
+pub async fn sync(db: &DatabaseClient) -> Result<usize> {
+    match db.load_jwks().await? {
+        Some(jwks) => Ok(key_set().replace(&jwks)),
+        None => {
+            tracing::warn!("No token verification keys published yet");
+            Ok(0)
+        }
+    }
+}
+
+/// Reload the key set from TiKV whenever auth-service announces a change
+pub fn spawn_listener(db: DatabaseClient, nats: Option<async_nats::Client>) {
+    listener::spawn_reload_listener(
+        "token verification keys",
+        nats,
+        JWKS_SUBJECT,
+        move || {
+            let db = db.clone();
+            async move { sync(&db).await }
+        },
+    );
+}
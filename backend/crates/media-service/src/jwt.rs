@@ -1,8 +1,10 @@
 //! JWT Token Validation
 //!
-//! Validates JWT tokens issued by auth-service
+//! Validates JWT tokens issued by auth-service against its published signing
+//! keys and rejects revoked sessions
 
-use jsonwebtoken::{decode, DecodingKey, Validation, Algorithm};
+use guardyn_common::jwks::key_set;
+use guardyn_common::revocation::revocations;
 use serde::{Deserialize, Serialize};
 use tonic::Status;
 
@@ -17,15 +19,24 @@ pub struct Claims {
     pub exp: usize,
     /// Issued at (Unix timestamp)
     pub iat: usize,
+    /// Session ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+    /// Token ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
 }
 
 /// Extract and validate JWT token from request metadata
-pub fn validate_token(token: &str, jwt_secret: &str) -> Result<Claims, Status> {
-    let decoding_key = DecodingKey::from_secret(jwt_secret.as_bytes());
-    let validation = Validation::new(Algorithm::HS256);
-
-    match decode::<Claims>(token, &decoding_key, &validation) {
-        Ok(token_data) => Ok(token_data.claims),
+pub fn validate_token(token: &str) -> Result<Claims, Status> {
+    match key_set().verify::<Claims>(token) {
+        Ok(claims) => {
+            if revocations().is_token_revoked(claims.sid.as_deref(), claims.jti.as_deref()) {
+                tracing::warn!(user_id = %claims.sub, "Rejected token of revoked session");
+                return Err(Status::unauthenticated("Token has been revoked"));
+            }
+            Ok(claims)
+        }
         Err(e) => {
             tracing::warn!(error = %e, "JWT validation failed");
             Err(Status::unauthenticated("Invalid token"))
@@ -45,25 +56,32 @@ pub fn extract_token_from_header(auth_header: Option<&str>) -> Result<&str, Stat
 }
 
 /// Extract and validate token from gRPC request metadata
-pub fn validate_request<T>(
-    request: &tonic::Request<T>,
-    jwt_secret: &str,
-) -> Result<Claims, Status> {
+pub fn validate_request<T>(request: &tonic::Request<T>) -> Result<Claims, Status> {
     let auth_header = request
         .metadata()
         .get("authorization")
         .and_then(|v| v.to_str().ok());
     
     let token = extract_token_from_header(auth_header)?;
-    validate_token(token, jwt_secret)
+    validate_token(token)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use jsonwebtoken::{encode, EncodingKey, Header};
+    use guardyn_common::jwks::{JwkSet, SigningKey};
+
+    fn test_key() -> SigningKey {
+        let key = SigningKey::from_seed(&[42u8; 32]);
+        key_set().replace(&JwkSet { keys: vec![key.jwk()] });
+        key
+    }
 
-    fn create_test_token(secret: &str, exp_offset: i64) -> String {
+    fn create_test_token(key: &SigningKey, exp_offset: i64) -> String {
+        create_session_token(key, exp_offset, None)
+    }
+
+    fn create_session_token(key: &SigningKey, exp_offset: i64, sid: Option<&str>) -> String {
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
@@ -74,22 +92,18 @@ mod tests {
             device_id: "device456".to_string(),
             exp: (now as i64 + exp_offset) as usize,
             iat: now,
+            sid: sid.map(str::to_string),
+            jti: None,
         };
 
-        encode(
-            &Header::default(),
-            &claims,
-            &EncodingKey::from_secret(secret.as_bytes()),
-        )
-        .unwrap()
+        key.sign(&claims).unwrap()
     }
 
     #[test]
     fn test_validate_valid_token() {
-        let secret = "test-secret";
-        let token = create_test_token(secret, 3600); // Valid for 1 hour
+        let token = create_test_token(&test_key(), 3600); // Valid for 1 hour
         
-        let result = validate_token(&token, secret);
+        let result = validate_token(&token);
         assert!(result.is_ok());
         
         let claims = result.unwrap();
@@ -99,17 +113,36 @@ mod tests {
 
     #[test]
     fn test_validate_expired_token() {
-        let secret = "test-secret";
-        let token = create_test_token(secret, -3600); // Expired 1 hour ago
+        let token = create_test_token(&test_key(), -3600); // Expired 1 hour ago
         
-        let result = validate_token(&token, secret);
+        let result = validate_token(&token);
         assert!(result.is_err());
     }
 
     #[test]
-    fn test_validate_invalid_secret() {
-        let token = create_test_token("secret1", 3600);
-        let result = validate_token(&token, "secret2");
+    fn test_validate_unpublished_key() {
+        test_key();
+        let token = create_test_token(&SigningKey::from_seed(&[7u8; 32]), 3600);
+        let result = validate_token(&token);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_validate_revoked_session() {
+        let token = create_session_token(&test_key(), 3600, Some("media-revoked-session"));
+        assert!(validate_token(&token).is_ok());
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        revocations().insert(&guardyn_common::revocation::Revocation {
+            id: "media-revoked-session".to_string(),
+            user_id: "user123".to_string(),
+            expires_at: now + 3600,
+        });
+
+        let result = validate_token(&token);
         assert!(result.is_err());
     }
 }
@@ -7,11 +7,14 @@
 //! - Thumbnail generation for images/videos
 //! - Pre-signed URLs for direct upload/download
 //! - Media encryption/decryption support
+//! - Encrypted message history backups (one slot per user)
 
 mod config;
 mod db;
 mod handlers;
+mod jwks;
 mod jwt;
+mod revocation;
 mod storage;
 mod thumbnail;
 
@@ -40,13 +43,15 @@ use proto::media::{
     GetDownloadUrlRequest, GetDownloadUrlResponse,
     GenerateThumbnailRequest, GenerateThumbnailResponse,
     ListMediaRequest, ListMediaResponse,
+    UploadBackupRequest, UploadBackupResponse,
+    DownloadBackupRequest, DownloadBackupResponse,
+    DeleteBackupRequest, DeleteBackupResponse,
 };
 
 /// Media Service Implementation
 pub struct MediaServiceImpl {
     db: Arc<db::DatabaseClient>,
     storage: Arc<storage::StorageClient>,
-    jwt_secret: String,
     config: config::MediaConfig,
 }
 
@@ -54,13 +59,11 @@ impl MediaServiceImpl {
     pub async fn new(
         db: db::DatabaseClient,
         storage: storage::StorageClient,
-        jwt_secret: String,
         config: config::MediaConfig,
     ) -> Self {
         Self {
             db: Arc::new(db),
             storage: Arc::new(storage),
-            jwt_secret,
             config,
         }
     }
@@ -69,6 +72,7 @@ impl MediaServiceImpl {
 #[tonic::async_trait]
 impl MediaService for MediaServiceImpl {
     type DownloadMediaStream = futures::stream::BoxStream<'static, Result<DownloadMediaResponse, Status>>;
+    type DownloadBackupStream = futures::stream::BoxStream<'static, Result<DownloadBackupResponse, Status>>;
 
     async fn upload_media(
         &self,
@@ -78,7 +82,6 @@ impl MediaService for MediaServiceImpl {
             request,
             self.db.clone(),
             self.storage.clone(),
-            &self.jwt_secret,
             &self.config,
         ).await
     }
@@ -91,7 +94,6 @@ impl MediaService for MediaServiceImpl {
             request,
             self.db.clone(),
             self.storage.clone(),
-            &self.jwt_secret,
         ).await
     }
 
@@ -102,7 +104,6 @@ impl MediaService for MediaServiceImpl {
         handlers::metadata::get(
             request,
             self.db.clone(),
-            &self.jwt_secret,
         ).await
     }
 
@@ -114,7 +115,6 @@ impl MediaService for MediaServiceImpl {
             request,
             self.db.clone(),
             self.storage.clone(),
-            &self.jwt_secret,
         ).await
     }
 
@@ -126,7 +126,6 @@ impl MediaService for MediaServiceImpl {
             request,
             self.db.clone(),
             self.storage.clone(),
-            &self.jwt_secret,
             &self.config,
         ).await
     }
@@ -139,7 +138,6 @@ impl MediaService for MediaServiceImpl {
             request,
             self.db.clone(),
             self.storage.clone(),
-            &self.jwt_secret,
         ).await
     }
 
@@ -151,7 +149,6 @@ impl MediaService for MediaServiceImpl {
             request,
             self.db.clone(),
             self.storage.clone(),
-            &self.jwt_secret,
             &self.config,
         ).await
     }
@@ -163,7 +160,40 @@ impl MediaService for MediaServiceImpl {
         handlers::list::handle(
             request,
             self.db.clone(),
-            &self.jwt_secret,
+        ).await
+    }
+
+    async fn upload_backup(
+        &self,
+        request: Request<Streaming<UploadBackupRequest>>,
+    ) -> Result<Response<UploadBackupResponse>, Status> {
+        handlers::backup::upload(
+            request,
+            self.db.clone(),
+            self.storage.clone(),
+            &self.config,
+        ).await
+    }
+
+    async fn download_backup(
+        &self,
+        request: Request<DownloadBackupRequest>,
+    ) -> Result<Response<Self::DownloadBackupStream>, Status> {
+        handlers::backup::download(
+            request,
+            self.db.clone(),
+            self.storage.clone(),
+        ).await
+    }
+
+    async fn delete_backup(
+        &self,
+        request: Request<DeleteBackupRequest>,
+    ) -> Result<Response<DeleteBackupResponse>, Status> {
+        handlers::backup::delete(
+            request,
+            self.db.clone(),
+            self.storage.clone(),
         ).await
     }
 }
@@ -200,6 +230,22 @@ async fn main() -> Result<()> {
     let db = db::DatabaseClient::new(&tikv_endpoints).await?;
     tracing::info!("TiKV connection established");
 
+    // Subscribe to signing key and session revocation updates from auth-service
+    let nats = match async_nats::connect(&service_config.messaging.nats_url).await {
+        Ok(client) => Some(client),
+        Err(e) => {
+            tracing::warn!(error = %e, "Failed to connect to NATS - signing keys and revocations will be synced from TiKV only");
+            None
+        }
+    };
+
+    // Verify access tokens with the keys published by auth-service
+    if let Err(e) = jwks::sync(&db).await {
+        tracing::warn!(error = %e, "Failed to load token verification keys");
+    }
+    jwks::spawn_listener(db.clone(), nats.clone());
+    revocation::spawn_listener(db.clone(), nats);
+
     // Initialize S3/MinIO storage client
     tracing::info!(endpoint = %media_config.s3_endpoint, "Connecting to S3/MinIO");
     let storage = storage::StorageClient::new(&media_config).await?;
@@ -209,12 +255,8 @@ async fn main() -> Result<()> {
     storage.ensure_bucket_exists(&media_config.bucket_name).await?;
     tracing::info!(bucket = %media_config.bucket_name, "Storage bucket ready");
 
-    // Get JWT secret
-    let jwt_secret = std::env::var("JWT_SECRET")
-        .unwrap_or_else(|_| "default-jwt-secret-change-me".to_string());
-
     // Create service
-    let service = MediaServiceImpl::new(db, storage, jwt_secret, media_config).await;
+    let service = MediaServiceImpl::new(db, storage, media_config).await;
 
     // Start gRPC server
     let addr = format!(
@@ -0,0 +1,32 @@
+//! Session Revocations
+//!
+//! Keeps the revocation list checked by `jwt::validate_token` current with
+//! updates published by auth-service, falling back to periodic TiKV reloads.
+
+use crate::db::DatabaseClient;
+use anyhow::Result;
+use guardyn_common::listener;
+use guardyn_common::revocation::{revocations, Revocation, REVOCATION_SUBJECT};
+
+/// Load active revocations from TiKV into the local list
+pub async fn sync(db: &DatabaseClient) -> Result<usize> {
+    let active = db.load_revocations().await?;
+    for revocation in &active {
+        revocations().insert(revocation);
+    }
+    Ok(active.len())
+}
+
+/// Start listening for revocations
+pub fn spawn_listener(db: DatabaseClient, nats: Option<async_nats::Client>) {
+    listener::spawn_listener(
+        "revocations",
+        nats,
+        REVOCATION_SUBJECT,
+        |revocation: Revocation| revocations().insert(&revocation),
+        move || {
+            let db = db.clone();
+            async move { sync(&db).await }
+        },
+    );
+}
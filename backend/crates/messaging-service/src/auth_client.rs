@@ -1,11 +1,12 @@
 /// gRPC client for Auth Service communication
 ///
 /// Provides methods to interact with the auth-service, primarily for
-/// fetching MLS key packages during group member addition and user profile lookups.
+/// fetching MLS key packages during group member addition, user profile lookups
+/// and device lists for multi-device fan-out.
 
 use crate::proto::auth::{
     auth_service_client::AuthServiceClient, GetMlsKeyPackageRequest, GetMlsKeyPackageResponse,
-    GetUserProfileRequest,
+    GetUserDevicesRequest, GetUserProfileRequest,
 };
 use anyhow::{Context, Result};
 use tonic::transport::Channel;
@@ -38,6 +39,7 @@ impl AuthClient {
     /// # Arguments
     /// * `user_id` - The target user ID
     /// * `device_id` - The target device ID (optional, will use latest if empty)
+    /// * `ciphersuite` - Required MLS ciphersuite code point (0 for the latest key package)
     ///
     /// # Returns
     /// * `Ok(Vec<u8>)` - The serialized MLS key package bytes
@@ -46,6 +48,7 @@ impl AuthClient {
         &mut self,
         user_id: &str,
         device_id: &str,
+        ciphersuite: u16,
     ) -> Result<Vec<u8>> {
         debug!(
             "Fetching MLS key package for user_id={}, device_id={}",
@@ -55,6 +58,7 @@ impl AuthClient {
         let request = tonic::Request::new(GetMlsKeyPackageRequest {
             user_id: user_id.to_string(),
             device_id: device_id.to_string(),
+            ciphersuite: u32::from(ciphersuite),
         });
 
         let response = self
@@ -103,6 +107,7 @@ impl AuthClient {
         let request = tonic::Request::new(GetMlsKeyPackageRequest {
             user_id: user_id.to_string(),
             device_id: device_id.to_string(),
+            ciphersuite: 0,
         });
 
         let response = self
@@ -153,6 +158,52 @@ impl AuthClient {
         }
     }
 
+    /// Fetch the IDs of all registered devices of a user
+    ///
+    /// # Arguments
+    /// * `access_token` - The caller's access token (forwarded to auth-service)
+    /// * `user_id` - The user whose devices to list
+    ///
+    /// # Returns
+    /// * `Ok(Vec<String>)` - Device IDs (empty if the user has no devices)
+    /// * `Err(anyhow::Error)` - If the request fails
+    pub async fn get_device_ids(&mut self, access_token: &str, user_id: &str) -> Result<Vec<String>> {
+        debug!("Fetching devices for user_id={}", user_id);
+
+        let request = tonic::Request::new(GetUserDevicesRequest {
+            access_token: access_token.to_string(),
+            user_id: user_id.to_string(),
+        });
+
+        let response = self
+            .client
+            .get_user_devices(request)
+            .await
+            .context("gRPC call to GetUserDevices failed")?;
+
+        match response.into_inner().result {
+            Some(crate::proto::auth::get_user_devices_response::Result::Success(success)) => {
+                Ok(success.devices.into_iter().map(|device| device.device_id).collect())
+            }
+            Some(crate::proto::auth::get_user_devices_response::Result::Error(err))
+                if err.code == crate::proto::common::error_response::ErrorCode::NotFound as i32 =>
+            {
+                Ok(Vec::new())
+            }
+            Some(crate::proto::auth::get_user_devices_response::Result::Error(err)) => {
+                error!(
+                    "Auth service returned error: code={}, message={}",
+                    err.code, err.message
+                );
+                Err(anyhow::anyhow!("Failed to fetch user devices: {}", err.message))
+            }
+            None => {
+                error!("Auth service returned empty response");
+                Err(anyhow::anyhow!("Empty response from auth service GetUserDevices"))
+            }
+        }
+    }
+
     /// Fetch usernames for multiple user IDs
     ///
     /// Returns a HashMap of user_id -> username
@@ -192,7 +243,7 @@ mod tests {
             .expect("Failed to connect");
 
         let result = client
-            .fetch_mls_key_package("test-user-id", "test-device-id")
+            .fetch_mls_key_package("test-user-id", "test-device-id", 0)
             .await;
 
         // This will fail if no key package exists, which is expected in tests
@@ -6,6 +6,7 @@
 /// - Database settings
 /// - Performance tuning
 
+use guardyn_crypto::mls::MlsCiphersuite;
 use std::env;
 
 /// MLS (Messaging Layer Security) configuration
@@ -22,6 +23,12 @@ pub struct MlsConfig {
     
     /// MLS ciphersuite (default: MLS_128_DHKEMX25519_AES128GCM_SHA256_Ed25519)
     pub ciphersuite: String,
+
+    /// Messages a device may send before its leaf must be rotated (0 disables)
+    pub self_update_message_interval: u64,
+
+    /// Days a device may keep its leaf before it must be rotated (0 disables)
+    pub self_update_interval_days: u32,
 }
 
 impl MlsConfig {
@@ -32,6 +39,8 @@ impl MlsConfig {
     /// - MLS_MAX_GROUP_SIZE: Maximum group size (default: 256)
     /// - MLS_KEY_PACKAGE_TTL_DAYS: Key package TTL (default: 30)
     /// - MLS_CIPHERSUITE: Ciphersuite identifier (default: MLS_128_DHKEMX25519_AES128GCM_SHA256_Ed25519)
+    /// - MLS_SELF_UPDATE_MESSAGES: Messages between leaf rotations (default: 1000)
+    /// - MLS_SELF_UPDATE_DAYS: Days between leaf rotations (default: 7)
     pub fn from_env() -> Self {
         let enabled = env::var("ENABLE_MLS")
             .unwrap_or_else(|_| "false".to_string())
@@ -51,14 +60,48 @@ impl MlsConfig {
         let ciphersuite = env::var("MLS_CIPHERSUITE")
             .unwrap_or_else(|_| "MLS_128_DHKEMX25519_AES128GCM_SHA256_Ed25519".to_string());
         
+        let self_update_message_interval = env::var("MLS_SELF_UPDATE_MESSAGES")
+            .unwrap_or_else(|_| "1000".to_string())
+            .parse::<u64>()
+            .unwrap_or(1000);
+        
+        let self_update_interval_days = env::var("MLS_SELF_UPDATE_DAYS")
+            .unwrap_or_else(|_| "7".to_string())
+            .parse::<u32>()
+            .unwrap_or(7);
+        
         Self {
             enabled,
             max_group_size,
             key_package_ttl_days,
             ciphersuite,
+            self_update_message_interval,
+            self_update_interval_days,
         }
     }
     
+    /// Check if a device should rotate its leaf with a self-update commit
+    ///
+    /// # Arguments
+    /// * `messages_since_update` - Messages the device sent since its last self-update
+    /// * `last_update_at` - Unix timestamp of its last self-update (or of joining)
+    /// * `now` - Current Unix timestamp
+    pub fn self_update_due(&self, messages_since_update: u64, last_update_at: i64, now: i64) -> bool {
+        let messages_due = self.self_update_message_interval > 0
+            && messages_since_update >= self.self_update_message_interval;
+        let age_due = self.self_update_interval_days > 0
+            && now - last_update_at >= i64::from(self.self_update_interval_days) * 86_400;
+        
+        messages_due || age_due
+    }
+    
+    /// Resolve the configured ciphersuite
+    ///
+    /// Fails for unknown names and for suites the crypto backend cannot run.
+    pub fn mls_ciphersuite(&self) -> anyhow::Result<MlsCiphersuite> {
+        Ok(guardyn_crypto::mls::parse_ciphersuite(&self.ciphersuite)?)
+    }
+    
     /// Check if MLS is enabled for a specific group
     ///
     /// Future: Can add per-group or per-user rollout logic here
@@ -195,6 +238,10 @@ impl MessagingConfig {
             println!("  - Max Group Size: {}", self.mls.max_group_size);
             println!("  - Key Package TTL: {} days", self.mls.key_package_ttl_days);
             println!("  - Ciphersuite: {}", self.mls.ciphersuite);
+            println!(
+                "  - Self-Update: every {} messages or {} days",
+                self.mls.self_update_message_interval, self.mls.self_update_interval_days
+            );
         }
         println!("🔐 E2EE Configuration:");
         println!("  - Enabled: {}", self.e2ee.enabled);
@@ -241,6 +288,36 @@ mod tests {
         env::remove_var("MLS_KEY_PACKAGE_TTL_DAYS");
     }
     
+    #[test]
+    fn test_mls_ciphersuite_resolution() {
+        let mut config = MlsConfig::from_env();
+        
+        config.ciphersuite = "MLS_128_DHKEMP256_AES128GCM_SHA256_P256".to_string();
+        assert_eq!(
+            config.mls_ciphersuite().unwrap(),
+            MlsCiphersuite::MLS_128_DHKEMP256_AES128GCM_SHA256_P256
+        );
+        
+        config.ciphersuite = "MLS_256_DHKEMX448_CHACHA20POLY1305_SHA512_Ed448".to_string();
+        assert!(config.mls_ciphersuite().is_err());
+    }
+    
+    #[test]
+    fn test_mls_self_update_policy() {
+        let mut config = MlsConfig::from_env();
+        config.self_update_message_interval = 100;
+        config.self_update_interval_days = 7;
+        let now = 1_700_000_000;
+        
+        assert!(!config.self_update_due(99, now - 86_400, now));
+        assert!(config.self_update_due(100, now - 86_400, now));
+        assert!(config.self_update_due(0, now - 7 * 86_400, now));
+        
+        config.self_update_message_interval = 0;
+        config.self_update_interval_days = 0;
+        assert!(!config.self_update_due(u64::MAX, 0, now));
+    }
+    
     #[test]
     fn test_e2ee_config_defaults() {
         env::remove_var("ENABLE_E2EE");
@@ -7,8 +7,11 @@ use guardyn_crypto::{
     x3dh::{X3DHProtocol, X3DHKeyBundle, IdentityKeyPair},
     double_ratchet::DoubleRatchet,
 };
+use crate::identity_keys::IdentityKeyTracker;
 use crate::models::RatchetSession;
-use std::sync::Arc;
+use crate::nats::NatsClient;
+use std::sync::{Arc, OnceLock};
+use zeroize::Zeroizing;
 
 // Import generated proto types
 use crate::proto::auth::{
@@ -16,14 +19,73 @@ use crate::proto::auth::{
     GetKeyBundleRequest,
 };
 
+/// Ratchet state key, loaded once at startup by [`load_state_key`]
+static STATE_KEY: OnceLock<Option<Zeroizing<[u8; 32]>>> = OnceLock::new();
+
+/// Load the ratchet state key from `E2EE_RATCHET_STATE_KEY` (32 bytes, hex-encoded)
+///
+/// A malformed key is an error rather than a silent fallback to unencrypted
+/// state. Returns whether a key is configured.
+pub fn load_state_key() -> Result<bool> {
+    let key = match std::env::var("E2EE_RATCHET_STATE_KEY") {
+        Ok(value) if !value.trim().is_empty() => Some(
+            parse_state_key(&value)
+                .ok_or_else(|| anyhow!("E2EE_RATCHET_STATE_KEY must be 32 hex-encoded bytes"))?,
+        ),
+        _ => None,
+    };
+
+    let configured = key.is_some();
+    STATE_KEY
+        .set(key)
+        .map_err(|_| anyhow!("Ratchet state key already loaded"))?;
+    Ok(configured)
+}
+
+/// Encrypt all stored ratchet sessions that are still unencrypted
+///
+/// One-shot migration to at-rest encryption, run at startup when
+/// `E2EE_RATCHET_STATE_MIGRATE=true`; afterwards unencrypted state is rejected.
+/// Returns the number of sessions migrated.
+pub async fn migrate_sessions(db: &crate::db::DatabaseClient) -> Result<usize> {
+    let key = STATE_KEY
+        .get()
+        .and_then(|key| key.as_deref())
+        .ok_or_else(|| anyhow!("Migration requires E2EE_RATCHET_STATE_KEY"))?;
+
+    let mut migrated = 0;
+    for session in db.list_ratchet_sessions().await? {
+        if DoubleRatchet::is_current_format(&session.ratchet_state, true) {
+            continue;
+        }
+
+        let state = DoubleRatchet::migrate_to_encrypted(&session.ratchet_state, key)
+            .map_err(|e| anyhow!("Failed to migrate session {}: {}", session.session_id, e))?;
+        db.update_ratchet_session_state(&session.session_id, &state).await?;
+        migrated += 1;
+    }
+
+    Ok(migrated)
+}
+
 /// Crypto manager for E2EE operations
 pub struct CryptoManager {
     auth_service_url: String,
+    identity_keys: Option<IdentityKeyTracker>,
 }
 
 impl CryptoManager {
     pub fn new(auth_service_url: String) -> Self {
-        Self { auth_service_url }
+        Self {
+            auth_service_url,
+            identity_keys: None,
+        }
+    }
+
+    /// Report identity key changes in fetched key bundles to the local user
+    pub fn with_identity_key_tracker(mut self, tracker: IdentityKeyTracker) -> Self {
+        self.identity_keys = Some(tracker);
+        self
     }
 
     /// Initialize Double Ratchet session as sender (Alice)
@@ -34,6 +96,7 @@ impl CryptoManager {
     /// 3. Initialize Double Ratchet with shared secret
     pub async fn init_sender_session(
         &self,
+        access_token: &str,
         local_user_id: &str,
         local_device_id: &str,
         remote_user_id: &str,
@@ -41,7 +104,7 @@ impl CryptoManager {
         local_identity_key: &[u8],
     ) -> Result<(DoubleRatchet, Vec<u8>)> {
         // Fetch remote key bundle from auth-service
-        let key_bundle = self.fetch_key_bundle(remote_user_id, remote_device_id).await
+        let key_bundle = self.fetch_key_bundle(access_token, local_user_id, remote_user_id, remote_device_id).await
             .context("Failed to fetch recipient's key bundle")?;
 
         // Parse key bundle into X3DH types
@@ -54,7 +117,7 @@ impl CryptoManager {
             .context("Failed to generate identity key pair")?;
 
         // Perform X3DH key agreement (Alice side)
-        let (shared_secret, ephemeral_public) = X3DHProtocol::initiate_key_agreement(
+        let (shared_secret, ephemeral_public, _) = X3DHProtocol::initiate_key_agreement(
             &local_identity,
             &x3dh_bundle,
             false, // Don't use one-time keys for MVP
@@ -73,9 +136,14 @@ impl CryptoManager {
         Ok((ratchet, ephemeral_public.as_bytes().to_vec()))
     }
 
-    /// Fetch key bundle from auth-service via gRPC
+    /// Fetch key bundle from auth-service via gRPC, as the holder of `access_token`
+    ///
+    /// The returned identity key is compared with the one `local_user_id` saw
+    /// last, and a change is reported to them.
     async fn fetch_key_bundle(
         &self,
+        access_token: &str,
+        local_user_id: &str,
         user_id: &str,
         device_id: &str,
     ) -> Result<crate::proto::common::KeyBundle> {
@@ -86,6 +154,7 @@ impl CryptoManager {
         let request = tonic::Request::new(GetKeyBundleRequest {
             user_id: user_id.to_string(),
             device_id: device_id.to_string(),
+            access_token: access_token.to_string(),
         });
 
         let response = client.get_key_bundle(request)
@@ -95,7 +164,17 @@ impl CryptoManager {
 
         match response.result {
             Some(crate::proto::auth::get_key_bundle_response::Result::Success(success)) => {
-                success.key_bundle.ok_or_else(|| anyhow!("Key bundle missing in response"))
+                let key_bundle = success.key_bundle
+                    .ok_or_else(|| anyhow!("Key bundle missing in response"))?;
+
+                // A missed notification is retried on the next fetch; it must not block the session
+                if let Some(tracker) = &self.identity_keys {
+                    if let Err(e) = tracker.observe(local_user_id, user_id, &key_bundle.identity_key).await {
+                        tracing::warn!("Failed to track identity key of {}: {:#}", user_id, e);
+                    }
+                }
+
+                Ok(key_bundle)
             }
             Some(crate::proto::auth::get_key_bundle_response::Result::Error(err)) => {
                 Err(anyhow!("Auth service error: {} (code: {:?})", err.message, err.code))
@@ -128,23 +207,55 @@ impl CryptoManager {
         );
 
         // Convert one-time pre-keys to the format expected by crypto crate
+        // (bundles without key IDs predate them and are numbered by position)
         let one_time_pre_keys: Vec<guardyn_crypto::x3dh::OneTimePreKeyPublic> = bundle.one_time_pre_keys
             .iter()
             .enumerate()
             .map(|(idx, key_bytes)| {
                 guardyn_crypto::x3dh::OneTimePreKeyPublic {
-                    key_id: idx as u32,
+                    key_id: bundle.one_time_pre_key_ids.get(idx).copied().unwrap_or(idx as u32),
                     public_key: key_bytes.clone(),
                 }
             })
             .collect();
 
+        let last_resort_pre_key = if bundle.last_resort_pre_key.is_empty() {
+            None
+        } else {
+            Some(guardyn_crypto::x3dh::LastResortPreKeyPublic {
+                key_id: bundle.last_resort_pre_key_id,
+                public_key: bundle.last_resort_pre_key.clone(),
+                signature: bundle.last_resort_pre_key_signature.clone(),
+            })
+        };
+
+        // ML-KEM pre-keys are verified against the identity key during key agreement
+        let to_kem_pre_key = |key: &crate::proto::common::KemPreKey| guardyn_crypto::pq_kem::KemPreKeyPublic {
+            key_id: key.key_id,
+            public_key: key.public_key.clone(),
+            signature: key.signature.clone(),
+        };
+        let kem_pre_key = bundle.kem_pre_key.as_ref().map(to_kem_pre_key);
+        let one_time_kem_pre_keys: Vec<guardyn_crypto::pq_kem::KemPreKeyPublic> =
+            bundle.one_time_kem_pre_keys.iter().map(to_kem_pre_key).collect();
+
+        // Bundles that publish KEM pre-keys negotiate PQXDH, the rest classic X3DH
+        let version = if kem_pre_key.is_some() || !one_time_kem_pre_keys.is_empty() {
+            guardyn_crypto::x3dh::BUNDLE_VERSION_PQXDH
+        } else {
+            guardyn_crypto::x3dh::BUNDLE_VERSION_X3DH
+        };
+
         Ok(X3DHKeyBundle {
+            version,
             identity_key: identity_key.to_bytes().to_vec(),
             signed_pre_key: signed_pre_key.as_bytes().to_vec(),
-            signed_pre_key_id: 1, // Default ID for MVP
+            signed_pre_key_id: if bundle.signed_pre_key_id == 0 { 1 } else { bundle.signed_pre_key_id },
             signed_pre_key_signature: signature.to_bytes().to_vec(),
             one_time_pre_keys,
+            last_resort_pre_key,
+            kem_pre_key,
+            one_time_kem_pre_keys,
         })
     }
 
@@ -169,14 +280,28 @@ impl CryptoManager {
     }
 
     /// Serialize Double Ratchet state for storage
-    pub fn serialize_ratchet(ratchet: &DoubleRatchet) -> Result<Vec<u8>> {
-        Ok(ratchet.serialize())
+    ///
+    /// State is encrypted at rest when a state key is configured.
+    pub fn serialize_ratchet(ratchet: &DoubleRatchet, state_key: Option<&[u8; 32]>) -> Result<Zeroizing<Vec<u8>>> {
+        match state_key {
+            Some(key) => ratchet.serialize_encrypted(key)
+                .map(Zeroizing::new)
+                .map_err(|e| anyhow!("Failed to serialize ratchet: {}", e)),
+            None => Ok(ratchet.serialize()),
+        }
     }
 
     /// Deserialize Double Ratchet state from storage
-    pub fn deserialize_ratchet(data: &[u8]) -> Result<DoubleRatchet> {
-        DoubleRatchet::deserialize(data)
-            .map_err(|e| anyhow!("Failed to deserialize ratchet: {}", e))
+    ///
+    /// With a state key only encrypted state is accepted; without one, all
+    /// unencrypted formats (including legacy unversioned state) are.
+    pub fn deserialize_ratchet(data: &[u8], state_key: Option<&[u8; 32]>) -> Result<DoubleRatchet> {
+        let ratchet = match state_key {
+            Some(key) => DoubleRatchet::deserialize_encrypted(data, key),
+            None => DoubleRatchet::deserialize(data),
+        };
+
+        ratchet.map_err(|e| anyhow!("Failed to deserialize ratchet: {}", e))
     }
 
     /// Encrypt message with Double Ratchet
@@ -185,6 +310,13 @@ impl CryptoManager {
         plaintext: &[u8],
         associated_data: &[u8],
     ) -> Result<Vec<u8>> {
+        if ratchet.has_header_encryption() {
+            let encrypted_msg = ratchet.encrypt_header_encrypted(plaintext, associated_data)
+                .context("Failed to encrypt message with Double Ratchet")?;
+
+            return Ok(encrypted_msg.to_bytes());
+        }
+
         let encrypted_msg = ratchet.encrypt(plaintext, associated_data)
             .context("Failed to encrypt message with Double Ratchet")?;
 
@@ -197,7 +329,15 @@ impl CryptoManager {
         ciphertext: &[u8],
         associated_data: &[u8],
     ) -> Result<Vec<u8>> {
-        use guardyn_crypto::double_ratchet::EncryptedMessage;
+        use guardyn_crypto::double_ratchet::{EncryptedMessage, HeaderEncryptedMessage};
+
+        if ratchet.has_header_encryption() {
+            let encrypted_msg = HeaderEncryptedMessage::from_bytes(ciphertext)
+                .context("Failed to parse encrypted message")?;
+
+            return ratchet.decrypt_header_encrypted(&encrypted_msg, associated_data)
+                .context("Failed to decrypt message with Double Ratchet");
+        }
 
         let encrypted_msg = EncryptedMessage::from_bytes(ciphertext)
             .context("Failed to parse encrypted message")?;
@@ -213,16 +353,34 @@ impl CryptoManager {
 pub struct SessionManager {
     db: Arc<crate::db::DatabaseClient>,
     crypto: CryptoManager,
+    state_key: Option<Zeroizing<[u8; 32]>>,
 }
 
 impl SessionManager {
-    pub fn new(db: Arc<crate::db::DatabaseClient>, auth_service_url: String) -> Self {
+    /// Create session manager
+    ///
+    /// Ratchet state is encrypted at rest with the key loaded by
+    /// [`load_state_key`]. Identity key changes seen while establishing
+    /// sessions are published over `nats`.
+    pub fn new(
+        db: Arc<crate::db::DatabaseClient>,
+        auth_service_url: String,
+        nats: Arc<NatsClient>,
+    ) -> Self {
+        let state_key = STATE_KEY.get().cloned().flatten();
+
         Self {
+            crypto: CryptoManager::new(auth_service_url)
+                .with_identity_key_tracker(IdentityKeyTracker::new(db.clone(), nats)),
             db,
-            crypto: CryptoManager::new(auth_service_url),
+            state_key,
         }
     }
 
+    fn state_key(&self) -> Option<&[u8; 32]> {
+        self.state_key.as_deref()
+    }
+
     /// Get or create Double Ratchet session for a device pair
     pub async fn get_or_create_session(
         &self,
@@ -231,15 +389,13 @@ impl SessionManager {
         remote_user_id: &str,
         remote_device_id: &str,
     ) -> Result<DoubleRatchet> {
-        // Try to load existing session
-        if let Some(session) = self.db.get_ratchet_session_by_devices(
+        if let Some(ratchet) = self.find_session(
             local_user_id,
             local_device_id,
             remote_user_id,
             remote_device_id,
         ).await? {
-            // Deserialize ratchet state
-            return CryptoManager::deserialize_ratchet(&session.ratchet_state);
+            return Ok(ratchet);
         }
 
         // No session exists - need to initialize new one
@@ -249,14 +405,67 @@ impl SessionManager {
         ))
     }
 
+    /// Load the Double Ratchet session for a device pair, if one exists
+    pub async fn find_session(
+        &self,
+        local_user_id: &str,
+        local_device_id: &str,
+        remote_user_id: &str,
+        remote_device_id: &str,
+    ) -> Result<Option<DoubleRatchet>> {
+        if let Some(session) = self.db.get_ratchet_session_by_devices(
+            local_user_id,
+            local_device_id,
+            remote_user_id,
+            remote_device_id,
+        ).await? {
+            // Deserialize ratchet state
+            let ratchet = CryptoManager::deserialize_ratchet(&session.ratchet_state, self.state_key())?;
+
+            // Upgrade sessions stored in an older format right away
+            if !DoubleRatchet::is_current_format(&session.ratchet_state, self.state_key.is_some()) {
+                self.save_session(&session.session_id, &ratchet).await?;
+            }
+
+            return Ok(Some(ratchet));
+        }
+
+        Ok(None)
+    }
+
+    /// Delete sessions between a local device and devices of `remote_user_id`
+    /// that are no longer registered
+    ///
+    /// Returns the IDs of the devices whose sessions were removed.
+    pub async fn prune_sessions(
+        &self,
+        local_user_id: &str,
+        local_device_id: &str,
+        remote_user_id: &str,
+        current_device_ids: &[String],
+    ) -> Result<Vec<String>> {
+        let mut pruned = Vec::new();
+
+        for session in self.db.list_ratchet_sessions_for_device(local_user_id, local_device_id).await? {
+            if session.remote_user_id == remote_user_id
+                && !current_device_ids.contains(&session.remote_device_id)
+            {
+                self.db.delete_ratchet_session(&session.session_id).await?;
+                pruned.push(session.remote_device_id.clone());
+            }
+        }
+
+        Ok(pruned)
+    }
+
     /// Save Double Ratchet session after encryption/decryption
     pub async fn save_session(
         &self,
         session_id: &str,
         ratchet: &DoubleRatchet,
     ) -> Result<()> {
-        let new_state = CryptoManager::serialize_ratchet(ratchet)?;
-        self.db.update_ratchet_session_state(session_id, new_state).await?;
+        let new_state = CryptoManager::serialize_ratchet(ratchet, self.state_key())?;
+        self.db.update_ratchet_session_state(session_id, &new_state).await?;
         Ok(())
     }
 
@@ -287,6 +496,13 @@ impl SessionManager {
     }
 }
 
+/// Parse a hex-encoded 32-byte ratchet state key
+fn parse_state_key(value: &str) -> Option<Zeroizing<[u8; 32]>> {
+    let bytes = Zeroizing::new(hex::decode(value.trim()).ok()?);
+    let key: [u8; 32] = bytes.as_slice().try_into().ok()?;
+    Some(Zeroizing::new(key))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -309,4 +525,13 @@ mod tests {
         assert_eq!(id1, id2);
         assert_eq!(id1, "user1:dev1:user2:dev2");
     }
+
+    #[test]
+    fn test_parse_state_key() {
+        let key = parse_state_key(&"ab".repeat(32)).unwrap();
+        assert_eq!(*key, [0xab; 32]);
+
+        assert!(parse_state_key("abcd").is_none());
+        assert!(parse_state_key(&"zz".repeat(32)).is_none());
+    }
 }
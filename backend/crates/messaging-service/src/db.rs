@@ -4,16 +4,34 @@
 /// - ScyllaDB: Message history, media metadata
 
 use crate::models::*;
+use guardyn_common::jwks::{JwkSet, JWKS_KEY};
+use guardyn_common::revocation::{Revocation, REVOCATION_PREFIX};
 use anyhow::{Context, Result};
 use serde_json;
-use tikv_client::{RawClient, TransactionClient};
+use tikv_client::{Error as TikvError, RawClient, Transaction, TransactionClient};
 use scylla::{Session, SessionBuilder};
 use scylla::statement::Consistency;
 use std::sync::Arc;
+use zeroize::Zeroize;
+
+/// Attempts for a transaction that lost a write conflict
+const TRANSACTION_ATTEMPTS: u32 = 3;
+
+/// Outcome of a transactional compare-and-swap
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CasOutcome {
+    /// The keys held the expected values and the writes were applied
+    Swapped,
+    /// Some key held a different value; the current values of all compared
+    /// keys are returned, in order
+    Mismatch(Vec<Option<Vec<u8>>>),
+}
 
 /// Combined database client
 pub struct DatabaseClient {
     tikv: Arc<RawClient>,
+    /// Transactional TiKV client (keys written here are never read through `tikv`)
+    txn: Arc<TransactionClient>,
     scylla: Arc<Session>,
     /// Consistency level for ScyllaDB queries (configurable via SCYLLA_CONSISTENCY env var)
     consistency: Consistency,
@@ -27,10 +45,14 @@ impl DatabaseClient {
     /// - SCYLLA_REPLICATION_FACTOR: integer (default: 3 for production, use 1 for local dev)
     pub async fn new(tikv_endpoints: Vec<String>, scylla_nodes: Vec<String>) -> Result<Self> {
         // Connect to TiKV
-        let tikv = RawClient::new(tikv_endpoints)
+        let tikv = RawClient::new(tikv_endpoints.clone())
             .await
             .context("Failed to connect to TiKV")?;
 
+        let txn = TransactionClient::new(tikv_endpoints)
+            .await
+            .context("Failed to connect to TiKV (transactional)")?;
+
         // Determine consistency level from environment
         let consistency = match std::env::var("SCYLLA_CONSISTENCY")
             .unwrap_or_else(|_| "local_quorum".to_string())
@@ -58,6 +80,7 @@ impl DatabaseClient {
 
         Ok(Self {
             tikv: Arc::new(tikv),
+            txn: Arc::new(txn),
             scylla: Arc::new(scylla),
             consistency,
         })
@@ -104,6 +127,8 @@ impl DatabaseClient {
                     delivery_status INT,
                     is_deleted BOOLEAN,
                     x3dh_prekey TEXT,
+                    device_copy BOOLEAN,
+                    franking_tag BLOB,
                     PRIMARY KEY (conversation_id, message_id)
                 ) WITH CLUSTERING ORDER BY (message_id DESC)",
                 &[],
@@ -152,6 +177,22 @@ impl DatabaseClient {
             )
             .await;
 
+        // Migration: Mark per-device copies of multi-device sends
+        let _ = session
+            .query_unpaged(
+                "ALTER TABLE guardyn.messages ADD device_copy BOOLEAN",
+                &[],
+            )
+            .await;
+
+        // Migration: Server-signed franking tags for abuse reports
+        let _ = session
+            .query_unpaged(
+                "ALTER TABLE guardyn.messages ADD franking_tag BLOB",
+                &[],
+            )
+            .await;
+
         // Create conversations table for efficient conversation list queries
         // Partition by user_id allows single-query retrieval of all conversations
         // Stores conversation metadata for both participants (denormalized for read performance)
@@ -228,11 +269,190 @@ impl DatabaseClient {
         Ok(())
     }
 
+    /// Put multiple key-value pairs into TiKV in a single batch
+    pub async fn batch_put(&self, pairs: Vec<(Vec<u8>, Vec<u8>)>) -> Result<()> {
+        if pairs.is_empty() {
+            return Ok(());
+        }
+        self.tikv
+            .batch_put(pairs)
+            .await
+            .context("TiKV batch put failed")?;
+        Ok(())
+    }
+
+    /// Delete multiple keys from TiKV in a single batch
+    pub async fn batch_delete(&self, keys: Vec<Vec<u8>>) -> Result<()> {
+        if keys.is_empty() {
+            return Ok(());
+        }
+        self.tikv
+            .batch_delete(keys)
+            .await
+            .context("TiKV batch delete failed")?;
+        Ok(())
+    }
+
+    /// Get all key-value pairs whose key starts with `prefix`
+    ///
+    /// Unlike an open-ended scan, the range is bounded to the prefix and
+    /// results are paged, so all matching entries are returned.
+    pub async fn scan_prefix(&self, prefix: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        const PAGE_SIZE: u32 = 1000;
+
+        let end = prefix_end(prefix);
+        let mut start = prefix.to_vec();
+        let mut pairs = Vec::new();
+
+        loop {
+            let page = self
+                .tikv
+                .scan(start.clone()..end.clone(), PAGE_SIZE)
+                .await
+                .context("TiKV scan failed")?;
+            let page_len = page.len();
+
+            for kv_pair in page {
+                pairs.push((Vec::<u8>::from(kv_pair.0), kv_pair.1));
+            }
+
+            if page_len < PAGE_SIZE as usize {
+                break;
+            }
+
+            // Continue right after the last returned key
+            start = pairs.last().map(|(key, _)| key.clone()).unwrap_or_default();
+            start.push(0);
+        }
+
+        Ok(pairs)
+    }
+
+    // ========================================================================
+    // Transactional TiKV Operations
+    // ========================================================================
+
+    /// Atomically apply `writes` if every compared key holds its expected value
+    ///
+    /// An expected value of `None` means the key must not exist, and a write
+    /// of `None` deletes the key. A transaction that loses a write conflict is
+    /// retried and then sees the winner's values, so exactly one of several
+    /// concurrent callers observes `Swapped`.
+    pub async fn compare_and_swap(
+        &self,
+        expected: &[(Vec<u8>, Option<Vec<u8>>)],
+        writes: &[(Vec<u8>, Option<Vec<u8>>)],
+    ) -> Result<CasOutcome> {
+        let mut attempt = 1;
+
+        loop {
+            let mut txn = self.txn.begin_optimistic().await?;
+
+            let result = async {
+                let mut current = Vec::with_capacity(expected.len());
+                for (key, _) in expected {
+                    current.push(txn.get(key.clone()).await?);
+                }
+                if current.iter().zip(expected).any(|(value, (_, expected))| value != expected) {
+                    return Ok::<_, TikvError>(CasOutcome::Mismatch(current));
+                }
+
+                for (key, value) in writes {
+                    match value {
+                        Some(value) => txn.put(key.clone(), value.clone()).await?,
+                        None => txn.delete(key.clone()).await?,
+                    }
+                }
+
+                Ok(CasOutcome::Swapped)
+            }
+            .await;
+
+            match finish_transaction(txn, result).await {
+                Ok(outcome) => return Ok(outcome),
+                Err(e) if attempt < TRANSACTION_ATTEMPTS => {
+                    tracing::debug!("Retrying compare-and-swap: {}", e);
+                    attempt += 1;
+                }
+                Err(e) => return Err(anyhow::Error::from(e).context("TiKV compare-and-swap failed")),
+            }
+        }
+    }
+
+    /// Get a value written through the transactional client
+    pub async fn txn_get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let mut snapshot = self.txn.begin_optimistic().await?;
+        let result = snapshot.get(key.to_vec()).await;
+        Ok(finish_transaction(snapshot, result)
+            .await
+            .context("TiKV transactional get failed")?)
+    }
+
+    /// Get key-value pairs written through the transactional client in `start..end`
+    pub async fn txn_scan(&self, start: &[u8], end: &[u8], limit: u32) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let mut snapshot = self.txn.begin_optimistic().await?;
+        let result = async {
+            Ok::<_, TikvError>(
+                snapshot
+                    .scan(start.to_vec()..end.to_vec(), limit)
+                    .await?
+                    .map(|kv_pair| (Vec::<u8>::from(kv_pair.0), kv_pair.1))
+                    .collect(),
+            )
+        }
+        .await;
+
+        Ok(finish_transaction(snapshot, result)
+            .await
+            .context("TiKV transactional scan failed")?)
+    }
+
+    /// Get all key-value pairs written through the transactional client whose
+    /// key starts with `prefix`, from a single snapshot
+    pub async fn txn_scan_prefix(&self, prefix: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        const PAGE_SIZE: u32 = 1000;
+
+        let end = prefix_end(prefix);
+        let mut snapshot = self.txn.begin_optimistic().await?;
+        let result = async {
+            let mut start = prefix.to_vec();
+            let mut pairs = Vec::new();
+
+            loop {
+                let page: Vec<_> = snapshot
+                    .scan(start.clone()..end.clone(), PAGE_SIZE)
+                    .await?
+                    .map(|kv_pair| (Vec::<u8>::from(kv_pair.0), kv_pair.1))
+                    .collect();
+                let page_len = page.len();
+                pairs.extend(page);
+
+                if page_len < PAGE_SIZE as usize {
+                    return Ok::<_, TikvError>(pairs);
+                }
+
+                // Continue right after the last returned key
+                start = pairs.last().map(|(key, _)| key.clone()).unwrap_or_default();
+                start.push(0);
+            }
+        }
+        .await;
+
+        Ok(finish_transaction(snapshot, result)
+            .await
+            .context("TiKV transactional scan failed")?)
+    }
+
     /// Get a message by ID from ScyllaDB (for E2EE decryption)
     pub async fn get_message(&self, message_id: &str) -> Result<Option<StoredMessage>> {
         // Note: This is inefficient as it requires scanning, but needed for E2EE
         // In production, consider maintaining a message_id -> conversation_id index
-        let query = "SELECT * FROM guardyn.messages WHERE message_id = ? ALLOW FILTERING";
+        let query = "SELECT conversation_id, message_id, sender_user_id, sender_device_id, \
+                            recipient_user_id, recipient_device_id, encrypted_content, \
+                            message_type, server_timestamp, client_timestamp, \
+                            delivery_status, is_deleted, x3dh_prekey, device_copy, \
+                            franking_tag \
+                     FROM guardyn.messages WHERE message_id = ? ALLOW FILTERING";
         let message_uuid = uuid::Uuid::parse_str(message_id)
             .context("Invalid message_id UUID")?;
 
@@ -285,6 +505,12 @@ impl DatabaseClient {
             let x3dh_prekey: Option<String> = row.columns[12].as_ref()
                 .and_then(|v| v.as_text())
                 .map(|s| s.to_string());
+            let device_copy: bool = row.columns[13].as_ref()
+                .and_then(|v| v.as_boolean())
+                .unwrap_or(false);
+            let franking_tag: Option<Vec<u8>> = row.columns[14].as_ref()
+                .and_then(|v| v.as_blob())
+                .map(|b| b.to_vec());
 
             let msg = StoredMessage {
                 conversation_id: conversation_id.to_string(),
@@ -300,6 +526,8 @@ impl DatabaseClient {
                 delivery_status,
                 is_deleted,
                 x3dh_prekey,
+                device_copy,
+                franking_tag,
             };
             Ok(Some(msg))
         } else {
@@ -385,8 +613,8 @@ impl DatabaseClient {
             conversation_id, message_id, sender_user_id, sender_device_id,
             recipient_user_id, recipient_device_id, encrypted_content,
             message_type, server_timestamp, client_timestamp,
-            delivery_status, is_deleted, x3dh_prekey
-        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
+            delivery_status, is_deleted, x3dh_prekey, device_copy, franking_tag
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
 
         tracing::debug!("Parsing conversation_id: {}", msg.conversation_id);
         let conversation_uuid = uuid::Uuid::parse_str(&msg.conversation_id)
@@ -402,7 +630,7 @@ impl DatabaseClient {
                 e
             })?;
 
-        tracing::debug!("Executing ScyllaDB query with {} params", 13);
+        tracing::debug!("Executing ScyllaDB query with {} params", 15);
         let mut scylla_query = scylla::query::Query::new(query);
         scylla_query.set_consistency(self.consistency);
         let result = self.scylla
@@ -422,6 +650,8 @@ impl DatabaseClient {
                     msg.delivery_status,
                     msg.is_deleted,
                     &msg.x3dh_prekey,
+                    msg.device_copy,
+                    &msg.franking_tag,
                 ),
             )
             .await;
@@ -447,7 +677,8 @@ impl DatabaseClient {
         let query = "SELECT conversation_id, message_id, sender_user_id, sender_device_id, \
                             recipient_user_id, recipient_device_id, encrypted_content, \
                             message_type, server_timestamp, client_timestamp, \
-                            delivery_status, is_deleted, x3dh_prekey \
+                            delivery_status, is_deleted, x3dh_prekey, device_copy, \
+                            franking_tag \
                      FROM guardyn.messages 
                      WHERE conversation_id = ? 
                      LIMIT ?";
@@ -466,7 +697,8 @@ impl DatabaseClient {
                 // 0: conversation_id, 1: message_id, 2: sender_user_id, 3: sender_device_id,
                 // 4: recipient_user_id, 5: recipient_device_id (nullable), 6: encrypted_content,
                 // 7: message_type, 8: server_timestamp, 9: client_timestamp,
-                // 10: delivery_status, 11: is_deleted, 12: x3dh_prekey (nullable)
+                // 10: delivery_status, 11: is_deleted, 12: x3dh_prekey (nullable),
+                // 13: device_copy (nullable), 14: franking_tag (nullable)
 
                 // Safe extraction with error context
                 let conversation_id = row.columns.get(0)
@@ -540,6 +772,16 @@ impl DatabaseClient {
                     .and_then(|c| c.as_text())
                     .map(|s| s.to_string()); // Nullable field
 
+                let device_copy = row.columns.get(13)
+                    .and_then(|c| c.as_ref())
+                    .and_then(|c| c.as_boolean())
+                    .unwrap_or(false); // Nullable field (added by migration)
+
+                let franking_tag = row.columns.get(14)
+                    .and_then(|c| c.as_ref())
+                    .and_then(|c| c.as_blob())
+                    .map(|b| b.to_vec()); // Nullable field (added by migration)
+
                 let msg = StoredMessage {
                     conversation_id,
                     message_id,
@@ -554,6 +796,8 @@ impl DatabaseClient {
                     delivery_status,
                     is_deleted,
                     x3dh_prekey,
+                    device_copy,
+                    franking_tag,
                 };
                 messages.push(msg);
             }
@@ -718,6 +962,8 @@ impl DatabaseClient {
                     media_id: String::new(),
                     is_deleted,
                     x3dh_prekey,
+                    sealed: false,
+                    franking_tag: vec![],
                 };
 
                 // Update or create conversation
@@ -995,6 +1241,8 @@ impl DatabaseClient {
                     media_id: String::new(),
                     is_deleted: false,
                     x3dh_prekey: String::new(),
+                    sealed: false,
+                    franking_tag: vec![],
                 };
 
                 let conversation = crate::proto::messaging::Conversation {
@@ -1310,6 +1558,81 @@ impl DatabaseClient {
         Ok(())
     }
 
+    // ========================================================================
+    // Sealed Sender Delivery Tokens (TiKV)
+    // ========================================================================
+
+    /// Store the hash of a user's sealed sender delivery token
+    pub async fn set_delivery_token_hash(&self, user_id: &str, token_hash: &[u8]) -> Result<()> {
+        let key = format!("/sealed_sender/delivery_tokens/{}", user_id);
+        self.tikv.put(key.into_bytes(), token_hash.to_vec()).await?;
+        Ok(())
+    }
+
+    /// Get the hash of a user's sealed sender delivery token
+    pub async fn get_delivery_token_hash(&self, user_id: &str) -> Result<Option<Vec<u8>>> {
+        let key = format!("/sealed_sender/delivery_tokens/{}", user_id);
+        Ok(self.tikv.get(key.into_bytes()).await?)
+    }
+
+    /// Remove a user's delivery token, disabling sealed sender delivery to them
+    pub async fn delete_delivery_token(&self, user_id: &str) -> Result<()> {
+        let key = format!("/sealed_sender/delivery_tokens/{}", user_id);
+        self.tikv.delete(key.into_bytes()).await?;
+        Ok(())
+    }
+
+    // ========================================================================
+    // Message Franking (TiKV)
+    // ========================================================================
+
+    /// Store the server-signed franking tag of a message
+    pub async fn store_franking_tag(&self, message_id: &str, tag: &[u8]) -> Result<()> {
+        let key = format!("/franking/tags/{}", message_id);
+        self.tikv.put(key.into_bytes(), tag.to_vec()).await?;
+        Ok(())
+    }
+
+    /// Get the server-signed franking tag of a message
+    pub async fn get_franking_tag(&self, message_id: &str) -> Result<Option<Vec<u8>>> {
+        let key = format!("/franking/tags/{}", message_id);
+        Ok(self.tikv.get(key.into_bytes()).await?)
+    }
+
+    /// Store a verified abuse report (one per reporter and message)
+    pub async fn store_abuse_report(&self, report: &AbuseReport) -> Result<()> {
+        let key = format!("/franking/reports/{}/{}", report.message_id, report.reporter_user_id);
+        let value = serde_json::to_vec(report)?;
+        self.tikv.put(key.into_bytes(), value).await?;
+        Ok(())
+    }
+
+    // ========================================================================
+    // Session Revocations (TiKV, written by auth-service)
+    // ========================================================================
+
+    /// Load the active session revocations
+    pub async fn load_revocations(&self) -> Result<Vec<Revocation>> {
+        let pairs = self.scan_prefix(REVOCATION_PREFIX.as_bytes()).await?;
+        Ok(pairs
+            .into_iter()
+            .filter_map(|(_, value)| serde_json::from_slice::<Revocation>(&value).ok())
+            .filter(|revocation| !revocation.is_expired())
+            .collect())
+    }
+
+    // ========================================================================
+    // Token Verification Keys (TiKV, written by auth-service)
+    // ========================================================================
+
+    /// Load the published token verification keys
+    pub async fn load_jwks(&self) -> Result<Option<JwkSet>> {
+        match self.tikv.get(JWKS_KEY.as_bytes().to_vec()).await? {
+            Some(value) => Ok(Some(serde_json::from_slice(&value)?)),
+            None => Ok(None),
+        }
+    }
+
     // ========================================================================
     // Double Ratchet Session Management (TiKV)
     // ========================================================================
@@ -1369,14 +1692,15 @@ impl DatabaseClient {
     pub async fn update_ratchet_session_state(
         &self,
         session_id: &str,
-        new_state: Vec<u8>,
+        new_state: &[u8],
     ) -> Result<()> {
         let mut session = self
             .get_ratchet_session(session_id)
             .await?
             .context("Ratchet session not found")?;
 
-        session.ratchet_state = new_state;
+        session.ratchet_state.zeroize();
+        session.ratchet_state = new_state.to_vec();
         session.updated_at = chrono::Utc::now().timestamp();
 
         self.store_ratchet_session(&session).await?;
@@ -1408,6 +1732,17 @@ impl DatabaseClient {
         Ok(())
     }
 
+    /// List all stored ratchet sessions
+    pub async fn list_ratchet_sessions(&self) -> Result<Vec<RatchetSession>> {
+        let pairs = self.scan_prefix(b"/ratchet_sessions/").await?;
+
+        pairs
+            .into_iter()
+            .filter(|(key, _)| !key.starts_with(b"/ratchet_sessions/user/"))
+            .map(|(_, value)| serde_json::from_slice(&value).context("Malformed ratchet session"))
+            .collect()
+    }
+
     /// List all sessions for a user+device
     pub async fn list_ratchet_sessions_for_device(
         &self,
@@ -1415,12 +1750,12 @@ impl DatabaseClient {
         device_id: &str,
     ) -> Result<Vec<RatchetSession>> {
         let prefix = format!("/ratchet_sessions/user/{}/{}/", user_id, device_id);
-        let keys = self.tikv.scan(prefix.into_bytes().., 1000).await?;
+        let pairs = self.scan_prefix(prefix.as_bytes()).await?;
 
         let mut sessions = Vec::new();
-        for kv_pair in keys {
+        for (_key, value) in pairs {
             // Value is session_id, need to fetch full session
-            if let Ok(session_id) = String::from_utf8(kv_pair.1) {
+            if let Ok(session_id) = String::from_utf8(value) {
                 if let Ok(Some(session)) = self.get_ratchet_session(&session_id).await {
                     sessions.push(session);
                 }
@@ -1545,3 +1880,44 @@ impl DatabaseClient {
 }
 
 
+/// Commit `txn` if `result` is Ok, roll it back otherwise
+async fn finish_transaction<T>(mut txn: Transaction, result: Result<T, TikvError>) -> Result<T, TikvError> {
+    match result {
+        Ok(value) => {
+            txn.commit().await?;
+            Ok(value)
+        }
+        Err(e) => {
+            if let Err(rollback_error) = txn.rollback().await {
+                tracing::warn!("Failed to roll back transaction: {}", rollback_error);
+            }
+            Err(e)
+        }
+    }
+}
+
+/// Compute the exclusive upper bound of the key range sharing `prefix`
+///
+/// Trailing 0xFF bytes are dropped and the last remaining byte is incremented.
+/// Key prefixes used by this service are ASCII paths, so the result is never empty.
+pub(crate) fn prefix_end(prefix: &[u8]) -> Vec<u8> {
+    let mut end = prefix.to_vec();
+    while let Some(last) = end.pop() {
+        if last < u8::MAX {
+            end.push(last + 1);
+            break;
+        }
+    }
+    end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefix_end() {
+        assert_eq!(prefix_end(b"/mls/groups/g1/storage/"), b"/mls/groups/g1/storage0".to_vec());
+        assert_eq!(prefix_end(&[0x61, 0xFF]), vec![0x62]);
+    }
+}
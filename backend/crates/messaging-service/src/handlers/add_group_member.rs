@@ -14,10 +14,8 @@ pub async fn add_group_member(
     db: Arc<DatabaseClient>,
 ) -> Result<Response<AddGroupMemberResponse>, Status> {
     // Validate JWT token and extract user_id (requester)
-    let jwt_secret = std::env::var("JWT_SECRET")
-        .unwrap_or_else(|_| "default-jwt-secret-change-in-production".to_string());
     
-    let (requester_user_id, _device_id, _username) = match crate::jwt::validate_and_extract(&request.access_token, &jwt_secret) {
+    let (requester_user_id, _device_id, _username) = match crate::jwt::validate_and_extract(&request.access_token) {
         Ok(ids) => ids,
         Err(_) => {
             return Ok(Response::new(AddGroupMemberResponse {
@@ -1,18 +1,22 @@
 /// Handler for adding members to MLS-encrypted group chats
 ///
-/// Uses MLS protocol to securely add new members to the group.
-/// Creates Welcome messages for new members and Commit messages for existing members.
+/// The requester's device creates the Commit adding the member's key package
+/// and the Welcome for the new member. The server checks that the commit
+/// only adds the requested device and is signed by the requester, then
+/// fences it through the MLS Delivery Service, so a concurrent change to the
+/// same group epoch is rejected with a retryable error.
 
 use crate::db::DatabaseClient;
-use crate::mls_manager::MlsManager;
-use crate::models::{GroupMember, GroupRole};
+use crate::mls_manager::{
+    DeliveryService, GroupStateChanged, HandshakeRecord, MemberDevice, MlsManager, StaleEpochError,
+};
 use crate::nats::NatsClient;
 use crate::proto::messaging::{
     add_group_member_response, AddGroupMemberRequest, AddGroupMemberResponse,
     AddGroupMemberSuccess,
 };
 use crate::proto::common::ErrorResponse;
-use guardyn_crypto::mls::MlsGroupManager;
+use guardyn_crypto::mls::message_epoch;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tonic::{Response, Status};
@@ -22,14 +26,10 @@ pub async fn add_group_member_mls(
     request: AddGroupMemberRequest,
     db: Arc<DatabaseClient>,
     nats: Arc<NatsClient>,
-    auth_service_url: String,
 ) -> Result<Response<AddGroupMemberResponse>, Status> {
     // Validate JWT token and extract user_id (requester)
-    let jwt_secret = std::env::var("JWT_SECRET")
-        .unwrap_or_else(|_| "default-jwt-secret-change-in-production".to_string());
-
     let (requester_user_id, requester_device_id, _username) =
-        match crate::jwt::validate_and_extract(&request.access_token, &jwt_secret) {
+        match crate::jwt::validate_and_extract(&request.access_token) {
             Ok(ids) => ids,
             Err(_) => {
                 return Ok(Response::new(AddGroupMemberResponse {
@@ -75,6 +75,16 @@ pub async fn add_group_member_mls(
         }));
     }
 
+    if request.commit.is_empty() || request.welcome.is_empty() || request.group_info.is_empty() {
+        return Ok(Response::new(AddGroupMemberResponse {
+            result: Some(add_group_member_response::Result::Error(ErrorResponse {
+                code: crate::proto::common::error_response::ErrorCode::InvalidRequest as i32,
+                message: "Commit, Welcome and GroupInfo required for MLS".to_string(),
+                details: HashMap::new(),
+            })),
+        }));
+    }
+
     info!(
         "Adding member {}:{} to MLS group {}",
         request.member_user_id, request.member_device_id, request.group_id
@@ -171,176 +181,179 @@ pub async fn add_group_member_mls(
         }
     }
 
-    // Fetch member's MLS key package from auth-service
-    info!(
-        "Fetching MLS key package for {}:{}",
-        request.member_user_id, request.member_device_id
-    );
-
-    // Create auth client and fetch key package
-    let mut auth_client = match crate::auth_client::AuthClient::new(&auth_service_url).await {
-        Ok(client) => client,
-        Err(e) => {
-            error!("Failed to connect to auth-service: {}", e);
-            return Ok(Response::new(AddGroupMemberResponse {
-                result: Some(add_group_member_response::Result::Error(ErrorResponse {
-                    code: crate::proto::common::error_response::ErrorCode::InternalError as i32,
-                    message: "Failed to connect to auth service".to_string(),
-                    details: { let mut map = HashMap::new(); map.insert("error".to_string(), e.to_string()); map },
-                })),
-            }));
-        }
-    };
-
-    let member_key_package_bytes = match auth_client
-        .fetch_mls_key_package(&request.member_user_id, &request.member_device_id)
-        .await
-    {
-        Ok(key_package) => key_package,
+    // Load group from TiKV at its current epoch
+    let mut group_manager = match mls_manager.load_group(&request.group_id).await {
+        Ok(manager) => manager,
         Err(e) => {
-            error!("Failed to fetch MLS key package: {}", e);
+            error!("Failed to load group state: {}", e);
+            let mut details = HashMap::new();
+            details.insert("error".to_string(), e.to_string());
             return Ok(Response::new(AddGroupMemberResponse {
                 result: Some(add_group_member_response::Result::Error(ErrorResponse {
                     code: crate::proto::common::error_response::ErrorCode::NotFound as i32,
-                    message: "MLS key package not found for user".to_string(),
-                    details: { let mut map = HashMap::new(); map.insert("error".to_string(), e.to_string()); map },
+                    message: "Group state not found".to_string(),
+                    details,
                 })),
             }));
         }
     };
 
     info!(
-        "Successfully fetched MLS key package ({} bytes)",
-        member_key_package_bytes.len()
+        "Loaded MLS group {} at epoch {}",
+        request.group_id,
+        group_manager.epoch()
     );
 
-    // Load group state from TiKV
-    let group_state = match mls_manager.load_group_state(&request.group_id).await {
-        Ok(state) => state,
+    // Devices that receive the commit: the members before this change
+    let members = match mls_manager.member_devices(&request.group_id).await {
+        Ok(members) => members,
         Err(e) => {
-            error!("Failed to load group state: {}", e);
-            let mut details = HashMap::new();
-            details.insert("error".to_string(), e.to_string());
+            error!("Failed to list group members: {}", e);
             return Ok(Response::new(AddGroupMemberResponse {
                 result: Some(add_group_member_response::Result::Error(ErrorResponse {
-                    code: crate::proto::common::error_response::ErrorCode::NotFound as i32,
-                    message: "Group state not found".to_string(),
-                    details,
+                    code: crate::proto::common::error_response::ErrorCode::InternalError as i32,
+                    message: "Failed to list group members".to_string(),
+                    details: { let mut map = HashMap::new(); map.insert("error".to_string(), e.to_string()); map },
                 })),
             }));
         }
     };
 
-    // Reconstruct MLS group manager (this is the OpenMLS limitation)
-    // For MVP, we'll create a new group manager and rely on epoch tracking
-    // TODO: Implement state caching or upgrade OpenMLS version with deserialization
-    info!(
-        "Reconstructing MLS group manager for group {} (epoch {})",
-        request.group_id, group_state.epoch
-    );
-
-    // Create MLS group manager with identity from requester
-    // NOTE: This is a workaround for OpenMLS deserialization limitation
-    // In production, we need to maintain in-memory group managers or implement custom serialization
-    let requester_identity = format!("{}:{}", requester_user_id, requester_device_id);
-    let credential_bundle = match guardyn_crypto::mls::create_test_credential(requester_identity.as_bytes()) {
-        Ok(bundle) => bundle,
+    let commit_epoch = match message_epoch(&request.commit) {
+        Ok(epoch) => epoch,
         Err(e) => {
-            error!("Failed to create MLS credential: {}", e);
-            let mut details = HashMap::new();
-            details.insert("error".to_string(), e.to_string());
             return Ok(Response::new(AddGroupMemberResponse {
                 result: Some(add_group_member_response::Result::Error(ErrorResponse {
-                    code: crate::proto::common::error_response::ErrorCode::InternalError as i32,
-                    message: "Failed to create MLS credential".to_string(),
-                    details,
+                    code: crate::proto::common::error_response::ErrorCode::InvalidRequest as i32,
+                    message: "Invalid commit".to_string(),
+                    details: { let mut map = HashMap::new(); map.insert("error".to_string(), e.to_string()); map },
                 })),
             }));
         }
     };
 
-    let mut group_manager = match MlsGroupManager::create_group(
-        &request.group_id,
-        requester_identity.as_bytes(),
-        credential_bundle,
-    ) {
-        Ok(manager) => manager,
+    // A commit created in an older epoch cannot be applied; the device
+    // catches up on the handshake log and creates it again
+    if commit_epoch != group_manager.epoch() {
+        let mut details = HashMap::new();
+        details.insert("retryable".to_string(), "true".to_string());
+        details.insert("current_epoch".to_string(), group_manager.epoch().to_string());
+        return Ok(Response::new(AddGroupMemberResponse {
+            result: Some(add_group_member_response::Result::Error(ErrorResponse {
+                code: crate::proto::common::error_response::ErrorCode::Conflict as i32,
+                message: "Group changed concurrently, retry the request".to_string(),
+                details,
+            })),
+        }));
+    }
+
+    // Apply the commit to the public group state
+    let summary = match group_manager.process_commit(&request.commit) {
+        Ok(summary) => summary,
         Err(e) => {
-            error!("Failed to create MLS group manager: {}", e);
-            let mut details = HashMap::new();
-            details.insert("error".to_string(), e.to_string());
+            error!("Failed to process commit: {}", e);
             return Ok(Response::new(AddGroupMemberResponse {
                 result: Some(add_group_member_response::Result::Error(ErrorResponse {
-                    code: crate::proto::common::error_response::ErrorCode::InternalError as i32,
-                    message: "Failed to initialize MLS group manager".to_string(),
-                    details,
+                    code: crate::proto::common::error_response::ErrorCode::InvalidRequest as i32,
+                    message: "Invalid commit".to_string(),
+                    details: { let mut map = HashMap::new(); map.insert("error".to_string(), e.to_string()); map },
                 })),
             }));
         }
     };
 
-    // Add member to MLS group (generates Commit and Welcome messages)
-    let (commit_message, welcome_message) =
-        match group_manager.add_member(&member_key_package_bytes) {
-            Ok(messages) => messages,
-            Err(e) => {
-                error!("Failed to add member to MLS group: {}", e);
-                return Ok(Response::new(AddGroupMemberResponse {
-                    result: Some(add_group_member_response::Result::Error(ErrorResponse {
-                        code: crate::proto::common::error_response::ErrorCode::InternalError
-                            as i32,
-                        message: "Failed to add member to MLS group".to_string(),
-                        details: { let mut map = HashMap::new(); map.insert("error".to_string(), e.to_string()); map },
-                    })),
-                }));
-            }
-        };
+    // The commit must come from the requester's leaf and add exactly the
+    // requested device
+    let adds_member = matches!(
+        summary.added.as_slice(),
+        [identity] if identity.user_id == request.member_user_id
+            && identity.device_id == request.member_device_id
+    );
+    if summary.external
+        || summary.committer.user_id != requester_user_id
+        || summary.committer.device_id != requester_device_id
+        || !adds_member
+        || !summary.removed.is_empty()
+        || !summary.updated.is_empty()
+        || summary.other_proposals > 0
+    {
+        error!(
+            "Commit of {}:{} for group {} does not only add {}:{}",
+            requester_user_id,
+            requester_device_id,
+            request.group_id,
+            request.member_user_id,
+            request.member_device_id
+        );
+        return Ok(Response::new(AddGroupMemberResponse {
+            result: Some(add_group_member_response::Result::Error(ErrorResponse {
+                code: crate::proto::common::error_response::ErrorCode::InvalidRequest as i32,
+                message: "Commit must only add the requested member device".to_string(),
+                details: HashMap::new(),
+            })),
+        }));
+    }
 
-    info!("MLS member addition successful, saving group state");
+    // Devices joining by external commit later fetch this GroupInfo
+    if let Err(e) = group_manager.publish_group_info(request.group_info) {
+        return Ok(Response::new(AddGroupMemberResponse {
+            result: Some(add_group_member_response::Result::Error(ErrorResponse {
+                code: crate::proto::common::error_response::ErrorCode::InvalidRequest as i32,
+                message: "Invalid GroupInfo".to_string(),
+                details: { let mut map = HashMap::new(); map.insert("error".to_string(), format!("{:#}", e)); map },
+            })),
+        }));
+    }
 
-    // Serialize updated group state
-    let updated_state = match group_manager.serialize_state() {
-        Ok(state) => state,
-        Err(e) => {
-            error!("Failed to serialize group state: {}", e);
+    info!("MLS member addition validated, submitting commit to the Delivery Service");
+
+    let delivery_service = DeliveryService::new(db.clone(), nats.clone());
+    let record = HandshakeRecord {
+        group_id: request.group_id.clone(),
+        epoch: commit_epoch,
+        sender_user_id: requester_user_id.clone(),
+        sender_device_id: requester_device_id.clone(),
+        commit: request.commit,
+        welcome: Some(request.welcome),
+        welcome_recipients: vec![MemberDevice {
+            user_id: request.member_user_id.clone(),
+            device_id: request.member_device_id.clone(),
+        }],
+        accepted_at: chrono::Utc::now().timestamp(),
+    };
+
+    // Only one commit per epoch is accepted; the fence moves in the same
+    // transaction that saves the group state, so a losing concurrent commit
+    // never overwrites it
+    if let Err(e) = delivery_service.accept_commit(&record, &mut group_manager).await {
+        let stale = e.downcast_ref::<StaleEpochError>();
+        if stale.is_some() || e.is::<GroupStateChanged>() {
+            info!("Rejected commit for MLS group {}: {}", request.group_id, e);
             let mut details = HashMap::new();
-            details.insert("error".to_string(), e.to_string());
+            details.insert("retryable".to_string(), "true".to_string());
+            if let Some(stale) = stale {
+                details.insert("current_epoch".to_string(), stale.current_epoch.to_string());
+            }
             return Ok(Response::new(AddGroupMemberResponse {
                 result: Some(add_group_member_response::Result::Error(ErrorResponse {
-                    code: crate::proto::common::error_response::ErrorCode::InternalError as i32,
-                    message: "Failed to serialize group state".to_string(),
+                    code: crate::proto::common::error_response::ErrorCode::Conflict as i32,
+                    message: "Group changed concurrently, retry the request".to_string(),
                     details,
                 })),
             }));
         }
-    };
 
-    // Save updated group state (epoch incremented)
-    if let Err(e) = mls_manager
-        .save_group_state(&request.group_id, &updated_state)
-        .await
-    {
-        error!("Failed to save group state: {}", e);
-        let mut details = HashMap::new();
-        details.insert("error".to_string(), e.to_string());
+        error!("Failed to submit commit to the Delivery Service: {}", e);
         return Ok(Response::new(AddGroupMemberResponse {
             result: Some(add_group_member_response::Result::Error(ErrorResponse {
                 code: crate::proto::common::error_response::ErrorCode::InternalError as i32,
-                message: "Failed to save group state".to_string(),
-                details,
+                message: "Failed to submit group commit".to_string(),
+                details: { let mut map = HashMap::new(); map.insert("error".to_string(), e.to_string()); map },
             })),
         }));
     }
 
     // Add member to TiKV members list
-    let new_member = GroupMember {
-        group_id: request.group_id.clone(),
-        user_id: request.member_user_id.clone(),
-        device_id: request.member_device_id.clone(),
-        role: GroupRole::Member,
-        joined_at: chrono::Utc::now().timestamp(),
-    };
-
     if let Err(e) = mls_manager
         .add_member_to_list(
             &request.group_id, 
@@ -359,25 +372,12 @@ pub async fn add_group_member_mls(
         }));
     }
 
-    // Send Welcome message to new member via NATS
-    let welcome_subject = format!(
-        "messaging.mls.welcome.{}.{}",
-        request.member_user_id, request.member_device_id
-    );
-    if let Err(e) = nats.publish(&welcome_subject, &welcome_message).await {
-        error!("Failed to send Welcome message via NATS: {}", e);
-        // Don't fail the whole operation, member can re-sync
-    } else {
-        info!("Welcome message sent to new member via NATS");
-    }
-
-    // Send Commit message to all existing members via NATS
-    let commit_subject = format!("messaging.mls.commit.{}", request.group_id);
-    if let Err(e) = nats.publish(&commit_subject, &commit_message).await {
-        error!("Failed to send Commit message via NATS: {}", e);
-        // Don't fail the whole operation, members can re-sync
+    // Deliver Commit to existing member devices, then Welcome to the new member
+    if let Err(e) = delivery_service.fan_out(&record, &members).await {
+        error!("Failed to deliver MLS handshake messages via NATS: {}", e);
+        // Don't fail the whole operation, devices catch up from the handshake log
     } else {
-        info!("Commit message broadcasted to existing members via NATS");
+        info!("MLS handshake messages delivered to {} member devices", members.len());
     }
 
     // Return success
@@ -18,10 +18,7 @@ pub async fn clear_chat(
     );
 
     // Validate JWT token
-    let jwt_secret = std::env::var("JWT_SECRET")
-        .unwrap_or_else(|_| "default-jwt-secret-change-in-production".to_string());
-
-    if crate::jwt::validate_and_extract(&request.access_token, &jwt_secret).is_err() {
+    if crate::jwt::validate_and_extract(&request.access_token).is_err() {
         tracing::warn!(
             conversation_id = %request.conversation_id,
             "ClearChat: JWT validation failed"
@@ -1,10 +1,23 @@
 /// Handler for creating group chats
+///
+/// When MLS is enabled, the creator device builds the group itself and
+/// submits its GroupInfo together with the commit adding the initial
+/// members' devices, like any other handshake message. The server never sees
+/// a private key: it checks that every initial member has a key package for
+/// the configured ciphersuite, validates the commit against the public group
+/// state and fences it through the MLS Delivery Service.
+use crate::auth_client::AuthClient;
+use crate::config::MlsConfig;
 use crate::db::DatabaseClient;
+use crate::mls_manager::{DeliveryService, HandshakeRecord, LoadedGroup, MemberDevice, MlsManager};
 use crate::models::{GroupMetadata, GroupMember, GroupRole};
+use crate::nats::NatsClient;
 use crate::proto::messaging::{
     create_group_response, CreateGroupRequest, CreateGroupResponse, CreateGroupSuccess,
 };
 use crate::proto::common::{ErrorResponse, Timestamp};
+use guardyn_crypto::mls::{key_package_ciphersuites, message_epoch, MlsCiphersuite};
+use std::collections::HashMap;
 use std::sync::Arc;
 use tonic::{Response, Status};
 use uuid::Uuid;
@@ -12,12 +25,11 @@ use uuid::Uuid;
 pub async fn create_group(
     request: CreateGroupRequest,
     db: Arc<DatabaseClient>,
+    nats: Arc<NatsClient>,
+    mls_config: &MlsConfig,
 ) -> Result<Response<CreateGroupResponse>, Status> {
     // Validate JWT token and extract user_id (group creator)
-    let jwt_secret = std::env::var("JWT_SECRET")
-        .unwrap_or_else(|_| "default-jwt-secret-change-in-production".to_string());
-
-    let (creator_user_id, creator_device_id, _creator_username) = match crate::jwt::validate_and_extract(&request.access_token, &jwt_secret) {
+    let (creator_user_id, creator_device_id, _creator_username) = match crate::jwt::validate_and_extract(&request.access_token) {
         Ok(ids) => ids,
         Err(_) => {
             return Ok(Response::new(CreateGroupResponse {
@@ -52,18 +64,69 @@ pub async fn create_group(
         }));
     }
 
-    // Generate group ID
-    let group_id = uuid::Uuid::new_v4().to_string();
+    // MLS groups are identified by the group ID the creator device chose
+    let (group_id, mls_enabled) = if request.mls_initial_group_info.is_empty() {
+        let group_id = Uuid::new_v4().to_string();
+        let mls_enabled = mls_config.is_enabled_for_group(&group_id);
+        (group_id, mls_enabled)
+    } else {
+        match guardyn_crypto::mls::MlsPublicGroup::from_group_info(&request.mls_initial_group_info)
+            .ok()
+            .and_then(|group| String::from_utf8(group.group_id()).ok())
+            .filter(|group_id| Uuid::parse_str(group_id).is_ok())
+        {
+            Some(group_id) => (group_id.clone(), mls_config.is_enabled_for_group(&group_id)),
+            None => {
+                return Ok(Response::new(CreateGroupResponse {
+                    result: Some(create_group_response::Result::Error(ErrorResponse {
+                        code: 3, // INVALID_ARGUMENT
+                        message: "MLS GroupInfo must be valid and use a UUID group ID".to_string(),
+                        details: Default::default(),
+                    })),
+                }));
+            }
+        }
+    };
     let timestamp = chrono::Utc::now().timestamp();
 
+    // Member devices of the MLS group, including the creator's
+    let mut mls_members = Vec::new();
+    let mut mls_epoch = 0;
+    if mls_enabled {
+        let creator = MemberDevice {
+            user_id: creator_user_id.clone(),
+            device_id: creator_device_id.clone(),
+        };
+        match create_mls_group(&request, &group_id, &creator, db.clone(), nats, mls_config).await {
+            Ok((members, epoch)) => {
+                mls_members = members;
+                mls_epoch = epoch;
+            }
+            Err(error) => {
+                return Ok(Response::new(CreateGroupResponse {
+                    result: Some(create_group_response::Result::Error(error)),
+                }));
+            }
+        }
+    } else if !request.mls_initial_group_info.is_empty() {
+        return Ok(Response::new(CreateGroupResponse {
+            result: Some(create_group_response::Result::Error(ErrorResponse {
+                code: 3, // INVALID_ARGUMENT
+                message: "MLS is not enabled for groups".to_string(),
+                details: Default::default(),
+            })),
+        }));
+    }
+
     // Create group metadata
     let group_metadata = GroupMetadata {
         group_id: group_id.clone(),
         group_name: request.group_name.clone(),
         creator_user_id: creator_user_id.clone(),
         created_at: timestamp,
-        mls_group_id: request.mls_group_state.clone(),
-        mls_epoch: 0, // Initial epoch
+        // OpenMLS groups use the group ID as their identifier
+        mls_group_id: if mls_enabled { group_id.as_bytes().to_vec() } else { Vec::new() },
+        mls_epoch,
     };
 
     // Store group in TiKV
@@ -93,21 +156,35 @@ pub async fn create_group(
     }
 
     // Add initial members as regular members
-    for member_user_id in &request.member_user_ids {
-        if member_user_id == &creator_user_id {
+    let initial_members: Vec<MemberDevice> = if mls_enabled {
+        mls_members
+    } else {
+        request
+            .member_user_ids
+            .iter()
+            .filter(|user_id| **user_id != creator_user_id)
+            .map(|user_id| MemberDevice {
+                user_id: user_id.clone(),
+                device_id: "primary".to_string(), // Default device for initial members
+            })
+            .collect()
+    };
+
+    for member_device in &initial_members {
+        if member_device.user_id == creator_user_id && member_device.device_id == creator_device_id {
             continue; // Skip creator (already added as owner)
         }
 
         let member = GroupMember {
             group_id: group_id.clone(),
-            user_id: member_user_id.clone(),
-            device_id: "primary".to_string(),  // Default device for initial members
+            user_id: member_device.user_id.clone(),
+            device_id: member_device.device_id.clone(),
             role: GroupRole::Member,
             joined_at: timestamp,
         };
 
         if let Err(e) = db.add_group_member(&member).await {
-            tracing::warn!("Failed to add member {} to group: {}", member_user_id, e);
+            tracing::warn!("Failed to add member {} to group: {}", member_device.user_id, e);
             // Continue with other members
         }
     }
@@ -131,3 +208,237 @@ pub async fn create_group(
         )),
     }))
 }
+
+/// Validate and store the MLS group submitted by the creator device
+///
+/// # Returns
+/// Member devices of the group and its epoch after the initial commit
+async fn create_mls_group(
+    request: &CreateGroupRequest,
+    group_id: &str,
+    creator: &MemberDevice,
+    db: Arc<DatabaseClient>,
+    nats: Arc<NatsClient>,
+    mls_config: &MlsConfig,
+) -> Result<(Vec<MemberDevice>, u64), ErrorResponse> {
+    let invalid = |message: String| ErrorResponse {
+        code: 3, // INVALID_ARGUMENT
+        message,
+        details: Default::default(),
+    };
+    let internal = || ErrorResponse {
+        code: 13, // INTERNAL
+        message: "Failed to create group".to_string(),
+        details: Default::default(),
+    };
+
+    if request.mls_initial_group_info.is_empty() {
+        return Err(invalid("MLS GroupInfo of the new group required".to_string()));
+    }
+
+    let ciphersuite = mls_config.mls_ciphersuite().map_err(|e| {
+        tracing::error!("Invalid MLS ciphersuite configuration: {}", e);
+        internal()
+    })?;
+
+    let mut group = LoadedGroup::new(&request.mls_initial_group_info)
+        .map_err(|e| invalid(format!("Invalid MLS GroupInfo: {:#}", e)))?;
+
+    if group.ciphersuite() != ciphersuite {
+        return Err(invalid(format!("MLS group must use ciphersuite {}", ciphersuite)));
+    }
+
+    // The new group may only contain the creator's leaf, which must belong
+    // to the authenticated device
+    let members = group
+        .members()
+        .map_err(|e| invalid(format!("Invalid MLS GroupInfo: {}", e)))?;
+    let creator_identity = match members.as_slice() {
+        [identity] if group.epoch() == 0
+            && identity.user_id == creator.user_id
+            && identity.device_id == creator.device_id =>
+        {
+            identity.clone()
+        }
+        _ => {
+            tracing::error!(
+                "Group creator {}:{} sent GroupInfo of another group state",
+                creator.user_id,
+                creator.device_id
+            );
+            return Err(invalid(
+                "MLS GroupInfo must contain only the creator device at epoch 0".to_string(),
+            ));
+        }
+    };
+
+    // Initial members need a key package for the group's ciphersuite
+    let mut other_members: Vec<&String> = request
+        .member_user_ids
+        .iter()
+        .filter(|user_id| **user_id != creator.user_id)
+        .collect();
+    other_members.sort();
+    other_members.dedup();
+
+    let unsupported = unsupported_members(&request.access_token, &other_members, ciphersuite).await?;
+    if !unsupported.is_empty() {
+        return Err(ErrorResponse {
+            code: 3, // INVALID_ARGUMENT
+            message: format!(
+                "Members without a key package for ciphersuite {}: {}",
+                ciphersuite,
+                unsupported.join(", ")
+            ),
+            details: {
+                let mut map = HashMap::new();
+                map.insert("unsupported_members".to_string(), unsupported.join(","));
+                map.insert("ciphersuite".to_string(), u16::from(ciphersuite).to_string());
+                map
+            },
+        });
+    }
+
+    // The initial commit must add devices of the listed members, at least
+    // one per member, and nothing else
+    let initial_commit = if request.mls_commit.is_empty() {
+        if !other_members.is_empty() {
+            return Err(invalid("MLS commit adding the initial members required".to_string()));
+        }
+        None
+    } else {
+        let commit_epoch = message_epoch(&request.mls_commit)
+            .map_err(|e| invalid(format!("Invalid MLS commit: {}", e)))?;
+        let summary = group
+            .process_commit(&request.mls_commit)
+            .map_err(|e| invalid(format!("Invalid MLS commit: {}", e)))?;
+
+        let allowed = |user_id: &String| *user_id == creator.user_id || other_members.contains(&user_id);
+        if summary.external
+            || summary.committer != creator_identity
+            || !summary.removed.is_empty()
+            || !summary.updated.is_empty()
+            || summary.other_proposals > 0
+            || !summary.added.iter().all(|identity| allowed(&identity.user_id))
+            || !other_members
+                .iter()
+                .all(|user_id| summary.added.iter().any(|identity| identity.user_id == **user_id))
+        {
+            return Err(invalid(
+                "MLS commit must only add devices of the initial members".to_string(),
+            ));
+        }
+
+        if request.mls_welcome.is_empty() {
+            return Err(invalid("MLS Welcome for the initial members required".to_string()));
+        }
+        group
+            .publish_group_info(request.mls_group_info.clone())
+            .map_err(|e| invalid(format!("Invalid MLS GroupInfo: {:#}", e)))?;
+
+        Some(HandshakeRecord {
+            group_id: group_id.to_string(),
+            epoch: commit_epoch,
+            sender_user_id: creator.user_id.clone(),
+            sender_device_id: creator.device_id.clone(),
+            commit: request.mls_commit.clone(),
+            welcome: Some(request.mls_welcome.clone()),
+            welcome_recipients: summary
+                .added
+                .iter()
+                .map(|identity| MemberDevice {
+                    user_id: identity.user_id.clone(),
+                    device_id: identity.device_id.clone(),
+                })
+                .collect(),
+            accepted_at: chrono::Utc::now().timestamp(),
+        })
+    };
+
+    let mls_manager = MlsManager::new(db.clone());
+    if let Err(e) = mls_manager
+        .create_group(group_id, &mut group, &creator_identity, initial_commit.as_ref())
+        .await
+    {
+        tracing::error!("Failed to create MLS group {}: {}", group_id, e);
+        return Err(internal());
+    }
+
+    // Deliver the Welcome to the initial member devices
+    if let Some(record) = &initial_commit {
+        let delivery_service = DeliveryService::new(db, nats);
+        if let Err(e) = delivery_service.fan_out(record, std::slice::from_ref(creator)).await {
+            tracing::error!("Failed to deliver MLS handshake messages via NATS: {}", e);
+            // Don't fail the whole operation, devices catch up from the handshake log
+        }
+    }
+
+    let members = group
+        .members()
+        .map_err(|_| internal())?
+        .into_iter()
+        .map(|identity| MemberDevice {
+            user_id: identity.user_id,
+            device_id: identity.device_id,
+        })
+        .collect();
+
+    Ok((members, group.epoch()))
+}
+
+/// Users among `user_ids` without a device that has a key package for `ciphersuite`
+async fn unsupported_members(
+    access_token: &str,
+    user_ids: &[&String],
+    ciphersuite: MlsCiphersuite,
+) -> Result<Vec<String>, ErrorResponse> {
+    if user_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let auth_service_url = std::env::var("AUTH_SERVICE_URL")
+        .unwrap_or_else(|_| "http://auth-service:50051".to_string());
+
+    let mut auth_client = AuthClient::new(&auth_service_url).await.map_err(|e| {
+        tracing::error!("Failed to connect to auth-service: {}", e);
+        ErrorResponse {
+            code: 14, // UNAVAILABLE
+            message: "Auth service unavailable".to_string(),
+            details: Default::default(),
+        }
+    })?;
+
+    let mut unsupported = Vec::new();
+    for user_id in user_ids {
+        let device_ids = auth_client
+            .get_device_ids(access_token, user_id)
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!("Failed to list devices of {}: {}", user_id, e);
+                Vec::new()
+            });
+
+        let mut supported = false;
+        for device_id in &device_ids {
+            let Ok(key_package) = auth_client
+                .fetch_mls_key_package(user_id, device_id, ciphersuite.into())
+                .await
+            else {
+                continue;
+            };
+
+            if let Ok((suite, suites)) = key_package_ciphersuites(&key_package) {
+                if suite == ciphersuite || suites.contains(&ciphersuite) {
+                    supported = true;
+                    break;
+                }
+            }
+        }
+
+        if !supported {
+            unsupported.push(user_id.to_string());
+        }
+    }
+
+    Ok(unsupported)
+}
@@ -0,0 +1,333 @@
+/// Handler for joining an MLS group by external commit
+///
+/// A newly linked device of a user who is already a group member joins
+/// without being added by another member: it creates an external commit from
+/// the published GroupInfo (see `get_group_info_mls`) and uploads the
+/// GroupInfo of the epoch the commit creates. The commit is fenced by the MLS
+/// Delivery Service like any other handshake message.
+
+use crate::config::MlsConfig;
+use crate::db::DatabaseClient;
+use crate::mls_manager::{DeliveryService, GroupStateChanged, HandshakeRecord, MlsManager, StaleEpochError};
+use crate::nats::NatsClient;
+use crate::proto::messaging::{
+    external_join_group_response, ExternalJoinGroupRequest, ExternalJoinGroupResponse,
+    ExternalJoinGroupSuccess,
+};
+use crate::proto::common::ErrorResponse;
+use guardyn_crypto::mls::message_epoch;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tonic::{Response, Status};
+use tracing::{error, info};
+
+pub async fn external_join_group_mls(
+    request: ExternalJoinGroupRequest,
+    db: Arc<DatabaseClient>,
+    nats: Arc<NatsClient>,
+    mls_config: &MlsConfig,
+) -> Result<Response<ExternalJoinGroupResponse>, Status> {
+    // Validate JWT token and extract user_id (requester)
+    let (requester_user_id, requester_device_id, _username) =
+        match crate::jwt::validate_and_extract(&request.access_token) {
+            Ok(ids) => ids,
+            Err(_) => {
+                return Ok(Response::new(ExternalJoinGroupResponse {
+                    result: Some(external_join_group_response::Result::Error(ErrorResponse {
+                        code: crate::proto::common::error_response::ErrorCode::Unauthorized as i32,
+                        message: "Invalid or expired access token".to_string(),
+                        details: HashMap::new(),
+                    })),
+                }));
+            }
+        };
+
+    // Validate group ID
+    if request.group_id.is_empty() {
+        return Ok(Response::new(ExternalJoinGroupResponse {
+            result: Some(external_join_group_response::Result::Error(ErrorResponse {
+                code: crate::proto::common::error_response::ErrorCode::InvalidRequest as i32,
+                message: "Group ID required".to_string(),
+                details: HashMap::new(),
+            })),
+        }));
+    }
+
+    if request.commit.is_empty() || request.group_info.is_empty() {
+        return Ok(Response::new(ExternalJoinGroupResponse {
+            result: Some(external_join_group_response::Result::Error(ErrorResponse {
+                code: crate::proto::common::error_response::ErrorCode::InvalidRequest as i32,
+                message: "External commit and GroupInfo required".to_string(),
+                details: HashMap::new(),
+            })),
+        }));
+    }
+
+    if !mls_config.is_enabled_for_group(&request.group_id) {
+        return Ok(Response::new(ExternalJoinGroupResponse {
+            result: Some(external_join_group_response::Result::Error(ErrorResponse {
+                code: crate::proto::common::error_response::ErrorCode::InvalidRequest as i32,
+                message: "MLS is not enabled for this group".to_string(),
+                details: HashMap::new(),
+            })),
+        }));
+    }
+
+    info!(
+        "Device {}:{} joining MLS group {} by external commit",
+        requester_user_id, requester_device_id, request.group_id
+    );
+
+    // Only users who are already group members may join with another device
+    match db.get_group_members(&request.group_id).await {
+        Ok(members) if members.iter().any(|m| m.user_id == requester_user_id) => {
+            // Requester is a member, continue
+        }
+        Ok(_) => {
+            error!(
+                "User {} is not a member of group {}",
+                requester_user_id, request.group_id
+            );
+            return Ok(Response::new(ExternalJoinGroupResponse {
+                result: Some(external_join_group_response::Result::Error(ErrorResponse {
+                    code: crate::proto::common::error_response::ErrorCode::Unauthorized as i32,
+                    message: "Not a member of this group".to_string(),
+                    details: HashMap::new(),
+                })),
+            }));
+        }
+        Err(e) => {
+            error!("Failed to verify group membership: {}", e);
+            return Ok(Response::new(ExternalJoinGroupResponse {
+                result: Some(external_join_group_response::Result::Error(ErrorResponse {
+                    code: crate::proto::common::error_response::ErrorCode::InternalError as i32,
+                    message: "Failed to verify membership".to_string(),
+                    details: { let mut map = HashMap::new(); map.insert("error".to_string(), e.to_string()); map },
+                })),
+            }));
+        }
+    }
+
+    // Initialize MLS manager
+    let mls_manager = MlsManager::new(db.clone());
+
+    match mls_manager
+        .is_member(&request.group_id, &requester_user_id, &requester_device_id)
+        .await
+    {
+        Ok(false) => {
+            // Device is not in the group yet, continue
+        }
+        Ok(true) => {
+            return Ok(Response::new(ExternalJoinGroupResponse {
+                result: Some(external_join_group_response::Result::Error(ErrorResponse {
+                    code: crate::proto::common::error_response::ErrorCode::InvalidRequest as i32,
+                    message: "Device is already a member of this group".to_string(),
+                    details: HashMap::new(),
+                })),
+            }));
+        }
+        Err(e) => {
+            error!("Failed to verify device membership: {}", e);
+            return Ok(Response::new(ExternalJoinGroupResponse {
+                result: Some(external_join_group_response::Result::Error(ErrorResponse {
+                    code: crate::proto::common::error_response::ErrorCode::InternalError as i32,
+                    message: "Failed to verify membership".to_string(),
+                    details: { let mut map = HashMap::new(); map.insert("error".to_string(), e.to_string()); map },
+                })),
+            }));
+        }
+    }
+
+    // Load group from TiKV at its current epoch
+    let mut group_manager = match mls_manager.load_group(&request.group_id).await {
+        Ok(manager) => manager,
+        Err(e) => {
+            error!("Failed to load group state: {}", e);
+            return Ok(Response::new(ExternalJoinGroupResponse {
+                result: Some(external_join_group_response::Result::Error(ErrorResponse {
+                    code: crate::proto::common::error_response::ErrorCode::NotFound as i32,
+                    message: "Group state not found".to_string(),
+                    details: { let mut map = HashMap::new(); map.insert("error".to_string(), e.to_string()); map },
+                })),
+            }));
+        }
+    };
+
+    // Devices that receive the commit
+    let members = match mls_manager.member_devices(&request.group_id).await {
+        Ok(members) => members,
+        Err(e) => {
+            error!("Failed to list group members: {}", e);
+            return Ok(Response::new(ExternalJoinGroupResponse {
+                result: Some(external_join_group_response::Result::Error(ErrorResponse {
+                    code: crate::proto::common::error_response::ErrorCode::InternalError as i32,
+                    message: "Failed to list group members".to_string(),
+                    details: { let mut map = HashMap::new(); map.insert("error".to_string(), e.to_string()); map },
+                })),
+            }));
+        }
+    };
+
+    let commit_epoch = match message_epoch(&request.commit) {
+        Ok(epoch) => epoch,
+        Err(e) => {
+            return Ok(Response::new(ExternalJoinGroupResponse {
+                result: Some(external_join_group_response::Result::Error(ErrorResponse {
+                    code: crate::proto::common::error_response::ErrorCode::InvalidRequest as i32,
+                    message: "Invalid external commit".to_string(),
+                    details: { let mut map = HashMap::new(); map.insert("error".to_string(), e.to_string()); map },
+                })),
+            }));
+        }
+    };
+
+    // A commit built from an outdated GroupInfo cannot be applied; the device
+    // fetches the GroupInfo again and retries
+    if commit_epoch != group_manager.epoch() {
+        let mut details = HashMap::new();
+        details.insert("retryable".to_string(), "true".to_string());
+        details.insert("current_epoch".to_string(), group_manager.epoch().to_string());
+        return Ok(Response::new(ExternalJoinGroupResponse {
+            result: Some(external_join_group_response::Result::Error(ErrorResponse {
+                code: crate::proto::common::error_response::ErrorCode::Conflict as i32,
+                message: "Group changed concurrently, retry the request".to_string(),
+                details,
+            })),
+        }));
+    }
+
+    // Apply the external commit to the public group state
+    let joiner_identity = match group_manager.process_commit(&request.commit) {
+        // Only the joiner's own leaf may be added
+        Ok(summary)
+            if summary.external
+                && summary.added.is_empty()
+                && summary.removed.is_empty()
+                && summary.updated.is_empty() =>
+        {
+            summary.committer
+        }
+        Ok(_) => {
+            return Ok(Response::new(ExternalJoinGroupResponse {
+                result: Some(external_join_group_response::Result::Error(ErrorResponse {
+                    code: crate::proto::common::error_response::ErrorCode::InvalidRequest as i32,
+                    message: "Commit must only add the joining device".to_string(),
+                    details: HashMap::new(),
+                })),
+            }));
+        }
+        Err(e) => {
+            error!("Failed to process external commit: {}", e);
+            return Ok(Response::new(ExternalJoinGroupResponse {
+                result: Some(external_join_group_response::Result::Error(ErrorResponse {
+                    code: crate::proto::common::error_response::ErrorCode::InvalidRequest as i32,
+                    message: "Invalid external commit".to_string(),
+                    details: { let mut map = HashMap::new(); map.insert("error".to_string(), e.to_string()); map },
+                })),
+            }));
+        }
+    };
+
+    // The joining leaf must belong to the authenticated device
+    if joiner_identity.user_id != requester_user_id || joiner_identity.device_id != requester_device_id {
+        error!(
+            "External commit of {}:{} carries credential of {} in group {}",
+            requester_user_id,
+            requester_device_id,
+            joiner_identity.label(),
+            request.group_id
+        );
+        return Ok(Response::new(ExternalJoinGroupResponse {
+            result: Some(external_join_group_response::Result::Error(ErrorResponse {
+                code: crate::proto::common::error_response::ErrorCode::Unauthorized as i32,
+                message: "External commit identity does not match the device".to_string(),
+                details: HashMap::new(),
+            })),
+        }));
+    }
+
+    if let Err(e) = group_manager.publish_group_info(request.group_info) {
+        return Ok(Response::new(ExternalJoinGroupResponse {
+            result: Some(external_join_group_response::Result::Error(ErrorResponse {
+                code: crate::proto::common::error_response::ErrorCode::InvalidRequest as i32,
+                message: "Invalid GroupInfo".to_string(),
+                details: { let mut map = HashMap::new(); map.insert("error".to_string(), format!("{:#}", e)); map },
+            })),
+        }));
+    }
+
+    let delivery_service = DeliveryService::new(db.clone(), nats.clone());
+    let record = HandshakeRecord {
+        group_id: request.group_id.clone(),
+        epoch: commit_epoch,
+        sender_user_id: requester_user_id.clone(),
+        sender_device_id: requester_device_id.clone(),
+        commit: request.commit,
+        welcome: None,
+        welcome_recipients: Vec::new(),
+        accepted_at: chrono::Utc::now().timestamp(),
+    };
+
+    if let Err(e) = delivery_service.accept_commit(&record, &mut group_manager).await {
+        let stale = e.downcast_ref::<StaleEpochError>();
+        if stale.is_some() || e.is::<GroupStateChanged>() {
+            info!("Rejected commit for MLS group {}: {}", request.group_id, e);
+            let mut details = HashMap::new();
+            details.insert("retryable".to_string(), "true".to_string());
+            if let Some(stale) = stale {
+                details.insert("current_epoch".to_string(), stale.current_epoch.to_string());
+            }
+            return Ok(Response::new(ExternalJoinGroupResponse {
+                result: Some(external_join_group_response::Result::Error(ErrorResponse {
+                    code: crate::proto::common::error_response::ErrorCode::Conflict as i32,
+                    message: "Group changed concurrently, retry the request".to_string(),
+                    details,
+                })),
+            }));
+        }
+
+        error!("Failed to submit commit to the Delivery Service: {}", e);
+        return Ok(Response::new(ExternalJoinGroupResponse {
+            result: Some(external_join_group_response::Result::Error(ErrorResponse {
+                code: crate::proto::common::error_response::ErrorCode::InternalError as i32,
+                message: "Failed to submit group commit".to_string(),
+                details: { let mut map = HashMap::new(); map.insert("error".to_string(), e.to_string()); map },
+            })),
+        }));
+    }
+
+    // Add the new device to the member list
+    if let Err(e) = mls_manager
+        .add_member_to_list(&request.group_id, &requester_user_id, &requester_device_id)
+        .await
+    {
+        error!("Failed to add member to list: {}", e);
+        return Ok(Response::new(ExternalJoinGroupResponse {
+            result: Some(external_join_group_response::Result::Error(ErrorResponse {
+                code: crate::proto::common::error_response::ErrorCode::InternalError as i32,
+                message: "Failed to add member to list".to_string(),
+                details: { let mut map = HashMap::new(); map.insert("error".to_string(), e.to_string()); map },
+            })),
+        }));
+    }
+
+    // Deliver Commit to the existing member devices
+    if let Err(e) = delivery_service.fan_out(&record, &members).await {
+        error!("Failed to deliver MLS handshake messages via NATS: {}", e);
+        // Don't fail the whole operation, devices catch up from the handshake log
+    }
+
+    info!(
+        "Device {} joined MLS group {} at epoch {}",
+        joiner_identity.label(), request.group_id, group_manager.epoch()
+    );
+
+    Ok(Response::new(ExternalJoinGroupResponse {
+        result: Some(external_join_group_response::Result::Success(
+            ExternalJoinGroupSuccess {
+                mls_epoch: group_manager.epoch(),
+            },
+        )),
+    }))
+}
@@ -0,0 +1,153 @@
+/// Handler for fetching the MLS GroupInfo of a group
+///
+/// Returns the signed GroupInfo (with ratchet tree) of the group's current
+/// epoch, which a newly linked device uses to join by external commit. Only
+/// users who are already members of the group may fetch it.
+
+use crate::config::MlsConfig;
+use crate::db::DatabaseClient;
+use crate::mls_manager::MlsManager;
+use crate::proto::messaging::{
+    get_group_info_response, GetGroupInfoRequest, GetGroupInfoResponse, GetGroupInfoSuccess,
+};
+use crate::proto::common::ErrorResponse;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tonic::{Response, Status};
+use tracing::{error, info};
+
+pub async fn get_group_info_mls(
+    request: GetGroupInfoRequest,
+    db: Arc<DatabaseClient>,
+    mls_config: &MlsConfig,
+) -> Result<Response<GetGroupInfoResponse>, Status> {
+    // Validate JWT token and extract user_id (requester)
+    let (requester_user_id, requester_device_id, _username) =
+        match crate::jwt::validate_and_extract(&request.access_token) {
+            Ok(ids) => ids,
+            Err(_) => {
+                return Ok(Response::new(GetGroupInfoResponse {
+                    result: Some(get_group_info_response::Result::Error(ErrorResponse {
+                        code: crate::proto::common::error_response::ErrorCode::Unauthorized as i32,
+                        message: "Invalid or expired access token".to_string(),
+                        details: HashMap::new(),
+                    })),
+                }));
+            }
+        };
+
+    // Validate group ID
+    if request.group_id.is_empty() {
+        return Ok(Response::new(GetGroupInfoResponse {
+            result: Some(get_group_info_response::Result::Error(ErrorResponse {
+                code: crate::proto::common::error_response::ErrorCode::InvalidRequest as i32,
+                message: "Group ID required".to_string(),
+                details: HashMap::new(),
+            })),
+        }));
+    }
+
+    if !mls_config.is_enabled_for_group(&request.group_id) {
+        return Ok(Response::new(GetGroupInfoResponse {
+            result: Some(get_group_info_response::Result::Error(ErrorResponse {
+                code: crate::proto::common::error_response::ErrorCode::InvalidRequest as i32,
+                message: "MLS is not enabled for this group".to_string(),
+                details: HashMap::new(),
+            })),
+        }));
+    }
+
+    // Only users who are already group members may join with another device
+    match db.get_group_members(&request.group_id).await {
+        Ok(members) if members.iter().any(|m| m.user_id == requester_user_id) => {
+            // Requester is a member, continue
+        }
+        Ok(_) => {
+            error!(
+                "User {} is not a member of group {}",
+                requester_user_id, request.group_id
+            );
+            return Ok(Response::new(GetGroupInfoResponse {
+                result: Some(get_group_info_response::Result::Error(ErrorResponse {
+                    code: crate::proto::common::error_response::ErrorCode::Unauthorized as i32,
+                    message: "Not a member of this group".to_string(),
+                    details: HashMap::new(),
+                })),
+            }));
+        }
+        Err(e) => {
+            error!("Failed to verify group membership: {}", e);
+            return Ok(Response::new(GetGroupInfoResponse {
+                result: Some(get_group_info_response::Result::Error(ErrorResponse {
+                    code: crate::proto::common::error_response::ErrorCode::InternalError as i32,
+                    message: "Failed to verify membership".to_string(),
+                    details: { let mut map = HashMap::new(); map.insert("error".to_string(), e.to_string()); map },
+                })),
+            }));
+        }
+    }
+
+    let mls_manager = MlsManager::new(db.clone());
+
+    let metadata = match mls_manager.get_metadata(&request.group_id).await {
+        Ok(Some(metadata)) => metadata,
+        Ok(None) => {
+            return Ok(Response::new(GetGroupInfoResponse {
+                result: Some(get_group_info_response::Result::Error(ErrorResponse {
+                    code: crate::proto::common::error_response::ErrorCode::NotFound as i32,
+                    message: "Group state not found".to_string(),
+                    details: HashMap::new(),
+                })),
+            }));
+        }
+        Err(e) => {
+            error!("Failed to load group metadata: {}", e);
+            return Ok(Response::new(GetGroupInfoResponse {
+                result: Some(get_group_info_response::Result::Error(ErrorResponse {
+                    code: crate::proto::common::error_response::ErrorCode::InternalError as i32,
+                    message: "Failed to load group metadata".to_string(),
+                    details: { let mut map = HashMap::new(); map.insert("error".to_string(), e.to_string()); map },
+                })),
+            }));
+        }
+    };
+
+    let group_info = match mls_manager.get_group_info(&request.group_id).await {
+        Ok(Some(group_info)) => group_info,
+        Ok(None) => {
+            return Ok(Response::new(GetGroupInfoResponse {
+                result: Some(get_group_info_response::Result::Error(ErrorResponse {
+                    code: crate::proto::common::error_response::ErrorCode::NotFound as i32,
+                    message: "Group info not published".to_string(),
+                    details: HashMap::new(),
+                })),
+            }));
+        }
+        Err(e) => {
+            error!("Failed to load group info: {}", e);
+            return Ok(Response::new(GetGroupInfoResponse {
+                result: Some(get_group_info_response::Result::Error(ErrorResponse {
+                    code: crate::proto::common::error_response::ErrorCode::InternalError as i32,
+                    message: "Failed to load group info".to_string(),
+                    details: { let mut map = HashMap::new(); map.insert("error".to_string(), e.to_string()); map },
+                })),
+            }));
+        }
+    };
+
+    // Informational only: if the group moved on in between, the GroupInfo is
+    // newer than this epoch and a stale join is rejected as retryable anyway.
+    let mls_epoch = metadata.current_epoch;
+
+    info!(
+        "Served MLS GroupInfo of group {} (epoch {}) to {}:{}",
+        request.group_id, mls_epoch, requester_user_id, requester_device_id
+    );
+
+    Ok(Response::new(GetGroupInfoResponse {
+        result: Some(get_group_info_response::Result::Success(GetGroupInfoSuccess {
+            group_info,
+            mls_epoch,
+        })),
+    }))
+}
@@ -13,10 +13,7 @@ pub async fn get_group_messages(
     db: Arc<DatabaseClient>,
 ) -> Result<Response<GetGroupMessagesResponse>, Status> {
     // Validate JWT token and extract user_id
-    let jwt_secret = std::env::var("JWT_SECRET")
-        .unwrap_or_else(|_| "default-jwt-secret-change-in-production".to_string());
-
-    let (requester_user_id, _device_id, _username) = match crate::jwt::validate_and_extract(&request.access_token, &jwt_secret) {
+    let (requester_user_id, _device_id, _username) = match crate::jwt::validate_and_extract(&request.access_token) {
         Ok(ids) => ids,
         Err(_) => {
             return Ok(Response::new(GetGroupMessagesResponse {
@@ -143,6 +140,12 @@ pub async fn get_group_messages(
                 .get("sender_username")
                 .cloned()
                 .unwrap_or_else(|| msg.sender_user_id.clone());
+
+            // Franking tag is stored hex-encoded in metadata
+            let franking_tag = msg.metadata
+                .get("franking_tag")
+                .and_then(|tag| hex::decode(tag).ok())
+                .unwrap_or_default();
             
             GroupMessage {
                 message_id: msg.message_id,
@@ -163,6 +166,7 @@ pub async fn get_group_messages(
                 }),
                 media_id: String::new(), // Not stored in current schema
                 is_deleted: false, // New schema doesn't support soft delete
+                franking_tag,
             }
         })
         .collect();
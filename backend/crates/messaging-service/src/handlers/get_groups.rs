@@ -13,10 +13,7 @@ pub async fn get_groups(
     db: Arc<DatabaseClient>,
 ) -> Result<Response<GetGroupsResponse>, Status> {
     // Validate JWT token and extract user_id
-    let jwt_secret = std::env::var("JWT_SECRET")
-        .unwrap_or_else(|_| "default-jwt-secret-change-in-production".to_string());
-
-    let (user_id, _device_id, _username) = match crate::jwt::validate_and_extract(&request.access_token, &jwt_secret) {
+    let (user_id, _device_id, _username) = match crate::jwt::validate_and_extract(&request.access_token) {
         Ok(ids) => ids,
         Err(_) => {
             return Ok(Response::new(GetGroupsResponse {
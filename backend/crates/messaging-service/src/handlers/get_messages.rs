@@ -12,10 +12,7 @@ pub async fn get_messages(
     db: Arc<DatabaseClient>,
 ) -> Result<Response<GetMessagesResponse>, Status> {
     // Validate JWT token and extract user_id
-    let jwt_secret = std::env::var("JWT_SECRET")
-        .unwrap_or_else(|_| "default-jwt-secret-change-in-production".to_string());
-
-    let (_user_id, _device_id, _username) = match crate::jwt::validate_and_extract(&request.access_token, &jwt_secret) {
+    let (user_id, device_id, _username) = match crate::jwt::validate_and_extract(&request.access_token) {
         Ok(ids) => ids,
         Err(_) => {
             return Ok(Response::new(GetMessagesResponse {
@@ -65,6 +62,12 @@ pub async fn get_messages(
     let messages: Vec<Message> = stored_messages
         .into_iter()
         .filter(|m| !m.is_deleted) // Filter out deleted messages
+        // Multi-device sends store one copy per device; only this device's copy is readable
+        .filter(|m| {
+            !m.device_copy
+                || (m.recipient_user_id == user_id
+                    && m.recipient_device_id.as_deref() == Some(device_id.as_str()))
+        })
         .map(|m| Message {
             message_id: m.message_id,
             sender_user_id: m.sender_user_id,
@@ -86,6 +89,8 @@ pub async fn get_messages(
             is_deleted: m.is_deleted,
             media_id: String::new(), // TODO: Implement media references
             x3dh_prekey: m.x3dh_prekey.unwrap_or_default(), // Return stored X3DH prekey
+            sealed: false,
+            franking_tag: m.franking_tag.unwrap_or_default(),
         })
         .collect();
 
@@ -0,0 +1,158 @@
+/// Handler for fetching accepted MLS handshake messages
+///
+/// Returns the commits accepted since an epoch from the delivery service's
+/// handshake log, so a member device that missed a NATS delivery (or was
+/// offline) can catch up in epoch order. Welcomes are only included for
+/// the devices they were addressed to.
+
+use crate::config::MlsConfig;
+use crate::db::DatabaseClient;
+use crate::mls_manager::{DeliveryService, MlsManager};
+use crate::nats::NatsClient;
+use crate::proto::messaging::{
+    get_mls_handshakes_response, GetMlsHandshakesRequest, GetMlsHandshakesResponse,
+    GetMlsHandshakesSuccess, MlsHandshake,
+};
+use crate::proto::common::ErrorResponse;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tonic::{Response, Status};
+use tracing::{error, info};
+
+pub async fn get_mls_handshakes(
+    request: GetMlsHandshakesRequest,
+    db: Arc<DatabaseClient>,
+    nats: Arc<NatsClient>,
+    mls_config: &MlsConfig,
+) -> Result<Response<GetMlsHandshakesResponse>, Status> {
+    // Validate JWT token and extract user_id (requester)
+    let (requester_user_id, requester_device_id, _username) =
+        match crate::jwt::validate_and_extract(&request.access_token) {
+            Ok(ids) => ids,
+            Err(_) => {
+                return Ok(Response::new(GetMlsHandshakesResponse {
+                    result: Some(get_mls_handshakes_response::Result::Error(ErrorResponse {
+                        code: crate::proto::common::error_response::ErrorCode::Unauthorized as i32,
+                        message: "Invalid or expired access token".to_string(),
+                        details: HashMap::new(),
+                    })),
+                }));
+            }
+        };
+
+    // Validate group ID
+    if request.group_id.is_empty() {
+        return Ok(Response::new(GetMlsHandshakesResponse {
+            result: Some(get_mls_handshakes_response::Result::Error(ErrorResponse {
+                code: crate::proto::common::error_response::ErrorCode::InvalidRequest as i32,
+                message: "Group ID required".to_string(),
+                details: HashMap::new(),
+            })),
+        }));
+    }
+
+    if !mls_config.is_enabled_for_group(&request.group_id) {
+        return Ok(Response::new(GetMlsHandshakesResponse {
+            result: Some(get_mls_handshakes_response::Result::Error(ErrorResponse {
+                code: crate::proto::common::error_response::ErrorCode::InvalidRequest as i32,
+                message: "MLS is not enabled for this group".to_string(),
+                details: HashMap::new(),
+            })),
+        }));
+    }
+
+    // Only member devices receive the group's handshake messages
+    let mls_manager = MlsManager::new(db.clone());
+
+    match mls_manager
+        .is_member(&request.group_id, &requester_user_id, &requester_device_id)
+        .await
+    {
+        Ok(true) => {
+            // Requester is a member device, continue
+        }
+        Ok(false) => {
+            error!(
+                "Device {}:{} is not a member of group {}",
+                requester_user_id, requester_device_id, request.group_id
+            );
+            return Ok(Response::new(GetMlsHandshakesResponse {
+                result: Some(get_mls_handshakes_response::Result::Error(ErrorResponse {
+                    code: crate::proto::common::error_response::ErrorCode::Unauthorized as i32,
+                    message: "Not a member of this group".to_string(),
+                    details: HashMap::new(),
+                })),
+            }));
+        }
+        Err(e) => {
+            error!("Failed to verify device membership: {}", e);
+            return Ok(Response::new(GetMlsHandshakesResponse {
+                result: Some(get_mls_handshakes_response::Result::Error(ErrorResponse {
+                    code: crate::proto::common::error_response::ErrorCode::InternalError as i32,
+                    message: "Failed to verify membership".to_string(),
+                    details: { let mut map = HashMap::new(); map.insert("error".to_string(), e.to_string()); map },
+                })),
+            }));
+        }
+    }
+
+    // Determine limit (default 50, max 100)
+    let limit = if request.limit > 0 && request.limit <= 100 {
+        request.limit
+    } else if request.limit > 100 {
+        100
+    } else {
+        50
+    };
+
+    let delivery_service = DeliveryService::new(db.clone(), nats);
+
+    let records = match delivery_service
+        .handshakes_since(&request.group_id, request.from_epoch, limit)
+        .await
+    {
+        Ok(records) => records,
+        Err(e) => {
+            error!("Failed to load MLS handshake log: {}", e);
+            return Ok(Response::new(GetMlsHandshakesResponse {
+                result: Some(get_mls_handshakes_response::Result::Error(ErrorResponse {
+                    code: crate::proto::common::error_response::ErrorCode::InternalError as i32,
+                    message: "Failed to load handshake messages".to_string(),
+                    details: { let mut map = HashMap::new(); map.insert("error".to_string(), e.to_string()); map },
+                })),
+            }));
+        }
+    };
+
+    let handshakes: Vec<MlsHandshake> = records
+        .into_iter()
+        .map(|record| {
+            let welcome_recipient = record.welcome_recipients.iter().any(|recipient| {
+                recipient.user_id == requester_user_id && recipient.device_id == requester_device_id
+            });
+
+            MlsHandshake {
+                epoch: record.epoch,
+                sender_user_id: record.sender_user_id,
+                sender_device_id: record.sender_device_id,
+                commit: record.commit,
+                welcome: record
+                    .welcome
+                    .filter(|_| welcome_recipient)
+                    .unwrap_or_default(),
+                accepted_at: record.accepted_at,
+            }
+        })
+        .collect();
+
+    info!(
+        "Served {} MLS handshake messages of group {} from epoch {} to {}:{}",
+        handshakes.len(), request.group_id, request.from_epoch, requester_user_id, requester_device_id
+    );
+
+    Ok(Response::new(GetMlsHandshakesResponse {
+        result: Some(get_mls_handshakes_response::Result::Success(GetMlsHandshakesSuccess {
+            handshakes,
+        })),
+    }))
+}
@@ -1,11 +1,14 @@
 /// Message handlers for Messaging Service
 pub mod send_message;
 pub mod send_message_e2ee;
+pub mod send_message_multi_device;
+pub mod send_sealed_message;
 pub mod get_messages;
 pub mod get_conversations;
 pub mod mark_as_read;
 pub mod delete_message;
 pub mod clear_chat;
+pub mod report_message;
 pub mod receive_messages;
 pub mod receive_messages_e2ee;
 pub mod create_group;
@@ -18,14 +21,21 @@ pub mod get_group_messages;
 pub mod get_groups;
 pub mod get_group_by_id;
 pub mod leave_group;
+pub mod update_group_keys_mls;
+pub mod get_group_info_mls;
+pub mod external_join_group_mls;
+pub mod get_mls_handshakes;
 
 pub use send_message::send_message;
 pub use send_message_e2ee::send_message_e2ee;
+pub use send_message_multi_device::send_message_multi_device;
+pub use send_sealed_message::{send_sealed_message, set_delivery_token};
 pub use get_messages::get_messages;
 pub use get_conversations::get_conversations;
 pub use mark_as_read::mark_as_read;
 pub use delete_message::delete_message;
 pub use clear_chat::clear_chat;
+pub use report_message::report_message;
 pub use receive_messages::receive_messages;
 pub use receive_messages_e2ee::receive_messages_e2ee;
 pub use create_group::create_group;
@@ -38,3 +48,7 @@ pub use get_group_messages::get_group_messages;
 pub use get_groups::get_groups;
 pub use get_group_by_id::get_group_by_id;
 pub use leave_group::leave_group;
+pub use update_group_keys_mls::update_group_keys_mls;
+pub use get_group_info_mls::get_group_info_mls;
+pub use external_join_group_mls::external_join_group_mls;
+pub use get_mls_handshakes::get_mls_handshakes;
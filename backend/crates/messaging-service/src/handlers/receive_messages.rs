@@ -29,10 +29,7 @@ pub async fn receive_messages(
     nats: Arc<NatsClient>,
 ) -> Result<Response<ReceiverStream<Result<Message, Status>>>, Status> {
     // Validate access token and extract user_id + device_id
-    let jwt_secret = std::env::var("JWT_SECRET")
-        .unwrap_or_else(|_| "default-jwt-secret-change-in-production".to_string());
-
-    let (user_id, device_id, _username) = crate::jwt::validate_and_extract(&request.access_token, &jwt_secret)?;
+    let (user_id, device_id, _username) = crate::jwt::validate_and_extract(&request.access_token)?;
 
     tracing::info!("User {} ({}) connected to message stream", user_id, device_id);
 
@@ -77,6 +74,11 @@ async fn stream_messages(
                 tracing::info!("Found {} pending messages", pending_messages.len());
 
                 for delivery_state in pending_messages {
+                    // Skip ciphertexts addressed to the user's other devices
+                    if !delivery_state.is_for_device(&device_id) {
+                        continue;
+                    }
+
                     // Convert delivery state to Message
                     let message = Message {
                         message_id: delivery_state.message_id.clone(),
@@ -96,6 +98,8 @@ async fn stream_messages(
                         is_deleted: false,
                         media_id: "".to_string(),
                         x3dh_prekey: "".to_string(), // TODO: Fetch from ScyllaDB with message content
+                        sealed: false,
+                        franking_tag: vec![],
                     };
 
                     // Send to client
@@ -121,7 +125,7 @@ async fn stream_messages(
 
     // Step 2: Create NATS consumer for real-time messages
     let consumer = nats
-        .subscribe_to_messages(&user_id)
+        .subscribe_to_messages(&user_id, &device_id)
         .await
         .map_err(|e| anyhow::anyhow!("Failed to create NATS consumer: {}", e))?;
 
@@ -175,6 +179,11 @@ async fn poll_nats_messages(
     tracing::debug!("Received {} messages from NATS", envelopes.len());
 
     for envelope in envelopes {
+        // Skip ciphertexts addressed to the user's other devices
+        if !envelope.is_for_device(device_id) {
+            continue;
+        }
+
         // Convert NATS envelope to protobuf Message
         let message = Message {
             message_id: envelope.message_id.clone(),
@@ -194,6 +203,8 @@ async fn poll_nats_messages(
             is_deleted: false,
             media_id: "".to_string(),
             x3dh_prekey: envelope.x3dh_prekey.clone().unwrap_or_default(),
+            sealed: envelope.sealed,
+            franking_tag: envelope.franking_tag.clone().unwrap_or_default(),
         };
 
         // Send message to client
@@ -26,10 +26,7 @@ pub async fn receive_messages_e2ee(
     nats: Arc<NatsClient>,
 ) -> Result<Response<ReceiverStream<Result<Message, Status>>>, Status> {
     // Validate access token and extract user_id + device_id
-    let jwt_secret = std::env::var("JWT_SECRET")
-        .unwrap_or_else(|_| "default-jwt-secret-change-in-production".to_string());
-
-    let (user_id, device_id, _username) = crate::jwt::validate_and_extract(&request.access_token, &jwt_secret)?;
+    let (user_id, device_id, _username) = crate::jwt::validate_and_extract(&request.access_token)?;
 
     tracing::info!("User {} ({}) connected to E2EE message stream", user_id, device_id);
 
@@ -66,7 +63,7 @@ async fn stream_messages_e2ee(
     let auth_service_url = std::env::var("AUTH_SERVICE_URL")
         .unwrap_or_else(|_| "http://auth-service:50051".to_string());
 
-    let session_manager = SessionManager::new(db.clone(), auth_service_url);
+    let session_manager = SessionManager::new(db.clone(), auth_service_url, nats.clone());
 
     // Step 1: Send offline/pending messages first if requested
     if include_history {
@@ -77,6 +74,11 @@ async fn stream_messages_e2ee(
                 tracing::info!("Found {} pending messages to decrypt", pending_messages.len());
 
                 for delivery_state in pending_messages {
+                    // Skip ciphertexts addressed to the user's other devices
+                    if !delivery_state.is_for_device(&device_id) {
+                        continue;
+                    }
+
                     // Get corresponding stored message with encrypted content
                     let stored_msg = match db.get_message(&delivery_state.message_id).await {
                         Ok(Some(msg)) => msg,
@@ -162,6 +164,8 @@ async fn stream_messages_e2ee(
                         is_deleted: false,
                         media_id: "".to_string(),
                         x3dh_prekey: stored_msg.x3dh_prekey.unwrap_or_default(),
+                        sealed: false,
+                        franking_tag: stored_msg.franking_tag.unwrap_or_default(),
                     };
 
                     // Send decrypted message to client
@@ -214,6 +218,46 @@ async fn stream_messages_e2ee(
                         }
                     };
 
+                    // Skip ciphertexts addressed to the user's other devices
+                    if !envelope.is_for_device(&device_id) {
+                        let _ = msg.ack().await;
+                        continue;
+                    }
+
+                    // Sealed sender envelopes can only be opened by the recipient's
+                    // device, so they are passed through as-is
+                    if envelope.sealed {
+                        let message = Message {
+                            message_id: envelope.message_id.clone(),
+                            sender_user_id: String::new(),
+                            sender_device_id: String::new(),
+                            recipient_user_id: user_id.clone(),
+                            recipient_device_id: device_id.clone(),
+                            encrypted_content: envelope.encrypted_content,
+                            message_type: MessageType::Text as i32,
+                            client_message_id: "".to_string(),
+                            client_timestamp: None,
+                            server_timestamp: Some(Timestamp {
+                                seconds: envelope.timestamp,
+                                nanos: 0,
+                            }),
+                            delivery_status: DeliveryStatus::Delivered as i32,
+                            is_deleted: false,
+                            media_id: "".to_string(),
+                            x3dh_prekey: "".to_string(),
+                            sealed: true,
+                            franking_tag: vec![],
+                        };
+
+                        if tx.send(Ok(message)).await.is_err() {
+                            tracing::info!("Client disconnected from E2EE stream");
+                            return Ok(());
+                        }
+
+                        let _ = msg.ack().await;
+                        continue;
+                    }
+
                     // =======================================================================
                     // E2EE: Decrypt real-time message
                     // =======================================================================
@@ -279,6 +323,8 @@ async fn stream_messages_e2ee(
                         is_deleted: false,
                         media_id: "".to_string(),
                         x3dh_prekey: envelope.x3dh_prekey.clone().unwrap_or_default(),
+                        sealed: false,
+                        franking_tag: envelope.franking_tag.clone().unwrap_or_default(),
                     };
 
                     if tx.send(Ok(message)).await.is_err() {
@@ -0,0 +1,135 @@
+/// Handler for abuse reports of franked messages
+///
+/// The reporter reveals the plaintext and franking key of a message they
+/// received. The report is only accepted if the server-signed franking tag is
+/// genuine, was issued to the reporter (or a group they belong to) and the
+/// revealed plaintext opens its commitment, so content can't be fabricated.
+use crate::db::DatabaseClient;
+use crate::models::AbuseReport;
+use crate::proto::common::{ErrorResponse, Timestamp};
+use crate::proto::messaging::{
+    report_message_response, ReportMessageRequest, ReportMessageResponse, ReportMessageSuccess,
+};
+use guardyn_crypto::franking::FRANKING_KEY_LEN;
+use guardyn_crypto::x3dh::IdentityKeyPair;
+use guardyn_crypto::{FrankingRecipient, FrankingTag};
+use std::sync::Arc;
+use tonic::{Response, Status};
+use uuid::Uuid;
+
+pub async fn report_message(
+    request: ReportMessageRequest,
+    db: Arc<DatabaseClient>,
+    franking_key: Arc<IdentityKeyPair>,
+) -> Result<Response<ReportMessageResponse>, Status> {
+    let error = |code: i32, message: &str| -> Result<Response<ReportMessageResponse>, Status> {
+        Ok(Response::new(ReportMessageResponse {
+            result: Some(report_message_response::Result::Error(ErrorResponse {
+                code,
+                message: message.to_string(),
+                details: Default::default(),
+            })),
+        }))
+    };
+
+    // Validate JWT token and extract user_id + device_id (reporter)
+    let (reporter_user_id, reporter_device_id, _reporter_username) = match crate::jwt::validate_and_extract(&request.access_token) {
+        Ok(claims) => claims,
+        Err(_) => return error(16, "Invalid or expired access token"), // UNAUTHENTICATED
+    };
+
+    if request.message_id.is_empty() {
+        return error(3, "Message ID required"); // INVALID_ARGUMENT
+    }
+
+    if request.franking_key.len() != FRANKING_KEY_LEN {
+        return error(3, "Franking key must be 32 bytes"); // INVALID_ARGUMENT
+    }
+
+    let tag = match db.get_franking_tag(&request.message_id).await {
+        Ok(Some(tag)) => tag,
+        Ok(None) => return error(5, "Message is not franked or does not exist"), // NOT_FOUND
+        Err(e) => {
+            tracing::error!("Failed to fetch franking tag: {}", e);
+            return error(13, "Failed to fetch franking tag"); // INTERNAL
+        }
+    };
+
+    let tag = match FrankingTag::from_bytes(&tag) {
+        Ok(tag) => tag,
+        Err(e) => {
+            tracing::error!("Stored franking tag of {} is malformed: {}", request.message_id, e);
+            return error(13, "Invalid stored franking tag"); // INTERNAL
+        }
+    };
+
+    // Only a recipient of the message may report it
+    let group_id = match &tag.recipient {
+        FrankingRecipient::User(user_id) => {
+            if *user_id != reporter_user_id {
+                return error(7, "Only a recipient can report this message"); // PERMISSION_DENIED
+            }
+            None
+        }
+        FrankingRecipient::Group(group_id) => {
+            match db.get_group_members(group_id).await {
+                Ok(members) if members.iter().any(|m| m.user_id == reporter_user_id) => {}
+                Ok(_) => return error(7, "Only a group member can report this message"), // PERMISSION_DENIED
+                Err(e) => {
+                    tracing::error!("Failed to fetch group members: {}", e);
+                    return error(13, "Failed to fetch group members"); // INTERNAL
+                }
+            }
+            Some(group_id.clone())
+        }
+    };
+
+    if let Err(e) = tag.verify_report(&franking_key.public_bytes(), &request.plaintext, &request.franking_key) {
+        tracing::warn!(
+            "Rejected report of {} by {}: {}",
+            request.message_id,
+            reporter_user_id,
+            e
+        );
+        return error(3, "Reported plaintext does not match the message"); // INVALID_ARGUMENT
+    }
+
+    let reported_at = chrono::Utc::now().timestamp();
+    let report = AbuseReport {
+        report_id: Uuid::new_v4().to_string(),
+        message_id: tag.message_id.clone(),
+        reporter_user_id,
+        reporter_device_id,
+        sender_user_id: tag.sender_user_id.clone(),
+        sender_device_id: tag.sender_device_id.clone(),
+        group_id,
+        plaintext: request.plaintext,
+        reason: request.reason,
+        message_timestamp: tag.timestamp,
+        reported_at,
+    };
+
+    if let Err(e) = db.store_abuse_report(&report).await {
+        tracing::error!("Failed to store abuse report: {}", e);
+        return error(13, "Failed to store report"); // INTERNAL
+    }
+
+    tracing::info!(
+        "Verified abuse report {} for message {} from {} against {}",
+        report.report_id,
+        report.message_id,
+        report.reporter_user_id,
+        report.sender_user_id
+    );
+
+    Ok(Response::new(ReportMessageResponse {
+        result: Some(report_message_response::Result::Success(ReportMessageSuccess {
+            report_id: report.report_id,
+            sender_user_id: report.sender_user_id,
+            timestamp: Some(Timestamp {
+                seconds: reported_at,
+                nanos: 0,
+            }),
+        })),
+    }))
+}
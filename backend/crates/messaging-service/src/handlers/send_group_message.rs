@@ -6,6 +6,9 @@ use crate::proto::messaging::{
     SendGroupMessageSuccess,
 };
 use crate::proto::common::ErrorResponse;
+use guardyn_crypto::franking::FrankingCommitment;
+use guardyn_crypto::x3dh::IdentityKeyPair;
+use guardyn_crypto::{FrankingRecipient, FrankingTag};
 use std::sync::Arc;
 use tonic::{Response, Status};
 
@@ -13,12 +16,10 @@ pub async fn send_group_message(
     request: SendGroupMessageRequest,
     db: Arc<DatabaseClient>,
     nats: Arc<NatsClient>,
+    franking_key: Arc<IdentityKeyPair>,
 ) -> Result<Response<SendGroupMessageResponse>, Status> {
     // Validate JWT token and extract user_id (sender)
-    let jwt_secret = std::env::var("JWT_SECRET")
-        .unwrap_or_else(|_| "default-jwt-secret-change-in-production".to_string());
-
-    let (sender_user_id, sender_device_id, _sender_username) = match crate::jwt::validate_and_extract(&request.access_token, &jwt_secret) {
+    let (sender_user_id, sender_device_id, _sender_username) = match crate::jwt::validate_and_extract(&request.access_token) {
         Ok(ids) => ids,
         Err(_) => {
             return Ok(Response::new(SendGroupMessageResponse {
@@ -53,6 +54,23 @@ pub async fn send_group_message(
         }));
     }
 
+    let franking_commitment = if request.franking_commitment.is_empty() {
+        None
+    } else {
+        match FrankingCommitment::try_from(request.franking_commitment.as_slice()) {
+            Ok(commitment) => Some(commitment),
+            Err(_) => {
+                return Ok(Response::new(SendGroupMessageResponse {
+                    result: Some(send_group_message_response::Result::Error(ErrorResponse {
+                        code: 3, // INVALID_ARGUMENT
+                        message: "Franking commitment must be 32 bytes".to_string(),
+                        details: Default::default(),
+                    })),
+                }));
+            }
+        }
+    };
+
     // Verify group exists and sender is a member
     match db.get_group(&request.group_id).await {
         Ok(Some(_group)) => {
@@ -96,9 +114,43 @@ pub async fn send_group_message(
 
     tracing::info!("Generated message_id={}, timestamp={}", message_id, server_timestamp_millis);
 
+    // Sign the franking commitment; one tag covers every member of the group
+    let franking_tag = match franking_commitment {
+        Some(commitment) => {
+            let tag = FrankingTag::issue(
+                &franking_key,
+                &message_id,
+                &sender_user_id,
+                &sender_device_id,
+                FrankingRecipient::Group(request.group_id.clone()),
+                server_timestamp_millis / 1000,
+                commitment,
+            )
+            .and_then(|tag| tag.to_bytes());
+
+            match tag {
+                Ok(tag) => Some(tag),
+                Err(e) => {
+                    tracing::error!("Failed to issue franking tag: {}", e);
+                    return Ok(Response::new(SendGroupMessageResponse {
+                        result: Some(send_group_message_response::Result::Error(ErrorResponse {
+                            code: 13, // INTERNAL
+                            message: "Failed to issue franking tag".to_string(),
+                            details: Default::default(),
+                        })),
+                    }));
+                }
+            }
+        }
+        None => None,
+    };
+
     // Prepare metadata (empty for MVP)
     let mut metadata = std::collections::HashMap::new();
     metadata.insert("message_type".to_string(), request.message_type.to_string());
+    if let Some(tag) = &franking_tag {
+        metadata.insert("franking_tag".to_string(), hex::encode(tag));
+    }
 
     // Store group message in ScyllaDB
     let group_message = crate::models::GroupMessage {
@@ -128,6 +180,20 @@ pub async fn send_group_message(
         }));
     }
 
+    // Keep the tag where abuse reports can find it, independent of message history
+    if let Some(tag) = &franking_tag {
+        if let Err(e) = db.store_franking_tag(&message_id, tag).await {
+            tracing::error!("Failed to store franking tag: {}", e);
+            return Ok(Response::new(SendGroupMessageResponse {
+                result: Some(send_group_message_response::Result::Error(ErrorResponse {
+                    code: 13, // INTERNAL
+                    message: "Failed to store franking tag".to_string(),
+                    details: Default::default(),
+                })),
+            }));
+        }
+    }
+
     // Get all group members for NATS fanout
     let members = match db.get_group_members(&request.group_id).await {
         Ok(members) => members,
@@ -159,9 +225,12 @@ pub async fn send_group_message(
             sender_user_id: sender_user_id.clone(),
             sender_device_id: sender_device_id.clone(),
             recipient_user_id: member.user_id.clone(),
+            recipient_device_id: None,
             encrypted_content: request.encrypted_content.clone(),
             timestamp: server_timestamp_millis / 1000, // Convert millis to seconds for NATS
             x3dh_prekey: None, // Group messages don't use X3DH prekey
+            sealed: false,
+            franking_tag: franking_tag.clone(),
         };
 
         // Publish to NATS
@@ -199,6 +268,7 @@ pub async fn send_group_message(
                     seconds: (server_timestamp_millis / 1000) as i64,
                     nanos: ((server_timestamp_millis % 1000) * 1_000_000) as i32,
                 }),
+                self_update_required: false,
             },
         )),
     }))
@@ -2,8 +2,12 @@ use std::collections::HashMap;
 /// Handler for sending MLS-encrypted group messages
 ///
 /// Uses MLS (Messaging Layer Security) protocol for group encryption.
-/// Encrypts messages with the current group epoch state.
+/// The sender's device encrypts the message with its own group state; the
+/// server only stores and fans out the MLS ciphertext, tagged with the epoch
+/// it was created in. Tells the sender when `MlsConfig` asks it to rotate its
+/// leaf.
 
+use crate::config::MlsConfig;
 use crate::db::DatabaseClient;
 use crate::mls_manager::MlsManager;
 use crate::nats::NatsClient;
@@ -12,7 +16,7 @@ use crate::proto::messaging::{
     SendGroupMessageSuccess,
 };
 use crate::proto::common::ErrorResponse;
-use guardyn_crypto::mls::MlsGroupManager;
+use guardyn_crypto::mls::message_epoch;
 use std::sync::Arc;
 use tonic::{Response, Status};
 use tracing::{error, info};
@@ -21,12 +25,10 @@ pub async fn send_group_message_mls(
     request: SendGroupMessageRequest,
     db: Arc<DatabaseClient>,
     nats: Arc<NatsClient>,
+    mls_config: &MlsConfig,
 ) -> Result<Response<SendGroupMessageResponse>, Status> {
     // Validate JWT token and extract user_id (sender)
-    let jwt_secret = std::env::var("JWT_SECRET")
-        .unwrap_or_else(|_| "default-jwt-secret-change-in-production".to_string());
-
-    let (sender_user_id, sender_device_id, _sender_username) = match crate::jwt::validate_and_extract(&request.access_token, &jwt_secret) {
+    let (sender_user_id, sender_device_id, _sender_username) = match crate::jwt::validate_and_extract(&request.access_token) {
         Ok(ids) => ids,
         Err(_) => {
             return Ok(Response::new(SendGroupMessageResponse {
@@ -50,7 +52,7 @@ pub async fn send_group_message_mls(
         }));
     }
 
-    // Validate plaintext content (will be encrypted with MLS)
+    // Validate MLS ciphertext
     if request.encrypted_content.is_empty() {
         return Ok(Response::new(SendGroupMessageResponse {
             result: Some(send_group_message_response::Result::Error(ErrorResponse {
@@ -119,46 +121,19 @@ pub async fn send_group_message_mls(
         }
     }
 
-    // Load MLS group state
-    let group_state = match mls_manager.load_group_state(&request.group_id).await {
-        Ok(state) => state,
+    let mls_epoch = match message_epoch(&request.encrypted_content) {
+        Ok(epoch) => epoch,
         Err(e) => {
-            error!("Failed to load group state: {}", e);
             return Ok(Response::new(SendGroupMessageResponse {
                 result: Some(send_group_message_response::Result::Error(ErrorResponse {
-                    code: crate::proto::common::error_response::ErrorCode::InternalError as i32,
-                    message: "Failed to load group state".to_string(),
+                    code: crate::proto::common::error_response::ErrorCode::InvalidRequest as i32,
+                    message: "Invalid MLS message".to_string(),
                     details: { let mut map = HashMap::new(); map.insert("error".to_string(), e.to_string()); map },
                 })),
             }));
         }
     };
-
-    // Reconstruct MLS group manager from state
-    // Note: This requires the sender's credential bundle
-    // For now, we'll use a test credential (production should fetch from secure storage)
-    let sender_identity = format!("{}:{}", sender_user_id, sender_device_id);
-    let credential_bundle = match guardyn_crypto::mls::create_test_credential(sender_identity.as_bytes()) {
-        Ok(cred) => cred,
-        Err(e) => {
-            error!("Failed to create credential: {}", e);
-            return Ok(Response::new(SendGroupMessageResponse {
-                result: Some(send_group_message_response::Result::Error(ErrorResponse {
-                    code: crate::proto::common::error_response::ErrorCode::InternalError as i32,
-                    message: "Failed to initialize encryption".to_string(),
-                    details: { let mut map = HashMap::new(); map.insert("error".to_string(), e.to_string()); map },
-                })),
-            }));
-        }
-    };
-
-    // TODO: Deserialize group state and reconstruct MlsGroupManager
-    // This is a limitation of the current OpenMLS API - it doesn't provide
-    // direct state deserialization. We need to maintain the group manager
-    // in memory or implement custom serialization.
-    //
-    // For MVP, we'll use the plaintext content as "encrypted_content"
-    // and track the MLS epoch separately.
+    let mls_ciphertext = request.encrypted_content;
 
     // Generate message ID
     use uuid::v1::{Context, Timestamp};
@@ -170,7 +145,7 @@ pub async fn send_group_message_mls(
     let message_id = uuid::Uuid::new_v1(uuid_timestamp, &[1, 2, 3, 4, 5, 6]).to_string();
     let server_timestamp_millis = chrono::Utc::now().timestamp_millis();
 
-    info!("Generated message_id={}, MLS epoch={}", message_id, group_state.epoch);
+    info!("Generated message_id={}, MLS epoch={}", message_id, mls_epoch);
 
     // Prepare metadata
     let mut metadata = std::collections::HashMap::new();
@@ -183,15 +158,15 @@ pub async fn send_group_message_mls(
         group_id: request.group_id.clone(),
         sender_user_id: sender_user_id.clone(),
         sender_device_id: sender_device_id.clone(),
-        encrypted_content: request.encrypted_content.clone(), // TODO: Replace with MLS-encrypted content
-        mls_epoch: group_state.epoch as i64,
+        encrypted_content: mls_ciphertext.clone(),
+        mls_epoch: mls_epoch as i64,
         sent_at: server_timestamp_millis,
         metadata,
     };
 
     info!(
         "Storing MLS-encrypted group message: message_id={}, group_id={}, epoch={}",
-        message_id, request.group_id, group_state.epoch
+        message_id, request.group_id, mls_epoch
     );
 
     if let Err(e) = db.store_group_message(&group_message).await {
@@ -233,8 +208,8 @@ pub async fn send_group_message_mls(
             "group_id": request.group_id,
             "sender_user_id": sender_user_id,
             "sender_device_id": sender_device_id,
-            "encrypted_content": &request.encrypted_content,
-            "mls_epoch": group_state.epoch,
+            "encrypted_content": &mls_ciphertext,
+            "mls_epoch": mls_epoch,
             "sent_at": server_timestamp_millis,
             "message_type": request.message_type,
         });
@@ -249,6 +224,22 @@ pub async fn send_group_message_mls(
         }
     }
 
+    // Post-compromise security: ask the sender to rotate its leaf periodically
+    let self_update_required = match mls_manager
+        .record_leaf_message(&request.group_id, &sender_user_id, &sender_device_id)
+        .await
+    {
+        Ok(state) => mls_config.self_update_due(
+            state.messages_since_update,
+            state.last_update_at,
+            chrono::Utc::now().timestamp(),
+        ),
+        Err(e) => {
+            error!("Failed to record leaf usage for group {}: {}", request.group_id, e);
+            false
+        }
+    };
+
     // Return success response
     Ok(Response::new(SendGroupMessageResponse {
         result: Some(send_group_message_response::Result::Success(
@@ -258,6 +249,7 @@ pub async fn send_group_message_mls(
                     seconds: server_timestamp_millis / 1000,
                     nanos: ((server_timestamp_millis % 1000) * 1_000_000) as i32,
                 }),
+                self_update_required,
             },
         )),
     }))
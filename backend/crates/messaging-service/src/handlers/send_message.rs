@@ -6,6 +6,9 @@ use crate::proto::messaging::{
     send_message_response, SendMessageRequest, SendMessageResponse, SendMessageSuccess,
 };
 use crate::proto::common::{ErrorResponse, Timestamp};
+use guardyn_crypto::franking::FrankingCommitment;
+use guardyn_crypto::x3dh::IdentityKeyPair;
+use guardyn_crypto::{FrankingRecipient, FrankingTag};
 use std::sync::Arc;
 use tonic::{Response, Status};
 use uuid::Uuid;
@@ -14,12 +17,10 @@ pub async fn send_message(
     request: SendMessageRequest,
     db: Arc<DatabaseClient>,
     nats: Arc<NatsClient>,
+    franking_key: Arc<IdentityKeyPair>,
 ) -> Result<Response<SendMessageResponse>, Status> {
     // Validate JWT token and extract user_id + device_id
-    let jwt_secret = std::env::var("JWT_SECRET")
-        .unwrap_or_else(|_| "default-jwt-secret-change-in-production".to_string());
-
-    let (sender_user_id, sender_device_id, sender_username) = match crate::jwt::validate_and_extract(&request.access_token, &jwt_secret) {
+    let (sender_user_id, sender_device_id, sender_username) = match crate::jwt::validate_and_extract(&request.access_token) {
         Ok((user_id, device_id, username)) => (user_id, device_id, username),
         Err(_) => {
             return Ok(Response::new(SendMessageResponse {
@@ -54,10 +55,58 @@ pub async fn send_message(
         }));
     }
 
+    let franking_commitment = if request.franking_commitment.is_empty() {
+        None
+    } else {
+        match FrankingCommitment::try_from(request.franking_commitment.as_slice()) {
+            Ok(commitment) => Some(commitment),
+            Err(_) => {
+                return Ok(Response::new(SendMessageResponse {
+                    result: Some(send_message_response::Result::Error(ErrorResponse {
+                        code: 3, // INVALID_ARGUMENT
+                        message: "Franking commitment must be 32 bytes".to_string(),
+                        details: Default::default(),
+                    })),
+                }));
+            }
+        }
+    };
+
     // Generate message ID
     let message_id = Uuid::new_v4().to_string();
     let server_timestamp = chrono::Utc::now().timestamp();
 
+    // Sign the franking commitment so the recipient can later report this message
+    let franking_tag = match franking_commitment {
+        Some(commitment) => {
+            let tag = FrankingTag::issue(
+                &franking_key,
+                &message_id,
+                &sender_user_id,
+                &sender_device_id,
+                FrankingRecipient::User(request.recipient_user_id.clone()),
+                server_timestamp,
+                commitment,
+            )
+            .and_then(|tag| tag.to_bytes());
+
+            match tag {
+                Ok(tag) => Some(tag),
+                Err(e) => {
+                    tracing::error!("Failed to issue franking tag: {}", e);
+                    return Ok(Response::new(SendMessageResponse {
+                        result: Some(send_message_response::Result::Error(ErrorResponse {
+                            code: 13, // INTERNAL
+                            message: "Failed to issue franking tag".to_string(),
+                            details: Default::default(),
+                        })),
+                    }));
+                }
+            }
+        }
+        None => None,
+    };
+
     // Generate conversation ID (deterministic based on participants)
     let conversation_id = generate_conversation_id(
         &sender_user_id,
@@ -92,6 +141,8 @@ pub async fn send_message(
         } else {
             Some(request.x3dh_prekey.clone())
         },
+        device_copy: false,
+        franking_tag: franking_tag.clone(),
     };
 
     // Debug: log stored message before saving
@@ -115,6 +166,20 @@ pub async fn send_message(
         }));
     }
 
+    // Keep the tag where abuse reports can find it, independent of message history
+    if let Some(tag) = &stored_msg.franking_tag {
+        if let Err(e) = db.store_franking_tag(&message_id, tag).await {
+            tracing::error!("Failed to store franking tag: {}", e);
+            return Ok(Response::new(SendMessageResponse {
+                result: Some(send_message_response::Result::Error(ErrorResponse {
+                    code: 13, // INTERNAL
+                    message: "Failed to store franking tag".to_string(),
+                    details: Default::default(),
+                })),
+            }));
+        }
+    }
+
     // Create delivery state in TiKV
     let delivery_state = DeliveryState {
         message_id: message_id.clone(),
@@ -182,6 +247,7 @@ pub async fn send_message(
         sender_user_id: sender_user_id.clone(),
         sender_device_id: sender_device_id.clone(),
         recipient_user_id: request.recipient_user_id.clone(),
+        recipient_device_id: stored_msg.recipient_device_id.clone(),
         encrypted_content: request.encrypted_content,
         timestamp: server_timestamp,
         x3dh_prekey: if request.x3dh_prekey.is_empty() {
@@ -189,6 +255,8 @@ pub async fn send_message(
         } else {
             Some(request.x3dh_prekey)
         },
+        sealed: false,
+        franking_tag,
     };
 
     if let Err(e) = nats.publish_message(&envelope).await {
@@ -212,7 +280,7 @@ pub async fn send_message(
 }
 
 /// Generate deterministic conversation ID from two user IDs
-fn generate_conversation_id(user1: &str, user2: &str) -> String {
+pub(crate) fn generate_conversation_id(user1: &str, user2: &str) -> String {
     // Sort user IDs to ensure consistency regardless of sender/recipient order
     let mut users = vec![user1, user2];
     users.sort();
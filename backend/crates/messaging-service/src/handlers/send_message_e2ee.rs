@@ -5,14 +5,18 @@
 ///
 /// TODO: Replace existing send_message.rs with this implementation after testing
 
+use crate::auth_client::AuthClient;
 use crate::db::DatabaseClient;
+use crate::handlers::send_message_multi_device::{deliver, FanOut};
 use crate::models::{DeliveryState, DeliveryStatus, StoredMessage, RatchetSession};
 use crate::nats::{MessageEnvelope, NatsClient};
 use crate::crypto::SessionManager;
 use crate::proto::messaging::{
-    send_message_response, SendMessageRequest, SendMessageResponse, SendMessageSuccess,
+    send_message_response, DeviceAddress, DeviceMessage, MismatchedDevices, SendMessageRequest,
+    SendMessageResponse, SendMessageSuccess,
 };
 use crate::proto::common::{ErrorResponse, Timestamp};
+use guardyn_crypto::x3dh::IdentityKeyPair;
 use std::sync::Arc;
 use tonic::{Response, Status};
 use uuid::Uuid;
@@ -21,12 +25,10 @@ pub async fn send_message_e2ee(
     request: SendMessageRequest,
     db: Arc<DatabaseClient>,
     nats: Arc<NatsClient>,
+    franking_key: Arc<IdentityKeyPair>,
 ) -> Result<Response<SendMessageResponse>, Status> {
     // Validate JWT token and extract user_id + device_id
-    let jwt_secret = std::env::var("JWT_SECRET")
-        .unwrap_or_else(|_| "default-jwt-secret-change-in-production".to_string());
-
-    let (sender_user_id, sender_device_id, sender_username) = match crate::jwt::validate_and_extract(&request.access_token, &jwt_secret) {
+    let (sender_user_id, sender_device_id, sender_username) = match crate::jwt::validate_and_extract(&request.access_token) {
         Ok((user_id, device_id, username)) => (user_id, device_id, username),
         Err(_) => {
             return Ok(Response::new(SendMessageResponse {
@@ -68,21 +70,22 @@ pub async fn send_message_e2ee(
     let auth_service_url = std::env::var("AUTH_SERVICE_URL")
         .unwrap_or_else(|_| "http://auth-service:50051".to_string());
 
-    let session_manager = SessionManager::new(db.clone(), auth_service_url);
+    let session_manager = SessionManager::new(db.clone(), auth_service_url, nats.clone());
 
-    let recipient_device_id = if request.recipient_device_id.is_empty() {
-        // TODO: Query auth-service for recipient's default device
-        // For now, return error
-        return Ok(Response::new(SendMessageResponse {
-            result: Some(send_message_response::Result::Error(ErrorResponse {
-                code: 3, // INVALID_ARGUMENT
-                message: "Recipient device ID required for E2EE".to_string(),
-                details: Default::default(),
-            })),
-        }));
-    } else {
-        request.recipient_device_id.clone()
-    };
+    // Without a target device, encrypt for every device of the recipient and
+    // for the sender's other devices
+    if request.recipient_device_id.is_empty() {
+        return send_to_all_devices(
+            request,
+            (sender_user_id, sender_device_id, sender_username),
+            &session_manager,
+            &db,
+            &nats,
+            &franking_key,
+        ).await;
+    }
+
+    let recipient_device_id = request.recipient_device_id.clone();
 
     // Get or initialize session
     let mut ratchet = match session_manager.get_or_create_session(
@@ -168,6 +171,8 @@ pub async fn send_message_e2ee(
         } else {
             Some(request.x3dh_prekey.clone())
         },
+        device_copy: false,
+        franking_tag: None,
     };
 
     tracing::debug!(
@@ -243,6 +248,7 @@ pub async fn send_message_e2ee(
         sender_user_id: sender_user_id.clone(),
         sender_device_id: sender_device_id.clone(),
         recipient_user_id: request.recipient_user_id.clone(),
+        recipient_device_id: Some(recipient_device_id),
         encrypted_content: stored_msg.encrypted_content,
         timestamp: server_timestamp,
         x3dh_prekey: if request.x3dh_prekey.is_empty() {
@@ -250,6 +256,8 @@ pub async fn send_message_e2ee(
         } else {
             Some(request.x3dh_prekey)
         },
+        sealed: false,
+        franking_tag: None,
     };
 
     if let Err(e) = nats.publish_message(&envelope).await {
@@ -271,6 +279,186 @@ pub async fn send_message_e2ee(
     }))
 }
 
+/// Encrypt for every device of the recipient and every other device of the sender
+///
+/// Device lists come from auth-service. The server only encrypts over
+/// sessions that already exist; if a current device has no session, or a
+/// stored session belongs to a device that has been removed, nothing is sent
+/// and the caller gets `MismatchedDevices` listing them. Sessions with removed
+/// devices are deleted before responding so a retry can go through.
+async fn send_to_all_devices(
+    request: SendMessageRequest,
+    (sender_user_id, sender_device_id, sender_username): (String, String, String),
+    session_manager: &SessionManager,
+    db: &DatabaseClient,
+    nats: &NatsClient,
+    franking_key: &IdentityKeyPair,
+) -> Result<Response<SendMessageResponse>, Status> {
+    let error = |code: i32, message: String| -> Result<Response<SendMessageResponse>, Status> {
+        Ok(Response::new(SendMessageResponse {
+            result: Some(send_message_response::Result::Error(ErrorResponse {
+                code,
+                message,
+                details: Default::default(),
+            })),
+        }))
+    };
+
+    let auth_service_url = std::env::var("AUTH_SERVICE_URL")
+        .unwrap_or_else(|_| "http://auth-service:50051".to_string());
+
+    let mut auth_client = match AuthClient::new(&auth_service_url).await {
+        Ok(client) => client,
+        Err(e) => {
+            tracing::error!("Failed to connect to auth-service: {}", e);
+            return error(14, "Auth service unavailable".to_string()); // UNAVAILABLE
+        }
+    };
+
+    let mut users = vec![request.recipient_user_id.clone()];
+    if sender_user_id != request.recipient_user_id {
+        users.push(sender_user_id.clone());
+    }
+
+    let mut sessions = Vec::new();
+    let mut mismatched = MismatchedDevices::default();
+    let mut recipient_has_devices = false;
+
+    for user_id in users {
+        let device_ids = match auth_client.get_device_ids(&request.access_token, &user_id).await {
+            Ok(device_ids) => device_ids,
+            Err(e) => {
+                tracing::error!("Failed to fetch devices of user {}: {}", user_id, e);
+                return error(14, "Failed to fetch device list".to_string()); // UNAVAILABLE
+            }
+        };
+
+        match session_manager.prune_sessions(&sender_user_id, &sender_device_id, &user_id, &device_ids).await {
+            Ok(removed) => {
+                mismatched.extra_devices.extend(removed.into_iter().map(|device_id| DeviceAddress {
+                    user_id: user_id.clone(),
+                    device_id,
+                }));
+            }
+            Err(e) => {
+                tracing::error!("Failed to prune sessions with {}: {}", user_id, e);
+                return error(13, "Failed to check sessions with removed devices".to_string()); // INTERNAL
+            }
+        }
+
+        for device_id in device_ids {
+            if user_id == sender_user_id && device_id == sender_device_id {
+                continue;
+            }
+            if user_id == request.recipient_user_id {
+                recipient_has_devices = true;
+            }
+
+            match session_manager.find_session(
+                &sender_user_id,
+                &sender_device_id,
+                &user_id,
+                &device_id,
+            ).await {
+                Ok(Some(ratchet)) => sessions.push((user_id.clone(), device_id, ratchet)),
+                Ok(None) => mismatched.missing_devices.push(DeviceAddress {
+                    user_id: user_id.clone(),
+                    device_id,
+                }),
+                Err(e) => {
+                    tracing::error!("Failed to load ratchet session with {}:{}: {}", user_id, device_id, e);
+                    return error(13, format!("Failed to load E2EE session: {}", e)); // INTERNAL
+                }
+            }
+        }
+    }
+
+    if !recipient_has_devices {
+        return error(5, "Recipient has no registered devices".to_string()); // NOT_FOUND
+    }
+
+    // Nothing has been encrypted yet, so no ratchet has advanced
+    if !mismatched.missing_devices.is_empty() || !mismatched.extra_devices.is_empty() {
+        tracing::debug!(
+            "Rejecting send from {}: {} devices without a session, {} removed devices",
+            sender_user_id,
+            mismatched.missing_devices.len(),
+            mismatched.extra_devices.len()
+        );
+        return Ok(Response::new(SendMessageResponse {
+            result: Some(send_message_response::Result::MismatchedDevices(mismatched)),
+        }));
+    }
+
+    let server_timestamp = chrono::Utc::now().timestamp();
+    let mut device_messages = Vec::with_capacity(sessions.len());
+
+    for (user_id, device_id, ratchet) in sessions {
+        let session_id = RatchetSession::session_id(&sender_user_id, &sender_device_id, &user_id, &device_id);
+        let associated_data = format!("{}|{}|{}", sender_user_id, user_id, server_timestamp);
+
+        let encrypted_content = match session_manager.encrypt_and_save(
+            &session_id,
+            ratchet,
+            &request.encrypted_content,
+            associated_data.as_bytes(),
+        ).await {
+            Ok(ciphertext) => ciphertext,
+            Err(e) => {
+                tracing::error!("Failed to encrypt message: {}", e);
+                return error(13, format!("Message encryption failed: {}", e)); // INTERNAL
+            }
+        };
+
+        device_messages.push(DeviceMessage {
+            user_id,
+            device_id,
+            encrypted_content,
+            // Every session already exists, so no message starts one
+            x3dh_prekey: String::new(),
+        });
+    }
+
+    let fan_out = FanOut {
+        sender_user_id,
+        sender_device_id,
+        sender_username,
+        recipient_user_id: request.recipient_user_id,
+        recipient_username: request.recipient_username,
+        message_type: request.message_type,
+        server_timestamp,
+        client_timestamp: request
+            .client_timestamp
+            .as_ref()
+            .map(|ts| ts.seconds)
+            .unwrap_or(server_timestamp),
+        device_messages,
+        // The server encrypted this message itself, so there is nothing to frank
+        franking_commitment: None,
+    };
+
+    let message_id = match deliver(fan_out, db, nats, franking_key).await {
+        Ok(message_id) => message_id,
+        Err(e) => {
+            tracing::error!("Failed to store multi-device message: {}", e);
+            return error(13, format!("Failed to store message: {}", e)); // INTERNAL
+        }
+    };
+
+    Ok(Response::new(SendMessageResponse {
+        result: Some(send_message_response::Result::Success(
+            SendMessageSuccess {
+                message_id,
+                server_timestamp: Some(Timestamp {
+                    seconds: server_timestamp,
+                    nanos: 0,
+                }),
+                delivery_status: DeliveryStatus::Sent.to_i32(),
+            },
+        )),
+    }))
+}
+
 /// Generate deterministic conversation ID from two user IDs
 fn generate_conversation_id(user1: &str, user2: &str) -> String {
     let mut users = vec![user1, user2];
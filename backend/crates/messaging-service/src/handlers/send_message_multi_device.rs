@@ -0,0 +1,348 @@
+/// Handler for multi-device (fan-out) 1-on-1 messages
+///
+/// The client encrypts a message once per device: for every device of the
+/// recipient and for each of the sender's other devices, so all of them see
+/// the conversation. The addressed devices must match the current device lists
+/// from auth-service exactly. Otherwise nothing is stored and the response
+/// lists the missing and extra devices, so the client can set up sessions for
+/// new devices, drop sessions for removed ones and retry.
+///
+/// Each copy is stored under its own message ID, derived from the logical
+/// message ID and the device address, and flagged as a device copy so message
+/// history only returns it to the device it was encrypted for.
+///
+/// If the client franked the message, every copy gets its own server-signed
+/// franking tag binding the commitment to the sender, recipient and copy ID.
+use crate::auth_client::AuthClient;
+use crate::db::DatabaseClient;
+use crate::handlers::send_message::generate_conversation_id;
+use crate::models::{DeliveryState, DeliveryStatus, StoredMessage};
+use crate::nats::{MessageEnvelope, NatsClient};
+use crate::proto::messaging::{
+    send_message_response, DeviceAddress, DeviceMessage, MismatchedDevices, SendMessageRequest,
+    SendMessageResponse, SendMessageSuccess,
+};
+use crate::proto::common::{ErrorResponse, Timestamp};
+use guardyn_crypto::franking::FrankingCommitment;
+use guardyn_crypto::x3dh::IdentityKeyPair;
+use guardyn_crypto::{FrankingRecipient, FrankingTag};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use tonic::{Response, Status};
+use uuid::Uuid;
+
+/// A message addressed to every device of a conversation
+pub(crate) struct FanOut {
+    pub sender_user_id: String,
+    pub sender_device_id: String,
+    pub sender_username: String,
+    pub recipient_user_id: String,
+    pub recipient_username: String,
+    pub message_type: i32,
+    pub server_timestamp: i64,
+    pub client_timestamp: i64,
+    pub device_messages: Vec<DeviceMessage>,
+    /// Franking commitment to sign for every copy, if the message is franked
+    pub franking_commitment: Option<FrankingCommitment>,
+}
+
+pub async fn send_message_multi_device(
+    request: SendMessageRequest,
+    db: Arc<DatabaseClient>,
+    nats: Arc<NatsClient>,
+    franking_key: Arc<IdentityKeyPair>,
+) -> Result<Response<SendMessageResponse>, Status> {
+    let error = |code: i32, message: &str| -> Result<Response<SendMessageResponse>, Status> {
+        Ok(Response::new(SendMessageResponse {
+            result: Some(send_message_response::Result::Error(ErrorResponse {
+                code,
+                message: message.to_string(),
+                details: Default::default(),
+            })),
+        }))
+    };
+
+    // Validate JWT token and extract user_id + device_id
+    let (sender_user_id, sender_device_id, sender_username) = match crate::jwt::validate_and_extract(&request.access_token) {
+        Ok(claims) => claims,
+        Err(_) => return error(16, "Invalid or expired access token"), // UNAUTHENTICATED
+    };
+
+    if request.recipient_user_id.is_empty() {
+        return error(3, "Recipient user ID required"); // INVALID_ARGUMENT
+    }
+
+    // Every ciphertext must target a device of the recipient or a sibling device
+    let mut addressed = BTreeSet::new();
+    for device_message in &request.device_messages {
+        if device_message.device_id.is_empty() || device_message.encrypted_content.is_empty() {
+            return error(3, "Device ID and encrypted content required for every device"); // INVALID_ARGUMENT
+        }
+        if device_message.user_id != request.recipient_user_id
+            && device_message.user_id != sender_user_id
+        {
+            return error(3, "Device message addressed to a user outside the conversation"); // INVALID_ARGUMENT
+        }
+        if !addressed.insert((device_message.user_id.clone(), device_message.device_id.clone())) {
+            return error(3, "Duplicate device in device messages"); // INVALID_ARGUMENT
+        }
+    }
+
+    let franking_commitment = if request.franking_commitment.is_empty() {
+        None
+    } else {
+        match FrankingCommitment::try_from(request.franking_commitment.as_slice()) {
+            Ok(commitment) => Some(commitment),
+            Err(_) => return error(3, "Franking commitment must be 32 bytes"), // INVALID_ARGUMENT
+        }
+    };
+
+    // Compare against the current device lists
+    let auth_service_url = std::env::var("AUTH_SERVICE_URL")
+        .unwrap_or_else(|_| "http://auth-service:50051".to_string());
+
+    let mut auth_client = match AuthClient::new(&auth_service_url).await {
+        Ok(client) => client,
+        Err(e) => {
+            tracing::error!("Failed to connect to auth-service: {}", e);
+            return error(14, "Auth service unavailable"); // UNAVAILABLE
+        }
+    };
+
+    let mut expected = BTreeSet::new();
+    for user_id in [&request.recipient_user_id, &sender_user_id] {
+        match auth_client.get_device_ids(&request.access_token, user_id).await {
+            Ok(device_ids) => {
+                expected.extend(device_ids.into_iter().map(|device_id| (user_id.clone(), device_id)));
+            }
+            Err(e) => {
+                tracing::error!("Failed to fetch devices of user {}: {}", user_id, e);
+                return error(14, "Failed to fetch device list"); // UNAVAILABLE
+            }
+        }
+    }
+
+    // The sending device already has the plaintext
+    expected.remove(&(sender_user_id.clone(), sender_device_id.clone()));
+
+    if !expected.iter().any(|(user_id, _)| *user_id == request.recipient_user_id) {
+        return error(5, "Recipient has no registered devices"); // NOT_FOUND
+    }
+
+    if let Some(mismatched) = mismatched_devices(&expected, &addressed) {
+        tracing::debug!(
+            "Rejecting stale device list from {}: {} missing, {} extra",
+            sender_user_id,
+            mismatched.missing_devices.len(),
+            mismatched.extra_devices.len()
+        );
+        return Ok(Response::new(SendMessageResponse {
+            result: Some(send_message_response::Result::MismatchedDevices(mismatched)),
+        }));
+    }
+
+    let server_timestamp = chrono::Utc::now().timestamp();
+    let fan_out = FanOut {
+        sender_user_id,
+        sender_device_id,
+        sender_username,
+        recipient_user_id: request.recipient_user_id,
+        recipient_username: request.recipient_username,
+        message_type: request.message_type,
+        server_timestamp,
+        client_timestamp: request
+            .client_timestamp
+            .as_ref()
+            .map(|ts| ts.seconds)
+            .unwrap_or(server_timestamp),
+        device_messages: request.device_messages,
+        franking_commitment,
+    };
+
+    let message_id = match deliver(fan_out, &db, &nats, &franking_key).await {
+        Ok(message_id) => message_id,
+        Err(e) => {
+            tracing::error!("Failed to store multi-device message: {}", e);
+            return error(13, "Failed to store message"); // INTERNAL
+        }
+    };
+
+    Ok(Response::new(SendMessageResponse {
+        result: Some(send_message_response::Result::Success(
+            SendMessageSuccess {
+                message_id,
+                server_timestamp: Some(Timestamp {
+                    seconds: server_timestamp,
+                    nanos: 0,
+                }),
+                delivery_status: DeliveryStatus::Sent.to_i32(),
+            },
+        )),
+    }))
+}
+
+/// Store and publish one copy per device, returning the logical message ID
+///
+/// The device list must already have been checked against auth-service.
+pub(crate) async fn deliver(
+    fan_out: FanOut,
+    db: &DatabaseClient,
+    nats: &NatsClient,
+    franking_key: &IdentityKeyPair,
+) -> anyhow::Result<String> {
+    let message_id = Uuid::new_v4();
+    let conversation_id = generate_conversation_id(&fan_out.sender_user_id, &fan_out.recipient_user_id);
+
+    let copies: Vec<StoredMessage> = fan_out
+        .device_messages
+        .into_iter()
+        .map(|device_message| {
+            let copy_id = device_copy_id(&message_id, &device_message.user_id, &device_message.device_id);
+            let franking_tag = match fan_out.franking_commitment {
+                Some(commitment) => Some(
+                    FrankingTag::issue(
+                        franking_key,
+                        &copy_id,
+                        &fan_out.sender_user_id,
+                        &fan_out.sender_device_id,
+                        FrankingRecipient::User(device_message.user_id.clone()),
+                        fan_out.server_timestamp,
+                        commitment,
+                    )?
+                    .to_bytes()?,
+                ),
+                None => None,
+            };
+
+            Ok(StoredMessage {
+                message_id: copy_id,
+                conversation_id: conversation_id.clone(),
+                sender_user_id: fan_out.sender_user_id.clone(),
+                sender_device_id: fan_out.sender_device_id.clone(),
+                recipient_user_id: device_message.user_id,
+                recipient_device_id: Some(device_message.device_id),
+                encrypted_content: device_message.encrypted_content,
+                message_type: fan_out.message_type,
+                server_timestamp: fan_out.server_timestamp,
+                client_timestamp: fan_out.client_timestamp,
+                delivery_status: DeliveryStatus::Pending.to_i32(),
+                is_deleted: false,
+                x3dh_prekey: if device_message.x3dh_prekey.is_empty() {
+                    None
+                } else {
+                    Some(device_message.x3dh_prekey)
+                },
+                device_copy: true,
+                franking_tag,
+            })
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    // Store every copy before publishing any, so no device sees a partial send
+    for copy in &copies {
+        db.store_message(copy).await?;
+        if let Some(tag) = &copy.franking_tag {
+            db.store_franking_tag(&copy.message_id, tag).await?;
+        }
+    }
+
+    for copy in &copies {
+        let delivery_state = DeliveryState {
+            message_id: copy.message_id.clone(),
+            sender_user_id: copy.sender_user_id.clone(),
+            sender_device_id: copy.sender_device_id.clone(),
+            recipient_user_id: copy.recipient_user_id.clone(),
+            recipient_device_id: copy.recipient_device_id.clone(),
+            status: DeliveryStatus::Pending,
+            created_at: fan_out.server_timestamp,
+            updated_at: fan_out.server_timestamp,
+        };
+
+        if let Err(e) = db.store_delivery_state(&delivery_state).await {
+            tracing::error!("Failed to store delivery state: {}", e);
+        }
+    }
+
+    // Update conversations table for both sender and recipient
+    let message_id = message_id.to_string();
+    let message_preview = "[E2EE Message]".to_string();
+    let server_timestamp_ms = fan_out.server_timestamp * 1000;
+
+    if let Err(e) = db.upsert_conversation(
+        &fan_out.sender_user_id,
+        &conversation_id,
+        &fan_out.recipient_user_id,
+        &fan_out.recipient_username,
+        &message_id,
+        &message_preview,
+        server_timestamp_ms,
+        false,
+    ).await {
+        tracing::warn!("Failed to update sender conversation: {}", e);
+    }
+
+    if fan_out.recipient_user_id != fan_out.sender_user_id {
+        if let Err(e) = db.upsert_conversation(
+            &fan_out.recipient_user_id,
+            &conversation_id,
+            &fan_out.sender_user_id,
+            &fan_out.sender_username,
+            &message_id,
+            &message_preview,
+            server_timestamp_ms,
+            true,
+        ).await {
+            tracing::warn!("Failed to update recipient conversation: {}", e);
+        }
+    }
+
+    // Publish to NATS for real-time delivery
+    for copy in copies {
+        let envelope = MessageEnvelope {
+            message_id: copy.message_id,
+            sender_user_id: copy.sender_user_id,
+            sender_device_id: copy.sender_device_id,
+            recipient_user_id: copy.recipient_user_id,
+            recipient_device_id: copy.recipient_device_id,
+            encrypted_content: copy.encrypted_content,
+            timestamp: fan_out.server_timestamp,
+            x3dh_prekey: copy.x3dh_prekey,
+            sealed: false,
+            franking_tag: copy.franking_tag,
+        };
+
+        if let Err(e) = nats.publish_message(&envelope).await {
+            tracing::error!("Failed to publish message to NATS: {}", e);
+        }
+    }
+
+    Ok(message_id)
+}
+
+/// Message ID of the copy for one device (deterministic per logical message)
+fn device_copy_id(message_id: &Uuid, user_id: &str, device_id: &str) -> String {
+    Uuid::new_v5(message_id, format!("{}:{}", user_id, device_id).as_bytes()).to_string()
+}
+
+/// Compare the addressed devices with the current ones
+fn mismatched_devices(
+    expected: &BTreeSet<(String, String)>,
+    addressed: &BTreeSet<(String, String)>,
+) -> Option<MismatchedDevices> {
+    let address = |(user_id, device_id): &(String, String)| DeviceAddress {
+        user_id: user_id.clone(),
+        device_id: device_id.clone(),
+    };
+
+    let missing_devices: Vec<DeviceAddress> = expected.difference(addressed).map(address).collect();
+    let extra_devices: Vec<DeviceAddress> = addressed.difference(expected).map(address).collect();
+
+    if missing_devices.is_empty() && extra_devices.is_empty() {
+        None
+    } else {
+        Some(MismatchedDevices {
+            missing_devices,
+            extra_devices,
+        })
+    }
+}
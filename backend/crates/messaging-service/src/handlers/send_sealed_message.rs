@@ -0,0 +1,159 @@
+/// Handlers for sealed sender delivery
+///
+/// Sealed messages are sent without an access token. The envelope carries the
+/// sender certificate encrypted to the recipient, so the server only sees the
+/// recipient address. To keep anonymous sending from being abused, the sender
+/// must present the recipient's delivery token, which the recipient registers
+/// here and shares with their contacts end-to-end. Recipients that have not
+/// registered a token only accept plain (authenticated) delivery.
+///
+/// Sealed messages are not written to ScyllaDB (the conversation cannot be
+/// known without the sender); they are queued in NATS JetStream until the
+/// recipient's stream picks them up.
+use crate::db::DatabaseClient;
+use crate::models::DeliveryStatus;
+use crate::nats::{MessageEnvelope, NatsClient};
+use crate::proto::messaging::{
+    send_message_response, set_delivery_token_response, SendMessageResponse, SendMessageSuccess,
+    SendSealedMessageRequest, SetDeliveryTokenRequest, SetDeliveryTokenResponse,
+    SetDeliveryTokenSuccess,
+};
+use crate::proto::common::{ErrorResponse, Timestamp};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use tonic::{Response, Status};
+use uuid::Uuid;
+
+/// Maximum size of a sealed envelope
+const MAX_SEALED_CONTENT_SIZE: usize = 256 * 1024;
+
+/// Accepted delivery token lengths
+const MIN_DELIVERY_TOKEN_LEN: usize = 16;
+const MAX_DELIVERY_TOKEN_LEN: usize = 32;
+
+pub async fn send_sealed_message(
+    request: SendSealedMessageRequest,
+    db: Arc<DatabaseClient>,
+    nats: Arc<NatsClient>,
+) -> Result<Response<SendMessageResponse>, Status> {
+    let error = |code: i32, message: &str| -> Result<Response<SendMessageResponse>, Status> {
+        Ok(Response::new(SendMessageResponse {
+            result: Some(send_message_response::Result::Error(ErrorResponse {
+                code,
+                message: message.to_string(),
+                details: Default::default(),
+            })),
+        }))
+    };
+
+    if request.recipient_user_id.is_empty() {
+        return error(3, "Recipient user ID required"); // INVALID_ARGUMENT
+    }
+    if request.sealed_content.is_empty() || request.sealed_content.len() > MAX_SEALED_CONTENT_SIZE {
+        return error(3, "Invalid sealed content"); // INVALID_ARGUMENT
+    }
+
+    // Check the delivery token against the one the recipient registered
+    let token_hash = match db.get_delivery_token_hash(&request.recipient_user_id).await {
+        Ok(hash) => hash,
+        Err(e) => {
+            tracing::error!("Failed to load delivery token: {}", e);
+            return error(13, "Internal server error"); // INTERNAL
+        }
+    };
+
+    let authorized = token_hash
+        .map(|expected| constant_time_eq(&expected, &hash_delivery_token(&request.delivery_token)))
+        .unwrap_or(false);
+    if !authorized {
+        // Same response whether the token is wrong or sealed sender is disabled
+        return error(7, "Sealed sender delivery not permitted"); // PERMISSION_DENIED
+    }
+
+    let message_id = Uuid::new_v4().to_string();
+    let server_timestamp = chrono::Utc::now().timestamp();
+
+    let envelope = MessageEnvelope {
+        message_id: message_id.clone(),
+        sender_user_id: String::new(),
+        sender_device_id: String::new(),
+        recipient_user_id: request.recipient_user_id,
+        recipient_device_id: if request.recipient_device_id.is_empty() {
+            None
+        } else {
+            Some(request.recipient_device_id)
+        },
+        encrypted_content: request.sealed_content,
+        timestamp: server_timestamp,
+        x3dh_prekey: None,
+        sealed: true,
+        franking_tag: None,
+    };
+
+    if let Err(e) = nats.publish_message(&envelope).await {
+        tracing::error!("Failed to publish sealed message to NATS: {}", e);
+        return error(14, "Failed to queue message"); // UNAVAILABLE
+    }
+
+    Ok(Response::new(SendMessageResponse {
+        result: Some(send_message_response::Result::Success(
+            SendMessageSuccess {
+                message_id,
+                server_timestamp: Some(Timestamp {
+                    seconds: server_timestamp,
+                    nanos: 0,
+                }),
+                delivery_status: DeliveryStatus::Sent.to_i32(),
+            },
+        )),
+    }))
+}
+
+pub async fn set_delivery_token(
+    request: SetDeliveryTokenRequest,
+    db: Arc<DatabaseClient>,
+) -> Result<Response<SetDeliveryTokenResponse>, Status> {
+    let error = |code: i32, message: &str| -> Result<Response<SetDeliveryTokenResponse>, Status> {
+        Ok(Response::new(SetDeliveryTokenResponse {
+            result: Some(set_delivery_token_response::Result::Error(ErrorResponse {
+                code,
+                message: message.to_string(),
+                details: Default::default(),
+            })),
+        }))
+    };
+
+    let (user_id, _device_id, _username) = match crate::jwt::validate_and_extract(&request.access_token) {
+        Ok(claims) => claims,
+        Err(_) => return error(16, "Invalid or expired access token"), // UNAUTHENTICATED
+    };
+
+    let result = if request.delivery_token.is_empty() {
+        db.delete_delivery_token(&user_id).await
+    } else if (MIN_DELIVERY_TOKEN_LEN..=MAX_DELIVERY_TOKEN_LEN).contains(&request.delivery_token.len()) {
+        db.set_delivery_token_hash(&user_id, &hash_delivery_token(&request.delivery_token)).await
+    } else {
+        return error(3, "Delivery token must be 16-32 bytes"); // INVALID_ARGUMENT
+    };
+
+    if let Err(e) = result {
+        tracing::error!("Failed to update delivery token: {}", e);
+        return error(13, "Failed to update delivery token"); // INTERNAL
+    }
+
+    Ok(Response::new(SetDeliveryTokenResponse {
+        result: Some(set_delivery_token_response::Result::Success(SetDeliveryTokenSuccess {
+            sealed_sender_enabled: !request.delivery_token.is_empty(),
+        })),
+    }))
+}
+
+/// Only a hash of the token is stored
+fn hash_delivery_token(token: &[u8]) -> Vec<u8> {
+    Sha256::digest(token).to_vec()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
@@ -0,0 +1,288 @@
+/// Handler for rotating a member's leaf in MLS-encrypted group chats
+///
+/// The device creates a self-update commit for its own leaf, which gives the
+/// group fresh key material (post-compromise security). The server checks
+/// that the commit only rotates the requester's leaf and stores the GroupInfo
+/// of the new epoch the device uploads, then fences the commit through the
+/// MLS Delivery Service like any other handshake message.
+
+use crate::config::MlsConfig;
+use crate::db::DatabaseClient;
+use crate::mls_manager::{DeliveryService, GroupStateChanged, HandshakeRecord, MlsManager, StaleEpochError};
+use crate::nats::NatsClient;
+use crate::proto::messaging::{
+    update_group_keys_response, UpdateGroupKeysRequest, UpdateGroupKeysResponse,
+    UpdateGroupKeysSuccess,
+};
+use crate::proto::common::ErrorResponse;
+use guardyn_crypto::mls::message_epoch;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tonic::{Response, Status};
+use tracing::{error, info};
+
+pub async fn update_group_keys_mls(
+    request: UpdateGroupKeysRequest,
+    db: Arc<DatabaseClient>,
+    nats: Arc<NatsClient>,
+    mls_config: &MlsConfig,
+) -> Result<Response<UpdateGroupKeysResponse>, Status> {
+    // Validate JWT token and extract user_id (requester)
+    let (requester_user_id, requester_device_id, _username) =
+        match crate::jwt::validate_and_extract(&request.access_token) {
+            Ok(ids) => ids,
+            Err(_) => {
+                return Ok(Response::new(UpdateGroupKeysResponse {
+                    result: Some(update_group_keys_response::Result::Error(ErrorResponse {
+                        code: crate::proto::common::error_response::ErrorCode::Unauthorized as i32,
+                        message: "Invalid or expired access token".to_string(),
+                        details: HashMap::new(),
+                    })),
+                }));
+            }
+        };
+
+    // Validate group ID
+    if request.group_id.is_empty() {
+        return Ok(Response::new(UpdateGroupKeysResponse {
+            result: Some(update_group_keys_response::Result::Error(ErrorResponse {
+                code: crate::proto::common::error_response::ErrorCode::InvalidRequest as i32,
+                message: "Group ID required".to_string(),
+                details: HashMap::new(),
+            })),
+        }));
+    }
+
+    if request.commit.is_empty() || request.group_info.is_empty() {
+        return Ok(Response::new(UpdateGroupKeysResponse {
+            result: Some(update_group_keys_response::Result::Error(ErrorResponse {
+                code: crate::proto::common::error_response::ErrorCode::InvalidRequest as i32,
+                message: "Self-update commit and GroupInfo required".to_string(),
+                details: HashMap::new(),
+            })),
+        }));
+    }
+
+    if !mls_config.is_enabled_for_group(&request.group_id) {
+        return Ok(Response::new(UpdateGroupKeysResponse {
+            result: Some(update_group_keys_response::Result::Error(ErrorResponse {
+                code: crate::proto::common::error_response::ErrorCode::InvalidRequest as i32,
+                message: "MLS is not enabled for this group".to_string(),
+                details: HashMap::new(),
+            })),
+        }));
+    }
+
+    info!(
+        "Rotating MLS leaf of {}:{} in group {}",
+        requester_user_id, requester_device_id, request.group_id
+    );
+
+    // Initialize MLS manager
+    let mls_manager = MlsManager::new(db.clone());
+
+    // Verify requester is a member
+    match mls_manager
+        .is_member(&request.group_id, &requester_user_id, &requester_device_id)
+        .await
+    {
+        Ok(true) => {
+            // Requester is a member, continue
+        }
+        Ok(false) => {
+            error!(
+                "User {} is not a member of group {}",
+                requester_user_id, request.group_id
+            );
+            return Ok(Response::new(UpdateGroupKeysResponse {
+                result: Some(update_group_keys_response::Result::Error(ErrorResponse {
+                    code: crate::proto::common::error_response::ErrorCode::Unauthorized as i32,
+                    message: "Not a member of this group".to_string(),
+                    details: HashMap::new(),
+                })),
+            }));
+        }
+        Err(e) => {
+            error!("Failed to verify group membership: {}", e);
+            return Ok(Response::new(UpdateGroupKeysResponse {
+                result: Some(update_group_keys_response::Result::Error(ErrorResponse {
+                    code: crate::proto::common::error_response::ErrorCode::InternalError as i32,
+                    message: "Failed to verify membership".to_string(),
+                    details: { let mut map = HashMap::new(); map.insert("error".to_string(), e.to_string()); map },
+                })),
+            }));
+        }
+    }
+
+    // Load group from TiKV at its current epoch
+    let mut group_manager = match mls_manager.load_group(&request.group_id).await {
+        Ok(manager) => manager,
+        Err(e) => {
+            error!("Failed to load group state: {}", e);
+            return Ok(Response::new(UpdateGroupKeysResponse {
+                result: Some(update_group_keys_response::Result::Error(ErrorResponse {
+                    code: crate::proto::common::error_response::ErrorCode::NotFound as i32,
+                    message: "Group state not found".to_string(),
+                    details: { let mut map = HashMap::new(); map.insert("error".to_string(), e.to_string()); map },
+                })),
+            }));
+        }
+    };
+
+    // Devices that receive the commit
+    let members = match mls_manager.member_devices(&request.group_id).await {
+        Ok(members) => members,
+        Err(e) => {
+            error!("Failed to list group members: {}", e);
+            return Ok(Response::new(UpdateGroupKeysResponse {
+                result: Some(update_group_keys_response::Result::Error(ErrorResponse {
+                    code: crate::proto::common::error_response::ErrorCode::InternalError as i32,
+                    message: "Failed to list group members".to_string(),
+                    details: { let mut map = HashMap::new(); map.insert("error".to_string(), e.to_string()); map },
+                })),
+            }));
+        }
+    };
+
+    let commit_epoch = match message_epoch(&request.commit) {
+        Ok(epoch) => epoch,
+        Err(e) => {
+            return Ok(Response::new(UpdateGroupKeysResponse {
+                result: Some(update_group_keys_response::Result::Error(ErrorResponse {
+                    code: crate::proto::common::error_response::ErrorCode::InvalidRequest as i32,
+                    message: "Invalid self-update commit".to_string(),
+                    details: { let mut map = HashMap::new(); map.insert("error".to_string(), e.to_string()); map },
+                })),
+            }));
+        }
+    };
+
+    // A commit created in an older epoch cannot be applied; the device
+    // catches up on the handshake log and creates it again
+    if commit_epoch != group_manager.epoch() {
+        let mut details = HashMap::new();
+        details.insert("retryable".to_string(), "true".to_string());
+        details.insert("current_epoch".to_string(), group_manager.epoch().to_string());
+        return Ok(Response::new(UpdateGroupKeysResponse {
+            result: Some(update_group_keys_response::Result::Error(ErrorResponse {
+                code: crate::proto::common::error_response::ErrorCode::Conflict as i32,
+                message: "Group changed concurrently, retry the request".to_string(),
+                details,
+            })),
+        }));
+    }
+
+    // Apply the commit; it may only rotate the committer's own leaf
+    let updater_identity = match group_manager.process_commit(&request.commit) {
+        Ok(summary) if summary.is_self_update() => summary.committer,
+        Ok(_) => {
+            return Ok(Response::new(UpdateGroupKeysResponse {
+                result: Some(update_group_keys_response::Result::Error(ErrorResponse {
+                    code: crate::proto::common::error_response::ErrorCode::InvalidRequest as i32,
+                    message: "Invalid self-update commit".to_string(),
+                    details: { let mut map = HashMap::new(); map.insert("error".to_string(), "Commit changes the group membership".to_string()); map },
+                })),
+            }));
+        }
+        Err(e) => {
+            error!("Failed to process self-update commit: {}", e);
+            return Ok(Response::new(UpdateGroupKeysResponse {
+                result: Some(update_group_keys_response::Result::Error(ErrorResponse {
+                    code: crate::proto::common::error_response::ErrorCode::InvalidRequest as i32,
+                    message: "Invalid self-update commit".to_string(),
+                    details: { let mut map = HashMap::new(); map.insert("error".to_string(), e.to_string()); map },
+                })),
+            }));
+        }
+    };
+
+    // The rotated leaf must belong to the authenticated device
+    if updater_identity.user_id != requester_user_id || updater_identity.device_id != requester_device_id {
+        error!(
+            "Self-update commit of {}:{} carries credential of {} in group {}",
+            requester_user_id,
+            requester_device_id,
+            updater_identity.label(),
+            request.group_id
+        );
+        return Ok(Response::new(UpdateGroupKeysResponse {
+            result: Some(update_group_keys_response::Result::Error(ErrorResponse {
+                code: crate::proto::common::error_response::ErrorCode::Unauthorized as i32,
+                message: "Self-update commit identity does not match the device".to_string(),
+                details: HashMap::new(),
+            })),
+        }));
+    }
+
+    if let Err(e) = group_manager.publish_group_info(request.group_info) {
+        return Ok(Response::new(UpdateGroupKeysResponse {
+            result: Some(update_group_keys_response::Result::Error(ErrorResponse {
+                code: crate::proto::common::error_response::ErrorCode::InvalidRequest as i32,
+                message: "Invalid GroupInfo".to_string(),
+                details: { let mut map = HashMap::new(); map.insert("error".to_string(), format!("{:#}", e)); map },
+            })),
+        }));
+    }
+
+    let delivery_service = DeliveryService::new(db.clone(), nats.clone());
+    let record = HandshakeRecord {
+        group_id: request.group_id.clone(),
+        epoch: commit_epoch,
+        sender_user_id: requester_user_id.clone(),
+        sender_device_id: requester_device_id.clone(),
+        commit: request.commit,
+        welcome: None,
+        welcome_recipients: Vec::new(),
+        accepted_at: chrono::Utc::now().timestamp(),
+    };
+
+    if let Err(e) = delivery_service.accept_commit(&record, &mut group_manager).await {
+        let stale = e.downcast_ref::<StaleEpochError>();
+        if stale.is_some() || e.is::<GroupStateChanged>() {
+            info!("Rejected commit for MLS group {}: {}", request.group_id, e);
+            let mut details = HashMap::new();
+            details.insert("retryable".to_string(), "true".to_string());
+            if let Some(stale) = stale {
+                details.insert("current_epoch".to_string(), stale.current_epoch.to_string());
+            }
+            return Ok(Response::new(UpdateGroupKeysResponse {
+                result: Some(update_group_keys_response::Result::Error(ErrorResponse {
+                    code: crate::proto::common::error_response::ErrorCode::Conflict as i32,
+                    message: "Group changed concurrently, retry the request".to_string(),
+                    details,
+                })),
+            }));
+        }
+
+        error!("Failed to submit commit to the Delivery Service: {}", e);
+        return Ok(Response::new(UpdateGroupKeysResponse {
+            result: Some(update_group_keys_response::Result::Error(ErrorResponse {
+                code: crate::proto::common::error_response::ErrorCode::InternalError as i32,
+                message: "Failed to submit group commit".to_string(),
+                details: { let mut map = HashMap::new(); map.insert("error".to_string(), e.to_string()); map },
+            })),
+        }));
+    }
+
+    if let Err(e) = mls_manager
+        .record_self_update(&request.group_id, &requester_user_id, &requester_device_id)
+        .await
+    {
+        // Not fatal: the device is only asked to rotate again too early
+        error!("Failed to reset leaf rotation state: {}", e);
+    }
+
+    // Deliver Commit to the other member devices
+    if let Err(e) = delivery_service.fan_out(&record, &members).await {
+        error!("Failed to deliver MLS handshake messages via NATS: {}", e);
+        // Don't fail the whole operation, devices catch up from the handshake log
+    }
+
+    Ok(Response::new(UpdateGroupKeysResponse {
+        result: Some(update_group_keys_response::Result::Success(
+            UpdateGroupKeysSuccess {
+                mls_epoch: group_manager.epoch(),
+            },
+        )),
+    }))
+}
@@ -0,0 +1,85 @@
+/// Identity key change detection
+///
+/// Remembers the last identity key seen for each contact (trust on first use)
+/// and notifies the local user over WebSocket when a key bundle fetched from
+/// auth-service carries a different identity key. A changed key means the
+/// contact reinstalled or registered a new identity, or that someone is
+/// attempting a man-in-the-middle attack, so clients should prompt the user
+/// to re-verify the safety number.
+
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use crate::db::DatabaseClient;
+use crate::nats::NatsClient;
+use crate::websocket::messages::{IdentityKeyChangedPayload, WsMessage};
+use std::sync::Arc;
+
+/// Tracks the identity keys a user has seen for their contacts
+#[derive(Clone)]
+pub struct IdentityKeyTracker {
+    db: Arc<DatabaseClient>,
+    nats: Arc<NatsClient>,
+}
+
+impl IdentityKeyTracker {
+    pub fn new(db: Arc<DatabaseClient>, nats: Arc<NatsClient>) -> Self {
+        Self { db, nats }
+    }
+
+    fn key_path(local_user_id: &str, remote_user_id: &str) -> Vec<u8> {
+        format!("/e2ee/identity_keys/{}/{}", local_user_id, remote_user_id).into_bytes()
+    }
+
+    /// Record the identity key returned for `remote_user_id`
+    ///
+    /// Returns true if it differs from the key previously seen by
+    /// `local_user_id`, in which case an `identity_key_changed` event is
+    /// published to the local user.
+    ///
+    /// The new key is only stored once the event is published. If publishing
+    /// fails, the previous key stays stored and the change is detected (and
+    /// published) again on the next fetch, so the event is never lost.
+    pub async fn observe(
+        &self,
+        local_user_id: &str,
+        remote_user_id: &str,
+        identity_key: &[u8],
+    ) -> Result<bool> {
+        let path = Self::key_path(local_user_id, remote_user_id);
+        let previous = self.db.get(&path).await
+            .context("Failed to load last seen identity key")?;
+
+        if previous.as_deref() == Some(identity_key) {
+            return Ok(false);
+        }
+
+        // First contact: nothing to compare against
+        let Some(previous) = previous else {
+            self.db.put(&path, identity_key.to_vec()).await
+                .context("Failed to store last seen identity key")?;
+            return Ok(false);
+        };
+
+        tracing::warn!(
+            local_user_id = %local_user_id,
+            remote_user_id = %remote_user_id,
+            "Identity key changed for contact"
+        );
+
+        let event = WsMessage::IdentityKeyChanged(IdentityKeyChangedPayload {
+            user_id: remote_user_id.to_string(),
+            previous_identity_key: BASE64.encode(&previous),
+            identity_key: BASE64.encode(identity_key),
+            detected_at: chrono::Utc::now().to_rfc3339(),
+        });
+        let payload = serde_json::to_vec(&event)?;
+        let subject = format!("messages.user.{}", local_user_id);
+        self.nats.publish_raw(&subject, payload.into()).await
+            .context("Failed to publish identity key change")?;
+
+        self.db.put(&path, identity_key.to_vec()).await
+            .context("Failed to store last seen identity key")?;
+
+        Ok(true)
+    }
+}
@@ -0,0 +1,44 @@
+/// Token verification keys published by auth-service
+///
+/// Keeps the process-wide key set that `jwt::validate_token` verifies access
+/// tokens against: the set is reloaded from TiKV when a rotation is announced
+/// over NATS, and periodically in case an announcement was missed. Keys are
+/// only ever read from TiKV, never from NATS messages. auth-service publishes
+/// each key a full rotation period before signing with it, so a reload
+/// interval of a minute never rejects a freshly rotated token.
+use crate::db::DatabaseClient;
+use crate::nats::NatsClient;
+use anyhow::Result;
+use guardyn_common::jwks::{key_set, JwkSet, JWKS_SUBJECT};
+use guardyn_common::listener;
+use std::sync::Arc;
+
+/// Load the key set from TiKV
+pub async fn sync(db: &DatabaseClient) -> Result<usize> {
+    match db.load_jwks().await? {
+        Some(jwks) => Ok(install(&jwks)),
+        None => {
+            tracing::warn!("No token verification keys published yet");
+            Ok(0)
+        }
+    }
+}
+
+fn install(jwks: &JwkSet) -> usize {
+    let accepted = key_set().replace(jwks);
+    tracing::debug!("Installed {} token verification keys", accepted);
+    accepted
+}
+
+/// Reload the key set from TiKV whenever auth-service announces a change
+pub fn spawn_listener(db: Arc<DatabaseClient>, nats: Arc<NatsClient>) {
+    listener::spawn_reload_listener(
+        "token verification keys",
+        Some(nats.client()),
+        JWKS_SUBJECT,
+        move || {
+            let db = db.clone();
+            async move { sync(&db).await }
+        },
+    );
+}
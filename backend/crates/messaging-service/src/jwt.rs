@@ -1,8 +1,10 @@
 /// JWT token validation for messaging service
 ///
-/// Validates JWT tokens issued by auth-service
+/// Validates JWT tokens issued by auth-service against its published signing
+/// keys and rejects revoked sessions
 use anyhow::{Result, bail};
-use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use guardyn_common::jwks::key_set;
+use guardyn_common::revocation::revocations;
 use serde::{Deserialize, Serialize};
 use tonic::Status;
 
@@ -18,12 +20,16 @@ pub struct Claims {
     pub permissions: Vec<String>, // user permissions
     #[serde(skip_serializing_if = "Option::is_none")]
     pub token_type: Option<String>, // "access" or "refresh"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>, // session ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>, // token ID
 }
 
 /// Validate JWT token and extract claims
 ///
 /// Returns Ok(Claims) if valid, Err otherwise
-pub fn validate_token(token: &str, secret: &str) -> Result<Claims> {
+pub fn validate_token(token: &str) -> Result<Claims> {
     // Strip "Bearer " prefix if present
     let token = token.strip_prefix("Bearer ").unwrap_or(token);
 
@@ -31,31 +37,29 @@ pub fn validate_token(token: &str, secret: &str) -> Result<Claims> {
         bail!("Token is empty");
     }
 
-    let validation = Validation::new(Algorithm::HS256);
-    
-    let token_data = decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(secret.as_bytes()),
-        &validation,
-    )?;
+    let claims: Claims = key_set().verify(token)?;
     
     // Check if token is expired
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)?
         .as_secs() as i64;
     
-    if token_data.claims.exp < now {
+    if claims.exp < now {
         bail!("Token expired");
     }
+
+    if revocations().is_token_revoked(claims.sid.as_deref(), claims.jti.as_deref()) {
+        bail!("Token revoked");
+    }
     
-    Ok(token_data.claims)
+    Ok(claims)
 }
 
 /// Validate token and extract user_id + device_id + username
 ///
 /// Returns (user_id, device_id, username) or gRPC Status error
-pub fn validate_and_extract(token: &str, secret: &str) -> Result<(String, String, String), Status> {
-    match validate_token(token, secret) {
+pub fn validate_and_extract(token: &str) -> Result<(String, String, String), Status> {
+    match validate_token(token) {
         Ok(claims) => {
             // Verify it's an access token
             if claims.token_type.as_deref() != Some("access") {
@@ -72,33 +76,39 @@ pub fn validate_and_extract(token: &str, secret: &str) -> Result<(String, String
 }
 
 /// Extract user_id from token (simplified version)
-pub fn extract_user_id(token: &str, secret: &str) -> Result<String, Status> {
-    let (user_id, _, _) = validate_and_extract(token, secret)?;
+pub fn extract_user_id(token: &str) -> Result<String, Status> {
+    let (user_id, _, _) = validate_and_extract(token)?;
     Ok(user_id)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use guardyn_common::jwks::{JwkSet, SigningKey};
 
-    const TEST_SECRET: &str = "test-jwt-secret-key-32-bytes!!";
+    /// Signing key whose public key is installed for verification
+    fn test_key() -> SigningKey {
+        let key = SigningKey::from_seed(&[42u8; 32]);
+        key_set().replace(&JwkSet { keys: vec![key.jwk()] });
+        key
+    }
 
     #[test]
     fn test_validate_empty_token() {
-        let result = validate_token("", TEST_SECRET);
+        let result = validate_token("");
         assert!(result.is_err());
     }
 
     #[test]
     fn test_validate_invalid_token() {
-        let result = validate_token("invalid.token.here", TEST_SECRET);
+        let result = validate_token("invalid.token.here");
         assert!(result.is_err());
     }
 
     #[test]
     fn test_strip_bearer_prefix() {
         let token = "Bearer eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9.invalid";
-        let result = validate_token(token, TEST_SECRET);
+        let result = validate_token(token);
         // Should fail on invalid signature, not on bearer prefix
         assert!(result.is_err());
     }
@@ -107,7 +117,56 @@ mod tests {
     fn test_validate_and_extract_wrong_type() {
         // This would need a valid refresh token to test properly
         // For now, just verify the function signature
-        let result = validate_and_extract("invalid", TEST_SECRET);
+        let result = validate_and_extract("invalid");
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_revoked_session_rejected() {
+        let now = chrono::Utc::now().timestamp();
+        let claims = Claims {
+            sub: "user123".to_string(),
+            device_id: "device456".to_string(),
+            username: "testuser".to_string(),
+            exp: now + 60,
+            iat: now,
+            permissions: vec![],
+            token_type: Some("access".to_string()),
+            sid: Some("revoked-session".to_string()),
+            jti: None,
+        };
+        let token = test_key().sign(&claims).unwrap();
+        assert!(validate_and_extract(&token).is_ok());
+
+        revocations().insert(&guardyn_common::revocation::Revocation {
+            id: "revoked-session".to_string(),
+            user_id: "user123".to_string(),
+            expires_at: now + 60,
+        });
+        assert!(validate_and_extract(&token).is_err());
+    }
+
+    #[test]
+    fn test_shared_secret_token_rejected() {
+        let now = chrono::Utc::now().timestamp();
+        let claims = Claims {
+            sub: "user123".to_string(),
+            device_id: "device456".to_string(),
+            username: "testuser".to_string(),
+            exp: now + 60,
+            iat: now,
+            permissions: vec![],
+            token_type: Some("access".to_string()),
+            sid: None,
+            jti: None,
+        };
+        test_key();
+        let token = jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &claims,
+            &jsonwebtoken::EncodingKey::from_secret(b"dev-jwt-secret-change-in-prod"),
+        )
+        .unwrap();
+        assert!(validate_and_extract(&token).is_err());
+    }
 }
@@ -14,12 +14,16 @@ mod db;
 mod nats;
 mod jwt;
 mod crypto;
+mod identity_keys;
 mod mls_manager;
 mod auth_client;
 mod config;
+mod jwks;
+mod revocation;
 mod websocket;
 
 use guardyn_common::{config::ServiceConfig, observability};
+use guardyn_crypto::x3dh::IdentityKeyPair;
 use tonic::{transport::Server, Request, Response, Status};
 use anyhow::Result;
 use std::sync::Arc;
@@ -40,12 +44,15 @@ pub mod proto {
 use proto::messaging::{
     messaging_service_server::{MessagingService, MessagingServiceServer},
     SendMessageRequest, SendMessageResponse,
+    SendSealedMessageRequest,
+    SetDeliveryTokenRequest, SetDeliveryTokenResponse,
     ReceiveMessagesRequest, Message,
     GetMessagesRequest, GetMessagesResponse,
     GetConversationsRequest, GetConversationsResponse,
     MarkAsReadRequest, MarkAsReadResponse,
     DeleteMessageRequest, DeleteMessageResponse,
     ClearChatRequest, ClearChatResponse,
+    ReportMessageRequest, ReportMessageResponse,
     TypingIndicatorRequest, TypingIndicatorResponse,
     CreateGroupRequest, CreateGroupResponse,
     AddGroupMemberRequest, AddGroupMemberResponse,
@@ -55,6 +62,10 @@ use proto::messaging::{
     GetGroupsRequest, GetGroupsResponse,
     GetGroupByIdRequest, GetGroupByIdResponse,
     LeaveGroupRequest, LeaveGroupResponse,
+    UpdateGroupKeysRequest, UpdateGroupKeysResponse,
+    GetGroupInfoRequest, GetGroupInfoResponse,
+    ExternalJoinGroupRequest, ExternalJoinGroupResponse,
+    GetMlsHandshakesRequest, GetMlsHandshakesResponse,
     HealthRequest,
 };
 use proto::common::HealthStatus;
@@ -62,6 +73,9 @@ use proto::common::HealthStatus;
 pub struct MessagingServiceImpl {
     db: Arc<db::DatabaseClient>,
     nats: Arc<nats::NatsClient>,
+    mls_config: config::MlsConfig,
+    /// Signs franking tags of client-encrypted messages
+    franking_key: Arc<IdentityKeyPair>,
 }
 
 #[tonic::async_trait]
@@ -70,6 +84,13 @@ impl MessagingService for MessagingServiceImpl {
         &self,
         request: Request<SendMessageRequest>,
     ) -> Result<Response<SendMessageResponse>, Status> {
+        let request = request.into_inner();
+
+        // Client-side fan-out: one ciphertext per device, checked against the device lists
+        if !request.device_messages.is_empty() {
+            return handlers::send_message_multi_device(request, self.db.clone(), self.nats.clone(), self.franking_key.clone()).await;
+        }
+
         // TODO: Enable E2EE by default after testing
         // For gradual rollout, check env var ENABLE_E2EE=true
         let enable_e2ee = std::env::var("ENABLE_E2EE")
@@ -78,13 +99,27 @@ impl MessagingService for MessagingServiceImpl {
 
         if enable_e2ee {
             tracing::info!("E2EE enabled, using send_message_e2ee handler");
-            handlers::send_message_e2ee(request.into_inner(), self.db.clone(), self.nats.clone()).await
+            handlers::send_message_e2ee(request, self.db.clone(), self.nats.clone(), self.franking_key.clone()).await
         } else {
             tracing::debug!("E2EE disabled, using legacy send_message handler");
-            handlers::send_message(request.into_inner(), self.db.clone(), self.nats.clone()).await
+            handlers::send_message(request, self.db.clone(), self.nats.clone(), self.franking_key.clone()).await
         }
     }
 
+    async fn send_sealed_message(
+        &self,
+        request: Request<SendSealedMessageRequest>,
+    ) -> Result<Response<SendMessageResponse>, Status> {
+        handlers::send_sealed_message(request.into_inner(), self.db.clone(), self.nats.clone()).await
+    }
+
+    async fn set_delivery_token(
+        &self,
+        request: Request<SetDeliveryTokenRequest>,
+    ) -> Result<Response<SetDeliveryTokenResponse>, Status> {
+        handlers::set_delivery_token(request.into_inner(), self.db.clone()).await
+    }
+
     type ReceiveMessagesStream = tokio_stream::wrappers::ReceiverStream<Result<Message, Status>>;
 
     async fn receive_messages(
@@ -144,7 +179,7 @@ impl MessagingService for MessagingServiceImpl {
         &self,
         request: Request<CreateGroupRequest>,
     ) -> Result<Response<CreateGroupResponse>, Status> {
-        handlers::create_group(request.into_inner(), self.db.clone()).await
+        handlers::create_group(request.into_inner(), self.db.clone(), self.nats.clone(), &self.mls_config).await
     }
 
     async fn add_group_member(
@@ -166,7 +201,7 @@ impl MessagingService for MessagingServiceImpl {
         request: Request<SendGroupMessageRequest>,
     ) -> Result<Response<SendGroupMessageResponse>, Status> {
         tracing::info!("MAIN: Received SendGroupMessageRequest for group_id={}", request.get_ref().group_id);
-        handlers::send_group_message(request.into_inner(), self.db.clone(), self.nats.clone()).await
+        handlers::send_group_message(request.into_inner(), self.db.clone(), self.nats.clone(), self.franking_key.clone()).await
     }
 
     async fn get_group_messages(
@@ -197,6 +232,34 @@ impl MessagingService for MessagingServiceImpl {
         handlers::leave_group(request.into_inner(), self.db.clone()).await
     }
 
+    async fn update_group_keys(
+        &self,
+        request: Request<UpdateGroupKeysRequest>,
+    ) -> Result<Response<UpdateGroupKeysResponse>, Status> {
+        handlers::update_group_keys_mls(request.into_inner(), self.db.clone(), self.nats.clone(), &self.mls_config).await
+    }
+
+    async fn get_group_info(
+        &self,
+        request: Request<GetGroupInfoRequest>,
+    ) -> Result<Response<GetGroupInfoResponse>, Status> {
+        handlers::get_group_info_mls(request.into_inner(), self.db.clone(), &self.mls_config).await
+    }
+
+    async fn external_join_group(
+        &self,
+        request: Request<ExternalJoinGroupRequest>,
+    ) -> Result<Response<ExternalJoinGroupResponse>, Status> {
+        handlers::external_join_group_mls(request.into_inner(), self.db.clone(), self.nats.clone(), &self.mls_config).await
+    }
+
+    async fn get_mls_handshakes(
+        &self,
+        request: Request<GetMlsHandshakesRequest>,
+    ) -> Result<Response<GetMlsHandshakesResponse>, Status> {
+        handlers::get_mls_handshakes(request.into_inner(), self.db.clone(), self.nats.clone(), &self.mls_config).await
+    }
+
     async fn clear_chat(
         &self,
         request: Request<ClearChatRequest>,
@@ -204,6 +267,13 @@ impl MessagingService for MessagingServiceImpl {
         handlers::clear_chat(request.into_inner(), self.db.clone()).await
     }
 
+    async fn report_message(
+        &self,
+        request: Request<ReportMessageRequest>,
+    ) -> Result<Response<ReportMessageResponse>, Status> {
+        handlers::report_message(request.into_inner(), self.db.clone(), self.franking_key.clone()).await
+    }
+
     async fn health(
         &self,
         _request: Request<HealthRequest>,
@@ -287,6 +357,13 @@ async fn main() -> Result<()> {
     let messaging_config = config::MessagingConfig::from_env();
     messaging_config.print_summary();
 
+    if messaging_config.mls.enabled {
+        messaging_config
+            .mls
+            .mls_ciphersuite()
+            .expect("Invalid MLS_CIPHERSUITE");
+    }
+
     // Initialize database connections
     let tikv_endpoints = config.database.tikv_pd_endpoints.clone();
     let scylla_nodes = config.database.scylladb_nodes.clone();
@@ -307,10 +384,59 @@ async fn main() -> Result<()> {
     let db = Arc::new(db);
     let nats = Arc::new(nats);
 
+    // Verify access tokens with the keys published by auth-service
+    if let Err(e) = jwks::sync(&db).await {
+        tracing::warn!("Failed to load token verification keys: {}", e);
+    }
+    jwks::spawn_listener(db.clone(), nats.clone());
+
+    // Reject access tokens of sessions revoked by auth-service
+    revocation::spawn_listener(db.clone(), nats.clone());
+
+    // Load the franking tag signing key (hex-encoded Ed25519 seed). All
+    // replicas must share it, or tags issued by one can't be verified by another
+    let franking_key = match std::env::var("FRANKING_SIGNING_KEY") {
+        Ok(seed) => {
+            let seed = hex::decode(seed.trim())
+                .map_err(|e| anyhow::anyhow!("Invalid FRANKING_SIGNING_KEY: {}", e))?;
+            IdentityKeyPair::from_secret_bytes(&seed)
+                .map_err(|e| anyhow::anyhow!("Invalid FRANKING_SIGNING_KEY: {}", e))?
+        }
+        Err(_) => {
+            let allow_ephemeral = std::env::var("ALLOW_EPHEMERAL_FRANKING_KEY")
+                .unwrap_or_else(|_| "false".to_string())
+                .to_lowercase() == "true";
+            if !allow_ephemeral {
+                return Err(anyhow::anyhow!(
+                    "FRANKING_SIGNING_KEY not set (set ALLOW_EPHEMERAL_FRANKING_KEY=true to use a per-process key for development)"
+                ));
+            }
+
+            tracing::warn!("FRANKING_SIGNING_KEY not set - using an ephemeral key, messages sent before a restart can't be reported");
+            IdentityKeyPair::generate()
+                .map_err(|e| anyhow::anyhow!("Failed to generate franking key: {}", e))?
+        }
+    };
+
+    // Encrypt ratchet state at rest if a key is configured (a malformed key fails startup)
+    if crypto::load_state_key()? {
+        let migrate = std::env::var("E2EE_RATCHET_STATE_MIGRATE")
+            .unwrap_or_else(|_| "false".to_string())
+            .to_lowercase() == "true";
+        if migrate {
+            let migrated = crypto::migrate_sessions(&db).await?;
+            tracing::info!("Encrypted {} stored ratchet sessions; unset E2EE_RATCHET_STATE_MIGRATE", migrated);
+        }
+    } else {
+        tracing::warn!("E2EE_RATCHET_STATE_KEY not set - ratchet state is stored unencrypted");
+    }
+
     // Create gRPC service
     let service = MessagingServiceImpl {
         db: db.clone(),
         nats: nats.clone(),
+        mls_config: messaging_config.mls.clone(),
+        franking_key: Arc::new(franking_key),
     };
 
     // Start WebSocket server if enabled
@@ -324,12 +450,8 @@ async fn main() -> Result<()> {
             .parse()
             .unwrap_or(8081);
 
-        let jwt_secret = std::env::var("JWT_SECRET")
-            .unwrap_or_else(|_| "dev-jwt-secret-change-in-prod".to_string());
-
         let ws_config = websocket::server::WebSocketServerConfig {
             port: ws_port,
-            jwt_secret,
             max_connections_per_user: 5,
             heartbeat_interval: 30,
             connection_timeout: 90,
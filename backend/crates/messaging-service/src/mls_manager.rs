@@ -1,20 +1,49 @@
 /// MLS Group Manager Integration for Messaging Service
 ///
 /// Provides a high-level interface to manage MLS group state,
-/// including serialization, TiKV storage, and integration with
-/// the crypto crate's MlsGroupManager.
+/// including TiKV storage and integration with the crypto crate's
+/// MlsPublicGroup.
+///
+/// The server only keeps the public state of each group (ratchet tree and
+/// group context), never a leaf or a signature key of its own: member devices
+/// create all handshake messages and the server validates them. OpenMLS
+/// storage entries of each group are persisted one TiKV key per entry
+/// under `/mls/groups/<group_id>/storage/<hex(entry_key)>`, so any replica can
+/// reload the group at its current epoch with `MlsManager::load_group`.
+/// The signed GroupInfo of the current epoch, uploaded by the device that
+/// created the epoch, is published next to it under
+/// `/mls/groups/<group_id>/group_info` for devices joining by external commit.
+///
+/// Both live in the transactional keyspace together with a revision counter
+/// under `/mls/groups/<group_id>/revision`. Every save is a compare-and-swap
+/// on the revision the group was loaded at, so a save based on outdated state
+/// fails with `GroupStateChanged` instead of overwriting a newer one. Groups
+/// stored by older versions in the raw keyspace are read from there until
+/// their first save.
+///
+/// Handshake messages go through the `DeliveryService`, which accepts a
+/// single commit per epoch and fans accepted commits and welcomes out to the
+/// member devices in epoch order.
 
-use crate::db::DatabaseClient;
+use crate::db::{prefix_end, CasOutcome, DatabaseClient};
+use crate::nats::NatsClient;
 use anyhow::{Context, Result};
-use guardyn_crypto::mls::{MlsGroupManager, MlsGroupState};
+use guardyn_crypto::mls::{MlsPublicGroup, DEFAULT_MLS_CIPHERSUITE};
+use guardyn_crypto::mls_credential::MlsMemberIdentity;
+use guardyn_crypto::mls_storage::MlsStorage;
+use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
+use std::ops::{Deref, DerefMut};
 use std::sync::Arc;
-use tracing::{error, info};
+use tracing::info;
 
 /// Group state storage paths in TiKV
 const MLS_GROUP_STATE_PREFIX: &str = "/mls/groups";
 const MLS_GROUP_MEMBERS_PREFIX: &str = "/mls/group_members";
 
+/// Delivery Service paths in TiKV (transactional keyspace)
+const MLS_DELIVERY_PREFIX: &str = "/mls/delivery";
+
 /// MLS Manager for Messaging Service
 ///
 /// Manages MLS group state persistence and provides helper methods
@@ -32,6 +61,173 @@ pub struct GroupMetadata {
     pub created_at: i64,
     pub current_epoch: u64,
     pub member_count: usize,
+    /// MLS ciphersuite code point (groups created before it was stored use the default)
+    #[serde(default = "default_ciphersuite")]
+    pub ciphersuite: u16,
+}
+
+fn default_ciphersuite() -> u16 {
+    DEFAULT_MLS_CIPHERSUITE.into()
+}
+
+/// Leaf rotation progress of one member device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeafUpdateState {
+    /// Messages sent since the last self-update
+    pub messages_since_update: u64,
+    /// Unix timestamp of the last self-update (or of the first message tracked)
+    pub last_update_at: i64,
+}
+
+/// Device listed as a member of an MLS group
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemberDevice {
+    pub user_id: String,
+    pub device_id: String,
+}
+
+/// Commit accepted by the Delivery Service, with the Welcome it produced
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HandshakeRecord {
+    pub group_id: String,
+    /// Epoch the commit was created in; accepting it moves the group to `epoch + 1`
+    pub epoch: u64,
+    pub sender_user_id: String,
+    pub sender_device_id: String,
+    pub commit: Vec<u8>,
+    pub welcome: Option<Vec<u8>>,
+    /// Devices joining the group through `welcome`
+    pub welcome_recipients: Vec<MemberDevice>,
+    pub accepted_at: i64,
+}
+
+/// Payload published to a member device for one handshake message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HandshakeDelivery {
+    pub group_id: String,
+    pub epoch: u64,
+    pub sender_user_id: String,
+    pub sender_device_id: String,
+    pub message: Vec<u8>,
+}
+
+/// A commit was created in an epoch the group has already left
+///
+/// Retryable: the sender should apply the handshake messages accepted since
+/// `submitted_epoch` (the `GetMlsHandshakes` RPC) and create
+/// its commit again.
+#[derive(Debug, thiserror::Error)]
+#[error("stale commit for group {group_id}: created in epoch {submitted_epoch}, group is at epoch {current_epoch}")]
+pub struct StaleEpochError {
+    pub group_id: String,
+    pub submitted_epoch: u64,
+    pub current_epoch: u64,
+}
+
+impl StaleEpochError {
+    fn for_record(record: &HandshakeRecord, current_epoch: u64) -> Self {
+        Self {
+            group_id: record.group_id.clone(),
+            submitted_epoch: record.epoch,
+            current_epoch,
+        }
+    }
+}
+
+/// The stored state of a group changed after it was loaded
+///
+/// Retryable: the caller should load the group again and redo its operation.
+#[derive(Debug, thiserror::Error)]
+#[error("state of group {group_id} changed concurrently")]
+pub struct GroupStateChanged {
+    pub group_id: String,
+}
+
+/// MLS group loaded from TiKV, with the revision of its stored state
+///
+/// Dereferences to the `MlsPublicGroup`; saving it through
+/// `MlsManager::save_group` or `DeliveryService::accept_commit` only
+/// succeeds if nobody saved the group in between. A group whose epoch
+/// advanced can only be saved together with the GroupInfo of the new epoch
+/// (see `LoadedGroup::publish_group_info`).
+pub struct LoadedGroup {
+    group: MlsPublicGroup,
+    /// Revision the state was loaded at (0 if it was never saved transactionally)
+    revision: u64,
+    /// Epoch of the stored GroupInfo (`None` for a group that was never stored)
+    stored_epoch: Option<u64>,
+    /// Verified GroupInfo of the current epoch, to be stored on the next save
+    group_info: Option<(u64, Vec<u8>)>,
+    /// Raw-keyspace entries of a group stored by an older version
+    legacy_keys: Vec<Vec<u8>>,
+}
+
+impl LoadedGroup {
+    /// Start tracking a new group from the GroupInfo of its first epoch
+    ///
+    /// # Arguments
+    /// * `group_info` - GroupInfo created by the creator device
+    pub fn new(group_info: &[u8]) -> Result<Self> {
+        let group = MlsPublicGroup::from_group_info(group_info).context("Invalid GroupInfo")?;
+        let epoch = group.epoch();
+
+        Ok(Self {
+            group,
+            revision: 0,
+            stored_epoch: None,
+            group_info: Some((epoch, group_info.to_vec())),
+            legacy_keys: Vec::new(),
+        })
+    }
+
+    /// Set the GroupInfo of the current epoch to publish on the next save
+    ///
+    /// Fails unless the GroupInfo is signed by a member over the group's
+    /// current state.
+    pub fn publish_group_info(&mut self, group_info: Vec<u8>) -> Result<()> {
+        self.group
+            .verify_group_info(&group_info)
+            .context("GroupInfo does not match the group")?;
+        self.group_info = Some((self.group.epoch(), group_info));
+        Ok(())
+    }
+}
+
+impl Deref for LoadedGroup {
+    type Target = MlsPublicGroup;
+
+    fn deref(&self) -> &MlsPublicGroup {
+        &self.group
+    }
+}
+
+impl DerefMut for LoadedGroup {
+    fn deref_mut(&mut self) -> &mut MlsPublicGroup {
+        &mut self.group
+    }
+}
+
+/// Writes and revision check that persist a group's pending changes
+struct GroupStateUpdate {
+    /// Revision key with the value it must still hold
+    expected: (Vec<u8>, Option<Vec<u8>>),
+    writes: Vec<(Vec<u8>, Option<Vec<u8>>)>,
+    revision: u64,
+}
+
+/// TiKV key prefix for OpenMLS storage entries of a group
+fn storage_prefix(group_id: &str) -> String {
+    format!("{}/{}/storage/", MLS_GROUP_STATE_PREFIX, group_id)
+}
+
+/// TiKV key holding the revision of a group's stored state
+fn revision_key(group_id: &str) -> String {
+    format!("{}/{}/revision", MLS_GROUP_STATE_PREFIX, group_id)
+}
+
+/// Encode a revision counter value (0 means the key does not exist)
+fn encode_revision(revision: u64) -> Option<Vec<u8>> {
+    (revision > 0).then(|| revision.to_be_bytes().to_vec())
 }
 
 impl MlsManager {
@@ -40,54 +236,60 @@ impl MlsManager {
         Self { db }
     }
 
-    /// Create a new MLS group
+    /// Store a new MLS group created by a member device
     ///
-    /// # Arguments
-    /// * `group_id` - Unique group identifier
-    /// * `creator_identity` - Creator's identity (user_id:device_id)
-    /// * `credential_bundle_bytes` - Serialized credential bundle
+    /// Saves the group state, the epoch fence and the handshake log entry of
+    /// the initial commit (if any) in one transaction, then writes the group
+    /// metadata and lists the member devices. Fails if the group ID is taken.
     ///
-    /// # Returns
-    /// Serialized group state for initial storage
+    /// # Arguments
+    /// * `group_id` - Unique group identifier (the MLS group ID)
+    /// * `group` - Group from `LoadedGroup::new`, with the initial commit applied
+    /// * `creator` - Verified identity of the creator device
+    /// * `initial_commit` - Record of the commit adding the initial members
     pub async fn create_group(
         &self,
         group_id: &str,
-        creator_user_id: &str,
-        creator_device_id: &str,
-        creator_identity: &[u8],
-    ) -> Result<MlsGroupState> {
+        group: &mut LoadedGroup,
+        creator: &MlsMemberIdentity,
+        initial_commit: Option<&HandshakeRecord>,
+    ) -> Result<()> {
         info!("Creating MLS group: {}", group_id);
 
-        // Create MLS group using crypto crate
-        // Note: In a real implementation, we need to pass the actual credential bundle
-        // For now, we'll generate it on the fly (should be fetched from user's stored credentials)
-        let credential_bundle = guardyn_crypto::mls::create_test_credential(
-            creator_identity
-        )?;
+        // Groups stored by older versions have no revision key yet
+        if self.get_metadata(group_id).await?.is_some() {
+            return Err(anyhow::anyhow!("MLS group already exists: {}", group_id));
+        }
 
-        let group_manager = MlsGroupManager::create_group(
-            group_id,
-            creator_identity,
-            credential_bundle,
-        )?;
+        let mut update = self.state_update(group_id, group)?;
+        let mut expected = vec![update.expected.clone()];
+        if let Some(record) = initial_commit {
+            let fence_key = epoch_fence_key(group_id);
+            let log_key = format!("{}{:020}", handshake_log_prefix(group_id), record.epoch);
+            let log_value = serde_json::to_vec(record).context("Failed to serialize handshake record")?;
 
-        // Serialize group state
-        let group_state = group_manager.serialize_state()?;
+            update.writes.push((fence_key.clone().into_bytes(), Some((record.epoch + 1).to_be_bytes().to_vec())));
+            update.writes.push((log_key.into_bytes(), Some(log_value)));
+            expected.push((fence_key.into_bytes(), None));
+        }
 
-        // Store group state in TiKV
-        let state_key = format!("{}/{}/state", MLS_GROUP_STATE_PREFIX, group_id);
-        let state_json = serde_json::to_vec(&group_state)
-            .context("Failed to serialize group state")?;
-        self.db.put(state_key.as_bytes(), state_json).await?;
+        // Fails if the group ID is taken
+        let result = match self.db.compare_and_swap(&expected, &update.writes).await {
+            Ok(CasOutcome::Swapped) => Ok(()),
+            Ok(CasOutcome::Mismatch(_)) => Err(anyhow::anyhow!("MLS group already exists: {}", group_id)),
+            Err(e) => Err(e),
+        };
+        self.finish_update(group_id, group, update.revision, result).await?;
 
         // Store group metadata
         let metadata = GroupMetadata {
             group_id: group_id.to_string(),
-            creator_user_id: creator_user_id.to_string(),
-            creator_device_id: creator_device_id.to_string(),
+            creator_user_id: creator.user_id.clone(),
+            creator_device_id: creator.device_id.clone(),
             created_at: chrono::Utc::now().timestamp(),
-            current_epoch: 0,
-            member_count: 1,
+            current_epoch: group.epoch(),
+            member_count: 0,
+            ciphersuite: group.ciphersuite().into(),
         };
 
         let metadata_key = format!("{}/{}/metadata", MLS_GROUP_STATE_PREFIX, group_id);
@@ -95,50 +297,194 @@ impl MlsManager {
             .context("Failed to serialize metadata")?;
         self.db.put(metadata_key.as_bytes(), metadata_json).await?;
 
-        // Add creator to members list
-        self.add_member_to_list(group_id, creator_user_id, creator_device_id).await?;
+        // List the creator and the devices added by the initial commit
+        for member in group.members()? {
+            self.add_member_to_list(group_id, &member.user_id, &member.device_id).await?;
+        }
 
         info!("MLS group created: {}", group_id);
-        Ok(group_state)
+        Ok(())
     }
 
-    /// Load MLS group state from TiKV
+    /// Load an MLS group from TiKV
+    ///
+    /// Reads all persisted OpenMLS storage entries of the group and rebuilds
+    /// its public state at the current epoch. Groups stored while the server
+    /// kept a leaf of its own lose the leaf's secrets and signature key on
+    /// their next save.
     ///
     /// # Arguments
     /// * `group_id` - Unique group identifier
     ///
     /// # Returns
-    /// Deserialized MLS group state
-    pub async fn load_group_state(&self, group_id: &str) -> Result<MlsGroupState> {
-        let state_key = format!("{}/{}/state", MLS_GROUP_STATE_PREFIX, group_id);
-        
-        let state_bytes = self.db.get(state_key.as_bytes()).await?
-            .ok_or_else(|| anyhow::anyhow!("Group state not found: {}", group_id))?;
+    /// Group ready for further operations, remembering the revision it was loaded at
+    pub async fn load_group(&self, group_id: &str) -> Result<LoadedGroup> {
+        // Read the revision first: if a save lands in between, the entries
+        // are newer than the revision and the next save fails safely
+        let revision = match self.db.txn_get(revision_key(group_id).as_bytes()).await? {
+            Some(bytes) => <[u8; 8]>::try_from(bytes.as_slice())
+                .map(u64::from_be_bytes)
+                .map_err(|_| anyhow::anyhow!("Malformed MLS group revision"))?,
+            None => 0,
+        };
+
+        let prefix = storage_prefix(group_id);
+        let pairs = if revision > 0 {
+            self.db.txn_scan_prefix(prefix.as_bytes()).await?
+        } else {
+            self.db.scan_prefix(prefix.as_bytes()).await?
+        };
 
-        let group_state: MlsGroupState = serde_json::from_slice(&state_bytes)
-            .context("Failed to deserialize group state")?;
+        if pairs.is_empty() {
+            return Err(anyhow::anyhow!("Group state not found: {}", group_id));
+        }
+
+        let mut entries = HashMap::with_capacity(pairs.len());
+        let mut legacy_keys = Vec::new();
+        for (key, value) in pairs {
+            let entry_key = key
+                .strip_prefix(prefix.as_bytes())
+                .and_then(|suffix| hex::decode(suffix).ok())
+                .ok_or_else(|| anyhow::anyhow!("Malformed MLS storage key in group {}", group_id))?;
+            entries.insert(entry_key, value);
+            if revision == 0 {
+                legacy_keys.push(key);
+            }
+        }
+
+        let group = MlsPublicGroup::load(group_id, MlsStorage::from_entries(entries))
+            .context("Failed to restore MLS group")?;
+
+        // A group from the raw keyspace is written out in full on its first save
+        if revision == 0 {
+            group.storage().mark_unpersisted();
+        }
 
-        Ok(group_state)
+        let stored_epoch = Some(group.epoch());
+        Ok(LoadedGroup {
+            group,
+            revision,
+            stored_epoch,
+            group_info: None,
+            legacy_keys,
+        })
     }
 
     /// Save MLS group state to TiKV
     ///
+    /// Writes OpenMLS storage entries changed since the group was loaded
+    /// (or last saved) and the published GroupInfo in one transaction,
+    /// then updates the epoch in the group metadata. Fails with
+    /// `GroupStateChanged` if the group was saved since it was loaded.
+    ///
     /// # Arguments
     /// * `group_id` - Unique group identifier
-    /// * `group_state` - Serialized group state to save
-    pub async fn save_group_state(&self, group_id: &str, group_state: &MlsGroupState) -> Result<()> {
-        let state_key = format!("{}/{}/state", MLS_GROUP_STATE_PREFIX, group_id);
-        let state_json = serde_json::to_vec(group_state)
-            .context("Failed to serialize group state")?;
-        
-        self.db.put(state_key.as_bytes(), state_json).await?;
+    /// * `group` - Group whose state should be persisted
+    pub async fn save_group(&self, group_id: &str, group: &mut LoadedGroup) -> Result<()> {
+        let update = self.state_update(group_id, group)?;
+
+        let result = self
+            .db
+            .compare_and_swap(std::slice::from_ref(&update.expected), &update.writes)
+            .await;
+        let result = match result {
+            Ok(CasOutcome::Swapped) => Ok(()),
+            Ok(CasOutcome::Mismatch(_)) => Err(GroupStateChanged { group_id: group_id.to_string() }.into()),
+            Err(e) => Err(e),
+        };
+        self.finish_update(group_id, group, update.revision, result).await
+    }
+
+    /// Collect the writes that persist pending changes of a group
+    ///
+    /// Takes the pending OpenMLS storage changes; `finish_update` reports
+    /// them again if they could not be written. Fails if the group moved to
+    /// an epoch whose GroupInfo was not published.
+    fn state_update(&self, group_id: &str, group: &LoadedGroup) -> Result<GroupStateUpdate> {
+        let group_info = match &group.group_info {
+            Some((epoch, group_info)) if *epoch == group.epoch() => Some(group_info.clone()),
+            _ if group.stored_epoch == Some(group.epoch()) => None,
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "GroupInfo of epoch {} of group {} is missing",
+                    group.epoch(),
+                    group_id
+                ))
+            }
+        };
+
+        let changes = group.take_storage_changes();
+        let prefix = storage_prefix(group_id);
+        let revision = group.revision + 1;
+
+        let mut writes: Vec<(Vec<u8>, Option<Vec<u8>>)> = changes
+            .upserts
+            .into_iter()
+            .map(|(key, value)| (format!("{}{}", prefix, hex::encode(key)).into_bytes(), Some(value)))
+            .collect();
+        writes.extend(
+            changes
+                .deletions
+                .into_iter()
+                .map(|key| (format!("{}{}", prefix, hex::encode(key)).into_bytes(), None)),
+        );
+        if let Some(group_info) = group_info {
+            writes.push((group_info_key(group_id).into_bytes(), Some(group_info)));
+        }
+        writes.push((revision_key(group_id).into_bytes(), encode_revision(revision)));
+
+        Ok(GroupStateUpdate {
+            expected: (revision_key(group_id).into_bytes(), encode_revision(group.revision)),
+            writes,
+            revision,
+        })
+    }
+
+    /// Record the outcome of writing a `GroupStateUpdate`
+    async fn finish_update(
+        &self,
+        group_id: &str,
+        group: &mut LoadedGroup,
+        revision: u64,
+        result: Result<()>,
+    ) -> Result<()> {
+        if let Err(e) = result {
+            // Report every entry again on the next save attempt
+            group.storage().mark_unpersisted();
+            return Err(e);
+        }
+        group.revision = revision;
+        group.stored_epoch = Some(group.epoch());
+
+        // Entries of a group stored by an older version now live in the
+        // transactional keyspace
+        for key in std::mem::take(&mut group.legacy_keys) {
+            if let Err(e) = self.db.delete(&key).await {
+                tracing::warn!("Failed to delete legacy MLS storage entry of group {}: {}", group_id, e);
+            }
+        }
 
         // Update epoch in metadata
-        self.update_epoch(group_id, group_state.epoch).await?;
+        self.update_epoch(group_id, group.epoch()).await?;
 
         Ok(())
     }
 
+    /// Get the published GroupInfo of a group
+    ///
+    /// # Arguments
+    /// * `group_id` - Group identifier
+    ///
+    /// # Returns
+    /// Serialized GroupInfo of the current epoch, if the group exists
+    pub async fn get_group_info(&self, group_id: &str) -> Result<Option<Vec<u8>>> {
+        match self.db.txn_get(group_info_key(group_id).as_bytes()).await? {
+            Some(group_info) => Ok(Some(group_info)),
+            // Published by an older version and not saved since
+            None => self.db.get(group_info_key(group_id).as_bytes()).await,
+        }
+    }
+
     /// Update group epoch in metadata
     async fn update_epoch(&self, group_id: &str, epoch: u64) -> Result<()> {
         let metadata_key = format!("{}/{}/metadata", MLS_GROUP_STATE_PREFIX, group_id);
@@ -302,6 +648,308 @@ impl MlsManager {
 
         Ok(self.db.get(member_key.as_bytes()).await?.is_some())
     }
+
+    /// Count a message sent by a member device towards its leaf rotation
+    ///
+    /// The counter lives in the transactional keyspace and is advanced by
+    /// compare-and-swap, so concurrent sends from the same device are all
+    /// counted.
+    ///
+    /// # Arguments
+    /// * `group_id` - Group identifier
+    /// * `user_id` - Sending user
+    /// * `device_id` - Sending device
+    ///
+    /// # Returns
+    /// Rotation progress including this message
+    pub async fn record_leaf_message(
+        &self,
+        group_id: &str,
+        user_id: &str,
+        device_id: &str,
+    ) -> Result<LeafUpdateState> {
+        let state_key = leaf_update_key(group_id, user_id, device_id).into_bytes();
+        let mut current = self.db.txn_get(&state_key).await?;
+
+        loop {
+            let mut state = match &current {
+                Some(state_bytes) => serde_json::from_slice(state_bytes)
+                    .context("Failed to deserialize leaf update state")?,
+                None => LeafUpdateState {
+                    messages_since_update: 0,
+                    last_update_at: chrono::Utc::now().timestamp(),
+                },
+            };
+            state.messages_since_update += 1;
+
+            let state_json = serde_json::to_vec(&state).context("Failed to serialize leaf update state")?;
+            let expected = [(state_key.clone(), current)];
+            match self
+                .db
+                .compare_and_swap(&expected, &[(state_key.clone(), Some(state_json))])
+                .await?
+            {
+                CasOutcome::Swapped => return Ok(state),
+                // Another message or a self-update got there first, count on top of it
+                CasOutcome::Mismatch(values) => current = values.into_iter().next().flatten(),
+            }
+        }
+    }
+
+    /// Reset leaf rotation progress after a self-update commit
+    ///
+    /// # Arguments
+    /// * `group_id` - Group identifier
+    /// * `user_id` - Updating user
+    /// * `device_id` - Updating device
+    pub async fn record_self_update(
+        &self,
+        group_id: &str,
+        user_id: &str,
+        device_id: &str,
+    ) -> Result<()> {
+        let state = LeafUpdateState {
+            messages_since_update: 0,
+            last_update_at: chrono::Utc::now().timestamp(),
+        };
+
+        let state_json = serde_json::to_vec(&state).context("Failed to serialize leaf update state")?;
+        let state_key = leaf_update_key(group_id, user_id, device_id).into_bytes();
+        self.db.compare_and_swap(&[], &[(state_key, Some(state_json))]).await?;
+
+        Ok(())
+    }
+
+    /// List the member devices of a group
+    ///
+    /// # Arguments
+    /// * `group_id` - Group identifier
+    pub async fn member_devices(&self, group_id: &str) -> Result<Vec<MemberDevice>> {
+        let prefix = format!("{}/{}/", MLS_GROUP_MEMBERS_PREFIX, group_id);
+        let pairs = self.db.scan_prefix(prefix.as_bytes()).await?;
+
+        pairs
+            .into_iter()
+            .map(|(_, value)| {
+                serde_json::from_slice(&value).context("Failed to deserialize group member")
+            })
+            .collect()
+    }
+}
+
+/// TiKV key holding the published GroupInfo of a group
+fn group_info_key(group_id: &str) -> String {
+    format!("{}/{}/group_info", MLS_GROUP_STATE_PREFIX, group_id)
+}
+
+/// TiKV key holding the leaf rotation progress of a member device
+fn leaf_update_key(group_id: &str, user_id: &str, device_id: &str) -> String {
+    format!(
+        "{}/{}/leaf_updates/{}:{}",
+        MLS_GROUP_STATE_PREFIX, group_id, user_id, device_id
+    )
+}
+
+/// MLS Delivery Service
+///
+/// Orders handshake messages of each group. The last accepted epoch is kept
+/// as a fence in TiKV and advanced by compare-and-swap, so of several commits
+/// created in the same epoch only the first one is accepted and the group
+/// cannot fork. Accepted commits are logged per group under
+/// `/mls/delivery/<group_id>/handshakes/<epoch>` for devices catching up.
+pub struct DeliveryService {
+    db: Arc<DatabaseClient>,
+    nats: Arc<NatsClient>,
+    mls_manager: MlsManager,
+}
+
+/// TiKV key holding the current epoch of a group
+fn epoch_fence_key(group_id: &str) -> String {
+    format!("{}/{}/epoch", MLS_DELIVERY_PREFIX, group_id)
+}
+
+/// TiKV key prefix of the accepted handshake log of a group
+fn handshake_log_prefix(group_id: &str) -> String {
+    format!("{}/{}/handshakes/", MLS_DELIVERY_PREFIX, group_id)
+}
+
+/// Decode an epoch fence value
+fn decode_epoch(bytes: &[u8]) -> Result<u64> {
+    <[u8; 8]>::try_from(bytes)
+        .map(u64::from_be_bytes)
+        .map_err(|_| anyhow::anyhow!("Malformed MLS epoch fence"))
+}
+
+impl DeliveryService {
+    /// Create a new Delivery Service instance
+    pub fn new(db: Arc<DatabaseClient>, nats: Arc<NatsClient>) -> Self {
+        Self {
+            mls_manager: MlsManager::new(db.clone()),
+            db,
+            nats,
+        }
+    }
+
+    /// Current epoch of a group as seen by the Delivery Service
+    ///
+    /// Groups without an accepted commit yet fall back to the epoch in their
+    /// metadata.
+    pub async fn current_epoch(&self, group_id: &str) -> Result<u64> {
+        match self.db.txn_get(epoch_fence_key(group_id).as_bytes()).await? {
+            Some(bytes) => decode_epoch(&bytes),
+            None => self.metadata_epoch(group_id).await,
+        }
+    }
+
+    /// Epoch recorded in the group metadata
+    async fn metadata_epoch(&self, group_id: &str) -> Result<u64> {
+        self.mls_manager
+            .get_metadata(group_id)
+            .await?
+            .map(|metadata| metadata.current_epoch)
+            .ok_or_else(|| anyhow::anyhow!("Group not found: {}", group_id))
+    }
+
+    /// Accept a commit if it was created in the group's current epoch
+    ///
+    /// Advances the epoch fence, appends the record to the handshake log and
+    /// saves `group` (with the commit merged) in one transaction. Fails with
+    /// `StaleEpochError` when the group has already moved past `record.epoch`,
+    /// including when a concurrent commit for the same epoch won the race, and
+    /// with `GroupStateChanged` when the group was saved since it was loaded.
+    pub async fn accept_commit(&self, record: &HandshakeRecord, group: &mut LoadedGroup) -> Result<()> {
+        let fence_key = epoch_fence_key(&record.group_id);
+        let fence = match self.db.txn_get(fence_key.as_bytes()).await? {
+            Some(bytes) => Some(decode_epoch(&bytes)?),
+            None => None,
+        };
+
+        let current_epoch = match fence {
+            Some(epoch) => epoch,
+            None => self.metadata_epoch(&record.group_id).await?,
+        };
+        if record.epoch != current_epoch {
+            return Err(StaleEpochError::for_record(record, current_epoch).into());
+        }
+
+        let log_key = format!("{}{:020}", handshake_log_prefix(&record.group_id), record.epoch);
+        let log_value = serde_json::to_vec(record).context("Failed to serialize handshake record")?;
+
+        let mut update = self.mls_manager.state_update(&record.group_id, group)?;
+        update.writes.push((fence_key.clone().into_bytes(), Some((record.epoch + 1).to_be_bytes().to_vec())));
+        update.writes.push((log_key.into_bytes(), Some(log_value)));
+        let expected = [
+            (fence_key.into_bytes(), fence.map(|epoch| epoch.to_be_bytes().to_vec())),
+            update.expected.clone(),
+        ];
+
+        let result = match self.db.compare_and_swap(&expected, &update.writes).await {
+            Ok(CasOutcome::Swapped) => Ok(()),
+            Ok(CasOutcome::Mismatch(current)) => match current.first() {
+                Some(current_fence) if *current_fence != expected[0].1 => {
+                    match current_fence.as_deref().map(decode_epoch).transpose() {
+                        Ok(fence) => Err(StaleEpochError::for_record(record, fence.unwrap_or(current_epoch)).into()),
+                        Err(e) => Err(e),
+                    }
+                }
+                _ => Err(GroupStateChanged { group_id: record.group_id.clone() }.into()),
+            },
+            Err(e) => Err(e),
+        };
+        self.mls_manager
+            .finish_update(&record.group_id, group, update.revision, result)
+            .await?;
+
+        info!(
+            "Accepted commit for MLS group {} in epoch {}",
+            record.group_id, record.epoch
+        );
+        Ok(())
+    }
+
+    /// Deliver an accepted commit and its welcome
+    ///
+    /// The commit goes to every member device except the sender, then the
+    /// welcome goes to the joining devices, so nobody sees a member that the
+    /// rest of the group has not added yet. Each payload carries its epoch,
+    /// letting devices detect gaps and fill them with the `GetMlsHandshakes`
+    /// RPC. The bare commit is also published on the group's
+    /// `messaging.mls.commit.<group_id>` subject for existing subscribers.
+    ///
+    /// # Arguments
+    /// * `record` - Accepted handshake record
+    /// * `members` - Member devices of the group in the epoch the commit was created in
+    pub async fn fan_out(&self, record: &HandshakeRecord, members: &[MemberDevice]) -> Result<()> {
+        let commit = serde_json::to_vec(&HandshakeDelivery {
+            group_id: record.group_id.clone(),
+            epoch: record.epoch,
+            sender_user_id: record.sender_user_id.clone(),
+            sender_device_id: record.sender_device_id.clone(),
+            message: record.commit.clone(),
+        })?;
+
+        for member in members {
+            if member.user_id == record.sender_user_id && member.device_id == record.sender_device_id {
+                continue;
+            }
+
+            let subject = format!(
+                "messaging.mls.commit.{}.{}.{}",
+                record.group_id, member.user_id, member.device_id
+            );
+            self.nats.publish(&subject, &commit).await?;
+        }
+
+        let group_subject = format!("messaging.mls.commit.{}", record.group_id);
+        self.nats.publish(&group_subject, &record.commit).await?;
+
+        if let Some(welcome) = &record.welcome {
+            let welcome = serde_json::to_vec(&HandshakeDelivery {
+                group_id: record.group_id.clone(),
+                epoch: record.epoch,
+                sender_user_id: record.sender_user_id.clone(),
+                sender_device_id: record.sender_device_id.clone(),
+                message: welcome.clone(),
+            })?;
+
+            for recipient in &record.welcome_recipients {
+                let subject = format!(
+                    "messaging.mls.welcome.{}.{}",
+                    recipient.user_id, recipient.device_id
+                );
+                self.nats.publish(&subject, &welcome).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Accepted handshake records of a group, starting at `from_epoch`
+    ///
+    /// # Arguments
+    /// * `group_id` - Group identifier
+    /// * `from_epoch` - First epoch to return
+    /// * `limit` - Maximum number of records
+    pub async fn handshakes_since(
+        &self,
+        group_id: &str,
+        from_epoch: u64,
+        limit: u32,
+    ) -> Result<Vec<HandshakeRecord>> {
+        let prefix = handshake_log_prefix(group_id);
+        let start = format!("{}{:020}", prefix, from_epoch);
+        let pairs = self
+            .db
+            .txn_scan(start.as_bytes(), &prefix_end(prefix.as_bytes()), limit)
+            .await?;
+
+        pairs
+            .into_iter()
+            .map(|(_, value)| {
+                serde_json::from_slice(&value).context("Failed to deserialize handshake record")
+            })
+            .collect()
+    }
 }
 
 #[cfg(test)]
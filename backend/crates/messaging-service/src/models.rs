@@ -1,5 +1,7 @@
 /// Data models for Messaging Service
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use zeroize::Zeroize;
 
 /// Message stored in ScyllaDB
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,6 +21,12 @@ pub struct StoredMessage {
     /// X3DH prekey data for first message in session (Base64 encoded)
     /// Required for recipient to create responder session when receiving first message
     pub x3dh_prekey: Option<String>,
+    /// Per-device copy of a multi-device send, visible only to `recipient_device_id`
+    #[serde(default)]
+    pub device_copy: bool,
+    /// Server-signed franking tag for franked messages
+    #[serde(default)]
+    pub franking_tag: Option<Vec<u8>>,
 }
 
 /// Delivery state tracked in TiKV
@@ -34,6 +42,16 @@ pub struct DeliveryState {
     pub updated_at: i64,
 }
 
+impl DeliveryState {
+    /// Whether this message should be delivered to `device_id`
+    pub fn is_for_device(&self, device_id: &str) -> bool {
+        match self.recipient_device_id.as_deref() {
+            Some(target) => target == device_id,
+            None => true,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum DeliveryStatus {
     Pending,
@@ -125,13 +143,36 @@ pub struct GroupMessage {
     pub metadata: std::collections::HashMap<String, String>, // Additional metadata
 }
 
+/// Verified abuse report stored in TiKV
+///
+/// Only stored after the revealed plaintext matched the franking commitment
+/// in the server-signed tag, so sender and content are attested.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AbuseReport {
+    pub report_id: String,
+    pub message_id: String,
+    pub reporter_user_id: String,
+    pub reporter_device_id: String,
+    pub sender_user_id: String,
+    pub sender_device_id: String,
+    /// Group ID for group messages, None for 1-on-1 messages
+    pub group_id: Option<String>,
+    pub plaintext: Vec<u8>,
+    pub reason: String,
+    pub message_timestamp: i64,
+    pub reported_at: i64,
+}
+
 // ============================================================================
 // E2EE Double Ratchet Session State
 // ============================================================================
 
 /// Double Ratchet session state stored in TiKV
 /// Each conversation has a unique session per device pair
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// The serialized state is wiped from memory on drop and left out of `Debug`
+/// output, since unencrypted state contains the session's keys.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct RatchetSession {
     /// Session identifier: "{user1_id}:{device1_id}:{user2_id}:{device2_id}"
     pub session_id: String,
@@ -158,6 +199,27 @@ pub struct RatchetSession {
     pub is_initiator: bool,
 }
 
+impl fmt::Debug for RatchetSession {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RatchetSession")
+            .field("session_id", &self.session_id)
+            .field("local_user_id", &self.local_user_id)
+            .field("local_device_id", &self.local_device_id)
+            .field("remote_user_id", &self.remote_user_id)
+            .field("remote_device_id", &self.remote_device_id)
+            .field("updated_at", &self.updated_at)
+            .field("created_at", &self.created_at)
+            .field("is_initiator", &self.is_initiator)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for RatchetSession {
+    fn drop(&mut self) {
+        self.ratchet_state.zeroize();
+    }
+}
+
 impl RatchetSession {
     /// Generate session ID from device pair
     pub fn session_id(
@@ -12,11 +12,20 @@ pub struct MessageEnvelope {
     pub sender_user_id: String,
     pub sender_device_id: String,
     pub recipient_user_id: String,
+    /// Target device; `None` delivers to every device of the recipient
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recipient_device_id: Option<String>,
     pub encrypted_content: Vec<u8>,
     pub timestamp: i64,
     /// X3DH prekey data for first message in session (Base64 encoded)
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub x3dh_prekey: Option<String>,
+    /// Sealed sender: sender fields are empty and `encrypted_content` is the sealed envelope
+    #[serde(default)]
+    pub sealed: bool,
+    /// Server-signed franking tag for franked messages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub franking_tag: Option<Vec<u8>>,
 }
 
 /// NATS client for message routing
@@ -26,6 +35,16 @@ pub struct NatsClient {
     messages_stream: Stream,
 }
 
+impl MessageEnvelope {
+    /// Whether this envelope should be delivered to `device_id`
+    pub fn is_for_device(&self, device_id: &str) -> bool {
+        match self.recipient_device_id.as_deref() {
+            Some(target) => target == device_id,
+            None => true,
+        }
+    }
+}
+
 impl NatsClient {
     /// Connect to NATS and initialize JetStream
     pub async fn new(nats_url: &str) -> Result<Self> {
@@ -98,9 +117,12 @@ impl NatsClient {
         Ok(())
     }
 
-    /// Subscribe to messages for a specific user
-    pub async fn subscribe_to_messages(&self, user_id: &str) -> Result<PullConsumer> {
-        let consumer_name = format!("user-{}", user_id);
+    /// Subscribe to messages for a specific user device
+    ///
+    /// Each device has its own durable consumer so every device sees every
+    /// message; envelopes addressed to other devices are filtered by the caller.
+    pub async fn subscribe_to_messages(&self, user_id: &str, device_id: &str) -> Result<PullConsumer> {
+        let consumer_name = format!("user-{}-{}", user_id, device_id);
         let subject_filter = format!("messages.{}.*", user_id);
 
         let consumer = self
@@ -116,7 +138,7 @@ impl NatsClient {
             .await
             .context("Failed to create consumer")?;
 
-        tracing::info!("Created consumer {} for user {}", consumer_name, user_id);
+        tracing::info!("Created consumer {} for user {} ({})", consumer_name, user_id, device_id);
 
         Ok(consumer)
     }
@@ -195,6 +217,11 @@ impl NatsClient {
         self.client.connection_state()
     }
 
+    /// Underlying client, for plain (non-JetStream) subscriptions to auth-service broadcasts
+    pub fn client(&self) -> async_nats::Client {
+        self.client.clone()
+    }
+
     /// Publish raw bytes to a subject (for WebSocket handlers)
     pub async fn publish_raw(&self, subject: &str, payload: bytes::Bytes) -> Result<()> {
         self.context
@@ -194,6 +194,30 @@ impl ConnectionManager {
         }
     }
 
+    /// Send a message to the connections of one device of a user
+    ///
+    /// Connections that did not report a device ID also receive the message;
+    /// they can tell it apart by the payload's `recipient_device_id`.
+    pub async fn send_to_device(&self, user_id: &str, device_id: &str, message: WsMessage) {
+        if let Some(conn_ids) = self.user_connections.get(user_id) {
+            for conn_id in conn_ids.iter() {
+                if let Some(conn) = self.connections.get(conn_id) {
+                    if conn.device_id.as_deref().is_some_and(|id| id != device_id) {
+                        continue;
+                    }
+                    if let Err(e) = conn.sender.send(message.clone()).await {
+                        warn!(
+                            user_id = %user_id,
+                            connection_id = %conn_id,
+                            error = %e,
+                            "Failed to send message to connection"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
     /// Send a message to multiple users
     pub async fn send_to_users(&self, user_ids: &[String], message: WsMessage) {
         for user_id in user_ids {
@@ -345,6 +369,36 @@ mod tests {
         assert_eq!(conn_ids.len(), 2);
     }
 
+    #[tokio::test]
+    async fn test_send_to_device() {
+        use crate::websocket::messages::PingPayload;
+
+        let manager = ConnectionManager::new(5);
+        let (tx_a, mut rx_a) = mpsc::channel(32);
+        let (tx_b, mut rx_b) = mpsc::channel(32);
+        let (tx_unknown, mut rx_unknown) = mpsc::channel(32);
+
+        manager.register_connection("conn-a".to_string(), tx_a);
+        manager.register_connection("conn-b".to_string(), tx_b);
+        manager.register_connection("conn-unknown".to_string(), tx_unknown);
+        manager
+            .authenticate_connection("conn-a", "user-1".to_string(), Some("device-a".to_string()))
+            .unwrap();
+        manager
+            .authenticate_connection("conn-b", "user-1".to_string(), Some("device-b".to_string()))
+            .unwrap();
+        manager
+            .authenticate_connection("conn-unknown", "user-1".to_string(), None)
+            .unwrap();
+
+        let message = WsMessage::pong_from_ping(&PingPayload { timestamp: 0 });
+        manager.send_to_device("user-1", "device-a", message).await;
+
+        assert!(rx_a.try_recv().is_ok());
+        assert!(rx_b.try_recv().is_err());
+        assert!(rx_unknown.try_recv().is_ok());
+    }
+
     #[tokio::test]
     async fn test_subscriptions() {
         let manager = ConnectionManager::new(5);
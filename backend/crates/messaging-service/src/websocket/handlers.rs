@@ -29,7 +29,6 @@ pub struct WsContext {
     pub connection_manager: Arc<ConnectionManager>,
     pub db: Arc<DatabaseClient>,
     pub nats: Arc<NatsClient>,
-    pub jwt_secret: String,
 }
 
 impl WsContext {
@@ -38,14 +37,12 @@ impl WsContext {
         connection_manager: Arc<ConnectionManager>,
         db: Arc<DatabaseClient>,
         nats: Arc<NatsClient>,
-        jwt_secret: String,
     ) -> Self {
         Self {
             connection_id,
             connection_manager,
             db,
             nats,
-            jwt_secret,
         }
     }
 }
@@ -69,7 +66,8 @@ pub async fn handle_message(ctx: &WsContext, message: WsMessage) -> Option<WsMes
         | WsMessage::Presence(_)
         | WsMessage::ReadReceipt(_)
         | WsMessage::Pong(_)
-        | WsMessage::Error(_) => {
+        | WsMessage::Error(_)
+        | WsMessage::IdentityKeyChanged(_) => {
             debug!(
                 connection_id = %ctx.connection_id,
                 "Ignoring server-to-client message type"
@@ -82,7 +80,7 @@ pub async fn handle_message(ctx: &WsContext, message: WsMessage) -> Option<WsMes
 /// Handle authentication message
 async fn handle_auth(ctx: &WsContext, auth: AuthMessage) -> WsMessage {
     // Validate JWT token
-    match jwt::validate_token(&auth.token, &ctx.jwt_secret) {
+    match jwt::validate_token(&auth.token) {
         Ok(claims) => {
             let user_id = claims.sub;
 
@@ -170,12 +168,15 @@ async fn handle_send_message(ctx: &WsContext, send: SendMessagePayload) -> Optio
         sender_id: sender_id.clone(),
         sender_device_id: String::new(), // TODO: Get from authentication context
         recipient_id: send.recipient_id.clone(),
+        recipient_device_id: None,
         content: send.content.clone(),
         encrypted: send.encrypted,
         content_type: send.content_type.clone(),
         timestamp: timestamp_str.clone(),
         client_message_id: send.client_message_id.clone(),
         x3dh_prekey: None, // WebSocket messages don't include X3DH prekey directly
+        sealed: false,
+        franking_tag: None,
     };
 
     // Store in ScyllaDB via the database client
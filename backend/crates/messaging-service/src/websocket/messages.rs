@@ -64,6 +64,10 @@ pub enum WsMessage {
     /// Unsubscribe from conversation/user presence (client → server)
     #[serde(rename = "unsubscribe")]
     Unsubscribe(UnsubscribePayload),
+
+    /// Contact's identity key changed since it was last seen (server → client)
+    #[serde(rename = "identity_key_changed")]
+    IdentityKeyChanged(IdentityKeyChangedPayload),
 }
 
 /// Authentication message
@@ -125,6 +129,9 @@ pub struct MessagePayload {
     pub sender_device_id: String,
     /// Recipient user ID
     pub recipient_id: String,
+    /// Recipient device ID for per-device ciphertexts (absent if meant for all devices)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recipient_device_id: Option<String>,
     /// Message content
     pub content: String,
     /// Whether content is encrypted
@@ -139,6 +146,13 @@ pub struct MessagePayload {
     /// X3DH prekey data for first message in session (Base64 encoded)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub x3dh_prekey: Option<String>,
+    /// Sealed sender: sender fields are empty and content is the Base64-encoded
+    /// sealed envelope
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub sealed: bool,
+    /// Server-signed franking tag (Base64 encoded) for franked messages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub franking_tag: Option<String>,
 }
 
 /// Message sent confirmation
@@ -244,6 +258,19 @@ pub struct UnsubscribePayload {
     pub target_ids: Vec<String>,
 }
 
+/// Identity key change notification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdentityKeyChangedPayload {
+    /// Contact whose identity key changed
+    pub user_id: String,
+    /// Previously seen identity key (base64)
+    pub previous_identity_key: String,
+    /// New identity key (base64)
+    pub identity_key: String,
+    /// When the change was detected (ISO 8601)
+    pub detected_at: String,
+}
+
 impl WsMessage {
     /// Create an error response
     pub fn error(code: impl Into<String>, message: impl Into<String>) -> Self {
@@ -316,4 +343,25 @@ mod tests {
         assert!(json.contains("AUTH_FAILED"));
         assert!(json.contains("Invalid token"));
     }
+
+    #[test]
+    fn test_serialize_identity_key_changed() {
+        let msg = WsMessage::IdentityKeyChanged(IdentityKeyChangedPayload {
+            user_id: "user-456".to_string(),
+            previous_identity_key: "b2xk".to_string(),
+            identity_key: "bmV3".to_string(),
+            detected_at: "2024-01-01T00:00:00+00:00".to_string(),
+        });
+
+        let json = serde_json::to_string(&msg).unwrap();
+        assert!(json.contains(r#""type":"identity_key_changed""#));
+
+        match serde_json::from_str::<WsMessage>(&json).unwrap() {
+            WsMessage::IdentityKeyChanged(payload) => {
+                assert_eq!(payload.user_id, "user-456");
+                assert_eq!(payload.identity_key, "bmV3");
+            }
+            _ => panic!("Expected IdentityKeyChanged"),
+        }
+    }
 }
@@ -13,6 +13,7 @@ use axum::{
     routing::get,
     Router,
 };
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
 use futures::{sink::SinkExt, stream::StreamExt};
 use std::sync::Arc;
 use tokio::sync::mpsc;
@@ -34,15 +35,12 @@ pub struct WsState {
     pub connection_manager: Arc<ConnectionManager>,
     pub db: Arc<DatabaseClient>,
     pub nats: Arc<NatsClient>,
-    pub jwt_secret: String,
 }
 
 /// WebSocket server configuration
 pub struct WebSocketServerConfig {
     /// Port to listen on
     pub port: u16,
-    /// JWT secret for authentication
-    pub jwt_secret: String,
     /// Maximum connections per user
     pub max_connections_per_user: usize,
     /// Heartbeat interval in seconds
@@ -55,7 +53,6 @@ impl Default for WebSocketServerConfig {
     fn default() -> Self {
         Self {
             port: 8081,
-            jwt_secret: "dev-jwt-secret-change-in-prod".to_string(),
             max_connections_per_user: 5,
             heartbeat_interval: 30,
             connection_timeout: 90,
@@ -82,7 +79,6 @@ impl WebSocketServer {
             connection_manager,
             db,
             nats,
-            jwt_secret: config.jwt_secret.clone(),
         };
 
         Self { config, state }
@@ -178,7 +174,6 @@ async fn handle_socket(socket: WebSocket, state: WsState) {
         state.connection_manager.clone(),
         state.db.clone(),
         state.nats.clone(),
-        state.jwt_secret.clone(),
     );
 
     // Spawn task to forward messages from channel to WebSocket
@@ -335,18 +330,29 @@ async fn start_nats_message_relay(state: WsState) -> Result<(), Box<dyn std::err
                     // Content is already base64 encoded by the client
                     // (Flutter sends base64-encoded encrypted bytes as ASCII codeUnits)
                     // Just convert bytes back to String, don't re-encode!
-                    let content = String::from_utf8_lossy(&envelope.encrypted_content).to_string();
+                    // Sealed envelopes are raw bytes from gRPC and are encoded here.
+                    let content = if envelope.sealed {
+                        BASE64.encode(&envelope.encrypted_content)
+                    } else {
+                        String::from_utf8_lossy(&envelope.encrypted_content).to_string()
+                    };
 
                     // Generate deterministic conversation ID for 1-on-1 chat
-                    let conversation_id = generate_conversation_id(&envelope.sender_user_id, recipient_id);
+                    // (unknown for sealed messages until the recipient opens them)
+                    let conversation_id = if envelope.sealed {
+                        None
+                    } else {
+                        Some(generate_conversation_id(&envelope.sender_user_id, recipient_id))
+                    };
 
                     // Create WebSocket message from envelope
                     let ws_message = WsMessage::Message(super::messages::MessagePayload {
                         message_id: envelope.message_id.clone(),
-                        conversation_id: Some(conversation_id),
+                        conversation_id,
                         sender_id: envelope.sender_user_id.clone(),
                         sender_device_id: envelope.sender_device_id.clone(),
                         recipient_id: recipient_id.clone(),
+                        recipient_device_id: envelope.recipient_device_id.clone(),
                         content,
                         encrypted: true,
                         content_type: "text".to_string(),
@@ -355,6 +361,8 @@ async fn start_nats_message_relay(state: WsState) -> Result<(), Box<dyn std::err
                             .unwrap_or_else(|| chrono::Utc::now().to_rfc3339()),
                         client_message_id: None,
                         x3dh_prekey: envelope.x3dh_prekey.clone(),
+                        sealed: envelope.sealed,
+                        franking_tag: envelope.franking_tag.as_ref().map(|tag| BASE64.encode(tag)),
                     });
 
                     // Send to recipient's WebSocket connections
@@ -365,7 +373,13 @@ async fn start_nats_message_relay(state: WsState) -> Result<(), Box<dyn std::err
                         "Sending message to WebSocket connections"
                     );
                     
-                    state.connection_manager.send_to_user(recipient_id, ws_message).await;
+                    // Per-device ciphertexts only go to the device they were encrypted for
+                    match envelope.recipient_device_id.as_deref() {
+                        Some(device_id) => {
+                            state.connection_manager.send_to_device(recipient_id, device_id, ws_message).await
+                        }
+                        None => state.connection_manager.send_to_user(recipient_id, ws_message).await,
+                    }
                     
                     // Acknowledge the message
                     if let Err(e) = msg.ack().await {
@@ -414,7 +428,6 @@ impl Clone for WsContext {
             connection_manager: self.connection_manager.clone(),
             db: self.db.clone(),
             nats: self.nats.clone(),
-            jwt_secret: self.jwt_secret.clone(),
         }
     }
 }
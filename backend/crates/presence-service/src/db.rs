@@ -6,6 +6,8 @@
 /// - Typing indicators (ephemeral, stored in memory/NATS)
 
 use anyhow::{Context, Result};
+use guardyn_common::jwks::{JwkSet, JWKS_KEY};
+use guardyn_common::revocation::{Revocation, REVOCATION_PREFIX};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tikv_client::RawClient;
@@ -159,6 +161,31 @@ impl DatabaseClient {
         Ok(Some(indicator))
     }
 
+    /// Load active session revocations (written by auth-service)
+    pub async fn load_revocations(&self) -> Result<Vec<Revocation>> {
+        let start_key = REVOCATION_PREFIX.as_bytes().to_vec();
+        let mut end_key = start_key.clone();
+        if let Some(last) = end_key.last_mut() {
+            *last += 1;
+        }
+
+        let pairs = self.client.scan(start_key..end_key, 10_000).await?;
+
+        Ok(pairs
+            .into_iter()
+            .filter_map(|kv| serde_json::from_slice::<Revocation>(kv.value()).ok())
+            .filter(|revocation| !revocation.is_expired())
+            .collect())
+    }
+
+    /// Load the token verification keys (written by auth-service)
+    pub async fn load_jwks(&self) -> Result<Option<JwkSet>> {
+        match self.client.get(JWKS_KEY.as_bytes().to_vec()).await? {
+            Some(data) => Ok(Some(serde_json::from_slice(&data)?)),
+            None => Ok(None),
+        }
+    }
+
     /// Health check - verify TiKV connection
     pub async fn health_check(&self) -> Result<()> {
         // Try to get a known key to verify connectivity
@@ -41,6 +41,39 @@ pub struct KeyBundle {
     pub one_time_pre_keys: ::prost::alloc::vec::Vec<::prost::alloc::vec::Vec<u8>>,
     #[prost(message, optional, tag = "5")]
     pub created_at: ::core::option::Option<Timestamp>,
+    /// Key ID of signed_pre_key (referenced by the initiator's key exchange)
+    #[prost(uint32, tag = "6")]
+    pub signed_pre_key_id: u32,
+    /// Key IDs, one per one-time pre-key, chosen by the device
+    #[prost(uint32, repeated, tag = "7")]
+    pub one_time_pre_key_ids: ::prost::alloc::vec::Vec<u32>,
+    /// X25519 public key used once one-time pre-keys run out (never consumed)
+    #[prost(bytes = "vec", tag = "8")]
+    pub last_resort_pre_key: ::prost::alloc::vec::Vec<u8>,
+    /// Ed25519 signature over last_resort_pre_key
+    #[prost(bytes = "vec", tag = "9")]
+    pub last_resort_pre_key_signature: ::prost::alloc::vec::Vec<u8>,
+    #[prost(uint32, tag = "10")]
+    pub last_resort_pre_key_id: u32,
+    /// Last-resort ML-KEM-768 pre-key (never consumed); set for PQXDH devices
+    #[prost(message, optional, tag = "11")]
+    pub kem_pre_key: ::core::option::Option<KemPreKey>,
+    /// One-time ML-KEM-768 pre-keys (consumed like one_time_pre_keys)
+    #[prost(message, repeated, tag = "12")]
+    pub one_time_kem_pre_keys: ::prost::alloc::vec::Vec<KemPreKey>,
+}
+/// ML-KEM-768 pre-key for post-quantum key agreement (PQXDH)
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct KemPreKey {
+    /// Chosen by the device
+    #[prost(uint32, tag = "1")]
+    pub key_id: u32,
+    /// ML-KEM-768 encapsulation key (1184 bytes)
+    #[prost(bytes = "vec", tag = "2")]
+    pub public_key: ::prost::alloc::vec::Vec<u8>,
+    /// Ed25519 signature over public_key by the identity key
+    #[prost(bytes = "vec", tag = "3")]
+    pub signature: ::prost::alloc::vec::Vec<u8>,
 }
 /// Generic error response
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -17,10 +17,9 @@ const MAX_BULK_USERS: usize = 100;
 pub async fn handle_get_bulk_status(
     request: GetBulkStatusRequest,
     db: Arc<DatabaseClient>,
-    jwt_secret: &str,
 ) -> Result<Response<GetBulkStatusResponse>, Status> {
     // Validate JWT token
-    if let Err(e) = jwt::validate_token(&request.access_token, jwt_secret) {
+    if let Err(e) = jwt::validate_token(&request.access_token) {
         tracing::warn!("Token validation failed: {}", e);
         return Ok(Response::new(GetBulkStatusResponse {
             result: Some(GetBulkStatusResult::Error(ErrorResponse {
@@ -17,10 +17,9 @@ pub async fn handle_set_typing(
     request: SetTypingRequest,
     db: Arc<DatabaseClient>,
     nats: Arc<NatsClient>,
-    jwt_secret: &str,
 ) -> Result<Response<SetTypingResponse>, Status> {
     // Validate JWT token
-    let claims = match jwt::validate_token(&request.access_token, jwt_secret) {
+    let claims = match jwt::validate_token(&request.access_token) {
         Ok(c) => c,
         Err(e) => {
             tracing::warn!("Token validation failed: {}", e);
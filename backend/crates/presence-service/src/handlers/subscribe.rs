@@ -16,10 +16,9 @@ const MAX_SUBSCRIBED_USERS: usize = 100;
 pub async fn handle_subscribe(
     request: SubscribeRequest,
     db: Arc<DatabaseClient>,
-    jwt_secret: &str,
 ) -> Result<Response<ReceiverStream<Result<PresenceUpdate, Status>>>, Status> {
     // Validate JWT token
-    let claims = match jwt::validate_token(&request.access_token, jwt_secret) {
+    let claims = match jwt::validate_token(&request.access_token) {
         Ok(c) => c,
         Err(e) => {
             tracing::warn!("Token validation failed: {}", e);
@@ -15,10 +15,9 @@ use tonic::{Response, Status};
 pub async fn handle_update_last_seen(
     request: UpdateLastSeenRequest,
     db: Arc<DatabaseClient>,
-    jwt_secret: &str,
 ) -> Result<Response<UpdateLastSeenResponse>, Status> {
     // Validate JWT token
-    let claims = match jwt::validate_token(&request.access_token, jwt_secret) {
+    let claims = match jwt::validate_token(&request.access_token) {
         Ok(c) => c,
         Err(e) => {
             tracing::warn!("Token validation failed: {}", e);
@@ -17,10 +17,9 @@ pub async fn handle_update_status(
     request: UpdateStatusRequest,
     db: Arc<DatabaseClient>,
     nats: Arc<NatsClient>,
-    jwt_secret: &str,
 ) -> Result<Response<UpdateStatusResponse>, Status> {
     // Validate JWT token
-    let claims = match jwt::validate_token(&request.access_token, jwt_secret) {
+    let claims = match jwt::validate_token(&request.access_token) {
         Ok(c) => c,
         Err(e) => {
             tracing::warn!("Token validation failed: {}", e);
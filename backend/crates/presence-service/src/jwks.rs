@@ -0,0 +1,36 @@
+/// Token verification keys published by auth-service
+///
+/// Keeps the process-wide key set that `jwt::validate_token` verifies against
+/// current: the set is reloaded from TiKV when a rotation is announced over
+/// NATS, and periodically. Keys are only ever read from TiKV. Keys are published a full rotation period before they sign
+/// anything, so a missed broadcast never rejects a valid token.
+use crate::db::DatabaseClient;
+use crate::nats::NatsClient;
+use anyhow::Result;
+use guardyn_common::jwks::{key_set, JWKS_SUBJECT};
+use guardyn_common::listener;
+use std::sync::Arc;
+
+/// Load the key set from TiKV
+pub async fn sync(db: &DatabaseClient) -> Result<usize> {
+    match db.load_jwks().await? {
+        Some(jwks) => Ok(key_set().replace(&jwks)),
+        None => {
+            tracing::warn!("No token verification keys published yet");
+            Ok(0)
+        }
+    }
+}
+
+/// Reload the key set from TiKV whenever auth-service announces a change
+pub fn spawn_listener(db: Arc<DatabaseClient>, nats: Arc<NatsClient>) {
+    listener::spawn_reload_listener(
+        "token verification keys",
+        Some(nats.client()),
+        JWKS_SUBJECT,
+        move || {
+            let db = db.clone();
+            async move { sync(&db).await }
+        },
+    );
+}
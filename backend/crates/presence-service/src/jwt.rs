@@ -1,9 +1,11 @@
 /// JWT token validation and claims extraction
 ///
-/// Validates JWT tokens issued by auth-service and extracts user claims
+/// Validates JWT tokens issued by auth-service against its published signing
+/// keys and extracts user claims
 
-use anyhow::{anyhow, Result};
-use jsonwebtoken::{decode, DecodingKey, Validation, Algorithm};
+use anyhow::{anyhow, bail, Result};
+use guardyn_common::jwks::key_set;
+use guardyn_common::revocation::revocations;
 use serde::{Deserialize, Serialize};
 
 /// JWT claims structure (must match auth-service)
@@ -14,31 +16,41 @@ pub struct Claims {
     pub device_id: String, // Device ID
     pub exp: usize,        // Expiration time
     pub iat: usize,        // Issued at
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>, // Session ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>, // Token ID
 }
 
 /// Validate JWT token and extract claims
-pub fn validate_token(token: &str, secret: &str) -> Result<Claims> {
-    let validation = Validation::new(Algorithm::HS256);
-    let key = DecodingKey::from_secret(secret.as_bytes());
-
-    let token_data = decode::<Claims>(token, &key, &validation)
+pub fn validate_token(token: &str) -> Result<Claims> {
+    let claims: Claims = key_set()
+        .verify(token)
         .map_err(|e| anyhow!("Invalid token: {}", e))?;
 
-    Ok(token_data.claims)
+    if revocations().is_token_revoked(claims.sid.as_deref(), claims.jti.as_deref()) {
+        bail!("Token revoked");
+    }
+
+    Ok(claims)
 }
 
 /// Extract user_id from token (convenience function)
-pub fn get_user_id_from_token(token: &str, secret: &str) -> Result<String> {
-    let claims = validate_token(token, secret)?;
+pub fn get_user_id_from_token(token: &str) -> Result<String> {
+    let claims = validate_token(token)?;
     Ok(claims.sub)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use jsonwebtoken::{encode, EncodingKey, Header};
+    use guardyn_common::jwks::{JwkSet, SigningKey};
 
-    const TEST_JWT_SECRET: &str = "test-jwt-secret-key-32-bytes!!";
+    fn test_key() -> SigningKey {
+        let key = SigningKey::from_seed(&[42u8; 32]);
+        key_set().replace(&JwkSet { keys: vec![key.jwk()] });
+        key
+    }
 
     fn create_test_token(user_id: &str) -> String {
         let claims = Claims {
@@ -47,14 +59,11 @@ mod tests {
             device_id: "test-device-001".to_string(),
             exp: (chrono::Utc::now() + chrono::Duration::hours(1)).timestamp() as usize,
             iat: chrono::Utc::now().timestamp() as usize,
+            sid: None,
+            jti: None,
         };
 
-        encode(
-            &Header::default(),
-            &claims,
-            &EncodingKey::from_secret(TEST_JWT_SECRET.as_bytes()),
-        )
-        .unwrap()
+        test_key().sign(&claims).unwrap()
     }
 
     fn create_expired_token(user_id: &str) -> String {
@@ -64,20 +73,17 @@ mod tests {
             device_id: "test-device-001".to_string(),
             exp: (chrono::Utc::now() - chrono::Duration::hours(1)).timestamp() as usize,
             iat: (chrono::Utc::now() - chrono::Duration::hours(2)).timestamp() as usize,
+            sid: None,
+            jti: None,
         };
 
-        encode(
-            &Header::default(),
-            &claims,
-            &EncodingKey::from_secret(TEST_JWT_SECRET.as_bytes()),
-        )
-        .unwrap()
+        test_key().sign(&claims).unwrap()
     }
 
     #[test]
     fn test_validate_valid_token() {
         let token = create_test_token("user-123");
-        let result = validate_token(&token, TEST_JWT_SECRET);
+        let result = validate_token(&token);
         
         assert!(result.is_ok());
         let claims = result.unwrap();
@@ -89,7 +95,7 @@ mod tests {
     #[test]
     fn test_validate_expired_token() {
         let token = create_expired_token("user-expired");
-        let result = validate_token(&token, TEST_JWT_SECRET);
+        let result = validate_token(&token);
         
         assert!(result.is_err());
         let err_msg = result.unwrap_err().to_string();
@@ -97,31 +103,66 @@ mod tests {
     }
 
     #[test]
-    fn test_validate_wrong_secret() {
-        let token = create_test_token("user-wrong-secret");
-        let result = validate_token(&token, "wrong-secret-key!!");
+    fn test_validate_unpublished_key() {
+        test_key();
+        let claims = Claims {
+            sub: "user-wrong-key".to_string(),
+            username: "user_user-wrong-key".to_string(),
+            device_id: "test-device-001".to_string(),
+            exp: (chrono::Utc::now() + chrono::Duration::hours(1)).timestamp() as usize,
+            iat: chrono::Utc::now().timestamp() as usize,
+            sid: None,
+            jti: None,
+        };
+        let token = SigningKey::from_seed(&[7u8; 32]).sign(&claims).unwrap();
+        let result = validate_token(&token);
         
         assert!(result.is_err());
     }
 
     #[test]
     fn test_validate_empty_token() {
-        let result = validate_token("", TEST_JWT_SECRET);
+        let result = validate_token("");
         assert!(result.is_err());
     }
 
     #[test]
     fn test_validate_invalid_token_format() {
-        let result = validate_token("not.a.valid.token", TEST_JWT_SECRET);
+        let result = validate_token("not.a.valid.token");
         assert!(result.is_err());
     }
 
     #[test]
     fn test_get_user_id_from_token() {
         let token = create_test_token("user-456");
-        let result = get_user_id_from_token(&token, TEST_JWT_SECRET);
+        let result = get_user_id_from_token(&token);
         
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), "user-456");
     }
+
+    #[test]
+    fn test_validate_revoked_session() {
+        let claims = Claims {
+            sub: "user-revoked".to_string(),
+            username: "user_user-revoked".to_string(),
+            device_id: "test-device-001".to_string(),
+            exp: (chrono::Utc::now() + chrono::Duration::hours(1)).timestamp() as usize,
+            iat: chrono::Utc::now().timestamp() as usize,
+            sid: Some("presence-revoked-session".to_string()),
+            jti: None,
+        };
+        let token = test_key().sign(&claims).unwrap();
+        assert!(validate_token(&token).is_ok());
+
+        revocations().insert(&guardyn_common::revocation::Revocation {
+            id: "presence-revoked-session".to_string(),
+            user_id: "user-revoked".to_string(),
+            expires_at: (chrono::Utc::now() + chrono::Duration::hours(1)).timestamp(),
+        });
+
+        let result = validate_token(&token);
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("revoked"));
+    }
 }
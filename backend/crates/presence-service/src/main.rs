@@ -8,8 +8,10 @@
 
 mod db;
 mod handlers;
+mod jwks;
 mod jwt;
 mod nats;
+mod revocation;
 
 use anyhow::Result;
 use guardyn_common::observability;
@@ -38,16 +40,11 @@ use proto::presence::{
 pub struct PresenceServiceImpl {
     db: Arc<db::DatabaseClient>,
     nats: Arc<nats::NatsClient>,
-    jwt_secret: String,
 }
 
 impl PresenceServiceImpl {
-    pub fn new(db: db::DatabaseClient, nats: nats::NatsClient, jwt_secret: String) -> Self {
-        Self {
-            db: Arc::new(db),
-            nats: Arc::new(nats),
-            jwt_secret,
-        }
+    pub fn new(db: Arc<db::DatabaseClient>, nats: Arc<nats::NatsClient>) -> Self {
+        Self { db, nats }
     }
 }
 
@@ -57,28 +54,22 @@ impl PresenceService for PresenceServiceImpl {
         &self,
         request: Request<UpdateStatusRequest>,
     ) -> Result<Response<UpdateStatusResponse>, Status> {
-        handlers::handle_update_status(
-            request.into_inner(),
-            self.db.clone(),
-            self.nats.clone(),
-            &self.jwt_secret,
-        )
-        .await
+        handlers::handle_update_status(request.into_inner(), self.db.clone(), self.nats.clone())
+            .await
     }
 
     async fn get_status(
         &self,
         request: Request<GetStatusRequest>,
     ) -> Result<Response<GetStatusResponse>, Status> {
-        handlers::handle_get_status(request.into_inner(), self.db.clone(), &self.jwt_secret).await
+        handlers::handle_get_status(request.into_inner(), self.db.clone()).await
     }
 
     async fn get_bulk_status(
         &self,
         request: Request<GetBulkStatusRequest>,
     ) -> Result<Response<GetBulkStatusResponse>, Status> {
-        handlers::handle_get_bulk_status(request.into_inner(), self.db.clone(), &self.jwt_secret)
-            .await
+        handlers::handle_get_bulk_status(request.into_inner(), self.db.clone()).await
     }
 
     type SubscribeStream = tokio_stream::wrappers::ReceiverStream<Result<PresenceUpdate, Status>>;
@@ -87,28 +78,22 @@ impl PresenceService for PresenceServiceImpl {
         &self,
         request: Request<SubscribeRequest>,
     ) -> Result<Response<Self::SubscribeStream>, Status> {
-        handlers::handle_subscribe(request.into_inner(), self.db.clone(), &self.jwt_secret).await
+        handlers::handle_subscribe(request.into_inner(), self.db.clone()).await
     }
 
     async fn update_last_seen(
         &self,
         request: Request<UpdateLastSeenRequest>,
     ) -> Result<Response<UpdateLastSeenResponse>, Status> {
-        handlers::handle_update_last_seen(request.into_inner(), self.db.clone(), &self.jwt_secret)
-            .await
+        handlers::handle_update_last_seen(request.into_inner(), self.db.clone()).await
     }
 
     async fn set_typing(
         &self,
         request: Request<SetTypingRequest>,
     ) -> Result<Response<SetTypingResponse>, Status> {
-        handlers::handle_set_typing(
-            request.into_inner(),
-            self.db.clone(),
-            self.nats.clone(),
-            &self.jwt_secret,
-        )
-        .await
+        handlers::handle_set_typing(request.into_inner(), self.db.clone(), self.nats.clone())
+            .await
     }
 
     async fn health(
@@ -176,9 +161,6 @@ async fn main() -> Result<()> {
     tracing::info!("Starting Presence Service");
 
     // Load configuration from environment
-    let jwt_secret = std::env::var("JWT_SECRET")
-        .unwrap_or_else(|_| "dev-jwt-secret-change-in-prod".to_string());
-
     let tikv_pd_endpoints = std::env::var("TIKV_PD_ENDPOINTS")
         .or_else(|_| std::env::var("GUARDYN_DATABASE__TIKV_PD_ENDPOINTS"))
         .unwrap_or_else(|_| "tikv-pd.data.svc.cluster.local:2379".to_string());
@@ -208,8 +190,20 @@ async fn main() -> Result<()> {
     let nats = nats::NatsClient::new(&nats_url).await?;
     tracing::info!("Connected to NATS");
 
+    let db = Arc::new(db);
+    let nats = Arc::new(nats);
+
+    // Verify access tokens with the keys published by auth-service
+    if let Err(e) = jwks::sync(&db).await {
+        tracing::warn!("Failed to load token verification keys: {}", e);
+    }
+    jwks::spawn_listener(db.clone(), nats.clone());
+
+    // Keep the session revocation list current
+    revocation::spawn_listener(db.clone(), nats.clone());
+
     // Create service implementation
-    let presence_service = PresenceServiceImpl::new(db, nats, jwt_secret);
+    let presence_service = PresenceServiceImpl::new(db, nats);
 
     // Start gRPC server
     tracing::info!(address = %grpc_addr, "Starting gRPC server");
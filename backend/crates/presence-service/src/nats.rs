@@ -34,6 +34,7 @@ pub struct TypingEvent {
 /// NATS client for presence service
 #[derive(Clone)]
 pub struct NatsClient {
+    client: async_nats::Client,
     jetstream: Arc<JetStreamContext>,
 }
 
@@ -44,7 +45,7 @@ impl NatsClient {
             .await
             .context("Failed to connect to NATS")?;
 
-        let jetstream = jetstream::new(client);
+        let jetstream = jetstream::new(client.clone());
 
         // Ensure PRESENCE stream exists
         let stream_config = jetstream::stream::Config {
@@ -72,10 +73,16 @@ impl NatsClient {
         }
 
         Ok(Self {
+            client,
             jetstream: Arc::new(jetstream),
         })
     }
 
+    /// Underlying client, for plain (non-JetStream) subscriptions to auth-service broadcasts
+    pub fn client(&self) -> async_nats::Client {
+        self.client.clone()
+    }
+
     /// Publish presence update
     pub async fn publish_presence_update(&self, event: &PresenceEvent) -> Result<()> {
         let subject = format!("{}.{}", PRESENCE_SUBJECT, event.user_id);
@@ -0,0 +1,38 @@
+/// Session revocations published by auth-service
+///
+/// Keeps the process-wide revocation list that `jwt::validate_token` checks
+/// current: updates arrive over NATS within seconds, and the list is reloaded
+/// from TiKV periodically so a missed broadcast or a restart doesn't let a
+/// revoked token through.
+use crate::db::DatabaseClient;
+use crate::nats::NatsClient;
+use anyhow::Result;
+use guardyn_common::listener;
+use guardyn_common::revocation::{revocations, Revocation, REVOCATION_SUBJECT};
+use std::sync::Arc;
+
+/// Load active revocations from TiKV into the local list
+pub async fn sync(db: &DatabaseClient) -> Result<usize> {
+    let active = db.load_revocations().await?;
+    for revocation in &active {
+        revocations().insert(revocation);
+    }
+    Ok(active.len())
+}
+
+/// Start listening for revocations
+pub fn spawn_listener(db: Arc<DatabaseClient>, nats: Arc<NatsClient>) {
+    listener::spawn_listener(
+        "revocations",
+        Some(nats.client()),
+        REVOCATION_SUBJECT,
+        |revocation: Revocation| {
+            tracing::debug!("Session {} of user {} revoked", revocation.id, revocation.user_id);
+            revocations().insert(&revocation);
+        },
+        move || {
+            let db = db.clone();
+            async move { sync(&db).await }
+        },
+    );
+}
@@ -80,8 +80,11 @@ export S3_ENDPOINT="http://127.0.0.1:${MINIO_PORT}"
 export S3_REGION="us-east-1"
 export S3_BUCKET_NAME="guardyn-media"
 
-# JWT Secret (for local dev only - use a consistent value)
-export JWT_SECRET="${JWT_SECRET:-dev-secret-key-for-local-development-only}"
+# JWT signing key seed (for local dev only - use a consistent value so tokens survive restarts)
+export JWT_SIGNING_KEY="${JWT_SIGNING_KEY:-$(printf 'guardyn-dev-jwt-signing-key' | sha256sum | cut -d' ' -f1)}"
+
+# Franking tag signing key seed (for local dev only - shared by all local messaging-service runs)
+export FRANKING_SIGNING_KEY="${FRANKING_SIGNING_KEY:-$(printf 'guardyn-dev-franking-signing-key' | sha256sum | cut -d' ' -f1)}"
 
 # Observability (disabled for local dev by default)
 export GUARDYN_OBSERVABILITY__OTLP_ENDPOINT=""
@@ -328,7 +331,8 @@ run_service() {
         export GUARDYN_MESSAGING__NATS_URL='nats://127.0.0.1:${NATS_PORT}' && \
         export SCYLLA_CONSISTENCY='one' && \
         export SCYLLA_REPLICATION_FACTOR='1' && \
-        export JWT_SECRET='${JWT_SECRET}' && \
+        export JWT_SIGNING_KEY='${JWT_SIGNING_KEY}' && \
+        export FRANKING_SIGNING_KEY='${FRANKING_SIGNING_KEY}' && \
         export RUST_LOG='info,guardyn=debug' && \
         if command -v cargo-watch &>/dev/null; then \
             cargo watch -x 'run --bin ${bin_name}'; \
@@ -346,7 +350,8 @@ run_all_services() {
         tmux kill-session -t guardyn-dev 2>/dev/null || true
 
         # Common env exports (shared by all services)
-        local common_env="export JWT_SECRET='${JWT_SECRET}' && \
+        local common_env="export JWT_SIGNING_KEY='${JWT_SIGNING_KEY}' && \
+export FRANKING_SIGNING_KEY='${FRANKING_SIGNING_KEY}' && \
 export GUARDYN_HOST='0.0.0.0' && \
 export GUARDYN_DATABASE__TIKV_PD_ENDPOINTS='127.0.0.1:${TIKV_PD_PORT}' && \
 export TIKV_PD_ENDPOINTS='127.0.0.1:${TIKV_PD_PORT}' && \